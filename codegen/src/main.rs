@@ -29,6 +29,23 @@ fn main() {
         true,
     );
 
+    // tonic-orca
+    codegen(
+        &PathBuf::from(std::env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .join("tonic-orca"),
+        &[
+            "proto/xds/data/orca/v3/orca_load_report.proto",
+            "proto/xds/service/orca/v3/orca.proto",
+        ],
+        &["proto"],
+        &PathBuf::from("src/generated"),
+        &PathBuf::from("src/generated/orca_v3.bin"),
+        true,
+        true,
+    );
+
     // tonic-types
     codegen(
         &PathBuf::from(std::env!("CARGO_MANIFEST_DIR"))
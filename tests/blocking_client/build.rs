@@ -0,0 +1,6 @@
+fn main() {
+    tonic_build::configure()
+        .blocking_client(true)
+        .compile(&["proto/test.proto"], &["proto"])
+        .unwrap();
+}
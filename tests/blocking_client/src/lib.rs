@@ -0,0 +1,64 @@
+use tonic::Status;
+
+tonic::include_proto!("test");
+
+/// Calls the server at `dst` without an asynchronous runtime.
+#[allow(dead_code)]
+fn call(dst: String) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut client = test_client::blocking::TestClient::connect(dst)?;
+
+    client.unary(Input {})?;
+    let outputs = client
+        .server_stream(Input {})?
+        .into_inner()
+        .collect::<Result<Vec<Output>, Status>>()?;
+    Ok(outputs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::{
+        transport::{server::TcpIncoming, Server},
+        Request, Response,
+    };
+
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+            Ok(Response::new(Output {}))
+        }
+
+        type ServerStreamStream = tokio_stream::Iter<std::vec::IntoIter<Result<Output, Status>>>;
+
+        async fn server_stream(
+            &self,
+            _: Request<Input>,
+        ) -> Result<Response<Self::ServerStreamStream>, Status> {
+            let outputs = vec![Ok(Output {}), Ok(Output {})];
+            Ok(Response::new(tokio_stream::iter(outputs)))
+        }
+    }
+
+    #[test]
+    fn calls_without_runtime() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (addr, incoming) = rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            (
+                addr,
+                TcpIncoming::from_listener(listener, true, None).unwrap(),
+            )
+        });
+        rt.spawn(
+            Server::builder()
+                .add_service(test_server::TestServer::new(Svc))
+                .serve_with_incoming(incoming),
+        );
+
+        assert_eq!(call(format!("http://{}", addr)).unwrap(), 2);
+    }
+}
@@ -0,0 +1,12 @@
+use tonic::{transport::Channel, Status};
+
+tonic::include_proto!("test");
+
+/// Makes concurrent calls with a shared client.
+#[allow(dead_code)]
+async fn call(client: &test_client::TestClient<Channel>) -> Result<(), Status> {
+    let (unary, stream) = tokio::join!(client.unary(Input {}), client.server_stream(Input {}));
+    unary?;
+    stream?;
+    Ok(())
+}
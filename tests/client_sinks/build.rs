@@ -0,0 +1,6 @@
+fn main() {
+    tonic_build::configure()
+        .client_sinks(true)
+        .compile(&["proto/test.proto"], &["proto"])
+        .unwrap();
+}
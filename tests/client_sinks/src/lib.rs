@@ -0,0 +1,87 @@
+use std::pin::Pin;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming};
+
+tonic::include_proto!("test");
+
+#[derive(Default)]
+pub struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn sum(&self, request: Request<Streaming<Input>>) -> Result<Response<Output>, Status> {
+        let mut inputs = request.into_inner();
+        let mut value = 0;
+        while let Some(input) = inputs.next().await {
+            value += input?.value;
+        }
+        Ok(Response::new(Output { value }))
+    }
+
+    type EchoStream = Pin<Box<dyn Stream<Item = Result<Output, Status>> + Send>>;
+
+    async fn echo(
+        &self,
+        request: Request<Streaming<Input>>,
+    ) -> Result<Response<Self::EchoStream>, Status> {
+        let outputs = request
+            .into_inner()
+            .map(|input| input.map(|input| Output { value: input.value }));
+        Ok(Response::new(Box::pin(outputs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::SinkExt;
+    use test_client::TestClient;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, Server};
+
+    async fn connect() -> TestClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(test_server::TestServer::new(Svc))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        TestClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn client_streaming() {
+        let client = connect().await;
+
+        let (mut inputs, response) = client.sum_sink();
+        let response = tokio::spawn(response);
+        for value in 1..=4 {
+            inputs.send(Input { value }).await.unwrap();
+        }
+        inputs.close().await.unwrap();
+
+        let output = response.await.unwrap().unwrap().into_inner();
+        assert_eq!(output.value, 10);
+    }
+
+    #[tokio::test]
+    async fn bidi_streaming() {
+        let client = connect().await;
+
+        let (mut inputs, response) = client.echo_sink();
+        let response = tokio::spawn(response);
+        inputs.send(Input { value: 1 }).await.unwrap();
+        let mut outputs = response.await.unwrap().unwrap().into_inner();
+        assert_eq!(outputs.message().await.unwrap(), Some(Output { value: 1 }));
+
+        inputs.send(Input { value: 2 }).await.unwrap();
+        assert_eq!(outputs.message().await.unwrap(), Some(Output { value: 2 }));
+
+        drop(inputs);
+        assert_eq!(outputs.message().await.unwrap(), None);
+    }
+}
@@ -0,0 +1,7 @@
+fn main() {
+    tonic_build::configure()
+        .build_server(false)
+        .client_trait(true)
+        .compile(&["proto/test.proto"], &["proto"])
+        .unwrap();
+}
@@ -0,0 +1,50 @@
+use test_client::TestClientApi;
+use tokio_stream::StreamExt;
+use tonic::{Request, Status};
+
+tonic::include_proto!("test");
+
+/// Application code, depending on the calls rather than the client.
+#[allow(dead_code)]
+async fn count(client: &mut impl TestClientApi) -> Result<usize, Status> {
+    client.unary(Request::new(Input {})).await?;
+    let outputs = client
+        .server_stream(Request::new(Input {}))
+        .await?
+        .into_inner();
+    Ok(outputs.collect::<Vec<_>>().await.len())
+}
+
+#[allow(dead_code)]
+fn assert_client_impls_trait(client: test_client::TestClient<tonic::transport::Channel>) {
+    fn assert_impl<T: TestClientApi>(_: T) {}
+    assert_impl(client);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::{codegen::BoxStream, Response};
+
+    struct Mock;
+
+    #[tonic::async_trait]
+    impl TestClientApi for Mock {
+        async fn unary(&mut self, _: Request<Input>) -> Result<Response<Output>, Status> {
+            Ok(Response::new(Output {}))
+        }
+
+        async fn server_stream(
+            &mut self,
+            _: Request<Input>,
+        ) -> Result<Response<BoxStream<Output>>, Status> {
+            let outputs = tokio_stream::iter(vec![Ok(Output {}), Ok(Output {})]);
+            Ok(Response::new(Box::pin(outputs)))
+        }
+    }
+
+    #[tokio::test]
+    async fn mocks_calls() {
+        assert_eq!(count(&mut Mock).await.unwrap(), 2);
+    }
+}
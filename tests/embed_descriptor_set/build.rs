@@ -0,0 +1,6 @@
+fn main() {
+    tonic_build::configure()
+        .embed_file_descriptor_set(true)
+        .compile(&["proto/foo.proto", "proto/bar.proto"], &["proto"])
+        .unwrap();
+}
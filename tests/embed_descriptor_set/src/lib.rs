@@ -0,0 +1,33 @@
+pub mod foo {
+    tonic::include_proto!("foo");
+}
+
+pub mod bar {
+    tonic::include_proto!("bar");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use prost_types::FileDescriptorSet;
+
+    #[test]
+    fn embeds_the_compiled_files() {
+        // Each package holds the set of all files compiled together.
+        assert_eq!(foo::FILE_DESCRIPTOR_SET, bar::FILE_DESCRIPTOR_SET);
+
+        let fds = FileDescriptorSet::decode(foo::FILE_DESCRIPTOR_SET).unwrap();
+        let mut names: Vec<_> = fds.file.iter().map(|file| file.name()).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["bar.proto", "foo.proto"]);
+    }
+
+    #[test]
+    fn registers_with_reflection() {
+        tonic_reflection::server::Builder::configure()
+            .register_all(&[foo::FILE_DESCRIPTOR_SET, bar::FILE_DESCRIPTOR_SET])
+            .build()
+            .unwrap();
+    }
+}
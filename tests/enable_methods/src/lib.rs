@@ -0,0 +1,45 @@
+use tonic::{Request, Response, Status};
+
+tonic::include_proto!("test");
+
+#[derive(Default)]
+pub struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn enabled(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+
+    async fn disabled(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_client::TestClient;
+    use test_server::{methods::Method, TestServer};
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{transport::Server, Code};
+
+    #[tokio::test]
+    async fn serves_enabled_methods() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(TestServer::new(Svc).enable_methods([Method::Enabled]))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = TestClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+
+        client.enabled(Input {}).await.unwrap();
+        let status = client.disabled(Input {}).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+    }
+}
@@ -0,0 +1,6 @@
+fn main() {
+    tonic_build::configure()
+        .local_server(true)
+        .compile(&["proto/test.proto"], &["proto"])
+        .unwrap();
+}
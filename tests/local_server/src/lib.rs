@@ -0,0 +1,25 @@
+use std::{cell::RefCell, rc::Rc};
+use tonic::{transport::server::LocalRoutes, Request, Response, Status};
+
+tonic::include_proto!("test");
+
+/// Holds state that isn't `Send` across an await.
+#[derive(Debug, Default)]
+struct Svc {
+    calls: Rc<RefCell<u32>>,
+}
+
+#[tonic::async_trait(?Send)]
+impl test_server::Test for Svc {
+    async fn test_request(&self, req: Request<SomeData>) -> Result<Response<SomeData>, Status> {
+        let calls = self.calls.clone();
+        tokio::task::yield_now().await;
+        *calls.borrow_mut() += 1;
+        Ok(Response::new(req.into_inner()))
+    }
+}
+
+#[allow(dead_code)]
+fn routes() -> LocalRoutes {
+    LocalRoutes::new(test_server::TestServer::new(Svc::default()))
+}
@@ -0,0 +1,7 @@
+fn main() {
+    tonic_build::configure()
+        .client_method_option_attribute("idempotency_level=NO_SIDE_EFFECTS", "#[must_use]")
+        .server_method_option_attribute("idempotency_level", r#"#[cfg(feature = "never")]"#)
+        .compile(&["proto/test.proto"], &["proto"])
+        .unwrap();
+}
@@ -0,0 +1,22 @@
+use tonic::{transport::Channel, Request, Response, Status};
+
+tonic::include_proto!("test");
+
+#[derive(Debug, Default)]
+struct Svc;
+
+// `Get` is compiled out of the server by its `cfg` attribute.
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn old(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+#[allow(dead_code)]
+async fn call(mut client: test_client::TestClient<Channel>) -> Result<(), Status> {
+    let _ = client.get(Input {}).await?;
+    #[allow(deprecated)]
+    client.old(Input {}).await?;
+    Ok(())
+}
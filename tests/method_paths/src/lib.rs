@@ -0,0 +1,32 @@
+tonic::include_proto!("test");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_server::methods::{self, Method};
+
+    #[test]
+    fn service_names() {
+        assert_eq!(test_server::SERVICE_NAME, "test.Test");
+        assert_eq!(test_client::SERVICE_NAME, "test.Test");
+    }
+
+    #[test]
+    fn method_paths() {
+        assert_eq!(methods::UNARY, "/test.Test/Unary");
+        assert_eq!(methods::SERVER_STREAM, "/test.Test/ServerStream");
+        assert_eq!(test_client::methods::UNARY, methods::UNARY);
+    }
+
+    #[test]
+    fn parses_method_paths() {
+        assert_eq!(Method::from_path("/test.Test/Unary"), Some(Method::Unary));
+        assert_eq!(Method::from_path("/test.Test/Other"), None);
+        assert_eq!(Method::from_path("/test.Other/Unary"), None);
+
+        for method in Method::ALL {
+            assert_eq!(Method::from_path(method.path()), Some(*method));
+        }
+        assert_eq!(Method::ServerStream.name(), "ServerStream");
+    }
+}
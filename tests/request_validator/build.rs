@@ -0,0 +1,6 @@
+fn main() {
+    tonic_build::configure()
+        .request_validator("crate::validate")
+        .compile(&["proto/test.proto"], &["proto"])
+        .unwrap();
+}
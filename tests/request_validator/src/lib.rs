@@ -0,0 +1,92 @@
+use tokio_stream::StreamExt;
+use tonic::{Request, Response, Status, Streaming};
+
+tonic::include_proto!("test");
+
+/// Stands in for the checks generated from `buf.validate` rules.
+pub trait Validate {
+    fn validate(&self) -> Result<(), String>;
+}
+
+impl Validate for Input {
+    fn validate(&self) -> Result<(), String> {
+        if self.value < 0 {
+            Err("value must not be negative".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub fn validate<M: Validate>(message: &M) -> Result<(), Status> {
+    message.validate().map_err(Status::invalid_argument)
+}
+
+#[derive(Default)]
+pub struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary(&self, request: Request<Input>) -> Result<Response<Output>, Status> {
+        let value = request.into_inner().value;
+        Ok(Response::new(Output { value }))
+    }
+
+    async fn client_stream(
+        &self,
+        request: Request<Streaming<Input>>,
+    ) -> Result<Response<Output>, Status> {
+        let mut inputs = request.into_inner();
+        let mut value = 0;
+        while let Some(input) = inputs.next().await {
+            value += input?.value;
+        }
+        Ok(Response::new(Output { value }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_client::TestClient;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{
+        transport::{Channel, Server},
+        Code,
+    };
+
+    async fn connect() -> TestClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(test_server::TestServer::new(Svc))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        TestClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn validates_unary_requests() {
+        let mut client = connect().await;
+
+        let output = client.unary(Input { value: 1 }).await.unwrap();
+        assert_eq!(output.into_inner().value, 1);
+
+        let status = client.unary(Input { value: -1 }).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "value must not be negative");
+    }
+
+    #[tokio::test]
+    async fn validates_streamed_requests() {
+        let mut client = connect().await;
+
+        let inputs = tokio_stream::iter([Input { value: 1 }, Input { value: -2 }]);
+        let status = client.client_stream(inputs).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+}
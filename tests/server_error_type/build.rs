@@ -0,0 +1,7 @@
+fn main() {
+    tonic_build::configure()
+        .build_client(false)
+        .server_error_type("crate::Error")
+        .compile(&["proto/test.proto"], &["proto"])
+        .unwrap();
+}
@@ -0,0 +1,43 @@
+use std::pin::Pin;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+tonic::include_proto!("test");
+
+/// The domain errors of handlers.
+#[derive(Debug)]
+pub enum Error {
+    NotFound,
+}
+
+impl From<Error> for Status {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::NotFound => Status::not_found("not found"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary(&self, _: Request<Input>) -> Result<Response<Output>, Error> {
+        Err(Error::NotFound)
+    }
+
+    type ServerStreamStream = Pin<Box<dyn Stream<Item = Result<Output, Status>> + Send + 'static>>;
+
+    async fn server_stream(
+        &self,
+        _: Request<Input>,
+    ) -> Result<Response<Self::ServerStreamStream>, Error> {
+        Err(Error::NotFound)
+    }
+}
+
+#[allow(dead_code)]
+fn server() -> test_server::TestServer<Svc> {
+    test_server::TestServer::new(Svc)
+}
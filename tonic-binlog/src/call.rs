@@ -0,0 +1,334 @@
+//! The log entries of a call.
+
+use crate::{
+    pb::{
+        address, grpc_log_entry::EventType, grpc_log_entry::Logger, grpc_log_entry::Payload,
+        Address, ClientHeader, GrpcLogEntry, Message, Metadata, MetadataEntry, ServerHeader,
+        Trailer,
+    },
+    util::base64::STANDARD_NO_PAD,
+    Limits, Sink,
+};
+use base64::Engine as _;
+use http::HeaderMap;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+/// The identifier of the next call, which must not be 0.
+static NEXT_CALL_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Logs the events of a call, numbering them.
+pub(crate) struct Call {
+    id: u64,
+    sequence: AtomicU64,
+    logger: Logger,
+    limits: Limits,
+    sink: Arc<dyn Sink>,
+}
+
+impl Call {
+    pub(crate) fn new(logger: Logger, limits: Limits, sink: Arc<dyn Sink>) -> Self {
+        Call {
+            id: NEXT_CALL_ID.fetch_add(1, Ordering::Relaxed),
+            sequence: AtomicU64::new(1),
+            logger,
+            limits,
+            sink,
+        }
+    }
+
+    fn log(
+        &self,
+        event: EventType,
+        payload: Option<Payload>,
+        truncated: bool,
+        peer: Option<Address>,
+    ) {
+        self.sink.write(GrpcLogEntry {
+            timestamp: Some(SystemTime::now().into()),
+            call_id: self.id,
+            sequence_id_within_call: self.sequence.fetch_add(1, Ordering::Relaxed),
+            r#type: event as i32,
+            logger: self.logger as i32,
+            payload_truncated: truncated,
+            peer,
+            payload,
+        });
+    }
+
+    pub(crate) fn client_header(
+        &self,
+        method: &str,
+        authority: &str,
+        headers: &HeaderMap,
+        peer: Option<Address>,
+    ) {
+        let (metadata, truncated) = self.metadata(headers);
+        let header = ClientHeader {
+            metadata: Some(metadata),
+            method_name: method.to_string(),
+            authority: authority.to_string(),
+            timeout: headers
+                .get("grpc-timeout")
+                .and_then(|timeout| parse_timeout(timeout.to_str().ok()?))
+                .and_then(|timeout| timeout.try_into().ok()),
+        };
+        self.log(
+            EventType::ClientHeader,
+            Some(Payload::ClientHeader(header)),
+            truncated,
+            peer,
+        );
+    }
+
+    pub(crate) fn server_header(&self, headers: &HeaderMap) {
+        let (metadata, truncated) = self.metadata(headers);
+        let header = ServerHeader {
+            metadata: Some(metadata),
+        };
+        self.log(
+            EventType::ServerHeader,
+            Some(Payload::ServerHeader(header)),
+            truncated,
+            None,
+        );
+    }
+
+    pub(crate) fn message(&self, event: EventType, length: u32, data: Vec<u8>) {
+        let truncated = data.len() < length as usize;
+        let message = Message { length, data };
+        self.log(event, Some(Payload::Message(message)), truncated, None);
+    }
+
+    pub(crate) fn client_half_close(&self) {
+        self.log(EventType::ClientHalfClose, None, false, None);
+    }
+
+    pub(crate) fn server_trailer(&self, trailers: &HeaderMap) {
+        let (metadata, truncated) = self.metadata(trailers);
+        let trailer = Trailer {
+            metadata: Some(metadata),
+            status_code: trailers
+                .get("grpc-status")
+                .and_then(|code| code.to_str().ok()?.parse().ok())
+                .unwrap_or_default(),
+            status_message: trailers
+                .get("grpc-message")
+                .and_then(|message| {
+                    let message = percent_encoding::percent_decode(message.as_bytes());
+                    Some(message.decode_utf8().ok()?.into_owned())
+                })
+                .unwrap_or_default(),
+            status_details: trailers
+                .get("grpc-status-details-bin")
+                .and_then(|details| STANDARD_NO_PAD.decode(details.as_bytes()).ok())
+                .unwrap_or_default(),
+        };
+        self.log(
+            EventType::ServerTrailer,
+            Some(Payload::Trailer(trailer)),
+            truncated,
+            None,
+        );
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.log(EventType::Cancel, None, false, None);
+    }
+
+    /// The metadata of the application in `headers`, up to the header
+    /// limit, and whether entries were left out for the limit.
+    fn metadata(&self, headers: &HeaderMap) -> (Metadata, bool) {
+        let mut metadata = Metadata::default();
+        let mut size = 0usize;
+        let mut truncated = false;
+
+        for (key, value) in headers {
+            let key = key.as_str();
+            let trace = key == "grpc-trace-bin";
+            if !trace && (key.starts_with("grpc-") || TRANSPORT_HEADERS.contains(&key)) {
+                continue;
+            }
+
+            let value = if key.ends_with("-bin") {
+                match STANDARD_NO_PAD.decode(value.as_bytes()) {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                }
+            } else {
+                value.as_bytes().to_vec()
+            };
+
+            // The trace context is always logged, and doesn't count.
+            if !trace {
+                let entry_size = key.len() + value.len();
+                if size.saturating_add(entry_size) > self.limits.header {
+                    truncated = true;
+                    continue;
+                }
+                size += entry_size;
+            }
+            metadata.entry.push(MetadataEntry {
+                key: key.to_string(),
+                value,
+            });
+        }
+
+        (metadata, truncated)
+    }
+
+    pub(crate) fn message_limit(&self) -> usize {
+        self.limits.message
+    }
+}
+
+/// The headers of the transport, which aren't metadata of the application.
+const TRANSPORT_HEADERS: &[&str] = &[
+    "content-type",
+    "content-encoding",
+    "content-length",
+    "te",
+    "user-agent",
+    "host",
+];
+
+/// The address of a peer, as logged.
+pub(crate) fn address(addr: SocketAddr) -> Address {
+    let r#type = match addr {
+        SocketAddr::V4(_) => address::Type::Ipv4,
+        SocketAddr::V6(_) => address::Type::Ipv6,
+    };
+    Address {
+        r#type: r#type as i32,
+        address: addr.ip().to_string(),
+        ip_port: addr.port().into(),
+    }
+}
+
+/// Parse a `grpc-timeout` header, such as `100m` for 100 milliseconds.
+fn parse_timeout(timeout: &str) -> Option<Duration> {
+    if timeout.is_empty() || timeout.len() > 9 || !timeout.is_ascii() {
+        return None;
+    }
+    let (value, unit) = timeout.split_at(timeout.len() - 1);
+    let value: u64 = value.parse().ok()?;
+    let timeout = match unit {
+        "H" => Duration::from_secs(value * 60 * 60),
+        "M" => Duration::from_secs(value * 60),
+        "S" => Duration::from_secs(value),
+        "m" => Duration::from_millis(value),
+        "u" => Duration::from_micros(value),
+        "n" => Duration::from_nanos(value),
+        _ => return None,
+    };
+    Some(timeout)
+}
+
+/// Reads the length prefixed messages of a gRPC body, as its frames go by.
+#[derive(Debug, Default)]
+pub(crate) struct Frames {
+    header: [u8; 5],
+    header_len: usize,
+    /// The message being read, once its header is.
+    message: Option<PartialMessage>,
+}
+
+#[derive(Debug)]
+struct PartialMessage {
+    length: u32,
+    remaining: usize,
+    /// The logged part of the message.
+    data: Vec<u8>,
+}
+
+impl Frames {
+    /// Read `data`, passing the messages it completes to `on_message` with
+    /// their length and their first `limit` bytes.
+    pub(crate) fn push(
+        &mut self,
+        mut data: &[u8],
+        limit: usize,
+        mut on_message: impl FnMut(u32, Vec<u8>),
+    ) {
+        loop {
+            let message = match &mut self.message {
+                Some(message) => message,
+                None => {
+                    let n = (self.header.len() - self.header_len).min(data.len());
+                    self.header[self.header_len..self.header_len + n].copy_from_slice(&data[..n]);
+                    self.header_len += n;
+                    data = &data[n..];
+                    if self.header_len < self.header.len() {
+                        return;
+                    }
+
+                    self.header_len = 0;
+                    let length = u32::from_be_bytes([
+                        self.header[1],
+                        self.header[2],
+                        self.header[3],
+                        self.header[4],
+                    ]);
+                    self.message.insert(PartialMessage {
+                        length,
+                        remaining: length as usize,
+                        data: Vec::new(),
+                    })
+                }
+            };
+
+            let n = message.remaining.min(data.len());
+            let logged = (limit.min(message.length as usize) - message.data.len()).min(n);
+            message.data.extend_from_slice(&data[..logged]);
+            message.remaining -= n;
+            data = &data[n..];
+
+            if message.remaining > 0 {
+                return;
+            }
+            let message = self.message.take().expect("a message is being read");
+            on_message(message.length, message.data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_messages_split_across_frames() {
+        let mut body = vec![0, 0, 0, 0, 3, b'a', b'b', b'c'];
+        body.extend_from_slice(&[0, 0, 0, 0, 0]);
+        body.extend_from_slice(&[1, 0, 0, 0, 2, b'd', b'e']);
+
+        for split in 0..body.len() {
+            let mut frames = Frames::default();
+            let mut messages = Vec::new();
+            let (first, second) = body.split_at(split);
+            frames.push(first, 2, |length, data| messages.push((length, data)));
+            frames.push(second, 2, |length, data| messages.push((length, data)));
+
+            assert_eq!(
+                messages,
+                [(3, b"ab".to_vec()), (0, vec![]), (2, b"de".to_vec())],
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[test]
+    fn parses_timeouts() {
+        assert_eq!(parse_timeout("100m"), Some(Duration::from_millis(100)));
+        assert_eq!(parse_timeout("3S"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_timeout("1000000000n"), None);
+        assert_eq!(parse_timeout("5x"), None);
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+/// How many bytes of the metadata and messages of a call are logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The bytes of metadata logged with each header and trailer event.
+    pub header: usize,
+    /// The bytes logged of each message.
+    pub message: usize,
+}
+
+impl Limits {
+    /// Log whole metadata and messages.
+    pub const UNLIMITED: Limits = Limits {
+        header: usize::MAX,
+        message: usize::MAX,
+    };
+}
+
+/// The methods whose calls are logged, and their [`Limits`].
+///
+/// Filters are parsed from the syntax of the `GRPC_BINARY_LOG_FILTER`
+/// environment variable: a comma separated list of
+///
+/// - `*`, for every method,
+/// - `package.Service/*`, for the methods of a service,
+/// - `package.Service/Method`, for a method,
+/// - `-package.Service/Method`, to not log a method at all,
+///
+/// each but the last optionally followed by the limits of the methods it
+/// matches: `{h}` logs whole metadata but no messages, `{m}` whole messages
+/// but no metadata, `{h:256}` and `{m:256}` limit them to 256 bytes, and
+/// `{h:256;m:256}` logs both. Without limits, both are logged whole.
+///
+/// The most specific rule of a method applies, whatever their order.
+///
+/// ```
+/// use tonic_binlog::{Filter, Limits};
+///
+/// let filter: Filter = "*{h:64},grpc.health.v1.Health/*,-grpc.health.v1.Health/Watch"
+///     .parse()
+///     .unwrap();
+///
+/// assert_eq!(filter.limits("/echo.Echo/Call"), Some(Limits { header: 64, message: 0 }));
+/// assert_eq!(filter.limits("/grpc.health.v1.Health/Check"), Some(Limits::UNLIMITED));
+/// assert_eq!(filter.limits("/grpc.health.v1.Health/Watch"), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    all: Option<Limits>,
+    services: HashMap<String, Limits>,
+    methods: HashMap<String, Limits>,
+    excluded: HashSet<String>,
+}
+
+impl Filter {
+    /// A filter logging every method whole.
+    pub fn all() -> Self {
+        Filter {
+            all: Some(Limits::UNLIMITED),
+            ..Default::default()
+        }
+    }
+
+    /// The limits of the method at `path`, such as `/package.Service/Method`,
+    /// or `None` if it isn't logged.
+    pub fn limits(&self, path: &str) -> Option<Limits> {
+        let method = path.strip_prefix('/').unwrap_or(path);
+        if self.excluded.contains(method) {
+            return None;
+        }
+        if let Some(limits) = self.methods.get(method) {
+            return Some(*limits);
+        }
+        let service = method.split_once('/').map(|(service, _)| service)?;
+        self.services.get(service).copied().or(self.all)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Filter::default();
+
+        for rule in s.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+            let error = || FilterError(rule.to_string());
+
+            if let Some(method) = rule.strip_prefix('-') {
+                if !is_method(method) || !filter.excluded.insert(method.to_string()) {
+                    return Err(error());
+                }
+                continue;
+            }
+
+            let (pattern, limits) = match rule.split_once('{') {
+                Some((pattern, options)) => {
+                    let options = options.strip_suffix('}').ok_or_else(error)?;
+                    (pattern, parse_limits(options).ok_or_else(error)?)
+                }
+                None => (rule, Limits::UNLIMITED),
+            };
+
+            let duplicate = if pattern == "*" {
+                filter.all.replace(limits).is_some()
+            } else if let Some(service) = pattern.strip_suffix("/*") {
+                if service.is_empty() || service.contains('/') {
+                    return Err(error());
+                }
+                filter
+                    .services
+                    .insert(service.to_string(), limits)
+                    .is_some()
+            } else if is_method(pattern) {
+                filter.methods.insert(pattern.to_string(), limits).is_some()
+            } else {
+                return Err(error());
+            };
+            if duplicate {
+                return Err(error());
+            }
+        }
+
+        Ok(filter)
+    }
+}
+
+/// Whether `s` is a `Service/Method` name.
+fn is_method(s: &str) -> bool {
+    match s.split_once('/') {
+        Some((service, method)) => {
+            !service.is_empty()
+                && !method.is_empty()
+                && !s.contains(['{', '}'])
+                && !method.contains(['/', '*'])
+        }
+        None => false,
+    }
+}
+
+/// Parse the `h`, `m`, `h:N`, `m:N` and `h:N;m:N` options of a rule.
+fn parse_limits(options: &str) -> Option<Limits> {
+    let mut limits = Limits {
+        header: 0,
+        message: 0,
+    };
+    let mut options = options.split(';');
+
+    let mut next = options.next();
+    if let Some(header) = next.and_then(|option| option.strip_prefix('h')) {
+        limits.header = parse_limit(header)?;
+        next = options.next();
+    }
+    if let Some(message) = next.and_then(|option| option.strip_prefix('m')) {
+        limits.message = parse_limit(message)?;
+        next = options.next();
+    }
+
+    match next {
+        None if limits.header > 0 || limits.message > 0 => Some(limits),
+        _ => None,
+    }
+}
+
+fn parse_limit(limit: &str) -> Option<usize> {
+    match limit.strip_prefix(':') {
+        Some(limit) => limit.parse().ok(),
+        None if limit.is_empty() => Some(usize::MAX),
+        None => None,
+    }
+}
+
+/// Error returned when a [`Filter`] can't be parsed, naming the invalid
+/// rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError(String);
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid binary log filter rule `{}`", self.0)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() {
+        let filter: Filter = "Foo/*{m:128}, Foo/Bar{h;m:4}, Baz/Qux".parse().unwrap();
+
+        assert_eq!(
+            filter.limits("/Foo/Other"),
+            Some(Limits {
+                header: 0,
+                message: 128
+            })
+        );
+        assert_eq!(
+            filter.limits("/Foo/Bar"),
+            Some(Limits {
+                header: usize::MAX,
+                message: 4
+            })
+        );
+        assert_eq!(filter.limits("/Baz/Qux"), Some(Limits::UNLIMITED));
+        assert_eq!(filter.limits("/Baz/Other"), None);
+        assert_eq!(Filter::all().limits("/Baz/Other"), Some(Limits::UNLIMITED));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for invalid in [
+            "*,*",
+            "Foo",
+            "/Foo/Bar",
+            "Foo/Bar/Baz",
+            "-Foo/*",
+            "-Foo/Bar{h}",
+            "Foo/*{}",
+            "Foo/*{m;h}",
+            "Foo/*{h:x}",
+            "Foo/*{h:1",
+        ] {
+            assert!(invalid.parse::<Filter>().is_err(), "{}", invalid);
+        }
+    }
+}
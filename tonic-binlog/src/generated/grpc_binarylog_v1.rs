@@ -0,0 +1,330 @@
+// This file is @generated by prost-build.
+/// Log entry we store in binary logs
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GrpcLogEntry {
+    /// The timestamp of the binary log message
+    #[prost(message, optional, tag = "1")]
+    pub timestamp: ::core::option::Option<::prost_types::Timestamp>,
+    /// Uniquely identifies a call. The value must not be 0 in order to disambiguate
+    /// from an unset value.
+    /// Each call may have several log entries, they will all have the same call_id.
+    /// Nothing is guaranteed about their value other than they are unique across
+    /// different RPCs in the same gRPC process.
+    #[prost(uint64, tag = "2")]
+    pub call_id: u64,
+    /// The entry sequence id for this call. The first GrpcLogEntry has a
+    /// value of 1, to disambiguate from an unset value. The purpose of
+    /// this field is to detect missing entries in environments where
+    /// durability or ordering is not guaranteed.
+    #[prost(uint64, tag = "3")]
+    pub sequence_id_within_call: u64,
+    #[prost(enumeration = "grpc_log_entry::EventType", tag = "4")]
+    pub r#type: i32,
+    /// One of the above Logger enum
+    #[prost(enumeration = "grpc_log_entry::Logger", tag = "5")]
+    pub logger: i32,
+    /// true if payload does not represent the full message or metadata.
+    #[prost(bool, tag = "10")]
+    pub payload_truncated: bool,
+    /// Peer address information, will only be recorded on the first
+    /// incoming event. On client side, peer is logged on
+    /// EVENT_TYPE_SERVER_HEADER normally or EVENT_TYPE_SERVER_TRAILER in
+    /// the case of trailers-only. On server side, peer is always
+    /// logged on EVENT_TYPE_CLIENT_HEADER.
+    #[prost(message, optional, tag = "11")]
+    pub peer: ::core::option::Option<Address>,
+    /// The logger uses one of the following fields to record the payload,
+    /// according to the type of the log entry.
+    #[prost(oneof = "grpc_log_entry::Payload", tags = "6, 7, 8, 9")]
+    pub payload: ::core::option::Option<grpc_log_entry::Payload>,
+}
+/// Nested message and enum types in `GrpcLogEntry`.
+pub mod grpc_log_entry {
+    /// Enumerates the type of event
+    /// Note the terminology is different from the RPC semantics
+    /// definition, but the same meaning is expressed here.
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum EventType {
+        Unknown = 0,
+        /// Header sent from client to server
+        ClientHeader = 1,
+        /// Header sent from server to client
+        ServerHeader = 2,
+        /// Message sent from client to server
+        ClientMessage = 3,
+        /// Message sent from server to client
+        ServerMessage = 4,
+        /// A signal that client is done sending
+        ClientHalfClose = 5,
+        /// Trailer indicates the end of the RPC.
+        /// On client side, this event means a trailer was either received
+        /// from the network or the gRPC library locally generated a status
+        /// to inform the application about a failure.
+        /// On server side, this event means the server application requested
+        /// to send a trailer. Note: EVENT_TYPE_CANCEL may still arrive after
+        /// this due to races on server side.
+        ServerTrailer = 6,
+        /// A signal that the RPC is cancelled. On client side, this
+        /// indicates the client application requests a cancellation.
+        /// On server side, this indicates that cancellation was detected.
+        /// Note: This marks the end of the RPC. Events may arrive after
+        /// this due to races. For example, on client side a trailer
+        /// may arrive even though the application requested to cancel the RPC.
+        Cancel = 7,
+    }
+    impl EventType {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                EventType::Unknown => "EVENT_TYPE_UNKNOWN",
+                EventType::ClientHeader => "EVENT_TYPE_CLIENT_HEADER",
+                EventType::ServerHeader => "EVENT_TYPE_SERVER_HEADER",
+                EventType::ClientMessage => "EVENT_TYPE_CLIENT_MESSAGE",
+                EventType::ServerMessage => "EVENT_TYPE_SERVER_MESSAGE",
+                EventType::ClientHalfClose => "EVENT_TYPE_CLIENT_HALF_CLOSE",
+                EventType::ServerTrailer => "EVENT_TYPE_SERVER_TRAILER",
+                EventType::Cancel => "EVENT_TYPE_CANCEL",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "EVENT_TYPE_UNKNOWN" => Some(Self::Unknown),
+                "EVENT_TYPE_CLIENT_HEADER" => Some(Self::ClientHeader),
+                "EVENT_TYPE_SERVER_HEADER" => Some(Self::ServerHeader),
+                "EVENT_TYPE_CLIENT_MESSAGE" => Some(Self::ClientMessage),
+                "EVENT_TYPE_SERVER_MESSAGE" => Some(Self::ServerMessage),
+                "EVENT_TYPE_CLIENT_HALF_CLOSE" => Some(Self::ClientHalfClose),
+                "EVENT_TYPE_SERVER_TRAILER" => Some(Self::ServerTrailer),
+                "EVENT_TYPE_CANCEL" => Some(Self::Cancel),
+                _ => None,
+            }
+        }
+    }
+    /// Enumerates the entity that generates the log entry
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Logger {
+        Unknown = 0,
+        Client = 1,
+        Server = 2,
+    }
+    impl Logger {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Logger::Unknown => "LOGGER_UNKNOWN",
+                Logger::Client => "LOGGER_CLIENT",
+                Logger::Server => "LOGGER_SERVER",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "LOGGER_UNKNOWN" => Some(Self::Unknown),
+                "LOGGER_CLIENT" => Some(Self::Client),
+                "LOGGER_SERVER" => Some(Self::Server),
+                _ => None,
+            }
+        }
+    }
+    /// The logger uses one of the following fields to record the payload,
+    /// according to the type of the log entry.
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Payload {
+        #[prost(message, tag = "6")]
+        ClientHeader(super::ClientHeader),
+        #[prost(message, tag = "7")]
+        ServerHeader(super::ServerHeader),
+        /// Used by EVENT_TYPE_CLIENT_MESSAGE, EVENT_TYPE_SERVER_MESSAGE
+        #[prost(message, tag = "8")]
+        Message(super::Message),
+        #[prost(message, tag = "9")]
+        Trailer(super::Trailer),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClientHeader {
+    /// This contains only the metadata from the application.
+    #[prost(message, optional, tag = "1")]
+    pub metadata: ::core::option::Option<Metadata>,
+    /// The name of the RPC method, which looks something like:
+    /// /<service>/<method>
+    /// Note the leading "/" character.
+    #[prost(string, tag = "2")]
+    pub method_name: ::prost::alloc::string::String,
+    /// A single process may be used to run multiple virtual
+    /// servers with different identities.
+    /// The authority is the name of such a server identity.
+    /// It is typically a portion of the URI in the form of
+    /// <host> or <host>:<port> .
+    #[prost(string, tag = "3")]
+    pub authority: ::prost::alloc::string::String,
+    /// the RPC timeout
+    #[prost(message, optional, tag = "4")]
+    pub timeout: ::core::option::Option<::prost_types::Duration>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ServerHeader {
+    /// This contains only the metadata from the application.
+    #[prost(message, optional, tag = "1")]
+    pub metadata: ::core::option::Option<Metadata>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Trailer {
+    /// This contains only the metadata from the application.
+    #[prost(message, optional, tag = "1")]
+    pub metadata: ::core::option::Option<Metadata>,
+    /// The gRPC status code.
+    #[prost(uint32, tag = "2")]
+    pub status_code: u32,
+    /// An original status message before any transport specific
+    /// encoding.
+    #[prost(string, tag = "3")]
+    pub status_message: ::prost::alloc::string::String,
+    /// The value of the 'grpc-status-details-bin' metadata key. If
+    /// present, this is always an encoded 'google.rpc.Status' message.
+    #[prost(bytes = "vec", tag = "4")]
+    pub status_details: ::prost::alloc::vec::Vec<u8>,
+}
+/// Message payload, used by CLIENT_MESSAGE and SERVER_MESSAGE
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Message {
+    /// Length of the message. It may not be the same as the length of the
+    /// data field, as the logging payload can be truncated or omitted.
+    #[prost(uint32, tag = "1")]
+    pub length: u32,
+    /// May be truncated or omitted.
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+}
+/// A list of metadata pairs, used in the payload of client header,
+/// server header, and server trailer.
+/// Implementations may omit some entries to honor the header limits
+/// of GRPC_BINARY_LOG_CONFIG.
+///
+/// Header keys added by gRPC are omitted. To be more specific,
+/// implementations will not log the following entries, and this is
+/// not to be treated as a truncation:
+/// - entries handled by grpc that are not user visible, such as those
+///   that begin with 'grpc-' (with exception of grpc-trace-bin)
+///   or keys like 'lb-token'
+/// - transport specific entries, including but not limited to:
+///   ':path', ':authority', 'content-encoding', 'user-agent', 'te', etc
+/// - entries added for call credentials
+///
+/// Implementations must always log grpc-trace-bin if it is present.
+/// Practically speaking it will only be visible on server side because
+/// grpc-trace-bin is managed by low level client side mechanisms
+/// inaccessible from the application level. On server side, the
+/// header is just a normal metadata key.
+/// The pair will not count towards the size limit.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Metadata {
+    #[prost(message, repeated, tag = "1")]
+    pub entry: ::prost::alloc::vec::Vec<MetadataEntry>,
+}
+/// A metadata key value pair
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MetadataEntry {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: ::prost::alloc::vec::Vec<u8>,
+}
+/// Address information
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Address {
+    #[prost(enumeration = "address::Type", tag = "1")]
+    pub r#type: i32,
+    #[prost(string, tag = "2")]
+    pub address: ::prost::alloc::string::String,
+    /// only for TYPE_IPV4 and TYPE_IPV6
+    #[prost(uint32, tag = "3")]
+    pub ip_port: u32,
+}
+/// Nested message and enum types in `Address`.
+pub mod address {
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Type {
+        Unknown = 0,
+        /// address is in 1.2.3.4 form
+        Ipv4 = 1,
+        /// address is in IPv6 canonical form (RFC5952 section 4)
+        /// The scope is NOT included in the address string.
+        Ipv6 = 2,
+        /// address is UDS string
+        Unix = 3,
+    }
+    impl Type {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Type::Unknown => "TYPE_UNKNOWN",
+                Type::Ipv4 => "TYPE_IPV4",
+                Type::Ipv6 => "TYPE_IPV6",
+                Type::Unix => "TYPE_UNIX",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "TYPE_UNKNOWN" => Some(Self::Unknown),
+                "TYPE_IPV4" => Some(Self::Ipv4),
+                "TYPE_IPV6" => Some(Self::Ipv6),
+                "TYPE_UNIX" => Some(Self::Unix),
+                _ => None,
+            }
+        }
+    }
+}
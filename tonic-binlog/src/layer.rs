@@ -0,0 +1,523 @@
+use crate::{
+    call::{address, Call, Frames},
+    pb::{grpc_log_entry::EventType, grpc_log_entry::Logger, Address},
+    Filter, Sink,
+};
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use http_body::Body;
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+use tonic::{body::BoxBody, transport::server::TcpConnectInfo};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Layer logging the calls of a server or a client to a [`Sink`].
+///
+/// Server layers are added with
+/// [`Server::layer`](tonic::transport::Server::layer), and client layers
+/// wrap a [`Channel`](tonic::transport::Channel).
+#[derive(Clone)]
+pub struct BinaryLogLayer {
+    logger: Logger,
+    filter: Arc<Filter>,
+    sink: Arc<dyn Sink>,
+}
+
+impl BinaryLogLayer {
+    /// Create a layer logging the calls a server receives.
+    pub fn server(sink: impl Sink, filter: Filter) -> Self {
+        BinaryLogLayer {
+            logger: Logger::Server,
+            filter: Arc::new(filter),
+            sink: Arc::new(sink),
+        }
+    }
+
+    /// Create a layer logging the calls a client makes.
+    pub fn client(sink: impl Sink, filter: Filter) -> Self {
+        BinaryLogLayer {
+            logger: Logger::Client,
+            ..Self::server(sink, filter)
+        }
+    }
+}
+
+impl<S> Layer<S> for BinaryLogLayer {
+    type Service = BinaryLog<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BinaryLog {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for BinaryLogLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinaryLogLayer")
+            .field("logger", &self.logger)
+            .field("filter", &self.filter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Service created by [`BinaryLogLayer`].
+///
+/// Servers are services of `hyper::Body` requests, and clients of
+/// [`BoxBody`] requests.
+#[derive(Debug, Clone)]
+pub struct BinaryLog<S> {
+    inner: S,
+    layer: BinaryLogLayer,
+}
+
+impl<S> BinaryLog<S> {
+    /// Start logging the call of `request`, if the filter selects its
+    /// method.
+    fn start<B>(&self, request: &Request<B>) -> Option<Arc<Call>> {
+        let limits = self.layer.filter.limits(request.uri().path())?;
+        let call = Call::new(self.layer.logger, limits, self.layer.sink.clone());
+
+        let authority = match request.uri().authority() {
+            Some(authority) => authority.as_str(),
+            None => request
+                .headers()
+                .get(http::header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .unwrap_or_default(),
+        };
+        let peer = match self.layer.logger {
+            Logger::Server => peer(request),
+            _ => None,
+        };
+        call.client_header(request.uri().path(), authority, request.headers(), peer);
+
+        Some(Arc::new(call))
+    }
+}
+
+/// The address of the client of a server request.
+fn peer<B>(request: &Request<B>) -> Option<Address> {
+    let info = request.extensions().get::<TcpConnectInfo>()?;
+    info.remote_addr().map(address)
+}
+
+impl<S, ResBody> Service<Request<hyper::Body>> for BinaryLog<S>
+where
+    S: Service<Request<hyper::Body>, Response = Response<ResBody>>,
+    ResBody: Body<Data = Bytes>,
+{
+    type Response = Response<BinaryLogBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<hyper::Body>) -> Self::Future {
+        let call = self.start(&request);
+        let request = match &call {
+            Some(call) => request.map(|body| {
+                let body = BinaryLogBody::request(body, call.clone());
+                hyper::Body::wrap_stream(DataStream(body))
+            }),
+            None => request,
+        };
+
+        ResponseFuture {
+            inner: self.inner.call(request),
+            call,
+        }
+    }
+}
+
+impl<S, ResBody> Service<Request<BoxBody>> for BinaryLog<S>
+where
+    S: Service<Request<BoxBody>, Response = Response<ResBody>>,
+    ResBody: Body<Data = Bytes>,
+{
+    type Response = Response<BinaryLogBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        let call = self.start(&request);
+        let request = match &call {
+            Some(call) => {
+                request.map(|body| BoxBody::new(BinaryLogBody::request(body, call.clone())))
+            }
+            None => request,
+        };
+
+        ResponseFuture {
+            inner: self.inner.call(request),
+            call,
+        }
+    }
+}
+
+/// Response future for [`BinaryLog`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    call: Option<Arc<Call>>,
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: Body<Data = Bytes>,
+{
+    type Output = Result<Response<BinaryLogBody<ResBody>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = match ready!(this.inner.poll(cx)) {
+            Ok(response) => response,
+            Err(error) => {
+                if let Some(call) = this.call.take() {
+                    call.cancel();
+                }
+                return Poll::Ready(Err(error));
+            }
+        };
+
+        let call = match this.call.take() {
+            Some(call) => call,
+            None => return Poll::Ready(Ok(response.map(BinaryLogBody::unlogged))),
+        };
+
+        // Trailers-only responses carry their trailers in the headers.
+        if response.headers().contains_key("grpc-status") {
+            call.server_trailer(response.headers());
+            return Poll::Ready(Ok(response.map(BinaryLogBody::unlogged)));
+        }
+        call.server_header(response.headers());
+        Poll::Ready(Ok(response.map(|body| BinaryLogBody::response(body, call))))
+    }
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish_non_exhaustive()
+    }
+}
+
+/// Body logging the messages it carries, for [`BinaryLog`].
+///
+/// Request bodies log the end of the messages of the client, and response
+/// bodies the trailers, or the cancellation of the call if they are dropped
+/// before their trailers.
+#[pin_project]
+pub struct BinaryLogBody<B> {
+    #[pin]
+    inner: B,
+    log: Option<BodyLog>,
+}
+
+impl<B> BinaryLogBody<B> {
+    fn request(inner: B, call: Arc<Call>) -> Self {
+        Self::logged(inner, call, EventType::ClientMessage)
+    }
+
+    fn response(inner: B, call: Arc<Call>) -> Self {
+        Self::logged(inner, call, EventType::ServerMessage)
+    }
+
+    fn logged(inner: B, call: Arc<Call>, messages: EventType) -> Self {
+        BinaryLogBody {
+            inner,
+            log: Some(BodyLog {
+                call,
+                messages,
+                frames: Frames::default(),
+                ended: false,
+            }),
+        }
+    }
+
+    fn unlogged(inner: B) -> Self {
+        BinaryLogBody { inner, log: None }
+    }
+}
+
+impl<B> Body for BinaryLogBody<B>
+where
+    B: Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let data = ready!(this.inner.poll_data(cx));
+
+        if let Some(log) = this.log {
+            match &data {
+                Some(Ok(data)) => log.data(data),
+                // Requests end with their data, responses with the trailers.
+                None if log.messages == EventType::ClientMessage => log.end(None),
+                _ => {}
+            }
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx));
+
+        if let (Some(log), Ok(trailers)) = (this.log, &trailers) {
+            log.end(trailers.as_ref());
+        }
+        Poll::Ready(trailers)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B> fmt::Debug for BinaryLogBody<B>
+where
+    B: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinaryLogBody")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The logging state of a [`BinaryLogBody`].
+struct BodyLog {
+    call: Arc<Call>,
+    /// The event of the messages of the body.
+    messages: EventType,
+    frames: Frames,
+    ended: bool,
+}
+
+impl BodyLog {
+    fn data(&mut self, data: &Bytes) {
+        let (call, messages) = (&self.call, self.messages);
+        self.frames
+            .push(data, call.message_limit(), |length, data| {
+                call.message(messages, length, data)
+            });
+    }
+
+    fn end(&mut self, trailers: Option<&HeaderMap>) {
+        if std::mem::replace(&mut self.ended, true) {
+            return;
+        }
+        match (self.messages, trailers) {
+            (EventType::ClientMessage, _) => self.call.client_half_close(),
+            (_, Some(trailers)) => self.call.server_trailer(trailers),
+            (_, None) => self.call.server_trailer(&HeaderMap::new()),
+        }
+    }
+}
+
+impl Drop for BodyLog {
+    fn drop(&mut self) {
+        if !self.ended && self.messages == EventType::ServerMessage {
+            self.call.cancel();
+        }
+    }
+}
+
+/// The data of a request body, as a stream for `hyper::Body::wrap_stream`.
+///
+/// Clients don't send trailers, so the body doesn't lose any.
+struct DataStream<B>(B);
+
+impl<B> tokio_stream::Stream for DataStream<B>
+where
+    B: Body + Unpin,
+{
+    type Item = Result<B::Data, B::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_data(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::{grpc_log_entry::Payload, GrpcLogEntry};
+    use std::{convert::Infallible, sync::Mutex};
+    use tower::ServiceExt;
+
+    /// A gRPC message frame of `data`.
+    fn frame(data: &[u8]) -> Bytes {
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        frame.extend_from_slice(data);
+        frame.into()
+    }
+
+    fn entries() -> (Arc<Mutex<Vec<GrpcLogEntry>>>, impl Sink) {
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let entries = entries.clone();
+            move |entry| entries.lock().unwrap().push(entry)
+        };
+        (entries, sink)
+    }
+
+    #[tokio::test]
+    async fn logs_server_calls() {
+        let (entries, sink) = entries();
+        let filter = "*{h;m:2}".parse().unwrap();
+
+        let inner = tower::service_fn(|request: Request<hyper::Body>| async move {
+            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+            assert_eq!(body, frame(b"ping"));
+
+            let (mut tx, body) = hyper::Body::channel();
+            tokio::spawn(async move {
+                tx.send_data(frame(b"pong")).await.unwrap();
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", "5".parse().unwrap());
+                trailers.insert("grpc-message", "not%20here".parse().unwrap());
+                tx.send_trailers(trailers).await.unwrap();
+            });
+            let mut response = Response::new(body);
+            response
+                .headers_mut()
+                .insert("x-answer", "42".parse().unwrap());
+            Ok::<_, Infallible>(response)
+        });
+        let service = BinaryLogLayer::server(sink, filter).layer(inner);
+
+        let request = Request::builder()
+            .uri("http://example.com/echo.Echo/Call")
+            .header("x-question", "?")
+            .header("grpc-timeout", "5S")
+            .header("content-type", "application/grpc")
+            .body(hyper::Body::from(frame(b"ping")))
+            .unwrap();
+        let mut response = service.oneshot(request).await.unwrap();
+        hyper::body::to_bytes(response.body_mut()).await.unwrap();
+        response.body_mut().trailers().await.unwrap();
+        drop(response);
+
+        let entries = entries.lock().unwrap();
+        let types: Vec<_> = entries.iter().map(|entry| entry.r#type()).collect();
+        assert_eq!(
+            types,
+            [
+                EventType::ClientHeader,
+                EventType::ClientMessage,
+                EventType::ClientHalfClose,
+                EventType::ServerHeader,
+                EventType::ServerMessage,
+                EventType::ServerTrailer,
+            ]
+        );
+        assert!(entries.iter().all(|entry| entry.logger() == Logger::Server));
+        let sequence: Vec<_> = entries.iter().map(|e| e.sequence_id_within_call).collect();
+        assert_eq!(sequence, [1, 2, 3, 4, 5, 6]);
+
+        match &entries[0].payload {
+            Some(Payload::ClientHeader(header)) => {
+                assert_eq!(header.method_name, "/echo.Echo/Call");
+                assert_eq!(header.authority, "example.com");
+                assert_eq!(header.timeout.as_ref().unwrap().seconds, 5);
+                let metadata = &header.metadata.as_ref().unwrap().entry;
+                assert_eq!(metadata.len(), 1);
+                assert_eq!(metadata[0].key, "x-question");
+            }
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+        match &entries[4].payload {
+            Some(Payload::Message(message)) => {
+                assert_eq!(message.length, 4);
+                assert_eq!(message.data, b"po");
+                assert!(entries[4].payload_truncated);
+            }
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+        match &entries[5].payload {
+            Some(Payload::Trailer(trailer)) => {
+                assert_eq!(trailer.status_code, 5);
+                assert_eq!(trailer.status_message, "not here");
+            }
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+    }
+
+    #[tokio::test]
+    async fn logs_cancelled_client_calls() {
+        let (entries, sink) = entries();
+
+        let inner = tower::service_fn(|_: Request<BoxBody>| async {
+            Ok::<_, Infallible>(Response::new(hyper::Body::from(frame(b"partial"))))
+        });
+        let service = BinaryLogLayer::client(sink, Filter::all()).layer(inner);
+
+        let request = Request::builder()
+            .uri("/echo.Echo/Call")
+            .body(tonic::body::empty_body())
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        drop(response);
+
+        let entries = entries.lock().unwrap();
+        let types: Vec<_> = entries.iter().map(|entry| entry.r#type()).collect();
+        assert_eq!(
+            types,
+            [
+                EventType::ClientHeader,
+                EventType::ServerHeader,
+                EventType::Cancel
+            ]
+        );
+        assert!(entries.iter().all(|entry| entry.logger() == Logger::Client));
+    }
+
+    #[tokio::test]
+    async fn skips_filtered_methods() {
+        let (entries, sink) = entries();
+        let filter = "echo.Echo/*,-echo.Echo/Call".parse().unwrap();
+
+        let inner = tower::service_fn(|_: Request<hyper::Body>| async {
+            Ok::<_, Infallible>(Response::new(hyper::Body::empty()))
+        });
+        let service = BinaryLogLayer::server(sink, filter).layer(inner);
+
+        let request = Request::builder()
+            .uri("/echo.Echo/Call")
+            .body(hyper::Body::empty())
+            .unwrap();
+        service.oneshot(request).await.unwrap();
+
+        assert!(entries.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+//! [gRPC binary logging] for `tonic` servers and clients.
+//!
+//! A [`BinaryLogLayer`] records the calls of a server or a client as
+//! [`GrpcLogEntry`](pb::GrpcLogEntry) messages of the `grpc.binarylog.v1`
+//! format, written to a [`Sink`]:
+//!
+//! - the client headers, with the method, authority, timeout and peer,
+//! - the messages of both sides, along with their length,
+//! - the end of the messages of the client,
+//! - the server headers and trailers, with the status of the call,
+//! - and the cancellation of calls that end before their trailers.
+//!
+//! A [`Filter`] selects the logged methods and how many bytes of their
+//! metadata and messages are logged, in the syntax of the
+//! `GRPC_BINARY_LOG_FILTER` environment variable of other gRPC
+//! implementations.
+//!
+//! Messages are logged as they are sent, compressed messages included.
+//!
+//! # Example
+//!
+//! ```
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! use tonic::transport::{Channel, Server};
+//! use tonic_binlog::{BinaryLogLayer, Filter, GrpcLogEntry};
+//! use tower_layer::Layer;
+//!
+//! let sink = |entry: GrpcLogEntry| println!("{:?}", entry);
+//! let filter: Filter = "*{h:256;m:256}".parse().unwrap();
+//!
+//! let server = Server::builder().layer(BinaryLogLayer::server(sink, filter.clone()));
+//!
+//! let channel = Channel::from_static("http://[::1]:50051").connect_lazy();
+//! let channel = BinaryLogLayer::client(sink, filter).layer(channel);
+//! # }
+//! ```
+//!
+//! [gRPC binary logging]: https://github.com/grpc/proposal/blob/master/A16-binary-logging.md
+
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
+)]
+#![doc(html_root_url = "https://docs.rs/tonic-binlog/0.11.0")]
+#![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
+
+mod generated {
+    #![allow(unreachable_pub)]
+    #![allow(missing_docs)]
+    #![allow(rustdoc::invalid_html_tags)]
+    include!("generated/grpc_binarylog_v1.rs");
+}
+
+/// Generated protobuf types from the `grpc.binarylog.v1` package.
+pub mod pb {
+    pub use crate::generated::*;
+}
+
+mod call;
+mod filter;
+mod layer;
+mod sink;
+
+pub use filter::{Filter, FilterError, Limits};
+pub use layer::{BinaryLog, BinaryLogBody, BinaryLogLayer, ResponseFuture};
+pub use pb::GrpcLogEntry;
+pub use sink::{Sink, WriterSink};
+
+pub(crate) mod util {
+    pub(crate) mod base64 {
+        use base64::{
+            alphabet,
+            engine::{
+                general_purpose::{GeneralPurpose, GeneralPurposeConfig},
+                DecodePaddingMode,
+            },
+        };
+
+        pub(crate) const STANDARD_NO_PAD: GeneralPurpose = GeneralPurpose::new(
+            &alphabet::STANDARD,
+            GeneralPurposeConfig::new()
+                .with_encode_padding(false)
+                .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use crate::pb::GrpcLogEntry;
+use prost::Message as _;
+use std::{fmt, io::Write, sync::Mutex};
+
+/// Destination of the entries of a binary log.
+///
+/// Entries are written as the calls progress, from the tasks driving the
+/// calls, so sinks shouldn't block for long.
+pub trait Sink: Send + Sync + 'static {
+    /// Write `entry` to the log.
+    fn write(&self, entry: GrpcLogEntry);
+}
+
+impl<F> Sink for F
+where
+    F: Fn(GrpcLogEntry) + Send + Sync + 'static,
+{
+    fn write(&self, entry: GrpcLogEntry) {
+        self(entry)
+    }
+}
+
+/// Writes the entries to `W`, each preceded by its length as a big endian
+/// `u32`.
+///
+/// This is the format other gRPC implementations write binary logs in.
+/// Errors writing an entry are ignored, the log is only missing the entry.
+pub struct WriterSink<W> {
+    writer: Mutex<W>,
+}
+
+impl<W> WriterSink<W> {
+    /// Create a sink writing to `writer`, such as a buffered file.
+    pub fn new(writer: W) -> Self {
+        WriterSink {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Get back the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(|poison| poison.into_inner())
+    }
+}
+
+impl<W> Sink for WriterSink<W>
+where
+    W: Write + Send + 'static,
+{
+    fn write(&self, entry: GrpcLogEntry) {
+        let mut buf = Vec::with_capacity(4 + entry.encoded_len());
+        buf.extend_from_slice(&(entry.encoded_len() as u32).to_be_bytes());
+        entry
+            .encode(&mut buf)
+            .expect("the buffer has enough capacity");
+
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        let _ = writer.write_all(&buf);
+    }
+}
+
+impl<W> fmt::Debug for WriterSink<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriterSink").finish_non_exhaustive()
+    }
+}
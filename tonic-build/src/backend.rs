@@ -0,0 +1,368 @@
+//! Code generation of services whose messages come from a protobuf
+//! implementation other than `prost`, such as the `protobuf` crate.
+//!
+//! The services are described by their protobuf names, as in their
+//! descriptors, and a [`Backend`] tells the Rust types of their messages and
+//! the codec encoding them.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use tonic_build::backend::{Backend, Builder, Method, Service};
+//!
+//! /// Messages generated by `protobuf-codegen`, a module per `.proto` file.
+//! struct RustProtobuf;
+//!
+//! impl Backend for RustProtobuf {
+//!     fn codec_path(&self) -> &str {
+//!         "crate::codec::ProtobufCodec"
+//!     }
+//!
+//!     fn message_type(&self, _package: &str, proto_type: &str, proto_path: &str) -> String {
+//!         let name = proto_type.rsplit('.').next().unwrap();
+//!         format!("{}::helloworld::{}", proto_path, name)
+//!     }
+//! }
+//!
+//! let greeter = Service {
+//!     proto_name: "Greeter".to_owned(),
+//!     package: "helloworld".to_owned(),
+//!     comments: Vec::new(),
+//!     methods: vec![Method {
+//!         proto_name: "SayHello".to_owned(),
+//!         input_type: ".helloworld.HelloRequest".to_owned(),
+//!         output_type: ".helloworld.HelloReply".to_owned(),
+//!         client_streaming: false,
+//!         server_streaming: false,
+//!         comments: Vec::new(),
+//!     }],
+//! };
+//!
+//! Builder::new(RustProtobuf).compile(&[greeter]).unwrap();
+//! ```
+
+use crate::{code_gen::CodeGenBuilder, naive_snake_case};
+
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A protobuf implementation providing the messages of generated services.
+pub trait Backend {
+    /// The path of the codec of the messages, a type with a `default()`
+    /// associated function, such as `tonic::codec::ProstCodec`.
+    fn codec_path(&self) -> &str;
+
+    /// The Rust type of the message `proto_type`, a fully qualified protobuf
+    /// name such as `.helloworld.HelloRequest`, for a service of `package`.
+    ///
+    /// The generated code is in a module of its own, from which
+    /// `proto_path` is the path of the module of the package.
+    fn message_type(&self, package: &str, proto_type: &str, proto_path: &str) -> String;
+}
+
+/// A service, described as in its descriptor.
+#[derive(Debug, Clone, Default)]
+pub struct Service {
+    /// The name of the service, such as `Greeter`.
+    pub proto_name: String,
+    /// The package of the service, such as `helloworld`.
+    pub package: String,
+    /// The leading comments of the service.
+    pub comments: Vec<String>,
+    /// The methods of the service.
+    pub methods: Vec<Method>,
+}
+
+/// A method of a [`Service`], described as in its descriptor.
+#[derive(Debug, Clone, Default)]
+pub struct Method {
+    /// The name of the method, such as `SayHello`.
+    pub proto_name: String,
+    /// The fully qualified name of the request message, such as
+    /// `.helloworld.HelloRequest`.
+    pub input_type: String,
+    /// The fully qualified name of the response message.
+    pub output_type: String,
+    /// Whether the client streams requests.
+    pub client_streaming: bool,
+    /// Whether the server streams responses.
+    pub server_streaming: bool,
+    /// The leading comments of the method.
+    pub comments: Vec<String>,
+}
+
+/// A [`Service`] with the types of its backend.
+struct BackendService<'a> {
+    service: &'a Service,
+    methods: Vec<BackendMethod<'a>>,
+}
+
+struct BackendMethod<'a> {
+    method: &'a Method,
+    name: String,
+    codec_path: String,
+    request: String,
+    response: String,
+}
+
+impl<'a> BackendService<'a> {
+    fn new(service: &'a Service, backend: &impl Backend, proto_path: &str) -> Self {
+        let methods = service.methods.iter().map(|method| BackendMethod {
+            method,
+            name: naive_snake_case(&method.proto_name),
+            codec_path: backend.codec_path().to_owned(),
+            request: backend.message_type(&service.package, &method.input_type, proto_path),
+            response: backend.message_type(&service.package, &method.output_type, proto_path),
+        });
+        BackendService {
+            service,
+            methods: methods.collect(),
+        }
+    }
+}
+
+impl<'a> crate::Service for BackendService<'a> {
+    type Comment = String;
+    type Method = BackendMethod<'a>;
+
+    fn name(&self) -> &str {
+        &self.service.proto_name
+    }
+
+    fn package(&self) -> &str {
+        &self.service.package
+    }
+
+    fn identifier(&self) -> &str {
+        &self.service.proto_name
+    }
+
+    fn methods(&self) -> &[Self::Method] {
+        &self.methods
+    }
+
+    fn comment(&self) -> &[Self::Comment] {
+        &self.service.comments
+    }
+}
+
+impl crate::Method for BackendMethod<'_> {
+    type Comment = String;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn identifier(&self) -> &str {
+        &self.method.proto_name
+    }
+
+    fn codec_path(&self) -> &str {
+        &self.codec_path
+    }
+
+    fn client_streaming(&self) -> bool {
+        self.method.client_streaming
+    }
+
+    fn server_streaming(&self) -> bool {
+        self.method.server_streaming
+    }
+
+    fn comment(&self) -> &[Self::Comment] {
+        &self.method.comments
+    }
+
+    fn request_response_name(
+        &self,
+        _proto_path: &str,
+        _compile_well_known_types: bool,
+    ) -> (TokenStream, TokenStream) {
+        let parse = |ty: &str| {
+            syn::parse_str::<syn::Type>(ty)
+                .unwrap_or_else(|_| panic!("`{}` isn't a Rust type", ty))
+                .to_token_stream()
+        };
+        (parse(&self.request), parse(&self.response))
+    }
+}
+
+/// Service generator builder, for the messages of a [`Backend`].
+#[derive(Debug)]
+pub struct Builder<B> {
+    backend: B,
+    build_server: bool,
+    build_client: bool,
+    build_transport: bool,
+    emit_package: bool,
+    message_interceptors: bool,
+    proto_path: String,
+
+    out_dir: Option<PathBuf>,
+}
+
+impl<B: Backend> Builder<B> {
+    /// Create a new Builder of the services of `backend`.
+    pub fn new(backend: B) -> Self {
+        Builder {
+            backend,
+            build_server: true,
+            build_client: true,
+            build_transport: true,
+            emit_package: true,
+            message_interceptors: false,
+            proto_path: "super".to_owned(),
+            out_dir: None,
+        }
+    }
+
+    /// Enable or disable gRPC client code generation.
+    ///
+    /// Defaults to enabling client code generation.
+    pub fn build_client(mut self, enable: bool) -> Self {
+        self.build_client = enable;
+        self
+    }
+
+    /// Enable or disable gRPC server code generation.
+    ///
+    /// Defaults to enabling server code generation.
+    pub fn build_server(mut self, enable: bool) -> Self {
+        self.build_server = enable;
+        self
+    }
+
+    /// Enable or disable generated clients and servers to have built-in tonic
+    /// transport features.
+    ///
+    /// When the `transport` feature is disabled this does nothing.
+    pub fn build_transport(mut self, enable: bool) -> Self {
+        self.build_transport = enable;
+        self
+    }
+
+    /// Emit package names in the paths of the methods of the services.
+    ///
+    /// Defaults to `true`.
+    pub fn emit_package(mut self, enable: bool) -> Self {
+        self.emit_package = enable;
+        self
+    }
+
+    /// Enable or disable generated clients to have a `with_message_interceptor` method, setting a
+    /// `tonic::client::MessageInterceptor`.
+    ///
+    /// Defaults to `false`.
+    pub fn client_message_interceptors(mut self, enable: bool) -> Self {
+        self.message_interceptors = enable;
+        self
+    }
+
+    /// Set the path of the module of the package of the services, as passed
+    /// to [`Backend::message_type`].
+    ///
+    /// Defaults to `super`.
+    pub fn proto_path(mut self, proto_path: impl AsRef<str>) -> Self {
+        self.proto_path = proto_path.as_ref().to_owned();
+        self
+    }
+
+    /// Set the output directory to generate code to.
+    ///
+    /// Defaults to the `OUT_DIR` environment variable.
+    pub fn out_dir(mut self, out_dir: impl AsRef<Path>) -> Self {
+        self.out_dir = Some(out_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Generate the code of the client and the server of `service`.
+    pub fn generate(&self, service: &Service) -> String {
+        let service = BackendService::new(service, &self.backend, &self.proto_path);
+        let mut code = TokenStream::new();
+
+        if self.build_client {
+            code.extend(
+                CodeGenBuilder::new()
+                    .emit_package(self.emit_package)
+                    .build_transport(self.build_transport)
+                    .message_interceptors(self.message_interceptors)
+                    .generate_client(&service, &self.proto_path),
+            );
+        }
+        if self.build_server {
+            code.extend(
+                CodeGenBuilder::new()
+                    .emit_package(self.emit_package)
+                    .generate_server(&service, &self.proto_path),
+            );
+        }
+
+        let ast: syn::File = syn::parse2(code).expect("not a valid tokenstream");
+        prettyplease::unparse(&ast)
+    }
+
+    /// Performs code generation for the provided services.
+    ///
+    /// Generated services will be output into the directory specified by `out_dir`
+    /// with files named `<package_name>.<service_name>.rs`.
+    pub fn compile(&self, services: &[Service]) -> io::Result<()> {
+        let out_dir = match &self.out_dir {
+            Some(out_dir) => out_dir.clone(),
+            None => std::env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?,
+        };
+
+        for service in services {
+            let out_file = out_dir.join(format!("{}.{}.rs", service.package, service.proto_name));
+            fs::write(out_file, self.generate(service))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Json;
+
+    impl Backend for Json {
+        fn codec_path(&self) -> &str {
+            "crate::JsonCodec"
+        }
+
+        fn message_type(&self, package: &str, proto_type: &str, proto_path: &str) -> String {
+            let name = proto_type
+                .strip_prefix(&format!(".{}.", package))
+                .expect("messages of the package of the service");
+            format!("{}::json::{}", proto_path, name)
+        }
+    }
+
+    #[test]
+    fn generates_the_types_of_the_backend() {
+        let service = Service {
+            proto_name: "Greeter".to_owned(),
+            package: "helloworld".to_owned(),
+            methods: vec![Method {
+                proto_name: "SayHello".to_owned(),
+                input_type: ".helloworld.HelloRequest".to_owned(),
+                output_type: ".helloworld.HelloReply".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let code = Builder::new(Json).generate(&service);
+        assert!(code.contains("pub async fn say_hello("));
+        assert!(code.contains("request: impl tonic::IntoRequest<super::json::HelloRequest>"));
+        assert!(code.contains("tonic::Response<super::json::HelloReply>"));
+        assert!(code.contains("let codec = crate::JsonCodec::default();"));
+        assert!(code.contains("\"/helloworld.Greeter/SayHello\""));
+    }
+}
@@ -2,12 +2,13 @@ use std::collections::HashSet;
 
 use super::{Attributes, Method, Service};
 use crate::{
-    format_method_name, format_method_path, format_service_name, generate_doc_comments,
-    naive_snake_case,
+    cfg_attributes, format_method_name, format_method_path, format_service_name,
+    generate_doc_comments, generate_method_paths, naive_snake_case,
 };
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn generate_internal<T: Service>(
     service: &T,
     emit_package: bool,
@@ -16,6 +17,11 @@ pub(crate) fn generate_internal<T: Service>(
     build_transport: bool,
     attributes: &Attributes,
     disable_comments: &HashSet<String>,
+    message_interceptors: bool,
+    blocking: bool,
+    ref_self: bool,
+    client_trait: bool,
+    sinks: bool,
 ) -> TokenStream {
     let service_ident = quote::format_ident!("{}Client", service.name());
     let client_mod = quote::format_ident!("{}_client", naive_snake_case(service.name()));
@@ -25,12 +31,39 @@ pub(crate) fn generate_internal<T: Service>(
         proto_path,
         compile_well_known_types,
         disable_comments,
+        ref_self,
+        sinks,
+        attributes,
     );
 
     let connect = generate_connect(&service_ident, build_transport);
+    let message_interceptor = generate_message_interceptor(message_interceptors);
+    let blocking = generate_blocking(
+        service,
+        &service_ident,
+        emit_package,
+        proto_path,
+        compile_well_known_types,
+        build_transport,
+        disable_comments,
+        blocking,
+        ref_self,
+        attributes,
+    );
 
     let package = if emit_package { service.package() } else { "" };
     let service_name = format_service_name(service, emit_package);
+    let client_trait = generate_trait(
+        service,
+        &service_ident,
+        emit_package,
+        proto_path,
+        compile_well_known_types,
+        disable_comments,
+        ref_self,
+        client_trait,
+        attributes,
+    );
 
     let service_doc = if disable_comments.contains(&service_name) {
         TokenStream::new()
@@ -38,8 +71,16 @@ pub(crate) fn generate_internal<T: Service>(
         generate_doc_comments(service.comment())
     };
 
+    let method_paths = generate_method_paths(service, emit_package, attributes);
     let mod_attributes = attributes.for_mod(package);
-    let struct_attributes = attributes.for_struct(&service_name);
+    let mut struct_attributes = attributes.for_struct(&service_name);
+    if service
+        .options()
+        .iter()
+        .any(|(name, value)| name == "deprecated" && value == "true")
+    {
+        struct_attributes.push(syn::parse_quote!(#[deprecated]));
+    }
 
     quote! {
         /// Generated client implementations.
@@ -51,10 +92,14 @@ pub(crate) fn generate_internal<T: Service>(
                 missing_docs,
                 // will trigger if compression is disabled
                 clippy::let_unit_value,
+                // will trigger if methods are deprecated
+                deprecated,
             )]
             use tonic::codegen::*;
             use tonic::codegen::http::Uri;
 
+            #method_paths
+
             #service_doc
             #(#struct_attributes)*
             #[derive(Debug, Clone)]
@@ -94,6 +139,21 @@ pub(crate) fn generate_internal<T: Service>(
                     #service_ident::new(InterceptedService::new(inner, interceptor))
                 }
 
+                pub fn with_async_interceptor<F>(inner: T, interceptor: F) -> #service_ident<AsyncInterceptedService<T, F>>
+                where
+                    F: tonic::service::AsyncInterceptor,
+                    T::ResponseBody: Default,
+                    T: Clone + tonic::codegen::Service<
+                        http::Request<tonic::body::BoxBody>,
+                        Response = http::Response<<T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody>
+                    >,
+                    <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error: Into<StdError> + Send + Sync,
+                {
+                    #service_ident::new(AsyncInterceptedService::new(inner, interceptor))
+                }
+
+                #message_interceptor
+
                 /// Compress requests with the given encoding.
                 ///
                 /// This requires the server to support it otherwise it might respond with an
@@ -104,6 +164,22 @@ pub(crate) fn generate_internal<T: Service>(
                     self
                 }
 
+                /// Send the requests smaller than `bytes` uncompressed.
+                ///
+                /// Default: `0`
+                #[must_use]
+                pub fn send_compressed_min_size(mut self, bytes: usize) -> Self {
+                    self.inner = self.inner.send_compressed_min_size(bytes);
+                    self
+                }
+
+                /// Compress requests at the given level.
+                #[must_use]
+                pub fn send_compression_level(mut self, level: CompressionLevel) -> Self {
+                    self.inner = self.inner.send_compression_level(level);
+                    self
+                }
+
                 /// Enable decompressing responses.
                 #[must_use]
                 pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
@@ -131,6 +207,236 @@ pub(crate) fn generate_internal<T: Service>(
 
                 #methods
             }
+
+            #client_trait
+
+            #blocking
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_trait<T: Service>(
+    service: &T,
+    service_ident: &syn::Ident,
+    emit_package: bool,
+    proto_path: &str,
+    compile_well_known_types: bool,
+    disable_comments: &HashSet<String>,
+    ref_self: bool,
+    enabled: bool,
+    attributes: &Attributes,
+) -> TokenStream {
+    if !enabled {
+        return TokenStream::new();
+    }
+
+    let trait_ident = format_ident!("{}Api", service_ident);
+    let doc = format!(
+        " The calls of a [`{}`], for code that mocks them in tests.",
+        service_ident
+    );
+    let (receiver, _) = generate_receiver(ref_self);
+    let clone_bound = if ref_self {
+        quote! { + Clone }
+    } else {
+        TokenStream::new()
+    };
+    let mut trait_methods = TokenStream::new();
+    let mut impl_methods = TokenStream::new();
+
+    for method in service.methods() {
+        if !disable_comments.contains(&format_method_name(service, method, emit_package)) {
+            trait_methods.extend(generate_doc_comments(method.comment()));
+        }
+        let method_attributes = attributes.for_method(method);
+        let cfg_attributes = cfg_attributes(&method_attributes);
+
+        let ident = format_ident!("{}", method.name());
+        let (request, response) =
+            method.request_response_name(proto_path, compile_well_known_types);
+        let request = if method.client_streaming() {
+            quote! { tonic::Request<RequestStream<#request>> }
+        } else {
+            quote! { tonic::Request<#request> }
+        };
+        let (response, into_response) = if method.server_streaming() {
+            (
+                quote! { BoxStream<#response> },
+                quote! { .map(|response| response.map(|stream| Box::pin(stream) as BoxStream<_>)) },
+            )
+        } else {
+            (quote! { #response }, TokenStream::new())
+        };
+
+        trait_methods.extend(quote! {
+            #(#cfg_attributes)*
+            async fn #ident(
+                #receiver,
+                request: #request,
+            ) -> std::result::Result<tonic::Response<#response>, tonic::Status>;
+        });
+        impl_methods.extend(quote! {
+            #(#cfg_attributes)*
+            async fn #ident(
+                #receiver,
+                request: #request,
+            ) -> std::result::Result<tonic::Response<#response>, tonic::Status> {
+                #service_ident::#ident(self, request).await #into_response
+            }
+        });
+    }
+
+    quote! {
+        #[doc = #doc]
+        ///
+        /// Streaming responses are boxed, so mocks may return any stream.
+        #[async_trait]
+        pub trait #trait_ident: Send + Sync + 'static {
+            #trait_methods
+        }
+
+        #[async_trait]
+        impl<T> #trait_ident for #service_ident<T>
+        where
+            T: tonic::client::GrpcService<tonic::body::BoxBody> + Send + Sync #clone_bound + 'static,
+            T::Future: Send,
+            T::Error: Into<StdError>,
+            T::ResponseBody: Body<Data = Bytes> + Send  + 'static,
+            <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+        {
+            #impl_methods
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_blocking<T: Service>(
+    service: &T,
+    service_ident: &syn::Ident,
+    emit_package: bool,
+    proto_path: &str,
+    compile_well_known_types: bool,
+    build_transport: bool,
+    disable_comments: &HashSet<String>,
+    enabled: bool,
+    ref_self: bool,
+    attributes: &Attributes,
+) -> TokenStream {
+    if !enabled {
+        return TokenStream::new();
+    }
+
+    let connect = generate_blocking_connect(service_ident, build_transport);
+    let (receiver, where_clause) = generate_receiver(ref_self);
+    let mut methods = TokenStream::new();
+
+    for method in service.methods() {
+        if !disable_comments.contains(&format_method_name(service, method, emit_package)) {
+            methods.extend(generate_doc_comments(method.comment()));
+        }
+        let method_attributes = attributes.for_method(method);
+        methods.extend(quote! { #(#method_attributes)* });
+
+        let ident = format_ident!("{}", method.name());
+        let (request, response) =
+            method.request_response_name(proto_path, compile_well_known_types);
+        let (request, response) = (nested_path(request), nested_path(response));
+        let request = if method.client_streaming() {
+            quote! { impl tonic::IntoStreamingRequest<Message = #request> }
+        } else {
+            quote! { impl tonic::IntoRequest<#request> }
+        };
+
+        methods.extend(if method.server_streaming() {
+            quote! {
+                pub fn #ident(
+                    #receiver,
+                    request: #request,
+                ) -> std::result::Result<tonic::Response<tonic::client::blocking::Streaming<#response>>, tonic::Status>
+                #where_clause
+                {
+                    let response = self.rt.block_on(self.inner.#ident(request))?;
+                    let rt = self.rt.clone();
+                    Ok(response.map(|stream| tonic::client::blocking::Streaming::new(stream, rt)))
+                }
+            }
+        } else {
+            quote! {
+                pub fn #ident(
+                    #receiver,
+                    request: #request,
+                ) -> std::result::Result<tonic::Response<#response>, tonic::Status>
+                #where_clause
+                {
+                    self.rt.block_on(self.inner.#ident(request))
+                }
+            }
+        });
+    }
+
+    quote! {
+        /// Generated blocking client implementations.
+        pub mod blocking {
+            use tonic::codegen::*;
+
+            /// A client whose calls block on a `tonic::client::blocking::Runtime`.
+            #[derive(Debug, Clone)]
+            pub struct #service_ident<T> {
+                inner: super::#service_ident<T>,
+                rt: tonic::client::blocking::Runtime,
+            }
+
+            #connect
+
+            impl<T> #service_ident<T>
+            where
+                T: tonic::client::GrpcService<tonic::body::BoxBody>,
+                T::Error: Into<StdError>,
+                T::ResponseBody: Body<Data = Bytes> + Send  + 'static,
+                <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+            {
+                /// Make the calls of `inner` on `rt`, which drives its connections.
+                pub fn new(inner: super::#service_ident<T>, rt: tonic::client::blocking::Runtime) -> Self {
+                    Self { inner, rt }
+                }
+
+                /// The asynchronous client of the calls.
+                pub fn into_inner(self) -> super::#service_ident<T> {
+                    self.inner
+                }
+
+                #methods
+            }
+        }
+    }
+}
+
+/// The path `ty` from the `blocking` module, nested in the client module
+/// relative paths such as `super::Message` start from.
+fn nested_path(ty: TokenStream) -> TokenStream {
+    match syn::parse2::<syn::Path>(ty.clone()) {
+        Ok(path)
+            if path.leading_colon.is_none()
+                && path.segments.first().is_some_and(|s| s.ident == "super") =>
+        {
+            quote! { super::#path }
+        }
+        _ => ty,
+    }
+}
+
+fn generate_message_interceptor(enabled: bool) -> TokenStream {
+    if !enabled {
+        return TokenStream::new();
+    }
+
+    quote! {
+        /// Intercept the messages of calls, before they are encoded and once they are decoded.
+        #[must_use]
+        pub fn with_message_interceptor(mut self, interceptor: impl tonic::client::MessageInterceptor) -> Self {
+            self.inner = self.inner.message_interceptor(interceptor);
+            self
         }
     }
 }
@@ -158,17 +464,70 @@ fn generate_connect(service_ident: &syn::Ident, enabled: bool) -> TokenStream {
     }
 }
 
+#[cfg(feature = "transport")]
+fn generate_blocking_connect(service_ident: &syn::Ident, enabled: bool) -> TokenStream {
+    if !enabled {
+        return TokenStream::new();
+    }
+
+    quote! {
+        impl #service_ident<tonic::transport::Channel> {
+            /// Attempt to create a new client by connecting to a given endpoint, with a
+            /// runtime of its own.
+            pub fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+            where
+                D: TryInto<tonic::transport::Endpoint>,
+                D::Error: Into<StdError>,
+            {
+                let (conn, rt) = tonic::client::blocking::connect(dst)?;
+                Ok(Self::new(super::#service_ident::new(conn), rt))
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "transport"))]
+fn generate_blocking_connect(_service_ident: &syn::Ident, _enabled: bool) -> TokenStream {
+    TokenStream::new()
+}
+
 #[cfg(not(feature = "transport"))]
 fn generate_connect(_service_ident: &syn::Ident, _enabled: bool) -> TokenStream {
     TokenStream::new()
 }
 
+/// The receiver of call methods, and the bounds `&self` calls need to clone
+/// the inner client.
+fn generate_receiver(ref_self: bool) -> (TokenStream, TokenStream) {
+    if ref_self {
+        (quote! { &self }, quote! { where T: Clone })
+    } else {
+        (quote! { &mut self }, TokenStream::new())
+    }
+}
+
+/// The inner client of call methods, cloned for `&self` calls.
+fn generate_inner(ref_self: bool) -> (TokenStream, TokenStream) {
+    if ref_self {
+        (
+            quote! { inner },
+            quote! { let mut inner = self.inner.clone(); },
+        )
+    } else {
+        (quote! { self.inner }, TokenStream::new())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn generate_methods<T: Service>(
     service: &T,
     emit_package: bool,
     proto_path: &str,
     compile_well_known_types: bool,
     disable_comments: &HashSet<String>,
+    ref_self: bool,
+    sinks: bool,
+    attributes: &Attributes,
 ) -> TokenStream {
     let mut stream = TokenStream::new();
 
@@ -176,14 +535,17 @@ fn generate_methods<T: Service>(
         if !disable_comments.contains(&format_method_name(service, method, emit_package)) {
             stream.extend(generate_doc_comments(method.comment()));
         }
+        let method_attributes = attributes.for_method(method);
+        stream.extend(quote! { #(#method_attributes)* });
 
-        let method = match (method.client_streaming(), method.server_streaming()) {
+        let call = match (method.client_streaming(), method.server_streaming()) {
             (false, false) => generate_unary(
                 service,
                 method,
                 emit_package,
                 proto_path,
                 compile_well_known_types,
+                ref_self,
             ),
             (false, true) => generate_server_streaming(
                 service,
@@ -191,6 +553,7 @@ fn generate_methods<T: Service>(
                 emit_package,
                 proto_path,
                 compile_well_known_types,
+                ref_self,
             ),
             (true, false) => generate_client_streaming(
                 service,
@@ -198,6 +561,7 @@ fn generate_methods<T: Service>(
                 emit_package,
                 proto_path,
                 compile_well_known_types,
+                ref_self,
             ),
             (true, true) => generate_streaming(
                 service,
@@ -205,21 +569,78 @@ fn generate_methods<T: Service>(
                 emit_package,
                 proto_path,
                 compile_well_known_types,
+                ref_self,
             ),
         };
 
-        stream.extend(method);
+        stream.extend(call);
+
+        if sinks && method.client_streaming() {
+            stream.extend(quote! { #(#method_attributes)* });
+            stream.extend(generate_sink(
+                method,
+                proto_path,
+                compile_well_known_types,
+                ref_self,
+            ));
+        }
     }
 
     stream
 }
 
+fn generate_sink<T: Method>(
+    method: &T,
+    proto_path: &str,
+    compile_well_known_types: bool,
+    ref_self: bool,
+) -> TokenStream {
+    let ident = format_ident!("{}", method.name());
+    let sink_ident = format_ident!("{}_sink", method.name());
+    let (request, response) = method.request_response_name(proto_path, compile_well_known_types);
+    let response = if method.server_streaming() {
+        quote! { tonic::codec::Streaming<#response> }
+    } else {
+        response
+    };
+    let doc = format!(
+        " Call [`{}`](Self::{}) with the messages sent to the returned sink, until it's closed or dropped.",
+        ident, ident
+    );
+    let mutability = if ref_self {
+        TokenStream::new()
+    } else {
+        quote! { mut }
+    };
+
+    quote! {
+        #[doc = #doc]
+        ///
+        /// The returned future calls a clone of the client, so it may be spawned. The messages
+        /// are only sent while it's polled.
+        pub fn #sink_ident(
+            &self,
+        ) -> (
+            tonic::client::sink::RequestSink<#request>,
+            impl std::future::Future<Output = std::result::Result<tonic::Response<#response>, tonic::Status>>,
+        )
+        where
+            T: Clone,
+        {
+            let (sink, requests) = tonic::client::sink::channel(0);
+            let #mutability client = self.clone();
+            (sink, async move { client.#ident(requests).await })
+        }
+    }
+}
+
 fn generate_unary<T: Service>(
     service: &T,
     method: &T::Method,
     emit_package: bool,
     proto_path: &str,
     compile_well_known_types: bool,
+    ref_self: bool,
 ) -> TokenStream {
     let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
     let ident = format_ident!("{}", method.name());
@@ -227,20 +648,25 @@ fn generate_unary<T: Service>(
     let service_name = format_service_name(service, emit_package);
     let path = format_method_path(service, method, emit_package);
     let method_name = method.identifier();
+    let (receiver, where_clause) = generate_receiver(ref_self);
+    let (inner, clone_inner) = generate_inner(ref_self);
 
     quote! {
         pub async fn #ident(
-            &mut self,
+            #receiver,
             request: impl tonic::IntoRequest<#request>,
-        ) -> std::result::Result<tonic::Response<#response>, tonic::Status> {
-           self.inner.ready().await.map_err(|e| {
+        ) -> std::result::Result<tonic::Response<#response>, tonic::Status>
+        #where_clause
+        {
+           #clone_inner
+           #inner.ready().await.map_err(|e| {
                tonic::Status::new(tonic::Code::Unknown, format!("Service was not ready: {}", e.into()))
            })?;
            let codec = #codec_name::default();
            let path = http::uri::PathAndQuery::from_static(#path);
            let mut req = request.into_request();
            req.extensions_mut().insert(GrpcMethod::new(#service_name, #method_name));
-           self.inner.unary(req, path, codec).await
+           #inner.unary(req, path, codec).await
         }
     }
 }
@@ -251,6 +677,7 @@ fn generate_server_streaming<T: Service>(
     emit_package: bool,
     proto_path: &str,
     compile_well_known_types: bool,
+    ref_self: bool,
 ) -> TokenStream {
     let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
     let ident = format_ident!("{}", method.name());
@@ -258,20 +685,25 @@ fn generate_server_streaming<T: Service>(
     let service_name = format_service_name(service, emit_package);
     let path = format_method_path(service, method, emit_package);
     let method_name = method.identifier();
+    let (receiver, where_clause) = generate_receiver(ref_self);
+    let (inner, clone_inner) = generate_inner(ref_self);
 
     quote! {
         pub async fn #ident(
-            &mut self,
+            #receiver,
             request: impl tonic::IntoRequest<#request>,
-        ) -> std::result::Result<tonic::Response<tonic::codec::Streaming<#response>>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
+        ) -> std::result::Result<tonic::Response<tonic::codec::Streaming<#response>>, tonic::Status>
+        #where_clause
+        {
+            #clone_inner
+            #inner.ready().await.map_err(|e| {
                         tonic::Status::new(tonic::Code::Unknown, format!("Service was not ready: {}", e.into()))
             })?;
             let codec = #codec_name::default();
             let path = http::uri::PathAndQuery::from_static(#path);
             let mut req = request.into_request();
             req.extensions_mut().insert(GrpcMethod::new(#service_name, #method_name));
-            self.inner.server_streaming(req, path, codec).await
+            #inner.server_streaming(req, path, codec).await
         }
     }
 }
@@ -282,6 +714,7 @@ fn generate_client_streaming<T: Service>(
     emit_package: bool,
     proto_path: &str,
     compile_well_known_types: bool,
+    ref_self: bool,
 ) -> TokenStream {
     let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
     let ident = format_ident!("{}", method.name());
@@ -289,20 +722,25 @@ fn generate_client_streaming<T: Service>(
     let service_name = format_service_name(service, emit_package);
     let path = format_method_path(service, method, emit_package);
     let method_name = method.identifier();
+    let (receiver, where_clause) = generate_receiver(ref_self);
+    let (inner, clone_inner) = generate_inner(ref_self);
 
     quote! {
         pub async fn #ident(
-            &mut self,
+            #receiver,
             request: impl tonic::IntoStreamingRequest<Message = #request>
-        ) -> std::result::Result<tonic::Response<#response>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
+        ) -> std::result::Result<tonic::Response<#response>, tonic::Status>
+        #where_clause
+        {
+            #clone_inner
+            #inner.ready().await.map_err(|e| {
                         tonic::Status::new(tonic::Code::Unknown, format!("Service was not ready: {}", e.into()))
             })?;
             let codec = #codec_name::default();
             let path = http::uri::PathAndQuery::from_static(#path);
             let mut req = request.into_streaming_request();
             req.extensions_mut().insert(GrpcMethod::new(#service_name, #method_name));
-            self.inner.client_streaming(req, path, codec).await
+            #inner.client_streaming(req, path, codec).await
         }
     }
 }
@@ -313,6 +751,7 @@ fn generate_streaming<T: Service>(
     emit_package: bool,
     proto_path: &str,
     compile_well_known_types: bool,
+    ref_self: bool,
 ) -> TokenStream {
     let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
     let ident = format_ident!("{}", method.name());
@@ -320,20 +759,25 @@ fn generate_streaming<T: Service>(
     let service_name = format_service_name(service, emit_package);
     let path = format_method_path(service, method, emit_package);
     let method_name = method.identifier();
+    let (receiver, where_clause) = generate_receiver(ref_self);
+    let (inner, clone_inner) = generate_inner(ref_self);
 
     quote! {
         pub async fn #ident(
-            &mut self,
+            #receiver,
             request: impl tonic::IntoStreamingRequest<Message = #request>
-        ) -> std::result::Result<tonic::Response<tonic::codec::Streaming<#response>>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
+        ) -> std::result::Result<tonic::Response<tonic::codec::Streaming<#response>>, tonic::Status>
+        #where_clause
+        {
+            #clone_inner
+            #inner.ready().await.map_err(|e| {
                         tonic::Status::new(tonic::Code::Unknown, format!("Service was not ready: {}", e.into()))
             })?;
             let codec = #codec_name::default();
             let path = http::uri::PathAndQuery::from_static(#path);
             let mut req = request.into_streaming_request();
             req.extensions_mut().insert(GrpcMethod::new(#service_name,#method_name));
-            self.inner.streaming(req, path, codec).await
+            #inner.streaming(req, path, codec).await
         }
     }
 }
@@ -14,6 +14,14 @@ pub struct CodeGenBuilder {
     disable_comments: HashSet<String>,
     use_arc_self: bool,
     generate_default_stubs: bool,
+    local_server: bool,
+    server_error_type: Option<String>,
+    request_validator: Option<String>,
+    message_interceptors: bool,
+    blocking_client: bool,
+    client_ref_self: bool,
+    client_trait: bool,
+    client_sinks: bool,
 }
 
 impl CodeGenBuilder {
@@ -71,6 +79,63 @@ impl CodeGenBuilder {
         self
     }
 
+    /// Generate servers whose trait, and the futures of its methods, aren't
+    /// `Send`, for serving with `tonic::transport::server::LocalRoutes`.
+    pub fn local_server(&mut self, enable: bool) -> &mut Self {
+        self.local_server = enable;
+        self
+    }
+
+    /// The path of the error type of the handlers of servers, instead of
+    /// `tonic::Status`, which must implement `Into<tonic::Status>`.
+    pub fn server_error_type(&mut self, error_type: Option<String>) -> &mut Self {
+        self.server_error_type = error_type;
+        self
+    }
+
+    /// The path of a function validating the requests of servers, of the
+    /// signature `fn(&M) -> Result<(), tonic::Status>` for every request
+    /// message `M`.
+    pub fn request_validator(&mut self, validator: Option<String>) -> &mut Self {
+        self.request_validator = validator;
+        self
+    }
+
+    /// Enable generated clients to accept a
+    /// [`MessageInterceptor`](https://docs.rs/tonic/latest/tonic/client/trait.MessageInterceptor.html).
+    pub fn message_interceptors(&mut self, enable: bool) -> &mut Self {
+        self.message_interceptors = enable;
+        self
+    }
+
+    /// Generate a `blocking` module with synchronous clients, whose calls block
+    /// on a runtime of `tonic`'s `blocking` feature.
+    pub fn blocking_client(&mut self, enable: bool) -> &mut Self {
+        self.blocking_client = enable;
+        self
+    }
+
+    /// Emit `&self` instead of `&mut self` in the call methods of clients,
+    /// which clone their inner service for each call.
+    pub fn client_ref_self(&mut self, enable: bool) -> &mut Self {
+        self.client_ref_self = enable;
+        self
+    }
+
+    /// Generate a `{Service}ClientApi` trait of the calls of clients, with an
+    /// impl for the clients, for code that mocks them.
+    pub fn client_trait(&mut self, enable: bool) -> &mut Self {
+        self.client_trait = enable;
+        self
+    }
+
+    /// Generate a `{method}_sink` method for each client-streaming and
+    /// bidirectional method, whose request is fed from a sink.
+    pub fn client_sinks(&mut self, enable: bool) -> &mut Self {
+        self.client_sinks = enable;
+        self
+    }
+
     /// Generate client code based on `Service`.
     ///
     /// This takes some `Service` and will generate a `TokenStream` that contains
@@ -84,6 +149,11 @@ impl CodeGenBuilder {
             self.build_transport,
             &self.attributes,
             &self.disable_comments,
+            self.message_interceptors,
+            self.blocking_client,
+            self.client_ref_self,
+            self.client_trait,
+            self.client_sinks,
         )
     }
 
@@ -101,6 +171,9 @@ impl CodeGenBuilder {
             &self.disable_comments,
             self.use_arc_self,
             self.generate_default_stubs,
+            self.local_server,
+            self.server_error_type.as_deref(),
+            self.request_validator.as_deref(),
         )
     }
 }
@@ -115,6 +188,14 @@ impl Default for CodeGenBuilder {
             disable_comments: HashSet::default(),
             use_arc_self: false,
             generate_default_stubs: false,
+            local_server: false,
+            server_error_type: None,
+            request_validator: None,
+            message_interceptors: false,
+            blocking_client: false,
+            client_ref_self: false,
+            client_trait: false,
+            client_sinks: false,
         }
     }
 }
@@ -0,0 +1,344 @@
+//! Code generation of the `rpc_service`s of [flatbuffers] schemas.
+//!
+//! The generated clients and servers send and receive the buffers of
+//! messages as [`Bytes`](https://docs.rs/bytes), with the
+//! `tonic::codec::FlatbuffersCodec` of the `flatbuffers` feature of `tonic`,
+//! and the types that `flatc --rust` generates read them in place.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     tonic_build::flatbuffers::compile(&["schemas/greeter.fbs"])?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! For a schema such as:
+//!
+//! ```text
+//! namespace helloworld;
+//!
+//! table HelloRequest { name: string; }
+//! table HelloReply { message: string; }
+//!
+//! rpc_service Greeter {
+//!   SayHello(HelloRequest): HelloReply;
+//!   SayHellos(HelloRequest): HelloReply (streaming: "server");
+//! }
+//! ```
+//!
+//! the `helloworld.Greeter.rs` file of `OUT_DIR` has the `greeter_client`
+//! and `greeter_server` modules, with the `/helloworld.Greeter/SayHello`
+//! and `/helloworld.Greeter/SayHellos` methods.
+//!
+//! [flatbuffers]: https://flatbuffers.dev
+
+use crate::backend::{self, Backend, Method, Service};
+
+use std::{fs, io, path::Path};
+
+/// The [`Backend`] of flatbuffers messages, as their buffers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Flatbuffers;
+
+impl Backend for Flatbuffers {
+    fn codec_path(&self) -> &str {
+        "tonic::codec::FlatbuffersCodec"
+    }
+
+    fn message_type(&self, _package: &str, _proto_type: &str, _proto_path: &str) -> String {
+        "tonic::codegen::Bytes".to_owned()
+    }
+}
+
+/// Generate the clients and servers of the `rpc_service`s of `schemas`.
+///
+/// Use [`backend::Builder`] with [`services`] and [`Flatbuffers`] instead if
+/// you need more options.
+pub fn compile(schemas: &[impl AsRef<Path>]) -> io::Result<()> {
+    let builder = backend::Builder::new(Flatbuffers);
+    for schema in schemas {
+        let schema = schema.as_ref();
+        if std::env::var_os("CARGO").is_some() {
+            println!("cargo:rerun-if-changed={}", schema.display());
+        }
+        builder.compile(&services(&fs::read_to_string(schema)?)?)?;
+    }
+    Ok(())
+}
+
+/// The `rpc_service`s of a flatbuffers schema, with the fully qualified names
+/// of their messages.
+pub fn services(schema: &str) -> io::Result<Vec<Service>> {
+    Parser {
+        tokens: tokenize(schema)?,
+        position: 0,
+    }
+    .services()
+    .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// An identifier, with the `.` of namespaced names.
+    Ident(String),
+    String(String),
+    Punct(char),
+    /// A `///` comment.
+    Doc(String),
+}
+
+fn tokenize(schema: &str) -> io::Result<Vec<Token>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut tokens = Vec::new();
+    let mut chars = schema.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => {}
+            '/' if schema[start..].starts_with("//") => {
+                let end = schema[start..]
+                    .find('\n')
+                    .map_or(schema.len(), |i| start + i);
+                if let Some(doc) = schema[start..end].strip_prefix("///") {
+                    let doc = doc.strip_prefix(' ').unwrap_or(doc);
+                    tokens.push(Token::Doc(doc.trim_end().to_owned()));
+                }
+                while chars.next_if(|(i, _)| *i < end).is_some() {}
+            }
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => string.extend(chars.next().map(|(_, c)| c)),
+                        Some((_, c)) => string.push(c),
+                        None => return Err(invalid("a string isn't closed".to_owned())),
+                    }
+                }
+                tokens.push(Token::String(string));
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '.')
+                {
+                    end = i + c.len_utf8();
+                }
+                tokens.push(Token::Ident(schema[start..end].to_owned()));
+            }
+            _ => tokens.push(Token::Punct(c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            other => Err(format!("expected {}, found {:?}", what, other)),
+        }
+    }
+
+    fn punct(&mut self, punct: char) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Punct(c)) if c == punct => Ok(()),
+            other => Err(format!("expected `{}`, found {:?}", punct, other)),
+        }
+    }
+
+    fn services(mut self) -> Result<Vec<Service>, String> {
+        let mut services = Vec::new();
+        let mut namespace = String::new();
+        let mut comments = Vec::new();
+        let mut depth = 0usize;
+
+        while let Some(token) = self.next() {
+            match token {
+                Token::Doc(doc) => {
+                    comments.push(doc);
+                    continue;
+                }
+                Token::Punct('{') => depth += 1,
+                Token::Punct('}') => depth = depth.saturating_sub(1),
+                Token::Ident(ident) if depth == 0 && ident == "namespace" => {
+                    namespace = self.ident("a namespace")?;
+                    self.punct(';')?;
+                }
+                Token::Ident(ident) if depth == 0 && ident == "rpc_service" => {
+                    let mut service = self.service(&namespace)?;
+                    service.comments = std::mem::take(&mut comments);
+                    services.push(service);
+                }
+                _ => {}
+            }
+            comments.clear();
+        }
+
+        Ok(services)
+    }
+
+    fn service(&mut self, namespace: &str) -> Result<Service, String> {
+        let proto_name = self.ident("the name of a service")?;
+        self.punct('{')?;
+
+        let qualify = |name: String| match namespace {
+            _ if name.contains('.') => format!(".{}", name),
+            "" => format!(".{}", name),
+            namespace => format!(".{}.{}", namespace, name),
+        };
+
+        let mut methods = Vec::new();
+        let mut comments = Vec::new();
+        loop {
+            let proto_name = match self.next() {
+                Some(Token::Punct('}')) => break,
+                Some(Token::Doc(doc)) => {
+                    comments.push(doc);
+                    continue;
+                }
+                Some(Token::Ident(name)) => name,
+                other => return Err(format!("expected a method, found {:?}", other)),
+            };
+
+            self.punct('(')?;
+            let input_type = qualify(self.ident("a request type")?);
+            self.punct(')')?;
+            self.punct(':')?;
+            let output_type = qualify(self.ident("a response type")?);
+
+            let mut streaming = String::from("none");
+            match self.next() {
+                Some(Token::Punct(';')) => {}
+                Some(Token::Punct('(')) => {
+                    loop {
+                        let key = self.ident("an attribute")?;
+                        let value = match self.next() {
+                            Some(Token::Punct(':')) => match self.next() {
+                                Some(Token::String(value) | Token::Ident(value)) => value,
+                                other => {
+                                    return Err(format!("expected a value, found {:?}", other))
+                                }
+                            },
+                            _ => {
+                                self.position -= 1;
+                                String::new()
+                            }
+                        };
+                        if key == "streaming" {
+                            streaming = value;
+                        }
+                        match self.next() {
+                            Some(Token::Punct(',')) => {}
+                            Some(Token::Punct(')')) => break,
+                            other => return Err(format!("expected `)`, found {:?}", other)),
+                        }
+                    }
+                    self.punct(';')?;
+                }
+                other => return Err(format!("expected `;`, found {:?}", other)),
+            }
+
+            let (client_streaming, server_streaming) = match streaming.as_str() {
+                "none" => (false, false),
+                "client" => (true, false),
+                "server" => (false, true),
+                "bidi" => (true, true),
+                other => {
+                    return Err(format!(
+                        "invalid streaming `{}` of method `{}`",
+                        other, proto_name
+                    ))
+                }
+            };
+
+            methods.push(Method {
+                proto_name,
+                input_type,
+                output_type,
+                client_streaming,
+                server_streaming,
+                comments: std::mem::take(&mut comments),
+            });
+        }
+
+        Ok(Service {
+            proto_name,
+            package: namespace.to_owned(),
+            comments: Vec::new(),
+            methods,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        // The greeting service.
+        namespace helloworld;
+
+        table HelloRequest {
+          name: string (id: 0);
+        }
+
+        table HelloReply { message: string; }
+
+        /// Greets.
+        rpc_service Greeter {
+          /// Greets once.
+          SayHello(HelloRequest): HelloReply;
+          SayHellos(HelloRequest):common.Reply (streaming: "server", idempotent);
+        }
+    "#;
+
+    #[test]
+    fn parses_services() {
+        let services = services(SCHEMA).unwrap();
+        assert_eq!(services.len(), 1);
+        let greeter = &services[0];
+        assert_eq!(greeter.proto_name, "Greeter");
+        assert_eq!(greeter.package, "helloworld");
+        assert_eq!(greeter.comments, ["Greets."]);
+
+        let [say_hello, say_hellos] = &greeter.methods[..] else {
+            panic!("two methods");
+        };
+        assert_eq!(say_hello.proto_name, "SayHello");
+        assert_eq!(say_hello.input_type, ".helloworld.HelloRequest");
+        assert_eq!(say_hello.output_type, ".helloworld.HelloReply");
+        assert_eq!(say_hello.comments, ["Greets once."]);
+        assert!(!say_hello.client_streaming && !say_hello.server_streaming);
+
+        assert_eq!(say_hellos.output_type, ".common.Reply");
+        assert!(!say_hellos.client_streaming && say_hellos.server_streaming);
+    }
+
+    #[test]
+    fn generates_services_of_buffers() {
+        let code = backend::Builder::new(Flatbuffers).generate(&services(SCHEMA).unwrap()[0]);
+        assert!(code.contains("let codec = tonic::codec::FlatbuffersCodec::default();"));
+        assert!(code.contains("request: impl tonic::IntoRequest<tonic::codegen::Bytes>"));
+        assert!(code.contains("\"/helloworld.Greeter/SayHellos\""));
+    }
+
+    #[test]
+    fn rejects_invalid_streaming() {
+        let error = services("rpc_service S { M(A):B (streaming: \"both\"); }").unwrap_err();
+        assert_eq!(error.to_string(), "invalid streaming `both` of method `M`");
+    }
+}
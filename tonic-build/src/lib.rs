@@ -6,6 +6,8 @@
 //! - `cleanup-markdown`: Enables cleaning up documentation from the generated code. Useful
 //! when documentation of the generated code fails `cargo test --doc` for example.
 //! - `prost`: Enables usage of prost generator (enabled by default).
+//! - `protox`: Enables parsing `proto` files in-process with `protox` instead of running
+//! `protoc`, with `Builder::use_protox`.
 //! - `transport`: Enables generation of `connect` method using `tonic::transport::Channel`
 //! (enabled by default).
 //!
@@ -87,6 +89,8 @@ mod prost;
 #[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
 pub use prost::{compile_protos, configure, Builder};
 
+pub mod backend;
+pub mod flatbuffers;
 pub mod manual;
 
 /// Service code generation for client
@@ -122,6 +126,11 @@ pub trait Service {
     fn methods(&self) -> &[Self::Method];
     /// Get comments about this item.
     fn comment(&self) -> &[Self::Comment];
+    /// Options of the service, as `(name, value)` pairs, such as
+    /// `("deprecated", "true")`.
+    fn options(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
 }
 
 /// Method generation trait.
@@ -152,15 +161,22 @@ pub trait Method {
         proto_path: &str,
         compile_well_known_types: bool,
     ) -> (TokenStream, TokenStream);
+    /// Options of the method, as `(name, value)` pairs, such as
+    /// `("idempotency_level", "NO_SIDE_EFFECTS")`.
+    fn options(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
 }
 
-/// Attributes that will be added to `mod` and `struct` items.
+/// Attributes that will be added to `mod`, `struct` and method items.
 #[derive(Debug, Default, Clone)]
 pub struct Attributes {
     /// `mod` attributes.
     module: Vec<(String, String)>,
     /// `struct` attributes.
     structure: Vec<(String, String)>,
+    /// Method attributes, keyed by option.
+    method_option: Vec<(String, String)>,
 }
 
 impl Attributes {
@@ -172,6 +188,31 @@ impl Attributes {
         generate_attributes(name, &self.structure)
     }
 
+    fn for_method<T: Method>(&self, method: &T) -> Vec<syn::Attribute> {
+        let options = method.options();
+        let mut attributes = self
+            .method_option
+            .iter()
+            .filter(|(option, _)| {
+                options
+                    .iter()
+                    .any(|(name, value)| match option.split_once('=') {
+                        Some((option, option_value)) => option == name && option_value == value,
+                        None => option == name,
+                    })
+            })
+            .flat_map(|(_, attr)| parse_attributes(attr))
+            .collect::<Vec<_>>();
+
+        if options
+            .iter()
+            .any(|(name, value)| name == "deprecated" && value == "true")
+        {
+            attributes.push(syn::parse_quote!(#[deprecated]));
+        }
+        attributes
+    }
+
     /// Add an attribute that will be added to `mod` items matching the given pattern.
     ///
     /// # Examples
@@ -197,6 +238,30 @@ impl Attributes {
     pub fn push_struct(&mut self, pattern: impl Into<String>, attr: impl Into<String>) {
         self.structure.push((pattern.into(), attr.into()));
     }
+
+    /// Add an attribute that will be added to the methods with the given option, either
+    /// `name`, for any of its values, or `name=value`.
+    ///
+    /// The `cfg` attributes of a method are also added to the code calling it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic_build::*;
+    /// let mut attributes = Attributes::default();
+    /// attributes.push_method_option("idempotency_level=NO_SIDE_EFFECTS", "#[doc(alias = \"query\")]");
+    /// ```
+    pub fn push_method_option(&mut self, option: impl Into<String>, attr: impl Into<String>) {
+        self.method_option.push((option.into(), attr.into()));
+    }
+}
+
+/// The `cfg` attributes of `attributes`, for the code of an item they're on.
+fn cfg_attributes(attributes: &[syn::Attribute]) -> Vec<&syn::Attribute> {
+    attributes
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .collect()
 }
 
 fn format_service_name<T: Service>(service: &T, emit_package: bool) -> String {
@@ -225,6 +290,102 @@ fn format_method_name<T: Service>(service: &T, method: &T::Method, emit_package:
     )
 }
 
+// Generates the `SERVICE_NAME` of a service and the `methods` module with the paths of its methods.
+fn generate_method_paths<T: Service>(
+    service: &T,
+    emit_package: bool,
+    attributes: &Attributes,
+) -> TokenStream {
+    let service_name = format_service_name(service, emit_package);
+    let mut consts = TokenStream::new();
+    let mut variants = TokenStream::new();
+    let mut from_path = TokenStream::new();
+    let mut to_path = TokenStream::new();
+    let mut to_name = TokenStream::new();
+    let mut all = TokenStream::new();
+
+    for method in service.methods() {
+        let method_attributes = attributes.for_method(method);
+        let cfg_attributes = cfg_attributes(&method_attributes);
+        let path = format_method_path(service, method, emit_package);
+        let path_doc = generate_doc_comment(format!(" `{}`", path));
+        let path_const =
+            quote::format_ident!("{}", naive_snake_case(method.identifier()).to_uppercase());
+        let variant = quote::format_ident!("{}", method.identifier());
+        let name = method.identifier();
+
+        consts.extend(quote::quote! {
+            #path_doc
+            #(#cfg_attributes)*
+            pub const #path_const: &str = #path;
+        });
+        variants.extend(quote::quote! {
+            #path_doc
+            #(#cfg_attributes)*
+            #variant,
+        });
+        from_path.extend(quote::quote! {
+            #(#cfg_attributes)*
+            #path_const => Some(Method::#variant),
+        });
+        to_path.extend(quote::quote! {
+            #(#cfg_attributes)*
+            Method::#variant => #path_const,
+        });
+        to_name.extend(quote::quote! {
+            #(#cfg_attributes)*
+            Method::#variant => #name,
+        });
+        all.extend(quote::quote! {
+            #(#cfg_attributes)*
+            Method::#variant,
+        });
+    }
+
+    quote::quote! {
+        /// The fully-qualified name of the service.
+        pub const SERVICE_NAME: &str = #service_name;
+
+        /// The paths of the methods of the service.
+        pub mod methods {
+            #consts
+
+            /// The methods of the service.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            pub enum Method {
+                #variants
+            }
+
+            impl Method {
+                /// All the methods of the service.
+                pub const ALL: &'static [Method] = &[#all];
+
+                /// The method of the request path `path`, if it's one of the service.
+                pub fn from_path(path: &str) -> Option<Self> {
+                    match path {
+                        #from_path
+                        _ => None,
+                    }
+                }
+
+                /// The request path of the method.
+                pub fn path(self) -> &'static str {
+                    match self {
+                        #to_path
+                    }
+                }
+
+                /// The name of the method in the service.
+                pub fn name(self) -> &'static str {
+                    match self {
+                        #to_name
+                    }
+                }
+            }
+        }
+    }
+}
+
 // Generates attributes given a list of (`pattern`, `attribute`) pairs. If `pattern` matches `name`, `attribute` will be included.
 fn generate_attributes<'a>(
     name: &str,
@@ -233,15 +394,17 @@ fn generate_attributes<'a>(
     attrs
         .into_iter()
         .filter(|(matcher, _)| match_name(matcher, name))
-        .flat_map(|(_, attr)| {
-            // attributes cannot be parsed directly, so we pretend they're on a struct
-            syn::parse_str::<syn::DeriveInput>(&format!("{}\nstruct fake;", attr))
-                .unwrap()
-                .attrs
-        })
+        .flat_map(|(_, attr)| parse_attributes(attr))
         .collect::<Vec<_>>()
 }
 
+fn parse_attributes(attr: &str) -> Vec<syn::Attribute> {
+    // attributes cannot be parsed directly, so we pretend they're on a struct
+    syn::parse_str::<syn::DeriveInput>(&format!("{}\nstruct fake;", attr))
+        .unwrap()
+        .attrs
+}
+
 // Generate a singular line of a doc comment
 fn generate_doc_comment<S: AsRef<str>>(comment: S) -> TokenStream {
     let comment = comment.as_ref();
@@ -355,4 +518,29 @@ mod tests {
             assert_eq!(naive_snake_case(case.0), case.1)
         }
     }
+
+    #[test]
+    fn test_method_option_attributes() {
+        let method = manual::Method::builder()
+            .name("get")
+            .route_name("Get")
+            .input_type("Input")
+            .output_type("Output")
+            .codec_path("Codec")
+            .option("idempotency_level", "NO_SIDE_EFFECTS")
+            .option("deprecated", "true")
+            .build();
+
+        let mut attributes = Attributes::default();
+        attributes.push_method_option("idempotency_level=NO_SIDE_EFFECTS", "#[cfg(query)]");
+        attributes.push_method_option("idempotency_level=IDEMPOTENT", "#[cfg(idempotent)]");
+        attributes.push_method_option("idempotency_level", "#[cfg(any_level)]");
+
+        let attributes = attributes.for_method(&method);
+        let attributes = quote::quote!(#(#attributes)*).to_string();
+        assert_eq!(
+            attributes,
+            "# [cfg (query)] # [cfg (any_level)] # [deprecated]"
+        );
+    }
 }
@@ -28,7 +28,7 @@
 //! }
 //! ```
 
-use crate::code_gen::CodeGenBuilder;
+use crate::{code_gen::CodeGenBuilder, Attributes};
 
 use proc_macro2::TokenStream;
 use quote::ToTokens;
@@ -63,6 +63,8 @@ pub struct ServiceBuilder {
     comments: Vec<String>,
     /// The service methods.
     methods: Vec<Method>,
+    /// The service options.
+    options: Vec<(String, String)>,
 }
 
 impl ServiceBuilder {
@@ -97,6 +99,13 @@ impl ServiceBuilder {
         self
     }
 
+    /// Set an option of this Service, such as `deprecated`.
+    pub fn option(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.options
+            .push((name.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
     /// Build a Service.
     ///
     /// Panics if `name` or `package` weren't set.
@@ -106,6 +115,7 @@ impl ServiceBuilder {
             comments: self.comments,
             package: self.package.unwrap(),
             methods: self.methods,
+            options: self.options,
         }
     }
 }
@@ -121,6 +131,8 @@ pub struct Service {
     comments: Vec<String>,
     /// The service methods.
     methods: Vec<Method>,
+    /// The service options.
+    options: Vec<(String, String)>,
 }
 
 impl Service {
@@ -154,6 +166,10 @@ impl crate::Service for Service {
     fn comment(&self) -> &[Self::Comment] {
         &self.comments
     }
+
+    fn options(&self) -> Vec<(String, String)> {
+        self.options.clone()
+    }
 }
 
 /// A service method descriptor.
@@ -175,6 +191,8 @@ pub struct Method {
     server_streaming: bool,
     /// The path to the codec to use for this method
     codec_path: String,
+    /// The method options.
+    options: Vec<(String, String)>,
 }
 
 impl Method {
@@ -224,6 +242,10 @@ impl crate::Method for Method {
             .to_token_stream();
         (request, response)
     }
+
+    fn options(&self) -> Vec<(String, String)> {
+        self.options.clone()
+    }
 }
 
 /// Method builder.
@@ -264,6 +286,8 @@ pub struct MethodBuilder {
     server_streaming: bool,
     /// The path to the codec to use for this method
     codec_path: Option<String>,
+    /// The method options.
+    options: Vec<(String, String)>,
 }
 
 impl MethodBuilder {
@@ -326,6 +350,14 @@ impl MethodBuilder {
         self
     }
 
+    /// Set an option of this Method, such as `deprecated` or `idempotency_level`, or one of
+    /// your own, which attributes may be keyed by.
+    pub fn option(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.options
+            .push((name.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
     /// Build a Method
     ///
     /// Panics if `name`, `route_name`, `input_type`, `output_type`, or `codec_path` weren't set.
@@ -339,6 +371,7 @@ impl MethodBuilder {
             client_streaming: self.client_streaming,
             server_streaming: self.server_streaming,
             codec_path: self.codec_path.unwrap(),
+            options: self.options,
         }
     }
 }
@@ -355,6 +388,7 @@ impl ServiceGenerator {
             let server = CodeGenBuilder::new()
                 .emit_package(true)
                 .compile_well_known_types(false)
+                .attributes(self.builder.server_attributes.clone())
                 .generate_server(service, "");
 
             self.servers.extend(server);
@@ -364,7 +398,13 @@ impl ServiceGenerator {
             let client = CodeGenBuilder::new()
                 .emit_package(true)
                 .compile_well_known_types(false)
+                .attributes(self.builder.client_attributes.clone())
                 .build_transport(self.builder.build_transport)
+                .message_interceptors(self.builder.message_interceptors)
+                .blocking_client(self.builder.blocking_client)
+                .client_ref_self(self.builder.client_ref_self)
+                .client_trait(self.builder.client_trait)
+                .client_sinks(self.builder.client_sinks)
                 .generate_client(service, "");
 
             self.clients.extend(client);
@@ -408,6 +448,13 @@ pub struct Builder {
     build_server: bool,
     build_client: bool,
     build_transport: bool,
+    message_interceptors: bool,
+    blocking_client: bool,
+    client_ref_self: bool,
+    client_trait: bool,
+    client_sinks: bool,
+    server_attributes: Attributes,
+    client_attributes: Attributes,
 
     out_dir: Option<PathBuf>,
 }
@@ -418,6 +465,13 @@ impl Default for Builder {
             build_server: true,
             build_client: true,
             build_transport: true,
+            message_interceptors: false,
+            blocking_client: false,
+            client_ref_self: false,
+            client_trait: false,
+            client_sinks: false,
+            server_attributes: Attributes::default(),
+            client_attributes: Attributes::default(),
             out_dir: None,
         }
     }
@@ -454,6 +508,75 @@ impl Builder {
         self
     }
 
+    /// Enable or disable generated clients to have a `with_message_interceptor` method, setting a
+    /// `tonic::client::MessageInterceptor`.
+    ///
+    /// Defaults to `false`.
+    pub fn client_message_interceptors(mut self, enable: bool) -> Self {
+        self.message_interceptors = enable;
+        self
+    }
+
+    /// Enable or disable generating a `blocking` module next to each client, with synchronous
+    /// clients. This requires `tonic`'s `blocking` feature.
+    ///
+    /// Defaults to `false`.
+    pub fn blocking_client(mut self, enable: bool) -> Self {
+        self.blocking_client = enable;
+        self
+    }
+
+    /// Enable or disable generated clients taking `&self` instead of `&mut self` in their call
+    /// methods, cloning their inner service for each call.
+    ///
+    /// Defaults to `false`.
+    pub fn client_ref_self(mut self, enable: bool) -> Self {
+        self.client_ref_self = enable;
+        self
+    }
+
+    /// Enable or disable generating a `{Service}ClientApi` trait of the calls of each client,
+    /// with an impl for the client, for code that mocks them.
+    ///
+    /// Defaults to `false`.
+    pub fn client_trait(mut self, enable: bool) -> Self {
+        self.client_trait = enable;
+        self
+    }
+
+    /// Enable or disable generating a `{method}_sink` method for each client-streaming and
+    /// bidirectional method of clients, whose request is fed from a sink.
+    ///
+    /// Defaults to `false`.
+    pub fn client_sinks(mut self, enable: bool) -> Self {
+        self.client_sinks = enable;
+        self
+    }
+
+    /// Add additional attribute to the server trait methods with the option `option`, either
+    /// its name or `name=value`.
+    pub fn server_method_option_attribute(
+        mut self,
+        option: impl AsRef<str>,
+        attribute: impl AsRef<str>,
+    ) -> Self {
+        self.server_attributes
+            .push_method_option(option.as_ref(), attribute.as_ref());
+        self
+    }
+
+    /// Add additional attribute to the client methods with the option `option`, either its
+    /// name or `name=value`.
+    pub fn client_method_option_attribute(
+        mut self,
+        option: impl AsRef<str>,
+        attribute: impl AsRef<str>,
+    ) -> Self {
+        self.client_attributes
+            .push_method_option(option.as_ref(), attribute.as_ref());
+        self
+    }
+
     /// Set the output directory to generate code to.
     ///
     /// Defaults to the `OUT_DIR` environment variable.
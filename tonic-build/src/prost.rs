@@ -1,13 +1,15 @@
-use crate::{code_gen::CodeGenBuilder, compile_settings::CompileSettings};
+use crate::{code_gen::CodeGenBuilder, compile_settings::CompileSettings, match_name};
 
 use super::Attributes;
 use proc_macro2::TokenStream;
+use prost::Message as _;
 use prost_build::{Config, Method, Service};
+use prost_types::FileDescriptorSet;
 use quote::ToTokens;
 use std::{
     collections::HashSet,
     ffi::OsString,
-    io,
+    fs, io,
     path::{Path, PathBuf},
 };
 
@@ -20,7 +22,10 @@ pub fn configure() -> Builder {
         build_server: true,
         build_transport: true,
         file_descriptor_set_path: None,
+        embed_file_descriptor_set: false,
         skip_protoc_run: false,
+        #[cfg(feature = "protox")]
+        use_protox: false,
         out_dir: None,
         extern_path: Vec::new(),
         field_attributes: Vec::new(),
@@ -39,8 +44,18 @@ pub fn configure() -> Builder {
         include_file: None,
         emit_rerun_if_changed: std::env::var_os("CARGO").is_some(),
         disable_comments: HashSet::default(),
+        include_services: None,
+        exclude_methods: Vec::new(),
         use_arc_self: false,
         generate_default_stubs: false,
+        local_server: false,
+        server_error_type: None,
+        request_validator: None,
+        client_message_interceptors: false,
+        blocking_client: false,
+        client_ref_self: false,
+        client_trait: false,
+        client_sinks: false,
         compile_settings: CompileSettings::default(),
     }
 }
@@ -118,6 +133,14 @@ impl crate::Service for TonicBuildService {
     fn methods(&self) -> &[Self::Method] {
         &self.methods
     }
+
+    fn options(&self) -> Vec<(String, String)> {
+        let mut options = Vec::new();
+        if let Some(deprecated) = self.prost_service.options.deprecated {
+            options.push(("deprecated".to_string(), deprecated.to_string()));
+        }
+        options
+    }
 }
 
 impl crate::Method for TonicBuildMethod {
@@ -188,6 +211,22 @@ impl crate::Method for TonicBuildMethod {
         );
         (request, response)
     }
+
+    // `prost` doesn't keep the values of custom options.
+    fn options(&self) -> Vec<(String, String)> {
+        let prost_options = &self.prost_method.options;
+        let mut options = Vec::new();
+        if let Some(deprecated) = prost_options.deprecated {
+            options.push(("deprecated".to_string(), deprecated.to_string()));
+        }
+        if prost_options.idempotency_level.is_some() {
+            options.push((
+                "idempotency_level".to_string(),
+                prost_options.idempotency_level().as_str_name().to_string(),
+            ));
+        }
+        options
+    }
 }
 
 fn is_google_type(ty: &str) -> bool {
@@ -211,7 +250,29 @@ impl ServiceGenerator {
 }
 
 impl prost_build::ServiceGenerator for ServiceGenerator {
-    fn generate(&mut self, service: prost_build::Service, _buf: &mut String) {
+    fn generate(&mut self, mut service: prost_build::Service, _buf: &mut String) {
+        let service_path = if service.package.is_empty() {
+            format!(".{}", service.proto_name)
+        } else {
+            format!(".{}.{}", service.package, service.proto_name)
+        };
+        if let Some(ref patterns) = self.builder.include_services {
+            if !patterns
+                .iter()
+                .any(|pattern| match_name(pattern, &service_path))
+            {
+                return;
+            }
+        }
+        service.methods.retain(|method| {
+            let method_path = format!("{}.{}", service_path, method.proto_name);
+            !self
+                .builder
+                .exclude_methods
+                .iter()
+                .any(|pattern| match_name(pattern, &method_path))
+        });
+
         if self.builder.build_server {
             let server = CodeGenBuilder::new()
                 .emit_package(self.builder.emit_package)
@@ -220,6 +281,9 @@ impl prost_build::ServiceGenerator for ServiceGenerator {
                 .disable_comments(self.builder.disable_comments.clone())
                 .use_arc_self(self.builder.use_arc_self)
                 .generate_default_stubs(self.builder.generate_default_stubs)
+                .local_server(self.builder.local_server)
+                .server_error_type(self.builder.server_error_type.clone())
+                .request_validator(self.builder.request_validator.clone())
                 .generate_server(
                     &TonicBuildService::new(service.clone(), self.builder.compile_settings.clone()),
                     &self.builder.proto_path,
@@ -235,6 +299,11 @@ impl prost_build::ServiceGenerator for ServiceGenerator {
                 .attributes(self.builder.client_attributes.clone())
                 .disable_comments(self.builder.disable_comments.clone())
                 .build_transport(self.builder.build_transport)
+                .message_interceptors(self.builder.client_message_interceptors)
+                .blocking_client(self.builder.blocking_client)
+                .client_ref_self(self.builder.client_ref_self)
+                .client_trait(self.builder.client_trait)
+                .client_sinks(self.builder.client_sinks)
                 .generate_client(
                     &TonicBuildService::new(service, self.builder.compile_settings.clone()),
                     &self.builder.proto_path,
@@ -273,6 +342,36 @@ impl prost_build::ServiceGenerator for ServiceGenerator {
             self.servers = TokenStream::default();
         }
     }
+
+    fn finalize_package(&mut self, _package: &str, buf: &mut String) {
+        if !self.builder.embed_file_descriptor_set {
+            return;
+        }
+        let Some(path) = self.builder.descriptor_set_path() else {
+            return;
+        };
+
+        // Generated files include the set relative to themselves, if it's beside them.
+        let out_dir = self
+            .builder
+            .out_dir
+            .clone()
+            .or_else(|| std::env::var_os("OUT_DIR").map(PathBuf::from));
+        let path = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) if Some(parent) == out_dir.as_deref() => PathBuf::from(name),
+            _ => fs::canonicalize(&path).unwrap_or(path),
+        };
+        let path = path
+            .to_str()
+            .expect("file descriptor set path is not UTF-8");
+
+        let descriptor_set = quote::quote! {
+            /// The encoded `FileDescriptorSet` of the compiled .proto files.
+            pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!(#path);
+        };
+        let ast: syn::File = syn::parse2(descriptor_set).expect("not a valid tokenstream");
+        buf.push_str(&prettyplease::unparse(&ast));
+    }
 }
 
 /// Service generator builder.
@@ -282,7 +381,10 @@ pub struct Builder {
     pub(crate) build_server: bool,
     pub(crate) build_transport: bool,
     pub(crate) file_descriptor_set_path: Option<PathBuf>,
+    pub(crate) embed_file_descriptor_set: bool,
     pub(crate) skip_protoc_run: bool,
+    #[cfg(feature = "protox")]
+    pub(crate) use_protox: bool,
     pub(crate) extern_path: Vec<(String, String)>,
     pub(crate) field_attributes: Vec<(String, String)>,
     pub(crate) type_attributes: Vec<(String, String)>,
@@ -300,8 +402,18 @@ pub struct Builder {
     pub(crate) include_file: Option<PathBuf>,
     pub(crate) emit_rerun_if_changed: bool,
     pub(crate) disable_comments: HashSet<String>,
+    pub(crate) include_services: Option<Vec<String>>,
+    pub(crate) exclude_methods: Vec<String>,
     pub(crate) use_arc_self: bool,
     pub(crate) generate_default_stubs: bool,
+    pub(crate) local_server: bool,
+    pub(crate) server_error_type: Option<String>,
+    pub(crate) request_validator: Option<String>,
+    pub(crate) client_message_interceptors: bool,
+    pub(crate) blocking_client: bool,
+    pub(crate) client_ref_self: bool,
+    pub(crate) client_trait: bool,
+    pub(crate) client_sinks: bool,
     pub(crate) compile_settings: CompileSettings,
 
     out_dir: Option<PathBuf>,
@@ -336,6 +448,25 @@ impl Builder {
         self
     }
 
+    /// Enable or disable emitting a `FILE_DESCRIPTOR_SET` constant in the module of each package
+    /// with services, holding the encoded `prost_types::FileDescriptorSet` of the compiled
+    /// .proto files, which registers their services with `tonic-reflection` in one call:
+    ///
+    /// ```rust,ignore
+    /// let reflection = tonic_reflection::server::Builder::configure()
+    ///     .register_all(&[proto::FILE_DESCRIPTOR_SET])
+    ///     .build()?;
+    /// ```
+    ///
+    /// The set is written to [`file_descriptor_set_path`](Self::file_descriptor_set_path),
+    /// or `file_descriptor_set.bin` in the output directory if unset.
+    ///
+    /// This defaults to `false`.
+    pub fn embed_file_descriptor_set(mut self, enable: bool) -> Self {
+        self.embed_file_descriptor_set = enable;
+        self
+    }
+
     /// In combination with with file_descriptor_set_path, this can be used to provide a file
     /// descriptor set as an input file, rather than having prost-build generate the file by
     /// calling protoc.
@@ -344,6 +475,20 @@ impl Builder {
         self
     }
 
+    /// Enable or disable parsing the .proto files in-process with [`protox`] instead of running
+    /// `protoc`, for build environments without it. The `protoc` options of the builder, such as
+    /// [`protoc_arg`](Self::protoc_arg), don't apply.
+    ///
+    /// This defaults to `false`.
+    ///
+    /// [`protox`]: https://docs.rs/protox
+    #[cfg(feature = "protox")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "protox")))]
+    pub fn use_protox(mut self, enable: bool) -> Self {
+        self.use_protox = enable;
+        self
+    }
+
     /// Set the output directory to generate code to.
     ///
     /// Defaults to the `OUT_DIR` environment variable.
@@ -489,6 +634,39 @@ impl Builder {
         self
     }
 
+    /// Add additional attribute to the server trait methods whose proto option matches `option`,
+    /// either its name, such as `deprecated`, or `name=value`, such as
+    /// `idempotency_level=NO_SIDE_EFFECTS`. Their `cfg` attributes are added to their routes
+    /// too.
+    ///
+    /// Only the `deprecated` and `idempotency_level` options are supported, as `prost` doesn't
+    /// keep custom ones.
+    pub fn server_method_option_attribute<O: AsRef<str>, A: AsRef<str>>(
+        mut self,
+        option: O,
+        attribute: A,
+    ) -> Self {
+        self.server_attributes
+            .push_method_option(option.as_ref().to_string(), attribute.as_ref().to_string());
+        self
+    }
+
+    /// Add additional attribute to the client methods whose proto option matches `option`,
+    /// either its name, such as `deprecated`, or `name=value`, such as
+    /// `idempotency_level=NO_SIDE_EFFECTS`.
+    ///
+    /// Methods and services with `option deprecated = true` are `#[deprecated]` in clients
+    /// regardless.
+    pub fn client_method_option_attribute<O: AsRef<str>, A: AsRef<str>>(
+        mut self,
+        option: O,
+        attribute: A,
+    ) -> Self {
+        self.client_attributes
+            .push_method_option(option.as_ref().to_string(), attribute.as_ref().to_string());
+        self
+    }
+
     /// Set the path to where tonic will search for the Request/Response proto structs
     /// live relative to the module where you call `include_proto!`.
     ///
@@ -512,6 +690,41 @@ impl Builder {
         self
     }
 
+    /// Generate only the clients and servers of the services matching one of `paths`, such as
+    /// `.my.package` for the services of a package or `.my.package.Greeter` for a single one.
+    ///
+    /// Paths are matched like those of [`type_attribute`](Self::type_attribute). Calls add to the
+    /// paths of earlier calls. The messages of the compiled .proto files are generated
+    /// regardless, as they're compiled by `prost-build`.
+    ///
+    /// This defaults to all the services.
+    pub fn include_services<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.include_services
+            .get_or_insert_with(Vec::new)
+            .extend(paths.into_iter().map(|path| path.as_ref().to_string()));
+        self
+    }
+
+    /// Leave the methods matching one of `paths`, such as `.my.package.Greeter.SayHello`, out of
+    /// generated clients and servers.
+    ///
+    /// Paths are matched like those of [`type_attribute`](Self::type_attribute), so `SayHello`
+    /// matches the methods of that name in all services. Servers answer the calls of excluded
+    /// methods with `Unimplemented`.
+    pub fn exclude_methods<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.exclude_methods
+            .extend(paths.into_iter().map(|path| path.as_ref().to_string()));
+        self
+    }
+
     /// Emit `Arc<Self>` receiver type in server traits instead of `&self`.
     pub fn use_arc_self(mut self, enable: bool) -> Self {
         self.use_arc_self = enable;
@@ -575,10 +788,105 @@ impl Builder {
         self
     }
 
+    /// Enable or disable generating servers whose trait isn't `Send` or `Sync`, and whose methods
+    /// return futures that aren't `Send`, so their handlers may hold `Rc` or `RefCell` state. These
+    /// servers run on a `tokio::task::LocalSet` with `tonic::transport::server::LocalRoutes`.
+    ///
+    /// This defaults to `false`.
+    pub fn local_server(mut self, enable: bool) -> Self {
+        self.local_server = enable;
+        self
+    }
+
+    /// Set the error type of the methods of generated server traits, such as an enum of domain
+    /// errors, instead of `tonic::Status`. The type must implement `Into<tonic::Status>`, which
+    /// converts the errors of handlers into the status of their calls, and, with
+    /// [`generate_default_stubs`](Self::generate_default_stubs), `From<tonic::Status>`. The items
+    /// of streamed responses are still `Result<_, tonic::Status>`.
+    ///
+    /// This defaults to `tonic::Status`.
+    pub fn server_error_type(mut self, error_type: impl Into<String>) -> Self {
+        self.server_error_type = Some(error_type.into());
+        self
+    }
+
+    /// Set the path of a function validating every request message decoded by generated servers,
+    /// generic over the messages, such as `crate::validate` for
+    /// `fn validate<M: Validate>(message: &M) -> Result<(), tonic::Status>`, which may check the
+    /// `buf.validate` rules of the messages with a crate like `prost-validate`. Invalid requests
+    /// fail with the status it returns, before their handler is called for unary and
+    /// server-streaming methods, and as the handler receives them for streaming requests.
+    ///
+    /// Requests aren't validated by default.
+    pub fn request_validator(mut self, validator: impl Into<String>) -> Self {
+        self.request_validator = Some(validator.into());
+        self
+    }
+
+    /// Enable or disable generated clients to have a `with_message_interceptor` method, setting a
+    /// `tonic::client::MessageInterceptor` that sees the messages of calls before they are
+    /// encoded and once they are decoded.
+    ///
+    /// This defaults to `false`.
+    pub fn client_message_interceptors(mut self, enable: bool) -> Self {
+        self.client_message_interceptors = enable;
+        self
+    }
+
+    /// Enable or disable generating a `blocking` module next to each client, with a synchronous
+    /// client of the same name whose calls block on a `tonic::client::blocking::Runtime`, for
+    /// command line tools or FFI layers without a runtime of their own. Streaming responses are
+    /// iterated. This requires `tonic`'s `blocking` feature.
+    ///
+    /// This defaults to `false`.
+    pub fn blocking_client(mut self, enable: bool) -> Self {
+        self.blocking_client = enable;
+        self
+    }
+
+    /// Enable or disable generated clients taking `&self` instead of `&mut self` in their call
+    /// methods, so they may be shared without a mutex or a clone at each call site. Each call
+    /// clones the inner service, which is cheap for a `tonic::transport::Channel`, and these
+    /// methods are only available when it's `Clone`.
+    ///
+    /// This defaults to `false`.
+    pub fn client_ref_self(mut self, enable: bool) -> Self {
+        self.client_ref_self = enable;
+        self
+    }
+
+    /// Enable or disable generating a `{Service}ClientApi` trait next to each client, with async
+    /// methods mirroring its calls and an impl for the client, so application code may depend on
+    /// the trait and tests may mock it, e.g. with `mockall::mock!`. The methods take a
+    /// `tonic::Request` of the message, or of a `tonic::codegen::RequestStream` of messages, and
+    /// return a `tonic::codegen::BoxStream` of streamed responses, which mocks can create.
+    ///
+    /// This defaults to `false`.
+    pub fn client_trait(mut self, enable: bool) -> Self {
+        self.client_trait = enable;
+        self
+    }
+
+    /// Enable or disable generating a `{method}_sink` method for each client-streaming and
+    /// bidirectional method of clients, returning a `tonic::client::sink::RequestSink` the
+    /// messages of the request are sent to, and the future of the response, for requests fed
+    /// from imperative code rather than a stream. Requires the `sink` feature of `tonic`.
+    ///
+    /// This defaults to `false`.
+    pub fn client_sinks(mut self, enable: bool) -> Self {
+        self.client_sinks = enable;
+        self
+    }
+
     /// Override the default codec.
     ///
     /// If set, writes `{codec_path}::default()` in generated code wherever a codec is created.
     ///
+    /// With the `json` feature of `tonic`, `"tonic::codec::JsonCodec"` makes
+    /// `application/grpc+json` calls, and servers generated with
+    /// `"tonic::codec::ProstJsonCodec"` serve both JSON and protobuf calls,
+    /// given messages deriving the `serde` traits, such as with `pbjson-build`.
+    ///
     /// This defaults to `"tonic::codec::ProstCodec"`
     pub fn codec_path(mut self, codec_path: impl Into<String>) -> Self {
         self.compile_settings.codec_path = codec_path.into();
@@ -602,10 +910,84 @@ impl Builder {
         protos: &[impl AsRef<Path>],
         includes: &[impl AsRef<Path>],
     ) -> io::Result<()> {
+        if self.emit_rerun_if_changed {
+            for path in protos.iter() {
+                println!("cargo:rerun-if-changed={}", path.as_ref().display())
+            }
+
+            for path in includes.iter() {
+                // Cargo will watch the **entire** directory recursively. If we
+                // could figure out which files are imported by our protos we
+                // could specify only those files instead.
+                println!("cargo:rerun-if-changed={}", path.as_ref().display())
+            }
+        }
+
+        #[cfg(feature = "protox")]
+        if self.use_protox {
+            let fds = protox::compile(protos, includes)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+            return self.compile_fds_with_config(config, fds);
+        }
+
+        self.setup_config(&mut config);
+
+        config.service_generator(self.service_generator());
+
+        config.compile_protos(protos, includes)?;
+
+        Ok(())
+    }
+
+    /// Execute code generation for already parsed .proto files, without
+    /// running `protoc`.
+    ///
+    /// The files may be parsed in-process by [`protox`], for build
+    /// environments without `protoc`, as [`compile`](Self::compile) does with
+    /// `use_protox` and the `protox` feature:
+    ///
+    /// ```rust,ignore
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let fds = protox::compile(["proto/service.proto"], ["proto"])?;
+    ///     tonic_build::configure().compile_fds(fds)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// The `protoc` options of the builder, such as [`protoc_arg`](Self::protoc_arg),
+    /// don't apply, and `cargo:rerun-if-changed` lines aren't emitted, which the
+    /// caller may print for the files it parsed.
+    ///
+    /// [`protox`]: https://docs.rs/protox
+    pub fn compile_fds(self, fds: FileDescriptorSet) -> io::Result<()> {
+        self.compile_fds_with_config(Config::new(), fds)
+    }
+
+    /// Execute code generation for already parsed .proto files using a
+    /// custom `prost_build::Config`.
+    pub fn compile_fds_with_config(
+        self,
+        mut config: Config,
+        fds: FileDescriptorSet,
+    ) -> io::Result<()> {
+        self.setup_config(&mut config);
+
+        if let Some(path) = self.descriptor_set_path() {
+            fs::write(path, fds.encode_to_vec())?;
+        }
+
+        config.service_generator(self.service_generator());
+
+        config.compile_fds(fds)?;
+
+        Ok(())
+    }
+
+    fn setup_config(&self, config: &mut Config) {
         if let Some(out_dir) = self.out_dir.as_ref() {
             config.out_dir(out_dir);
         }
-        if let Some(path) = self.file_descriptor_set_path.as_ref() {
+        if let Some(path) = self.descriptor_set_path() {
             config.file_descriptor_set_path(path);
         }
         if self.skip_protoc_run {
@@ -645,25 +1027,19 @@ impl Builder {
         for arg in self.protoc_args.iter() {
             config.protoc_arg(arg);
         }
+    }
 
-        if self.emit_rerun_if_changed {
-            for path in protos.iter() {
-                println!("cargo:rerun-if-changed={}", path.as_ref().display())
-            }
-
-            for path in includes.iter() {
-                // Cargo will watch the **entire** directory recursively. If we
-                // could figure out which files are imported by our protos we
-                // could specify only those files instead.
-                println!("cargo:rerun-if-changed={}", path.as_ref().display())
-            }
+    /// The path of the file descriptor set, defaulted in the output directory if it's embedded.
+    fn descriptor_set_path(&self) -> Option<PathBuf> {
+        match &self.file_descriptor_set_path {
+            Some(path) => Some(path.clone()),
+            None if self.embed_file_descriptor_set => self
+                .out_dir
+                .clone()
+                .or_else(|| std::env::var_os("OUT_DIR").map(PathBuf::from))
+                .map(|out_dir| out_dir.join("file_descriptor_set.bin")),
+            None => None,
         }
-
-        config.service_generator(self.service_generator());
-
-        config.compile_protos(protos, includes)?;
-
-        Ok(())
     }
 
     /// Turn the builder into a `ServiceGenerator` ready to be passed to `prost-build`s
@@ -672,3 +1048,145 @@ impl Builder {
         Box::new(ServiceGenerator::new(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_types::{
+        DescriptorProto, FileDescriptorProto, MethodDescriptorProto, ServiceDescriptorProto,
+    };
+
+    fn test_fds() -> FileDescriptorSet {
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("test.proto".to_string()),
+                package: Some("test".to_string()),
+                syntax: Some("proto3".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Message".to_string()),
+                    ..Default::default()
+                }],
+                service: vec![ServiceDescriptorProto {
+                    name: Some("Test".to_string()),
+                    method: vec![MethodDescriptorProto {
+                        name: Some("Call".to_string()),
+                        input_type: Some(".test.Message".to_string()),
+                        output_type: Some(".test.Message".to_string()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn compiles_fds_without_protoc() {
+        let out_dir = std::env::temp_dir().join(format!("tonic-build-fds-{}", std::process::id()));
+        fs::create_dir_all(&out_dir).unwrap();
+
+        configure()
+            .out_dir(&out_dir)
+            .compile_fds(test_fds())
+            .unwrap();
+
+        let generated = fs::read_to_string(out_dir.join("test.rs")).unwrap();
+        fs::remove_dir_all(&out_dir).unwrap();
+        assert!(generated.contains("pub struct Message"));
+        assert!(generated.contains("pub mod test_client"));
+        assert!(generated.contains("pub mod test_server"));
+    }
+
+    #[test]
+    fn embeds_file_descriptor_set() {
+        let out_dir =
+            std::env::temp_dir().join(format!("tonic-build-embed-{}", std::process::id()));
+        fs::create_dir_all(&out_dir).unwrap();
+
+        configure()
+            .out_dir(&out_dir)
+            .embed_file_descriptor_set(true)
+            .compile_fds(test_fds())
+            .unwrap();
+
+        let generated = fs::read_to_string(out_dir.join("test.rs")).unwrap();
+        let encoded = fs::read(out_dir.join("file_descriptor_set.bin")).unwrap();
+        fs::remove_dir_all(&out_dir).unwrap();
+        assert!(generated.contains(
+            "pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!(\"file_descriptor_set.bin\");"
+        ));
+        assert_eq!(FileDescriptorSet::decode(&*encoded).unwrap(), test_fds());
+    }
+
+    #[test]
+    fn filters_services_and_methods() {
+        let out_dir =
+            std::env::temp_dir().join(format!("tonic-build-filter-{}", std::process::id()));
+        fs::create_dir_all(&out_dir).unwrap();
+
+        let service = |name: &str, methods: &[&str]| ServiceDescriptorProto {
+            name: Some(name.to_string()),
+            method: methods
+                .iter()
+                .map(|method| MethodDescriptorProto {
+                    name: Some(method.to_string()),
+                    input_type: Some(".test.Message".to_string()),
+                    output_type: Some(".test.Message".to_string()),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut fds = test_fds();
+        fds.file[0].service = vec![
+            service("Included", &["Kept", "Excluded"]),
+            service("Other", &["Kept"]),
+        ];
+        configure()
+            .out_dir(&out_dir)
+            .include_services([".test.Included"])
+            .exclude_methods(["Included.Excluded"])
+            .compile_fds(fds)
+            .unwrap();
+
+        let generated = fs::read_to_string(out_dir.join("test.rs")).unwrap();
+        fs::remove_dir_all(&out_dir).unwrap();
+        assert!(generated.contains("pub struct Message"));
+        assert!(generated.contains("pub mod included_client"));
+        assert!(generated.contains("pub mod included_server"));
+        assert!(generated.contains("/test.Included/Kept"));
+        assert!(!generated.contains("/test.Included/Excluded"));
+        assert!(!generated.contains("pub mod other_client"));
+        assert!(!generated.contains("pub mod other_server"));
+    }
+
+    #[cfg(feature = "protox")]
+    #[test]
+    fn compiles_with_protox() {
+        let dir = std::env::temp_dir().join(format!("tonic-build-protox-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let proto = dir.join("test.proto");
+        fs::write(
+            &proto,
+            "syntax = \"proto3\";\n\
+             package test;\n\
+             message Message {}\n\
+             service Test { rpc Call(Message) returns (Message); }\n",
+        )
+        .unwrap();
+
+        configure()
+            .out_dir(&dir)
+            .emit_rerun_if_changed(false)
+            .use_protox(true)
+            .compile(&[&proto], &[&dir])
+            .unwrap();
+
+        let generated = fs::read_to_string(dir.join("test.rs")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(generated.contains("pub struct Message"));
+        assert!(generated.contains("pub mod test_client"));
+        assert!(generated.contains("pub mod test_server"));
+    }
+}
@@ -2,8 +2,8 @@ use std::collections::HashSet;
 
 use super::{Attributes, Method, Service};
 use crate::{
-    format_method_name, format_method_path, format_service_name, generate_doc_comment,
-    generate_doc_comments, naive_snake_case,
+    cfg_attributes, format_method_name, format_method_path, format_service_name,
+    generate_doc_comment, generate_doc_comments, generate_method_paths, naive_snake_case,
 };
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
@@ -19,7 +19,12 @@ pub(crate) fn generate_internal<T: Service>(
     disable_comments: &HashSet<String>,
     use_arc_self: bool,
     generate_default_stubs: bool,
+    local: bool,
+    error_type: Option<&str>,
+    validator: Option<&str>,
 ) -> TokenStream {
+    let error_type = error_type.map(|error_type| syn::parse_str::<syn::Type>(error_type).unwrap());
+    let validator = validator.map(|validator| syn::parse_str::<syn::Path>(validator).unwrap());
     let methods = generate_methods(
         service,
         emit_package,
@@ -27,6 +32,10 @@ pub(crate) fn generate_internal<T: Service>(
         compile_well_known_types,
         use_arc_self,
         generate_default_stubs,
+        local,
+        error_type.is_some(),
+        validator.as_ref(),
+        attributes,
     );
 
     let server_service = quote::format_ident!("{}Server", service.name());
@@ -41,6 +50,9 @@ pub(crate) fn generate_internal<T: Service>(
         disable_comments,
         use_arc_self,
         generate_default_stubs,
+        local,
+        error_type.as_ref(),
+        attributes,
     );
     let package = if emit_package { service.package() } else { "" };
     // Transport based implementations
@@ -53,8 +65,10 @@ pub(crate) fn generate_internal<T: Service>(
     };
 
     let named = generate_named(&server_service, &server_trait, &service_name);
+    let method_paths = generate_method_paths(service, emit_package, attributes);
     let mod_attributes = attributes.for_mod(package);
     let struct_attributes = attributes.for_struct(&service_name);
+    let box_future = box_future(local);
 
     let configure_compression_methods = quote! {
         /// Enable decompressing requests with the given encoding.
@@ -70,6 +84,22 @@ pub(crate) fn generate_internal<T: Service>(
             self.send_compression_encodings.enable(encoding);
             self
         }
+
+        /// Send the responses smaller than `bytes` uncompressed.
+        ///
+        /// Default: `0`
+        #[must_use]
+        pub fn send_compressed_min_size(mut self, bytes: usize) -> Self {
+            self.send_compressed_min_size = bytes;
+            self
+        }
+
+        /// Compress responses at the given level.
+        #[must_use]
+        pub fn send_compression_level(mut self, level: CompressionLevel) -> Self {
+            self.send_compression_level = level;
+            self
+        }
     };
 
     let configure_max_message_size_methods = quote! {
@@ -102,9 +132,13 @@ pub(crate) fn generate_internal<T: Service>(
                 missing_docs,
                 // will trigger if compression is disabled
                 clippy::let_unit_value,
+                // will trigger if methods are deprecated
+                deprecated,
             )]
             use tonic::codegen::*;
 
+            #method_paths
+
             #generated_trait
 
             #service_doc
@@ -114,8 +148,11 @@ pub(crate) fn generate_internal<T: Service>(
                 inner: _Inner<T>,
                 accept_compression_encodings: EnabledCompressionEncodings,
                 send_compression_encodings: EnabledCompressionEncodings,
+                send_compressed_min_size: usize,
+                send_compression_level: CompressionLevel,
                 max_decoding_message_size: Option<usize>,
                 max_encoding_message_size: Option<usize>,
+                enabled_methods: Option<Arc<[methods::Method]>>,
             }
 
             struct _Inner<T>(Arc<T>);
@@ -131,8 +168,11 @@ pub(crate) fn generate_internal<T: Service>(
                         inner,
                         accept_compression_encodings: Default::default(),
                         send_compression_encodings: Default::default(),
+                        send_compressed_min_size: 0,
+                        send_compression_level: Default::default(),
                         max_decoding_message_size: None,
                         max_encoding_message_size: None,
+                        enabled_methods: None,
                     }
                 }
 
@@ -143,9 +183,24 @@ pub(crate) fn generate_internal<T: Service>(
                     InterceptedService::new(Self::new(inner), interceptor)
                 }
 
+                pub fn with_async_interceptor<F>(inner: T, interceptor: F) -> AsyncInterceptedService<Self, F>
+                where
+                    F: tonic::service::AsyncInterceptor,
+                {
+                    AsyncInterceptedService::new(Self::new(inner), interceptor)
+                }
+
                 #configure_compression_methods
 
                 #configure_max_message_size_methods
+
+                /// Serve only the given methods of the service, answering the calls of the others
+                /// with `Unimplemented`, such as for a service split across servers.
+                #[must_use]
+                pub fn enable_methods(mut self, methods: impl IntoIterator<Item = methods::Method>) -> Self {
+                    self.enabled_methods = Some(methods.into_iter().collect());
+                    self
+                }
             }
 
             impl<T, B> tonic::codegen::Service<http::Request<B>> for #server_service<T>
@@ -156,7 +211,7 @@ pub(crate) fn generate_internal<T: Service>(
             {
                 type Response = http::Response<tonic::body::BoxBody>;
                 type Error = std::convert::Infallible;
-                type Future = BoxFuture<Self::Response, Self::Error>;
+                type Future = #box_future<Self::Response, Self::Error>;
 
                 fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
                     Poll::Ready(Ok(()))
@@ -164,8 +219,13 @@ pub(crate) fn generate_internal<T: Service>(
 
                 fn call(&mut self, req: http::Request<B>) -> Self::Future {
                     let inner = self.inner.clone();
+                    let path = match &self.enabled_methods {
+                        Some(enabled) if !methods::Method::from_path(req.uri().path())
+                            .is_some_and(|method| enabled.contains(&method)) => "",
+                        _ => req.uri().path(),
+                    };
 
-                    match req.uri().path() {
+                    match path {
                         #methods
 
                         _ => Box::pin(async move {
@@ -187,8 +247,11 @@ pub(crate) fn generate_internal<T: Service>(
                         inner,
                         accept_compression_encodings: self.accept_compression_encodings,
                         send_compression_encodings: self.send_compression_encodings,
+                        send_compressed_min_size: self.send_compressed_min_size,
+                        send_compression_level: self.send_compression_level,
                         max_decoding_message_size: self.max_decoding_message_size,
                         max_encoding_message_size: self.max_encoding_message_size,
+                        enabled_methods: self.enabled_methods.clone(),
                     }
                 }
             }
@@ -220,6 +283,9 @@ fn generate_trait<T: Service>(
     disable_comments: &HashSet<String>,
     use_arc_self: bool,
     generate_default_stubs: bool,
+    local: bool,
+    error_type: Option<&syn::Type>,
+    attributes: &Attributes,
 ) -> TokenStream {
     let methods = generate_trait_methods(
         service,
@@ -229,12 +295,25 @@ fn generate_trait<T: Service>(
         disable_comments,
         use_arc_self,
         generate_default_stubs,
+        error_type,
+        attributes,
     );
     let trait_doc = generate_doc_comment(format!(
         " Generated trait containing gRPC methods that should be implemented for use with {}Server.",
         service.name()
     ));
 
+    // Local servers are `!Send`, as are the futures of their methods.
+    if local {
+        return quote! {
+            #trait_doc
+            #[async_trait(?Send)]
+            pub trait #server_trait : 'static {
+                #methods
+            }
+        };
+    }
+
     quote! {
         #trait_doc
         #[async_trait]
@@ -244,6 +323,16 @@ fn generate_trait<T: Service>(
     }
 }
 
+/// The type of the boxed futures of servers, only `Send` unless `local`.
+fn box_future(local: bool) -> TokenStream {
+    if local {
+        quote!(LocalBoxFuture)
+    } else {
+        quote!(BoxFuture)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn generate_trait_methods<T: Service>(
     service: &T,
     emit_package: bool,
@@ -252,9 +341,23 @@ fn generate_trait_methods<T: Service>(
     disable_comments: &HashSet<String>,
     use_arc_self: bool,
     generate_default_stubs: bool,
+    error_type: Option<&syn::Type>,
+    attributes: &Attributes,
 ) -> TokenStream {
     let mut stream = TokenStream::new();
 
+    // Handlers may return their own errors, converted into a `Status`.
+    let (error, unimplemented) = match error_type {
+        Some(error_type) => (
+            quote!(#error_type),
+            quote!(tonic::Status::unimplemented("Not yet implemented").into()),
+        ),
+        None => (
+            quote!(tonic::Status),
+            quote!(tonic::Status::unimplemented("Not yet implemented")),
+        ),
+    };
+
     for method in service.methods() {
         let name = quote::format_ident!("{}", method.name());
 
@@ -267,6 +370,12 @@ fn generate_trait_methods<T: Service>(
             } else {
                 generate_doc_comments(method.comment())
             };
+        let method_attributes = attributes.for_method(method);
+        let cfg_attributes = cfg_attributes(&method_attributes);
+        let method_doc = quote! {
+            #method_doc
+            #(#method_attributes)*
+        };
 
         let self_param = if use_arc_self {
             quote!(self: std::sync::Arc<Self>)
@@ -283,8 +392,8 @@ fn generate_trait_methods<T: Service>(
                 quote! {
                     #method_doc
                     async fn #name(#self_param, request: tonic::Request<#req_message>)
-                        -> std::result::Result<tonic::Response<#res_message>, tonic::Status> {
-                        Err(tonic::Status::unimplemented("Not yet implemented"))
+                        -> std::result::Result<tonic::Response<#res_message>, #error> {
+                        Err(#unimplemented)
                     }
                 }
             }
@@ -292,15 +401,15 @@ fn generate_trait_methods<T: Service>(
                 quote! {
                     #method_doc
                     async fn #name(#self_param, request: tonic::Request<#req_message>)
-                        -> std::result::Result<tonic::Response<#res_message>, tonic::Status>;
+                        -> std::result::Result<tonic::Response<#res_message>, #error>;
                 }
             }
             (true, false, true) => {
                 quote! {
                     #method_doc
                     async fn #name(#self_param, request: tonic::Request<tonic::Streaming<#req_message>>)
-                        -> std::result::Result<tonic::Response<#res_message>, tonic::Status> {
-                        Err(tonic::Status::unimplemented("Not yet implemented"))
+                        -> std::result::Result<tonic::Response<#res_message>, #error> {
+                        Err(#unimplemented)
                     }
                 }
             }
@@ -308,15 +417,15 @@ fn generate_trait_methods<T: Service>(
                 quote! {
                     #method_doc
                     async fn #name(#self_param, request: tonic::Request<tonic::Streaming<#req_message>>)
-                        -> std::result::Result<tonic::Response<#res_message>, tonic::Status>;
+                        -> std::result::Result<tonic::Response<#res_message>, #error>;
                 }
             }
             (false, true, true) => {
                 quote! {
                     #method_doc
                     async fn #name(#self_param, request: tonic::Request<#req_message>)
-                        -> std::result::Result<tonic::Response<BoxStream<#res_message>>, tonic::Status> {
-                        Err(tonic::Status::unimplemented("Not yet implemented"))
+                        -> std::result::Result<tonic::Response<BoxStream<#res_message>>, #error> {
+                        Err(#unimplemented)
                     }
                 }
             }
@@ -329,19 +438,20 @@ fn generate_trait_methods<T: Service>(
 
                 quote! {
                     #stream_doc
+                    #(#cfg_attributes)*
                     type #stream: tonic::codegen::tokio_stream::Stream<Item = std::result::Result<#res_message, tonic::Status>> + Send + 'static;
 
                     #method_doc
                     async fn #name(#self_param, request: tonic::Request<#req_message>)
-                        -> std::result::Result<tonic::Response<Self::#stream>, tonic::Status>;
+                        -> std::result::Result<tonic::Response<Self::#stream>, #error>;
                 }
             }
             (true, true, true) => {
                 quote! {
                     #method_doc
                     async fn #name(#self_param, request: tonic::Request<tonic::Streaming<#req_message>>)
-                        -> std::result::Result<tonic::Response<BoxStream<#res_message>>, tonic::Status> {
-                        Err(tonic::Status::unimplemented("Not yet implemented"))
+                        -> std::result::Result<tonic::Response<BoxStream<#res_message>>, #error> {
+                        Err(#unimplemented)
                     }
                 }
             }
@@ -354,11 +464,12 @@ fn generate_trait_methods<T: Service>(
 
                 quote! {
                     #stream_doc
+                    #(#cfg_attributes)*
                     type #stream: tonic::codegen::tokio_stream::Stream<Item = std::result::Result<#res_message, tonic::Status>> + Send + 'static;
 
                     #method_doc
                     async fn #name(#self_param, request: tonic::Request<tonic::Streaming<#req_message>>)
-                        -> std::result::Result<tonic::Response<Self::#stream>, tonic::Status>;
+                        -> std::result::Result<tonic::Response<Self::#stream>, #error>;
                 }
             }
         };
@@ -383,6 +494,7 @@ fn generate_named(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_methods<T: Service>(
     service: &T,
     emit_package: bool,
@@ -390,14 +502,30 @@ fn generate_methods<T: Service>(
     compile_well_known_types: bool,
     use_arc_self: bool,
     generate_default_stubs: bool,
+    local: bool,
+    into_status: bool,
+    validator: Option<&syn::Path>,
+    attributes: &Attributes,
 ) -> TokenStream {
     let mut stream = TokenStream::new();
+    let into_status = if into_status {
+        quote!(.map_err(Into::into))
+    } else {
+        TokenStream::new()
+    };
 
     for method in service.methods() {
         let path = format_method_path(service, method, emit_package);
         let method_path = Lit::Str(LitStr::new(&path, Span::call_site()));
         let ident = quote::format_ident!("{}", method.name());
         let server_trait = quote::format_ident!("{}", service.name());
+        let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
+        let codec = match validator {
+            Some(validator) => {
+                quote!(tonic::codec::ValidateCodec::new(#codec_name::default(), #validator))
+            }
+            None => quote!(#codec_name::default()),
+        };
 
         let method_stream = match (method.client_streaming(), method.server_streaming()) {
             (false, false) => generate_unary(
@@ -407,6 +535,9 @@ fn generate_methods<T: Service>(
                 ident,
                 server_trait,
                 use_arc_self,
+                local,
+                &into_status,
+                &codec,
             ),
 
             (false, true) => generate_server_streaming(
@@ -417,6 +548,9 @@ fn generate_methods<T: Service>(
                 server_trait,
                 use_arc_self,
                 generate_default_stubs,
+                local,
+                &into_status,
+                &codec,
             ),
             (true, false) => generate_client_streaming(
                 method,
@@ -425,6 +559,9 @@ fn generate_methods<T: Service>(
                 ident.clone(),
                 server_trait,
                 use_arc_self,
+                local,
+                &into_status,
+                &codec,
             ),
 
             (true, true) => generate_streaming(
@@ -435,10 +572,16 @@ fn generate_methods<T: Service>(
                 server_trait,
                 use_arc_self,
                 generate_default_stubs,
+                local,
+                &into_status,
+                &codec,
             ),
         };
 
+        let method_attributes = attributes.for_method(method);
+        let cfg_attributes = cfg_attributes(&method_attributes);
         let method = quote! {
+            #(#cfg_attributes)*
             #method_path => {
                 #method_stream
             }
@@ -449,6 +592,7 @@ fn generate_methods<T: Service>(
     stream
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_unary<T: Method>(
     method: &T,
     proto_path: &str,
@@ -456,9 +600,10 @@ fn generate_unary<T: Method>(
     method_ident: Ident,
     server_trait: Ident,
     use_arc_self: bool,
+    local: bool,
+    into_status: &TokenStream,
+    codec: &TokenStream,
 ) -> TokenStream {
-    let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
-
     let service_ident = quote::format_ident!("{}Svc", method.identifier());
 
     let (request, response) = method.request_response_name(proto_path, compile_well_known_types);
@@ -468,6 +613,7 @@ fn generate_unary<T: Method>(
     } else {
         quote!(&inner)
     };
+    let box_future = box_future(local);
 
     quote! {
         #[allow(non_camel_case_types)]
@@ -475,12 +621,12 @@ fn generate_unary<T: Method>(
 
         impl<T: #server_trait> tonic::server::UnaryService<#request> for #service_ident<T> {
             type Response = #response;
-            type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+            type Future = #box_future<tonic::Response<Self::Response>, tonic::Status>;
 
             fn call(&mut self, request: tonic::Request<#request>) -> Self::Future {
                 let inner = Arc::clone(&self.0);
                 let fut = async move {
-                    <T as #server_trait>::#method_ident(#inner_arg, request).await
+                    <T as #server_trait>::#method_ident(#inner_arg, request).await #into_status
                 };
                 Box::pin(fut)
             }
@@ -488,16 +634,20 @@ fn generate_unary<T: Method>(
 
         let accept_compression_encodings = self.accept_compression_encodings;
         let send_compression_encodings = self.send_compression_encodings;
+        let send_compressed_min_size = self.send_compressed_min_size;
+        let send_compression_level = self.send_compression_level;
         let max_decoding_message_size = self.max_decoding_message_size;
         let max_encoding_message_size = self.max_encoding_message_size;
         let inner = self.inner.clone();
         let fut = async move {
             let inner = inner.0;
             let method = #service_ident(inner);
-            let codec = #codec_name::default();
+            let codec = #codec;
 
             let mut grpc = tonic::server::Grpc::new(codec)
                 .apply_compression_config(accept_compression_encodings, send_compression_encodings)
+                .send_compressed_min_size(send_compressed_min_size)
+                .send_compression_level(send_compression_level)
                 .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
 
             let res = grpc.unary(method, req).await;
@@ -508,6 +658,7 @@ fn generate_unary<T: Method>(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_server_streaming<T: Method>(
     method: &T,
     proto_path: &str,
@@ -516,9 +667,10 @@ fn generate_server_streaming<T: Method>(
     server_trait: Ident,
     use_arc_self: bool,
     generate_default_stubs: bool,
+    local: bool,
+    into_status: &TokenStream,
+    codec: &TokenStream,
 ) -> TokenStream {
-    let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
-
     let service_ident = quote::format_ident!("{}Svc", method.identifier());
 
     let (request, response) = method.request_response_name(proto_path, compile_well_known_types);
@@ -535,6 +687,7 @@ fn generate_server_streaming<T: Method>(
     } else {
         quote!(&inner)
     };
+    let box_future = box_future(local);
 
     quote! {
         #[allow(non_camel_case_types)]
@@ -543,12 +696,12 @@ fn generate_server_streaming<T: Method>(
         impl<T: #server_trait> tonic::server::ServerStreamingService<#request> for #service_ident<T> {
             type Response = #response;
             #response_stream;
-            type Future = BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+            type Future = #box_future<tonic::Response<Self::ResponseStream>, tonic::Status>;
 
             fn call(&mut self, request: tonic::Request<#request>) -> Self::Future {
                 let inner = Arc::clone(&self.0);
                 let fut = async move {
-                    <T as #server_trait>::#method_ident(#inner_arg, request).await
+                    <T as #server_trait>::#method_ident(#inner_arg, request).await #into_status
                 };
                 Box::pin(fut)
             }
@@ -556,16 +709,20 @@ fn generate_server_streaming<T: Method>(
 
         let accept_compression_encodings = self.accept_compression_encodings;
         let send_compression_encodings = self.send_compression_encodings;
+        let send_compressed_min_size = self.send_compressed_min_size;
+        let send_compression_level = self.send_compression_level;
         let max_decoding_message_size = self.max_decoding_message_size;
         let max_encoding_message_size = self.max_encoding_message_size;
         let inner = self.inner.clone();
         let fut = async move {
             let inner = inner.0;
             let method = #service_ident(inner);
-            let codec = #codec_name::default();
+            let codec = #codec;
 
             let mut grpc = tonic::server::Grpc::new(codec)
                 .apply_compression_config(accept_compression_encodings, send_compression_encodings)
+                .send_compressed_min_size(send_compressed_min_size)
+                .send_compression_level(send_compression_level)
                 .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
 
             let res = grpc.server_streaming(method, req).await;
@@ -576,6 +733,7 @@ fn generate_server_streaming<T: Method>(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_client_streaming<T: Method>(
     method: &T,
     proto_path: &str,
@@ -583,17 +741,20 @@ fn generate_client_streaming<T: Method>(
     method_ident: Ident,
     server_trait: Ident,
     use_arc_self: bool,
+    local: bool,
+    into_status: &TokenStream,
+    codec: &TokenStream,
 ) -> TokenStream {
     let service_ident = quote::format_ident!("{}Svc", method.identifier());
 
     let (request, response) = method.request_response_name(proto_path, compile_well_known_types);
-    let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
 
     let inner_arg = if use_arc_self {
         quote!(inner)
     } else {
         quote!(&inner)
     };
+    let box_future = box_future(local);
 
     quote! {
         #[allow(non_camel_case_types)]
@@ -602,12 +763,12 @@ fn generate_client_streaming<T: Method>(
         impl<T: #server_trait> tonic::server::ClientStreamingService<#request> for #service_ident<T>
         {
             type Response = #response;
-            type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+            type Future = #box_future<tonic::Response<Self::Response>, tonic::Status>;
 
             fn call(&mut self, request: tonic::Request<tonic::Streaming<#request>>) -> Self::Future {
                 let inner = Arc::clone(&self.0);
                 let fut = async move {
-                    <T as #server_trait>::#method_ident(#inner_arg, request).await
+                    <T as #server_trait>::#method_ident(#inner_arg, request).await #into_status
                 };
                 Box::pin(fut)
             }
@@ -615,16 +776,20 @@ fn generate_client_streaming<T: Method>(
 
         let accept_compression_encodings = self.accept_compression_encodings;
         let send_compression_encodings = self.send_compression_encodings;
+        let send_compressed_min_size = self.send_compressed_min_size;
+        let send_compression_level = self.send_compression_level;
         let max_decoding_message_size = self.max_decoding_message_size;
         let max_encoding_message_size = self.max_encoding_message_size;
         let inner = self.inner.clone();
         let fut = async move {
             let inner = inner.0;
             let method = #service_ident(inner);
-            let codec = #codec_name::default();
+            let codec = #codec;
 
             let mut grpc = tonic::server::Grpc::new(codec)
                 .apply_compression_config(accept_compression_encodings, send_compression_encodings)
+                .send_compressed_min_size(send_compressed_min_size)
+                .send_compression_level(send_compression_level)
                 .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
 
             let res = grpc.client_streaming(method, req).await;
@@ -635,6 +800,7 @@ fn generate_client_streaming<T: Method>(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_streaming<T: Method>(
     method: &T,
     proto_path: &str,
@@ -643,9 +809,10 @@ fn generate_streaming<T: Method>(
     server_trait: Ident,
     use_arc_self: bool,
     generate_default_stubs: bool,
+    local: bool,
+    into_status: &TokenStream,
+    codec: &TokenStream,
 ) -> TokenStream {
-    let codec_name = syn::parse_str::<syn::Path>(method.codec_path()).unwrap();
-
     let service_ident = quote::format_ident!("{}Svc", method.identifier());
 
     let (request, response) = method.request_response_name(proto_path, compile_well_known_types);
@@ -662,6 +829,7 @@ fn generate_streaming<T: Method>(
     } else {
         quote!(&inner)
     };
+    let box_future = box_future(local);
 
     quote! {
         #[allow(non_camel_case_types)]
@@ -671,12 +839,12 @@ fn generate_streaming<T: Method>(
         {
             type Response = #response;
             #response_stream;
-            type Future = BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+            type Future = #box_future<tonic::Response<Self::ResponseStream>, tonic::Status>;
 
             fn call(&mut self, request: tonic::Request<tonic::Streaming<#request>>) -> Self::Future {
                 let inner = Arc::clone(&self.0);
                 let fut = async move {
-                    <T as #server_trait>::#method_ident(#inner_arg, request).await
+                    <T as #server_trait>::#method_ident(#inner_arg, request).await #into_status
                 };
                 Box::pin(fut)
             }
@@ -684,16 +852,20 @@ fn generate_streaming<T: Method>(
 
         let accept_compression_encodings = self.accept_compression_encodings;
         let send_compression_encodings = self.send_compression_encodings;
+        let send_compressed_min_size = self.send_compressed_min_size;
+        let send_compression_level = self.send_compression_level;
         let max_decoding_message_size = self.max_decoding_message_size;
         let max_encoding_message_size = self.max_encoding_message_size;
         let inner = self.inner.clone();
         let fut = async move {
             let inner = inner.0;
             let method = #service_ident(inner);
-            let codec = #codec_name::default();
+            let codec = #codec;
 
             let mut grpc = tonic::server::Grpc::new(codec)
                 .apply_compression_config(accept_compression_encodings, send_compression_encodings)
+                .send_compressed_min_size(send_compressed_min_size)
+                .send_compression_level(send_compression_level)
                 .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
 
             let res = grpc.streaming(method, req).await;
@@ -0,0 +1,137 @@
+//! Descriptions of the symbols of descriptors, in the syntax of their proto
+//! files.
+
+use prost_reflect::{
+    DescriptorPool, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor, MethodDescriptor,
+    ServiceDescriptor,
+};
+use std::fmt::Write;
+
+/// The description of the service, method, message or enum `symbol` of
+/// `pool`.
+pub(crate) fn describe(pool: &DescriptorPool, symbol: &str) -> Option<String> {
+    if let Some(service) = pool.get_service_by_name(symbol) {
+        return Some(service_definition(&service));
+    }
+    if let Some(message) = pool.get_message_by_name(symbol) {
+        return Some(message_definition(&message));
+    }
+    if let Some(enum_) = pool.get_enum_by_name(symbol) {
+        return Some(enum_definition(&enum_));
+    }
+    let (service, method) = symbol.rsplit_once(['.', '/'])?;
+    let method = pool
+        .get_service_by_name(service)?
+        .methods()
+        .find(|m| m.name() == method)?;
+    Some(method_definition(&method))
+}
+
+/// The name of the type of the values of `kind`.
+pub(crate) fn kind_name(kind: &Kind) -> String {
+    let name = match kind {
+        Kind::Double => "double",
+        Kind::Float => "float",
+        Kind::Int32 => "int32",
+        Kind::Int64 => "int64",
+        Kind::Uint32 => "uint32",
+        Kind::Uint64 => "uint64",
+        Kind::Sint32 => "sint32",
+        Kind::Sint64 => "sint64",
+        Kind::Fixed32 => "fixed32",
+        Kind::Fixed64 => "fixed64",
+        Kind::Sfixed32 => "sfixed32",
+        Kind::Sfixed64 => "sfixed64",
+        Kind::Bool => "bool",
+        Kind::String => "string",
+        Kind::Bytes => "bytes",
+        Kind::Message(message) => message.full_name(),
+        Kind::Enum(enum_) => enum_.full_name(),
+    };
+    name.to_owned()
+}
+
+fn service_definition(service: &ServiceDescriptor) -> String {
+    let mut definition = format!("service {} {{\n", service.full_name());
+    for method in service.methods() {
+        let _ = writeln!(definition, "  {}", method_definition(&method));
+    }
+    definition.push('}');
+    definition
+}
+
+fn method_definition(method: &MethodDescriptor) -> String {
+    let stream = |streaming| if streaming { "stream " } else { "" };
+    format!(
+        "rpc {}({}{}) returns ({}{});",
+        method.name(),
+        stream(method.is_client_streaming()),
+        method.input().full_name(),
+        stream(method.is_server_streaming()),
+        method.output().full_name()
+    )
+}
+
+fn message_definition(message: &MessageDescriptor) -> String {
+    let mut definition = format!("message {} {{\n", message.full_name());
+    for field in message.fields() {
+        let _ = writeln!(
+            definition,
+            "  {} {} = {};",
+            field_type(&field),
+            field.name(),
+            field.number()
+        );
+    }
+    definition.push('}');
+    definition
+}
+
+fn field_type(field: &FieldDescriptor) -> String {
+    let kind = field.kind();
+    match kind.as_message() {
+        Some(entry) if field.is_map() => format!(
+            "map<{}, {}>",
+            kind_name(&entry.map_entry_key_field().kind()),
+            kind_name(&entry.map_entry_value_field().kind())
+        ),
+        _ if field.is_list() => format!("repeated {}", kind_name(&kind)),
+        _ => kind_name(&kind),
+    }
+}
+
+fn enum_definition(enum_: &EnumDescriptor) -> String {
+    let mut definition = format!("enum {} {{\n", enum_.full_name());
+    for value in enum_.values() {
+        let _ = writeln!(definition, "  {} = {};", value.name(), value.number());
+    }
+    definition.push('}');
+    definition
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::pool;
+
+    #[test]
+    fn describes_symbols() {
+        let pool = pool();
+        assert_eq!(
+            describe(&pool, "test.Sampler").unwrap(),
+            "service test.Sampler {\n  rpc Sample(test.Sample) returns (stream test.Sample);\n}"
+        );
+        assert_eq!(
+            describe(&pool, "test.Sampler/Sample").unwrap(),
+            "rpc Sample(test.Sample) returns (stream test.Sample);"
+        );
+        let sample = describe(&pool, "test.Sample").unwrap();
+        assert!(sample.contains("  repeated string tags = 6;\n"));
+        assert!(sample.contains("  map<int32, int32> scores = 7;\n"));
+        assert_eq!(
+            describe(&pool, "test.Kind").unwrap(),
+            "enum test.Kind {\n  KIND_A = 0;\n  KIND_B = 1;\n}"
+        );
+        assert!(describe(&pool, "test.Missing").is_none());
+    }
+}
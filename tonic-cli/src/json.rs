@@ -0,0 +1,272 @@
+//! The JSON mapping of protobuf messages, for the requests and responses of
+//! calls.
+
+use crate::describe::kind_name;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use prost_reflect::{DynamicMessage, Kind, MapKey, MessageDescriptor, Value};
+use serde_json::{Map, Number, Value as Json};
+use std::collections::HashMap;
+
+/// The messages of `data`, a sequence of JSON objects.
+pub(crate) fn messages(
+    data: &str,
+    descriptor: &MessageDescriptor,
+) -> Result<Vec<DynamicMessage>, String> {
+    serde_json::Deserializer::from_str(data)
+        .into_iter::<Json>()
+        .map(|json| {
+            let json = json.map_err(|e| format!("invalid JSON: {}", e))?;
+            from_json(descriptor, &json)
+        })
+        .collect()
+}
+
+/// The message of the JSON object `json`.
+pub(crate) fn from_json(
+    descriptor: &MessageDescriptor,
+    json: &Json,
+) -> Result<DynamicMessage, String> {
+    let Json::Object(object) = json else {
+        return Err(format!(
+            "expected a `{}` object, found {}",
+            descriptor.full_name(),
+            json
+        ));
+    };
+
+    let mut message = DynamicMessage::new(descriptor.clone());
+    for (name, json) in object {
+        let field = descriptor
+            .get_field_by_json_name(name)
+            .or_else(|| descriptor.get_field_by_name(name))
+            .ok_or_else(|| format!("`{}` has no field `{}`", descriptor.full_name(), name))?;
+        if json.is_null() {
+            continue;
+        }
+
+        let kind = field.kind();
+        let value = if field.is_map() {
+            map_from_json(&kind, json)
+        } else if field.is_list() {
+            match json {
+                Json::Array(values) => values
+                    .iter()
+                    .map(|json| value_from_json(&kind, json))
+                    .collect::<Result<_, _>>()
+                    .map(Value::List),
+                json => Err(format!("expected a list, found {}", json)),
+            }
+        } else {
+            value_from_json(&kind, json)
+        };
+        let value = value.map_err(|e| format!("{}: {}", field.full_name(), e))?;
+        message.set_field(&field, value);
+    }
+    Ok(message)
+}
+
+/// The JSON object of `message`, without its fields of default values.
+pub(crate) fn to_json(message: &DynamicMessage) -> Json {
+    let object = message
+        .fields()
+        .map(|(field, value)| {
+            (
+                field.json_name().to_owned(),
+                value_to_json(&field.kind(), value),
+            )
+        })
+        .collect();
+    Json::Object(object)
+}
+
+fn value_from_json(kind: &Kind, json: &Json) -> Result<Value, String> {
+    let invalid = || format!("expected {}, found {}", kind_name(kind), json);
+
+    let value = match kind {
+        Kind::Double => Value::F64(float(json).ok_or_else(invalid)?),
+        Kind::Float => Value::F32(float(json).ok_or_else(invalid)? as f32),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            Value::I32(integer(json).ok_or_else(invalid)?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            Value::I64(integer(json).ok_or_else(invalid)?)
+        }
+        Kind::Uint32 | Kind::Fixed32 => Value::U32(integer(json).ok_or_else(invalid)?),
+        Kind::Uint64 | Kind::Fixed64 => Value::U64(integer(json).ok_or_else(invalid)?),
+        Kind::Bool => Value::Bool(json.as_bool().ok_or_else(invalid)?),
+        Kind::String => Value::String(json.as_str().ok_or_else(invalid)?.to_owned()),
+        Kind::Bytes => {
+            let bytes = json.as_str().and_then(|s| STANDARD.decode(s).ok());
+            Value::Bytes(bytes.ok_or_else(invalid)?.into())
+        }
+        Kind::Enum(descriptor) => {
+            let number = match json {
+                Json::String(name) => descriptor.get_value_by_name(name).map(|v| v.number()),
+                json => integer(json),
+            };
+            Value::EnumNumber(number.ok_or_else(invalid)?)
+        }
+        Kind::Message(descriptor) => Value::Message(from_json(descriptor, json)?),
+    };
+    Ok(value)
+}
+
+fn map_from_json(kind: &Kind, json: &Json) -> Result<Value, String> {
+    let entry = kind.as_message().expect("map fields are of entry messages");
+    let key_kind = entry.map_entry_key_field().kind();
+    let value_kind = entry.map_entry_value_field().kind();
+
+    let Json::Object(object) = json else {
+        return Err(format!("expected a map, found {}", json));
+    };
+    let mut map = HashMap::with_capacity(object.len());
+    for (key, json) in object {
+        let invalid = || format!("expected a {} key, found `{}`", kind_name(&key_kind), key);
+        let key = match key_kind {
+            Kind::Bool => MapKey::Bool(key.parse().map_err(|_| invalid())?),
+            Kind::String => MapKey::String(key.clone()),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+                MapKey::I32(key.parse().map_err(|_| invalid())?)
+            }
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+                MapKey::I64(key.parse().map_err(|_| invalid())?)
+            }
+            Kind::Uint32 | Kind::Fixed32 => MapKey::U32(key.parse().map_err(|_| invalid())?),
+            Kind::Uint64 | Kind::Fixed64 => MapKey::U64(key.parse().map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        };
+        map.insert(key, value_from_json(&value_kind, json)?);
+    }
+    Ok(Value::Map(map))
+}
+
+fn value_to_json(kind: &Kind, value: &Value) -> Json {
+    match value {
+        Value::Bool(b) => Json::Bool(*b),
+        Value::I32(n) => Json::from(*n),
+        Value::U32(n) => Json::from(*n),
+        // 64 bit integers are strings, as JSON numbers lose their precision.
+        Value::I64(n) => Json::String(n.to_string()),
+        Value::U64(n) => Json::String(n.to_string()),
+        Value::F32(n) => float_to_json(f64::from(*n)),
+        Value::F64(n) => float_to_json(*n),
+        Value::String(s) => Json::String(s.clone()),
+        Value::Bytes(bytes) => Json::String(STANDARD.encode(bytes)),
+        Value::EnumNumber(number) => match kind.as_enum().and_then(|e| e.get_value(*number)) {
+            Some(value) => Json::String(value.name().to_owned()),
+            None => Json::from(*number),
+        },
+        Value::Message(message) => to_json(message),
+        Value::List(values) => Json::Array(values.iter().map(|v| value_to_json(kind, v)).collect()),
+        Value::Map(map) => {
+            let value_kind = kind
+                .as_message()
+                .expect("map fields are of entry messages")
+                .map_entry_value_field()
+                .kind();
+            let object: Map<_, _> = map
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        MapKey::Bool(b) => b.to_string(),
+                        MapKey::I32(n) => n.to_string(),
+                        MapKey::I64(n) => n.to_string(),
+                        MapKey::U32(n) => n.to_string(),
+                        MapKey::U64(n) => n.to_string(),
+                        MapKey::String(s) => s.clone(),
+                    };
+                    (key, value_to_json(&value_kind, value))
+                })
+                .collect();
+            Json::Object(object)
+        }
+    }
+}
+
+fn float(json: &Json) -> Option<f64> {
+    match json {
+        Json::Number(n) => n.as_f64(),
+        Json::String(s) => match s.as_str() {
+            "NaN" => Some(f64::NAN),
+            "Infinity" => Some(f64::INFINITY),
+            "-Infinity" => Some(f64::NEG_INFINITY),
+            s => s.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn float_to_json(n: f64) -> Json {
+    match Number::from_f64(n) {
+        Some(n) => Json::Number(n),
+        None if n.is_nan() => Json::String("NaN".to_owned()),
+        None if n > 0.0 => Json::String("Infinity".to_owned()),
+        None => Json::String("-Infinity".to_owned()),
+    }
+}
+
+/// An integer of a JSON number or, as 64 bit integers are, a JSON string.
+fn integer<N>(json: &Json) -> Option<N>
+where
+    N: TryFrom<i64> + TryFrom<u64> + std::str::FromStr,
+{
+    match json {
+        Json::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(n), _) => N::try_from(n).ok(),
+            (None, Some(n)) => N::try_from(n).ok(),
+            (None, None) => None,
+        },
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::pool;
+    use serde_json::json;
+
+    #[test]
+    fn maps_messages_to_json_and_back() {
+        let descriptor = pool().get_message_by_name("test.Sample").unwrap();
+        let json = json!({
+            "text": "hello",
+            "count": "-12",
+            "ratio": "NaN",
+            "data": "AAE=",
+            "kind": "KIND_B",
+            "tags": ["a", "b"],
+            "scores": {"7": 1},
+            "nested": {"text": "inner"},
+        });
+
+        let message = from_json(&descriptor, &json).unwrap();
+        assert_eq!(
+            message.get_field_by_name("count").unwrap().as_i64(),
+            Some(-12)
+        );
+        assert_eq!(to_json(&message), json);
+    }
+
+    #[test]
+    fn accepts_the_proto_names_of_fields_and_numbers() {
+        let descriptor = pool().get_message_by_name("test.Sample").unwrap();
+        let messages = messages(r#"{"count": 3, "kind": 1} {"text": null}"#, &descriptor).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            to_json(&messages[0]),
+            json!({"count": "3", "kind": "KIND_B"})
+        );
+        assert_eq!(to_json(&messages[1]), json!({}));
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        let descriptor = pool().get_message_by_name("test.Sample").unwrap();
+        let error = from_json(&descriptor, &json!({"color": 1})).unwrap_err();
+        assert_eq!(error, "`test.Sample` has no field `color`");
+        let error = from_json(&descriptor, &json!({"tags": [1]})).unwrap_err();
+        assert_eq!(error, "test.Sample.tags: expected string, found 1");
+    }
+}
@@ -0,0 +1,478 @@
+//! A command-line client of gRPC servers, in the manner of `grpcurl`.
+//!
+//! `tonic-cli` finds the services of a server and their descriptors with
+//! server reflection, and calls their methods with a
+//! [`DynamicClient`](tonic_dynamic::DynamicClient), composing the requests
+//! from JSON and printing the responses as JSON.
+//!
+//! ```text
+//! tonic-cli localhost:50051 list
+//! tonic-cli localhost:50051 describe helloworld.Greeter
+//! tonic-cli localhost:50051 helloworld.Greeter/SayHello -d '{"name": "tonic"}'
+//! ```
+
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+
+mod describe;
+mod json;
+
+use std::{
+    io::{self, Read, Write},
+    process::ExitCode,
+};
+use tonic::{
+    metadata::{AsciiMetadataValue, MetadataKey},
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Request, Status,
+};
+use tonic_dynamic::DynamicClient;
+use tonic_reflection::client::ReflectionClient;
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+const USAGE: &str = "\
+Usage: tonic-cli [OPTIONS] <ADDRESS> <COMMAND>
+
+Commands:
+  list [SERVICE]     List the services of the server, or the methods of SERVICE
+  describe <SYMBOL>  Describe a service, method, message or enum
+  <METHOD>           Call METHOD, such as `helloworld.Greeter/SayHello`
+
+Options:
+  -d, --data <DATA>      The JSON request messages of a call, or `@` to read them from stdin
+  -H, --header <HEADER>  A `name: value` header of a call, repeatable
+  -h, --help             Print this help
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Args {
+    address: String,
+    command: Command,
+    data: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    List(Option<String>),
+    Describe(String),
+    Call(String),
+}
+
+impl Args {
+    /// The arguments of the command line `args`, or `None` if they ask for
+    /// help.
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+        let mut args = args.into_iter();
+        let mut data = None;
+        let mut headers = Vec::new();
+        let mut positional = Vec::new();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("`{}` needs a value", name))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-d" | "--data" => data = Some(value(&arg)?),
+                "-H" | "--header" => {
+                    let header = value(&arg)?;
+                    let (name, value) = header
+                        .split_once(':')
+                        .ok_or_else(|| format!("invalid header `{}`", header))?;
+                    headers.push((name.trim().to_owned(), value.trim().to_owned()));
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let address = positional.next().ok_or("missing the address")?;
+        let command = match positional.next().as_deref() {
+            None => return Err("missing the command".to_owned()),
+            Some("list") => Command::List(positional.next()),
+            Some("describe") => Command::Describe(positional.next().ok_or("missing the symbol")?),
+            Some(method) => Command::Call(method.to_owned()),
+        };
+        if let Some(arg) = positional.next() {
+            return Err(format!("unexpected argument `{}`", arg));
+        }
+
+        Ok(Some(Args {
+            address,
+            command,
+            data,
+            headers,
+        }))
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprint!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match connect(&args.address).await {
+        Ok(channel) => run(args, channel, &mut io::stdout()).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Connect to `address`, with TLS if it is an `https` URI.
+async fn connect(address: &str) -> Result<Channel, Error> {
+    let uri = if address.contains("://") {
+        address.to_owned()
+    } else {
+        format!("http://{}", address)
+    };
+    let mut endpoint = Endpoint::from_shared(uri)?;
+    if endpoint.uri().scheme_str() == Some("https") {
+        endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+    }
+    Ok(endpoint.connect().await?)
+}
+
+async fn run(args: Args, channel: Channel, out: &mut impl Write) -> Result<(), Error> {
+    let mut reflection = ReflectionClient::new(channel.clone());
+    match args.command {
+        Command::List(None) => {
+            let mut services = reflection.list_services().await?;
+            services.sort();
+            for service in services {
+                writeln!(out, "{}", service)?;
+            }
+        }
+        Command::List(Some(service)) => {
+            let pool = reflection.descriptor_pool(&[&service]).await?;
+            let service = pool
+                .get_service_by_name(&service)
+                .ok_or_else(|| Status::not_found(format!("service `{}` not found", service)))?;
+            for method in service.methods() {
+                writeln!(out, "{}", method.full_name())?;
+            }
+        }
+        Command::Describe(symbol) => {
+            let pool = reflection.descriptor_pool(&[&symbol]).await?;
+            let description = describe::describe(&pool, &symbol)
+                .ok_or_else(|| Status::not_found(format!("symbol `{}` not found", symbol)))?;
+            writeln!(out, "{}", description)?;
+        }
+        Command::Call(method) => {
+            let service = method
+                .trim_start_matches('/')
+                .rsplit_once(['/', '.'])
+                .map_or(method.as_str(), |(service, _)| service);
+            let pool = reflection.descriptor_pool(&[service]).await?;
+            let mut client = DynamicClient::new(channel, pool);
+            let method = client.method(&method)?;
+
+            let data = match args.data.as_deref() {
+                None => "{}".to_owned(),
+                Some("@") => {
+                    let mut data = String::new();
+                    io::stdin().read_to_string(&mut data)?;
+                    data
+                }
+                Some(data) => data.to_owned(),
+            };
+            let mut messages = json::messages(&data, &method.input())?;
+
+            if method.is_client_streaming() {
+                let request = request(tokio_stream::iter(messages), &args.headers)?;
+                if method.is_server_streaming() {
+                    let mut responses = client.streaming(&method, request).await?.into_inner();
+                    while let Some(response) = responses.message().await? {
+                        print_message(out, &response)?;
+                    }
+                } else {
+                    let response = client.client_streaming(&method, request).await?;
+                    print_message(out, response.get_ref())?;
+                }
+            } else {
+                if messages.len() != 1 {
+                    let message = format!(
+                        "method `{}` takes one request message, not {}",
+                        method.full_name(),
+                        messages.len()
+                    );
+                    return Err(message.into());
+                }
+                let request = request(messages.remove(0), &args.headers)?;
+                if method.is_server_streaming() {
+                    let mut responses = client
+                        .server_streaming(&method, request)
+                        .await?
+                        .into_inner();
+                    while let Some(response) = responses.message().await? {
+                        print_message(out, &response)?;
+                    }
+                } else {
+                    let response = client.unary(&method, request).await?;
+                    print_message(out, response.get_ref())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A request of `message`, with the metadata of `headers`.
+fn request<T>(message: T, headers: &[(String, String)]) -> Result<Request<T>, Error> {
+    let mut request = Request::new(message);
+    for (name, value) in headers {
+        let name = MetadataKey::from_bytes(name.as_bytes())?;
+        let value: AsciiMetadataValue = value.parse()?;
+        request.metadata_mut().insert(name, value);
+    }
+    Ok(request)
+}
+
+fn print_message(
+    out: &mut impl Write,
+    message: &prost_reflect::DynamicMessage,
+) -> Result<(), Error> {
+    writeln!(
+        out,
+        "{}",
+        serde_json::to_string_pretty(&json::to_json(message))?
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use prost_reflect::{DescriptorPool, DynamicMessage, Value};
+    use prost_types::{
+        field_descriptor_proto::{Label, Type},
+        DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+        FileDescriptorProto, FileDescriptorSet, MessageOptions, MethodDescriptorProto,
+        ServiceDescriptorProto,
+    };
+    use std::{convert::Infallible, sync::OnceLock};
+    use tonic::transport::{local, Server};
+
+    /// A `test.Sampler` service of `test.Sample` messages, with fields of
+    /// most kinds, and a server streaming `Sample` method.
+    pub(crate) fn pool() -> DescriptorPool {
+        static POOL: OnceLock<DescriptorPool> = OnceLock::new();
+        POOL.get_or_init(|| DescriptorPool::decode(file_descriptor_set().as_slice()).unwrap())
+            .clone()
+    }
+
+    fn file_descriptor_set() -> Vec<u8> {
+        let field =
+            |name: &str, number, r#type: Type, type_name: Option<&str>| FieldDescriptorProto {
+                name: Some(name.to_owned()),
+                number: Some(number),
+                label: Some(Label::Optional.into()),
+                r#type: Some(r#type.into()),
+                type_name: type_name.map(str::to_owned),
+                ..Default::default()
+            };
+        let repeated = |field: FieldDescriptorProto| FieldDescriptorProto {
+            label: Some(Label::Repeated.into()),
+            ..field
+        };
+
+        let scores_entry = DescriptorProto {
+            name: Some("ScoresEntry".to_owned()),
+            field: vec![
+                field("key", 1, Type::Int32, None),
+                field("value", 2, Type::Int32, None),
+            ],
+            options: Some(MessageOptions {
+                map_entry: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let sample = DescriptorProto {
+            name: Some("Sample".to_owned()),
+            field: vec![
+                field("text", 1, Type::String, None),
+                field("count", 2, Type::Int64, None),
+                field("ratio", 3, Type::Double, None),
+                field("data", 4, Type::Bytes, None),
+                field("kind", 5, Type::Enum, Some(".test.Kind")),
+                repeated(field("tags", 6, Type::String, None)),
+                repeated(field(
+                    "scores",
+                    7,
+                    Type::Message,
+                    Some(".test.Sample.ScoresEntry"),
+                )),
+                field("nested", 8, Type::Message, Some(".test.Sample")),
+            ],
+            nested_type: vec![scores_entry],
+            ..Default::default()
+        };
+        let kind = EnumDescriptorProto {
+            name: Some("Kind".to_owned()),
+            value: ["KIND_A", "KIND_B"]
+                .iter()
+                .zip(0..)
+                .map(|(name, number)| EnumValueDescriptorProto {
+                    name: Some((*name).to_owned()),
+                    number: Some(number),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let file = FileDescriptorProto {
+            name: Some("test.proto".to_owned()),
+            package: Some("test".to_owned()),
+            message_type: vec![sample],
+            enum_type: vec![kind],
+            service: vec![ServiceDescriptorProto {
+                name: Some("Sampler".to_owned()),
+                method: vec![MethodDescriptorProto {
+                    name: Some("Sample".to_owned()),
+                    input_type: Some(".test.Sample".to_owned()),
+                    output_type: Some(".test.Sample".to_owned()),
+                    server_streaming: Some(true),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_owned()),
+            ..Default::default()
+        };
+        FileDescriptorSet { file: vec![file] }.encode_to_vec()
+    }
+
+    /// A channel of a server of `test.Sampler`, whose `Sample` method
+    /// responds with the request and with the `x-text` header of the call,
+    /// and of the reflection service of its descriptors.
+    fn channel() -> Channel {
+        let pool = pool();
+        static FILE_DESCRIPTOR_SET: OnceLock<Vec<u8>> = OnceLock::new();
+        let reflection = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(
+                FILE_DESCRIPTOR_SET.get_or_init(file_descriptor_set),
+            )
+            .build()
+            .unwrap();
+
+        let sampler = tower::service_fn(move |request: http::Request<hyper::Body>| {
+            let sample = pool.get_message_by_name("test.Sample").unwrap();
+            async move {
+                let mut grpc = tonic::server::Grpc::new(tonic_dynamic::DynamicCodec::new(sample));
+                let method = tower::service_fn(|request: Request<DynamicMessage>| async move {
+                    let text = request.metadata().get("x-text").unwrap().to_str().unwrap();
+                    let mut second = request.get_ref().clone();
+                    second.set_field_by_name("text", Value::String(text.to_owned()));
+                    let messages = vec![Ok(request.into_inner()), Ok(second)];
+                    Ok(tonic::Response::new(tokio_stream::iter(messages)))
+                });
+                Ok::<_, Infallible>(grpc.server_streaming(method, request).await)
+            }
+        });
+
+        let (channel, incoming) = local::pair();
+        tokio::spawn(
+            Server::builder()
+                .add_service(reflection)
+                .fallback_service(sampler)
+                .serve_with_incoming(incoming),
+        );
+        channel
+    }
+
+    async fn output(args: &[&str]) -> Result<String, Error> {
+        let args = ["localhost"].iter().chain(args).map(|&s| s.to_owned());
+        let args = Args::parse(args)?.unwrap();
+        let mut out = Vec::new();
+        run(args, channel(), &mut out).await?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn parses_args() {
+        let args = [
+            "localhost:50051",
+            "-H",
+            "a: b",
+            "test.Sampler/Sample",
+            "-d",
+            "{}",
+        ];
+        let args = Args::parse(args.iter().map(|&s| s.to_owned()));
+        assert_eq!(
+            args.unwrap().unwrap(),
+            Args {
+                address: "localhost:50051".to_owned(),
+                command: Command::Call("test.Sampler/Sample".to_owned()),
+                data: Some("{}".to_owned()),
+                headers: vec![("a".to_owned(), "b".to_owned())],
+            }
+        );
+
+        assert_eq!(Args::parse(["--help".to_owned()]), Ok(None));
+        let error = Args::parse(["localhost".to_owned()]).unwrap_err();
+        assert_eq!(error, "missing the command");
+    }
+
+    #[tokio::test]
+    async fn lists_and_describes_services() {
+        assert_eq!(
+            output(&["list"]).await.unwrap(),
+            "grpc.reflection.v1alpha.ServerReflection\ntest.Sampler\n"
+        );
+        assert_eq!(
+            output(&["list", "test.Sampler"]).await.unwrap(),
+            "test.Sampler.Sample\n"
+        );
+        assert_eq!(
+            output(&["describe", "test.Sampler.Sample"]).await.unwrap(),
+            "rpc Sample(test.Sample) returns (stream test.Sample);\n"
+        );
+
+        let error = output(&["describe", "test.Missing"]).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("symbol 'test.Missing' not found"));
+    }
+
+    #[tokio::test]
+    async fn calls_methods() {
+        let output = output(&[
+            "test.Sampler/Sample",
+            "-d",
+            r#"{"text": "hello"}"#,
+            "-H",
+            "x-text: again",
+        ])
+        .await
+        .unwrap();
+        assert_eq!(
+            output,
+            "{\n  \"text\": \"hello\"\n}\n{\n  \"text\": \"again\"\n}\n"
+        );
+    }
+}
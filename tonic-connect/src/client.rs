@@ -0,0 +1,403 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{
+    header::{self, HeaderName},
+    HeaderMap, HeaderValue, Request, Response, StatusCode, Version,
+};
+use http_body::Body;
+use tonic::{body::BoxBody, Status};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::BoxError;
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'static>>;
+
+const GRPC: &str = "application/grpc";
+const GRPC_ENCODING: &str = "grpc-encoding";
+const GRPC_ACCEPT_ENCODING: &str = "grpc-accept-encoding";
+const GRPC_TIMEOUT: &str = "grpc-timeout";
+const CONNECT_PROTOCOL_VERSION: &str = "connect-protocol-version";
+const CONNECT_TIMEOUT: &str = "connect-timeout-ms";
+/// The prefix of the headers of unary responses holding their trailers.
+const TRAILER_PREFIX: &str = "trailer-";
+
+// A gRPC message is prefixed by a u8 (compressed flag) and a u32 (length).
+const GRPC_HEADER_SIZE: usize = 1 + 4;
+
+/// Layer making the unary calls of clients with the Connect protocol.
+#[derive(Debug, Clone)]
+pub struct ConnectClientLayer {
+    _priv: (),
+}
+
+impl ConnectClientLayer {
+    /// Create a new Connect for clients layer.
+    pub fn new() -> ConnectClientLayer {
+        Self { _priv: () }
+    }
+}
+
+impl Default for ConnectClientLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for ConnectClientLayer {
+    type Service = ConnectClientService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectClientService::new(inner)
+    }
+}
+
+/// A [`Service`] that turns the unary requests coming from
+/// [`tonic::client::Grpc`] into Connect requests to some inner http
+/// service, such as a [`hyper::Client`], and their Connect responses back
+/// into gRPC ones.
+#[derive(Debug, Clone)]
+pub struct ConnectClientService<S> {
+    inner: S,
+}
+
+impl<S> ConnectClientService<S> {
+    /// Create a new Connect for clients service.
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, B1, B2> Service<Request<B1>> for ConnectClientService<S>
+where
+    S: Service<Request<hyper::Body>, Response = Response<B2>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError>,
+    B1: Body<Data = Bytes> + Send + 'static,
+    B1::Error: Into<BoxError>,
+    B2: Body<Data = Bytes> + Send + 'static,
+    B2::Error: Into<BoxError>,
+{
+    type Response = Response<BoxBody>;
+    type Error = BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<B1>) -> Self::Future {
+        // The request is sent once its message is read, with the service
+        // that is ready.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let (mut parts, body) = req.into_parts();
+            let body = hyper::body::to_bytes(body).await.map_err(Into::into)?;
+            let Some((compressed, message)) = unary_message(body) else {
+                let status = Status::unimplemented("Connect clients only make unary calls");
+                return Ok(status.to_http());
+            };
+
+            if parts.version == Version::HTTP_2 {
+                parts.version = Version::HTTP_11;
+            }
+            let content_type = coerce_request(&mut parts.headers, compressed);
+            let req = Request::from_parts(parts, hyper::Body::from(message));
+
+            let res = inner.call(req).await.map_err(Into::into)?;
+            coerce_response(res, content_type).await
+        })
+    }
+}
+
+/// The compressed flag and the message of the gRPC frame of a unary
+/// request, if it holds only one.
+fn unary_message(mut body: Bytes) -> Option<(bool, Bytes)> {
+    if body.len() < GRPC_HEADER_SIZE {
+        return None;
+    }
+    let compressed = body.get_u8() == 1;
+    let len = body.get_u32() as usize;
+    (body.len() == len).then_some((compressed, body))
+}
+
+/// Turn the gRPC headers of a request into Connect ones, returning the gRPC
+/// content type of the call.
+fn coerce_request(headers: &mut HeaderMap, compressed: bool) -> HeaderValue {
+    let content_type = headers
+        .remove(header::CONTENT_TYPE)
+        .unwrap_or_else(|| HeaderValue::from_static(GRPC));
+    let format = content_type
+        .to_str()
+        .ok()
+        .and_then(|content_type| content_type.strip_prefix(GRPC))
+        .and_then(|format| format.strip_prefix('+'))
+        .unwrap_or("proto");
+    if let Ok(connect) = HeaderValue::try_from(format!("application/{}", format)) {
+        headers.insert(header::CONTENT_TYPE, connect);
+    }
+    headers.insert(CONNECT_PROTOCOL_VERSION, HeaderValue::from_static("1"));
+    headers.remove(header::TE);
+
+    if let Some(timeout) = headers.remove(GRPC_TIMEOUT) {
+        if let Some(timeout) = timeout_millis(&timeout) {
+            headers.insert(CONNECT_TIMEOUT, timeout.into());
+        }
+    }
+    let encoding = headers.remove(GRPC_ENCODING);
+    if let (Some(encoding), true) = (encoding, compressed) {
+        headers.insert(header::CONTENT_ENCODING, encoding);
+    }
+    if let Some(encodings) = headers.remove(GRPC_ACCEPT_ENCODING) {
+        headers.insert(header::ACCEPT_ENCODING, encodings);
+    }
+    content_type
+}
+
+/// The milliseconds of a `grpc-timeout` header, rounded up.
+fn timeout_millis(timeout: &HeaderValue) -> Option<u64> {
+    let timeout = timeout.to_str().ok()?;
+    if timeout.is_empty() {
+        return None;
+    }
+    let (value, unit) = timeout.split_at(timeout.len() - 1);
+    let value = value.parse::<u64>().ok()?;
+    let unit = match unit {
+        "H" => 60 * 60 * 1_000_000_000,
+        "M" => 60 * 1_000_000_000,
+        "S" => 1_000_000_000,
+        "m" => 1_000_000,
+        "u" => 1_000,
+        "n" => 1,
+        _ => return None,
+    };
+    let nanos = value.saturating_mul(unit);
+    Some(nanos / 1_000_000 + u64::from(nanos % 1_000_000 != 0))
+}
+
+/// Turn the Connect response of a call with the gRPC `content_type` into a
+/// gRPC response.
+async fn coerce_response<B>(
+    res: Response<B>,
+    content_type: HeaderValue,
+) -> Result<Response<BoxBody>, BoxError>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    let (parts, body) = res.into_parts();
+    let body = hyper::body::to_bytes(body).await.map_err(Into::into)?;
+    let (mut headers, mut trailers) = split_trailers(parts.headers);
+    headers.remove(header::CONTENT_LENGTH);
+    headers.remove(header::CONTENT_TYPE);
+
+    if parts.status != StatusCode::OK {
+        headers.extend(trailers);
+        return Ok(crate::error::status(parts.status, &body, headers).to_http());
+    }
+
+    let compressed = match headers.remove(header::CONTENT_ENCODING) {
+        Some(encoding) => {
+            headers.insert(GRPC_ENCODING, encoding);
+            true
+        }
+        None => false,
+    };
+    headers.insert(header::CONTENT_TYPE, content_type);
+    trailers.insert("grpc-status", HeaderValue::from_static("0"));
+
+    let mut message = BytesMut::with_capacity(GRPC_HEADER_SIZE + body.len());
+    message.put_u8(compressed as u8);
+    message.put_u32(body.len() as u32);
+    message.put(body);
+    let body = UnaryBody {
+        message: Some(message.freeze()),
+        trailers: Some(trailers),
+    };
+
+    let mut res = Response::new(BoxBody::new(body));
+    *res.headers_mut() = headers;
+    Ok(res)
+}
+
+/// Split the headers of a unary response from its trailers, the headers
+/// with the `trailer-` prefix.
+fn split_trailers(all: HeaderMap) -> (HeaderMap, HeaderMap) {
+    let mut headers = HeaderMap::new();
+    let mut trailers = HeaderMap::new();
+    let mut name = None;
+    // The name is only yielded with the first value of each header.
+    for (next, value) in all {
+        if let Some(next) = next {
+            name = Some(next);
+        }
+        let Some(name) = &name else { continue };
+        let trailer = name
+            .as_str()
+            .strip_prefix(TRAILER_PREFIX)
+            .and_then(|trailer| HeaderName::from_bytes(trailer.as_bytes()).ok());
+        match trailer {
+            Some(trailer) => trailers.append(trailer, value),
+            None => headers.append(name.clone(), value),
+        };
+    }
+    (headers, trailers)
+}
+
+/// The body of a unary gRPC response: its message, then its trailers.
+struct UnaryBody {
+    message: Option<Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+impl Body for UnaryBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.message.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.trailers.take()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.message.is_none() && self.trailers.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tonic::Code;
+    use tower::{service_fn, util::BoxCloneService, ServiceExt};
+
+    type Requests = Arc<Mutex<Vec<(Version, HeaderMap, Bytes)>>>;
+    type Connect = BoxCloneService<Request<hyper::Body>, Response<hyper::Body>, BoxError>;
+
+    /// A Connect server answering with `res`, recording the requests it
+    /// receives.
+    fn connect(res: fn() -> Response<hyper::Body>) -> (ConnectClientService<Connect>, Requests) {
+        let requests = Requests::default();
+        let recorded = requests.clone();
+        let svc = service_fn(move |req: Request<hyper::Body>| {
+            let requests = recorded.clone();
+            async move {
+                let (parts, body) = req.into_parts();
+                let body = hyper::body::to_bytes(body).await?;
+                requests
+                    .lock()
+                    .unwrap()
+                    .push((parts.version, parts.headers, body));
+                Ok::<_, BoxError>(res())
+            }
+        });
+        (
+            ConnectClientService::new(BoxCloneService::new(svc)),
+            requests,
+        )
+    }
+
+    fn grpc_request(content_type: &'static str) -> Request<hyper::Body> {
+        Request::post("http://localhost/test.Test/Call")
+            .version(Version::HTTP_2)
+            .header("content-type", content_type)
+            .header("te", "trailers")
+            .header("grpc-timeout", "1500u")
+            .header("grpc-accept-encoding", "gzip")
+            .header("x-id", "1")
+            .body(hyper::Body::from(&b"\0\0\0\0\x02hi"[..]))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn makes_unary_calls() {
+        let (svc, requests) = connect(|| {
+            Response::builder()
+                .header("content-type", "application/json")
+                .header("x-id", "2")
+                .header("trailer-x-count", "3")
+                .body(hyper::Body::from("{}"))
+                .unwrap()
+        });
+
+        let res = svc
+            .oneshot(grpc_request("application/grpc+json"))
+            .await
+            .unwrap();
+        let (version, headers, body) = requests.lock().unwrap().remove(0);
+        assert_eq!(version, Version::HTTP_11);
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers["connect-protocol-version"], "1");
+        assert_eq!(headers["connect-timeout-ms"], "2");
+        assert_eq!(headers["accept-encoding"], "gzip");
+        assert_eq!(headers["x-id"], "1");
+        assert!(!headers.contains_key("te"));
+        assert!(!headers.contains_key("grpc-timeout"));
+        assert_eq!(body, "hi");
+
+        assert_eq!(res.headers()["content-type"], "application/grpc+json");
+        assert_eq!(res.headers()["x-id"], "2");
+        let mut body = res.into_body();
+        assert_eq!(body.data().await.unwrap().unwrap(), &b"\0\0\0\0\x02{}"[..]);
+        assert!(body.data().await.is_none());
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers["x-count"], "3");
+        assert_eq!(trailers["grpc-status"], "0");
+    }
+
+    #[tokio::test]
+    async fn returns_errors_as_statuses() {
+        let (svc, _) = connect(|| {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .header("content-type", "application/json")
+                .header("trailer-x-id", "2")
+                .body(hyper::Body::from(
+                    r#"{"code": "not_found", "message": "no such thing"}"#,
+                ))
+                .unwrap()
+        });
+
+        let res = svc.oneshot(grpc_request("application/grpc")).await.unwrap();
+        let status = Status::from_header_map(res.headers()).unwrap();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "no such thing");
+        assert_eq!(status.metadata().get("x-id").unwrap(), "2");
+    }
+
+    #[tokio::test]
+    async fn fails_streaming_calls() {
+        let (svc, requests) = connect(|| Response::new(hyper::Body::empty()));
+
+        let req = Request::post("http://localhost/test.Test/Stream")
+            .body(hyper::Body::from(&b"\0\0\0\0\x01a\0\0\0\0\x01b"[..]))
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+        let status = Status::from_header_map(res.headers()).unwrap();
+        assert_eq!(status.code(), Code::Unimplemented);
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn converts_timeouts() {
+        for (grpc, millis) in [("1H", 3_600_000), ("2S", 2_000), ("5m", 5), ("1n", 1)] {
+            let timeout = HeaderValue::from_static(grpc);
+            assert_eq!(timeout_millis(&timeout), Some(millis), "{}", grpc);
+        }
+        assert_eq!(timeout_millis(&HeaderValue::from_static("1x")), None);
+    }
+}
@@ -0,0 +1,169 @@
+use base64::{
+    alphabet,
+    engine::{
+        general_purpose::{GeneralPurpose, GeneralPurposeConfig},
+        DecodePaddingMode,
+    },
+    Engine as _,
+};
+use bytes::Bytes;
+use http::{HeaderMap, StatusCode};
+use prost::Message;
+use serde_json::Value;
+use tonic::{metadata::MetadataMap, Code, Status};
+
+/// Connect encodes the values of details without padding, which decoding
+/// tolerates either way.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// A `google.rpc.Status`, the `grpc-status-details-bin` of statuses.
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<Any>,
+}
+
+/// A `google.protobuf.Any`.
+#[derive(Clone, PartialEq, Message)]
+struct Any {
+    #[prost(string, tag = "1")]
+    type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    value: Vec<u8>,
+}
+
+/// The status of a response with the HTTP `status` other than OK, from the
+/// Connect error of its `body`, and the `metadata` of its headers.
+///
+/// Bodies without a Connect error get the code of their HTTP status.
+pub(crate) fn status(status: StatusCode, body: &[u8], metadata: HeaderMap) -> Status {
+    let metadata = MetadataMap::from_headers(metadata);
+    let error = serde_json::from_slice::<Value>(body).ok();
+    let Some(error) = error.as_ref().and_then(Value::as_object) else {
+        let message = status.canonical_reason().unwrap_or_default();
+        return Status::with_metadata(http_code(status), message, metadata);
+    };
+
+    let code = error
+        .get("code")
+        .and_then(Value::as_str)
+        .map_or_else(|| http_code(status), code);
+    let message = error
+        .get("message")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let details = error
+        .get("details")
+        .and_then(Value::as_array)
+        .map(|details| details.iter().filter_map(detail).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if details.is_empty() {
+        return Status::with_metadata(code, message, metadata);
+    }
+
+    let details = RpcStatus {
+        code: code.into(),
+        message: message.to_owned(),
+        details,
+    };
+    let details = Bytes::from(details.encode_to_vec());
+    Status::with_details_and_metadata(code, message, details, metadata)
+}
+
+/// A detail of a Connect error, as `{"type": ..., "value": ...}` with the
+/// fully-qualified name of the message and its base64 encoding.
+fn detail(detail: &Value) -> Option<Any> {
+    let name = detail.get("type")?.as_str()?;
+    let value = BASE64.decode(detail.get("value")?.as_str()?).ok()?;
+    Some(Any {
+        type_url: format!("type.googleapis.com/{}", name),
+        value,
+    })
+}
+
+/// The code of the name of a Connect error code.
+fn code(name: &str) -> Code {
+    match name {
+        "canceled" => Code::Cancelled,
+        "unknown" => Code::Unknown,
+        "invalid_argument" => Code::InvalidArgument,
+        "deadline_exceeded" => Code::DeadlineExceeded,
+        "not_found" => Code::NotFound,
+        "already_exists" => Code::AlreadyExists,
+        "permission_denied" => Code::PermissionDenied,
+        "resource_exhausted" => Code::ResourceExhausted,
+        "failed_precondition" => Code::FailedPrecondition,
+        "aborted" => Code::Aborted,
+        "out_of_range" => Code::OutOfRange,
+        "unimplemented" => Code::Unimplemented,
+        "internal" => Code::Internal,
+        "unavailable" => Code::Unavailable,
+        "data_loss" => Code::DataLoss,
+        "unauthenticated" => Code::Unauthenticated,
+        _ => Code::Unknown,
+    }
+}
+
+/// The code of responses with the HTTP `status` but no Connect error, such
+/// as those of proxies.
+fn http_code(status: StatusCode) -> Code {
+    match status {
+        StatusCode::BAD_REQUEST => Code::Internal,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::Unimplemented,
+        StatusCode::TOO_MANY_REQUESTS
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => Code::Unavailable,
+        _ => Code::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_errors() {
+        let body = br#"{
+            "code": "failed_precondition",
+            "message": "not yet",
+            "details": [{"type": "google.rpc.RetryInfo", "value": "CgIIAQ"}]
+        }"#;
+        let mut metadata = HeaderMap::new();
+        metadata.insert("x-id", "1".parse().unwrap());
+        let status = status(StatusCode::BAD_REQUEST, body, metadata);
+
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(status.message(), "not yet");
+        assert_eq!(status.metadata().get("x-id").unwrap(), "1");
+        let details = RpcStatus::decode(status.details()).unwrap();
+        assert_eq!(details.code, Code::FailedPrecondition as i32);
+        assert_eq!(
+            details.details,
+            [Any {
+                type_url: "type.googleapis.com/google.rpc.RetryInfo".to_owned(),
+                value: vec![0x0a, 0x02, 0x08, 0x01],
+            }]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_http_statuses() {
+        let status = status(StatusCode::SERVICE_UNAVAILABLE, b"<html>", HeaderMap::new());
+
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.message(), "Service Unavailable");
+        assert!(status.details().is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+//! [Connect protocol] clients for `tonic`.
+//!
+//! The [`ConnectClientLayer`] makes the unary calls of generated clients
+//! with the Connect protocol instead of gRPC, as plain HTTP/1.1 `POST`
+//! requests that infrastructure blocking HTTP/2 lets through, against
+//! servers such as those of `connect-go`. The generated methods keep their
+//! signatures, only the service they call through changes:
+//!
+//! ```ignore
+//! use tonic_connect::ConnectClientService;
+//!
+//! let svc = ConnectClientService::new(hyper::Client::new());
+//! let mut client = GreeterClient::with_origin(svc, "http://[::1]:8080".parse()?);
+//!
+//! let response = client.say_hello(HelloRequest::default()).await?;
+//! ```
+//!
+//! Messages are sent as `application/proto`, or as `application/json` with
+//! the `tonic::codec::JsonCodec` codec of the `json` feature of `tonic`.
+//! Compression, deadlines and metadata carry over to their Connect headers,
+//! and Connect errors, with their details, back to the [`Status`] of calls.
+//!
+//! Streaming calls aren't supported, and fail as unimplemented.
+//!
+//! [Connect protocol]: https://connectrpc.com/docs/protocol
+//! [`Status`]: tonic::Status
+
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
+)]
+#![doc(html_root_url = "https://docs.rs/tonic-connect/0.11.0")]
+#![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
+
+mod client;
+mod error;
+
+pub use client::{ConnectClientLayer, ConnectClientService};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
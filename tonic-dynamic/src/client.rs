@@ -0,0 +1,339 @@
+use crate::DynamicCodec;
+use bytes::Bytes;
+use http::uri::PathAndQuery;
+use http_body::Body;
+use prost_reflect::{
+    DescriptorError, DescriptorPool, DynamicMessage, MethodDescriptor, ReflectMessage,
+};
+use tonic::{
+    body::BoxBody,
+    client::{Grpc, GrpcService},
+    codec::{CompressionEncoding, Streaming},
+    Code, IntoRequest, IntoStreamingRequest, Response, Status,
+};
+
+type StdError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// A client of the methods of a [`DescriptorPool`], called with
+/// [`DynamicMessage`]s.
+///
+/// The methods are those of the pool, found by their full names with
+/// [`method`](Self::method), and called like the methods of generated
+/// clients, each client call checking that the kind of the method matches
+/// it.
+#[derive(Debug, Clone)]
+pub struct DynamicClient<T> {
+    inner: Grpc<T>,
+    pool: DescriptorPool,
+}
+
+impl<T> DynamicClient<T>
+where
+    T: GrpcService<BoxBody>,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    /// Create a client of the methods of `pool`.
+    pub fn new(inner: T, pool: DescriptorPool) -> Self {
+        DynamicClient {
+            inner: Grpc::new(inner),
+            pool,
+        }
+    }
+
+    /// Create a client of the methods of an encoded
+    /// `prost_types::FileDescriptorSet`.
+    pub fn from_file_descriptor_set(inner: T, encoded: &[u8]) -> Result<Self, DescriptorError> {
+        Ok(Self::new(inner, DescriptorPool::decode(encoded)?))
+    }
+
+    /// The descriptors of the methods of the client.
+    pub fn pool(&self) -> &DescriptorPool {
+        &self.pool
+    }
+
+    /// The method of the full name `name`, such as
+    /// `helloworld.Greeter.SayHello`, or of the path
+    /// `/helloworld.Greeter/SayHello`.
+    pub fn method(&self, name: &str) -> Result<MethodDescriptor, Status> {
+        let path = name.strip_prefix('/').unwrap_or(name);
+        let not_found = || Status::not_found(format!("method `{}` not found", name));
+        let (service, method) = path.rsplit_once(['/', '.']).ok_or_else(not_found)?;
+        self.pool
+            .get_service_by_name(service)
+            .and_then(|service| service.methods().find(|m| m.name() == method))
+            .ok_or_else(not_found)
+    }
+
+    /// Compress requests with the given encoding.
+    ///
+    /// This requires the server to support it otherwise it might respond with an
+    /// error.
+    #[must_use]
+    pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.inner = self.inner.send_compressed(encoding);
+        self
+    }
+
+    /// Enable decompressing responses.
+    #[must_use]
+    pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.inner = self.inner.accept_compressed(encoding);
+        self
+    }
+
+    /// Limits the maximum size of a decoded message.
+    ///
+    /// Default: `4MB`
+    #[must_use]
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.inner = self.inner.max_decoding_message_size(limit);
+        self
+    }
+
+    /// Limits the maximum size of an encoded message.
+    ///
+    /// Default: `usize::MAX`
+    #[must_use]
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.inner = self.inner.max_encoding_message_size(limit);
+        self
+    }
+
+    /// Call a unary method.
+    pub async fn unary(
+        &mut self,
+        method: &MethodDescriptor,
+        request: impl IntoRequest<DynamicMessage>,
+    ) -> Result<Response<DynamicMessage>, Status> {
+        let (path, codec) = self.prepare(method, (false, false)).await?;
+        let request = request.into_request();
+        check_input(method, request.get_ref())?;
+        self.inner.unary(request, path, codec).await
+    }
+
+    /// Call a server streaming method.
+    pub async fn server_streaming(
+        &mut self,
+        method: &MethodDescriptor,
+        request: impl IntoRequest<DynamicMessage>,
+    ) -> Result<Response<Streaming<DynamicMessage>>, Status> {
+        let (path, codec) = self.prepare(method, (false, true)).await?;
+        let request = request.into_request();
+        check_input(method, request.get_ref())?;
+        self.inner.server_streaming(request, path, codec).await
+    }
+
+    /// Call a client streaming method.
+    pub async fn client_streaming(
+        &mut self,
+        method: &MethodDescriptor,
+        request: impl IntoStreamingRequest<Message = DynamicMessage>,
+    ) -> Result<Response<DynamicMessage>, Status> {
+        let (path, codec) = self.prepare(method, (true, false)).await?;
+        self.inner
+            .client_streaming(request.into_streaming_request(), path, codec)
+            .await
+    }
+
+    /// Call a bi-directional streaming method.
+    pub async fn streaming(
+        &mut self,
+        method: &MethodDescriptor,
+        request: impl IntoStreamingRequest<Message = DynamicMessage>,
+    ) -> Result<Response<Streaming<DynamicMessage>>, Status> {
+        let (path, codec) = self.prepare(method, (true, true)).await?;
+        self.inner
+            .streaming(request.into_streaming_request(), path, codec)
+            .await
+    }
+
+    /// The path and the codec of a call of `method`, once the client is
+    /// ready, if the method streams as the call does.
+    async fn prepare(
+        &mut self,
+        method: &MethodDescriptor,
+        streaming: (bool, bool),
+    ) -> Result<(PathAndQuery, DynamicCodec), Status> {
+        if (method.is_client_streaming(), method.is_server_streaming()) != streaming {
+            let kind = match (method.is_client_streaming(), method.is_server_streaming()) {
+                (false, false) => "unary",
+                (true, false) => "client streaming",
+                (false, true) => "server streaming",
+                (true, true) => "bi-directional streaming",
+            };
+            return Err(Status::invalid_argument(format!(
+                "method `{}` is {}",
+                method.full_name(),
+                kind
+            )));
+        }
+
+        let path = format!("/{}/{}", method.parent_service().full_name(), method.name());
+        let path = PathAndQuery::try_from(path)
+            .map_err(|e| Status::invalid_argument(format!("invalid method path: {}", e)))?;
+
+        self.inner.ready().await.map_err(|e| {
+            Status::new(
+                Code::Unknown,
+                format!("Service was not ready: {}", e.into()),
+            )
+        })?;
+
+        Ok((path, DynamicCodec::new(method.output())))
+    }
+}
+
+fn check_input(method: &MethodDescriptor, message: &DynamicMessage) -> Result<(), Status> {
+    if message.descriptor() == method.input() {
+        return Ok(());
+    }
+    Err(Status::invalid_argument(format!(
+        "method `{}` takes `{}` messages, not `{}`",
+        method.full_name(),
+        method.input().full_name(),
+        message.descriptor().full_name()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_reflect::Value;
+    use prost_types::{
+        field_descriptor_proto::Type, DescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+        FileDescriptorSet, MethodDescriptorProto, ServiceDescriptorProto,
+    };
+    use std::{convert::Infallible, sync::OnceLock};
+
+    /// A `test.Echoer` service of `Echo` messages, with a unary `Say` and a
+    /// server streaming `Repeat` method.
+    fn pool() -> DescriptorPool {
+        static POOL: OnceLock<DescriptorPool> = OnceLock::new();
+        POOL.get_or_init(build_pool).clone()
+    }
+
+    fn build_pool() -> DescriptorPool {
+        let string = |name: &str, number| FieldDescriptorProto {
+            name: Some(name.to_owned()),
+            number: Some(number),
+            r#type: Some(Type::String.into()),
+            ..Default::default()
+        };
+        let method = |name: &str, server_streaming| MethodDescriptorProto {
+            name: Some(name.to_owned()),
+            input_type: Some(".test.Echo".to_owned()),
+            output_type: Some(".test.Echo".to_owned()),
+            server_streaming: Some(server_streaming),
+            ..Default::default()
+        };
+        let file = FileDescriptorProto {
+            name: Some("echo.proto".to_owned()),
+            package: Some("test".to_owned()),
+            message_type: vec![
+                DescriptorProto {
+                    name: Some("Echo".to_owned()),
+                    field: vec![string("text", 1)],
+                    ..Default::default()
+                },
+                DescriptorProto {
+                    name: Some("Other".to_owned()),
+                    ..Default::default()
+                },
+            ],
+            service: vec![ServiceDescriptorProto {
+                name: Some("Echoer".to_owned()),
+                method: vec![method("Say", false), method("Repeat", true)],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_owned()),
+            ..Default::default()
+        };
+        DescriptorPool::from_file_descriptor_set(FileDescriptorSet { file: vec![file] }).unwrap()
+    }
+
+    fn echo(text: &str) -> DynamicMessage {
+        let mut message = DynamicMessage::new(pool().get_message_by_name("test.Echo").unwrap());
+        message.set_field_by_name("text", Value::String(text.to_owned()));
+        message
+    }
+
+    fn text(message: &DynamicMessage) -> String {
+        message
+            .get_field_by_name("text")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    fn client(
+    ) -> DynamicClient<impl GrpcService<BoxBody, ResponseBody = BoxBody, Error = Infallible>> {
+        let server = tower::service_fn(|request: http::Request<BoxBody>| async move {
+            let echo = pool().get_message_by_name("test.Echo").unwrap();
+            let mut grpc = tonic::server::Grpc::new(DynamicCodec::new(echo));
+            let response = match request.uri().path() {
+                "/test.Echoer/Say" => {
+                    let say = tower::service_fn(|request: tonic::Request<DynamicMessage>| async {
+                        Ok(Response::new(request.into_inner()))
+                    });
+                    grpc.unary(say, request).await
+                }
+                "/test.Echoer/Repeat" => {
+                    let repeat = tower::service_fn(|request: tonic::Request<DynamicMessage>| {
+                        let message = request.into_inner();
+                        let messages = vec![Ok(message.clone()), Ok(message)];
+                        async { Ok(Response::new(tokio_stream::iter(messages))) }
+                    });
+                    grpc.server_streaming(repeat, request).await
+                }
+                _ => unreachable!(),
+            };
+            Ok::<_, Infallible>(response)
+        });
+        DynamicClient::new(server, pool())
+    }
+
+    #[tokio::test]
+    async fn calls_methods_by_name() {
+        let mut client = client();
+
+        let say = client.method("test.Echoer.Say").unwrap();
+        let response = client.unary(&say, echo("hello")).await.unwrap();
+        assert_eq!(text(response.get_ref()), "hello");
+
+        let repeat = client.method("/test.Echoer/Repeat").unwrap();
+        let mut responses = client
+            .server_streaming(&repeat, echo("again"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(text(&responses.message().await.unwrap().unwrap()), "again");
+        assert_eq!(text(&responses.message().await.unwrap().unwrap()), "again");
+        assert!(responses.message().await.unwrap().is_none());
+
+        let status = client.method("test.Echoer.Shout").unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_calls() {
+        let mut client = client();
+
+        let repeat = client.method("test.Echoer.Repeat").unwrap();
+        let status = client.unary(&repeat, echo("hello")).await.unwrap_err();
+        assert_eq!(
+            status.message(),
+            "method `test.Echoer.Repeat` is server streaming"
+        );
+
+        let say = client.method("test.Echoer.Say").unwrap();
+        let other = DynamicMessage::new(client.pool().get_message_by_name("test.Other").unwrap());
+        let status = client.unary(&say, other).await.unwrap_err();
+        assert_eq!(
+            status.message(),
+            "method `test.Echoer.Say` takes `test.Echo` messages, not `test.Other`"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use prost::Message;
+use prost_reflect::{DynamicMessage, MessageDescriptor};
+use tonic::{
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    Status,
+};
+
+/// A [`Codec`] of the protobuf encoding of [`DynamicMessage`]s, decoding
+/// the messages of a descriptor.
+///
+/// Clients decode the output messages of their methods, and servers the
+/// input messages.
+#[derive(Debug, Clone)]
+pub struct DynamicCodec {
+    decode: MessageDescriptor,
+}
+
+impl DynamicCodec {
+    /// Create a codec decoding messages of the `decode` descriptor.
+    pub fn new(decode: MessageDescriptor) -> Self {
+        DynamicCodec { decode }
+    }
+}
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        DynamicEncoder(())
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder(self.decode.clone())
+    }
+}
+
+/// A [`Encoder`] of [`DynamicMessage`]s.
+#[derive(Debug, Clone)]
+pub struct DynamicEncoder(());
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(buf)
+            .expect("Message only errors if not enough space");
+        Ok(())
+    }
+}
+
+/// A [`Decoder`] of the [`DynamicMessage`]s of a descriptor.
+#[derive(Debug, Clone)]
+pub struct DynamicDecoder(MessageDescriptor);
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        DynamicMessage::decode(self.0.clone(), buf)
+            .map(Some)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
@@ -0,0 +1,51 @@
+//! Dynamic gRPC clients for `tonic`, driven by [`prost_reflect`]
+//! descriptors.
+//!
+//! A [`DynamicClient`] calls the methods of a [`DescriptorPool`], such as
+//! that of an encoded `FileDescriptorSet`, with [`DynamicMessage`] requests
+//! and responses. Gateways, CLIs and test tools
+//! can so call any service, without generating its code at build time.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # const FILE_DESCRIPTOR_SET: &[u8] = &[];
+//! use prost_reflect::{DynamicMessage, Value};
+//! use tonic_dynamic::DynamicClient;
+//!
+//! let channel = tonic::transport::Channel::from_static("http://[::1]:50051").connect_lazy();
+//! let mut client = DynamicClient::from_file_descriptor_set(channel, FILE_DESCRIPTOR_SET)?;
+//!
+//! let method = client.method("helloworld.Greeter.SayHello")?;
+//! let mut request = DynamicMessage::new(method.input());
+//! request.set_field_by_name("name", Value::String("tonic".to_owned()));
+//!
+//! let response = client.unary(&method, request).await?;
+//! println!("{:?}", response.get_ref().get_field_by_name("message"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DescriptorPool`]: prost_reflect::DescriptorPool
+//! [`DynamicMessage`]: prost_reflect::DynamicMessage
+
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
+)]
+#![doc(html_root_url = "https://docs.rs/tonic-dynamic/0.11.0")]
+#![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
+
+mod client;
+mod codec;
+
+pub use client::DynamicClient;
+pub use codec::{DynamicCodec, DynamicDecoder, DynamicEncoder};
+pub use prost_reflect;
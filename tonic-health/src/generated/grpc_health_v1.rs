@@ -100,6 +100,26 @@ pub mod health_client {
         {
             HealthClient::new(InterceptedService::new(inner, interceptor))
         }
+        pub fn with_async_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> HealthClient<AsyncInterceptedService<T, F>>
+        where
+            F: tonic::service::AsyncInterceptor,
+            T::ResponseBody: Default,
+            T: Clone
+                + tonic::codegen::Service<
+                    http::Request<tonic::body::BoxBody>,
+                    Response = http::Response<
+                        <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                    >,
+                >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            HealthClient::new(AsyncInterceptedService::new(inner, interceptor))
+        }
         /// Compress requests with the given encoding.
         ///
         /// This requires the server to support it otherwise it might respond with an
@@ -109,6 +129,20 @@ pub mod health_client {
             self.inner = self.inner.send_compressed(encoding);
             self
         }
+        /// Send the requests smaller than `bytes` uncompressed.
+        ///
+        /// Default: `0`
+        #[must_use]
+        pub fn send_compressed_min_size(mut self, bytes: usize) -> Self {
+            self.inner = self.inner.send_compressed_min_size(bytes);
+            self
+        }
+        /// Compress requests at the given level.
+        #[must_use]
+        pub fn send_compression_level(mut self, level: CompressionLevel) -> Self {
+            self.inner = self.inner.send_compression_level(level);
+            self
+        }
         /// Enable decompressing responses.
         #[must_use]
         pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
@@ -247,6 +281,8 @@ pub mod health_server {
         inner: _Inner<T>,
         accept_compression_encodings: EnabledCompressionEncodings,
         send_compression_encodings: EnabledCompressionEncodings,
+        send_compressed_min_size: usize,
+        send_compression_level: CompressionLevel,
         max_decoding_message_size: Option<usize>,
         max_encoding_message_size: Option<usize>,
     }
@@ -261,6 +297,8 @@ pub mod health_server {
                 inner,
                 accept_compression_encodings: Default::default(),
                 send_compression_encodings: Default::default(),
+                send_compressed_min_size: 0,
+                send_compression_level: Default::default(),
                 max_decoding_message_size: None,
                 max_encoding_message_size: None,
             }
@@ -274,6 +312,15 @@ pub mod health_server {
         {
             InterceptedService::new(Self::new(inner), interceptor)
         }
+        pub fn with_async_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> AsyncInterceptedService<Self, F>
+        where
+            F: tonic::service::AsyncInterceptor,
+        {
+            AsyncInterceptedService::new(Self::new(inner), interceptor)
+        }
         /// Enable decompressing requests with the given encoding.
         #[must_use]
         pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
@@ -286,6 +333,20 @@ pub mod health_server {
             self.send_compression_encodings.enable(encoding);
             self
         }
+        /// Send the responses smaller than `bytes` uncompressed.
+        ///
+        /// Default: `0`
+        #[must_use]
+        pub fn send_compressed_min_size(mut self, bytes: usize) -> Self {
+            self.send_compressed_min_size = bytes;
+            self
+        }
+        /// Compress responses at the given level.
+        #[must_use]
+        pub fn send_compression_level(mut self, level: CompressionLevel) -> Self {
+            self.send_compression_level = level;
+            self
+        }
         /// Limits the maximum size of a decoded message.
         ///
         /// Default: `4MB`
@@ -346,6 +407,8 @@ pub mod health_server {
                     }
                     let accept_compression_encodings = self.accept_compression_encodings;
                     let send_compression_encodings = self.send_compression_encodings;
+                    let send_compressed_min_size = self.send_compressed_min_size;
+                    let send_compression_level = self.send_compression_level;
                     let max_decoding_message_size = self.max_decoding_message_size;
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
@@ -358,6 +421,8 @@ pub mod health_server {
                                 accept_compression_encodings,
                                 send_compression_encodings,
                             )
+                            .send_compressed_min_size(send_compressed_min_size)
+                            .send_compression_level(send_compression_level)
                             .apply_max_message_size_config(
                                 max_decoding_message_size,
                                 max_encoding_message_size,
@@ -393,6 +458,8 @@ pub mod health_server {
                     }
                     let accept_compression_encodings = self.accept_compression_encodings;
                     let send_compression_encodings = self.send_compression_encodings;
+                    let send_compressed_min_size = self.send_compressed_min_size;
+                    let send_compression_level = self.send_compression_level;
                     let max_decoding_message_size = self.max_decoding_message_size;
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
@@ -405,6 +472,8 @@ pub mod health_server {
                                 accept_compression_encodings,
                                 send_compression_encodings,
                             )
+                            .send_compressed_min_size(send_compressed_min_size)
+                            .send_compression_level(send_compression_level)
                             .apply_max_message_size_config(
                                 max_decoding_message_size,
                                 max_encoding_message_size,
@@ -436,6 +505,8 @@ pub mod health_server {
                 inner,
                 accept_compression_encodings: self.accept_compression_encodings,
                 send_compression_encodings: self.send_compression_encodings,
+                send_compressed_min_size: self.send_compressed_min_size,
+                send_compression_level: self.send_compression_level,
                 max_decoding_message_size: self.max_decoding_message_size,
                 max_encoding_message_size: self.max_encoding_message_size,
             }
@@ -3,11 +3,15 @@
 use crate::pb::health_server::{Health, HealthServer};
 use crate::pb::{HealthCheckRequest, HealthCheckResponse};
 use crate::ServingStatus;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use tokio::sync::{watch, RwLock};
 use tokio_stream::Stream;
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::codegen::{Bytes, Context, Poll, Service};
 #[cfg(feature = "transport")]
 use tonic::server::NamedService;
 use tonic::{Request, Response, Status};
@@ -30,12 +34,49 @@ pub fn health_reporter() -> (HealthReporter, HealthServer<impl Health>) {
 
 type StatusPair = (watch::Sender<ServingStatus>, watch::Receiver<ServingStatus>);
 
+/// How the status of a service is derived from the statuses of its
+/// dependencies.
+///
+/// See [`HealthReporter::set_service_dependencies`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Aggregation {
+    /// The service is `Serving` while all its dependencies are, and
+    /// `NotServing` while any of them isn't.
+    All,
+    /// The service is `Serving` while any of its dependencies is, and
+    /// `NotServing` while none of them is.
+    Any,
+}
+
+impl Aggregation {
+    fn status(self, mut dependencies: impl Iterator<Item = ServingStatus>) -> ServingStatus {
+        let serving = match self {
+            Aggregation::All => dependencies.all(|status| status == ServingStatus::Serving),
+            Aggregation::Any => dependencies.any(|status| status == ServingStatus::Serving),
+        };
+        if serving {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Dependencies {
+    services: Vec<String>,
+    aggregation: Aggregation,
+}
+
 /// A handle providing methods to update the health status of gRPC services. A
 /// `HealthReporter` is connected to a `HealthServer` which serves the statuses
 /// over the `grpc.health.v1.Health` service.
 #[derive(Clone, Debug)]
 pub struct HealthReporter {
     statuses: Arc<RwLock<HashMap<String, StatusPair>>>,
+    /// The dependencies of the services whose status is derived, only
+    /// locked while `statuses` is locked for writing.
+    dependencies: Arc<Mutex<HashMap<String, Dependencies>>>,
 }
 
 impl HealthReporter {
@@ -45,7 +86,10 @@ impl HealthReporter {
 
         let statuses = Arc::new(RwLock::new(HashMap::from([server_status])));
 
-        HealthReporter { statuses }
+        HealthReporter {
+            statuses,
+            dependencies: Arc::default(),
+        }
     }
 
     /// Sets the status of the service implemented by `S` to `Serving`. This notifies any watchers
@@ -76,30 +120,199 @@ impl HealthReporter {
 
     /// Sets the status of the service with `service_name` to `status`. This notifies any watchers
     /// if there is a change in status.
+    ///
+    /// The status of the service is no longer derived from its dependencies, if it was.
     pub async fn set_service_status<S>(&mut self, service_name: S, status: ServingStatus)
     where
         S: AsRef<str>,
     {
         let service_name = service_name.as_ref();
         let mut writer = self.statuses.write().await;
-        match writer.get(service_name) {
-            Some((tx, _)) => {
-                // We only ever hand out clones of the receiver, so the originally-created
-                // receiver should always be present, only being dropped when clearing the
-                // service status. Consequently, `tx.send` should not fail, making use
-                // of `expect` here safe.
-                tx.send(status).expect("channel should not be closed");
-            }
-            None => {
-                writer.insert(service_name.to_string(), watch::channel(status));
-            }
+        let mut dependencies = self.lock_dependencies();
+        dependencies.remove(service_name);
+
+        set_status(&mut writer, service_name, status);
+        propagate(&mut writer, &dependencies, service_name);
+    }
+
+    /// Derives the status of the service with `service_name` from the statuses of the
+    /// `dependencies` services, with `aggregation`. This notifies any watchers of the service
+    /// whenever its derived status changes.
+    ///
+    /// Services that aren't registered count as not serving. Dependencies may themselves be
+    /// derived from other services, such as an overall server health of `""` derived from the
+    /// health of a database and of a cache:
+    ///
+    /// ```
+    /// # async fn f() {
+    /// use tonic_health::server::{health_reporter, Aggregation};
+    /// use tonic_health::ServingStatus;
+    ///
+    /// let (mut reporter, _service) = health_reporter();
+    /// reporter
+    ///     .set_service_dependencies("", ["database", "cache"], Aggregation::All)
+    ///     .await;
+    /// reporter.set_service_status("database", ServingStatus::Serving).await;
+    /// # }
+    /// ```
+    ///
+    /// The statuses of services depending on each other in a cycle are only derived once per
+    /// status change.
+    pub async fn set_service_dependencies<S, D>(
+        &mut self,
+        service_name: S,
+        dependencies: D,
+        aggregation: Aggregation,
+    ) where
+        S: AsRef<str>,
+        D: IntoIterator,
+        D::Item: Into<String>,
+    {
+        let service_name = service_name.as_ref();
+        let services = dependencies.into_iter().map(Into::into).collect();
+        let mut writer = self.statuses.write().await;
+        let mut dependencies = self.lock_dependencies();
+
+        let derived = Dependencies {
+            services,
+            aggregation,
         };
+        let status = derived.status(&writer);
+        dependencies.insert(service_name.to_string(), derived);
+
+        if set_status(&mut writer, service_name, status) {
+            propagate(&mut writer, &dependencies, service_name);
+        }
+    }
+
+    /// Sets the status of all the registered services, including the overall server health, to
+    /// `NotServing`. This notifies any watchers of the services that were serving.
+    pub async fn set_all_not_serving(&mut self) {
+        let writer = self.statuses.write().await;
+        for (tx, _) in writer.values() {
+            tx.send_if_modified(|status| {
+                let modified = *status != ServingStatus::NotServing;
+                *status = ServingStatus::NotServing;
+                modified
+            });
+        }
+    }
+
+    /// A hook setting all the registered services to `NotServing`, for
+    /// [`Server::on_shutdown`](tonic::transport::Server::on_shutdown).
+    ///
+    /// Clients checking the health of the server then stop sending it calls while it drains
+    /// those in flight:
+    ///
+    /// ```
+    /// # async fn f() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (reporter, service) = tonic_health::server::health_reporter();
+    /// tonic::transport::Server::builder()
+    ///     .on_shutdown(reporter.shutdown_hook())
+    ///     .add_service(service)
+    ///     .serve_with_shutdown("[::1]:50051".parse()?, async {
+    ///         let _ = tokio::signal::ctrl_c().await;
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "transport")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
+    pub fn shutdown_hook(
+        &self,
+    ) -> impl Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync + 'static {
+        let reporter = self.clone();
+        move || {
+            let mut reporter = reporter.clone();
+            Box::pin(async move { reporter.set_all_not_serving().await })
+        }
+    }
+
+    /// A service answering HTTP probes with the statuses of the services, such as those of
+    /// Kubernetes.
+    ///
+    /// See [`HttpHealthService`].
+    pub fn http_service(&self) -> HttpHealthService {
+        HttpHealthService {
+            statuses: self.statuses.clone(),
+        }
     }
 
     /// Clear the status of the given service.
+    ///
+    /// Services derived from it count it as not serving.
     pub async fn clear_service_status(&mut self, service_name: &str) {
         let mut writer = self.statuses.write().await;
-        let _ = writer.remove(service_name);
+        let mut dependencies = self.lock_dependencies();
+        dependencies.remove(service_name);
+
+        if writer.remove(service_name).is_some() {
+            propagate(&mut writer, &dependencies, service_name);
+        }
+    }
+
+    fn lock_dependencies(&self) -> std::sync::MutexGuard<'_, HashMap<String, Dependencies>> {
+        self.dependencies
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Dependencies {
+    fn status(&self, statuses: &HashMap<String, StatusPair>) -> ServingStatus {
+        let dependencies = self.services.iter().map(|service| {
+            statuses
+                .get(service)
+                .map_or(ServingStatus::NotServing, |pair| *pair.1.borrow())
+        });
+        self.aggregation.status(dependencies)
+    }
+}
+
+/// Set the status of `service_name`, returning whether it changed.
+fn set_status(
+    statuses: &mut HashMap<String, StatusPair>,
+    service_name: &str,
+    status: ServingStatus,
+) -> bool {
+    match statuses.get(service_name) {
+        Some((tx, _)) => {
+            let changed = *tx.borrow() != status;
+            // We only ever hand out clones of the receiver, so the originally-created
+            // receiver should always be present, only being dropped when clearing the
+            // service status. Consequently, `tx.send` should not fail, making use
+            // of `expect` here safe.
+            tx.send(status).expect("channel should not be closed");
+            changed
+        }
+        None => {
+            statuses.insert(service_name.to_string(), watch::channel(status));
+            true
+        }
+    }
+}
+
+/// Derive again the statuses of the services depending on `service_name`, and transitively on
+/// those that changed.
+fn propagate(
+    statuses: &mut HashMap<String, StatusPair>,
+    dependencies: &HashMap<String, Dependencies>,
+    service_name: &str,
+) {
+    let mut changed = vec![service_name.to_string()];
+    let mut derived = HashSet::new();
+    while let Some(changed_name) = changed.pop() {
+        for (name, dependencies) in dependencies {
+            if !dependencies.services.contains(&changed_name) || !derived.insert(name.as_str()) {
+                continue;
+            }
+            let status = dependencies.status(statuses);
+            if statuses.get(name).map(|pair| *pair.1.borrow()) != Some(status) {
+                set_status(statuses, name, status);
+                changed.push(name.clone());
+            }
+        }
     }
 }
 
@@ -120,6 +333,125 @@ impl HealthService {
     }
 }
 
+/// A service answering the `GET` requests of HTTP liveness and readiness probes, with the
+/// statuses of the [`HealthReporter`] it is created by.
+///
+/// - `/healthz` answers `200 OK` as long as the server answers at all, including while it
+///   drains its calls after its shutdown started.
+/// - `/readyz` answers `200 OK` while the overall server health is `Serving`, and `503 Service
+///   Unavailable` otherwise. With a `service` query parameter, such as in
+///   `/readyz?service=helloworld.Greeter`, it answers with the status of that service instead.
+///
+/// Other requests are answered with `404 Not Found`. The service can answer probes on the
+/// listener of the gRPC services, as the fallback of their router, provided the server accepts
+/// HTTP/1.1:
+///
+/// ```
+/// # async fn f() -> Result<(), Box<dyn std::error::Error>> {
+/// let (reporter, service) = tonic_health::server::health_reporter();
+/// tonic::transport::Server::builder()
+///     .accept_http1(true)
+///     .add_service(service)
+///     .fallback_service(reporter.http_service())
+///     .serve("[::1]:50051".parse()?)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Or on a port of its own, with [`HttpHealthService::serve`].
+#[derive(Clone, Debug)]
+pub struct HttpHealthService {
+    statuses: Arc<RwLock<HashMap<String, StatusPair>>>,
+}
+
+impl HttpHealthService {
+    /// Serve the probes on `addr`, over HTTP/1.1 and HTTP/2.
+    #[cfg(feature = "transport")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
+    pub async fn serve(self, addr: std::net::SocketAddr) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .accept_http1(true)
+            .add_routes(tonic::transport::server::Routes::default().fallback_service(self))
+            .serve(addr)
+            .await
+    }
+
+    async fn respond(
+        statuses: Arc<RwLock<HashMap<String, StatusPair>>>,
+        method: http::Method,
+        uri: http::Uri,
+    ) -> http::Response<BoxBody> {
+        if method != http::Method::GET && method != http::Method::HEAD {
+            return text_response(http::StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+        }
+
+        match uri.path() {
+            "/healthz" => text_response(http::StatusCode::OK, "ok"),
+            "/readyz" => {
+                let query = uri.query().unwrap_or_default();
+                let service_name = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("service="))
+                    .unwrap_or_default();
+                let status = statuses
+                    .read()
+                    .await
+                    .get(service_name)
+                    .map(|pair| *pair.1.borrow());
+                match status {
+                    Some(ServingStatus::Serving) => text_response(http::StatusCode::OK, "SERVING"),
+                    Some(status) => text_response(
+                        http::StatusCode::SERVICE_UNAVAILABLE,
+                        match status {
+                            ServingStatus::NotServing => "NOT_SERVING",
+                            _ => "UNKNOWN",
+                        },
+                    ),
+                    None => text_response(
+                        http::StatusCode::SERVICE_UNAVAILABLE,
+                        "service not registered",
+                    ),
+                }
+            }
+            _ => text_response(http::StatusCode::NOT_FOUND, "not found"),
+        }
+    }
+}
+
+fn text_response(status: http::StatusCode, text: &'static str) -> http::Response<BoxBody> {
+    use http_body::Body as _;
+
+    let body = http_body::Full::new(Bytes::from_static(text.as_bytes()))
+        .map_err(|never| match never {})
+        .boxed_unsync();
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("text/plain"),
+    );
+    response
+}
+
+impl<B> Service<http::Request<B>> for HttpHealthService {
+    type Response = http::Response<BoxBody>;
+    type Error = std::convert::Infallible;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let statuses = self.statuses.clone();
+        let method = request.method().clone();
+        let uri = request.uri().clone();
+        Box::pin(async move { Ok(Self::respond(statuses, method, uri).await) })
+    }
+}
+
 #[tonic::async_trait]
 impl Health for HealthService {
     async fn check(
@@ -170,7 +502,7 @@ impl Health for HealthService {
 mod tests {
     use crate::pb::health_server::Health;
     use crate::pb::HealthCheckRequest;
-    use crate::server::{HealthReporter, HealthService};
+    use crate::server::{Aggregation, HealthReporter, HealthService};
     use crate::ServingStatus;
     use tokio::sync::watch;
     use tokio_stream::StreamExt;
@@ -322,4 +654,189 @@ mod tests {
         let item = resp.next().await;
         assert!(item.is_none());
     }
+
+    #[tokio::test]
+    async fn balances_to_serving_endpoints() {
+        use crate::pb::health_client::HealthClient;
+        use crate::server::health_reporter;
+        use tokio_stream::wrappers::TcpListenerStream;
+        use tonic::transport::{Channel, Endpoint, Server};
+
+        // Each server serves its name, so that checking it tells which one a
+        // request went to.
+        let mut servers = Vec::new();
+        let mut endpoints = Vec::new();
+        for name in ["a", "b"] {
+            let (mut reporter, service) = health_reporter();
+            reporter
+                .set_service_status(name, ServingStatus::Serving)
+                .await;
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let uri = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(
+                Server::builder()
+                    .add_service(service)
+                    .serve_with_incoming(TcpListenerStream::new(listener)),
+            );
+            servers.push(reporter);
+            endpoints.push(Endpoint::from_shared(uri).unwrap().health_check("test"));
+        }
+        let mut client = HealthClient::new(Channel::balance_list(endpoints.into_iter()));
+
+        async fn serves_only(client: &mut HealthClient<Channel>, name: &str) -> bool {
+            for _ in 0..10 {
+                let request = Request::new(HealthCheckRequest {
+                    service: name.to_owned(),
+                });
+                if client.check(request).await.is_err() {
+                    return false;
+                }
+            }
+            true
+        }
+
+        servers[0]
+            .set_service_status("test", ServingStatus::Serving)
+            .await;
+        servers[1]
+            .set_service_status("test", ServingStatus::NotServing)
+            .await;
+        while !serves_only(&mut client, "a").await {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        servers[0]
+            .set_service_status("test", ServingStatus::NotServing)
+            .await;
+        servers[1]
+            .set_service_status("test", ServingStatus::Serving)
+            .await;
+        while !serves_only(&mut client, "b").await {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn derives_statuses_from_dependencies() {
+        let (mut reporter, service) = make_test_service().await;
+        let status = |name: &'static str| service.service_health(name);
+
+        reporter
+            .set_service_dependencies("", ["storage", "cache"], Aggregation::All)
+            .await;
+        reporter
+            .set_service_dependencies("storage", ["primary", "replica"], Aggregation::Any)
+            .await;
+        assert_eq!(status("").await, Some(ServingStatus::NotServing));
+        assert_eq!(status("storage").await, Some(ServingStatus::NotServing));
+
+        let mut watch = service
+            .watch(Request::new(HealthCheckRequest {
+                service: "".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let item = watch.next().await.unwrap().unwrap();
+        assert_serving_status(item.status, ServingStatus::NotServing);
+
+        reporter
+            .set_service_status("replica", ServingStatus::Serving)
+            .await;
+        reporter
+            .set_service_status("cache", ServingStatus::Serving)
+            .await;
+        assert_eq!(status("storage").await, Some(ServingStatus::Serving));
+        assert_eq!(status("").await, Some(ServingStatus::Serving));
+        let item = watch.next().await.unwrap().unwrap();
+        assert_serving_status(item.status, ServingStatus::Serving);
+
+        reporter.clear_service_status("cache").await;
+        let item = watch.next().await.unwrap().unwrap();
+        assert_serving_status(item.status, ServingStatus::NotServing);
+
+        // Setting a derived status stops deriving it.
+        reporter
+            .set_service_status("", ServingStatus::Serving)
+            .await;
+        reporter
+            .set_service_status("replica", ServingStatus::NotServing)
+            .await;
+        assert_eq!(status("storage").await, Some(ServingStatus::NotServing));
+        assert_eq!(status("").await, Some(ServingStatus::Serving));
+    }
+
+    #[tokio::test]
+    async fn reports_not_serving_on_shutdown() {
+        use crate::pb::health_client::HealthClient;
+        use crate::server::health_reporter;
+        use tonic::transport::{local, Server};
+
+        let (mut reporter, service) = health_reporter();
+        reporter
+            .set_service_status("TestService", ServingStatus::Serving)
+            .await;
+
+        let (channel, incoming) = local::pair();
+        let (shutdown, signal) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(
+            Server::builder()
+                .on_shutdown(reporter.shutdown_hook())
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = signal.await;
+                }),
+        );
+
+        let mut client = HealthClient::new(channel);
+        let mut watch = client
+            .watch(HealthCheckRequest {
+                service: "TestService".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let item = watch.next().await.unwrap().unwrap();
+        assert_serving_status(item.status, ServingStatus::Serving);
+
+        // The watch, in flight, is told of the new status while the server drains.
+        shutdown.send(()).unwrap();
+        let item = watch.next().await.unwrap().unwrap();
+        assert_serving_status(item.status, ServingStatus::NotServing);
+        assert_eq!(
+            *reporter.statuses.read().await[""].1.borrow(),
+            ServingStatus::NotServing
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_http_probes() {
+        use tonic::codegen::{http, Service};
+
+        let (mut reporter, _) = make_test_service().await;
+        let mut probes = reporter.http_service();
+        let mut probe = |uri: &'static str| {
+            let request = http::Request::get(uri).body(()).unwrap();
+            let response = probes.call(request);
+            async move { response.await.unwrap().status() }
+        };
+
+        assert_eq!(probe("/healthz").await, http::StatusCode::OK);
+        assert_eq!(probe("/readyz").await, http::StatusCode::OK);
+        assert_eq!(
+            probe("/readyz?service=TestService").await,
+            http::StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(probe("/other").await, http::StatusCode::NOT_FOUND);
+
+        reporter
+            .set_service_status("TestService", ServingStatus::Serving)
+            .await;
+        reporter.set_all_not_serving().await;
+        assert_eq!(probe("/healthz").await, http::StatusCode::OK);
+        assert_eq!(
+            probe("/readyz").await,
+            http::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
 }
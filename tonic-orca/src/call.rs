@@ -0,0 +1,278 @@
+//! Per-call load reports.
+
+use crate::{pb::OrcaLoadReport, util::base64::STANDARD_NO_PAD, ENDPOINT_LOAD_METRICS_HEADER};
+use base64::Engine as _;
+use bytes::Bytes;
+use http::{header::HeaderName, HeaderMap, HeaderValue};
+use http_body::Body;
+use pin_project::pin_project;
+use prost::Message as _;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Records the metrics of a call, sent to the client in the trailers of its
+/// response.
+///
+/// Handlers get the recorder of their request with
+/// [`CallMetricsRecorder::from_request`] when the server uses a
+/// [`CallMetricsLayer`]. Nothing is sent if no metric is recorded.
+#[derive(Debug, Clone, Default)]
+pub struct CallMetricsRecorder {
+    report: Arc<Mutex<Option<OrcaLoadReport>>>,
+}
+
+impl CallMetricsRecorder {
+    /// The recorder of `request`, if the server uses a [`CallMetricsLayer`].
+    pub fn from_request<T>(request: &tonic::Request<T>) -> Option<&Self> {
+        request.extensions().get()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut OrcaLoadReport)) {
+        f(self
+            .report
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Default::default))
+    }
+
+    /// Record the CPU utilization, as a fraction of the available CPU.
+    pub fn record_cpu_utilization(&self, utilization: f64) {
+        self.update(|report| report.cpu_utilization = utilization)
+    }
+
+    /// Record the memory utilization, as a fraction of the available memory.
+    pub fn record_memory_utilization(&self, utilization: f64) {
+        self.update(|report| report.mem_utilization = utilization)
+    }
+
+    /// Record the application specific utilization, used by clients rather
+    /// than the CPU utilization when set.
+    pub fn record_application_utilization(&self, utilization: f64) {
+        self.update(|report| report.application_utilization = utilization)
+    }
+
+    /// Record the requests per second served by the server.
+    pub fn record_qps(&self, qps: f64) {
+        self.update(|report| report.rps_fractional = qps)
+    }
+
+    /// Record the errors per second returned by the server.
+    pub fn record_eps(&self, eps: f64) {
+        self.update(|report| report.eps = eps)
+    }
+
+    /// Record an absolute cost of the call, such as the bytes it stored.
+    pub fn record_request_cost(&self, name: impl Into<String>, cost: f64) {
+        self.update(|report| {
+            report.request_cost.insert(name.into(), cost);
+        })
+    }
+
+    /// Record the utilization of a named resource, as a fraction of its
+    /// capacity.
+    pub fn record_utilization(&self, name: impl Into<String>, utilization: f64) {
+        self.update(|report| {
+            report.utilization.insert(name.into(), utilization);
+        })
+    }
+
+    /// Record an application specific metric.
+    pub fn record_named_metric(&self, name: impl Into<String>, value: f64) {
+        self.update(|report| {
+            report.named_metrics.insert(name.into(), value);
+        })
+    }
+
+    /// Add the recorded metrics to `headers`.
+    fn write(&self, headers: &mut HeaderMap) {
+        if let Some(report) = &*self.report.lock().unwrap() {
+            let value = STANDARD_NO_PAD.encode(report.encode_to_vec());
+            headers.insert(
+                HeaderName::from_static(ENDPOINT_LOAD_METRICS_HEADER),
+                HeaderValue::from_str(&value).expect("base64 is a valid header value"),
+            );
+        }
+    }
+}
+
+/// Layer giving the requests of a server a [`CallMetricsRecorder`], whose
+/// metrics are sent in the trailers of their responses.
+#[derive(Debug, Clone, Default)]
+pub struct CallMetricsLayer {
+    _priv: (),
+}
+
+impl CallMetricsLayer {
+    /// Create a new call metrics layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for CallMetricsLayer {
+    type Service = CallMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CallMetrics { inner }
+    }
+}
+
+/// Service created by [`CallMetricsLayer`].
+#[derive(Debug, Clone)]
+pub struct CallMetrics<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for CallMetrics<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    ResBody: Body,
+{
+    type Response = http::Response<CallMetricsBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
+        let recorder = CallMetricsRecorder::default();
+        request.extensions_mut().insert(recorder.clone());
+
+        ResponseFuture {
+            inner: self.inner.call(request),
+            recorder: Some(recorder),
+        }
+    }
+}
+
+/// Response future for [`CallMetrics`].
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    recorder: Option<CallMetricsRecorder>,
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<ResBody>, E>>,
+    ResBody: Body,
+{
+    type Output = Result<http::Response<CallMetricsBody<ResBody>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.inner.poll(cx))?;
+        let recorder = this.recorder.take().expect("polled after ready");
+
+        // Trailers-only responses carry their trailers in the headers.
+        if response.body().is_end_stream() {
+            recorder.write(response.headers_mut());
+            return Poll::Ready(Ok(response.map(|inner| CallMetricsBody {
+                inner,
+                recorder: None,
+            })));
+        }
+
+        Poll::Ready(Ok(response.map(|inner| CallMetricsBody {
+            inner,
+            recorder: Some(recorder),
+        })))
+    }
+}
+
+/// Response body for [`CallMetrics`], adding the recorded metrics to the
+/// trailers.
+#[pin_project]
+#[derive(Debug)]
+pub struct CallMetricsBody<B> {
+    #[pin]
+    inner: B,
+    recorder: Option<CallMetricsRecorder>,
+}
+
+impl<B> Body for CallMetricsBody<B>
+where
+    B: Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx))?;
+
+        let recorder = match this.recorder.take() {
+            Some(recorder) => recorder,
+            None => return Poll::Ready(Ok(trailers)),
+        };
+        let mut trailers = trailers.unwrap_or_default();
+        recorder.write(&mut trailers);
+        Poll::Ready(Ok(Some(trailers).filter(|trailers| !trailers.is_empty())))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_report;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    async fn trailers(record: bool) -> Option<HeaderMap> {
+        let inner = tower::service_fn(move |request: http::Request<()>| async move {
+            let recorder = request.extensions().get::<CallMetricsRecorder>().unwrap();
+            if record {
+                recorder.record_cpu_utilization(0.5);
+                recorder.record_request_cost("queries", 2.0);
+            }
+            Ok::<_, Infallible>(http::Response::new(hyper::Body::from("response")))
+        });
+        let service = CallMetricsLayer::new().layer(inner);
+
+        let response = service.oneshot(http::Request::new(())).await.unwrap();
+        let mut body = response.into_body();
+        while let Some(data) = body.data().await {
+            data.unwrap();
+        }
+        body.trailers().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn adds_recorded_metrics_to_trailers() {
+        let trailers = trailers(true).await.unwrap();
+        let report = load_report(&trailers).unwrap();
+        assert_eq!(report.cpu_utilization, 0.5);
+        assert_eq!(report.request_cost["queries"], 2.0);
+        assert_eq!(crate::utilization(&trailers), Some(0.5));
+
+        assert_eq!(self::trailers(false).await, None);
+    }
+}
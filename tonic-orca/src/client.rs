@@ -0,0 +1,30 @@
+//! Reading load reports on clients.
+
+use crate::{pb::OrcaLoadReport, util::base64::STANDARD_NO_PAD, ENDPOINT_LOAD_METRICS_HEADER};
+use base64::Engine as _;
+use http::HeaderMap;
+use prost::Message as _;
+
+/// Decode the per-call load report in the trailers, or in the headers of a
+/// trailers-only response.
+pub fn load_report(trailers: &HeaderMap) -> Option<OrcaLoadReport> {
+    let value = trailers.get(ENDPOINT_LOAD_METRICS_HEADER)?;
+    let bytes = STANDARD_NO_PAD.decode(value.as_bytes()).ok()?;
+    OrcaLoadReport::decode(&bytes[..]).ok()
+}
+
+/// The utilization reported in the trailers: the application utilization
+/// if it is set, or the CPU utilization.
+///
+/// Pass this to [`Endpoint::load_from_trailers`] so balanced channels send
+/// more requests to the least utilized endpoints.
+///
+/// [`Endpoint::load_from_trailers`]: tonic::transport::Endpoint::load_from_trailers
+pub fn utilization(trailers: &HeaderMap) -> Option<f64> {
+    let report = load_report(trailers)?;
+    if report.application_utilization > 0.0 {
+        Some(report.application_utilization)
+    } else {
+        Some(report.cpu_utilization)
+    }
+}
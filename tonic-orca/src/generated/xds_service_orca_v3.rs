@@ -0,0 +1,398 @@
+// This file is @generated by prost-build.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OrcaLoadReportRequest {
+    /// Interval for generating Open RCA core metric responses.
+    #[prost(message, optional, tag = "1")]
+    pub report_interval: ::core::option::Option<::prost_types::Duration>,
+    /// Request costs to collect. If this is empty, all known requests costs tracked by
+    /// the load reporting agent will be returned. This provides an opportunity for
+    /// the client to selectively obtain a subset of tracked costs.
+    #[prost(string, repeated, tag = "2")]
+    pub request_cost_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Generated client implementations.
+pub mod open_rca_service_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Out-of-band (OOB) load reporting service for the additional load reporting
+    /// agent that does not sit in the request path. Reports are periodically sampled
+    /// with sufficient frequency to provide temporal association with requests.
+    /// OOB reporting compensates the limitation of in-band reporting in revealing
+    /// costs for backends that do not provide a steady stream of telemetry such as
+    /// long running stream operations and zero QPS services. This is a server
+    /// streaming service, client needs to terminate current RPC and initiate
+    /// a new call to change backend reporting frequency.
+    #[derive(Debug, Clone)]
+    pub struct OpenRcaServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl<T> OpenRcaServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> OpenRcaServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            OpenRcaServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        pub fn with_async_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> OpenRcaServiceClient<AsyncInterceptedService<T, F>>
+        where
+            F: tonic::service::AsyncInterceptor,
+            T::ResponseBody: Default,
+            T: Clone
+                + tonic::codegen::Service<
+                    http::Request<tonic::body::BoxBody>,
+                    Response = http::Response<
+                        <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                    >,
+                >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            OpenRcaServiceClient::new(AsyncInterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Send the requests smaller than `bytes` uncompressed.
+        ///
+        /// Default: `0`
+        #[must_use]
+        pub fn send_compressed_min_size(mut self, bytes: usize) -> Self {
+            self.inner = self.inner.send_compressed_min_size(bytes);
+            self
+        }
+        /// Compress requests at the given level.
+        #[must_use]
+        pub fn send_compression_level(mut self, level: CompressionLevel) -> Self {
+            self.inner = self.inner.send_compression_level(level);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        pub async fn stream_core_metrics(
+            &mut self,
+            request: impl tonic::IntoRequest<super::OrcaLoadReportRequest>,
+        ) -> std::result::Result<
+            tonic::Response<
+                tonic::codec::Streaming<
+                    super::super::super::super::data::orca::v3::OrcaLoadReport,
+                >,
+            >,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/xds.service.orca.v3.OpenRcaService/StreamCoreMetrics",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "xds.service.orca.v3.OpenRcaService",
+                        "StreamCoreMetrics",
+                    ),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod open_rca_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with OpenRcaServiceServer.
+    #[async_trait]
+    pub trait OpenRcaService: Send + Sync + 'static {
+        /// Server streaming response type for the StreamCoreMetrics method.
+        type StreamCoreMetricsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<
+                    super::super::super::super::data::orca::v3::OrcaLoadReport,
+                    tonic::Status,
+                >,
+            >
+            + Send
+            + 'static;
+        async fn stream_core_metrics(
+            &self,
+            request: tonic::Request<super::OrcaLoadReportRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::StreamCoreMetricsStream>,
+            tonic::Status,
+        >;
+    }
+    /// Out-of-band (OOB) load reporting service for the additional load reporting
+    /// agent that does not sit in the request path. Reports are periodically sampled
+    /// with sufficient frequency to provide temporal association with requests.
+    /// OOB reporting compensates the limitation of in-band reporting in revealing
+    /// costs for backends that do not provide a steady stream of telemetry such as
+    /// long running stream operations and zero QPS services. This is a server
+    /// streaming service, client needs to terminate current RPC and initiate
+    /// a new call to change backend reporting frequency.
+    #[derive(Debug)]
+    pub struct OpenRcaServiceServer<T: OpenRcaService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        send_compressed_min_size: usize,
+        send_compression_level: CompressionLevel,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: OpenRcaService> OpenRcaServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                send_compressed_min_size: 0,
+                send_compression_level: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        pub fn with_async_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> AsyncInterceptedService<Self, F>
+        where
+            F: tonic::service::AsyncInterceptor,
+        {
+            AsyncInterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Send the responses smaller than `bytes` uncompressed.
+        ///
+        /// Default: `0`
+        #[must_use]
+        pub fn send_compressed_min_size(mut self, bytes: usize) -> Self {
+            self.send_compressed_min_size = bytes;
+            self
+        }
+        /// Compress responses at the given level.
+        #[must_use]
+        pub fn send_compression_level(mut self, level: CompressionLevel) -> Self {
+            self.send_compression_level = level;
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for OpenRcaServiceServer<T>
+    where
+        T: OpenRcaService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/xds.service.orca.v3.OpenRcaService/StreamCoreMetrics" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamCoreMetricsSvc<T: OpenRcaService>(pub Arc<T>);
+                    impl<
+                        T: OpenRcaService,
+                    > tonic::server::ServerStreamingService<super::OrcaLoadReportRequest>
+                    for StreamCoreMetricsSvc<T> {
+                        type Response = super::super::super::super::data::orca::v3::OrcaLoadReport;
+                        type ResponseStream = T::StreamCoreMetricsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::OrcaLoadReportRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as OpenRcaService>::stream_core_metrics(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let send_compressed_min_size = self.send_compressed_min_size;
+                    let send_compression_level = self.send_compression_level;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StreamCoreMetricsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .send_compressed_min_size(send_compressed_min_size)
+                            .send_compression_level(send_compression_level)
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: OpenRcaService> Clone for OpenRcaServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                send_compressed_min_size: self.send_compressed_min_size,
+                send_compression_level: self.send_compression_level,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: OpenRcaService> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: OpenRcaService> tonic::server::NamedService for OpenRcaServiceServer<T> {
+        const NAME: &'static str = "xds.service.orca.v3.OpenRcaService";
+    }
+}
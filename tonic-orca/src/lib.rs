@@ -0,0 +1,130 @@
+//! [ORCA] load reporting for `tonic` servers and clients.
+//!
+//! Servers report their utilization to clients in two ways:
+//!
+//! - Per call, in the `endpoint-load-metrics-bin` trailer of responses.
+//!   [`CallMetricsLayer`] attaches the metrics handlers record with the
+//!   [`CallMetricsRecorder`] of their request.
+//! - Out of band, by serving the `xds.service.orca.v3.OpenRcaService`
+//!   service backed by a [`ServerMetricsRecorder`], see [`OrcaService`].
+//!
+//! Clients balance on the reported load with
+//! [`Endpoint::load_from_trailers`] and [`utilization`], or subscribe to the
+//! out-of-band reports with the generated
+//! [`OpenRcaServiceClient`](pb::open_rca_service_client::OpenRcaServiceClient).
+//!
+//! # Example
+//!
+//! ```
+//! use tonic::transport::{Endpoint, Server};
+//! use tonic_orca::{CallMetricsLayer, CallMetricsRecorder};
+//!
+//! // Only the layer is needed to report per-call metrics.
+//! let server = Server::builder().layer(CallMetricsLayer::new());
+//!
+//! // In the handlers.
+//! fn handle(request: &tonic::Request<()>) {
+//!     if let Some(recorder) = CallMetricsRecorder::from_request(request) {
+//!         recorder.record_cpu_utilization(0.4);
+//!         recorder.record_request_cost("db_queries", 3.0);
+//!     }
+//! }
+//!
+//! // Clients send more requests to the least loaded addresses.
+//! let endpoint = Endpoint::from_static("dns://greeter.svc:50051")
+//!     .load_from_trailers(tonic_orca::utilization);
+//! ```
+//!
+//! [ORCA]: https://github.com/grpc/proposal/blob/master/A51-custom-backend-metrics.md
+//! [`Endpoint::load_from_trailers`]: tonic::transport::Endpoint::load_from_trailers
+
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
+)]
+#![deny(rustdoc::broken_intra_doc_links)]
+#![doc(html_root_url = "https://docs.rs/tonic-orca/0.11.0")]
+#![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
+#![doc(test(no_crate_inject, attr(deny(rust_2018_idioms))))]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+mod generated {
+    #![allow(unreachable_pub)]
+    #![allow(missing_docs)]
+
+    pub mod xds {
+        pub mod data {
+            pub mod orca {
+                pub mod v3 {
+                    #![allow(rustdoc::invalid_html_tags)]
+                    include!("generated/xds_data_orca_v3.rs");
+                }
+            }
+        }
+
+        pub mod service {
+            pub mod orca {
+                pub mod v3 {
+                    include!("generated/xds_service_orca_v3.rs");
+                }
+            }
+        }
+    }
+
+    /// Byte encoded FILE_DESCRIPTOR_SET.
+    pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!("generated/orca_v3.bin");
+
+    #[cfg(test)]
+    mod tests {
+        use super::FILE_DESCRIPTOR_SET;
+        use prost::Message as _;
+
+        #[test]
+        fn file_descriptor_set_is_valid() {
+            prost_types::FileDescriptorSet::decode(FILE_DESCRIPTOR_SET).unwrap();
+        }
+    }
+}
+
+/// Generated protobuf types from the `xds.data.orca.v3` and
+/// `xds.service.orca.v3` packages.
+pub mod pb {
+    pub use crate::generated::xds::data::orca::v3::*;
+    pub use crate::generated::xds::service::orca::v3::*;
+    pub use crate::generated::FILE_DESCRIPTOR_SET;
+}
+
+mod call;
+mod client;
+mod server;
+
+pub use call::{CallMetrics, CallMetricsBody, CallMetricsLayer, CallMetricsRecorder};
+pub use client::{load_report, utilization};
+pub use server::{OrcaService, ServerMetricsRecorder};
+
+/// The trailer carrying per-call load reports.
+pub const ENDPOINT_LOAD_METRICS_HEADER: &str = "endpoint-load-metrics-bin";
+
+pub(crate) mod util {
+    pub(crate) mod base64 {
+        use base64::{
+            alphabet,
+            engine::{
+                general_purpose::{GeneralPurpose, GeneralPurposeConfig},
+                DecodePaddingMode,
+            },
+        };
+
+        pub(crate) const STANDARD_NO_PAD: GeneralPurpose = GeneralPurpose::new(
+            &alphabet::STANDARD,
+            GeneralPurposeConfig::new()
+                .with_encode_padding(false)
+                .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Out-of-band load reports.
+
+use crate::pb::{open_rca_service_server::OpenRcaService, OrcaLoadReport, OrcaLoadReportRequest};
+use std::{
+    pin::Pin,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio_stream::{wrappers::IntervalStream, Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+/// The shortest interval between reports, to which a zero interval is
+/// raised, as the interval of a stream can't be zero.
+const MIN_REPORT_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Records the utilization of a server, streamed to the clients subscribed
+/// to its [`OrcaService`].
+///
+/// Clones of a recorder update the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct ServerMetricsRecorder {
+    report: Arc<RwLock<OrcaLoadReport>>,
+}
+
+impl ServerMetricsRecorder {
+    /// Create a recorder without any metric.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut OrcaLoadReport)) {
+        f(&mut self.report.write().unwrap())
+    }
+
+    /// Sets the CPU utilization, as a fraction of the available CPU.
+    pub fn set_cpu_utilization(&self, utilization: f64) {
+        self.update(|report| report.cpu_utilization = utilization)
+    }
+
+    /// Sets the memory utilization, as a fraction of the available memory.
+    pub fn set_memory_utilization(&self, utilization: f64) {
+        self.update(|report| report.mem_utilization = utilization)
+    }
+
+    /// Sets the application specific utilization, used by clients rather
+    /// than the CPU utilization when set.
+    pub fn set_application_utilization(&self, utilization: f64) {
+        self.update(|report| report.application_utilization = utilization)
+    }
+
+    /// Sets the requests per second served by the server.
+    pub fn set_qps(&self, qps: f64) {
+        self.update(|report| report.rps_fractional = qps)
+    }
+
+    /// Sets the errors per second returned by the server.
+    pub fn set_eps(&self, eps: f64) {
+        self.update(|report| report.eps = eps)
+    }
+
+    /// Sets the utilization of a named resource, as a fraction of its
+    /// capacity.
+    pub fn set_utilization(&self, name: impl Into<String>, utilization: f64) {
+        self.update(|report| {
+            report.utilization.insert(name.into(), utilization);
+        })
+    }
+
+    /// Stop reporting the utilization of the resource named `name`.
+    pub fn remove_utilization(&self, name: &str) {
+        self.update(|report| {
+            report.utilization.remove(name);
+        })
+    }
+
+    /// Sets an application specific metric.
+    pub fn set_named_metric(&self, name: impl Into<String>, value: f64) {
+        self.update(|report| {
+            report.named_metrics.insert(name.into(), value);
+        })
+    }
+
+    /// Stop reporting the metric named `name`.
+    pub fn remove_named_metric(&self, name: &str) {
+        self.update(|report| {
+            report.named_metrics.remove(name);
+        })
+    }
+
+    /// The metrics currently recorded.
+    pub fn snapshot(&self) -> OrcaLoadReport {
+        self.report.read().unwrap().clone()
+    }
+}
+
+/// Implementation of the `xds.service.orca.v3.OpenRcaService` service,
+/// streaming the metrics of a [`ServerMetricsRecorder`] to clients.
+///
+/// ```
+/// use tonic_orca::{pb::open_rca_service_server::OpenRcaServiceServer, OrcaService, ServerMetricsRecorder};
+///
+/// let recorder = ServerMetricsRecorder::new();
+/// let service = OpenRcaServiceServer::new(OrcaService::new(recorder.clone()));
+///
+/// // Later, as the load of the server changes.
+/// recorder.set_cpu_utilization(0.7);
+/// ```
+#[derive(Debug)]
+pub struct OrcaService {
+    recorder: ServerMetricsRecorder,
+    min_report_interval: Duration,
+}
+
+impl OrcaService {
+    /// Create a service reporting the metrics of `recorder`.
+    pub fn new(recorder: ServerMetricsRecorder) -> Self {
+        OrcaService {
+            recorder,
+            min_report_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets the minimum interval between the reports sent to a client,
+    /// used when a client asks for reports more often.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn min_report_interval(self, interval: Duration) -> Self {
+        OrcaService {
+            min_report_interval: interval,
+            ..self
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl OpenRcaService for OrcaService {
+    type StreamCoreMetricsStream =
+        Pin<Box<dyn Stream<Item = Result<OrcaLoadReport, Status>> + Send + 'static>>;
+
+    async fn stream_core_metrics(
+        &self,
+        request: Request<OrcaLoadReportRequest>,
+    ) -> Result<Response<Self::StreamCoreMetricsStream>, Status> {
+        let requested = request
+            .into_inner()
+            .report_interval
+            .and_then(|interval| Duration::try_from(interval).ok())
+            .unwrap_or_default();
+        let interval = requested
+            .max(self.min_report_interval)
+            .max(MIN_REPORT_INTERVAL);
+        let interval = tokio::time::interval(interval);
+
+        let recorder = self.recorder.clone();
+        let reports = IntervalStream::new(interval).map(move |_| Ok(recorder.snapshot()));
+        Ok(Response::new(Box::pin(reports)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn streams_reports_without_intervals() {
+        let recorder = ServerMetricsRecorder::new();
+        recorder.set_cpu_utilization(0.5);
+        let service = OrcaService::new(recorder).min_report_interval(Duration::ZERO);
+
+        let request = Request::new(OrcaLoadReportRequest::default());
+        let response = service.stream_core_metrics(request).await.unwrap();
+        let reports = response.into_inner().take(2).collect::<Vec<_>>().await;
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[1].as_ref().unwrap().cpu_utilization, 0.5);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::pb::server_reflection_client::ServerReflectionClient;
+use crate::pb::server_reflection_request::MessageRequest;
+use crate::pb::server_reflection_response::MessageResponse;
+use crate::pb::{ServerReflectionRequest, ServerReflectionResponse};
+use prost::Message;
+use prost_reflect::DescriptorPool;
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::body::BoxBody;
+use tonic::client::GrpcService;
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::{Code, Status, Streaming};
+
+/// A client of the gRPC Reflection Service of a server.
+///
+/// The requests of a client share a single bidirectional stream, opened by
+/// the first request and reopened after an error ends it, so that they all
+/// go to the same server. The client caches the files it receives, and the
+/// files of the symbols it looks up, asking the server only for those it has
+/// not received yet.
+#[derive(Debug)]
+pub struct ReflectionClient<T> {
+    inner: ServerReflectionClient<T>,
+    host: String,
+    stream: Option<ReflectionStream>,
+    files: HashMap<String, FileDescriptorProto>,
+    symbols: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+struct ReflectionStream {
+    requests: mpsc::Sender<ServerReflectionRequest>,
+    responses: Streaming<ServerReflectionResponse>,
+}
+
+impl<T> ReflectionClient<T>
+where
+    T: GrpcService<BoxBody>,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    /// Create a client of the reflection service of `inner`.
+    pub fn new(inner: T) -> Self {
+        ReflectionClient {
+            inner: ServerReflectionClient::new(inner),
+            host: String::new(),
+            stream: None,
+            files: HashMap::new(),
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// Set the host of the requests, for servers that serve the services of
+    /// several virtual hosts.
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// The fully-qualified names of the services of the server.
+    pub async fn list_services(&mut self) -> Result<Vec<String>, Status> {
+        let request = MessageRequest::ListServices(String::new());
+        match self.request(request).await? {
+            MessageResponse::ListServicesResponse(response) => {
+                Ok(response.service.into_iter().map(|s| s.name).collect())
+            }
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// The file defining the fully-qualified `symbol`, such as a service, a
+    /// method or a message.
+    pub async fn file_containing_symbol(
+        &mut self,
+        symbol: &str,
+    ) -> Result<FileDescriptorProto, Status> {
+        if let Some(file) = self.symbols.get(symbol).and_then(|f| self.files.get(f)) {
+            return Ok(file.clone());
+        }
+
+        let file = self
+            .files(MessageRequest::FileContainingSymbol(symbol.to_owned()))
+            .await?;
+        self.symbols
+            .insert(symbol.to_owned(), file.name().to_owned());
+        Ok(file)
+    }
+
+    /// The file of the name `filename`, such as `google/protobuf/empty.proto`.
+    pub async fn file_by_filename(
+        &mut self,
+        filename: &str,
+    ) -> Result<FileDescriptorProto, Status> {
+        if let Some(file) = self.files.get(filename) {
+            return Ok(file.clone());
+        }
+
+        self.files(MessageRequest::FileByFilename(filename.to_owned()))
+            .await
+    }
+
+    /// The files of `symbols` and their transitive dependencies, each file
+    /// after its dependencies.
+    pub async fn file_descriptor_set(
+        &mut self,
+        symbols: &[&str],
+    ) -> Result<FileDescriptorSet, Status> {
+        let mut set = FileDescriptorSet::default();
+        let mut added = HashSet::new();
+        for symbol in symbols {
+            let file = self.file_containing_symbol(symbol).await?;
+            self.add_with_dependencies(file, &mut set, &mut added)
+                .await?;
+        }
+        Ok(set)
+    }
+
+    /// A pool of the descriptors of `symbols`, and of their dependencies.
+    pub async fn descriptor_pool(&mut self, symbols: &[&str]) -> Result<DescriptorPool, Status> {
+        let set = self.file_descriptor_set(symbols).await?;
+        DescriptorPool::from_file_descriptor_set(set)
+            .map_err(|e| Status::internal(format!("invalid descriptors of the server: {}", e)))
+    }
+
+    async fn add_with_dependencies(
+        &mut self,
+        file: FileDescriptorProto,
+        set: &mut FileDescriptorSet,
+        added: &mut HashSet<String>,
+    ) -> Result<(), Status> {
+        if !added.insert(file.name().to_owned()) {
+            return Ok(());
+        }
+
+        // The files still to add, each with the number of its dependencies
+        // that are already added, so that files follow their dependencies
+        // without recursing.
+        let mut pending = vec![(file, 0)];
+        while let Some((file, done)) = pending.pop() {
+            match file.dependency.get(done).cloned() {
+                Some(dependency) => {
+                    pending.push((file, done + 1));
+                    if added.insert(dependency.clone()) {
+                        pending.push((self.file_by_filename(&dependency).await?, 0));
+                    }
+                }
+                None => set.file.push(file),
+            }
+        }
+        Ok(())
+    }
+
+    /// Request files, caching all the files of the response, and return the
+    /// first one, that the request asks for.
+    async fn files(&mut self, request: MessageRequest) -> Result<FileDescriptorProto, Status> {
+        let response = match self.request(request).await? {
+            MessageResponse::FileDescriptorResponse(response) => response,
+            _ => return Err(unexpected_response()),
+        };
+
+        let mut files = response.file_descriptor_proto.iter().map(|file| {
+            FileDescriptorProto::decode(file.as_slice())
+                .map_err(|e| Status::internal(format!("invalid file descriptor: {}", e)))
+        });
+        let first = files.next().ok_or_else(unexpected_response)??;
+        for file in files {
+            let file = file?;
+            self.files.insert(file.name().to_owned(), file);
+        }
+        self.files.insert(first.name().to_owned(), first.clone());
+        Ok(first)
+    }
+
+    async fn request(&mut self, request: MessageRequest) -> Result<MessageResponse, Status> {
+        let request = ServerReflectionRequest {
+            host: self.host.clone(),
+            message_request: Some(request),
+        };
+
+        let response = match &mut self.stream {
+            Some(stream) => stream.call(request).await,
+            None => self.open(request).await,
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(status) => {
+                self.stream = None;
+                return Err(status);
+            }
+        };
+
+        match response.message_response {
+            Some(MessageResponse::ErrorResponse(error)) => Err(Status::new(
+                Code::from(error.error_code),
+                error.error_message,
+            )),
+            Some(response) => Ok(response),
+            None => Err(unexpected_response()),
+        }
+    }
+
+    /// Open the stream with its first request, that servers may need before
+    /// they respond with the headers of the stream.
+    async fn open(
+        &mut self,
+        request: ServerReflectionRequest,
+    ) -> Result<ServerReflectionResponse, Status> {
+        let (requests, rx) = mpsc::channel(1);
+        requests.send(request).await.expect("the receiver is alive");
+
+        let responses = self
+            .inner
+            .server_reflection_info(ReceiverStream::new(rx))
+            .await?
+            .into_inner();
+        let stream = self.stream.insert(ReflectionStream {
+            requests,
+            responses,
+        });
+        stream.response().await
+    }
+}
+
+impl ReflectionStream {
+    async fn call(
+        &mut self,
+        request: ServerReflectionRequest,
+    ) -> Result<ServerReflectionResponse, Status> {
+        self.requests
+            .send(request)
+            .await
+            .map_err(|_| Status::unavailable("the reflection stream is closed"))?;
+        self.response().await
+    }
+
+    async fn response(&mut self) -> Result<ServerReflectionResponse, Status> {
+        self.responses
+            .message()
+            .await?
+            .ok_or_else(|| Status::unavailable("the reflection stream is closed"))
+    }
+}
+
+fn unexpected_response() -> Status {
+    Status::internal("unexpected reflection response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Builder;
+    use prost_types::{DescriptorProto, MethodDescriptorProto, ServiceDescriptorProto};
+    use std::sync::OnceLock;
+    use tonic::transport::{local, Channel, Server};
+
+    /// A `b.proto` file of a `b.B` service, of the messages of an `a.proto`
+    /// file.
+    fn file_descriptor_set() -> Vec<u8> {
+        let a = FileDescriptorProto {
+            name: Some("a.proto".to_owned()),
+            package: Some("a".to_owned()),
+            message_type: vec![DescriptorProto {
+                name: Some("A".to_owned()),
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_owned()),
+            ..Default::default()
+        };
+        let b = FileDescriptorProto {
+            name: Some("b.proto".to_owned()),
+            package: Some("b".to_owned()),
+            dependency: vec!["a.proto".to_owned()],
+            service: vec![ServiceDescriptorProto {
+                name: Some("B".to_owned()),
+                method: vec![MethodDescriptorProto {
+                    name: Some("Call".to_owned()),
+                    input_type: Some(".a.A".to_owned()),
+                    output_type: Some(".a.A".to_owned()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_owned()),
+            ..Default::default()
+        };
+        FileDescriptorSet { file: vec![b, a] }.encode_to_vec()
+    }
+
+    fn client() -> ReflectionClient<Channel> {
+        static FILE_DESCRIPTOR_SET: OnceLock<Vec<u8>> = OnceLock::new();
+        let reflection = Builder::configure()
+            .register_encoded_file_descriptor_set(
+                FILE_DESCRIPTOR_SET.get_or_init(file_descriptor_set),
+            )
+            .build()
+            .unwrap();
+
+        let (channel, incoming) = local::pair();
+        tokio::spawn(
+            Server::builder()
+                .add_service(reflection)
+                .serve_with_incoming(incoming),
+        );
+        ReflectionClient::new(channel)
+    }
+
+    #[tokio::test]
+    async fn lists_services() {
+        let mut services = client().list_services().await.unwrap();
+        services.sort();
+        assert_eq!(
+            services,
+            ["b.B", "grpc.reflection.v1alpha.ServerReflection"]
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_dependencies() {
+        let mut client = client();
+
+        let file = client.file_containing_symbol("b.B.Call").await.unwrap();
+        assert_eq!(file.name(), "b.proto");
+
+        let set = client.file_descriptor_set(&["b.B"]).await.unwrap();
+        let names: Vec<_> = set.file.iter().map(|f| f.name()).collect();
+        assert_eq!(names, ["a.proto", "b.proto"]);
+
+        let pool = client.descriptor_pool(&["b.B"]).await.unwrap();
+        let call = pool.get_service_by_name("b.B").unwrap().methods().next();
+        assert_eq!(call.unwrap().input().full_name(), "a.A");
+    }
+
+    #[tokio::test]
+    async fn recovers_from_errors() {
+        let mut client = client();
+
+        let status = client
+            .file_containing_symbol("b.Missing")
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        let file = client.file_by_filename("a.proto").await.unwrap();
+        assert_eq!(file.name(), "a.proto");
+    }
+}
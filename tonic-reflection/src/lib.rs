@@ -1,4 +1,8 @@
 //! A `tonic` based gRPC Server Reflection implementation.
+//!
+//! The `server` feature, enabled by default, provides the reflection service
+//! of servers, and the `client` feature a client of it, that fetches the
+//! descriptors of the services of servers for tools and dynamic gateways.
 
 #![warn(
     missing_debug_implementations,
@@ -46,3 +50,8 @@ pub mod pb {
 #[cfg(feature = "server")]
 #[cfg_attr(docsrs, doc(cfg(feature = "server")))]
 pub mod server;
+
+/// Implementation of the client component of gRPC Server Reflection.
+#[cfg(feature = "client")]
+#[cfg_attr(docsrs, doc(cfg(feature = "client")))]
+pub mod client;
@@ -12,7 +12,7 @@ use prost_types::{
 };
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
 use tokio::sync::mpsc;
 use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tonic::{Request, Response, Status, Streaming};
@@ -54,7 +54,7 @@ pub struct Builder<'b> {
 
     service_names: Vec<String>,
     use_all_service_names: bool,
-    symbols: HashMap<String, Arc<FileDescriptorProto>>,
+    excluded_symbols: Vec<String>,
 }
 
 impl<'b> Builder<'b> {
@@ -67,7 +67,7 @@ impl<'b> Builder<'b> {
 
             service_names: Vec::new(),
             use_all_service_names: true,
-            symbols: HashMap::new(),
+            excluded_symbols: Vec::new(),
         }
     }
 
@@ -89,6 +89,17 @@ impl<'b> Builder<'b> {
         self
     }
 
+    /// Registers byte slices containing encoded `prost_types::FileDescriptorSet`s with the gRPC
+    /// Reflection Service builder, such as the `FILE_DESCRIPTOR_SET` constants of the packages
+    /// generated by `tonic-build` with `embed_file_descriptor_set`.
+    ///
+    /// Files registered more than once are only served once.
+    pub fn register_all(mut self, encoded_file_descriptor_sets: &[&'b [u8]]) -> Self {
+        self.encoded_file_descriptor_sets
+            .extend_from_slice(encoded_file_descriptor_sets);
+        self
+    }
+
     /// Serve the gRPC Reflection Service descriptor via the Reflection Service. This is enabled
     /// by default - set `include` to false to disable.
     pub fn include_reflection_service(mut self, include: bool) -> Self {
@@ -106,8 +117,34 @@ impl<'b> Builder<'b> {
         self
     }
 
+    /// Exclude a fully-qualified symbol, such as the name of an internal service, and the
+    /// symbols it defines from the Reflection Service.
+    ///
+    /// Excluded services are neither advertised nor defined by the files served. Excluded
+    /// messages and enums can't be looked up, but stay in the files that define them, as other
+    /// definitions may refer to them.
+    pub fn exclude_symbol(mut self, name: impl Into<String>) -> Self {
+        self.excluded_symbols.push(name.into());
+        self
+    }
+
     /// Build a gRPC Reflection Service to be served via Tonic.
-    pub fn build(mut self) -> Result<ServerReflectionServer<impl ServerReflection>, Error> {
+    pub fn build(self) -> Result<ServerReflectionServer<impl ServerReflection>, Error> {
+        let (_, server) = self.build_with_registry()?;
+        Ok(server)
+    }
+
+    /// Build a gRPC Reflection Service to be served via Tonic, and a [`ReflectionRegistry`]
+    /// registering more file descriptor sets with it while it runs.
+    pub fn build_with_registry(
+        mut self,
+    ) -> Result<
+        (
+            ReflectionRegistry,
+            ServerReflectionServer<impl ServerReflection>,
+        ),
+        Error,
+    > {
         if self.include_reflection_service {
             self = self.register_encoded_file_descriptor_set(crate::pb::FILE_DESCRIPTOR_SET);
         }
@@ -117,42 +154,143 @@ impl<'b> Builder<'b> {
             self.file_descriptor_sets.push(decoded);
         }
 
-        let all_fds = self.file_descriptor_sets.clone();
-        let mut files: HashMap<String, Arc<FileDescriptorProto>> = HashMap::new();
+        let mut state = ReflectionServiceState {
+            service_names: Vec::new(),
+            use_all_service_names: self.use_all_service_names,
+            excluded_symbols: self.excluded_symbols,
+            files: HashMap::new(),
+            symbols: HashMap::new(),
+        };
+        for name in self.service_names {
+            if !state.is_excluded(&name) {
+                state.service_names.push(ServiceResponse { name });
+            }
+        }
+        for fds in self.file_descriptor_sets {
+            state.add_file_descriptor_set(fds)?;
+        }
 
-        for fds in all_fds {
-            for fd in fds.file {
-                let name = match fd.name.clone() {
-                    None => {
-                        return Err(Error::InvalidFileDescriptorSet("missing name".to_string()));
-                    }
-                    Some(n) => n,
-                };
+        let state = Arc::new(RwLock::new(state));
+        let registry = ReflectionRegistry {
+            state: state.clone(),
+        };
+        let server = ServerReflectionServer::new(ReflectionService { state });
+        Ok((registry, server))
+    }
+}
 
-                if files.contains_key(&name) {
-                    continue;
-                }
+/// A handle registering file descriptor sets with a running gRPC Reflection Service, such as
+/// those of plugins loaded at runtime.
+///
+/// The services of the sets are advertised unless the Reflection Service only advertises the
+/// services named by [`Builder::with_service_name`], and the sets are subject to the
+/// exclusions of [`Builder::exclude_symbol`].
+#[derive(Debug, Clone)]
+pub struct ReflectionRegistry {
+    state: Arc<RwLock<ReflectionServiceState>>,
+}
 
-                let fd = Arc::new(fd);
-                files.insert(name, fd.clone());
+impl ReflectionRegistry {
+    /// Registers an instance of `prost_types::FileDescriptorSet` with the gRPC Reflection
+    /// Service.
+    ///
+    /// Files of names that the service already serves are ignored. If the set is invalid, none
+    /// of its files is registered.
+    pub fn register_file_descriptor_set(
+        &self,
+        file_descriptor_set: FileDescriptorSet,
+    ) -> Result<(), Error> {
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        let mut updated = state.clone();
+        updated.add_file_descriptor_set(file_descriptor_set)?;
+        *state = updated;
+        Ok(())
+    }
+
+    /// Registers a byte slice containing an encoded `prost_types::FileDescriptorSet` with the
+    /// gRPC Reflection Service.
+    pub fn register_encoded_file_descriptor_set(
+        &self,
+        encoded_file_descriptor_set: &[u8],
+    ) -> Result<(), Error> {
+        let decoded = FileDescriptorSet::decode(encoded_file_descriptor_set)?;
+        self.register_file_descriptor_set(decoded)
+    }
+}
 
-                self.process_file(fd)?;
+fn extract_name(
+    prefix: &str,
+    name_type: &str,
+    maybe_name: Option<&String>,
+) -> Result<String, Error> {
+    match maybe_name {
+        None => Err(Error::InvalidFileDescriptorSet(format!(
+            "missing {} name",
+            name_type
+        ))),
+        Some(name) => {
+            if prefix.is_empty() {
+                Ok(name.to_string())
+            } else {
+                Ok(format!("{}.{}", prefix, name))
             }
         }
+    }
+}
 
-        let service_names = self
-            .service_names
-            .iter()
-            .map(|name| ServiceResponse { name: name.clone() })
-            .collect();
-
-        Ok(ServerReflectionServer::new(ReflectionService {
-            state: Arc::new(ReflectionServiceState {
-                service_names,
-                files,
-                symbols: self.symbols,
-            }),
-        }))
+#[derive(Debug, Clone)]
+struct ReflectionServiceState {
+    service_names: Vec<ServiceResponse>,
+    use_all_service_names: bool,
+    excluded_symbols: Vec<String>,
+    files: HashMap<String, Arc<FileDescriptorProto>>,
+    symbols: HashMap<String, Arc<FileDescriptorProto>>,
+}
+
+impl ReflectionServiceState {
+    fn add_file_descriptor_set(&mut self, fds: FileDescriptorSet) -> Result<(), Error> {
+        for mut fd in fds.file {
+            let name = match fd.name.clone() {
+                None => {
+                    return Err(Error::InvalidFileDescriptorSet("missing name".to_string()));
+                }
+                Some(n) => n,
+            };
+
+            if self.files.contains_key(&name) {
+                continue;
+            }
+
+            let package = fd.package().to_owned();
+            fd.service.retain(|service| {
+                let service_name = match package.as_str() {
+                    "" => service.name().to_owned(),
+                    package => format!("{}.{}", package, service.name()),
+                };
+                !self.is_excluded(&service_name)
+            });
+
+            let fd = Arc::new(fd);
+            self.files.insert(name, fd.clone());
+
+            self.process_file(fd)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether `name` is an excluded symbol or is defined by one.
+    fn is_excluded(&self, name: &str) -> bool {
+        self.excluded_symbols.iter().any(|excluded| {
+            name.strip_prefix(excluded.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    }
+
+    fn insert_symbol(&mut self, name: String, fd: Arc<FileDescriptorProto>) {
+        if !self.is_excluded(&name) {
+            self.symbols.insert(name, fd);
+        }
     }
 
     fn process_file(&mut self, fd: Arc<FileDescriptorProto>) -> Result<(), Error> {
@@ -169,13 +307,15 @@ impl<'b> Builder<'b> {
         for service in &fd.service {
             let service_name = extract_name(prefix, "service", service.name.as_ref())?;
             if self.use_all_service_names {
-                self.service_names.push(service_name.clone());
+                self.service_names.push(ServiceResponse {
+                    name: service_name.clone(),
+                });
             }
-            self.symbols.insert(service_name.clone(), fd.clone());
+            self.insert_symbol(service_name.clone(), fd.clone());
 
             for method in &service.method {
                 let method_name = extract_name(&service_name, "method", method.name.as_ref())?;
-                self.symbols.insert(method_name, fd.clone());
+                self.insert_symbol(method_name, fd.clone());
             }
         }
 
@@ -189,7 +329,7 @@ impl<'b> Builder<'b> {
         msg: &DescriptorProto,
     ) -> Result<(), Error> {
         let message_name = extract_name(prefix, "message", msg.name.as_ref())?;
-        self.symbols.insert(message_name.clone(), fd.clone());
+        self.insert_symbol(message_name.clone(), fd.clone());
 
         for nested in &msg.nested_type {
             self.process_message(fd.clone(), &message_name, nested)?;
@@ -205,7 +345,7 @@ impl<'b> Builder<'b> {
 
         for oneof in &msg.oneof_decl {
             let oneof_name = extract_name(&message_name, "oneof", oneof.name.as_ref())?;
-            self.symbols.insert(oneof_name, fd.clone());
+            self.insert_symbol(oneof_name, fd.clone());
         }
 
         Ok(())
@@ -218,11 +358,11 @@ impl<'b> Builder<'b> {
         en: &EnumDescriptorProto,
     ) -> Result<(), Error> {
         let enum_name = extract_name(prefix, "enum", en.name.as_ref())?;
-        self.symbols.insert(enum_name.clone(), fd.clone());
+        self.insert_symbol(enum_name.clone(), fd.clone());
 
         for value in &en.value {
             let value_name = extract_name(&enum_name, "enum value", value.name.as_ref())?;
-            self.symbols.insert(value_name, fd.clone());
+            self.insert_symbol(value_name, fd.clone());
         }
 
         Ok(())
@@ -235,39 +375,10 @@ impl<'b> Builder<'b> {
         field: &FieldDescriptorProto,
     ) -> Result<(), Error> {
         let field_name = extract_name(prefix, "field", field.name.as_ref())?;
-        self.symbols.insert(field_name, fd);
+        self.insert_symbol(field_name, fd);
         Ok(())
     }
-}
-
-fn extract_name(
-    prefix: &str,
-    name_type: &str,
-    maybe_name: Option<&String>,
-) -> Result<String, Error> {
-    match maybe_name {
-        None => Err(Error::InvalidFileDescriptorSet(format!(
-            "missing {} name",
-            name_type
-        ))),
-        Some(name) => {
-            if prefix.is_empty() {
-                Ok(name.to_string())
-            } else {
-                Ok(format!("{}.{}", prefix, name))
-            }
-        }
-    }
-}
 
-#[derive(Debug)]
-struct ReflectionServiceState {
-    service_names: Vec<ServiceResponse>,
-    files: HashMap<String, Arc<FileDescriptorProto>>,
-    symbols: HashMap<String, Arc<FileDescriptorProto>>,
-}
-
-impl ReflectionServiceState {
     fn list_services(&self) -> MessageResponse {
         MessageResponse::ListServicesResponse(ListServiceResponse {
             service: self.service_names.clone(),
@@ -313,7 +424,7 @@ impl ReflectionServiceState {
 
 #[derive(Debug)]
 struct ReflectionService {
-    state: Arc<ReflectionServiceState>,
+    state: Arc<RwLock<ReflectionServiceState>>,
 }
 
 #[tonic::async_trait]
@@ -338,23 +449,26 @@ impl ServerReflection for ReflectionService {
                     }
                 };
 
-                let resp_msg = match req.message_request.clone() {
-                    None => Err(Status::invalid_argument("invalid MessageRequest")),
-                    Some(msg) => match msg {
-                        MessageRequest::FileByFilename(s) => state.file_by_filename(&s),
-                        MessageRequest::FileContainingSymbol(s) => state.symbol_by_name(&s),
-                        MessageRequest::FileContainingExtension(_) => {
-                            Err(Status::not_found("extensions are not supported"))
-                        }
-                        MessageRequest::AllExtensionNumbersOfType(_) => {
-                            // NOTE: Workaround. Some grpc clients (e.g. grpcurl) expect this method not to fail.
-                            // https://github.com/hyperium/tonic/issues/1077
-                            Ok(MessageResponse::AllExtensionNumbersResponse(
-                                ExtensionNumberResponse::default(),
-                            ))
-                        }
-                        MessageRequest::ListServices(_) => Ok(state.list_services()),
-                    },
+                let resp_msg = {
+                    let state = state.read().unwrap_or_else(PoisonError::into_inner);
+                    match req.message_request.clone() {
+                        None => Err(Status::invalid_argument("invalid MessageRequest")),
+                        Some(msg) => match msg {
+                            MessageRequest::FileByFilename(s) => state.file_by_filename(&s),
+                            MessageRequest::FileContainingSymbol(s) => state.symbol_by_name(&s),
+                            MessageRequest::FileContainingExtension(_) => {
+                                Err(Status::not_found("extensions are not supported"))
+                            }
+                            MessageRequest::AllExtensionNumbersOfType(_) => {
+                                // NOTE: Workaround. Some grpc clients (e.g. grpcurl) expect this method not to fail.
+                                // https://github.com/hyperium/tonic/issues/1077
+                                Ok(MessageResponse::AllExtensionNumbersResponse(
+                                    ExtensionNumberResponse::default(),
+                                ))
+                            }
+                            MessageRequest::ListServices(_) => Ok(state.list_services()),
+                        },
+                    }
                 };
 
                 match resp_msg {
@@ -2,14 +2,14 @@ use prost::Message;
 use std::net::SocketAddr;
 use tokio::sync::oneshot;
 use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
-use tonic::{transport::Server, Request};
+use tonic::{transport::Server, Code, Request, Status};
 use tonic_reflection::{
     pb::{
         server_reflection_client::ServerReflectionClient,
         server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
         ServerReflectionRequest, ServiceResponse, FILE_DESCRIPTOR_SET,
     },
-    server::Builder,
+    server::{Builder, ServerReflection, ServerReflectionServer},
 };
 
 pub(crate) fn get_encoded_reflection_service_fd() -> Vec<u8> {
@@ -90,7 +90,126 @@ async fn test_file_containing_symbol() {
     }
 }
 
+#[tokio::test]
+async fn test_excluded_symbols() {
+    let service = Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .exclude_symbol("grpc.reflection.v1alpha.ServerReflection")
+        .build()
+        .unwrap();
+    let response = make_reflection_request(
+        service,
+        ServerReflectionRequest {
+            host: "".to_string(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        },
+    )
+    .await;
+
+    if let Ok(MessageResponse::ListServicesResponse(services)) = response {
+        assert_eq!(services.service, vec![]);
+    } else {
+        panic!("Expected a ListServicesResponse variant");
+    }
+
+    let service = Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .exclude_symbol("grpc.reflection.v1alpha.ServerReflection")
+        .build()
+        .unwrap();
+    let status = make_reflection_request(
+        service,
+        ServerReflectionRequest {
+            host: "".to_string(),
+            message_request: Some(MessageRequest::FileContainingSymbol(String::from(
+                "grpc.reflection.v1alpha.ServerReflection.ServerReflectionInfo",
+            ))),
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_registry() {
+    let (registry, service) = Builder::configure()
+        .include_reflection_service(false)
+        .build_with_registry()
+        .unwrap();
+    registry
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .unwrap();
+
+    let response = make_reflection_request(
+        service,
+        ServerReflectionRequest {
+            host: "".to_string(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        },
+    )
+    .await;
+
+    if let Ok(MessageResponse::ListServicesResponse(services)) = response {
+        assert_eq!(
+            services.service,
+            vec![ServiceResponse {
+                name: String::from("grpc.reflection.v1alpha.ServerReflection")
+            }]
+        );
+    } else {
+        panic!("Expected a ListServicesResponse variant");
+    }
+
+    assert!(registry
+        .register_encoded_file_descriptor_set(b"invalid")
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_register_all() {
+    // The same files registered twice are served once.
+    let service = Builder::configure()
+        .include_reflection_service(false)
+        .register_all(&[FILE_DESCRIPTOR_SET, FILE_DESCRIPTOR_SET])
+        .build()
+        .unwrap();
+    let response = make_reflection_request(
+        service,
+        ServerReflectionRequest {
+            host: "".to_string(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        },
+    )
+    .await;
+
+    if let Ok(MessageResponse::ListServicesResponse(services)) = response {
+        assert_eq!(
+            services.service,
+            vec![ServiceResponse {
+                name: String::from("grpc.reflection.v1alpha.ServerReflection")
+            }]
+        );
+    } else {
+        panic!("Expected a ListServicesResponse variant");
+    }
+}
+
 async fn make_test_reflection_request(request: ServerReflectionRequest) -> MessageResponse {
+    let service = Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .build()
+        .unwrap();
+
+    make_reflection_request(service, request)
+        .await
+        .expect("successful response")
+}
+
+async fn make_reflection_request(
+    service: ServerReflectionServer<impl ServerReflection>,
+    request: ServerReflectionRequest,
+) -> Result<MessageResponse, Status> {
     // Run a test server
     let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
@@ -98,11 +217,6 @@ async fn make_test_reflection_request(request: ServerReflectionRequest) -> Messa
     let listener = tokio::net::TcpListener::bind(addr).await.expect("bind");
     let local_addr = format!("http://{}", listener.local_addr().expect("local address"));
     let jh = tokio::spawn(async move {
-        let service = Builder::configure()
-            .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
-            .build()
-            .unwrap();
-
         Server::builder()
             .add_service(service)
             .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async {
@@ -134,9 +248,7 @@ async fn make_test_reflection_request(request: ServerReflectionRequest) -> Messa
         .next()
         .await
         .expect("steamed response")
-        .expect("successful response")
-        .message_response
-        .expect("some MessageResponse");
+        .map(|response| response.message_response.expect("some MessageResponse"));
 
     // We only expect one response per request
     assert!(inbound.next().await.is_none());
@@ -0,0 +1,127 @@
+//! The parts of `google/protobuf/descriptor.proto` and `google/api/http.proto`
+//! used by transcoding.
+//!
+//! `prost_types` drops the extensions of options, so `MethodOptions` is
+//! declared here along with the `google.api.http` extension.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct FileDescriptorSet {
+    #[prost(message, repeated, tag = "1")]
+    pub(crate) file: Vec<FileDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct FileDescriptorProto {
+    #[prost(string, optional, tag = "2")]
+    pub(crate) package: Option<String>,
+    #[prost(message, repeated, tag = "4")]
+    pub(crate) message_type: Vec<DescriptorProto>,
+    #[prost(message, repeated, tag = "6")]
+    pub(crate) service: Vec<ServiceDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DescriptorProto {
+    #[prost(string, optional, tag = "1")]
+    pub(crate) name: Option<String>,
+    #[prost(message, repeated, tag = "2")]
+    pub(crate) field: Vec<FieldDescriptorProto>,
+    #[prost(message, repeated, tag = "3")]
+    pub(crate) nested_type: Vec<DescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct FieldDescriptorProto {
+    #[prost(string, optional, tag = "1")]
+    pub(crate) name: Option<String>,
+    #[prost(int32, optional, tag = "4")]
+    pub(crate) label: Option<i32>,
+    #[prost(int32, optional, tag = "5")]
+    pub(crate) r#type: Option<i32>,
+    #[prost(string, optional, tag = "6")]
+    pub(crate) type_name: Option<String>,
+    #[prost(string, optional, tag = "10")]
+    pub(crate) json_name: Option<String>,
+}
+
+pub(crate) const LABEL_REPEATED: i32 = 3;
+
+/// The values of `FieldDescriptorProto.Type`.
+pub(crate) mod field_type {
+    pub(crate) const DOUBLE: i32 = 1;
+    pub(crate) const FLOAT: i32 = 2;
+    pub(crate) const INT64: i32 = 3;
+    pub(crate) const UINT64: i32 = 4;
+    pub(crate) const INT32: i32 = 5;
+    pub(crate) const FIXED64: i32 = 6;
+    pub(crate) const FIXED32: i32 = 7;
+    pub(crate) const BOOL: i32 = 8;
+    pub(crate) const MESSAGE: i32 = 11;
+    pub(crate) const UINT32: i32 = 13;
+    pub(crate) const SFIXED32: i32 = 15;
+    pub(crate) const SFIXED64: i32 = 16;
+    pub(crate) const SINT32: i32 = 17;
+    pub(crate) const SINT64: i32 = 18;
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct ServiceDescriptorProto {
+    #[prost(string, optional, tag = "1")]
+    pub(crate) name: Option<String>,
+    #[prost(message, repeated, tag = "2")]
+    pub(crate) method: Vec<MethodDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct MethodDescriptorProto {
+    #[prost(string, optional, tag = "1")]
+    pub(crate) name: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub(crate) input_type: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub(crate) output_type: Option<String>,
+    #[prost(message, optional, tag = "4")]
+    pub(crate) options: Option<MethodOptions>,
+    #[prost(bool, optional, tag = "5")]
+    pub(crate) client_streaming: Option<bool>,
+    #[prost(bool, optional, tag = "6")]
+    pub(crate) server_streaming: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct MethodOptions {
+    /// The `google.api.http` extension.
+    #[prost(message, optional, tag = "72295728")]
+    pub(crate) http: Option<HttpRule>,
+}
+
+/// A `google.api.HttpRule`, with the fields of its `pattern` oneof.
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct HttpRule {
+    #[prost(string, optional, tag = "2")]
+    pub(crate) get: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub(crate) put: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub(crate) post: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub(crate) delete: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub(crate) patch: Option<String>,
+    #[prost(string, tag = "7")]
+    pub(crate) body: String,
+    #[prost(message, optional, tag = "8")]
+    pub(crate) custom: Option<CustomHttpPattern>,
+    #[prost(message, repeated, tag = "11")]
+    pub(crate) additional_bindings: Vec<HttpRule>,
+    #[prost(string, tag = "12")]
+    pub(crate) response_body: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct CustomHttpPattern {
+    #[prost(string, tag = "1")]
+    pub(crate) kind: String,
+    #[prost(string, tag = "2")]
+    pub(crate) path: String,
+}
@@ -0,0 +1,266 @@
+//! HTTP/JSON transcoding for `tonic` servers, in the manner of
+//! [grpc-gateway].
+//!
+//! A [`Transcoder`] serves the RESTful routes of the [`google.api.http`]
+//! annotations of the methods of file descriptor sets, by calling the gRPC
+//! services of the methods with JSON messages:
+//!
+//! - the variables of the path template of a route, such as the `name` of
+//!   `/v1/{name=shelves/*}`, set the fields of the request message,
+//! - the body sets the whole message with `body: "*"`, or one of its fields,
+//! - and the query parameters set the fields that neither of them set.
+//!
+//! Responses are the JSON of the response message, or of its field of the
+//! `response_body` of the route, with the HTTP status of the gRPC status of
+//! failed calls and a `google.rpc.Status` JSON body. Server streaming methods
+//! respond with a message per line, and client streaming methods take a
+//! message per line of their body.
+//!
+//! The services are called with `application/grpc+json` requests, so they
+//! must decode and encode JSON messages, such as with the
+//! `tonic::codec::ProstJsonCodec` codec of the `json` feature of `tonic`. The
+//! descriptors only tell the routes and the types of the fields of their
+//! messages.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use tonic::transport::{server::Routes, Server};
+//! use tonic_transcoding::Builder;
+//!
+//! let routes = Routes::new(GreeterServer::new(MyGreeter::default()));
+//! let routes = Builder::configure()
+//!     .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+//!     .build()?
+//!     .mount(routes);
+//!
+//! Server::builder().add_routes(routes).serve(addr).await?;
+//! ```
+//!
+//! [grpc-gateway]: https://github.com/grpc-ecosystem/grpc-gateway
+//! [`google.api.http`]: https://github.com/googleapis/googleapis/blob/master/google/api/http.proto
+
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
+)]
+#![doc(html_root_url = "https://docs.rs/tonic-transcoding/0.11.0")]
+#![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
+
+mod descriptor;
+mod rule;
+mod service;
+mod template;
+
+pub use service::TranscodingService;
+
+use prost::{DecodeError, Message};
+use rule::Rules;
+use std::{
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
+use tonic::transport::server::Routes;
+
+/// Represents an error in the construction of a [`Transcoder`].
+#[derive(Debug)]
+pub enum Error {
+    /// An error was encountered decoding a `FileDescriptorSet` from a buffer.
+    DecodeError(prost::DecodeError),
+    /// An invalid `google.api.http` rule was encountered.
+    InvalidHttpRule(String),
+}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Self {
+        Error::DecodeError(e)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::DecodeError(_) => f.write_str("error decoding FileDescriptorSet from buffer"),
+            Error::InvalidHttpRule(s) => write!(f, "invalid HTTP rule - {}", s),
+        }
+    }
+}
+
+/// A builder used to construct a [`Transcoder`].
+#[derive(Debug, Default)]
+pub struct Builder<'b> {
+    encoded_file_descriptor_sets: Vec<&'b [u8]>,
+}
+
+impl<'b> Builder<'b> {
+    /// Create a new builder that can configure a [`Transcoder`].
+    pub fn configure() -> Self {
+        Self::default()
+    }
+
+    /// Registers a byte slice containing an encoded `prost_types::FileDescriptorSet` with
+    /// the transcoder, whose methods with `google.api.http` options are routed.
+    ///
+    /// The set must be encoded with the options of its methods, which
+    /// `protoc` keeps in the `file_descriptor_set_path` of `tonic-build`.
+    pub fn register_encoded_file_descriptor_set(
+        mut self,
+        encoded_file_descriptor_set: &'b [u8],
+    ) -> Self {
+        self.encoded_file_descriptor_sets
+            .push(encoded_file_descriptor_set);
+        self
+    }
+
+    /// Build a [`Transcoder`] of the routes of the registered file
+    /// descriptor sets.
+    pub fn build(self) -> Result<Transcoder, Error> {
+        let mut rules = Rules::default();
+        for encoded in self.encoded_file_descriptor_sets {
+            rules.add(&descriptor::FileDescriptorSet::decode(encoded)?)?;
+        }
+        Ok(Transcoder {
+            rules: Arc::new(rules),
+        })
+    }
+}
+
+/// The HTTP/JSON routes of the methods of file descriptor sets, see the
+/// [crate documentation](crate).
+#[derive(Debug, Clone)]
+pub struct Transcoder {
+    rules: Arc<Rules>,
+}
+
+impl Transcoder {
+    /// Whether none of the methods of the file descriptor sets has a
+    /// `google.api.http` rule.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Add the routes of the transcoder to `routes`, calling their services.
+    ///
+    /// The gRPC requests still reach their services as they did, and the
+    /// requests that neither a service nor a route matches get a
+    /// `404 Not Found` response, or an `UNIMPLEMENTED` status for gRPC
+    /// requests.
+    pub fn mount(self, routes: Routes) -> Routes {
+        let service = self.service(routes.clone());
+        routes.fallback_service(service)
+    }
+
+    /// A [`Service`](tower_service::Service) serving the routes of the
+    /// transcoder by calling `inner`.
+    pub fn service<S>(self, inner: S) -> TranscodingService<S> {
+        TranscodingService::new(self.rules, inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{header, Method, Request, StatusCode};
+    use serde_json::{json, Value};
+    use tonic::codec::JsonCodec;
+    use tower::ServiceExt;
+
+    /// A server of the `test.Shelves` methods.
+    fn shelves() -> Routes {
+        let set = rule::tests::shelves().encode_to_vec();
+        let transcoder = Builder::configure()
+            .register_encoded_file_descriptor_set(&set)
+            .build()
+            .unwrap();
+
+        let server = tower::service_fn(|request: Request<hyper::Body>| async move {
+            let mut grpc = tonic::server::Grpc::new(JsonCodec::<Value, Value>::default());
+            let response = match request.uri().path() {
+                "/test.Shelves/GetBook" => {
+                    let get = tower::service_fn(|request: tonic::Request<Value>| async move {
+                        let name = request.get_ref()["name"].as_str().unwrap().to_owned();
+                        if name.ends_with("/0") {
+                            return Err(tonic::Status::not_found(name));
+                        }
+                        Ok(tonic::Response::new(json!({"name": name, "pageCount": 1})))
+                    });
+                    grpc.unary(get, request).await
+                }
+                "/test.Shelves/ListBooks" => {
+                    let list = tower::service_fn(|request: tonic::Request<Value>| async move {
+                        let tags = request.get_ref()["tags"].as_array().cloned();
+                        let books = tags
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|tag| Ok::<_, tonic::Status>(json!({"name": tag})));
+                        let books = futures_util::stream::iter(books);
+                        Ok(tonic::Response::new(
+                            Box::pin(books) as tonic::codegen::BoxStream<_>
+                        ))
+                    });
+                    grpc.server_streaming(list, request).await
+                }
+                _ => unreachable!(),
+            };
+            Ok::<_, std::convert::Infallible>(response)
+        });
+        transcoder.mount(Routes::from(
+            axum::Router::new().route_service("/test.Shelves/*rest", server),
+        ))
+    }
+
+    async fn call(method: Method, uri: &str) -> (StatusCode, String, String) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(hyper::Body::empty())
+            .unwrap();
+        let response = shelves().oneshot(request).await.unwrap();
+        let content_type = response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (
+            status,
+            content_type,
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn transcodes_unary_calls() {
+        let (status, content_type, body) = call(Method::GET, "/v1/shelves/1/books/2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/json");
+        // The `response_body` of the rule.
+        assert_eq!(body, r#""shelves/1/books/2""#);
+
+        let (status, _, body) = call(Method::GET, "/v1/shelves/1/books/0").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"code": 5, "message": "shelves/1/books/0", "details": []})
+        );
+
+        let (status, _, _) = call(Method::GET, "/v2/books").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn transcodes_server_streaming_calls() {
+        let (status, content_type, body) = call(Method::GET, "/v1/books?tags=a&tags=b").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/x-ndjson");
+        assert_eq!(body, "{\"name\":\"a\"}\n{\"name\":\"b\"}\n");
+    }
+}
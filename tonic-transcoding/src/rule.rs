@@ -0,0 +1,637 @@
+//! The HTTP rules of methods, and how they build the JSON messages of
+//! requests.
+
+use crate::{
+    descriptor::{field_type, DescriptorProto, FileDescriptorSet, HttpRule, LABEL_REPEATED},
+    template::{Binding, PathTemplate},
+    Error,
+};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tonic::Status;
+
+#[derive(Debug)]
+pub(crate) struct Field {
+    name: String,
+    json_name: String,
+    kind: i32,
+    repeated: bool,
+    type_name: Option<String>,
+}
+
+/// The fields of the messages of the file descriptor sets, by the full name
+/// of their message, such as `.helloworld.HelloRequest`.
+#[derive(Debug, Default)]
+pub(crate) struct Messages(HashMap<String, Vec<Field>>);
+
+impl Messages {
+    fn add(&mut self, scope: &str, message: &DescriptorProto) {
+        let name = format!("{}.{}", scope, message.name());
+        for nested in &message.nested_type {
+            self.add(&name, nested);
+        }
+
+        let fields = message.field.iter().map(|field| Field {
+            name: field.name().to_owned(),
+            json_name: field
+                .json_name
+                .clone()
+                .unwrap_or_else(|| to_json_name(field.name())),
+            kind: field.r#type(),
+            repeated: field.label() == LABEL_REPEATED,
+            type_name: field.type_name.clone(),
+        });
+        self.0.insert(name, fields.collect());
+    }
+
+    /// The field of `message` named `name`, by its name or its JSON name.
+    ///
+    /// Returns `Ok(None)` for the fields of unknown messages, and an error
+    /// for the unknown fields of known messages.
+    fn field(&self, message: Option<&str>, name: &str) -> Result<Option<&Field>, String> {
+        let Some(fields) = message.and_then(|message| self.0.get(message)) else {
+            return Ok(None);
+        };
+        fields
+            .iter()
+            .find(|field| field.name == name || field.json_name == name)
+            .map(Some)
+            .ok_or_else(|| format!("unknown field `{}`", name))
+    }
+
+    /// Check that `field_path` names a field of `message`.
+    fn check(&self, message: &str, field_path: &[String]) -> Result<(), String> {
+        let mut message = Some(message);
+        for name in field_path {
+            message = self
+                .field(message, name)?
+                .and_then(|field| field.type_name.as_deref());
+        }
+        Ok(())
+    }
+
+    /// Set the field at `field_path` of `object`, a `message`, to `value`,
+    /// converted to the JSON of the type of the field.
+    fn set(
+        &self,
+        message: Option<&str>,
+        object: &mut Map<String, Value>,
+        field_path: &[String],
+        value: &str,
+    ) -> Result<(), String> {
+        let (name, rest) = field_path.split_first().expect("field paths aren't empty");
+        let field = self.field(message, name)?;
+        let key = field.map_or(name, |field| &field.json_name).clone();
+
+        if !rest.is_empty() {
+            let nested = object
+                .entry(key)
+                .or_insert_with(|| Value::Object(Map::new()));
+            let Value::Object(nested) = nested else {
+                return Err(format!("field `{}` isn't a message", name));
+            };
+            let message = field.and_then(|field| field.type_name.as_deref());
+            return self.set(message, nested, rest, value);
+        }
+
+        let value = match field {
+            Some(field) => field.convert(value)?,
+            None => Value::String(value.to_owned()),
+        };
+        match field {
+            Some(field) if field.repeated => match object.entry(key).or_insert(Value::Null) {
+                Value::Array(values) => values.push(value),
+                other => *other = Value::Array(vec![value]),
+            },
+            _ => {
+                object.insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// The JSON name of the field `name` of `message`.
+    fn json_name(&self, message: &str, name: &str) -> String {
+        match self.field(Some(message), name) {
+            Ok(Some(field)) => field.json_name.clone(),
+            _ => name.to_owned(),
+        }
+    }
+}
+
+/// The well-known messages represented as strings in JSON.
+const STRING_MESSAGES: [&str; 3] = [
+    ".google.protobuf.Duration",
+    ".google.protobuf.FieldMask",
+    ".google.protobuf.Timestamp",
+];
+
+impl Field {
+    /// Convert the value of a path or query parameter to the JSON of the
+    /// field, per the protobuf JSON mapping.
+    fn convert(&self, value: &str) -> Result<Value, String> {
+        let invalid = || format!("invalid value `{}` for field `{}`", value, self.name);
+        Ok(match self.kind {
+            field_type::INT32
+            | field_type::SINT32
+            | field_type::SFIXED32
+            | field_type::UINT32
+            | field_type::FIXED32 => value.parse::<i64>().map_err(|_| invalid())?.into(),
+            field_type::DOUBLE | field_type::FLOAT => match value {
+                "NaN" | "Infinity" | "-Infinity" => value.into(),
+                _ => value.parse::<f64>().map_err(|_| invalid())?.into(),
+            },
+            field_type::BOOL => value.parse::<bool>().map_err(|_| invalid())?.into(),
+            field_type::INT64
+            | field_type::SINT64
+            | field_type::SFIXED64
+            | field_type::UINT64
+            | field_type::FIXED64 => {
+                value.parse::<i128>().map_err(|_| invalid())?;
+                value.into()
+            }
+            field_type::MESSAGE
+                if !STRING_MESSAGES.contains(&self.type_name.as_deref().unwrap_or_default()) =>
+            {
+                return Err(format!("field `{}` is a message", self.name))
+            }
+            // Strings, bytes, enums, and the well-known types represented as
+            // strings.
+            _ => value.into(),
+        })
+    }
+}
+
+/// The default JSON name of a field, in lower camel case.
+fn to_json_name(name: &str) -> String {
+    let mut json_name = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            json_name.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            json_name.push(c);
+        }
+    }
+    json_name
+}
+
+/// Where the body of requests goes in their message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BodyRule {
+    /// Requests have no body.
+    None,
+    /// The body is the whole message, `body: "*"`.
+    Message,
+    /// The body is a field of the message.
+    Field(String),
+}
+
+/// A route to a method.
+#[derive(Debug)]
+pub(crate) struct Rule {
+    method: http::Method,
+    template: PathTemplate,
+    /// The path of the method, such as `/helloworld.Greeter/SayHello`.
+    pub(crate) grpc_path: String,
+    input_type: String,
+    output_type: String,
+    body: BodyRule,
+    response_body: Option<String>,
+    pub(crate) client_streaming: bool,
+    pub(crate) server_streaming: bool,
+}
+
+/// The rules of file descriptor sets, in the order of their methods.
+#[derive(Debug, Default)]
+pub(crate) struct Rules {
+    rules: Vec<Rule>,
+    messages: Messages,
+}
+
+impl Rules {
+    pub(crate) fn add(&mut self, set: &FileDescriptorSet) -> Result<(), Error> {
+        for file in &set.file {
+            let scope = match file.package() {
+                "" => String::new(),
+                package => format!(".{}", package),
+            };
+            for message in &file.message_type {
+                self.messages.add(&scope, message);
+            }
+        }
+
+        for file in &set.file {
+            let package = match file.package() {
+                "" => String::new(),
+                package => format!("{}.", package),
+            };
+            for service in &file.service {
+                for method in &service.method {
+                    let Some(http) = method.options.as_ref().and_then(|o| o.http.as_ref()) else {
+                        continue;
+                    };
+                    let grpc_path = format!("/{}{}/{}", package, service.name(), method.name());
+                    for http in std::iter::once(http).chain(&http.additional_bindings) {
+                        let rule = Rule::new(
+                            http,
+                            grpc_path.clone(),
+                            method.input_type().to_owned(),
+                            method.output_type().to_owned(),
+                            method.client_streaming(),
+                            method.server_streaming(),
+                        )
+                        .map_err(|message| {
+                            Error::InvalidHttpRule(format!("{}: {}", grpc_path, message))
+                        })?;
+                        rule.check(&self.messages).map_err(|message| {
+                            Error::InvalidHttpRule(format!("{}: {}", grpc_path, message))
+                        })?;
+                        self.rules.push(rule);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The rule matching a request, with the bindings of its path.
+    pub(crate) fn find(
+        &self,
+        method: &http::Method,
+        path: &str,
+    ) -> Option<(&Rule, Vec<Binding<'_>>)> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.method == method)
+            .find_map(|rule| Some((rule, rule.template.matches(path)?)))
+    }
+
+    pub(crate) fn messages(&self) -> &Messages {
+        &self.messages
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Rule {
+    fn new(
+        http: &HttpRule,
+        grpc_path: String,
+        input_type: String,
+        output_type: String,
+        client_streaming: bool,
+        server_streaming: bool,
+    ) -> Result<Self, String> {
+        let (method, template) = if let Some(path) = &http.get {
+            (http::Method::GET, path)
+        } else if let Some(path) = &http.put {
+            (http::Method::PUT, path)
+        } else if let Some(path) = &http.post {
+            (http::Method::POST, path)
+        } else if let Some(path) = &http.delete {
+            (http::Method::DELETE, path)
+        } else if let Some(path) = &http.patch {
+            (http::Method::PATCH, path)
+        } else if let Some(custom) = &http.custom {
+            let method = custom
+                .kind
+                .parse()
+                .map_err(|_| format!("invalid HTTP method `{}`", custom.kind))?;
+            (method, &custom.path)
+        } else {
+            return Err("the rule has no pattern".to_owned());
+        };
+
+        let body = match http.body.as_str() {
+            "" => BodyRule::None,
+            "*" => BodyRule::Message,
+            field => BodyRule::Field(field.to_owned()),
+        };
+        if client_streaming && body == BodyRule::None {
+            return Err("client streaming methods need a body".to_owned());
+        }
+
+        Ok(Rule {
+            method,
+            template: PathTemplate::parse(template)?,
+            grpc_path,
+            input_type,
+            output_type,
+            body,
+            response_body: Some(http.response_body.clone()).filter(|field| !field.is_empty()),
+            client_streaming,
+            server_streaming,
+        })
+    }
+
+    fn check(&self, messages: &Messages) -> Result<(), String> {
+        for field_path in self.template.field_paths() {
+            messages.check(&self.input_type, field_path)?;
+        }
+        if let BodyRule::Field(field) = &self.body {
+            messages.check(&self.input_type, std::slice::from_ref(field))?;
+        }
+        if let Some(field) = &self.response_body {
+            messages.check(&self.output_type, std::slice::from_ref(field))?;
+        }
+        Ok(())
+    }
+
+    /// The JSON messages of a request, from the bindings of its path, its
+    /// query and its body, a message per line for client streaming methods.
+    pub(crate) fn requests(
+        &self,
+        messages: &Messages,
+        bindings: &[Binding<'_>],
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Status> {
+        let bodies: Vec<&[u8]> = if self.client_streaming {
+            body.split(|b| *b == b'\n')
+                .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+                .collect()
+        } else {
+            vec![body]
+        };
+
+        bodies
+            .into_iter()
+            .map(|body| {
+                let message = self.request(messages, bindings, query, body)?;
+                serde_json::to_vec(&message).map_err(|e| Status::internal(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn request(
+        &self,
+        messages: &Messages,
+        bindings: &[Binding<'_>],
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<Value, Status> {
+        let parse = |body: &[u8]| -> Result<Value, Status> {
+            if body.iter().all(u8::is_ascii_whitespace) {
+                return Ok(Value::Object(Map::new()));
+            }
+            serde_json::from_slice(body)
+                .map_err(|e| Status::invalid_argument(format!("invalid JSON body: {}", e)))
+        };
+
+        let mut message = Map::new();
+        match &self.body {
+            BodyRule::None => {}
+            BodyRule::Message => match parse(body)? {
+                Value::Object(object) => message = object,
+                _ => return Err(Status::invalid_argument("the body must be a JSON object")),
+            },
+            BodyRule::Field(field) => {
+                let name = messages.json_name(&self.input_type, field);
+                message.insert(name, parse(body)?);
+            }
+        }
+
+        let input_type = Some(self.input_type.as_str());
+        // The query sets the fields that neither the body nor the path set.
+        if self.body != BodyRule::Message {
+            for (name, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+                let field_path: Vec<String> = name.split('.').map(str::to_owned).collect();
+                if field_path.iter().any(String::is_empty) {
+                    continue;
+                }
+                match messages.set(input_type, &mut message, &field_path, &value) {
+                    Ok(()) => {}
+                    // Unknown parameters are ignored, like cache busters.
+                    Err(_) if messages.check(&self.input_type, &field_path).is_err() => {}
+                    Err(error) => return Err(Status::invalid_argument(error)),
+                }
+            }
+        }
+        for (field_path, value) in bindings {
+            messages
+                .set(input_type, &mut message, field_path, value)
+                .map_err(Status::invalid_argument)?;
+        }
+
+        Ok(Value::Object(message))
+    }
+
+    /// The JSON name of the field of the `response_body` of the rule.
+    pub(crate) fn response_field(&self, messages: &Messages) -> Option<String> {
+        let field = self.response_body.as_ref()?;
+        Some(messages.json_name(&self.output_type, field))
+    }
+}
+
+/// The JSON of a response message, or of its field `field`, the
+/// [`response_field`](Rule::response_field) of its rule.
+pub(crate) fn response(field: Option<&str>, message: &[u8]) -> Result<Vec<u8>, Status> {
+    let Some(field) = field else {
+        return Ok(message.to_vec());
+    };
+    let mut message: Value =
+        serde_json::from_slice(message).map_err(|e| Status::internal(e.to_string()))?;
+    let value = message
+        .get_mut(field)
+        .map(Value::take)
+        .unwrap_or(Value::Null);
+    serde_json::to_vec(&value).map_err(|e| Status::internal(e.to_string()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::descriptor::{
+        FieldDescriptorProto, FileDescriptorProto, MethodDescriptorProto, MethodOptions,
+        ServiceDescriptorProto,
+    };
+
+    pub(crate) fn field(name: &str, kind: i32, type_name: Option<&str>) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_owned()),
+            label: Some(1),
+            r#type: Some(kind),
+            type_name: type_name.map(str::to_owned),
+            json_name: Some(to_json_name(name)),
+        }
+    }
+
+    /// A `test.Shelves` service, with its `GetBook`, `UpdateBook` and
+    /// `ListBooks` methods.
+    pub(crate) fn shelves() -> FileDescriptorSet {
+        let message = |name: &str, field: Vec<FieldDescriptorProto>| DescriptorProto {
+            name: Some(name.to_owned()),
+            field,
+            nested_type: Vec::new(),
+        };
+        let mut tags = field("tags", 9, None);
+        tags.label = Some(LABEL_REPEATED);
+        let method = |name: &str, input: &str, http: HttpRule| MethodDescriptorProto {
+            name: Some(name.to_owned()),
+            input_type: Some(format!(".test.{}", input)),
+            output_type: Some(".test.Book".to_owned()),
+            options: Some(MethodOptions { http: Some(http) }),
+            client_streaming: None,
+            server_streaming: Some(name == "ListBooks"),
+        };
+
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                package: Some("test".to_owned()),
+                message_type: vec![
+                    message(
+                        "Book",
+                        vec![
+                            field("name", 9, None),
+                            field("page_count", field_type::INT32, None),
+                            tags,
+                        ],
+                    ),
+                    message(
+                        "GetBookRequest",
+                        vec![
+                            field("name", 9, None),
+                            field("read_only", field_type::BOOL, None),
+                        ],
+                    ),
+                    message(
+                        "UpdateBookRequest",
+                        vec![
+                            field("book", field_type::MESSAGE, Some(".test.Book")),
+                            field("version", field_type::INT64, None),
+                        ],
+                    ),
+                ],
+                service: vec![ServiceDescriptorProto {
+                    name: Some("Shelves".to_owned()),
+                    method: vec![
+                        method(
+                            "GetBook",
+                            "GetBookRequest",
+                            HttpRule {
+                                get: Some("/v1/{name=shelves/*/books/*}".to_owned()),
+                                response_body: "name".to_owned(),
+                                ..Default::default()
+                            },
+                        ),
+                        method(
+                            "UpdateBook",
+                            "UpdateBookRequest",
+                            HttpRule {
+                                patch: Some("/v1/{book.name=shelves/*/books/*}".to_owned()),
+                                body: "book".to_owned(),
+                                ..Default::default()
+                            },
+                        ),
+                        method(
+                            "ListBooks",
+                            "Book",
+                            HttpRule {
+                                get: Some("/v1/books".to_owned()),
+                                additional_bindings: vec![HttpRule {
+                                    post: Some("/v1/books:list".to_owned()),
+                                    body: "*".to_owned(),
+                                    ..Default::default()
+                                }],
+                                ..Default::default()
+                            },
+                        ),
+                    ],
+                }],
+            }],
+        }
+    }
+
+    fn request(method: http::Method, uri: &str, body: &str) -> Result<(String, Value), Status> {
+        let mut rules = Rules::default();
+        rules.add(&shelves()).unwrap();
+        let uri: http::Uri = uri.parse().unwrap();
+        let (rule, bindings) = rules.find(&method, uri.path()).expect("a rule matches");
+        let mut requests =
+            rule.requests(rules.messages(), &bindings, uri.query(), body.as_bytes())?;
+        assert_eq!(requests.len(), 1);
+        let message = serde_json::from_slice(&requests.remove(0)).unwrap();
+        Ok((rule.grpc_path.clone(), message))
+    }
+
+    #[test]
+    fn binds_paths_and_queries() {
+        let (path, message) = request(
+            http::Method::GET,
+            "/v1/shelves/1/books/2?readOnly=true&unknown=1",
+            "",
+        )
+        .unwrap();
+        assert_eq!(path, "/test.Shelves/GetBook");
+        assert_eq!(
+            message,
+            serde_json::json!({"name": "shelves/1/books/2", "readOnly": true})
+        );
+
+        let (_, message) = request(
+            http::Method::GET,
+            "/v1/books?tags=a&tags=b&page_count=3",
+            "",
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({"tags": ["a", "b"], "pageCount": 3})
+        );
+
+        let status = request(http::Method::GET, "/v1/books?page_count=many", "").unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn binds_bodies() {
+        let (path, message) = request(
+            http::Method::PATCH,
+            "/v1/shelves/1/books/2?version=7",
+            r#"{"pageCount": 10}"#,
+        )
+        .unwrap();
+        assert_eq!(path, "/test.Shelves/UpdateBook");
+        assert_eq!(
+            message,
+            serde_json::json!({
+                "book": {"pageCount": 10, "name": "shelves/1/books/2"},
+                "version": "7",
+            })
+        );
+
+        // The query is ignored when the body is the whole message.
+        let (path, message) = request(
+            http::Method::POST,
+            "/v1/books:list?name=ignored",
+            r#"{"name": "a"}"#,
+        )
+        .unwrap();
+        assert_eq!(path, "/test.Shelves/ListBooks");
+        assert_eq!(message, serde_json::json!({"name": "a"}));
+
+        let status = request(http::Method::PATCH, "/v1/shelves/1/books/2", "{").unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn rejects_rules_of_unknown_fields() {
+        let mut set = shelves();
+        let method = &mut set.file[0].service[0].method[0];
+        let http = method.options.as_mut().unwrap().http.as_mut().unwrap();
+        http.get = Some("/v1/{title}".to_owned());
+
+        let error = Rules::default().add(&set).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid HTTP rule - /test.Shelves/GetBook: unknown field `title`"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use crate::rule::{self, Rule, Rules};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use http_body::Body;
+use std::{
+    convert::Infallible,
+    fmt,
+    future::{poll_fn, Future},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tonic::{body::BoxBody, Code, Status};
+use tower_service::Service;
+
+type StdError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+const JSON: &str = "application/json";
+const NDJSON: &str = "application/x-ndjson";
+
+/// The headers of HTTP requests that aren't the metadata of their call.
+const HOP_HEADERS: [header::HeaderName; 7] = [
+    header::ACCEPT_ENCODING,
+    header::CONNECTION,
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::HOST,
+    header::TE,
+    header::TRANSFER_ENCODING,
+];
+
+/// A [`Service`] transcoding the HTTP/JSON requests of the rules of a
+/// [`Transcoder`](crate::Transcoder) into gRPC calls to the inner service.
+///
+/// The requests not matching any rule get a `404 Not Found` response, or an
+/// `UNIMPLEMENTED` status for gRPC requests.
+#[derive(Clone)]
+pub struct TranscodingService<S> {
+    rules: Arc<Rules>,
+    inner: S,
+}
+
+impl<S> TranscodingService<S> {
+    pub(crate) fn new(rules: Arc<Rules>, inner: S) -> Self {
+        TranscodingService { rules, inner }
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TranscodingService<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TranscodingService")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<S, B> Service<http::Request<hyper::Body>> for TranscodingService<S>
+where
+    S: Service<http::Request<hyper::Body>, Response = http::Response<B>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<StdError> + Send,
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<StdError> + Send,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The inner service is readied by each call, on its own clone.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<hyper::Body>) -> Self::Future {
+        let rules = self.rules.clone();
+        let inner = self.inner.clone();
+        Box::pin(async move { Ok(transcode(&rules, inner, request).await) })
+    }
+}
+
+async fn transcode<S, B>(
+    rules: &Rules,
+    mut inner: S,
+    request: http::Request<hyper::Body>,
+) -> http::Response<BoxBody>
+where
+    S: Service<http::Request<hyper::Body>, Response = http::Response<B>>,
+    S::Error: Into<StdError> + Send,
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<StdError> + Send,
+{
+    let (parts, body) = request.into_parts();
+
+    let Some((rule, bindings)) = rules.find(&parts.method, parts.uri.path()) else {
+        let grpc = parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|v| v.as_bytes().starts_with(b"application/grpc"));
+        return if grpc {
+            Status::unimplemented("").to_http()
+        } else {
+            error(&Status::not_found("no rule matches the request"))
+        };
+    };
+
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => return error(&Status::from_error(Box::new(e))),
+    };
+    let messages = match rule.requests(rules.messages(), &bindings, parts.uri.query(), &body) {
+        Ok(messages) => messages,
+        Err(status) => return error(&status),
+    };
+
+    let mut framed = BytesMut::new();
+    for message in messages {
+        framed.put_u8(0);
+        framed.put_u32(message.len() as u32);
+        framed.put_slice(&message);
+    }
+
+    let mut request = http::Request::builder()
+        .method(http::Method::POST)
+        .uri(rule.grpc_path.as_str())
+        .version(http::Version::HTTP_2)
+        .body(hyper::Body::from(framed.freeze()))
+        .expect("the path of methods is a valid URI");
+    let headers = request.headers_mut();
+    for (name, value) in &parts.headers {
+        if !HOP_HEADERS.contains(name) {
+            headers.append(name, value.clone());
+        }
+    }
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/grpc+json"),
+    );
+    headers.insert(header::TE, HeaderValue::from_static("trailers"));
+    *request.extensions_mut() = parts.extensions;
+
+    let response = match poll_fn(|cx| inner.poll_ready(cx)).await {
+        Ok(()) => inner.call(request).await,
+        Err(e) => Err(e),
+    };
+    match response {
+        Ok(response) if rule.server_streaming => stream(rules, rule, response),
+        Ok(response) => unary(rules, rule, response).await,
+        Err(e) => error(&Status::unavailable(e.into().to_string())),
+    }
+}
+
+/// The JSON response of a unary or client streaming call.
+async fn unary<B>(
+    rules: &Rules,
+    rule: &Rule,
+    response: http::Response<B>,
+) -> http::Response<BoxBody>
+where
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<StdError> + Send,
+{
+    let (parts, body) = response.into_parts();
+    if let Some(status) = Status::from_header_map(&parts.headers) {
+        return error(&status);
+    }
+
+    let mut body = Box::pin(body);
+    let mut buf = BytesMut::new();
+    while let Some(data) = body.data().await {
+        match data {
+            Ok(data) => buf.put(data),
+            Err(e) => return error(&Status::from_error(e.into())),
+        }
+    }
+    let status = match body.trailers().await {
+        Ok(trailers) => trailers.as_ref().and_then(Status::from_header_map),
+        Err(e) => Some(Status::from_error(e.into())),
+    };
+    let status = status.unwrap_or_else(|| Status::internal("missing grpc-status"));
+    if status.code() != Code::Ok {
+        return error(&status);
+    }
+
+    let message = match next_message(&mut buf) {
+        Ok(Some(message)) => message,
+        Ok(None) => return error(&Status::internal("missing response message")),
+        Err(status) => return error(&status),
+    };
+    let field = rule.response_field(rules.messages());
+    match rule::response(field.as_deref(), &message) {
+        Ok(json) => http_response(StatusCode::OK, JSON, metadata(parts.headers), full(json)),
+        Err(status) => error(&status),
+    }
+}
+
+/// The newline-delimited JSON response of a server streaming call, which
+/// ends with an `{"error": status}` line if the call fails once it has
+/// started.
+fn stream<B>(rules: &Rules, rule: &Rule, response: http::Response<B>) -> http::Response<BoxBody>
+where
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<StdError> + Send,
+{
+    let (parts, body) = response.into_parts();
+    if let Some(status) = Status::from_header_map(&parts.headers) {
+        return error(&status);
+    }
+
+    let field = rule.response_field(rules.messages());
+    let lines = async_stream::stream! {
+        let mut body = Box::pin(body);
+        let mut buf = BytesMut::new();
+        let mut failed = None;
+        'data: while let Some(data) = body.data().await {
+            match data {
+                Ok(data) => buf.put(data),
+                Err(e) => {
+                    failed = Some(Status::from_error(e.into()));
+                    break;
+                }
+            }
+            loop {
+                let line = next_message(&mut buf)
+                    .and_then(|message| message.map(|m| rule::response(field.as_deref(), &m)).transpose());
+                match line {
+                    Ok(Some(mut line)) => {
+                        line.push(b'\n');
+                        yield Ok::<_, Infallible>(Bytes::from(line));
+                    }
+                    Ok(None) => break,
+                    Err(status) => {
+                        failed = Some(status);
+                        break 'data;
+                    }
+                }
+            }
+        }
+
+        let status = match failed {
+            Some(status) => status,
+            None => match body.trailers().await {
+                Ok(trailers) => trailers
+                    .as_ref()
+                    .and_then(Status::from_header_map)
+                    .unwrap_or_else(|| Status::internal("missing grpc-status")),
+                Err(e) => Status::from_error(e.into()),
+            },
+        };
+        if status.code() != Code::Ok {
+            let mut line = serde_json::to_vec(&serde_json::json!({ "error": status_json(&status) }))
+                .expect("statuses are valid JSON");
+            line.push(b'\n');
+            yield Ok(Bytes::from(line));
+        }
+    };
+
+    let body = hyper::Body::wrap_stream(lines)
+        .map_err(|e| Status::internal(e.to_string()))
+        .boxed_unsync();
+    http_response(StatusCode::OK, NDJSON, metadata(parts.headers), body)
+}
+
+/// Split the next message of `buf`, if it is whole.
+fn next_message(buf: &mut BytesMut) -> Result<Option<Bytes>, Status> {
+    if buf.len() < 5 {
+        return Ok(None);
+    }
+    if buf[0] != 0 {
+        return Err(Status::internal("compressed responses aren't supported"));
+    }
+    let len = (&buf[1..5]).get_u32() as usize;
+    if buf.len() < 5 + len {
+        return Ok(None);
+    }
+    buf.advance(5);
+    Ok(Some(buf.split_to(len).freeze()))
+}
+
+/// The headers of a gRPC response to pass to the HTTP response.
+fn metadata(mut headers: HeaderMap) -> HeaderMap {
+    headers.remove(header::CONTENT_TYPE);
+    headers.remove(header::CONTENT_LENGTH);
+    headers.remove("grpc-encoding");
+    headers.remove("grpc-accept-encoding");
+    headers
+}
+
+fn full(json: Vec<u8>) -> BoxBody {
+    http_body::Full::new(Bytes::from(json))
+        .map_err(|e| match e {})
+        .boxed_unsync()
+}
+
+fn http_response(
+    status: StatusCode,
+    content_type: &'static str,
+    headers: HeaderMap,
+    body: BoxBody,
+) -> http::Response<BoxBody> {
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+/// The `google.rpc.Status` JSON of `status`.
+fn status_json(status: &Status) -> serde_json::Value {
+    serde_json::json!({
+        "code": status.code() as i32,
+        "message": status.message(),
+        "details": [],
+    })
+}
+
+/// The JSON response of a failed call, with the HTTP status of its code.
+fn error(status: &Status) -> http::Response<BoxBody> {
+    let json = serde_json::to_vec(&status_json(status)).expect("statuses are valid JSON");
+    http_response(
+        http_status(status.code()),
+        JSON,
+        HeaderMap::new(),
+        full(json),
+    )
+}
+
+/// The HTTP status of a gRPC status code, as mapped by `google.api.http`.
+pub(crate) fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::Cancelled => StatusCode::from_u16(499).expect("499 is a valid status"),
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
@@ -0,0 +1,260 @@
+//! The path templates of `google.api.http` rules, such as
+//! `/v1/{name=shelves/*/books/*}:publish`.
+
+use percent_encoding::percent_decode_str;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `*`, matching a single segment.
+    Wildcard,
+    /// `**`, matching the remaining segments.
+    DoubleWildcard,
+}
+
+/// A `{field.path=segments}` variable, binding the segments from `start` to
+/// `end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Variable {
+    field_path: Vec<String>,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PathTemplate {
+    segments: Vec<Segment>,
+    variables: Vec<Variable>,
+    verb: Option<String>,
+}
+
+/// The value bound to the field path of a variable.
+pub(crate) type Binding<'a> = (&'a [String], String);
+
+impl PathTemplate {
+    pub(crate) fn parse(template: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("invalid path template `{}`: {}", template, reason);
+
+        let path = template
+            .strip_prefix('/')
+            .ok_or_else(|| invalid("it must start with `/`"))?;
+
+        // The verb follows the last segment, which can't contain `:` unless
+        // it is within a variable.
+        let last_segment = path.rfind(['/', '}']).map_or(0, |i| i + 1);
+        let (path, verb) = match path[last_segment..].find(':') {
+            Some(i) => {
+                let (path, verb) = path.split_at(last_segment + i);
+                (path, Some(verb[1..].to_owned()))
+            }
+            None => (path, None),
+        };
+        if verb.as_deref() == Some("") {
+            return Err(invalid("its verb is empty"));
+        }
+
+        let mut this = PathTemplate {
+            segments: Vec::new(),
+            variables: Vec::new(),
+            verb,
+        };
+
+        let mut rest = path;
+        loop {
+            if let Some(variable) = rest.strip_prefix('{') {
+                let end = variable
+                    .find('}')
+                    .ok_or_else(|| invalid("a variable isn't closed"))?;
+                let (field_path, segments) = match variable[..end].split_once('=') {
+                    Some((field_path, segments)) => (field_path, segments),
+                    None => (&variable[..end], "*"),
+                };
+                if field_path.is_empty() || field_path.split('.').any(str::is_empty) {
+                    return Err(invalid("a variable has an invalid field path"));
+                }
+
+                let start = this.segments.len();
+                for segment in segments.split('/') {
+                    this.push(segment).map_err(invalid)?;
+                }
+                this.variables.push(Variable {
+                    field_path: field_path.split('.').map(str::to_owned).collect(),
+                    start,
+                    end: this.segments.len(),
+                });
+                rest = &variable[end + 1..];
+            } else {
+                let end = rest.find('/').unwrap_or(rest.len());
+                this.push(&rest[..end]).map_err(invalid)?;
+                rest = &rest[end..];
+            }
+
+            match rest.strip_prefix('/') {
+                Some(next) => rest = next,
+                None if rest.is_empty() => break,
+                None => return Err(invalid("a variable must be a whole segment")),
+            }
+        }
+
+        let double_wildcard = this
+            .segments
+            .iter()
+            .position(|segment| *segment == Segment::DoubleWildcard);
+        if double_wildcard.is_some_and(|i| i + 1 != this.segments.len()) {
+            return Err(invalid("`**` must be the last segment"));
+        }
+
+        Ok(this)
+    }
+
+    fn push(&mut self, segment: &str) -> Result<(), &'static str> {
+        self.segments.push(match segment {
+            "" => return Err("a segment is empty"),
+            "*" => Segment::Wildcard,
+            "**" => Segment::DoubleWildcard,
+            _ if segment.contains(['{', '}', '*', '=']) => {
+                return Err("a literal segment contains a reserved character")
+            }
+            _ => Segment::Literal(segment.to_owned()),
+        });
+        Ok(())
+    }
+
+    /// The field paths of the variables of the template.
+    pub(crate) fn field_paths(&self) -> impl Iterator<Item = &[String]> {
+        self.variables.iter().map(|v| v.field_path.as_slice())
+    }
+
+    /// The values bound to the variables of the template by `path`, if it
+    /// matches.
+    pub(crate) fn matches(&self, path: &str) -> Option<Vec<Binding<'_>>> {
+        let mut path = path.strip_prefix('/')?;
+        if let Some(verb) = &self.verb {
+            path = path.strip_suffix(verb.as_str())?.strip_suffix(':')?;
+        }
+        let parts: Vec<&str> = path.split('/').collect();
+
+        let double_wildcard = self.segments.last() == Some(&Segment::DoubleWildcard);
+        if double_wildcard {
+            if parts.len() < self.segments.len() - 1 {
+                return None;
+            }
+        } else if parts.len() != self.segments.len() {
+            return None;
+        }
+
+        for (segment, part) in self.segments.iter().zip(&parts) {
+            match segment {
+                Segment::Literal(literal) if literal != part => return None,
+                Segment::Wildcard if part.is_empty() => return None,
+                _ => {}
+            }
+        }
+
+        let bindings = self.variables.iter().map(|variable| {
+            let end = if double_wildcard && variable.end == self.segments.len() {
+                parts.len()
+            } else {
+                variable.end
+            };
+            let parts = &parts[variable.start..end];
+            let value = if let [part] = parts {
+                decode(part)
+            } else {
+                // `/` separates the segments of multi-segment variables, so
+                // it stays escaped within them.
+                parts
+                    .iter()
+                    .map(|part| decode(part).replace('/', "%2F"))
+                    .collect::<Vec<_>>()
+                    .join("/")
+            };
+            (variable.field_path.as_slice(), value)
+        });
+
+        Some(bindings.collect())
+    }
+}
+
+fn decode(segment: &str) -> String {
+    percent_decode_str(segment).decode_utf8_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(template: &str, path: &str) -> Option<Vec<(String, String)>> {
+        let template = PathTemplate::parse(template).unwrap();
+        let bindings = template.matches(path)?;
+        let bindings = bindings
+            .into_iter()
+            .map(|(field_path, value)| (field_path.join("."), value));
+        Some(bindings.collect())
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn binds_variables() {
+        assert_eq!(
+            bindings("/v1/messages/{message_id}", "/v1/messages/123%20a"),
+            pairs(&[("message_id", "123 a")])
+        );
+        assert_eq!(
+            bindings(
+                "/v1/{name=shelves/*/books/*}/{user.id}",
+                "/v1/shelves/1/books/2/me"
+            ),
+            pairs(&[("name", "shelves/1/books/2"), ("user.id", "me")])
+        );
+        assert_eq!(
+            bindings("/v1/{name=files/**}", "/v1/files/a/b%2Fc"),
+            pairs(&[("name", "files/a/b%2Fc")])
+        );
+        assert_eq!(bindings("/v1/*/items", "/v1/x/items"), pairs(&[]));
+    }
+
+    #[test]
+    fn matches_verbs() {
+        assert_eq!(
+            bindings("/v1/{name=operations/*}:cancel", "/v1/operations/7:cancel"),
+            pairs(&[("name", "operations/7")])
+        );
+        assert_eq!(
+            bindings("/v1/{name=operations/*}:cancel", "/v1/operations/7"),
+            None
+        );
+        assert_eq!(bindings("/v1/operations", "/v1/operations:cancel"), None);
+    }
+
+    #[test]
+    fn rejects_other_paths() {
+        assert_eq!(bindings("/v1/messages/{id}", "/v1/messages"), None);
+        assert_eq!(bindings("/v1/messages/{id}", "/v1/messages/"), None);
+        assert_eq!(bindings("/v1/messages/{id}", "/v1/messages/1/2"), None);
+        assert_eq!(bindings("/v1/messages/{id}", "/v2/messages/1"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in [
+            "v1/messages",
+            "/v1//messages",
+            "/v1/{id",
+            "/v1/{=*}",
+            "/v1/**/messages",
+            "/v1/a{id}",
+            "/v1/messages:",
+        ] {
+            assert!(PathTemplate::parse(template).is_err(), "{}", template);
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use std::{collections::HashMap, time};
 
+use prost::{Message, Name};
+use prost_types::Any;
+
 use super::std_messages::{
     BadRequest, DebugInfo, ErrorInfo, FieldViolation, Help, HelpLink, LocalizedMessage,
     PreconditionFailure, PreconditionViolation, QuotaFailure, QuotaViolation, RequestInfo,
@@ -44,6 +47,10 @@ pub struct ErrorDetails {
 
     /// This field stores [`LocalizedMessage`] data, if any.
     pub(crate) localized_message: Option<LocalizedMessage>,
+
+    /// This field stores the details that aren't of the standard error
+    /// messages, such as messages of the application.
+    pub(crate) custom: Vec<Any>,
 }
 
 impl ErrorDetails {
@@ -431,6 +438,36 @@ impl ErrorDetails {
         self.localized_message.as_ref()
     }
 
+    /// Get the details that aren't of the standard error messages, packed
+    /// in `Any` messages.
+    pub fn custom(&self) -> &[Any] {
+        &self.custom
+    }
+
+    /// Get the first custom details of type `M`, if any. Returns `None` if
+    /// the details of type `M` fail to decode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tonic_types::ErrorDetails;
+    ///
+    /// let mut err_details = ErrorDetails::new();
+    /// err_details.add_custom(prost_types::Duration { seconds: 5, nanos: 0 });
+    ///
+    /// let duration: Option<prost_types::Duration> = err_details.get_custom();
+    /// assert_eq!(duration.map(|d| d.seconds), Some(5));
+    /// ```
+    pub fn get_custom<M>(&self) -> Option<M>
+    where
+        M: Message + Name + Default,
+    {
+        self.custom
+            .iter()
+            .find(|any| any.type_url == M::type_url())
+            .and_then(|any| M::decode(any.value.as_slice()).ok())
+    }
+
     /// Set [`RetryInfo`] details. Can be chained with other `.set_` and
     /// `.add_` [`ErrorDetails`] methods.
     ///
@@ -879,4 +916,35 @@ impl ErrorDetails {
         self.localized_message = Some(LocalizedMessage::new(locale, message));
         self
     }
+
+    /// Add custom details of the message `M`, packed in an `Any` message
+    /// of the type URL of `M`. Can be chained with other `.set_` and `.add_`
+    /// [`ErrorDetails`] methods.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tonic_types::ErrorDetails;
+    ///
+    /// let mut err_details = ErrorDetails::new();
+    ///
+    /// err_details.add_custom(prost_types::Duration { seconds: 5, nanos: 0 });
+    /// ```
+    pub fn add_custom<M>(&mut self, message: M) -> &mut Self
+    where
+        M: Message + Name,
+    {
+        self.custom.push(Any {
+            type_url: M::type_url(),
+            value: message.encode_to_vec(),
+        });
+        self
+    }
+
+    /// Add custom details already packed in an `Any` message. Can be chained
+    /// with other `.set_` and `.add_` [`ErrorDetails`] methods.
+    pub fn add_custom_any(&mut self, any: Any) -> &mut Self {
+        self.custom.push(any);
+        self
+    }
 }
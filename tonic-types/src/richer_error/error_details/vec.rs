@@ -1,3 +1,5 @@
+use prost_types::Any;
+
 use super::super::std_messages::{
     BadRequest, DebugInfo, ErrorInfo, Help, LocalizedMessage, PreconditionFailure, QuotaFailure,
     RequestInfo, ResourceInfo, RetryInfo,
@@ -37,6 +39,10 @@ pub enum ErrorDetail {
 
     /// Wraps the [`LocalizedMessage`] struct.
     LocalizedMessage(LocalizedMessage),
+
+    /// Wraps details that aren't of the standard error messages, packed in
+    /// an `Any` message.
+    Custom(Any),
 }
 
 impl From<RetryInfo> for ErrorDetail {
@@ -98,3 +104,9 @@ impl From<LocalizedMessage> for ErrorDetail {
         ErrorDetail::LocalizedMessage(err_detail)
     }
 }
+
+impl From<Any> for ErrorDetail {
+    fn from(err_detail: Any) -> Self {
+        ErrorDetail::Custom(err_detail)
+    }
+}
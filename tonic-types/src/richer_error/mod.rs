@@ -1,6 +1,6 @@
 use prost::{
     bytes::{Bytes, BytesMut},
-    DecodeError, Message,
+    DecodeError, Message, Name,
 };
 use prost_types::Any;
 use tonic::{metadata::MetadataMap, Code};
@@ -476,6 +476,30 @@ pub trait StatusExt: crate::sealed::Sealed {
     /// }
     /// ```
     fn get_details_localized_message(&self) -> Option<LocalizedMessage>;
+
+    /// Get first custom details of type `M` found on `tonic::Status`, if
+    /// any. If some `prost::DecodeError` occurs, returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tonic::{Status, Response};
+    /// use tonic_types::StatusExt;
+    ///
+    /// fn handle_request_result<T>(req_result: Result<Response<T>, Status>) {
+    ///     match req_result {
+    ///         Ok(_) => {},
+    ///         Err(status) => {
+    ///             if let Some(duration) = status.get_details_custom::<prost_types::Duration>() {
+    ///                 // Handle custom details
+    ///             }
+    ///         }
+    ///     };
+    /// }
+    /// ```
+    fn get_details_custom<M>(&self) -> Option<M>
+    where
+        M: Message + Name + Default;
 }
 
 impl crate::sealed::Sealed for tonic::Status {}
@@ -531,6 +555,8 @@ impl StatusExt for tonic::Status {
             conv_details.push(localized_message.into_any());
         }
 
+        conv_details.extend(details.custom);
+
         let details = gen_details_bytes(code, &message, conv_details);
 
         tonic::Status::with_details_and_metadata(code, message, details, metadata)
@@ -582,6 +608,9 @@ impl StatusExt for tonic::Status {
                 ErrorDetail::LocalizedMessage(loc_message) => {
                     conv_details.push(loc_message.into_any());
                 }
+                ErrorDetail::Custom(any) => {
+                    conv_details.push(any);
+                }
             }
         }
 
@@ -682,6 +711,15 @@ impl StatusExt for tonic::Status {
 
         status.get_details_localized_message()
     }
+
+    fn get_details_custom<M>(&self) -> Option<M>
+    where
+        M: Message + Name + Default,
+    {
+        let status = pb::Status::decode(self.details()).ok()?;
+
+        status.get_details_custom()
+    }
 }
 
 impl crate::sealed::Sealed for pb::Status {}
@@ -754,6 +792,12 @@ pub trait RpcStatusExt: crate::sealed::Sealed {
     /// Get first [`LocalizedMessage`] details found on `pb::Status`, if
     /// any. If some `prost::DecodeError` occurs, returns `None`.
     fn get_details_localized_message(&self) -> Option<LocalizedMessage>;
+
+    /// Get first custom details of type `M` found on `pb::Status`, if any.
+    /// If some `prost::DecodeError` occurs, returns `None`.
+    fn get_details_custom<M>(&self) -> Option<M>
+    where
+        M: Message + Name + Default;
 }
 
 impl RpcStatusExt for pb::Status {
@@ -792,7 +836,9 @@ impl RpcStatusExt for pb::Status {
                 LocalizedMessage::TYPE_URL => {
                     details.localized_message = Some(LocalizedMessage::from_any_ref(any)?);
                 }
-                _ => {}
+                _ => {
+                    details.custom.push(any.clone());
+                }
             }
         }
 
@@ -838,7 +884,9 @@ impl RpcStatusExt for pb::Status {
                 LocalizedMessage::TYPE_URL => {
                     details.push(LocalizedMessage::from_any_ref(any)?.into());
                 }
-                _ => {}
+                _ => {
+                    details.push(any.clone().into());
+                }
             }
         }
 
@@ -968,6 +1016,21 @@ impl RpcStatusExt for pb::Status {
 
         None
     }
+
+    fn get_details_custom<M>(&self) -> Option<M>
+    where
+        M: Message + Name + Default,
+    {
+        for any in self.details.iter() {
+            if any.type_url == M::type_url() {
+                if let Ok(detail) = M::decode(any.value.as_slice()) {
+                    return Some(detail);
+                }
+            }
+        }
+
+        None
+    }
 }
 
 #[cfg(test)]
@@ -976,7 +1039,7 @@ mod tests {
     use tonic::{Code, Status};
 
     use super::{
-        BadRequest, DebugInfo, ErrorDetails, ErrorInfo, Help, LocalizedMessage,
+        BadRequest, DebugInfo, ErrorDetail, ErrorDetails, ErrorInfo, Help, LocalizedMessage,
         PreconditionFailure, QuotaFailure, RequestInfo, ResourceInfo, RetryInfo, StatusExt,
     };
 
@@ -1065,4 +1128,39 @@ mod tests {
             "Extracted details vec differs from original details vec"
         );
     }
+
+    #[test]
+    fn round_trips_custom_details() {
+        let duration = prost_types::Duration {
+            seconds: 5,
+            nanos: 0,
+        };
+        let unknown = prost_types::Any {
+            type_url: "type.example.local/example.Unknown".into(),
+            value: vec![8, 1],
+        };
+
+        let mut err_details = ErrorDetails::new();
+        err_details
+            .set_retry_info(Some(Duration::from_secs(5)))
+            .add_custom(duration.clone())
+            .add_custom_any(unknown.clone());
+        let status = Status::with_error_details(Code::Unavailable, "unavailable", err_details);
+
+        assert_eq!(status.get_details_custom(), Some(duration.clone()));
+        assert_eq!(status.get_details_custom::<prost_types::Timestamp>(), None);
+
+        let ext_details = status.get_error_details();
+        assert_eq!(ext_details.get_custom(), Some(duration));
+        assert_eq!(ext_details.custom().len(), 2);
+        assert!(ext_details.retry_info().is_some());
+
+        // Details of unknown types are kept when building a status again.
+        let status = Status::with_error_details(Code::Unavailable, "unavailable", ext_details);
+        let ext_details_vec = status.get_error_details_vec();
+        assert!(matches!(
+            ext_details_vec.last(),
+            Some(ErrorDetail::Custom(any)) if *any == unknown
+        ));
+    }
 }
@@ -0,0 +1,187 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use http_body::Body;
+use tokio_stream::Stream;
+use tonic::Status;
+use tower_service::Service;
+
+use crate::call::GrpcWebCall;
+use crate::BoxError;
+
+/// The future of the response of a [`Backend`].
+///
+/// Only `wasm32` futures, such as those of JavaScript promises, may not be
+/// [`Send`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type BackendFuture =
+    Pin<Box<dyn Future<Output = Result<Response<BackendStream>, BoxError>> + Send + 'static>>;
+
+/// The future of the response of a [`Backend`].
+///
+/// Only `wasm32` futures, such as those of JavaScript promises, may not be
+/// [`Send`].
+#[cfg(target_arch = "wasm32")]
+pub type BackendFuture =
+    Pin<Box<dyn Future<Output = Result<Response<BackendStream>, BoxError>> + 'static>>;
+
+/// The bytes of the body of the response of a [`Backend`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type BackendStream = Pin<Box<dyn Stream<Item = Result<Bytes, BoxError>> + Send + 'static>>;
+
+/// The bytes of the body of the response of a [`Backend`].
+#[cfg(target_arch = "wasm32")]
+pub type BackendStream = Pin<Box<dyn Stream<Item = Result<Bytes, BoxError>> + 'static>>;
+
+/// A way of sending the HTTP requests of grpc-web calls, such as the
+/// `fetch` API of browsers, or WebTransport.
+///
+/// Backends get requests with their whole body, as `fetch` doesn't stream
+/// them, which limits their calls to unary and server streaming ones, and
+/// stream the body of their responses.
+pub trait Backend {
+    /// Send `req`, resolving to its response once its headers arrive.
+    fn send(&self, req: Request<Bytes>) -> BackendFuture;
+}
+
+impl<T: Backend + ?Sized> Backend for Arc<T> {
+    fn send(&self, req: Request<Bytes>) -> BackendFuture {
+        (**self).send(req)
+    }
+}
+
+/// A [`Service`] making the calls of a [`GrpcWebClientService`] with a
+/// [`Backend`], for clients without `hyper`, such as those of
+/// `wasm32-unknown-unknown`:
+///
+/// ```ignore
+/// let svc = GrpcWebClientService::new(BackendService::new(Fetch::new()));
+/// let mut client = GreeterClient::with_origin(svc, "https://example.com".parse()?);
+/// ```
+///
+/// [`GrpcWebClientService`]: crate::GrpcWebClientService
+#[derive(Debug, Clone)]
+pub struct BackendService<T> {
+    backend: Arc<T>,
+}
+
+impl<T> BackendService<T> {
+    /// Create a new service sending requests with `backend`.
+    pub fn new(backend: T) -> Self {
+        BackendService {
+            backend: Arc::new(backend),
+        }
+    }
+}
+
+impl<T, B> Service<Request<GrpcWebCall<B>>> for BackendService<T>
+where
+    T: Backend + 'static,
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: std::error::Error,
+{
+    type Response = Response<BackendBody>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<GrpcWebCall<B>>) -> Self::Future {
+        let backend = self.backend.clone();
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = hyper::body::to_bytes(body).await?;
+            let res = backend.send(Request::from_parts(parts, body)).await?;
+            Ok(res.map(|stream| BackendBody { stream }))
+        })
+    }
+}
+
+/// The body of the responses of a [`BackendService`].
+pub struct BackendBody {
+    stream: BackendStream,
+}
+
+// `wasm32-unknown-unknown` without atomics has a single thread, which the
+// body can't be sent away from, though tonic requires bodies to be `Send`.
+#[cfg(all(target_arch = "wasm32", not(target_feature = "atomics")))]
+unsafe impl Send for BackendBody {}
+
+impl Body for BackendBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.stream
+            .as_mut()
+            .poll_next(cx)
+            .map(|data| data.map(|data| data.map_err(Status::from_error)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        // grpc-web trailers are part of the body.
+        Poll::Ready(Ok(None))
+    }
+}
+
+impl fmt::Debug for BackendBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackendBody").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GrpcWebClientService;
+    use http::{header, StatusCode};
+
+    struct Echo;
+
+    impl Backend for Echo {
+        fn send(&self, req: Request<Bytes>) -> BackendFuture {
+            assert_eq!(
+                req.headers()[header::CONTENT_TYPE],
+                "application/grpc-web+proto"
+            );
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", "0".parse().unwrap());
+            let chunks = vec![
+                Ok(req.into_body()),
+                Ok(crate::call::make_trailers_frame(trailers).into()),
+            ];
+            let stream: BackendStream = Box::pin(tokio_stream::iter(chunks));
+            Box::pin(async move { Ok(Response::new(stream)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_backends() {
+        let mut svc = GrpcWebClientService::new(BackendService::new(Echo));
+
+        let mut req = Request::new(hyper::Body::from(&b"\0\0\0\0\x01a"[..]));
+        req.headers_mut()
+            .insert(header::CONTENT_TYPE, "application/grpc".parse().unwrap());
+        let res = svc.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let mut body = res.into_body();
+        assert_eq!(body.data().await.unwrap().unwrap(), &b"\0\0\0\0\x01a"[..]);
+        assert!(body.data().await.is_none());
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers["grpc-status"], "0");
+    }
+}
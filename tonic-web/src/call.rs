@@ -101,6 +101,10 @@ impl<B> GrpcWebCall<B> {
         Self::new_client(inner, Direction::Decode, Encoding::None)
     }
 
+    pub(crate) fn client_empty(inner: B) -> Self {
+        Self::new_client(inner, Direction::Empty, Encoding::None)
+    }
+
     fn new_client(inner: B, direction: Direction, encoding: Encoding) -> Self {
         GrpcWebCall {
             inner,
@@ -368,25 +372,15 @@ fn decode_trailers_frame(mut buf: Bytes) -> Result<Option<HeaderMap>, Status> {
     buf.get_u32();
 
     let mut map = HeaderMap::new();
-    let mut temp_buf = buf.clone();
-
-    let mut trailers = Vec::new();
-    let mut cursor_pos = 0;
-
-    for (i, b) in buf.iter().enumerate() {
-        if b == &b'\r' && buf.get(i + 1) == Some(&b'\n') {
-            let trailer = temp_buf.copy_to_bytes(i - cursor_pos);
-            cursor_pos = i;
-            trailers.push(trailer);
-            if temp_buf.has_remaining() {
-                temp_buf.get_u8();
-                temp_buf.get_u8();
-            }
-        }
-    }
+
+    // Lines end with `\r\n`, though the last one may omit it.
+    let trailers = buf
+        .split(|b| b == &b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty());
 
     for trailer in trailers {
-        let mut s = trailer.split(|b| b == &b':');
+        let mut s = trailer.splitn(2, |b| b == &b':');
         let key = s
             .next()
             .ok_or_else(|| Status::internal("trailers couldn't parse key"))?;
@@ -394,22 +388,24 @@ fn decode_trailers_frame(mut buf: Bytes) -> Result<Option<HeaderMap>, Status> {
             .next()
             .ok_or_else(|| Status::internal("trailers couldn't parse value"))?;
 
-        let value = value
-            .split(|b| b == &b'\r')
-            .next()
-            .ok_or_else(|| Status::internal("trailers was not escaped"))?;
+        // Some proxies write a space after the colon, as in HTTP/1 headers.
+        let start = value
+            .iter()
+            .position(|b| b != &b' ' && b != &b'\t')
+            .unwrap_or(value.len());
+        let value = &value[start..];
 
         let header_key = HeaderName::try_from(key)
             .map_err(|e| Status::internal(format!("Unable to parse HeaderName: {}", e)))?;
         let header_value = HeaderValue::try_from(value)
             .map_err(|e| Status::internal(format!("Unable to parse HeaderValue: {}", e)))?;
-        map.insert(header_key, header_value);
+        map.append(header_key, header_value);
     }
 
     Ok(Some(map))
 }
 
-fn make_trailers_frame(trailers: HeaderMap) -> Vec<u8> {
+pub(crate) fn make_trailers_frame(trailers: HeaderMap) -> Vec<u8> {
     let trailers = encode_trailers(trailers);
     let len = trailers.len();
     assert!(len <= u32::MAX as usize);
@@ -513,6 +509,21 @@ mod tests {
         assert_eq!(headers, map);
     }
 
+    #[test]
+    fn decode_proxy_trailers() {
+        let trailers = b"Grpc-Status: 5\r\nGrpc-Message: not found: a\r\nx-id: 1\r\nx-id: 2";
+        let mut frame = vec![GRPC_WEB_TRAILERS_BIT];
+        frame.put_u32(trailers.len() as u32);
+        frame.extend_from_slice(trailers);
+
+        let map = decode_trailers_frame(Bytes::from(frame)).unwrap().unwrap();
+
+        assert_eq!(map["grpc-status"], "5");
+        assert_eq!(map["grpc-message"], "not found: a");
+        let ids: Vec<_> = map.get_all("x-id").iter().collect();
+        assert_eq!(ids, ["1", "2"]);
+    }
+
     #[test]
     fn find_trailers_non_buffered() {
         // Byte version of this:
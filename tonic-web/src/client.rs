@@ -1,6 +1,6 @@
 use bytes::Bytes;
-use http::header::CONTENT_TYPE;
-use http::{Request, Response, Version};
+use http::header::{ACCEPT, CONTENT_TYPE};
+use http::{HeaderMap, HeaderValue, Request, Response, StatusCode, Version};
 use http_body::Body;
 use pin_project::pin_project;
 use std::error::Error;
@@ -11,7 +11,7 @@ use tower_layer::Layer;
 use tower_service::Service;
 use tracing::debug;
 
-use crate::call::content_types::GRPC_WEB;
+use crate::call::content_types::{GRPC_WEB, GRPC_WEB_PROTO};
 use crate::call::GrpcWebCall;
 
 /// Layer implementing the grpc-web protocol for clients.
@@ -44,6 +44,18 @@ impl<S> Layer<S> for GrpcWebClientLayer {
 /// A [`Service`] that wraps some inner http service that will
 /// coerce requests coming from [`tonic::client::Grpc`] into proper
 /// `grpc-web` requests.
+///
+/// Over an HTTP/1.1 client, such as `hyper::Client`, this calls servers
+/// only reachable through grpc-web proxies, e.g. Envoy:
+///
+/// ```ignore
+/// let svc = GrpcWebClientService::new(hyper::Client::new());
+/// let mut client = GreeterClient::with_origin(svc, "http://[::1]:8080".parse()?);
+/// ```
+///
+/// Responses without a `grpc-status` and with an HTTP status other than
+/// OK, such as the error pages of proxies, fail with the code of their
+/// HTTP status.
 #[derive(Debug, Clone)]
 pub struct GrpcWebClientService<S> {
     inner: S,
@@ -78,8 +90,7 @@ where
             *req.version_mut() = Version::HTTP_11;
         }
 
-        req.headers_mut()
-            .insert(CONTENT_TYPE, GRPC_WEB.try_into().unwrap());
+        coerce_headers(req.headers_mut());
 
         let req = req.map(GrpcWebCall::client_request);
 
@@ -108,6 +119,77 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let res = ready!(self.project().inner.poll(cx));
 
-        Poll::Ready(res.map(|r| r.map(GrpcWebCall::client_response)))
+        Poll::Ready(res.map(|r| {
+            // Leave the body of errors of proxies to tonic, which infers
+            // the status of the call from the HTTP status.
+            if r.status() != StatusCode::OK && !r.headers().contains_key("grpc-status") {
+                r.map(GrpcWebCall::client_empty)
+            } else {
+                r.map(GrpcWebCall::client_response)
+            }
+        }))
+    }
+}
+
+fn coerce_headers(headers: &mut HeaderMap) {
+    // `application/grpc+json` calls become `application/grpc-web+json` ones.
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("application/grpc"))
+        .map(|subtype| match subtype {
+            "" => HeaderValue::from_static(GRPC_WEB_PROTO),
+            subtype => HeaderValue::try_from(format!("{}{}", GRPC_WEB, subtype))
+                .unwrap_or_else(|_| HeaderValue::from_static(GRPC_WEB)),
+        })
+        .unwrap_or_else(|| HeaderValue::from_static(GRPC_WEB));
+
+    headers.insert(ACCEPT, content_type.clone());
+    headers.insert(CONTENT_TYPE, content_type);
+    headers.insert("x-grpc-web", HeaderValue::from_static("1"));
+    // Trailers are part of the body of grpc-web responses.
+    headers.remove("te");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coerces_content_types() {
+        let cases = [
+            ("application/grpc", GRPC_WEB_PROTO),
+            ("application/grpc+proto", GRPC_WEB_PROTO),
+            ("application/grpc+json", "application/grpc-web+json"),
+            ("text/plain", GRPC_WEB),
+        ];
+
+        for (content_type, expected) in cases {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+            headers.insert("te", HeaderValue::from_static("trailers"));
+            coerce_headers(&mut headers);
+
+            assert_eq!(headers[CONTENT_TYPE], expected);
+            assert_eq!(headers[ACCEPT], expected);
+            assert_eq!(headers["x-grpc-web"], "1");
+            assert!(!headers.contains_key("te"));
+        }
+    }
+
+    #[tokio::test]
+    async fn empties_proxy_errors() {
+        let mut svc = GrpcWebClientService::new(tower::service_fn(|_| async {
+            let mut res = Response::new(hyper::Body::from("<html>bad gateway</html>"));
+            *res.status_mut() = StatusCode::BAD_GATEWAY;
+            Ok::<_, std::convert::Infallible>(res)
+        }));
+
+        let res = svc.call(Request::new(hyper::Body::empty())).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+
+        let mut body = res.into_body();
+        assert!(body.data().await.is_none());
+        assert!(body.trailers().await.unwrap().is_none());
     }
 }
@@ -80,7 +80,48 @@
 //! * Currently, grpc-web clients can only perform `unary` and `server-streaming` calls. These
 //! are the only requests this crate is designed to handle. Support for client and bi-directional
 //! streaming will be officially supported when clients do.
-//! * There is no support for web socket transports.
+//!
+//! ## WebSockets
+//!
+//! Clients speaking the `grpc-websockets` protocol, such as the websocket transport of
+//! `@improbable-eng/grpc-web`, make their calls over WebSockets, which supports client and
+//! bi-directional streaming. [`enable`] serves them, as does the [`GrpcWebSocketLayer`]:
+//!
+//! ```ignore
+//! Server::builder()
+//!    .accept_http1(true)
+//!    .layer(GrpcWebSocketLayer::new())
+//!    .add_service(greeter)
+//!    .serve(addr)
+//!    .await?;
+//! ```
+//!
+//! tonic clients make their calls over WebSockets with the [`GrpcWebSocketClientLayer`], to
+//! traverse proxies only forwarding WebSockets.
+//!
+//! ## Clients
+//!
+//! Native tonic clients call servers only exposed through grpc-web proxies, such as those of
+//! Envoy or Cloudflare, over HTTP/1.1 with the [`GrpcWebClientLayer`]:
+//!
+//! ```ignore
+//! let svc = GrpcWebClientLayer::new().layer(hyper::Client::new());
+//! let mut client = GreeterClient::with_origin(svc, "http://[::1]:8080".parse()?);
+//! ```
+//!
+//! ## WebAssembly
+//!
+//! `tonic_web`, and `tonic` without its `transport` feature, build for `wasm32-unknown-unknown`,
+//! where clients send their calls with a [`Backend`], such as one over the `fetch` API of
+//! browsers, through a [`BackendService`]:
+//!
+//! ```ignore
+//! let svc = GrpcWebClientService::new(BackendService::new(Fetch::new()));
+//! let mut client = GreeterClient::new(svc);
+//! ```
+//!
+//! These support unary and server streaming calls, and generated clients built with
+//! `build_transport(false)`.
 //!
 //!
 //! [`tonic`]: https://github.com/hyperium/tonic
@@ -97,15 +138,21 @@
 #![doc(html_root_url = "https://docs.rs/tonic-web/0.11.0")]
 #![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
 
+pub use backend::{Backend, BackendBody, BackendFuture, BackendService, BackendStream};
 pub use call::GrpcWebCall;
 pub use client::{GrpcWebClientLayer, GrpcWebClientService};
 pub use layer::GrpcWebLayer;
 pub use service::{GrpcWebService, ResponseFuture};
+#[cfg(not(target_arch = "wasm32"))]
+pub use websocket::{GrpcWebSocketClientLayer, GrpcWebSocketClientService};
+pub use websocket::{GrpcWebSocketLayer, GrpcWebSocketService};
 
+mod backend;
 mod call;
 mod client;
 mod layer;
 mod service;
+mod websocket;
 
 use http::header::HeaderName;
 use std::time::Duration;
@@ -122,7 +169,8 @@ const DEFAULT_ALLOW_HEADERS: [&str; 4] =
 
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
-/// Enable a tonic service to handle grpc-web requests with the default configuration.
+/// Enable a tonic service to handle grpc-web and grpc-websockets requests with the default
+/// configuration.
 ///
 /// You can customize the CORS configuration composing the [`GrpcWebLayer`] with the cors layer of your choice.
 pub fn enable<S>(service: S) -> CorsGrpcWeb<S>
@@ -151,13 +199,14 @@ where
                 .collect::<Vec<HeaderName>>(),
         );
 
-    tower_layer::layer_fn(|s| CorsGrpcWeb(cors.layer(s))).layer(GrpcWebService::new(service))
+    tower_layer::layer_fn(|s| CorsGrpcWeb(cors.layer(s)))
+        .layer(GrpcWebSocketService::new(GrpcWebService::new(service)))
 }
 
 /// A newtype wrapper around [`GrpcWebLayer`] and [`tower_http::cors::CorsLayer`] to allow
 /// `tonic_web::enable` to implement the [`NamedService`] trait.
 #[derive(Debug, Clone)]
-pub struct CorsGrpcWeb<S>(tower_http::cors::Cors<GrpcWebService<S>>);
+pub struct CorsGrpcWeb<S>(tower_http::cors::Cors<GrpcWebSocketService<GrpcWebService<S>>>);
 
 impl<S> Service<http::Request<hyper::Body>> for CorsGrpcWeb<S>
 where
@@ -168,8 +217,9 @@ where
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Future =
-        <tower_http::cors::Cors<GrpcWebService<S>> as Service<http::Request<hyper::Body>>>::Future;
+    type Future = <tower_http::cors::Cors<GrpcWebSocketService<GrpcWebService<S>>> as Service<
+        http::Request<hyper::Body>,
+    >>::Future;
 
     fn poll_ready(
         &mut self,
@@ -0,0 +1,233 @@
+use std::task::{Context, Poll};
+
+use base64::Engine as _;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{header, HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Version};
+use http_body::Body;
+use tokio::io::{AsyncRead, ReadHalf};
+use tokio::sync::mpsc;
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::debug;
+
+use super::{BoxFuture, Message, Reader, Writer, BUFFER_SIZE, DATA, END_STREAM, PROTOCOL};
+use crate::call::content_types::GRPC_WEB_PROTO;
+use crate::call::GrpcWebCall;
+use crate::BoxError;
+
+/// Layer making the gRPC calls of clients over WebSockets.
+#[derive(Debug, Clone)]
+pub struct GrpcWebSocketClientLayer {
+    _priv: (),
+}
+
+impl GrpcWebSocketClientLayer {
+    /// Create a new grpc-websockets for clients layer.
+    pub fn new() -> GrpcWebSocketClientLayer {
+        Self { _priv: () }
+    }
+}
+
+impl Default for GrpcWebSocketClientLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for GrpcWebSocketClientLayer {
+    type Service = GrpcWebSocketClientService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcWebSocketClientService::new(inner)
+    }
+}
+
+/// A [`Service`] making the requests coming from [`tonic::client::Grpc`]
+/// over WebSockets, upgrading HTTP/1.1 connections of some inner http
+/// service, such as a [`hyper::Client`].
+///
+/// The requests can traverse proxies only forwarding WebSockets, and
+/// stream in both directions against servers of the
+/// [`GrpcWebSocketLayer`](crate::GrpcWebSocketLayer).
+#[derive(Debug, Clone)]
+pub struct GrpcWebSocketClientService<S> {
+    inner: S,
+}
+
+impl<S> GrpcWebSocketClientService<S> {
+    /// Create a new grpc-websockets for clients service.
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, B> Service<Request<B>> for GrpcWebSocketClientService<S>
+where
+    S: Service<Request<hyper::Body>, Response = Response<hyper::Body>>,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError>,
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError> + Send,
+{
+    type Response = Response<GrpcWebCall<hyper::Body>>;
+    type Error = BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let (mut parts, body) = req.into_parts();
+        let key = crate::util::base64::STANDARD.encode(super::random::<16>());
+
+        let mut upgrade = Request::new(hyper::Body::empty());
+        *upgrade.method_mut() = Method::GET;
+        *upgrade.uri_mut() = parts.uri;
+        *upgrade.version_mut() = Version::HTTP_11;
+        let headers = upgrade.headers_mut();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(
+            header::SEC_WEBSOCKET_VERSION,
+            HeaderValue::from_static("13"),
+        );
+        headers.insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static(PROTOCOL),
+        );
+        headers.insert(header::SEC_WEBSOCKET_KEY, key.parse().unwrap());
+        let res = self.inner.call(upgrade);
+
+        parts.headers.remove(header::TE);
+        parts.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(GRPC_WEB_PROTO),
+        );
+
+        Box::pin(async move {
+            let res = res.await.map_err(Into::into)?;
+            if res.status() != StatusCode::SWITCHING_PROTOCOLS {
+                return Err(
+                    format!("websocket upgrade failed with status {}", res.status()).into(),
+                );
+            }
+            let accept = super::accept_key(key.as_bytes());
+            if res.headers().get(header::SEC_WEBSOCKET_ACCEPT) != Some(&accept.parse()?) {
+                return Err("websocket upgrade not accepted".into());
+            }
+
+            let io = hyper::upgrade::on(res).await?;
+            let (read, write) = tokio::io::split(io);
+            let (outgoing, messages) = mpsc::channel(BUFFER_SIZE);
+            tokio::spawn(super::write_messages(Writer::new(write, true), messages));
+            let headers = super::encode_headers(&parts.headers);
+            outgoing.send(Message::Data(headers)).await?;
+            tokio::spawn(send(body, outgoing.clone()));
+
+            let mut reader = Reader::new(read);
+            let mut buf = BytesMut::new();
+            let headers = loop {
+                if let Some(headers) = headers_frame(&mut buf)? {
+                    break headers;
+                }
+                match reader.next().await? {
+                    Some(Message::Data(data)) => buf.put(data),
+                    Some(Message::Ping(payload)) => outgoing.send(Message::Pong(payload)).await?,
+                    Some(Message::Pong(_)) => {}
+                    Some(Message::Close) | None => return Err("no response headers".into()),
+                }
+            };
+
+            let (sender, body) = hyper::Body::channel();
+            tokio::spawn(receive(reader, buf.freeze(), sender, outgoing));
+            let mut res = Response::new(GrpcWebCall::client_response(body));
+            *res.headers_mut() = headers;
+            Ok(res)
+        })
+    }
+}
+
+/// The headers of the first frame of the response, once `buf` holds it.
+fn headers_frame(buf: &mut BytesMut) -> Result<Option<HeaderMap>, BoxError> {
+    if buf.len() < 5 {
+        return Ok(None);
+    }
+    if buf[0] & 0b1000_0000 == 0 {
+        return Err("the response doesn't start with its headers".into());
+    }
+    let len = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+    if buf.len() < 5 + len {
+        return Ok(None);
+    }
+    buf.advance(5);
+    super::decode_headers(&buf.split_to(len)).map(Some)
+}
+
+/// Send the data of `body` as messages, then half-close the call.
+async fn send<B>(body: B, outgoing: mpsc::Sender<Message>)
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    let mut body = Box::pin(body);
+    while let Some(data) = body.data().await {
+        let data = match data {
+            Ok(data) => data,
+            Err(e) => {
+                debug!("websocket request error: {}", e.into());
+                let _ = outgoing.send(Message::Close).await;
+                return;
+            }
+        };
+        let mut message = BytesMut::with_capacity(data.len() + 1);
+        message.put_u8(DATA);
+        message.put(data);
+        if outgoing
+            .send(Message::Data(message.freeze()))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+    let end_stream = Bytes::from_static(&[END_STREAM]);
+    let _ = outgoing.send(Message::Data(end_stream)).await;
+}
+
+/// Send `buf`, then the data of the messages of the server to `sender`,
+/// until it closes the WebSocket.
+async fn receive<T: AsyncRead>(
+    mut reader: Reader<ReadHalf<T>>,
+    buf: Bytes,
+    mut sender: hyper::body::Sender,
+    outgoing: mpsc::Sender<Message>,
+) {
+    if !buf.is_empty() && sender.send_data(buf).await.is_err() {
+        let _ = outgoing.send(Message::Close).await;
+        return;
+    }
+    loop {
+        match reader.next().await {
+            Ok(Some(Message::Data(data))) => {
+                if sender.send_data(data).await.is_err() {
+                    let _ = outgoing.send(Message::Close).await;
+                    return;
+                }
+            }
+            Ok(Some(Message::Ping(payload))) => {
+                let _ = outgoing.send(Message::Pong(payload)).await;
+            }
+            Ok(Some(Message::Pong(_))) => {}
+            Ok(Some(Message::Close)) | Ok(None) => {
+                let _ = outgoing.send(Message::Close).await;
+                return;
+            }
+            Err(e) => {
+                debug!("websocket response error: {}", e);
+                sender.abort();
+                return;
+            }
+        }
+    }
+}
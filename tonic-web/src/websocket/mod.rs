@@ -0,0 +1,474 @@
+//! gRPC calls over WebSockets, as framed by the `grpc-websockets` subprotocol.
+//!
+//! Each call opens a WebSocket to the path of its method. The first message
+//! of the client holds the headers of the request as an HTTP/1 header block,
+//! the following ones a byte telling whether they carry data of the request
+//! (`0`) or half-close it (`1`), followed by the data.
+//!
+//! The server sends the body of a grpc-web response, whose first frame holds
+//! the headers of the response the way the last one holds its trailers.
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::pin::Pin;
+
+use base64::Engine as _;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::BoxError;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use client::{GrpcWebSocketClientLayer, GrpcWebSocketClientService};
+pub use server::{GrpcWebSocketLayer, GrpcWebSocketService};
+
+// Clients dial their servers with `hyper`, which can't on `wasm32`.
+#[cfg(not(target_arch = "wasm32"))]
+mod client;
+mod server;
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'static>>;
+
+/// The subprotocol of gRPC calls over WebSockets.
+const PROTOCOL: &str = "grpc-websockets";
+
+/// Appended to the key of the client to accept its handshake.
+const ACCEPT_GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest message read, reassembled from its fragments.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// The number of messages waiting to be written before their senders wait.
+const BUFFER_SIZE: usize = 16;
+
+// The first byte of the messages of the client after its headers.
+const DATA: u8 = 0;
+const END_STREAM: u8 = 1;
+
+const FIN: u8 = 0b1000_0000;
+const MASKED: u8 = 0b1000_0000;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Message {
+    /// A text or binary message.
+    Data(Bytes),
+    Ping(Bytes),
+    Pong(Bytes),
+    Close,
+}
+
+/// Reads the messages of a WebSocket from `io`.
+pub(crate) struct Reader<T> {
+    io: T,
+    buf: BytesMut,
+    /// The fragments of the message being read.
+    fragments: Option<BytesMut>,
+}
+
+impl<T: AsyncRead + Unpin> Reader<T> {
+    pub(crate) fn new(io: T) -> Self {
+        Self {
+            io,
+            buf: BytesMut::new(),
+            fragments: None,
+        }
+    }
+
+    /// The next message of the peer, or `None` once it closed the
+    /// connection.
+    pub(crate) async fn next(&mut self) -> Result<Option<Message>, BoxError> {
+        loop {
+            let Some((header, payload)) = self.frame().await? else {
+                return Ok(None);
+            };
+            let fin = header & FIN != 0;
+            let message = match (header & 0x0f, &mut self.fragments) {
+                (PING, _) => Message::Ping(payload),
+                (PONG, _) => Message::Pong(payload),
+                (CLOSE, _) => Message::Close,
+                (TEXT | BINARY, None) if fin => Message::Data(payload),
+                (TEXT | BINARY, None) => {
+                    self.fragments = Some(BytesMut::from(&payload[..]));
+                    continue;
+                }
+                (CONTINUATION, Some(fragments)) => {
+                    if fragments.len() + payload.len() > MAX_MESSAGE_SIZE {
+                        return Err("websocket message too large".into());
+                    }
+                    fragments.put(payload);
+                    if !fin {
+                        continue;
+                    }
+                    Message::Data(self.fragments.take().unwrap().freeze())
+                }
+                (opcode, _) => return Err(format!("unexpected websocket opcode {}", opcode).into()),
+            };
+            return Ok(Some(message));
+        }
+    }
+
+    /// The header byte and unmasked payload of the next frame.
+    async fn frame(&mut self) -> Result<Option<(u8, Bytes)>, BoxError> {
+        loop {
+            if let Some(frame) = self.parse()? {
+                return Ok(Some(frame));
+            }
+            if self.io.read_buf(&mut self.buf).await? == 0 {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                return Err("websocket closed in the middle of a frame".into());
+            }
+        }
+    }
+
+    fn parse(&mut self) -> Result<Option<(u8, Bytes)>, BoxError> {
+        let buf = &self.buf[..];
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let masked = buf[1] & MASKED != 0;
+        let (len, mut offset) = match buf[1] & 0x7f {
+            126 if buf.len() >= 4 => (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4),
+            127 if buf.len() >= 10 => (u64::from_be_bytes(buf[2..10].try_into().unwrap()), 10),
+            126 | 127 => return Ok(None),
+            len => (len as u64, 2),
+        };
+        if len > MAX_MESSAGE_SIZE as u64 {
+            return Err("websocket message too large".into());
+        }
+        let len = len as usize;
+        let mask = match masked {
+            true if buf.len() < offset + 4 => return Ok(None),
+            true => {
+                offset += 4;
+                Some([
+                    buf[offset - 4],
+                    buf[offset - 3],
+                    buf[offset - 2],
+                    buf[offset - 1],
+                ])
+            }
+            false => None,
+        };
+        if buf.len() < offset + len {
+            return Ok(None);
+        }
+
+        let header = buf[0];
+        self.buf.advance(offset);
+        let mut payload = self.buf.split_to(len);
+        if let Some(mask) = mask {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+        Ok(Some((header, payload.freeze())))
+    }
+}
+
+/// Writes the messages of a WebSocket to `io`, masking them if it's the
+/// client's.
+pub(crate) struct Writer<T> {
+    io: T,
+    masked: bool,
+}
+
+impl<T: AsyncWrite + Unpin> Writer<T> {
+    pub(crate) fn new(io: T, masked: bool) -> Self {
+        Self { io, masked }
+    }
+
+    pub(crate) async fn send(&mut self, message: Message) -> io::Result<()> {
+        let (opcode, payload) = match message {
+            Message::Data(payload) => (BINARY, payload),
+            Message::Ping(payload) => (PING, payload),
+            Message::Pong(payload) => (PONG, payload),
+            Message::Close => (CLOSE, Bytes::new()),
+        };
+        let mask_bit = if self.masked { MASKED } else { 0 };
+
+        let mut frame = BytesMut::with_capacity(payload.len() + 14);
+        frame.put_u8(FIN | opcode);
+        match payload.len() {
+            len if len < 126 => frame.put_u8(mask_bit | len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.put_u8(mask_bit | 126);
+                frame.put_u16(len as u16);
+            }
+            len => {
+                frame.put_u8(mask_bit | 127);
+                frame.put_u64(len as u64);
+            }
+        }
+        if self.masked {
+            let mask = random::<4>();
+            frame.put_slice(&mask);
+            frame.extend(
+                payload
+                    .iter()
+                    .enumerate()
+                    .map(|(i, byte)| byte ^ mask[i % 4]),
+            );
+        } else {
+            frame.put(payload);
+        }
+
+        self.io.write_all(&frame).await?;
+        self.io.flush().await
+    }
+}
+
+/// Write the messages of `outgoing` until one closes the WebSocket.
+pub(crate) async fn write_messages<T>(mut writer: Writer<T>, mut outgoing: mpsc::Receiver<Message>)
+where
+    T: AsyncWrite + Unpin,
+{
+    while let Some(message) = outgoing.recv().await {
+        let close = message == Message::Close;
+        if let Err(e) = writer.send(message).await {
+            debug!("websocket write error: {}", e);
+            return;
+        }
+        if close {
+            let _ = writer.io.shutdown().await;
+            return;
+        }
+    }
+}
+
+/// The `Sec-WebSocket-Accept` value of the handshake of a client sending
+/// `key`.
+fn accept_key(key: &[u8]) -> String {
+    let digest = Sha1::new()
+        .chain_update(key)
+        .chain_update(ACCEPT_GUID)
+        .finalize();
+    crate::util::base64::STANDARD.encode(digest)
+}
+
+/// Whether the `Sec-WebSocket-Protocol` header of `headers` lists the
+/// `grpc-websockets` subprotocol.
+fn lists_protocol(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|protocol| protocol.trim() == PROTOCOL)
+}
+
+/// Key-value pairs as a HTTP/1 header block, without the terminating
+/// newline.
+#[cfg(not(target_arch = "wasm32"))]
+fn encode_headers(headers: &HeaderMap) -> Bytes {
+    let mut block = BytesMut::new();
+    for (name, value) in headers {
+        block.put_slice(name.as_ref());
+        block.put_slice(b": ");
+        block.put_slice(value.as_bytes());
+        block.put_slice(b"\r\n");
+    }
+    block.freeze()
+}
+
+fn decode_headers(block: &[u8]) -> Result<HeaderMap, BoxError> {
+    let mut headers = HeaderMap::new();
+    for line in block.split(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let colon = line
+            .iter()
+            .position(|b| *b == b':')
+            .ok_or("websocket header without a value")?;
+        let name = HeaderName::from_bytes(trim(&line[..colon]))?;
+        let value = HeaderValue::from_bytes(trim(&line[colon + 1..]))?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace());
+    let end = bytes.iter().rposition(|b| !b.is_ascii_whitespace());
+    match (start, end) {
+        (Some(start), Some(end)) => &bytes[start..=end],
+        _ => &[],
+    }
+}
+
+/// Bytes for the keys and masks of clients, which only keep proxies from
+/// interpreting the frames they carry.
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    for chunk in bytes.chunks_mut(8) {
+        let random = RandomState::new().build_hasher().finish().to_ne_bytes();
+        chunk.copy_from_slice(&random[..chunk.len()]);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{Request, Response};
+    use http_body::Body as _;
+    use std::convert::Infallible;
+    use std::task::{Context, Poll};
+    use tokio::net::TcpListener;
+    use tonic::{body::BoxBody, Status};
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    /// Echoes the data of requests as it's received, with the trailers of
+    /// an OK status.
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<Request<hyper::Body>> for Echo {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request<hyper::Body>) -> Self::Future {
+            let mut body = req.into_body();
+            let (mut sender, echo) = hyper::Body::channel();
+            tokio::spawn(async move {
+                while let Some(data) = body.data().await {
+                    sender.send_data(data.unwrap()).await.unwrap();
+                }
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                sender.send_trailers(trailers).await.unwrap();
+            });
+            let echo = echo.map_err(|e| Status::internal(e.to_string()));
+            std::future::ready(Ok(Response::new(echo.boxed_unsync())))
+        }
+    }
+
+    async fn roundtrip(message: Message, masked: bool) -> Message {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut writer = Writer::new(client, masked);
+        let mut reader = Reader::new(server);
+        let (sent, received) = tokio::join!(writer.send(message), reader.next());
+        sent.unwrap();
+        received.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn roundtrips_messages() {
+        for masked in [false, true] {
+            for len in [0, 125, 126, u16::MAX as usize, u16::MAX as usize + 1] {
+                let payload = Bytes::from(vec![7; len]);
+                let message = roundtrip(Message::Data(payload.clone()), masked).await;
+                assert_eq!(message, Message::Data(payload), "{} bytes", len);
+            }
+            assert_eq!(roundtrip(Message::Close, masked).await, Message::Close);
+        }
+    }
+
+    #[tokio::test]
+    async fn reassembles_fragments() {
+        // A masked text frame, a ping and the last continuation frame.
+        let frames: &[u8] = &[
+            0x01,
+            0x83,
+            1,
+            2,
+            3,
+            4,
+            b'H' ^ 1,
+            b'e' ^ 2,
+            b'l' ^ 3, //
+            0x89,
+            0x00, //
+            0x80,
+            0x02,
+            b'l',
+            b'o',
+        ];
+        let mut reader = Reader::new(frames);
+        assert_eq!(
+            reader.next().await.unwrap(),
+            Some(Message::Ping(Bytes::new()))
+        );
+        assert_eq!(
+            reader.next().await.unwrap(),
+            Some(Message::Data(Bytes::from_static(b"Hello")))
+        );
+        assert_eq!(reader.next().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn streams_calls() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let svc = GrpcWebSocketLayer::new().layer(Echo);
+                let connection = hyper::server::conn::Http::new()
+                    .serve_connection(stream, svc)
+                    .with_upgrades();
+                tokio::spawn(connection);
+            }
+        });
+
+        let mut client = GrpcWebSocketClientService::new(hyper::Client::new());
+        let (mut sender, body) = hyper::Body::channel();
+        let req = Request::post(format!("http://{}/test.Test/Echo", addr))
+            .body(body)
+            .unwrap();
+        let res = client.call(req).await.unwrap();
+        assert_eq!(res.headers()["content-type"], "application/grpc-web+proto");
+        let mut body = res.into_body();
+
+        // The response streams before the request ends.
+        for message in [&b"\0\0\0\0\x01a"[..], b"\0\0\0\0\x02bc"] {
+            sender.send_data(Bytes::from(message)).await.unwrap();
+            assert_eq!(body.data().await.unwrap().unwrap(), message);
+        }
+        drop(sender);
+        assert!(body.data().await.is_none());
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers["grpc-status"], "0");
+    }
+
+    #[test]
+    fn accepts_key() {
+        // The handshake of RFC 6455.
+        assert_eq!(
+            accept_key(b"dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn decodes_headers() {
+        let headers =
+            decode_headers(b"content-type: application/grpc\r\nx-id:1\r\nx-id: 2\r\n").unwrap();
+        assert_eq!(headers["content-type"], "application/grpc");
+        let ids: Vec<_> = headers.get_all("x-id").iter().collect();
+        assert_eq!(ids, ["1", "2"]);
+        assert_eq!(decode_headers(&encode_headers(&headers)).unwrap(), headers);
+    }
+}
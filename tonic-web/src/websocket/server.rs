@@ -0,0 +1,282 @@
+use std::task::{Context, Poll};
+
+use bytes::{Buf, Bytes};
+use http::{header, HeaderValue, Method, Request, Response, StatusCode, Uri, Version};
+use http_body::Body as _;
+use hyper::{upgrade::OnUpgrade, Body};
+use tokio::io::{AsyncRead, ReadHalf};
+use tokio::sync::mpsc;
+use tonic::{
+    body::{empty_body, BoxBody},
+    server::NamedService,
+    Status,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::{debug, trace};
+
+use super::{BoxFuture, Message, Reader, Writer, BUFFER_SIZE, DATA, END_STREAM, PROTOCOL};
+use crate::call::content_types::GRPC_WEB_PROTO;
+use crate::call::make_trailers_frame;
+use crate::BoxError;
+
+const GRPC: &str = "application/grpc";
+
+/// Layer serving gRPC calls over WebSockets.
+#[derive(Debug, Clone)]
+pub struct GrpcWebSocketLayer {
+    _priv: (),
+}
+
+impl GrpcWebSocketLayer {
+    /// Create a new grpc-websockets layer.
+    pub fn new() -> GrpcWebSocketLayer {
+        Self { _priv: () }
+    }
+}
+
+impl Default for GrpcWebSocketLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for GrpcWebSocketLayer {
+    type Service = GrpcWebSocketService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcWebSocketService::new(inner)
+    }
+}
+
+/// Service upgrading the HTTP/1.1 requests of grpc-websockets clients to
+/// WebSockets, over which it calls the inner service, passing all other
+/// requests through.
+///
+/// Unlike grpc-web requests, these support client and bidirectional
+/// streaming.
+#[derive(Debug, Clone)]
+pub struct GrpcWebSocketService<S> {
+    inner: S,
+}
+
+impl<S> GrpcWebSocketService<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        GrpcWebSocketService { inner }
+    }
+}
+
+impl<S> Service<Request<Body>> for GrpcWebSocketService<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError> + Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let Some(accept) = upgrade_key(&req) else {
+            return Box::pin(self.inner.call(req));
+        };
+        trace!(kind = "websocket", path = ?req.uri().path());
+
+        // The call is made once the headers of the request are read, with
+        // the service that is ready.
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        let on_upgrade = hyper::upgrade::on(&mut req);
+        tokio::spawn(serve(inner, on_upgrade, req.uri().clone()));
+
+        let mut res = Response::new(empty_body());
+        *res.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+        let headers = res.headers_mut();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+        headers.insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static(PROTOCOL),
+        );
+        Box::pin(std::future::ready(Ok(res)))
+    }
+}
+
+impl<S: NamedService> NamedService for GrpcWebSocketService<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// The `Sec-WebSocket-Accept` value of `req`, if it's the handshake of a
+/// grpc-websockets client.
+fn upgrade_key(req: &Request<Body>) -> Option<HeaderValue> {
+    let headers = req.headers();
+    let websocket = headers
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    if req.method() != Method::GET
+        || req.version() != Version::HTTP_11
+        || !websocket
+        || !super::lists_protocol(headers)
+    {
+        return None;
+    }
+    let key = headers.get(header::SEC_WEBSOCKET_KEY)?;
+    HeaderValue::try_from(super::accept_key(key.as_bytes())).ok()
+}
+
+/// Serve the call of the WebSocket that `on_upgrade` resolves to with
+/// `svc`.
+async fn serve<S>(mut svc: S, on_upgrade: OnUpgrade, uri: Uri)
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>>,
+    S::Error: Into<BoxError>,
+{
+    let io = match on_upgrade.await {
+        Ok(io) => io,
+        Err(e) => {
+            debug!("websocket upgrade error: {}", e);
+            return;
+        }
+    };
+    let (read, write) = tokio::io::split(io);
+    let (outgoing, messages) = mpsc::channel(BUFFER_SIZE);
+    tokio::spawn(super::write_messages(Writer::new(write, false), messages));
+
+    let mut reader = Reader::new(read);
+    let headers = loop {
+        match reader.next().await {
+            Ok(Some(Message::Data(headers))) => break super::decode_headers(&headers),
+            Ok(Some(Message::Ping(payload))) => {
+                let _ = outgoing.send(Message::Pong(payload)).await;
+            }
+            Ok(Some(Message::Pong(_))) => {}
+            Ok(Some(Message::Close)) | Ok(None) => break Err("no request headers".into()),
+            Err(e) => break Err(e),
+        }
+    };
+    let headers = match headers {
+        Ok(headers) => headers,
+        Err(e) => {
+            debug!("websocket request error: {}", e);
+            let _ = outgoing.send(Message::Close).await;
+            return;
+        }
+    };
+
+    let (sender, body) = Body::channel();
+    let mut req = Request::new(body);
+    *req.method_mut() = Method::POST;
+    *req.uri_mut() = uri;
+    *req.version_mut() = Version::HTTP_2;
+    *req.headers_mut() = headers;
+    coerce_request(&mut req);
+    tokio::spawn(receive(reader, sender, outgoing.clone()));
+
+    let res = svc
+        .call(req)
+        .await
+        .map_err(|e| Status::from_error(e.into()));
+    respond(res, &outgoing).await;
+    let _ = outgoing.send(Message::Close).await;
+}
+
+fn coerce_request(req: &mut Request<Body>) {
+    let headers = req.headers_mut();
+    headers.remove(header::CONTENT_LENGTH);
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(GRPC));
+    headers.insert(header::TE, HeaderValue::from_static("trailers"));
+}
+
+/// Send the data of the messages of the client to `sender`, until it
+/// half-closes the call.
+async fn receive<T: AsyncRead>(
+    mut reader: Reader<ReadHalf<T>>,
+    sender: hyper::body::Sender,
+    outgoing: mpsc::Sender<Message>,
+) {
+    let mut sender = Some(sender);
+    loop {
+        match reader.next().await {
+            Ok(Some(Message::Data(mut data))) => match (data.first(), &mut sender) {
+                (Some(&DATA), Some(body)) => {
+                    data.advance(1);
+                    // The call may not read the rest of the request.
+                    if body.send_data(data).await.is_err() {
+                        sender = None;
+                    }
+                }
+                (Some(&END_STREAM), _) => sender = None,
+                (Some(&DATA), None) => {}
+                _ => {
+                    debug!("websocket request error: invalid message");
+                    break;
+                }
+            },
+            Ok(Some(Message::Ping(payload))) => {
+                let _ = outgoing.send(Message::Pong(payload)).await;
+            }
+            Ok(Some(Message::Pong(_))) => {}
+            Ok(Some(Message::Close)) | Ok(None) => {
+                let _ = outgoing.send(Message::Close).await;
+                break;
+            }
+            Err(e) => {
+                debug!("websocket request error: {}", e);
+                break;
+            }
+        }
+    }
+    // The request ends without being half-closed.
+    if let Some(sender) = sender {
+        sender.abort();
+    }
+}
+
+/// Send `res` as the body of a grpc-web response, its headers as its first
+/// frame.
+async fn respond(res: Result<Response<BoxBody>, Status>, outgoing: &mpsc::Sender<Message>) {
+    let mut res = match res {
+        Ok(res) => res,
+        Err(status) => status.to_http(),
+    };
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(GRPC_WEB_PROTO),
+    );
+    let (parts, mut body) = res.into_parts();
+    let headers = make_trailers_frame(parts.headers);
+    if outgoing.send(Message::Data(headers.into())).await.is_err() {
+        return;
+    }
+
+    let trailers = loop {
+        match body.data().await {
+            Some(Ok(data)) => {
+                if outgoing.send(Message::Data(data)).await.is_err() {
+                    return;
+                }
+            }
+            Some(Err(status)) => break status_trailers(status),
+            None => match body.trailers().await {
+                Ok(trailers) => break trailers,
+                Err(status) => break status_trailers(status),
+            },
+        }
+    };
+    if let Some(trailers) = trailers {
+        let trailers = Bytes::from(make_trailers_frame(trailers));
+        let _ = outgoing.send(Message::Data(trailers)).await;
+    }
+}
+
+fn status_trailers(status: Status) -> Option<http::HeaderMap> {
+    let mut trailers = http::HeaderMap::new();
+    status.add_header(&mut trailers).ok()?;
+    Some(trailers)
+}
@@ -0,0 +1,116 @@
+use std::net::SocketAddr;
+
+use hyper::{Client, Uri};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_stream::{self as stream, StreamExt};
+use tonic::transport::Server;
+use tonic::Code;
+
+use integration::pb::{test_client::TestClient, test_server::TestServer, Input, Output};
+use integration::Svc;
+use tonic_web::{GrpcWebSocketClientService, GrpcWebSocketLayer};
+
+type WebSocketClient = TestClient<GrpcWebSocketClientService<Client<hyper::client::HttpConnector>>>;
+
+#[tokio::test]
+async fn unary() {
+    let mut client = spawn().await;
+
+    let res = client.unary_call(input(1, "one")).await.unwrap();
+
+    assert_eq!(res.into_inner(), output(1, "one"));
+}
+
+#[tokio::test]
+async fn client_stream() {
+    let mut client = spawn().await;
+
+    let inputs = stream::iter(vec![input(1, "one"), input(2, "two")]);
+    let res = client.client_stream(inputs).await.unwrap();
+
+    assert_eq!(res.into_inner(), output(3, "onetwo"));
+}
+
+#[tokio::test]
+async fn server_stream() {
+    let mut client = spawn().await;
+
+    let res = client.server_stream(input(1, "one")).await.unwrap();
+    let outputs = res
+        .into_inner()
+        .collect::<Result<Vec<_>, _>>()
+        .await
+        .unwrap();
+
+    assert_eq!(outputs, [output(1, "1-one"), output(1, "2-one")]);
+}
+
+#[tokio::test]
+async fn error() {
+    let mut client = spawn().await;
+
+    let status = client.unary_call(input(1, "boom")).await.unwrap_err();
+
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(status.message(), "invalid boom");
+}
+
+#[tokio::test]
+async fn enabled_services() {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let listener = TcpListener::bind(addr).await.expect("listener");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    drop(tokio::spawn(async move {
+        Server::builder()
+            .accept_http1(true)
+            .add_service(tonic_web::enable(TestServer::new(Svc)))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .unwrap()
+    }));
+
+    let mut client = client(url);
+    let inputs = stream::iter(vec![input(1, "one"), input(2, "two")]);
+    let res = client.client_stream(inputs).await.unwrap();
+
+    assert_eq!(res.into_inner(), output(3, "onetwo"));
+}
+
+async fn spawn() -> WebSocketClient {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let listener = TcpListener::bind(addr).await.expect("listener");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    drop(tokio::spawn(async move {
+        Server::builder()
+            .accept_http1(true)
+            .layer(GrpcWebSocketLayer::new())
+            .add_service(TestServer::new(Svc))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .unwrap()
+    }));
+
+    client(url)
+}
+
+fn client(url: String) -> WebSocketClient {
+    let svc = GrpcWebSocketClientService::new(Client::new());
+    TestClient::with_origin(svc, url.parse::<Uri>().unwrap())
+}
+
+fn input(id: i32, desc: &str) -> Input {
+    Input {
+        id,
+        desc: desc.to_owned(),
+    }
+}
+
+fn output(id: i32, desc: &str) -> Output {
+    Output {
+        id,
+        desc: desc.to_owned(),
+    }
+}
@@ -0,0 +1,197 @@
+//! Parsing of the xDS bootstrap configuration.
+
+use crate::proto::Node;
+use http::Uri;
+use serde_json::Value;
+use std::fmt;
+
+const BOOTSTRAP_PATH_ENV: &str = "GRPC_XDS_BOOTSTRAP";
+const BOOTSTRAP_CONFIG_ENV: &str = "GRPC_XDS_BOOTSTRAP_CONFIG";
+
+/// How to reach the xDS control plane, and how to identify to it.
+///
+/// Follows the [gRPC xDS bootstrap] format. Only the first server of
+/// `xds_servers` is used.
+///
+/// ```
+/// # use tonic_xds::Bootstrap;
+/// let bootstrap = Bootstrap::from_json(r#"{
+///     "xds_servers": [{
+///         "server_uri": "istiod.istio-system.svc:15010",
+///         "channel_creds": [{ "type": "insecure" }]
+///     }],
+///     "node": { "id": "sidecar~10.0.0.1~greeter.default~default.svc.cluster.local" }
+/// }"#).unwrap();
+/// ```
+///
+/// [gRPC xDS bootstrap]: https://github.com/grpc/proposal/blob/master/A27-xds-global-load-balancing.md#xdsclient-and-bootstrap-file
+#[derive(Debug, Clone)]
+pub struct Bootstrap {
+    pub(crate) server_uri: Uri,
+    pub(crate) node: Node,
+}
+
+impl Bootstrap {
+    /// Load the bootstrap configuration from the file named by the
+    /// `GRPC_XDS_BOOTSTRAP` environment variable or, if it isn't set, from
+    /// the contents of `GRPC_XDS_BOOTSTRAP_CONFIG`.
+    pub fn from_env() -> Result<Self, BootstrapError> {
+        if let Some(path) = std::env::var_os(BOOTSTRAP_PATH_ENV) {
+            let json = std::fs::read_to_string(&path).map_err(|e| {
+                BootstrapError::new(format!("failed to read {}: {}", path.to_string_lossy(), e))
+            })?;
+            return Self::from_json(&json);
+        }
+
+        match std::env::var(BOOTSTRAP_CONFIG_ENV) {
+            Ok(json) => Self::from_json(&json),
+            Err(_) => Err(BootstrapError::new(format!(
+                "neither {} nor {} is set",
+                BOOTSTRAP_PATH_ENV, BOOTSTRAP_CONFIG_ENV
+            ))),
+        }
+    }
+
+    /// Parse a bootstrap configuration from JSON.
+    pub fn from_json(json: &str) -> Result<Self, BootstrapError> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| BootstrapError::new(e.to_string()))?;
+
+        let server = value
+            .get("xds_servers")
+            .and_then(Value::as_array)
+            .and_then(|servers| servers.first())
+            .ok_or_else(|| BootstrapError::new("xds_servers must list at least one server"))?;
+
+        let server_uri = server
+            .get("server_uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| BootstrapError::new("xds_servers[0].server_uri is required"))?;
+        let server_uri = if server_uri.contains("://") {
+            server_uri.parse()
+        } else {
+            format!("http://{}", server_uri).parse()
+        }
+        .map_err(|e| BootstrapError::new(format!("invalid server_uri: {}", e)))?;
+
+        if let Some(creds) = server.get("channel_creds").and_then(Value::as_array) {
+            let insecure = creds
+                .iter()
+                .any(|creds| creds.get("type").and_then(Value::as_str) == Some("insecure"));
+            if !creds.is_empty() && !insecure {
+                return Err(BootstrapError::new(
+                    "only insecure channel credentials are supported",
+                ));
+            }
+        }
+
+        let node = value.get("node");
+        let string = |name: &str| {
+            node.and_then(|node| node.get(name))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let node = Node {
+            id: string("id"),
+            cluster: string("cluster"),
+            metadata: node
+                .and_then(|node| node.get("metadata"))
+                .and_then(Value::as_object)
+                .map(to_struct),
+            user_agent_name: "tonic".to_string(),
+            user_agent_version: env!("CARGO_PKG_VERSION").to_string(),
+            client_features: Vec::new(),
+        };
+
+        Ok(Bootstrap { server_uri, node })
+    }
+}
+
+fn to_struct(object: &serde_json::Map<String, Value>) -> prost_types::Struct {
+    prost_types::Struct {
+        fields: object
+            .iter()
+            .map(|(key, value)| (key.clone(), to_value(value)))
+            .collect(),
+    }
+}
+
+fn to_value(value: &Value) -> prost_types::Value {
+    use prost_types::value::Kind;
+
+    let kind = match value {
+        Value::Null => Kind::NullValue(0),
+        Value::Bool(b) => Kind::BoolValue(*b),
+        Value::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or_default()),
+        Value::String(s) => Kind::StringValue(s.clone()),
+        Value::Array(values) => Kind::ListValue(prost_types::ListValue {
+            values: values.iter().map(to_value).collect(),
+        }),
+        Value::Object(object) => Kind::StructValue(to_struct(object)),
+    };
+
+    prost_types::Value { kind: Some(kind) }
+}
+
+/// Error returned when the bootstrap configuration cannot be loaded.
+#[derive(Debug)]
+pub struct BootstrapError {
+    message: String,
+}
+
+impl BootstrapError {
+    fn new(message: impl Into<String>) -> Self {
+        BootstrapError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for BootstrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid xDS bootstrap: {}", self.message)
+    }
+}
+
+impl std::error::Error for BootstrapError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bootstrap() {
+        let bootstrap = Bootstrap::from_json(
+            r#"{
+                "xds_servers": [{ "server_uri": "xds.example.com:18000" }],
+                "node": {
+                    "id": "node-1",
+                    "cluster": "cluster-1",
+                    "metadata": { "TRAFFICDIRECTOR_NETWORK_NAME": "default", "n": 1 }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(bootstrap.server_uri, "http://xds.example.com:18000/");
+        assert_eq!(bootstrap.node.id, "node-1");
+        assert_eq!(bootstrap.node.cluster, "cluster-1");
+        assert_eq!(bootstrap.node.metadata.unwrap().fields.len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_bootstrap() {
+        let invalid = [
+            "{}",
+            r#"{ "xds_servers": [] }"#,
+            r#"{ "xds_servers": [{}] }"#,
+            r#"{ "xds_servers": [{ "server_uri": "a:1", "channel_creds": [{ "type": "google_default" }] }] }"#,
+        ];
+
+        for json in invalid {
+            assert!(Bootstrap::from_json(json).is_err(), "{}", json);
+        }
+    }
+}
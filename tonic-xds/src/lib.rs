@@ -0,0 +1,49 @@
+//! [xDS] name resolution for `tonic` channels.
+//!
+//! [`XdsResolver`] implements the `tonic` [`Resolver`] trait on top of the
+//! Aggregated Discovery Service, so that channels can discover their
+//! backends from Istio, or any Envoy compatible control plane. Listeners,
+//! route configurations, clusters and cluster load assignments are followed
+//! as they change.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use tonic::transport::{Channel, Endpoint};
+//! use tonic_xds::XdsResolver;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let resolver = XdsResolver::from_env()?;
+//! let channel = Channel::balance_resolver(
+//!     Endpoint::from_static("xds://greeter.default.svc:50051"),
+//!     resolver,
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [xDS]: https://www.envoyproxy.io/docs/envoy/latest/api-docs/xds_protocol
+//! [`Resolver`]: tonic::transport::channel::Resolver
+
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
+)]
+#![deny(rustdoc::broken_intra_doc_links)]
+#![doc(html_root_url = "https://docs.rs/tonic-xds/0.11.0")]
+#![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
+#![doc(test(no_crate_inject, attr(deny(rust_2018_idioms))))]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+mod bootstrap;
+mod proto;
+mod resolver;
+mod state;
+
+pub use bootstrap::{Bootstrap, BootstrapError};
+pub use resolver::XdsResolver;
@@ -0,0 +1,277 @@
+//! The subset of the Envoy v3 API used for name resolution.
+//!
+//! Only the fields read or written by the resolver are declared. Field
+//! numbers match the upstream definitions, so other fields sent by the
+//! control plane are skipped when decoding.
+
+#![allow(missing_docs, unreachable_pub)]
+
+use prost_types::Any;
+
+pub(crate) const LISTENER_TYPE: &str = "type.googleapis.com/envoy.config.listener.v3.Listener";
+pub(crate) const ROUTE_CONFIGURATION_TYPE: &str =
+    "type.googleapis.com/envoy.config.route.v3.RouteConfiguration";
+pub(crate) const CLUSTER_TYPE: &str = "type.googleapis.com/envoy.config.cluster.v3.Cluster";
+pub(crate) const CLUSTER_LOAD_ASSIGNMENT_TYPE: &str =
+    "type.googleapis.com/envoy.config.endpoint.v3.ClusterLoadAssignment";
+pub(crate) const HTTP_CONNECTION_MANAGER_TYPE: &str = "type.googleapis.com/envoy.extensions.filters.network.http_connection_manager.v3.HttpConnectionManager";
+
+pub(crate) const AGGREGATED_RESOURCES_PATH: &str =
+    "/envoy.service.discovery.v3.AggregatedDiscoveryService/StreamAggregatedResources";
+
+/// `envoy.service.discovery.v3.DiscoveryRequest`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiscoveryRequest {
+    #[prost(string, tag = "1")]
+    pub version_info: String,
+    #[prost(message, optional, tag = "2")]
+    pub node: Option<Node>,
+    #[prost(string, repeated, tag = "3")]
+    pub resource_names: Vec<String>,
+    #[prost(string, tag = "4")]
+    pub type_url: String,
+    #[prost(string, tag = "5")]
+    pub response_nonce: String,
+}
+
+/// `envoy.service.discovery.v3.DiscoveryResponse`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiscoveryResponse {
+    #[prost(string, tag = "1")]
+    pub version_info: String,
+    #[prost(message, repeated, tag = "2")]
+    pub resources: Vec<Any>,
+    #[prost(string, tag = "4")]
+    pub type_url: String,
+    #[prost(string, tag = "5")]
+    pub nonce: String,
+}
+
+/// `envoy.config.core.v3.Node`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Node {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub cluster: String,
+    #[prost(message, optional, tag = "3")]
+    pub metadata: Option<prost_types::Struct>,
+    #[prost(string, tag = "6")]
+    pub user_agent_name: String,
+    #[prost(string, tag = "7")]
+    pub user_agent_version: String,
+    #[prost(string, repeated, tag = "10")]
+    pub client_features: Vec<String>,
+}
+
+/// `envoy.config.listener.v3.Listener`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Listener {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(message, optional, tag = "19")]
+    pub api_listener: Option<ApiListener>,
+}
+
+/// `envoy.config.listener.v3.ApiListener`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ApiListener {
+    #[prost(message, optional, tag = "1")]
+    pub api_listener: Option<Any>,
+}
+
+/// `envoy.extensions.filters.network.http_connection_manager.v3.HttpConnectionManager`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HttpConnectionManager {
+    #[prost(oneof = "RouteSpecifier", tags = "3, 4")]
+    pub route_specifier: Option<RouteSpecifier>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum RouteSpecifier {
+    #[prost(message, tag = "3")]
+    Rds(Rds),
+    #[prost(message, tag = "4")]
+    RouteConfig(RouteConfiguration),
+}
+
+/// `envoy.extensions.filters.network.http_connection_manager.v3.Rds`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Rds {
+    #[prost(string, tag = "2")]
+    pub route_config_name: String,
+}
+
+/// `envoy.config.route.v3.RouteConfiguration`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RouteConfiguration {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub virtual_hosts: Vec<VirtualHost>,
+}
+
+/// `envoy.config.route.v3.VirtualHost`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VirtualHost {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, repeated, tag = "2")]
+    pub domains: Vec<String>,
+    #[prost(message, repeated, tag = "3")]
+    pub routes: Vec<Route>,
+}
+
+/// `envoy.config.route.v3.Route`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Route {
+    #[prost(message, optional, tag = "1")]
+    pub r#match: Option<RouteMatch>,
+    #[prost(oneof = "RouteActionSpecifier", tags = "2")]
+    pub action: Option<RouteActionSpecifier>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum RouteActionSpecifier {
+    #[prost(message, tag = "2")]
+    Route(RouteAction),
+}
+
+/// `envoy.config.route.v3.RouteMatch`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RouteMatch {
+    #[prost(oneof = "PathSpecifier", tags = "1, 2")]
+    pub path_specifier: Option<PathSpecifier>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum PathSpecifier {
+    #[prost(string, tag = "1")]
+    Prefix(String),
+    #[prost(string, tag = "2")]
+    Path(String),
+}
+
+/// `envoy.config.route.v3.RouteAction`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RouteAction {
+    #[prost(oneof = "ClusterSpecifier", tags = "1, 3")]
+    pub cluster_specifier: Option<ClusterSpecifier>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum ClusterSpecifier {
+    #[prost(string, tag = "1")]
+    Cluster(String),
+    #[prost(message, tag = "3")]
+    WeightedClusters(WeightedCluster),
+}
+
+/// `envoy.config.route.v3.WeightedCluster`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WeightedCluster {
+    #[prost(message, repeated, tag = "1")]
+    pub clusters: Vec<ClusterWeight>,
+}
+
+/// `envoy.config.route.v3.WeightedCluster.ClusterWeight`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClusterWeight {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(message, optional, tag = "2")]
+    pub weight: Option<u32>,
+}
+
+/// `envoy.config.cluster.v3.Cluster`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Cluster {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(enumeration = "DiscoveryType", tag = "2")]
+    pub r#type: i32,
+    #[prost(message, optional, tag = "3")]
+    pub eds_cluster_config: Option<EdsClusterConfig>,
+    #[prost(message, optional, tag = "33")]
+    pub load_assignment: Option<ClusterLoadAssignment>,
+}
+
+/// `envoy.config.cluster.v3.Cluster.DiscoveryType`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum DiscoveryType {
+    Static = 0,
+    StrictDns = 1,
+    LogicalDns = 2,
+    Eds = 3,
+    OriginalDst = 4,
+}
+
+/// `envoy.config.cluster.v3.Cluster.EdsClusterConfig`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EdsClusterConfig {
+    #[prost(string, tag = "2")]
+    pub service_name: String,
+}
+
+/// `envoy.config.endpoint.v3.ClusterLoadAssignment`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClusterLoadAssignment {
+    #[prost(string, tag = "1")]
+    pub cluster_name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub endpoints: Vec<LocalityLbEndpoints>,
+}
+
+/// `envoy.config.endpoint.v3.LocalityLbEndpoints`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LocalityLbEndpoints {
+    #[prost(message, repeated, tag = "2")]
+    pub lb_endpoints: Vec<LbEndpoint>,
+    #[prost(uint32, tag = "5")]
+    pub priority: u32,
+}
+
+/// `envoy.config.endpoint.v3.LbEndpoint`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LbEndpoint {
+    #[prost(message, optional, tag = "1")]
+    pub endpoint: Option<Endpoint>,
+    #[prost(enumeration = "HealthStatus", tag = "2")]
+    pub health_status: i32,
+}
+
+/// `envoy.config.core.v3.HealthStatus`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum HealthStatus {
+    Unknown = 0,
+    Healthy = 1,
+    Unhealthy = 2,
+    Draining = 3,
+    Timeout = 4,
+    Degraded = 5,
+}
+
+/// `envoy.config.endpoint.v3.Endpoint`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Endpoint {
+    #[prost(message, optional, tag = "1")]
+    pub address: Option<Address>,
+}
+
+/// `envoy.config.core.v3.Address`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Address {
+    #[prost(message, optional, tag = "1")]
+    pub socket_address: Option<SocketAddress>,
+}
+
+/// `envoy.config.core.v3.SocketAddress`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SocketAddress {
+    #[prost(string, tag = "2")]
+    pub address: String,
+    #[prost(uint32, tag = "3")]
+    pub port_value: u32,
+}
@@ -0,0 +1,339 @@
+use crate::{
+    bootstrap::{Bootstrap, BootstrapError},
+    proto::{self, DiscoveryRequest, DiscoveryResponse},
+    state::{State, RESOURCE_TYPES},
+};
+use http::{uri::PathAndQuery, Uri};
+use std::{collections::HashMap, time::Duration};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{
+    codec::{ProstCodec, Streaming},
+    transport::{
+        channel::{Resolution, ResolutionStream, Resolver},
+        Endpoint,
+    },
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A [`Resolver`] for `xds://` targets.
+///
+/// Resolving `xds://greeter.default.svc:50051` subscribes to the listener
+/// `greeter.default.svc:50051` over the Aggregated Discovery Service of the
+/// control plane named by the [`Bootstrap`], then follows its route
+/// configuration, clusters and cluster load assignments. The channel is
+/// updated every time the control plane changes any of them.
+///
+/// The stream to the control plane is re-established with an exponential
+/// backoff when it fails, the channel keeps its last addresses meanwhile.
+///
+/// ```no_run
+/// # use tonic::transport::{Channel, Endpoint};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let resolver = tonic_xds::XdsResolver::from_env()?;
+/// let endpoint = Endpoint::from_static("xds://greeter.default.svc:50051");
+/// let channel = Channel::balance_resolver(endpoint, resolver);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct XdsResolver {
+    bootstrap: Bootstrap,
+}
+
+impl XdsResolver {
+    /// Create a resolver using the control plane of `bootstrap`.
+    pub fn new(bootstrap: Bootstrap) -> Self {
+        XdsResolver { bootstrap }
+    }
+
+    /// Create a resolver using the bootstrap configuration named by the
+    /// environment, see [`Bootstrap::from_env`].
+    pub fn from_env() -> Result<Self, BootstrapError> {
+        Bootstrap::from_env().map(XdsResolver::new)
+    }
+}
+
+impl Resolver for XdsResolver {
+    fn resolve(&self, target: &Uri) -> ResolutionStream {
+        let bootstrap = self.bootstrap.clone();
+        let listener = listener_name(target);
+
+        Box::pin(async_stream::stream! {
+            let mut backoff = INITIAL_BACKOFF;
+            let mut last = None;
+
+            loop {
+                let mut session = match Session::start(&bootstrap, &listener).await {
+                    Ok(session) => session,
+                    Err(error) => {
+                        yield Err(error);
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                        continue;
+                    }
+                };
+
+                loop {
+                    match session.next().await {
+                        Ok(Some(addresses)) => {
+                            backoff = INITIAL_BACKOFF;
+                            if last.as_ref() != Some(&addresses) {
+                                last = Some(addresses.clone());
+                                yield Ok(Resolution::new(addresses));
+                            }
+                        }
+                        Ok(None) => backoff = INITIAL_BACKOFF,
+                        Err(error) => {
+                            yield Err(error);
+                            break;
+                        }
+                    }
+                }
+
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        })
+    }
+}
+
+/// The listener named by `xds://name` or `xds://authority/name` targets.
+fn listener_name(target: &Uri) -> String {
+    match target.path().trim_start_matches('/') {
+        "" => target
+            .authority()
+            .map(|authority| authority.as_str().to_string())
+            .unwrap_or_default(),
+        name => name.to_string(),
+    }
+}
+
+/// A stream of Aggregated Discovery Service updates.
+struct Session {
+    subscriber: Subscriber,
+    responses: Streaming<DiscoveryResponse>,
+}
+
+/// Tracks the resources of a session and requests them.
+struct Subscriber {
+    requests: mpsc::Sender<DiscoveryRequest>,
+    state: State,
+    node: Option<proto::Node>,
+    subscriptions: HashMap<&'static str, Vec<String>>,
+    /// The last accepted version and last received nonce of each type.
+    versions: HashMap<&'static str, (String, String)>,
+}
+
+impl Session {
+    async fn start(bootstrap: &Bootstrap, listener: &str) -> Result<Self, BoxError> {
+        let channel = Endpoint::from(bootstrap.server_uri.clone())
+            .connect()
+            .await?;
+        let mut grpc = tonic::client::Grpc::new(channel);
+
+        // Queue the listener subscription first, the control plane may not
+        // send the response headers before receiving a request.
+        let (requests, rx) = mpsc::channel(RESOURCE_TYPES.len());
+        let mut subscriber = Subscriber {
+            requests,
+            state: State::new(listener),
+            node: Some(bootstrap.node.clone()),
+            subscriptions: HashMap::new(),
+            versions: HashMap::new(),
+        };
+        subscriber.subscribe().await?;
+
+        grpc.ready().await?;
+        let responses = grpc
+            .streaming(
+                tonic::Request::new(ReceiverStream::new(rx)),
+                PathAndQuery::from_static(proto::AGGREGATED_RESOURCES_PATH),
+                ProstCodec::<DiscoveryRequest, DiscoveryResponse>::default(),
+            )
+            .await?
+            .into_inner();
+
+        Ok(Session {
+            subscriber,
+            responses,
+        })
+    }
+
+    /// Process the next response, returning the resolved addresses if the
+    /// listener is resolved.
+    async fn next(&mut self) -> Result<Option<Vec<Uri>>, BoxError> {
+        let response = match self.responses.message().await? {
+            Some(response) => response,
+            None => return Err("xDS stream closed by the control plane".into()),
+        };
+
+        self.subscriber.handle(response).await?;
+
+        let addresses = match self.subscriber.state.addresses() {
+            Some(addresses) => addresses,
+            None => return Ok(None),
+        };
+
+        addresses
+            .iter()
+            .map(|address| format!("http://{}", address).parse().map_err(Into::into))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+impl Subscriber {
+    /// Apply `response` and acknowledge it, or reject it if it is invalid.
+    async fn handle(&mut self, response: DiscoveryResponse) -> Result<(), BoxError> {
+        let type_url = match RESOURCE_TYPES.iter().find(|&&t| t == response.type_url) {
+            Some(type_url) => *type_url,
+            None => {
+                tracing::debug!("ignoring xDS resources of type {}", response.type_url);
+                return Ok(());
+            }
+        };
+
+        // A rejection repeats the last accepted version.
+        let (version, _) = self.versions.remove(type_url).unwrap_or_default();
+        let version = match self.state.update(&response) {
+            Ok(()) => response.version_info,
+            Err(error) => {
+                tracing::warn!("rejecting xDS update: {}", error);
+                version
+            }
+        };
+        self.versions.insert(type_url, (version, response.nonce));
+
+        // Acknowledge the response, then follow the resources it references.
+        self.subscriptions.remove(type_url);
+        self.subscribe().await
+    }
+
+    /// Send requests for every resource type whose subscription changed.
+    async fn subscribe(&mut self) -> Result<(), BoxError> {
+        for type_url in RESOURCE_TYPES {
+            let names = self.state.subscriptions(type_url);
+            if names.is_empty() || self.subscriptions.get(type_url) == Some(&names) {
+                continue;
+            }
+
+            let (version, nonce) = self.versions.get(type_url).cloned().unwrap_or_default();
+            let request = DiscoveryRequest {
+                version_info: version,
+                node: self.node.take(),
+                resource_names: names.clone(),
+                type_url: type_url.to_string(),
+                response_nonce: nonce,
+            };
+
+            self.requests
+                .send(request)
+                .await
+                .map_err(|_| "xDS stream closed")?;
+            self.subscriptions.insert(type_url, names);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::*;
+    use crate::state::tests::{assignment, eds_cluster, listener, response, route_config};
+    use std::convert::Infallible;
+    use tokio_stream::StreamExt;
+    use tonic::{server::Grpc, Status};
+
+    /// A control plane answering each first subscription to a resource type.
+    #[derive(Clone)]
+    struct Ads;
+
+    impl tonic::server::StreamingService<DiscoveryRequest> for Ads {
+        type Response = DiscoveryResponse;
+        type ResponseStream = ReceiverStream<Result<DiscoveryResponse, Status>>;
+        type Future = std::future::Ready<Result<tonic::Response<Self::ResponseStream>, Status>>;
+
+        fn call(&mut self, request: tonic::Request<Streaming<DiscoveryRequest>>) -> Self::Future {
+            let mut requests = request.into_inner();
+            let (tx, rx) = mpsc::channel(4);
+
+            tokio::spawn(async move {
+                while let Some(Ok(request)) = requests.next().await {
+                    if !request.response_nonce.is_empty() {
+                        continue;
+                    }
+
+                    let response = match request.type_url.as_str() {
+                        LISTENER_TYPE => {
+                            response(LISTENER_TYPE, &[listener("greeter:50051", "routes")])
+                        }
+                        ROUTE_CONFIGURATION_TYPE => response(
+                            ROUTE_CONFIGURATION_TYPE,
+                            &[route_config("routes", "greeter:50051", "cluster")],
+                        ),
+                        CLUSTER_TYPE => response(CLUSTER_TYPE, &[eds_cluster("cluster")]),
+                        _ => response(
+                            CLUSTER_LOAD_ASSIGNMENT_TYPE,
+                            &[assignment(
+                                "cluster",
+                                &[("10.0.0.1", 8080, HealthStatus::Healthy, 0)],
+                            )],
+                        ),
+                    };
+                    if tx.send(Ok(response)).await.is_err() {
+                        return;
+                    }
+                }
+            });
+
+            std::future::ready(Ok(tonic::Response::new(ReceiverStream::new(rx))))
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves_through_control_plane() {
+        let make = hyper::service::make_service_fn(|_| async {
+            Ok::<_, Infallible>(hyper::service::service_fn(
+                |request: http::Request<hyper::Body>| async move {
+                    let mut grpc =
+                        Grpc::new(ProstCodec::<DiscoveryResponse, DiscoveryRequest>::default());
+                    Ok::<_, Infallible>(grpc.streaming(Ads, request).await)
+                },
+            ))
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let bootstrap = Bootstrap::from_json(&format!(
+            r#"{{ "xds_servers": [{{ "server_uri": "{}" }}], "node": {{ "id": "test" }} }}"#,
+            addr
+        ))
+        .unwrap();
+        let resolver = XdsResolver::new(bootstrap);
+        let mut stream = resolver.resolve(&Uri::from_static("xds://greeter:50051"));
+
+        let resolution = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            resolution.addresses(),
+            [Uri::from_static("http://10.0.0.1:8080")]
+        );
+    }
+
+    #[test]
+    fn listener_names() {
+        let name = |target| listener_name(&Uri::from_static(target));
+
+        assert_eq!(name("xds://greeter.svc:50051"), "greeter.svc:50051");
+        assert_eq!(name("xds://control-plane/greeter.svc"), "greeter.svc");
+    }
+}
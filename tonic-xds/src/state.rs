@@ -0,0 +1,466 @@
+//! The resources received from the control plane, and how they resolve a
+//! listener to addresses.
+
+use crate::proto::{
+    self, Cluster, ClusterLoadAssignment, ClusterSpecifier, DiscoveryResponse, DiscoveryType,
+    HealthStatus, HttpConnectionManager, Listener, PathSpecifier, RouteActionSpecifier,
+    RouteConfiguration, RouteSpecifier, VirtualHost,
+};
+use prost::Message;
+use prost_types::Any;
+use std::collections::{BTreeSet, HashMap};
+
+/// The resource types the resolver subscribes to, in dependency order.
+pub(crate) const RESOURCE_TYPES: [&str; 4] = [
+    proto::LISTENER_TYPE,
+    proto::ROUTE_CONFIGURATION_TYPE,
+    proto::CLUSTER_TYPE,
+    proto::CLUSTER_LOAD_ASSIGNMENT_TYPE,
+];
+
+#[derive(Debug, Default)]
+pub(crate) struct State {
+    listener: String,
+    listeners: HashMap<String, Listener>,
+    route_configs: HashMap<String, RouteConfiguration>,
+    clusters: HashMap<String, Cluster>,
+    assignments: HashMap<String, ClusterLoadAssignment>,
+}
+
+/// Where the route configuration of the listener comes from.
+enum Routes {
+    Rds(String),
+    Inline(RouteConfiguration),
+}
+
+impl State {
+    pub(crate) fn new(listener: impl Into<String>) -> Self {
+        State {
+            listener: listener.into(),
+            ..State::default()
+        }
+    }
+
+    /// Apply the resources of `response`.
+    ///
+    /// Returns an error, to be reported back to the control plane, if one
+    /// of the resources can't be decoded. The state is left unchanged in
+    /// that case.
+    pub(crate) fn update(&mut self, response: &DiscoveryResponse) -> Result<(), String> {
+        match response.type_url.as_str() {
+            // Listeners and clusters are "state of the world" resources:
+            // those missing from a response have been removed.
+            proto::LISTENER_TYPE => {
+                self.listeners = decode_all(&response.resources, |l: &Listener| &l.name)?;
+            }
+            proto::CLUSTER_TYPE => {
+                self.clusters = decode_all(&response.resources, |c: &Cluster| &c.name)?;
+            }
+            proto::ROUTE_CONFIGURATION_TYPE => {
+                let routes = decode_all(&response.resources, |r: &RouteConfiguration| &r.name)?;
+                self.route_configs.extend(routes);
+            }
+            proto::CLUSTER_LOAD_ASSIGNMENT_TYPE => {
+                let assignments = decode_all(&response.resources, |a: &ClusterLoadAssignment| {
+                    &a.cluster_name
+                })?;
+                self.assignments.extend(assignments);
+            }
+            type_url => return Err(format!("unexpected resource type {}", type_url)),
+        }
+
+        Ok(())
+    }
+
+    /// The names of the resources of `type_url` needed to resolve the
+    /// listener.
+    pub(crate) fn subscriptions(&self, type_url: &str) -> Vec<String> {
+        let names: BTreeSet<String> = match type_url {
+            proto::LISTENER_TYPE => [self.listener.clone()].into(),
+            proto::ROUTE_CONFIGURATION_TYPE => match self.routes() {
+                Some(Routes::Rds(name)) => [name].into(),
+                _ => BTreeSet::new(),
+            },
+            proto::CLUSTER_TYPE => self.cluster_names(),
+            proto::CLUSTER_LOAD_ASSIGNMENT_TYPE => self
+                .cluster_names()
+                .iter()
+                .filter_map(|name| self.clusters.get(name))
+                .filter_map(eds_service_name)
+                .collect(),
+            _ => BTreeSet::new(),
+        };
+
+        names.into_iter().collect()
+    }
+
+    /// The addresses the listener resolves to, or `None` while resources
+    /// needed to resolve it are missing.
+    pub(crate) fn addresses(&self) -> Option<Vec<String>> {
+        let clusters = self.cluster_names();
+        if clusters.is_empty() {
+            return None;
+        }
+
+        let mut addresses = BTreeSet::new();
+        for name in &clusters {
+            let cluster = self.clusters.get(name)?;
+            let assignment = match eds_service_name(cluster) {
+                Some(service_name) => self.assignments.get(&service_name)?,
+                None => cluster.load_assignment.as_ref()?,
+            };
+            addresses.extend(endpoints(assignment));
+        }
+
+        Some(addresses.into_iter().collect())
+    }
+
+    fn routes(&self) -> Option<Routes> {
+        let listener = self.listeners.get(&self.listener)?;
+        let any = listener.api_listener.as_ref()?.api_listener.as_ref()?;
+        if any.type_url != proto::HTTP_CONNECTION_MANAGER_TYPE {
+            return None;
+        }
+
+        match HttpConnectionManager::decode(&any.value[..])
+            .ok()?
+            .route_specifier?
+        {
+            RouteSpecifier::Rds(rds) => Some(Routes::Rds(rds.route_config_name)),
+            RouteSpecifier::RouteConfig(config) => Some(Routes::Inline(config)),
+        }
+    }
+
+    /// The clusters of the default route of the virtual host matching the
+    /// listener.
+    fn cluster_names(&self) -> BTreeSet<String> {
+        let config = match self.routes() {
+            Some(Routes::Rds(name)) => self.route_configs.get(&name).cloned(),
+            Some(Routes::Inline(config)) => Some(config),
+            None => None,
+        };
+
+        let virtual_host = match config
+            .as_ref()
+            .and_then(|config| matching_virtual_host(&config.virtual_hosts, &self.listener))
+        {
+            Some(virtual_host) => virtual_host,
+            None => return BTreeSet::new(),
+        };
+
+        let route = virtual_host
+            .routes
+            .iter()
+            .find(|route| is_default_route(route))
+            .or_else(|| virtual_host.routes.first());
+
+        match route.and_then(|route| route.action.as_ref()) {
+            Some(RouteActionSpecifier::Route(action)) => match &action.cluster_specifier {
+                Some(ClusterSpecifier::Cluster(name)) => [name.clone()].into(),
+                Some(ClusterSpecifier::WeightedClusters(weighted)) => weighted
+                    .clusters
+                    .iter()
+                    .map(|cluster| cluster.name.clone())
+                    .collect(),
+                None => BTreeSet::new(),
+            },
+            None => BTreeSet::new(),
+        }
+    }
+}
+
+fn decode_all<M, F>(resources: &[Any], name: F) -> Result<HashMap<String, M>, String>
+where
+    M: Message + Default,
+    F: Fn(&M) -> &String,
+{
+    resources
+        .iter()
+        .map(|any| {
+            let resource = M::decode(&any.value[..])
+                .map_err(|e| format!("failed to decode {}: {}", any.type_url, e))?;
+            Ok((name(&resource).clone(), resource))
+        })
+        .collect()
+}
+
+/// The name of the load assignment of an EDS cluster.
+fn eds_service_name(cluster: &Cluster) -> Option<String> {
+    if cluster.r#type != DiscoveryType::Eds as i32 {
+        return None;
+    }
+
+    let service_name = cluster
+        .eds_cluster_config
+        .as_ref()
+        .map(|config| config.service_name.as_str())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(&cluster.name);
+    Some(service_name.to_string())
+}
+
+/// Pick the virtual host whose domains match `host` most specifically: an
+/// exact match, then suffix and prefix wildcards, and finally `*`.
+fn matching_virtual_host<'a>(hosts: &'a [VirtualHost], host: &str) -> Option<&'a VirtualHost> {
+    let host = host.to_ascii_lowercase();
+
+    let rank = |domain: &str| -> Option<(u8, usize)> {
+        let domain = domain.to_ascii_lowercase();
+        if domain == host {
+            Some((3, domain.len()))
+        } else if domain == "*" {
+            Some((0, 0))
+        } else if let Some(suffix) = domain.strip_prefix('*') {
+            host.ends_with(suffix).then_some((2, domain.len()))
+        } else if let Some(prefix) = domain.strip_suffix('*') {
+            host.starts_with(prefix).then_some((1, domain.len()))
+        } else {
+            None
+        }
+    };
+
+    hosts
+        .iter()
+        .filter_map(|vh| {
+            vh.domains
+                .iter()
+                .filter_map(|domain| rank(domain))
+                .max()
+                .map(|rank| (rank, vh))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, vh)| vh)
+}
+
+fn is_default_route(route: &proto::Route) -> bool {
+    match route
+        .r#match
+        .as_ref()
+        .and_then(|m| m.path_specifier.as_ref())
+    {
+        Some(PathSpecifier::Prefix(prefix)) => prefix.is_empty() || prefix == "/",
+        _ => false,
+    }
+}
+
+/// The usable endpoints of the highest priority of `assignment`, as
+/// `host:port`.
+fn endpoints(assignment: &ClusterLoadAssignment) -> Vec<String> {
+    let priority = match assignment.endpoints.iter().map(|e| e.priority).min() {
+        Some(priority) => priority,
+        None => return Vec::new(),
+    };
+
+    assignment
+        .endpoints
+        .iter()
+        .filter(|locality| locality.priority == priority)
+        .flat_map(|locality| &locality.lb_endpoints)
+        .filter(|endpoint| {
+            matches!(
+                HealthStatus::try_from(endpoint.health_status),
+                Ok(HealthStatus::Unknown | HealthStatus::Healthy)
+            )
+        })
+        .filter_map(|endpoint| {
+            endpoint
+                .endpoint
+                .as_ref()?
+                .address
+                .as_ref()?
+                .socket_address
+                .as_ref()
+        })
+        .map(
+            |address| match address.address.parse::<std::net::Ipv6Addr>() {
+                Ok(_) => format!("[{}]:{}", address.address, address.port_value),
+                Err(_) => format!("{}:{}", address.address, address.port_value),
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::proto::*;
+
+    pub(crate) fn any<M: Message>(type_url: &str, message: &M) -> Any {
+        Any {
+            type_url: type_url.to_string(),
+            value: message.encode_to_vec(),
+        }
+    }
+
+    pub(crate) fn response<M: Message>(type_url: &str, resources: &[M]) -> DiscoveryResponse {
+        DiscoveryResponse {
+            version_info: "1".to_string(),
+            resources: resources.iter().map(|r| any(type_url, r)).collect(),
+            type_url: type_url.to_string(),
+            nonce: "nonce".to_string(),
+        }
+    }
+
+    pub(crate) fn listener(name: &str, route_config_name: &str) -> Listener {
+        let manager = HttpConnectionManager {
+            route_specifier: Some(RouteSpecifier::Rds(Rds {
+                route_config_name: route_config_name.to_string(),
+            })),
+        };
+        Listener {
+            name: name.to_string(),
+            api_listener: Some(ApiListener {
+                api_listener: Some(any(HTTP_CONNECTION_MANAGER_TYPE, &manager)),
+            }),
+        }
+    }
+
+    pub(crate) fn route_config(name: &str, domain: &str, cluster: &str) -> RouteConfiguration {
+        RouteConfiguration {
+            name: name.to_string(),
+            virtual_hosts: vec![
+                VirtualHost {
+                    name: "other".to_string(),
+                    domains: vec!["*".to_string()],
+                    routes: Vec::new(),
+                },
+                VirtualHost {
+                    name: "greeter".to_string(),
+                    domains: vec![domain.to_string()],
+                    routes: vec![Route {
+                        r#match: Some(RouteMatch {
+                            path_specifier: Some(PathSpecifier::Prefix(String::new())),
+                        }),
+                        action: Some(RouteActionSpecifier::Route(RouteAction {
+                            cluster_specifier: Some(ClusterSpecifier::Cluster(cluster.to_string())),
+                        })),
+                    }],
+                },
+            ],
+        }
+    }
+
+    pub(crate) fn eds_cluster(name: &str) -> Cluster {
+        Cluster {
+            name: name.to_string(),
+            r#type: DiscoveryType::Eds as i32,
+            eds_cluster_config: Some(EdsClusterConfig {
+                service_name: String::new(),
+            }),
+            load_assignment: None,
+        }
+    }
+
+    pub(crate) fn assignment(
+        name: &str,
+        endpoints: &[(&str, u32, HealthStatus, u32)],
+    ) -> ClusterLoadAssignment {
+        ClusterLoadAssignment {
+            cluster_name: name.to_string(),
+            endpoints: endpoints
+                .iter()
+                .map(|&(address, port, health, priority)| LocalityLbEndpoints {
+                    lb_endpoints: vec![LbEndpoint {
+                        endpoint: Some(Endpoint {
+                            address: Some(Address {
+                                socket_address: Some(SocketAddress {
+                                    address: address.to_string(),
+                                    port_value: port,
+                                }),
+                            }),
+                        }),
+                        health_status: health as i32,
+                    }],
+                    priority,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn resolves_listener_chain() {
+        let mut state = State::new("greeter:50051");
+        assert_eq!(state.subscriptions(LISTENER_TYPE), ["greeter:50051"]);
+        assert!(state.subscriptions(ROUTE_CONFIGURATION_TYPE).is_empty());
+
+        state
+            .update(&response(
+                LISTENER_TYPE,
+                &[listener("greeter:50051", "routes")],
+            ))
+            .unwrap();
+        assert_eq!(state.subscriptions(ROUTE_CONFIGURATION_TYPE), ["routes"]);
+        assert!(state.addresses().is_none());
+
+        state
+            .update(&response(
+                ROUTE_CONFIGURATION_TYPE,
+                &[route_config("routes", "greeter*", "greeter-cluster")],
+            ))
+            .unwrap();
+        assert_eq!(state.subscriptions(CLUSTER_TYPE), ["greeter-cluster"]);
+
+        state
+            .update(&response(CLUSTER_TYPE, &[eds_cluster("greeter-cluster")]))
+            .unwrap();
+        assert_eq!(
+            state.subscriptions(CLUSTER_LOAD_ASSIGNMENT_TYPE),
+            ["greeter-cluster"]
+        );
+        assert!(state.addresses().is_none());
+
+        state
+            .update(&response(
+                CLUSTER_LOAD_ASSIGNMENT_TYPE,
+                &[assignment(
+                    "greeter-cluster",
+                    &[
+                        ("10.0.0.1", 8080, HealthStatus::Healthy, 0),
+                        ("::1", 8080, HealthStatus::Unknown, 0),
+                        ("10.0.0.2", 8080, HealthStatus::Unhealthy, 0),
+                        ("10.0.0.3", 8080, HealthStatus::Healthy, 1),
+                    ],
+                )],
+            ))
+            .unwrap();
+        assert_eq!(state.addresses().unwrap(), ["10.0.0.1:8080", "[::1]:8080"]);
+
+        // Removing the cluster from the state of the world unresolves the
+        // listener.
+        state
+            .update(&response::<Cluster>(CLUSTER_TYPE, &[]))
+            .unwrap();
+        assert!(state.addresses().is_none());
+    }
+
+    #[test]
+    fn rejects_undecodable_resources() {
+        let mut state = State::new("greeter");
+        let mut response = response(LISTENER_TYPE, &[listener("greeter", "routes")]);
+        response.resources[0].value = vec![0xff];
+
+        assert!(state.update(&response).is_err());
+        assert!(state.listeners.is_empty());
+    }
+
+    #[test]
+    fn matches_most_specific_virtual_host() {
+        let host = |name: &str, domain: &str| VirtualHost {
+            name: name.to_string(),
+            domains: vec![domain.to_string()],
+            routes: Vec::new(),
+        };
+        let hosts = [
+            host("any", "*"),
+            host("prefix", "greeter.*"),
+            host("suffix", "*.svc"),
+            host("exact", "greeter.svc"),
+        ];
+
+        let matched = |name| matching_virtual_host(&hosts, name).map(|vh| vh.name.as_str());
+        assert_eq!(matched("greeter.svc"), Some("exact"));
+        assert_eq!(matched("other.svc"), Some("suffix"));
+        assert_eq!(matched("greeter.local"), Some("prefix"));
+        assert_eq!(matched("other"), Some("any"));
+    }
+}
@@ -0,0 +1,132 @@
+//! Synchronous clients.
+//!
+//! Generated clients built with `blocking_client` have a `blocking` module
+//! with a synchronous client of the same name, whose methods block on the
+//! [`Runtime`] they share:
+//!
+//! ```ignore
+//! let mut client = greeter_client::blocking::GreeterClient::connect("http://[::1]:50051")?;
+//! let response = client.say_hello(HelloRequest::default())?;
+//! ```
+//!
+//! The runtime has a thread of its own, which drives the connections of the
+//! clients between calls. Blocking clients may not be used from within an
+//! asynchronous runtime, where calls panic, as `tokio`'s `block_on` does.
+
+use crate::{metadata::MetadataMap, Status};
+use std::{fmt, future::Future, io, sync::Arc};
+
+/// The runtime of blocking clients, cheap to clone.
+#[derive(Clone)]
+pub struct Runtime {
+    inner: Arc<tokio::runtime::Runtime>,
+}
+
+impl Runtime {
+    /// Create a runtime with a thread of its own.
+    pub fn new() -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("tonic-blocking")
+            .enable_all()
+            .build()?;
+        Ok(Runtime {
+            inner: Arc::new(runtime),
+        })
+    }
+
+    /// Run `future` to completion on the runtime, blocking the current
+    /// thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within an asynchronous runtime.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.inner.block_on(future)
+    }
+}
+
+/// Connect to `dst` with a channel driven by a new runtime, as the `connect`
+/// of generated blocking clients does.
+#[cfg(feature = "transport")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
+pub fn connect<D>(dst: D) -> Result<(crate::transport::Channel, Runtime), crate::transport::Error>
+where
+    D: TryInto<crate::transport::Endpoint>,
+    D::Error: Into<crate::Error>,
+{
+    let runtime = Runtime::new().map_err(crate::transport::Error::from_source)?;
+    let endpoint = crate::transport::Endpoint::new(dst)?;
+    let channel = runtime.block_on(endpoint.connect())?;
+    Ok((channel, runtime))
+}
+
+impl fmt::Debug for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Runtime").finish()
+    }
+}
+
+/// The messages of a streaming response, received as they're iterated.
+#[derive(Debug)]
+pub struct Streaming<T> {
+    inner: crate::Streaming<T>,
+    runtime: Runtime,
+}
+
+impl<T> Streaming<T> {
+    /// Receive the messages of `inner` on `runtime`.
+    pub fn new(inner: crate::Streaming<T>, runtime: Runtime) -> Self {
+        Streaming { inner, runtime }
+    }
+
+    /// The next message of the response, `None` once it's over.
+    pub fn message(&mut self) -> Result<Option<T>, Status> {
+        self.runtime.block_on(self.inner.message())
+    }
+
+    /// The trailers of the response, once its messages were received.
+    pub fn trailers(&mut self) -> Result<Option<MetadataMap>, Status> {
+        self.runtime.block_on(self.inner.trailers())
+    }
+
+    /// The asynchronous stream of the messages.
+    pub fn into_inner(self) -> crate::Streaming<T> {
+        self.inner
+    }
+}
+
+impl<T> Iterator for Streaming<T> {
+    type Item = Result<T, Status>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.message().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drives_tasks_between_calls() {
+        let runtime = Runtime::new().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        // Spawned tasks run without a call blocking on the runtime.
+        runtime.block_on(async {
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                tx.send(()).unwrap();
+            });
+        });
+        rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+    }
+
+    #[tokio::test]
+    #[should_panic]
+    async fn panics_within_runtimes() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {});
+    }
+}
@@ -1,9 +1,13 @@
-use crate::codec::compression::{CompressionEncoding, EnabledCompressionEncodings};
+use super::interceptor::{CallInterceptor, InterceptedDecoder, InterceptedEncoder};
+use crate::codec::compression::{
+    CompressionConfig, CompressionEncoding, CompressionLevel, EnabledCompressionEncodings,
+};
 use crate::{
     body::BoxBody,
-    client::GrpcService,
+    client::{GrpcService, MessageInterceptor},
     codec::{encode_client, Codec, Decoder, Streaming},
-    request::SanitizeHeaders,
+    request::{CallAuthority, SanitizeHeaders, SendCompression},
+    stats::CallStats,
     Code, Request, Response, Status,
 };
 use http::{
@@ -11,7 +15,7 @@ use http::{
     uri::{PathAndQuery, Uri},
 };
 use http_body::Body;
-use std::{fmt, future, pin::pin};
+use std::{fmt, future, pin::pin, sync::Arc};
 use tokio_stream::{Stream, StreamExt};
 
 /// A gRPC client dispatcher.
@@ -38,10 +42,14 @@ struct GrpcConfig {
     accept_compression_encodings: EnabledCompressionEncodings,
     /// The compression encoding that will be applied to requests.
     send_compression_encodings: Option<CompressionEncoding>,
+    /// How the client compresses requests.
+    compression_config: CompressionConfig,
     /// Limits the maximum size of a decoded message.
     max_decoding_message_size: Option<usize>,
     /// Limits the maximum size of an encoded message.
     max_encoding_message_size: Option<usize>,
+    /// Intercepts the messages of calls.
+    message_interceptor: Option<Arc<dyn MessageInterceptor>>,
 }
 
 impl<T> Grpc<T> {
@@ -52,17 +60,20 @@ impl<T> Grpc<T> {
 
     /// Creates a new gRPC client with the provided [`GrpcService`] and `Uri`.
     ///
-    /// The provided Uri will use only the scheme and authority parts as the
-    /// path_and_query portion will be set for each method.
+    /// The scheme and authority of the provided Uri are those of the requests,
+    /// and its path, such as `/api/v1`, prefixes the path of each method, to
+    /// reach services a server nests under it.
     pub fn with_origin(inner: T, origin: Uri) -> Self {
         Self {
             inner,
             config: GrpcConfig {
                 origin,
                 send_compression_encodings: None,
+                compression_config: CompressionConfig::default(),
                 accept_compression_encodings: EnabledCompressionEncodings::default(),
                 max_decoding_message_size: None,
                 max_encoding_message_size: None,
+                message_interceptor: None,
             },
         }
     }
@@ -93,11 +104,83 @@ impl<T> Grpc<T> {
     /// let client = TestClient::new(channel).send_compressed(CompressionEncoding::Gzip);
     /// # };
     /// ```
+    ///
+    /// Each request can override this with
+    /// [`Request::set_compression`] or [`Request::disable_compression`].
     pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
         self.config.send_compression_encodings = Some(encoding);
         self
     }
 
+    /// Send the requests smaller than `bytes` uncompressed, even when
+    /// compressing requests.
+    ///
+    /// Compressing small messages costs more than it saves, and may even
+    /// make them larger. Defaults to `0`, compressing every request.
+    ///
+    /// # Example
+    ///
+    /// The most common way of using this is through a client generated by tonic-build:
+    ///
+    /// ```rust
+    /// use tonic::transport::Channel;
+    /// # enum CompressionEncoding { Gzip }
+    /// # struct TestClient<T>(T);
+    /// # impl<T> TestClient<T> {
+    /// #     fn new(channel: T) -> Self { Self(channel) }
+    /// #     fn send_compressed(self, _: CompressionEncoding) -> Self { self }
+    /// #     fn send_compressed_min_size(self, _: usize) -> Self { self }
+    /// # }
+    ///
+    /// # async {
+    /// let channel = Channel::builder("127.0.0.1:3000".parse().unwrap())
+    ///     .connect()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let client = TestClient::new(channel)
+    ///     .send_compressed(CompressionEncoding::Gzip)
+    ///     .send_compressed_min_size(1024);
+    /// # };
+    /// ```
+    pub fn send_compressed_min_size(mut self, bytes: usize) -> Self {
+        self.config.compression_config.min_size = bytes;
+        self
+    }
+
+    /// Compress requests at the given level, instead of the default level of
+    /// their encoding.
+    ///
+    /// # Example
+    ///
+    /// The most common way of using this is through a client generated by tonic-build:
+    ///
+    /// ```rust
+    /// use tonic::{codec::CompressionLevel, transport::Channel};
+    /// # enum CompressionEncoding { Gzip }
+    /// # struct TestClient<T>(T);
+    /// # impl<T> TestClient<T> {
+    /// #     fn new(channel: T) -> Self { Self(channel) }
+    /// #     fn send_compressed(self, _: CompressionEncoding) -> Self { self }
+    /// #     fn send_compression_level(self, _: CompressionLevel) -> Self { self }
+    /// # }
+    ///
+    /// # async {
+    /// let channel = Channel::builder("127.0.0.1:3000".parse().unwrap())
+    ///     .connect()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let client = TestClient::new(channel)
+    ///     .send_compressed(CompressionEncoding::Gzip)
+    ///     .send_compression_level(CompressionLevel::Fastest);
+    /// # };
+    /// ```
+    pub fn send_compression_level(mut self, level: CompressionLevel) -> Self {
+        self.config.compression_config.level = level;
+        self
+    }
+
     /// Enable accepting compressed responses.
     ///
     /// Requires the server to also support sending compressed responses.
@@ -131,6 +214,10 @@ impl<T> Grpc<T> {
 
     /// Limits the maximum size of a decoded message.
     ///
+    /// Compressed messages are limited both before and after they are
+    /// decompressed, failing with [`Code::ResourceExhausted`] if they expand
+    /// past the limit.
+    ///
     /// # Example
     ///
     /// The most common way of using this is through a client generated by tonic-build:
@@ -189,6 +276,15 @@ impl<T> Grpc<T> {
         self
     }
 
+    /// Intercept the messages of calls with `interceptor`, before they are
+    /// encoded and once they are decoded.
+    ///
+    /// See [`MessageInterceptor`] for more details.
+    pub fn message_interceptor(mut self, interceptor: impl MessageInterceptor) -> Self {
+        self.config.message_interceptor = Some(Arc::new(interceptor));
+        self
+    }
+
     /// Check if the inner [`GrpcService`] is able to accept a  new request.
     ///
     /// This will call [`GrpcService::poll_ready`] until it returns ready or
@@ -216,8 +312,19 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
+        let interceptor = self.config.call_interceptor(&path);
+        let request = match &interceptor {
+            Some(interceptor) => interceptor.request(request)?,
+            None => request,
+        };
         let request = request.map(|m| tokio_stream::once(m));
-        self.client_streaming(request, path, codec).await
+        let response = self
+            .client_streaming_with(request, path, codec, false)
+            .await?;
+        match &interceptor {
+            Some(interceptor) => interceptor.response(response),
+            None => Ok(response),
+        }
     }
 
     /// Send a client side streaming gRPC request.
@@ -236,8 +343,38 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
-        let (mut parts, body, extensions) =
-            self.streaming(request, path, codec).await?.into_parts();
+        let interceptor = self.config.call_interceptor(&path);
+        let response = self
+            .client_streaming_with(request, path, codec, true)
+            .await?;
+        match &interceptor {
+            Some(interceptor) => interceptor.response(response),
+            None => Ok(response),
+        }
+    }
+
+    /// Send a client side streaming gRPC request, intercepting each of its
+    /// messages if `intercept_messages`.
+    async fn client_streaming_with<S, M1, M2, C>(
+        &mut self,
+        request: Request<S>,
+        path: PathAndQuery,
+        codec: C,
+        intercept_messages: bool,
+    ) -> Result<Response<M2>, Status>
+    where
+        T: GrpcService<BoxBody>,
+        T::ResponseBody: Body + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<crate::Error>,
+        S: Stream<Item = M1> + Send + 'static,
+        C: Codec<Encode = M1, Decode = M2>,
+        M1: Send + Sync + 'static,
+        M2: Send + Sync + 'static,
+    {
+        let (mut parts, body, extensions) = self
+            .streaming_with(request, path, codec, (intercept_messages, false))
+            .await?
+            .into_parts();
 
         let mut body = pin!(body);
 
@@ -272,16 +409,43 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
+        let request = match self.config.call_interceptor(&path) {
+            Some(interceptor) => interceptor.request(request)?,
+            None => request,
+        };
         let request = request.map(|m| tokio_stream::once(m));
-        self.streaming(request, path, codec).await
+        self.streaming_with(request, path, codec, (false, true))
+            .await
     }
 
     /// Send a bi-directional streaming gRPC request.
     pub async fn streaming<S, M1, M2, C>(
+        &mut self,
+        request: Request<S>,
+        path: PathAndQuery,
+        codec: C,
+    ) -> Result<Response<Streaming<M2>>, Status>
+    where
+        T: GrpcService<BoxBody>,
+        T::ResponseBody: Body + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<crate::Error>,
+        S: Stream<Item = M1> + Send + 'static,
+        C: Codec<Encode = M1, Decode = M2>,
+        M1: Send + Sync + 'static,
+        M2: Send + Sync + 'static,
+    {
+        self.streaming_with(request, path, codec, (true, true))
+            .await
+    }
+
+    /// Send a bi-directional streaming gRPC request, intercepting each of
+    /// the messages sent and received as `intercept_messages` says.
+    async fn streaming_with<S, M1, M2, C>(
         &mut self,
         request: Request<S>,
         path: PathAndQuery,
         mut codec: C,
+        intercept_messages: (bool, bool),
     ) -> Result<Response<Streaming<M2>>, Status>
     where
         T: GrpcService<BoxBody>,
@@ -292,18 +456,37 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
-        let request = request
+        let interceptor = self.config.call_interceptor(&path);
+        let (requests, responses) = intercept_messages;
+        let encoder = InterceptedEncoder {
+            inner: codec.encoder(),
+            interceptor: interceptor.clone().filter(|_| requests),
+        };
+
+        let compression = request
+            .extensions()
+            .get::<SendCompression>()
+            .map_or(self.config.send_compression_encodings, |c| c.0);
+
+        // Filled by the transport, if it reports stats.
+        let stats = CallStats::caller();
+        let mut request = request
             .map(|s| {
                 encode_client(
-                    codec.encoder(),
+                    encoder,
                     s,
-                    self.config.send_compression_encodings,
+                    compression,
+                    self.config.compression_config,
                     self.config.max_encoding_message_size,
+                    Some(stats.clone()),
                 )
             })
             .map(BoxBody::new);
+        request.extensions_mut().insert(stats.clone());
 
-        let request = self.config.prepare_request(request, path);
+        let request = self
+            .config
+            .prepare_request(request, path, codec.content_type(), compression);
 
         let response = self
             .inner
@@ -311,9 +494,12 @@ impl<T> Grpc<T> {
             .await
             .map_err(Status::from_error_generic)?;
 
-        let decoder = codec.decoder();
+        let decoder = InterceptedDecoder {
+            inner: codec.decoder(),
+            interceptor: interceptor.filter(|_| responses),
+        };
 
-        self.create_response(decoder, response)
+        self.create_response(decoder, response, stats)
     }
 
     // Keeping this code in a separate function from Self::streaming lets functions that return the
@@ -322,6 +508,7 @@ impl<T> Grpc<T> {
         &self,
         decoder: impl Decoder<Item = M2, Error = Status> + Send + 'static,
         response: http::Response<T::ResponseBody>,
+        stats: CallStats,
     ) -> Result<Response<Streaming<M2>>, Status>
     where
         T: GrpcService<BoxBody>,
@@ -357,6 +544,7 @@ impl<T> Grpc<T> {
                     encoding,
                     self.config.max_decoding_message_size,
                 )
+                .with_stats(Some(stats))
             } else {
                 Streaming::new_empty(decoder, body)
             }
@@ -367,12 +555,26 @@ impl<T> Grpc<T> {
 }
 
 impl GrpcConfig {
+    fn call_interceptor(&self, path: &PathAndQuery) -> Option<CallInterceptor> {
+        self.message_interceptor
+            .clone()
+            .map(|interceptor| CallInterceptor::new(interceptor, path.path()))
+    }
+
     fn prepare_request(
         &self,
         request: Request<BoxBody>,
         path: PathAndQuery,
+        content_type: &'static str,
+        compression: Option<CompressionEncoding>,
     ) -> http::Request<BoxBody> {
         let mut parts = self.origin.clone().into_parts();
+        // An authority needs a scheme, which the transport sets otherwise.
+        if let (Some(_), Some(CallAuthority(authority))) =
+            (&parts.scheme, request.extensions().get::<CallAuthority>())
+        {
+            parts.authority = Some(authority.clone());
+        }
 
         match &parts.path_and_query {
             Some(pnq) if pnq != "/" => {
@@ -404,10 +606,9 @@ impl GrpcConfig {
         // Set the content type
         request
             .headers_mut()
-            .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+            .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
 
-        #[cfg(any(feature = "gzip", feature = "zstd"))]
-        if let Some(encoding) = self.send_compression_encodings {
+        if let Some(encoding) = compression {
             request.headers_mut().insert(
                 crate::codec::compression::ENCODING_HEADER,
                 encoding.into_header_value(),
@@ -435,9 +636,11 @@ impl<T: Clone> Clone for Grpc<T> {
             config: GrpcConfig {
                 origin: self.config.origin.clone(),
                 send_compression_encodings: self.config.send_compression_encodings,
+                compression_config: self.config.compression_config,
                 accept_compression_encodings: self.config.accept_compression_encodings,
                 max_encoding_message_size: self.config.max_encoding_message_size,
                 max_decoding_message_size: self.config.max_decoding_message_size,
+                message_interceptor: self.config.message_interceptor.clone(),
             },
         }
     }
@@ -456,6 +659,8 @@ impl<T: fmt::Debug> fmt::Debug for Grpc<T> {
             &self.config.send_compression_encodings,
         );
 
+        f.field("compression_config", &self.config.compression_config);
+
         f.field(
             "accept_compression_encodings",
             &self.config.accept_compression_encodings,
@@ -0,0 +1,302 @@
+use crate::{
+    codec::{BufferSettings, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    Request, Response, Status,
+};
+use std::{any::Any, sync::Arc};
+
+/// An interceptor of the messages of a client, before they are encoded and
+/// once they are decoded.
+///
+/// Unlike an [`Interceptor`](crate::service::Interceptor), which only sees
+/// the metadata of requests once their messages are opaque bodies, a
+/// message interceptor sees the messages themselves, as [`Any`] values to
+/// downcast into the message types of the calls. Returning an error fails
+/// the call with that status.
+///
+/// Generated clients accept a message interceptor when `tonic-build` is
+/// configured with `client_message_interceptors(true)`, or it can be set on
+/// a [`Grpc`](super::Grpc) with
+/// [`message_interceptor`](super::Grpc::message_interceptor).
+///
+/// ```
+/// use std::any::Any;
+/// use tonic::{client::MessageInterceptor, Request, Status};
+///
+/// # #[derive(Default)]
+/// # struct ListOrdersRequest { tenant_id: String }
+/// /// Sets the tenant of every `ListOrdersRequest`.
+/// struct Tenant(String);
+///
+/// impl MessageInterceptor for Tenant {
+///     fn request(&self, _path: &str, request: &mut Request<&mut dyn Any>) -> Result<(), Status> {
+///         if let Some(message) = request.get_mut().downcast_mut::<ListOrdersRequest>() {
+///             message.tenant_id = self.0.clone();
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+///
+/// The requests of unary and server streaming calls are intercepted whole,
+/// with their metadata and extensions, while each message of client
+/// streaming and bidirectional streaming calls is intercepted on its own,
+/// an error aborting the stream of requests. The same goes for responses.
+pub trait MessageInterceptor: Send + Sync + 'static {
+    /// Intercept the request of a unary or server streaming call to the
+    /// method of `path`, such as `/greeter.Greeter/SayHello`.
+    fn request(&self, path: &str, request: &mut Request<&mut dyn Any>) -> Result<(), Status> {
+        let _ = (path, request);
+        Ok(())
+    }
+
+    /// Intercept a message of a client streaming or bidirectional streaming
+    /// call.
+    fn request_message(&self, path: &str, message: &mut dyn Any) -> Result<(), Status> {
+        let _ = (path, message);
+        Ok(())
+    }
+
+    /// Intercept the response of a unary or client streaming call.
+    fn response(&self, path: &str, response: &mut Response<&mut dyn Any>) -> Result<(), Status> {
+        let _ = (path, response);
+        Ok(())
+    }
+
+    /// Intercept a message of a server streaming or bidirectional streaming
+    /// call.
+    fn response_message(&self, path: &str, message: &mut dyn Any) -> Result<(), Status> {
+        let _ = (path, message);
+        Ok(())
+    }
+}
+
+impl<T: MessageInterceptor> MessageInterceptor for Arc<T> {
+    fn request(&self, path: &str, request: &mut Request<&mut dyn Any>) -> Result<(), Status> {
+        (**self).request(path, request)
+    }
+
+    fn request_message(&self, path: &str, message: &mut dyn Any) -> Result<(), Status> {
+        (**self).request_message(path, message)
+    }
+
+    fn response(&self, path: &str, response: &mut Response<&mut dyn Any>) -> Result<(), Status> {
+        (**self).response(path, response)
+    }
+
+    fn response_message(&self, path: &str, message: &mut dyn Any) -> Result<(), Status> {
+        (**self).response_message(path, message)
+    }
+}
+
+/// The interceptor of a call, with the path of its method.
+#[derive(Clone)]
+pub(crate) struct CallInterceptor {
+    interceptor: Arc<dyn MessageInterceptor>,
+    path: Arc<str>,
+}
+
+impl CallInterceptor {
+    pub(crate) fn new(interceptor: Arc<dyn MessageInterceptor>, path: &str) -> Self {
+        CallInterceptor {
+            interceptor,
+            path: path.into(),
+        }
+    }
+
+    pub(crate) fn request<M: 'static>(&self, request: Request<M>) -> Result<Request<M>, Status> {
+        let (metadata, extensions, mut message) = request.into_parts();
+        let mut request = Request::from_parts(metadata, extensions, &mut message as &mut dyn Any);
+        self.interceptor.request(&self.path, &mut request)?;
+        let (metadata, extensions, _) = request.into_parts();
+        Ok(Request::from_parts(metadata, extensions, message))
+    }
+
+    pub(crate) fn response<M: 'static>(
+        &self,
+        response: Response<M>,
+    ) -> Result<Response<M>, Status> {
+        let (metadata, mut message, extensions) = response.into_parts();
+        let mut response = Response::from_parts(metadata, &mut message as &mut dyn Any, extensions);
+        self.interceptor.response(&self.path, &mut response)?;
+        let (metadata, _, extensions) = response.into_parts();
+        Ok(Response::from_parts(metadata, message, extensions))
+    }
+}
+
+/// Encoder intercepting each message before it is encoded.
+pub(crate) struct InterceptedEncoder<E> {
+    pub(crate) inner: E,
+    pub(crate) interceptor: Option<CallInterceptor>,
+}
+
+impl<E> Encoder for InterceptedEncoder<E>
+where
+    E: Encoder<Error = Status>,
+    E::Item: 'static,
+{
+    type Item = E::Item;
+    type Error = Status;
+
+    fn encode(&mut self, mut item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        if let Some(call) = &self.interceptor {
+            call.interceptor.request_message(&call.path, &mut item)?;
+        }
+        self.inner.encode(item, dst)
+    }
+
+    fn buffer_settings(&self) -> BufferSettings {
+        self.inner.buffer_settings()
+    }
+}
+
+/// Decoder intercepting each message once it is decoded.
+pub(crate) struct InterceptedDecoder<D> {
+    pub(crate) inner: D,
+    pub(crate) interceptor: Option<CallInterceptor>,
+}
+
+impl<D> Decoder for InterceptedDecoder<D>
+where
+    D: Decoder<Error = Status>,
+    D::Item: 'static,
+{
+    type Item = D::Item;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Status> {
+        let mut item = self.inner.decode(src)?;
+        if let (Some(call), Some(item)) = (&self.interceptor, &mut item) {
+            call.interceptor.response_message(&call.path, item)?;
+        }
+        Ok(item)
+    }
+
+    fn buffer_settings(&self) -> BufferSettings {
+        self.inner.buffer_settings()
+    }
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{body::BoxBody, client::Grpc, codec::ProstCodec, codec::Streaming, Code};
+    use http::uri::PathAndQuery;
+    use std::{convert::Infallible, sync::Mutex};
+    use tokio_stream::StreamExt;
+
+    /// Uppercases the messages sent, rejects `forbidden` and records the
+    /// messages received.
+    #[derive(Default)]
+    struct Shout(Mutex<Vec<String>>);
+
+    impl Shout {
+        fn shout(message: &mut dyn Any) -> Result<(), Status> {
+            let message = message.downcast_mut::<String>().unwrap();
+            if message == "forbidden" {
+                return Err(Status::invalid_argument("forbidden"));
+            }
+            *message = message.to_uppercase();
+            Ok(())
+        }
+    }
+
+    impl MessageInterceptor for Shout {
+        fn request(&self, path: &str, request: &mut Request<&mut dyn Any>) -> Result<(), Status> {
+            assert_eq!(path, "/test.Echo/Call");
+            request
+                .metadata_mut()
+                .insert("x-shout", "yes".parse().unwrap());
+            Self::shout(*request.get_mut())
+        }
+
+        fn request_message(&self, _: &str, message: &mut dyn Any) -> Result<(), Status> {
+            Self::shout(message)
+        }
+
+        fn response(&self, _: &str, response: &mut Response<&mut dyn Any>) -> Result<(), Status> {
+            let message = response.get_ref().downcast_ref::<String>().unwrap();
+            self.0.lock().unwrap().push(format!("response {}", message));
+            Ok(())
+        }
+
+        fn response_message(&self, _: &str, message: &mut dyn Any) -> Result<(), Status> {
+            let message = message.downcast_ref::<String>().unwrap();
+            self.0.lock().unwrap().push(format!("message {}", message));
+            Ok(())
+        }
+    }
+
+    /// A server echoing the messages of unary and streaming calls.
+    fn echo() -> impl crate::client::GrpcService<
+        BoxBody,
+        ResponseBody = BoxBody,
+        Error = Infallible,
+        Future = impl Send,
+    > + Clone {
+        tower::service_fn(|request: http::Request<BoxBody>| async move {
+            let mut grpc = crate::server::Grpc::new(ProstCodec::<String, String>::default());
+            if request.uri().path() == "/test.Echo/Stream" {
+                let echo = tower::service_fn(|request: Request<Streaming<String>>| async move {
+                    Ok(Response::new(request.into_inner()))
+                });
+                return Ok(grpc.streaming(echo, request).await);
+            }
+            let echo = tower::service_fn(|request: Request<String>| async move {
+                assert_eq!(request.metadata().get("x-shout").unwrap(), "yes");
+                Ok(Response::new(request.into_inner()))
+            });
+            Ok(grpc.unary(echo, request).await)
+        })
+    }
+
+    #[tokio::test]
+    async fn intercepts_unary_calls() {
+        let interceptor = Arc::new(Shout::default());
+        let mut client = Grpc::new(echo()).message_interceptor(interceptor.clone());
+        let path = PathAndQuery::from_static("/test.Echo/Call");
+
+        let response = client
+            .unary(
+                Request::new("hello".to_string()),
+                path.clone(),
+                ProstCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.into_inner(), "HELLO");
+
+        let status = client
+            .unary(
+                Request::new("forbidden".to_string()),
+                path,
+                ProstCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(*interceptor.0.lock().unwrap(), ["response HELLO"]);
+    }
+
+    #[tokio::test]
+    async fn intercepts_streaming_messages() {
+        let interceptor = Arc::new(Shout::default());
+        let mut client = Grpc::new(echo()).message_interceptor(interceptor.clone());
+
+        let requests = tokio_stream::iter(vec!["a".to_string(), "b".to_string()]);
+        let response = client
+            .streaming(
+                Request::new(requests),
+                PathAndQuery::from_static("/test.Echo/Stream"),
+                ProstCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap();
+        let messages: Vec<_> = response.into_inner().collect().await;
+
+        assert_eq!(
+            messages.into_iter().collect::<Result<Vec<_>, _>>().unwrap(),
+            ["A", "B"]
+        );
+        assert_eq!(*interceptor.0.lock().unwrap(), ["message A", "message B"]);
+    }
+}
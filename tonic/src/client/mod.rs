@@ -16,8 +16,18 @@
 //! communication. For more details, see
 //! [transport::Channel](../transport/struct.Channel.html#multiplexing-requests).
 
+#[cfg(feature = "blocking")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+pub mod blocking;
 mod grpc;
+mod interceptor;
+mod options;
 mod service;
+#[cfg(feature = "sink")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sink")))]
+pub mod sink;
 
 pub use self::grpc::Grpc;
+pub use self::interceptor::MessageInterceptor;
+pub use self::options::CallOptions;
 pub use self::service::GrpcService;
@@ -0,0 +1,219 @@
+use crate::{codec::CompressionEncoding, metadata::MetadataMap};
+use http::uri::Authority;
+use std::time::Duration;
+
+/// Options of a single call.
+///
+/// The options of a unary or server streaming call are passed along with its
+/// message to a generated client, as a `(message, options)` pair. Those of
+/// any call can also be set on its [`Request`] with
+/// [`Request::set_options`], such as `Request::new(stream)` for client
+/// streaming calls.
+///
+/// ```
+/// use std::time::Duration;
+/// use tonic::{client::CallOptions, IntoRequest, Request};
+///
+/// # pub struct HelloRequest {}
+/// let mut options = CallOptions::new()
+///     .timeout(Duration::from_secs(1))
+///     .wait_for_ready(true)
+///     .authority("greeter.internal".parse().unwrap());
+/// options
+///     .metadata_mut()
+///     .insert("x-request-id", "42".parse().unwrap());
+///
+/// // As in `client.say_hello((HelloRequest {}, options))`.
+/// let request: Request<HelloRequest> = (HelloRequest {}, options).into_request();
+/// assert!(request.wait_for_ready());
+/// assert_eq!(request.metadata().get("x-request-id").unwrap(), "42");
+/// ```
+///
+/// Options that aren't set keep the behavior configured on the client.
+///
+/// [`Request`]: crate::Request
+/// [`Request::set_options`]: crate::Request::set_options
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) wait_for_ready: Option<bool>,
+    pub(crate) compression: Option<Option<CompressionEncoding>>,
+    pub(crate) authority: Option<Authority>,
+    pub(crate) metadata: MetadataMap,
+}
+
+impl CallOptions {
+    /// Create options keeping the behavior of the client.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the deadline of the call, from the time it is sent.
+    ///
+    /// See [`Request::set_timeout`](crate::Request::set_timeout).
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        CallOptions {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Set whether the call waits for the channel to be ready.
+    ///
+    /// See [`Request::set_wait_for_ready`](crate::Request::set_wait_for_ready).
+    #[must_use]
+    pub fn wait_for_ready(self, wait_for_ready: bool) -> Self {
+        CallOptions {
+            wait_for_ready: Some(wait_for_ready),
+            ..self
+        }
+    }
+
+    /// Compress the requests of the call with `encoding`, whether the
+    /// client compresses its requests or not.
+    ///
+    /// See [`Request::set_compression`](crate::Request::set_compression).
+    #[must_use]
+    pub fn send_compressed(self, encoding: CompressionEncoding) -> Self {
+        CallOptions {
+            compression: Some(Some(encoding)),
+            ..self
+        }
+    }
+
+    /// Don't compress the requests of the call, even if the client does.
+    ///
+    /// See [`Request::disable_compression`](crate::Request::disable_compression).
+    #[must_use]
+    pub fn send_uncompressed(self) -> Self {
+        CallOptions {
+            compression: Some(None),
+            ..self
+        }
+    }
+
+    /// Override the `:authority` of the call, which is otherwise that of the
+    /// endpoint it is sent to.
+    ///
+    /// The call is still sent on a connection to the endpoint.
+    #[must_use]
+    pub fn authority(self, authority: Authority) -> Self {
+        CallOptions {
+            authority: Some(authority),
+            ..self
+        }
+    }
+
+    /// Get a reference to the metadata added to the request of the call.
+    pub fn metadata(&self) -> &MetadataMap {
+        &self.metadata
+    }
+
+    /// Get a mutable reference to the metadata added to the request of the
+    /// call, replacing the entries of the request with the same keys.
+    pub fn metadata_mut(&mut self) -> &mut MetadataMap {
+        &mut self.metadata
+    }
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{body::BoxBody, client::Grpc, codec::ProstCodec, IntoRequest, Request, Response};
+    use http::uri::PathAndQuery;
+    use std::convert::Infallible;
+
+    /// A server answering with the authority and the headers of requests.
+    fn echo() -> impl crate::client::GrpcService<
+        BoxBody,
+        ResponseBody = BoxBody,
+        Error = Infallible,
+        Future = impl Send,
+    > + Clone {
+        tower::service_fn(|request: http::Request<BoxBody>| async move {
+            let mut answer = vec![request.uri().authority().unwrap().to_string()];
+            for name in ["x-request-id", "grpc-encoding"] {
+                if let Some(value) = request.headers().get(name) {
+                    answer.push(format!("{}: {}", name, value.to_str().unwrap()));
+                }
+            }
+            let grpc = crate::server::Grpc::new(ProstCodec::<String, String>::default());
+            #[cfg(feature = "gzip")]
+            let grpc = grpc.accept_compressed(CompressionEncoding::Gzip);
+            let mut grpc = grpc;
+            let echo = tower::service_fn(move |_: Request<String>| {
+                let answer = answer.join(", ");
+                async move { Ok(Response::new(answer)) }
+            });
+            Ok(grpc.unary(echo, request).await)
+        })
+    }
+
+    #[test]
+    fn applies_to_requests() {
+        let mut options = CallOptions::new()
+            .timeout(Duration::from_millis(100))
+            .wait_for_ready(true);
+        options
+            .metadata_mut()
+            .insert("x-request-id", "42".parse().unwrap());
+
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("x-request-id", "1".parse().unwrap());
+        request.set_options(options);
+
+        assert!(request.wait_for_ready());
+        assert_eq!(request.metadata().get("grpc-timeout").unwrap(), "100000u");
+        assert_eq!(
+            request
+                .metadata()
+                .get_all("x-request-id")
+                .iter()
+                .collect::<Vec<_>>(),
+            ["42"]
+        );
+    }
+
+    #[tokio::test]
+    async fn overrides_the_client() {
+        let origin = "http://greeter.example".parse().unwrap();
+        let client = Grpc::with_origin(echo(), origin);
+        #[cfg(feature = "gzip")]
+        let client = client.send_compressed(CompressionEncoding::Gzip);
+        let mut client = client;
+        let path = PathAndQuery::from_static("/test.Echo/Call");
+
+        let mut options = CallOptions::new()
+            .authority("greeter.internal".parse().unwrap())
+            .send_uncompressed();
+        options
+            .metadata_mut()
+            .insert("x-request-id", "42".parse().unwrap());
+        let request: Request<String> = (String::new(), options).into_request();
+        let response = client
+            .unary(
+                request,
+                path.clone(),
+                ProstCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.get_ref(), "greeter.internal, x-request-id: 42");
+
+        let response = client
+            .unary(
+                Request::new(String::new()),
+                path,
+                ProstCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap();
+        #[cfg(feature = "gzip")]
+        assert_eq!(response.get_ref(), "greeter.example, grpc-encoding: gzip");
+        #[cfg(not(feature = "gzip"))]
+        assert_eq!(response.get_ref(), "greeter.example");
+    }
+}
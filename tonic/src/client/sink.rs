@@ -0,0 +1,134 @@
+//! Requests fed from sinks.
+//!
+//! Generated clients built with `client_sinks` have a `{method}_sink` method for each
+//! client-streaming and bidirectional method, returning a [`RequestSink`] the messages of the
+//! request are sent to, and the future of the response:
+//!
+//! ```ignore
+//! use futures_util::SinkExt;
+//!
+//! let (mut requests, response) = client.record_route_sink();
+//! let response = tokio::spawn(response);
+//! for point in points {
+//!     requests.send(point).await?;
+//! }
+//! // The request ends once the sink is closed or dropped.
+//! requests.close().await?;
+//! let summary = response.await??;
+//! ```
+//!
+//! The future of the response has to be polled for the messages to be sent.
+
+use futures_channel::mpsc;
+use futures_sink::Sink;
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio_stream::Stream;
+
+/// Create a sink with room for `buffer` messages besides one per clone of it, and the stream
+/// of the messages sent to it, the request of a call.
+pub fn channel<T>(buffer: usize) -> (RequestSink<T>, Requests<T>) {
+    let (tx, rx) = mpsc::channel(buffer);
+    (RequestSink { inner: tx }, Requests { inner: rx })
+}
+
+/// A sink of the messages of a streaming request, which ends once all its clones are closed or
+/// dropped.
+#[derive(Debug, Clone)]
+pub struct RequestSink<T> {
+    inner: mpsc::Sender<T>,
+}
+
+impl<T> Sink<T> for RequestSink<T> {
+    type Error = SendError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        Pin::new(&mut self.inner)
+            .poll_ready(cx)
+            .map_err(|_| SendError(()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), SendError> {
+        Pin::new(&mut self.inner)
+            .start_send(item)
+            .map_err(|_| SendError(()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        Pin::new(&mut self.inner)
+            .poll_flush(cx)
+            .map_err(|_| SendError(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        Pin::new(&mut self.inner)
+            .poll_close(cx)
+            .map_err(|_| SendError(()))
+    }
+}
+
+/// The messages sent to a [`RequestSink`].
+#[derive(Debug)]
+pub struct Requests<T> {
+    inner: mpsc::Receiver<T>,
+}
+
+impl<T> Stream for Requests<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// The error of sending to a [`RequestSink`] whose call ended, the status of which is that of
+/// its response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendError(());
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the call of the request ended")
+    }
+}
+
+impl std::error::Error for SendError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use tokio_stream::StreamExt;
+
+    async fn send<T>(sink: &mut RequestSink<T>, item: T) -> Result<(), SendError> {
+        poll_fn(|cx| Pin::new(&mut *sink).poll_ready(cx)).await?;
+        Pin::new(sink).start_send(item)
+    }
+
+    #[tokio::test]
+    async fn ends_with_the_sinks() {
+        let (mut sink, requests) = channel(1);
+        let mut clone = sink.clone();
+        send(&mut sink, 1).await.unwrap();
+        send(&mut clone, 2).await.unwrap();
+        drop(sink);
+        drop(clone);
+
+        assert_eq!(requests.collect::<Vec<_>>().await, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn fails_once_the_call_ended() {
+        let (mut sink, requests) = channel(1);
+        drop(requests);
+
+        assert_eq!(send(&mut sink, 1).await, Err(SendError(())));
+    }
+}
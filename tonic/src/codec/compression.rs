@@ -2,7 +2,10 @@ use crate::{metadata::MetadataValue, Status};
 use bytes::{Buf, BytesMut};
 #[cfg(feature = "gzip")]
 use flate2::read::{GzDecoder, GzEncoder};
-use std::fmt;
+use std::{
+    fmt, io,
+    sync::{PoisonError, RwLock},
+};
 #[cfg(feature = "zstd")]
 use zstd::stream::read::{Decoder, Encoder};
 
@@ -16,6 +19,8 @@ pub struct EnabledCompressionEncodings {
     pub(crate) gzip: bool,
     #[cfg(feature = "zstd")]
     pub(crate) zstd: bool,
+    // The indices of the enabled custom encodings.
+    pub(crate) custom: u32,
 }
 
 impl EnabledCompressionEncodings {
@@ -26,6 +31,7 @@ impl EnabledCompressionEncodings {
             CompressionEncoding::Gzip => self.gzip,
             #[cfg(feature = "zstd")]
             CompressionEncoding::Zstd => self.zstd,
+            CompressionEncoding::Custom(custom) => self.custom & (1 << custom.0) != 0,
         }
     }
 
@@ -36,36 +42,166 @@ impl EnabledCompressionEncodings {
             CompressionEncoding::Gzip => self.gzip = true,
             #[cfg(feature = "zstd")]
             CompressionEncoding::Zstd => self.zstd = true,
+            CompressionEncoding::Custom(custom) => self.custom |= 1 << custom.0,
         }
     }
 
+    /// The enabled encodings, built-in ones first.
+    pub(crate) fn iter(self) -> impl Iterator<Item = CompressionEncoding> {
+        let builtin = [
+            #[cfg(feature = "gzip")]
+            CompressionEncoding::Gzip,
+            #[cfg(feature = "zstd")]
+            CompressionEncoding::Zstd,
+        ];
+        let custom = (0..MAX_CUSTOM_ENCODINGS as u8)
+            .map(|index| CompressionEncoding::Custom(CustomEncoding(index)));
+        builtin
+            .into_iter()
+            .chain(custom)
+            .filter(move |&encoding| self.is_enabled(encoding))
+    }
+
     pub(crate) fn into_accept_encoding_header_value(self) -> Option<http::HeaderValue> {
-        match (self.is_gzip_enabled(), self.is_zstd_enabled()) {
-            (true, false) => Some(http::HeaderValue::from_static("gzip,identity")),
-            (false, true) => Some(http::HeaderValue::from_static("zstd,identity")),
-            (true, true) => Some(http::HeaderValue::from_static("gzip,zstd,identity")),
-            (false, false) => None,
+        let mut value = String::new();
+        for encoding in self.iter() {
+            value.push_str(encoding.as_str());
+            value.push(',');
         }
+        if value.is_empty() {
+            return None;
+        }
+        value.push_str("identity");
+        Some(http::HeaderValue::from_str(&value).expect("encodings are valid header values"))
     }
+}
 
-    #[cfg(feature = "gzip")]
-    const fn is_gzip_enabled(&self) -> bool {
-        self.gzip
+/// A compression algorithm, to compress messages with once registered with
+/// [`CompressionEncoding::register`].
+///
+/// ```
+/// use std::io::{self, Write};
+/// use tonic::codec::{CompressionEncoding, Compressor};
+///
+/// /// Stores messages as they are, under another name.
+/// struct Stored;
+///
+/// impl Compressor for Stored {
+///     fn name(&self) -> &'static str {
+///         "x-stored"
+///     }
+///
+///     fn compress(&self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+///         output.write_all(input)
+///     }
+///
+///     fn decompress(&self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+///         output.write_all(input)
+///     }
+/// }
+///
+/// let stored = CompressionEncoding::register(&Stored);
+/// assert_eq!(stored.to_string(), "x-stored");
+/// ```
+///
+/// The returned encoding is used like the built-in ones, with the
+/// `send_compressed` and `accept_compressed` methods of clients and servers,
+/// which negotiate it with peers by its name.
+pub trait Compressor: Send + Sync + 'static {
+    /// The `grpc-encoding` name of the algorithm, such as `snappy`.
+    fn name(&self) -> &'static str;
+
+    /// Compress all of `input` into `output`.
+    fn compress(&self, input: &[u8], output: &mut dyn io::Write) -> io::Result<()>;
+
+    /// Decompress all of `input` into `output`.
+    fn decompress(&self, input: &[u8], output: &mut dyn io::Write) -> io::Result<()>;
+}
+
+/// How many custom encodings can be registered.
+const MAX_CUSTOM_ENCODINGS: usize = 32;
+
+static CUSTOM_ENCODINGS: RwLock<Vec<&'static dyn Compressor>> = RwLock::new(Vec::new());
+
+/// A compression encoding registered with [`CompressionEncoding::register`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CustomEncoding(u8);
+
+impl CustomEncoding {
+    fn compressor(self) -> &'static dyn Compressor {
+        let custom = CUSTOM_ENCODINGS
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        custom[usize::from(self.0)]
     }
+}
 
-    #[cfg(not(feature = "gzip"))]
-    const fn is_gzip_enabled(&self) -> bool {
-        false
+impl fmt::Debug for CustomEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomEncoding")
+            .field(&self.compressor().name())
+            .finish()
     }
+}
 
-    #[cfg(feature = "zstd")]
-    const fn is_zstd_enabled(&self) -> bool {
-        self.zstd
+/// How the messages sent by a client or a server are compressed, whatever
+/// their encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct CompressionConfig {
+    /// The size below which messages are sent uncompressed.
+    pub(crate) min_size: usize,
+    /// How hard messages are compressed.
+    pub(crate) level: CompressionLevel,
+}
+
+/// How hard messages are compressed, trading speed for size.
+///
+/// ```
+/// use tonic::codec::CompressionLevel;
+///
+/// // Cheap compression for a fast internal link.
+/// let level = CompressionLevel::Fastest;
+/// // Or a level of the encoding, here the strongest of gzip.
+/// let level = CompressionLevel::Precise(9);
+/// ```
+///
+/// Custom encodings registered with [`CompressionEncoding::register`] choose
+/// their own level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompressionLevel {
+    /// The default level of the encoding, `6` for gzip and `3` for zstd.
+    #[default]
+    Default,
+    /// The fastest level of the encoding.
+    Fastest,
+    /// The level of the encoding compressing the most.
+    Best,
+    /// A level of the encoding, from `0` to `9` for gzip and from `1` to
+    /// `22` for zstd, clamped to the levels the encoding supports.
+    Precise(i32),
+}
+
+impl CompressionLevel {
+    #[cfg(feature = "gzip")]
+    fn gzip(self) -> flate2::Compression {
+        match self {
+            CompressionLevel::Default => flate2::Compression::new(6),
+            CompressionLevel::Fastest => flate2::Compression::fast(),
+            CompressionLevel::Best => flate2::Compression::best(),
+            CompressionLevel::Precise(level) => flate2::Compression::new(level.clamp(0, 9) as u32),
+        }
     }
 
-    #[cfg(not(feature = "zstd"))]
-    const fn is_zstd_enabled(&self) -> bool {
-        false
+    #[cfg(feature = "zstd")]
+    fn zstd(self) -> i32 {
+        let levels = zstd::compression_level_range();
+        match self {
+            CompressionLevel::Default => zstd::DEFAULT_COMPRESSION_LEVEL,
+            CompressionLevel::Fastest => 1,
+            CompressionLevel::Best => *levels.end(),
+            CompressionLevel::Precise(level) => level.clamp(*levels.start(), *levels.end()),
+        }
     }
 }
 
@@ -78,6 +214,8 @@ pub(crate) struct CompressionSettings {
 }
 
 /// The compression encodings Tonic supports.
+///
+/// Other algorithms can be plugged in with [`CompressionEncoding::register`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum CompressionEncoding {
@@ -89,27 +227,67 @@ pub enum CompressionEncoding {
     #[cfg(feature = "zstd")]
     #[cfg_attr(docsrs, doc(cfg(feature = "zstd")))]
     Zstd,
+    /// An encoding registered with [`CompressionEncoding::register`].
+    Custom(CustomEncoding),
 }
 
 impl CompressionEncoding {
+    /// Register a custom compression algorithm, returning its encoding.
+    ///
+    /// Registering another algorithm with the same name replaces it, under
+    /// the same encoding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name of the algorithm is `identity` or that of a
+    /// built-in encoding, if it isn't a valid header value, or if 32
+    /// algorithms are already registered.
+    pub fn register(compressor: &'static dyn Compressor) -> Self {
+        let name = compressor.name();
+        assert!(
+            !matches!(name, "identity" | "gzip" | "zstd")
+                && !name.is_empty()
+                && !name.contains(',')
+                && http::HeaderValue::from_str(name).is_ok(),
+            "invalid compression encoding name `{}`",
+            name
+        );
+
+        let mut custom = CUSTOM_ENCODINGS
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let index = match custom.iter().position(|c| c.name() == name) {
+            Some(index) => {
+                custom[index] = compressor;
+                index
+            }
+            None => {
+                assert!(
+                    custom.len() < MAX_CUSTOM_ENCODINGS,
+                    "at most {} compression encodings can be registered",
+                    MAX_CUSTOM_ENCODINGS
+                );
+                custom.push(compressor);
+                custom.len() - 1
+            }
+        };
+        CompressionEncoding::Custom(CustomEncoding(index as u8))
+    }
+
     /// Based on the `grpc-accept-encoding` header, pick an encoding to use.
     pub(crate) fn from_accept_encoding_header(
         map: &http::HeaderMap,
         enabled_encodings: EnabledCompressionEncodings,
     ) -> Option<Self> {
-        if !enabled_encodings.is_gzip_enabled() && !enabled_encodings.is_zstd_enabled() {
-            return None;
-        }
+        enabled_encodings.iter().next()?;
 
         let header_value = map.get(ACCEPT_ENCODING_HEADER)?;
         let header_value_str = header_value.to_str().ok()?;
 
-        split_by_comma(header_value_str).find_map(|value| match value {
-            #[cfg(feature = "gzip")]
-            "gzip" => Some(CompressionEncoding::Gzip),
-            #[cfg(feature = "zstd")]
-            "zstd" => Some(CompressionEncoding::Zstd),
-            _ => None,
+        split_by_comma(header_value_str).find_map(|value| {
+            enabled_encodings
+                .iter()
+                .find(|encoding| encoding.as_str() == value)
         })
     }
 
@@ -130,20 +308,19 @@ impl CompressionEncoding {
             return Ok(None);
         };
 
-        match header_value_str {
-            #[cfg(feature = "gzip")]
-            "gzip" if enabled_encodings.is_enabled(CompressionEncoding::Gzip) => {
-                Ok(Some(CompressionEncoding::Gzip))
-            }
-            #[cfg(feature = "zstd")]
-            "zstd" if enabled_encodings.is_enabled(CompressionEncoding::Zstd) => {
-                Ok(Some(CompressionEncoding::Zstd))
-            }
-            "identity" => Ok(None),
-            other => {
+        if header_value_str == "identity" {
+            return Ok(None);
+        }
+
+        match enabled_encodings
+            .iter()
+            .find(|encoding| encoding.as_str() == header_value_str)
+        {
+            Some(encoding) => Ok(Some(encoding)),
+            None => {
                 let mut status = Status::unimplemented(format!(
                     "Content is compressed with `{}` which isn't supported",
-                    other
+                    header_value_str
                 ));
 
                 let header_value = enabled_encodings
@@ -160,40 +337,24 @@ impl CompressionEncoding {
     }
 
     #[allow(missing_docs)]
-    #[cfg(any(feature = "gzip", feature = "zstd"))]
     pub(crate) fn as_str(&self) -> &'static str {
         match self {
             #[cfg(feature = "gzip")]
             CompressionEncoding::Gzip => "gzip",
             #[cfg(feature = "zstd")]
             CompressionEncoding::Zstd => "zstd",
+            CompressionEncoding::Custom(custom) => custom.compressor().name(),
         }
     }
 
-    #[cfg(any(feature = "gzip", feature = "zstd"))]
     pub(crate) fn into_header_value(self) -> http::HeaderValue {
         http::HeaderValue::from_static(self.as_str())
     }
-
-    pub(crate) fn encodings() -> &'static [Self] {
-        &[
-            #[cfg(feature = "gzip")]
-            CompressionEncoding::Gzip,
-            #[cfg(feature = "zstd")]
-            CompressionEncoding::Zstd,
-        ]
-    }
 }
 
 impl fmt::Display for CompressionEncoding {
-    #[allow(unused_variables)]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            #[cfg(feature = "gzip")]
-            CompressionEncoding::Gzip => write!(f, "gzip"),
-            #[cfg(feature = "zstd")]
-            CompressionEncoding::Zstd => write!(f, "zstd"),
-        }
+        f.write_str(self.as_str())
     }
 }
 
@@ -203,9 +364,9 @@ fn split_by_comma(s: &str) -> impl Iterator<Item = &str> {
 
 /// Compress `len` bytes from `decompressed_buf` into `out_buf`.
 /// buffer_size_increment is a hint to control the growth of out_buf versus the cost of resizing it.
-#[allow(unused_variables, unreachable_code)]
 pub(crate) fn compress(
     settings: CompressionSettings,
+    level: CompressionLevel,
     decompressed_buf: &mut BytesMut,
     out_buf: &mut BytesMut,
     len: usize,
@@ -214,28 +375,26 @@ pub(crate) fn compress(
     let capacity = ((len / buffer_growth_interval) + 1) * buffer_growth_interval;
     out_buf.reserve(capacity);
 
-    #[cfg(any(feature = "gzip", feature = "zstd"))]
     let mut out_writer = bytes::BufMut::writer(out_buf);
 
     match settings.encoding {
         #[cfg(feature = "gzip")]
         CompressionEncoding::Gzip => {
-            let mut gzip_encoder = GzEncoder::new(
-                &decompressed_buf[0..len],
-                // FIXME: support customizing the compression level
-                flate2::Compression::new(6),
-            );
+            let mut gzip_encoder = GzEncoder::new(&decompressed_buf[0..len], level.gzip());
             std::io::copy(&mut gzip_encoder, &mut out_writer)?;
         }
         #[cfg(feature = "zstd")]
         CompressionEncoding::Zstd => {
-            let mut zstd_encoder = Encoder::new(
-                &decompressed_buf[0..len],
-                // FIXME: support customizing the compression level
-                zstd::DEFAULT_COMPRESSION_LEVEL,
-            )?;
+            let mut zstd_encoder = Encoder::new(&decompressed_buf[0..len], level.zstd())?;
             std::io::copy(&mut zstd_encoder, &mut out_writer)?;
         }
+        CompressionEncoding::Custom(custom) => {
+            // Custom compressors choose their own level.
+            let _ = level;
+            custom
+                .compressor()
+                .compress(&decompressed_buf[0..len], &mut out_writer)?;
+        }
     }
 
     decompressed_buf.advance(len);
@@ -244,21 +403,27 @@ pub(crate) fn compress(
 }
 
 /// Decompress `len` bytes from `compressed_buf` into `out_buf`.
-#[allow(unused_variables, unreachable_code)]
+///
+/// At most `max_size + 1` bytes are written to `out_buf`, so that a small
+/// message can't expand into an unbounded allocation. Decompression fails if
+/// there is more to write.
 pub(crate) fn decompress(
     settings: CompressionSettings,
     compressed_buf: &mut BytesMut,
     out_buf: &mut BytesMut,
     len: usize,
+    max_size: usize,
 ) -> Result<(), std::io::Error> {
     let buffer_growth_interval = settings.buffer_growth_interval;
-    let estimate_decompressed_len = len * 2;
+    let estimate_decompressed_len = (len * 2).min(max_size);
     let capacity =
         ((estimate_decompressed_len / buffer_growth_interval) + 1) * buffer_growth_interval;
     out_buf.reserve(capacity);
 
-    #[cfg(any(feature = "gzip", feature = "zstd"))]
-    let mut out_writer = bytes::BufMut::writer(out_buf);
+    let mut out_writer = LimitedWriter {
+        inner: bytes::BufMut::writer(out_buf),
+        remaining: max_size.saturating_add(1),
+    };
 
     match settings.encoding {
         #[cfg(feature = "gzip")]
@@ -271,6 +436,11 @@ pub(crate) fn decompress(
             let mut zstd_decoder = Decoder::new(&compressed_buf[0..len])?;
             std::io::copy(&mut zstd_decoder, &mut out_writer)?;
         }
+        CompressionEncoding::Custom(custom) => {
+            custom
+                .compressor()
+                .decompress(&compressed_buf[0..len], &mut out_writer)?;
+        }
     }
 
     compressed_buf.advance(len);
@@ -278,6 +448,31 @@ pub(crate) fn decompress(
     Ok(())
 }
 
+/// A writer failing once `remaining` bytes are written.
+struct LimitedWriter<W> {
+    inner: W,
+    remaining: usize,
+}
+
+impl<W: io::Write> io::Write for LimitedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.remaining == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "decompressed message too large",
+            ));
+        }
+        let len = buf.len().min(self.remaining);
+        let written = self.inner.write(&buf[..len])?;
+        self.remaining -= written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum SingleMessageCompressionOverride {
     /// Inherit whatever compression is already configured. If the stream is compressed this
@@ -294,3 +489,117 @@ impl Default for SingleMessageCompressionOverride {
         Self::Inherit
     }
 }
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{body::BoxBody, client::Grpc, codec::ProstCodec, Code, Request, Response};
+    use http::uri::PathAndQuery;
+    use std::convert::Infallible;
+
+    /// Flips the bits of messages.
+    struct Flip;
+
+    impl Compressor for Flip {
+        fn name(&self) -> &'static str {
+            "x-flip"
+        }
+
+        fn compress(&self, input: &[u8], output: &mut dyn io::Write) -> io::Result<()> {
+            output.write_all(&input.iter().map(|b| !b).collect::<Vec<_>>())
+        }
+
+        fn decompress(&self, input: &[u8], output: &mut dyn io::Write) -> io::Result<()> {
+            self.compress(input, output)
+        }
+    }
+
+    /// A server of `encoding`, answering with the encodings of requests.
+    fn echo(
+        encoding: CompressionEncoding,
+    ) -> impl crate::client::GrpcService<
+        BoxBody,
+        ResponseBody = BoxBody,
+        Error = Infallible,
+        Future = impl Send,
+    > + Clone {
+        tower::service_fn(move |request: http::Request<BoxBody>| async move {
+            let headers = [ENCODING_HEADER, ACCEPT_ENCODING_HEADER].map(|name| {
+                let value = request.headers().get(name);
+                value.map_or("", |v| v.to_str().unwrap()).to_owned()
+            });
+            let mut grpc = crate::server::Grpc::new(ProstCodec::<String, String>::default())
+                .accept_compressed(encoding)
+                .send_compressed(encoding);
+            let echo = tower::service_fn(move |request: Request<String>| {
+                let answer = format!("{} {}", request.get_ref(), headers.join(" "));
+                async move { Ok(Response::new(answer)) }
+            });
+            Ok(grpc.unary(echo, request).await)
+        })
+    }
+
+    #[tokio::test]
+    async fn negotiates_custom_encodings() {
+        let flip = CompressionEncoding::register(&Flip);
+        assert_eq!(CompressionEncoding::register(&Flip), flip);
+        assert_eq!(format!("{:?}", flip), "Custom(CustomEncoding(\"x-flip\"))");
+
+        let mut client = Grpc::new(echo(flip))
+            .send_compressed(flip)
+            .accept_compressed(flip);
+        let path = PathAndQuery::from_static("/test.Echo/Call");
+        let response = client
+            .unary(
+                Request::new("hello".to_owned()),
+                path.clone(),
+                ProstCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.metadata().get(ENCODING_HEADER).unwrap(), "x-flip");
+        assert_eq!(response.get_ref(), "hello x-flip x-flip,identity");
+
+        let response = Grpc::new(echo(flip))
+            .unary(
+                Request::new("hello".to_owned()),
+                path,
+                ProstCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.get_ref(), "hello  ");
+
+        let mut unsupported = http::HeaderMap::new();
+        unsupported.insert(ENCODING_HEADER, "x-flip".parse().unwrap());
+        let status = CompressionEncoding::from_encoding_header(
+            &unsupported,
+            EnabledCompressionEncodings::default(),
+        )
+        .unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compresses_at_levels() {
+        let message = "hello ".repeat(1000);
+        let size = |level| {
+            let mut input = BytesMut::from(message.as_bytes());
+            let mut output = BytesMut::new();
+            let settings = CompressionSettings {
+                encoding: CompressionEncoding::Gzip,
+                buffer_growth_interval: 8 * 1024,
+            };
+            compress(settings, level, &mut input, &mut output, message.len()).unwrap();
+            output.len()
+        };
+
+        assert!(size(CompressionLevel::Precise(0)) > message.len());
+        assert!(size(CompressionLevel::Best) < size(CompressionLevel::Precise(0)));
+        assert_eq!(
+            size(CompressionLevel::Precise(100)),
+            size(CompressionLevel::Best)
+        );
+    }
+}
@@ -1,6 +1,11 @@
 use super::compression::{decompress, CompressionEncoding, CompressionSettings};
 use super::{BufferSettings, DecodeBuf, Decoder, DEFAULT_MAX_RECV_MESSAGE_SIZE, HEADER_SIZE};
-use crate::{body::BoxBody, metadata::MetadataMap, Code, Status};
+use crate::{
+    body::BoxBody,
+    metadata::MetadataMap,
+    stats::{CallStats, Payload},
+    Code, Status,
+};
 use bytes::{Buf, BufMut, BytesMut};
 use http::StatusCode;
 use http_body::Body;
@@ -31,6 +36,7 @@ struct StreamingInner {
     decompress_buf: BytesMut,
     encoding: Option<CompressionEncoding>,
     max_message_size: Option<usize>,
+    stats: Option<CallStats>,
 }
 
 impl<T> Unpin for Streaming<T> {}
@@ -131,9 +137,17 @@ impl<T> Streaming<T> {
                 decompress_buf: BytesMut::new(),
                 encoding,
                 max_message_size,
+                stats: None,
             },
         }
     }
+
+    /// Report the messages received, and the trailers of responses, to the
+    /// recorder of `stats`.
+    pub(crate) fn with_stats(mut self, stats: Option<CallStats>) -> Self {
+        self.inner.stats = stats;
+        self
+    }
 }
 
 impl StreamingInner {
@@ -207,7 +221,10 @@ impl StreamingInner {
             let decode_buf = if let Some(encoding) = compression {
                 self.decompress_buf.clear();
 
-                if let Err(err) = decompress(
+                let limit = self
+                    .max_message_size
+                    .unwrap_or(DEFAULT_MAX_RECV_MESSAGE_SIZE);
+                let decompressed = decompress(
                     CompressionSettings {
                         encoding,
                         buffer_growth_interval: buffer_settings.buffer_size,
@@ -215,7 +232,19 @@ impl StreamingInner {
                     &mut self.buf,
                     &mut self.decompress_buf,
                     len,
-                ) {
+                    limit,
+                );
+                if self.decompress_buf.len() > limit {
+                    self.decompress_buf.clear();
+                    return Err(Status::new(
+                        Code::ResourceExhausted,
+                        format!(
+                            "Error, decompressed message length too large: the limit is: {} bytes",
+                            limit
+                        ),
+                    ));
+                }
+                if let Err(err) = decompressed {
                     let message = if let Direction::Response(status) = self.direction {
                         format!(
                             "Error decompressing: {}, while receiving response with status: {}",
@@ -227,8 +256,10 @@ impl StreamingInner {
                     return Err(Status::new(Code::Internal, message));
                 }
                 let decompressed_len = self.decompress_buf.len();
+                self.record_payload(len, decompressed_len, true);
                 DecodeBuf::new(&mut self.decompress_buf, decompressed_len)
             } else {
+                self.record_payload(len, len, false);
                 DecodeBuf::new(&mut self.buf, len)
             };
 
@@ -238,6 +269,28 @@ impl StreamingInner {
         Ok(None)
     }
 
+    fn record_payload(&self, len: usize, uncompressed_size: usize, compressed: bool) {
+        if let Some(recorder) = self.recorder() {
+            recorder.in_payload(Payload {
+                wire_size: HEADER_SIZE + len,
+                uncompressed_size,
+                compressed,
+            });
+        }
+    }
+
+    /// The recorder of a response, which reports its end.
+    fn response_recorder(&self) -> Option<&crate::stats::Recorder> {
+        match self.direction {
+            Direction::Request => None,
+            _ => self.recorder(),
+        }
+    }
+
+    fn recorder(&self) -> Option<&crate::stats::Recorder> {
+        self.stats.as_ref().and_then(CallStats::get)
+    }
+
     // Returns Some(()) if data was found or None if the loop in `poll_next` should break
     fn poll_data(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<()>, Status>> {
         let chunk = match ready!(Pin::new(&mut self.body).poll_data(cx)) {
@@ -247,6 +300,9 @@ impl StreamingInner {
                     return Poll::Ready(Ok(None));
                 }
 
+                if let Some(recorder) = self.response_recorder() {
+                    recorder.end(status.code());
+                }
                 let _ = std::mem::replace(&mut self.state, State::Error);
                 debug!("decoder inner stream error: {:?}", status);
                 return Poll::Ready(Err(status));
@@ -275,7 +331,17 @@ impl StreamingInner {
         if let Direction::Response(status) = self.direction {
             match ready!(Pin::new(&mut self.body).poll_trailers(cx)) {
                 Ok(trailer) => {
-                    if let Err(e) = crate::status::infer_grpc_status(trailer.as_ref(), status) {
+                    let inferred = crate::status::infer_grpc_status(trailer.as_ref(), status);
+                    if let Some(recorder) = self.response_recorder() {
+                        if let Some(trailer) = &trailer {
+                            recorder.in_trailers(trailer);
+                        }
+                        recorder.end(match &inferred {
+                            Err(Some(status)) => status.code(),
+                            _ => Code::Ok,
+                        });
+                    }
+                    if let Err(e) = inferred {
                         if let Some(e) = e {
                             return Poll::Ready(Err(e));
                         } else {
@@ -286,6 +352,9 @@ impl StreamingInner {
                     }
                 }
                 Err(status) => {
+                    if let Some(recorder) = self.response_recorder() {
+                        recorder.end(status.code());
+                    }
                     debug!("decoder inner trailers error: {:?}", status);
                     return Poll::Ready(Err(status));
                 }
@@ -1,8 +1,12 @@
 use super::compression::{
-    compress, CompressionEncoding, CompressionSettings, SingleMessageCompressionOverride,
+    compress, CompressionConfig, CompressionEncoding, CompressionSettings,
+    SingleMessageCompressionOverride,
 };
 use super::{BufferSettings, EncodeBuf, Encoder, DEFAULT_MAX_SEND_MESSAGE_SIZE, HEADER_SIZE};
-use crate::{Code, Status};
+use crate::{
+    stats::{CallStats, Payload},
+    Code, Status,
+};
 use bytes::{BufMut, Bytes, BytesMut};
 use http::HeaderMap;
 use http_body::Body;
@@ -18,7 +22,9 @@ pub(crate) fn encode_server<T, U>(
     source: U,
     compression_encoding: Option<CompressionEncoding>,
     compression_override: SingleMessageCompressionOverride,
+    compression_config: CompressionConfig,
     max_message_size: Option<usize>,
+    stats: Option<CallStats>,
 ) -> EncodeBody<impl Stream<Item = Result<Bytes, Status>>>
 where
     T: Encoder<Error = Status>,
@@ -29,7 +35,9 @@ where
         source.fuse(),
         compression_encoding,
         compression_override,
+        compression_config,
         max_message_size,
+        stats,
     );
 
     EncodeBody::new_server(stream)
@@ -39,7 +47,9 @@ pub(crate) fn encode_client<T, U>(
     encoder: T,
     source: U,
     compression_encoding: Option<CompressionEncoding>,
+    compression_config: CompressionConfig,
     max_message_size: Option<usize>,
+    stats: Option<CallStats>,
 ) -> EncodeBody<impl Stream<Item = Result<Bytes, Status>>>
 where
     T: Encoder<Error = Status>,
@@ -50,7 +60,9 @@ where
         source.fuse().map(Ok),
         compression_encoding,
         SingleMessageCompressionOverride::default(),
+        compression_config,
         max_message_size,
+        stats,
     );
     EncodeBody::new_client(stream)
 }
@@ -71,9 +83,13 @@ where
     source: U,
     encoder: T,
     compression_encoding: Option<CompressionEncoding>,
+    compression_config: CompressionConfig,
     max_message_size: Option<usize>,
     buf: BytesMut,
     uncompression_buf: BytesMut,
+    /// The error to yield once the messages encoded before it are.
+    error: Option<Status>,
+    stats: Option<CallStats>,
 }
 
 impl<T, U> EncodedBytes<T, U>
@@ -87,7 +103,9 @@ where
         source: U,
         compression_encoding: Option<CompressionEncoding>,
         compression_override: SingleMessageCompressionOverride,
+        compression_config: CompressionConfig,
         max_message_size: Option<usize>,
+        stats: Option<CallStats>,
     ) -> Self {
         let buffer_settings = encoder.buffer_settings();
         let buf = BytesMut::with_capacity(buffer_settings.buffer_size);
@@ -109,9 +127,12 @@ where
             source,
             encoder,
             compression_encoding,
+            compression_config,
             max_message_size,
             buf,
             uncompression_buf,
+            error: None,
+            stats,
         }
     }
 }
@@ -128,12 +149,19 @@ where
             mut source,
             encoder,
             compression_encoding,
+            compression_config,
             max_message_size,
             buf,
             uncompression_buf,
+            error,
+            stats,
         } = self.project();
         let buffer_settings = encoder.buffer_settings();
 
+        if let Some(status) = error.take() {
+            return Poll::Ready(Some(Err(status)));
+        }
+
         loop {
             match source.as_mut().poll_next(cx) {
                 Poll::Pending if buf.is_empty() => {
@@ -146,16 +174,30 @@ where
                     return Poll::Ready(Some(Ok(buf.split_to(buf.len()).freeze())));
                 }
                 Poll::Ready(Some(Ok(item))) => {
-                    if let Err(status) = encode_item(
+                    let encoded = buf.len();
+                    let (uncompressed_size, compressed) = match encode_item(
                         encoder,
                         buf,
                         uncompression_buf,
                         *compression_encoding,
+                        *compression_config,
                         *max_message_size,
                         buffer_settings,
                         item,
                     ) {
-                        return Poll::Ready(Some(Err(status)));
+                        Ok(encoded) => encoded,
+                        Err(status) => {
+                            // Drop what was written of the item that failed.
+                            buf.truncate(encoded);
+                            return flush_before(buf, error, status);
+                        }
+                    };
+                    if let Some(recorder) = stats.as_ref().and_then(CallStats::get) {
+                        recorder.out_payload(Payload {
+                            wire_size: buf.len() - encoded,
+                            uncompressed_size,
+                            compressed,
+                        });
                     }
 
                     if buf.len() >= buffer_settings.yield_threshold {
@@ -163,22 +205,40 @@ where
                     }
                 }
                 Poll::Ready(Some(Err(status))) => {
-                    return Poll::Ready(Some(Err(status)));
+                    return flush_before(buf, error, status);
                 }
             }
         }
     }
 }
 
+/// Yield `status`, after the messages already encoded in `buf` if any,
+/// storing it in `error` until then.
+fn flush_before(
+    buf: &mut BytesMut,
+    error: &mut Option<Status>,
+    status: Status,
+) -> Poll<Option<Result<Bytes, Status>>> {
+    if buf.is_empty() {
+        return Poll::Ready(Some(Err(status)));
+    }
+    *error = Some(status);
+    Poll::Ready(Some(Ok(buf.split_to(buf.len()).freeze())))
+}
+
+/// Encode `item` at the end of `buf`, returning its size before
+/// compression and whether it is compressed.
+#[allow(clippy::too_many_arguments)]
 fn encode_item<T>(
     encoder: &mut T,
     buf: &mut BytesMut,
     uncompression_buf: &mut BytesMut,
     compression_encoding: Option<CompressionEncoding>,
+    compression_config: CompressionConfig,
     max_message_size: Option<usize>,
     buffer_settings: BufferSettings,
     item: T::Item,
-) -> Result<(), Status>
+) -> Result<(usize, bool), Status>
 where
     T: Encoder<Error = Status>,
 {
@@ -189,7 +249,7 @@ where
         buf.advance_mut(HEADER_SIZE);
     }
 
-    if let Some(encoding) = compression_encoding {
+    let (uncompressed_len, compressed) = if let Some(encoding) = compression_encoding {
         uncompression_buf.clear();
 
         encoder
@@ -198,28 +258,40 @@ where
 
         let uncompressed_len = uncompression_buf.len();
 
+        // Small messages aren't worth compressing, and may even grow.
+        if uncompressed_len < compression_config.min_size {
+            buf.extend_from_slice(uncompression_buf);
+            uncompression_buf.clear();
+            return finish_encoding(false, max_message_size, &mut buf[offset..])
+                .map(|()| (uncompressed_len, false));
+        }
+
         compress(
             CompressionSettings {
                 encoding,
                 buffer_growth_interval: buffer_settings.buffer_size,
             },
+            compression_config.level,
             uncompression_buf,
             buf,
             uncompressed_len,
         )
         .map_err(|err| Status::internal(format!("Error compressing: {}", err)))?;
+        (uncompressed_len, true)
     } else {
         encoder
             .encode(item, &mut EncodeBuf::new(buf))
             .map_err(|err| Status::internal(format!("Error encoding: {}", err)))?;
-    }
+        (buf.len() - offset - HEADER_SIZE, false)
+    };
 
     // now that we know length, we can write the header
-    finish_encoding(compression_encoding, max_message_size, &mut buf[offset..])
+    finish_encoding(compressed, max_message_size, &mut buf[offset..])?;
+    Ok((uncompressed_len, compressed))
 }
 
 fn finish_encoding(
-    compression_encoding: Option<CompressionEncoding>,
+    compressed: bool,
     max_message_size: Option<usize>,
     buf: &mut [u8],
 ) -> Result<(), Status> {
@@ -242,7 +314,7 @@ fn finish_encoding(
     }
     {
         let mut buf = &mut buf[..HEADER_SIZE];
-        buf.put_u8(compression_encoding.is_some() as u8);
+        buf.put_u8(compressed as u8);
         buf.put_u32(len as u32);
     }
 
@@ -0,0 +1,116 @@
+use super::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use crate::Status;
+use bytes::{Buf, BufMut, Bytes};
+use std::marker::PhantomData;
+
+/// A [`Codec`] of [flatbuffers] messages, which are sent as they were built
+/// and received without being parsed or copied.
+///
+/// Messages are encoded from their finished buffers, such as the `Vec<u8>`
+/// of `FlatBufferBuilder::collapse` or a copy of its `finished_data`, and
+/// decoded as the [`Bytes`] of their buffer, or any type built from them.
+/// Reading a received message, with `flatbuffers::root`, verifies it in
+/// place:
+///
+/// ```ignore
+/// let request: tonic::Request<Bytes> = ...;
+/// let hello = flatbuffers::root::<HelloRequest>(request.get_ref())
+///     .map_err(|e| Status::invalid_argument(e.to_string()))?;
+/// ```
+///
+/// The services of `.fbs` schemas are generated with the
+/// `tonic_build::flatbuffers` module, which uses this codec.
+///
+/// [flatbuffers]: https://flatbuffers.dev
+#[derive(Debug, Clone)]
+pub struct FlatbuffersCodec<T = Bytes, U = Bytes> {
+    _pd: PhantomData<(T, U)>,
+}
+
+impl<T, U> FlatbuffersCodec<T, U> {
+    /// Create a flatbuffers codec.
+    pub fn new() -> Self {
+        Self { _pd: PhantomData }
+    }
+}
+
+impl<T, U> Default for FlatbuffersCodec<T, U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, U> Codec for FlatbuffersCodec<T, U>
+where
+    T: AsRef<[u8]> + Send + 'static,
+    U: From<Bytes> + Send + 'static,
+{
+    type Encode = T;
+    type Decode = U;
+
+    type Encoder = FlatbuffersEncoder<T>;
+    type Decoder = FlatbuffersDecoder<U>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        FlatbuffersEncoder { _pd: PhantomData }
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        FlatbuffersDecoder { _pd: PhantomData }
+    }
+}
+
+/// A [`Encoder`] that writes the buffers of flatbuffers messages.
+#[derive(Debug, Clone, Default)]
+pub struct FlatbuffersEncoder<T> {
+    _pd: PhantomData<T>,
+}
+
+impl<T: AsRef<[u8]>> Encoder for FlatbuffersEncoder<T> {
+    type Item = T;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        buf.put_slice(item.as_ref());
+        Ok(())
+    }
+}
+
+/// A [`Decoder`] that splits the buffers of flatbuffers messages off the
+/// received data.
+#[derive(Debug, Clone, Default)]
+pub struct FlatbuffersDecoder<U> {
+    _pd: PhantomData<U>,
+}
+
+impl<U: From<Bytes>> Decoder for FlatbuffersDecoder<U> {
+    type Item = U;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(Some(buf.copy_to_bytes(buf.remaining()).into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Streaming;
+
+    #[tokio::test]
+    async fn decodes_the_buffers_of_messages() {
+        let mut data = Vec::new();
+        for message in ["hello", "world"] {
+            data.put_u8(0);
+            data.put_u32(message.len() as u32);
+            data.put_slice(message.as_bytes());
+        }
+        let body = http_body::Full::new(Bytes::from(data));
+
+        let decoder = FlatbuffersCodec::<Vec<u8>, Bytes>::new().decoder();
+        let mut stream = Streaming::new_request(decoder, crate::body::boxed(body), None, None);
+        assert_eq!(stream.message().await.unwrap().unwrap(), "hello");
+        assert_eq!(stream.message().await.unwrap().unwrap(), "world");
+        assert!(stream.message().await.unwrap().is_none());
+    }
+}
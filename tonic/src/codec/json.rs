@@ -0,0 +1,303 @@
+use super::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use crate::{Code, Status};
+use bytes::{Buf, BufMut};
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+
+const JSON_CONTENT_TYPE: &str = "application/grpc+json";
+
+/// Whether `content_type` is that of JSON calls, ignoring its parameters.
+fn is_json(content_type: &str) -> bool {
+    super::serde::is_content_type(content_type, JSON_CONTENT_TYPE)
+}
+
+/// A [`Codec`] that implements `application/grpc+json` via the serde library.
+///
+/// For the JSON to follow the [protobuf JSON mapping], and so to
+/// interoperate with the JSON codecs of other gRPC implementations, the
+/// `serde` implementations of messages must follow it too, such as those
+/// generated by `pbjson-build`.
+///
+/// Generated clients and servers use it when `tonic-build` is configured
+/// with `codec_path("tonic::codec::JsonCodec")`.
+///
+/// [protobuf JSON mapping]: https://protobuf.dev/programming-guides/proto3/#json
+#[derive(Debug, Clone)]
+pub struct JsonCodec<T, U> {
+    _pd: PhantomData<(T, U)>,
+}
+
+impl<T, U> JsonCodec<T, U> {
+    /// Create a JSON codec.
+    pub fn new() -> Self {
+        Self { _pd: PhantomData }
+    }
+}
+
+impl<T, U> Default for JsonCodec<T, U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, U> Codec for JsonCodec<T, U>
+where
+    T: Serialize + Send + 'static,
+    U: DeserializeOwned + Send + 'static,
+{
+    type Encode = T;
+    type Decode = U;
+
+    type Encoder = JsonEncoder<T>;
+    type Decoder = JsonDecoder<U>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        JsonEncoder { _pd: PhantomData }
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        JsonDecoder { _pd: PhantomData }
+    }
+
+    fn content_type(&self) -> &'static str {
+        JSON_CONTENT_TYPE
+    }
+
+    fn accept_content_type(&mut self, content_type: &str) -> bool {
+        is_json(content_type)
+    }
+}
+
+/// A [`Encoder`] that knows how to encode `T` as JSON.
+#[derive(Debug, Clone, Default)]
+pub struct JsonEncoder<T> {
+    _pd: PhantomData<T>,
+}
+
+impl<T: Serialize> Encoder for JsonEncoder<T> {
+    type Item = T;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        serde_json::to_writer(buf.writer(), &item)
+            .map_err(|error| Status::new(Code::Internal, error.to_string()))
+    }
+}
+
+/// A [`Decoder`] that knows how to decode `U` from JSON.
+#[derive(Debug, Clone, Default)]
+pub struct JsonDecoder<U> {
+    _pd: PhantomData<U>,
+}
+
+impl<U: DeserializeOwned> Decoder for JsonDecoder<U> {
+    type Item = U;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let bytes = buf.copy_to_bytes(buf.remaining());
+        // Like protobuf parse errors, see
+        // https://github.com/grpc/grpc/blob/master/doc/statuscodes.md
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|error| Status::new(Code::Internal, error.to_string()))
+    }
+}
+
+#[cfg(feature = "prost")]
+pub use self::prost_json::*;
+
+#[cfg(feature = "prost")]
+mod prost_json {
+    use super::*;
+    use crate::codec::{
+        prost::{ProstDecoder, ProstEncoder},
+        BufferSettings,
+    };
+
+    /// A [`Codec`] of both protobuf and JSON calls, which encodes and
+    /// decodes the messages of each call received by a server in the format
+    /// of its request, `application/grpc+json` or protobuf otherwise.
+    ///
+    /// A server generated by `tonic-build` configured with
+    /// `codec_path("tonic::codec::ProstJsonCodec")` serves the JSON clients
+    /// of other gRPC implementations alongside protobuf ones. Its messages
+    /// must implement both [`prost::Message`] and the `serde` traits, as
+    /// [`JsonCodec`] describes. Clients using this codec send protobuf.
+    #[derive(Debug, Clone)]
+    pub struct ProstJsonCodec<T, U> {
+        json: bool,
+        _pd: PhantomData<(T, U)>,
+    }
+
+    impl<T, U> ProstJsonCodec<T, U> {
+        /// Create a codec of protobuf calls, until a JSON request is
+        /// accepted.
+        pub fn new() -> Self {
+            Self {
+                json: false,
+                _pd: PhantomData,
+            }
+        }
+    }
+
+    impl<T, U> Default for ProstJsonCodec<T, U> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T, U> Codec for ProstJsonCodec<T, U>
+    where
+        T: prost::Message + Serialize + Send + 'static,
+        U: prost::Message + Default + DeserializeOwned + Send + 'static,
+    {
+        type Encode = T;
+        type Decode = U;
+
+        type Encoder = ProstJsonEncoder<T>;
+        type Decoder = ProstJsonDecoder<U>;
+
+        fn encoder(&mut self) -> Self::Encoder {
+            ProstJsonEncoder(if self.json {
+                Format::Json(JsonEncoder { _pd: PhantomData })
+            } else {
+                Format::Prost(ProstEncoder::new(BufferSettings::default()))
+            })
+        }
+
+        fn decoder(&mut self) -> Self::Decoder {
+            ProstJsonDecoder(if self.json {
+                Format::Json(JsonDecoder { _pd: PhantomData })
+            } else {
+                Format::Prost(ProstDecoder::new(BufferSettings::default()))
+            })
+        }
+
+        fn content_type(&self) -> &'static str {
+            if self.json {
+                JSON_CONTENT_TYPE
+            } else {
+                "application/grpc"
+            }
+        }
+
+        fn accept_content_type(&mut self, content_type: &str) -> bool {
+            self.json = is_json(content_type);
+            true
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    enum Format<P, J> {
+        Prost(P),
+        Json(J),
+    }
+
+    /// A [`Encoder`] that knows how to encode `T` as protobuf or JSON.
+    #[derive(Debug, Clone)]
+    pub struct ProstJsonEncoder<T>(Format<ProstEncoder<T>, JsonEncoder<T>>);
+
+    impl<T> Encoder for ProstJsonEncoder<T>
+    where
+        T: prost::Message + Serialize + Send + 'static,
+    {
+        type Item = T;
+        type Error = Status;
+
+        fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Status> {
+            match &mut self.0 {
+                Format::Prost(encoder) => encoder.encode(item, buf),
+                Format::Json(encoder) => encoder.encode(item, buf),
+            }
+        }
+    }
+
+    /// A [`Decoder`] that knows how to decode `U` from protobuf or JSON.
+    #[derive(Debug, Clone)]
+    pub struct ProstJsonDecoder<U>(Format<ProstDecoder<U>, JsonDecoder<U>>);
+
+    impl<U> Decoder for ProstJsonDecoder<U>
+    where
+        U: prost::Message + Default + DeserializeOwned + Send + 'static,
+    {
+        type Item = U;
+        type Error = Status;
+
+        fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<U>, Status> {
+            match &mut self.0 {
+                Format::Prost(decoder) => decoder.decode(buf),
+                Format::Json(decoder) => decoder.decode(buf),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{
+        body::BoxBody, client::Grpc, codec::ProstCodec, metadata::MetadataMap, Request, Response,
+    };
+    use http::uri::PathAndQuery;
+    use std::convert::Infallible;
+
+    /// A server of protobuf and JSON calls, answering with the content type
+    /// of requests.
+    fn echo() -> impl crate::client::GrpcService<
+        BoxBody,
+        ResponseBody = BoxBody,
+        Error = Infallible,
+        Future = impl Send,
+    > + Clone {
+        tower::service_fn(|request: http::Request<BoxBody>| async move {
+            let mut grpc = crate::server::Grpc::new(ProstJsonCodec::<String, String>::default());
+            let echo = tower::service_fn(|request: Request<String>| async move {
+                let content_type = request.metadata().get("content-type").unwrap();
+                let answer = format!("{} {}", request.get_ref(), content_type.to_str().unwrap());
+                Ok(Response::new(answer))
+            });
+            Ok(grpc.unary(echo, request).await)
+        })
+    }
+
+    fn content_type(metadata: &MetadataMap) -> &str {
+        metadata.get("content-type").unwrap().to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_calls_in_the_format_of_requests() {
+        let mut client = Grpc::new(echo());
+        let path = PathAndQuery::from_static("/test.Echo/Call");
+
+        let response = client
+            .unary(
+                Request::new("hello".to_owned()),
+                path.clone(),
+                JsonCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(content_type(response.metadata()), "application/grpc+json");
+        assert_eq!(response.get_ref(), "hello application/grpc+json");
+
+        let response = client
+            .unary(
+                Request::new("hello".to_owned()),
+                path,
+                ProstCodec::<String, String>::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(content_type(response.metadata()), "application/grpc");
+        assert_eq!(response.get_ref(), "hello application/grpc");
+    }
+
+    #[test]
+    fn accepts_json_content_types() {
+        let mut codec = JsonCodec::<String, String>::default();
+        assert!(codec.accept_content_type("application/grpc+json"));
+        assert!(codec.accept_content_type("application/grpc+JSON; charset=utf-8"));
+        assert!(!codec.accept_content_type("application/grpc"));
+    }
+}
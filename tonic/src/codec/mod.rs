@@ -1,14 +1,22 @@
 //! Generic encoding and decoding.
 //!
-//! This module contains the generic `Codec`, `Encoder` and `Decoder` traits
-//! and a protobuf codec based on prost.
+//! This module contains the generic `Codec`, `Encoder` and `Decoder` traits,
+//! a protobuf codec based on prost, a JSON codec and a codec of any format
+//! based on serde, and a flatbuffers codec.
 
 mod buffer;
 pub(crate) mod compression;
 mod decode;
 mod encode;
+#[cfg(feature = "flatbuffers")]
+mod flatbuffers;
+#[cfg(feature = "json")]
+mod json;
 #[cfg(feature = "prost")]
 mod prost;
+#[cfg(feature = "serde")]
+mod serde;
+mod validate;
 
 use crate::Status;
 use std::io;
@@ -16,11 +24,26 @@ use std::io;
 pub(crate) use self::encode::{encode_client, encode_server};
 
 pub use self::buffer::{DecodeBuf, EncodeBuf};
-pub use self::compression::{CompressionEncoding, EnabledCompressionEncodings};
+pub use self::compression::{
+    CompressionEncoding, CompressionLevel, Compressor, CustomEncoding, EnabledCompressionEncodings,
+};
 pub use self::decode::Streaming;
+#[cfg(feature = "flatbuffers")]
+#[cfg_attr(docsrs, doc(cfg(feature = "flatbuffers")))]
+pub use self::flatbuffers::{FlatbuffersCodec, FlatbuffersDecoder, FlatbuffersEncoder};
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub use self::json::JsonCodec;
+#[cfg(all(feature = "prost", feature = "json"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "prost", feature = "json"))))]
+pub use self::json::ProstJsonCodec;
 #[cfg(feature = "prost")]
 #[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
 pub use self::prost::ProstCodec;
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub use self::serde::{SerdeCodec, SerdeDecoder, SerdeEncoder, SerdeFormat};
+pub use self::validate::{ValidateCodec, ValidateDecoder};
 
 /// Unless overridden, this is the buffer size used for encoding requests.
 /// This is spent per-rpc, so you may wish to adjust it. The default is
@@ -92,7 +115,7 @@ impl Default for BufferSettings {
 }
 
 // 5 bytes
-const HEADER_SIZE: usize =
+pub(crate) const HEADER_SIZE: usize =
     // compression flag
     std::mem::size_of::<u8>() +
     // data length
@@ -118,6 +141,27 @@ pub trait Codec {
     fn encoder(&mut self) -> Self::Encoder;
     /// Fetch the decoder.
     fn decoder(&mut self) -> Self::Decoder;
+
+    /// The content type of the calls of this codec, which clients send with
+    /// their requests and servers with their responses.
+    ///
+    /// Defaults to `application/grpc`, which is protobuf by convention.
+    fn content_type(&self) -> &'static str {
+        "application/grpc"
+    }
+
+    /// Adapt the codec to the content type of a request received by a
+    /// server, such as `application/grpc+json`, before any message is
+    /// decoded or encoded.
+    ///
+    /// Codecs of several formats pick the format of the call here, like the
+    /// `ProstJsonCodec` of the `json` feature. Returning `false` fails the
+    /// call as unsupported.
+    /// The default accepts any content type.
+    fn accept_content_type(&mut self, content_type: &str) -> bool {
+        let _ = content_type;
+        true
+    }
 }
 
 /// Encodes gRPC message types
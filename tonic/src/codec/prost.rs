@@ -149,7 +149,7 @@ fn from_decode_error(error: prost::DecodeError) -> crate::Status {
 
 #[cfg(test)]
 mod tests {
-    use crate::codec::compression::SingleMessageCompressionOverride;
+    use crate::codec::compression::{CompressionConfig, SingleMessageCompressionOverride};
     use crate::codec::{
         encode_server, DecodeBuf, Decoder, EncodeBuf, Encoder, Streaming, HEADER_SIZE,
     };
@@ -221,6 +221,45 @@ mod tests {
         assert_eq!(actual.message(), expected.message());
     }
 
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn decode_decompressed_message_size_exceeded() {
+        use std::io::Write;
+
+        let decoder = MockDecoder::default();
+
+        // A thousand times smaller than once decompressed.
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        gzip.write_all(&vec![0u8; MAX_MESSAGE_SIZE + 1]).unwrap();
+        let msg = gzip.finish().unwrap();
+        assert!(msg.len() < MAX_MESSAGE_SIZE / 100);
+
+        let mut buf = BytesMut::new();
+        buf.put_u8(1);
+        buf.put_u32(msg.len() as u32);
+        buf.put(&msg[..]);
+
+        let body = body::MockBody::new(&buf[..], buf.len(), 0);
+
+        let mut stream = Streaming::new_request(
+            decoder,
+            body,
+            Some(crate::codec::CompressionEncoding::Gzip),
+            Some(MAX_MESSAGE_SIZE),
+        );
+
+        let actual = stream.message().await.unwrap_err();
+
+        assert_eq!(actual.code(), Code::ResourceExhausted);
+        assert_eq!(
+            actual.message(),
+            format!(
+                "Error, decompressed message length too large: the limit is: {} bytes",
+                MAX_MESSAGE_SIZE
+            )
+        );
+    }
+
     #[tokio::test]
     async fn encode() {
         let encoder = MockEncoder::default();
@@ -235,6 +274,8 @@ mod tests {
             source,
             None,
             SingleMessageCompressionOverride::default(),
+            CompressionConfig::default(),
+            None,
             None,
         ));
 
@@ -257,7 +298,9 @@ mod tests {
             source,
             None,
             SingleMessageCompressionOverride::default(),
+            CompressionConfig::default(),
             Some(MAX_MESSAGE_SIZE),
+            None,
         ));
 
         assert!(body.data().await.is_none());
@@ -273,6 +316,41 @@ mod tests {
         assert!(body.is_end_stream());
     }
 
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn encode_small_messages_uncompressed() {
+        let encoder = MockEncoder::default();
+
+        let messages = [vec![0u8; 100], vec![0u8; 1024]].map(Ok::<_, Status>);
+        let source = tokio_stream::iter(messages);
+
+        let mut body = pin!(encode_server(
+            encoder,
+            source,
+            Some(crate::codec::CompressionEncoding::Gzip),
+            SingleMessageCompressionOverride::default(),
+            CompressionConfig {
+                min_size: 1024,
+                ..Default::default()
+            },
+            None,
+            None,
+        ));
+
+        let mut buf = BytesMut::new();
+        while let Some(r) = body.data().await {
+            buf.put(r.unwrap());
+        }
+
+        let mut flags = Vec::new();
+        while buf.has_remaining() {
+            flags.push(buf.get_u8());
+            let len = buf.get_u32() as usize;
+            buf.advance(len);
+        }
+        assert_eq!(flags, [0, 1]);
+    }
+
     // skip on windows because CI stumbles over our 4GB allocation
     #[cfg(not(target_family = "windows"))]
     #[tokio::test]
@@ -289,7 +367,9 @@ mod tests {
             source,
             None,
             SingleMessageCompressionOverride::default(),
+            CompressionConfig::default(),
             Some(usize::MAX),
+            None,
         ));
 
         assert!(body.data().await.is_none());
@@ -0,0 +1,219 @@
+use super::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use crate::{Code, Status};
+use bytes::{Buf, BufMut};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, io, marker::PhantomData};
+
+/// Whether `content_type` is `expected`, ignoring its parameters and case.
+pub(crate) fn is_content_type(content_type: &str, expected: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default();
+    essence.trim().eq_ignore_ascii_case(expected)
+}
+
+/// A serde data format of the messages of a [`SerdeCodec`], such as CBOR,
+/// MessagePack or bincode.
+///
+/// ```ignore
+/// #[derive(Debug, Clone, Default)]
+/// struct Cbor;
+///
+/// impl SerdeFormat for Cbor {
+///     type Error = String;
+///
+///     fn content_type(&self) -> &'static str {
+///         "application/grpc+cbor"
+///     }
+///
+///     fn serialize<T: Serialize>(&self, item: &T, writer: impl io::Write) -> Result<(), String> {
+///         ciborium::into_writer(item, writer).map_err(|e| e.to_string())
+///     }
+///
+///     fn deserialize<U: DeserializeOwned>(&self, buf: &[u8]) -> Result<U, String> {
+///         ciborium::from_reader(buf).map_err(|e| e.to_string())
+///     }
+/// }
+///
+/// let codec = SerdeCodec::<Request, Response, Cbor>::default();
+/// ```
+pub trait SerdeFormat: Clone + Send + 'static {
+    /// The error of serializing or deserializing a message.
+    type Error: fmt::Display;
+
+    /// The content type of the calls of the format, such as
+    /// `application/grpc+cbor`.
+    fn content_type(&self) -> &'static str;
+
+    /// Serialize `item` to `writer`.
+    fn serialize<T: Serialize>(&self, item: &T, writer: impl io::Write) -> Result<(), Self::Error>;
+
+    /// Deserialize a message from the whole of `buf`.
+    fn deserialize<U: DeserializeOwned>(&self, buf: &[u8]) -> Result<U, Self::Error>;
+}
+
+/// A [`Codec`] of messages serialized by serde in the format `F`.
+///
+/// It serves and calls the methods of hand-written service definitions, via
+/// [`server::Grpc::new`](crate::server::Grpc::new) and
+/// [`client::Grpc`](crate::client::Grpc), or of the `tonic-build` generated
+/// code of a `codec_path` naming a codec of a default format.
+///
+/// Servers accept the requests of the content type of the format only.
+#[derive(Debug, Clone)]
+pub struct SerdeCodec<T, U, F> {
+    format: F,
+    _pd: PhantomData<(T, U)>,
+}
+
+impl<T, U, F> SerdeCodec<T, U, F> {
+    /// Create a codec of messages serialized in `format`.
+    pub fn new(format: F) -> Self {
+        Self {
+            format,
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl<T, U, F: Default> Default for SerdeCodec<T, U, F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<T, U, F> Codec for SerdeCodec<T, U, F>
+where
+    T: Serialize + Send + 'static,
+    U: DeserializeOwned + Send + 'static,
+    F: SerdeFormat,
+{
+    type Encode = T;
+    type Decode = U;
+
+    type Encoder = SerdeEncoder<T, F>;
+    type Decoder = SerdeDecoder<U, F>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        SerdeEncoder {
+            format: self.format.clone(),
+            _pd: PhantomData,
+        }
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        SerdeDecoder {
+            format: self.format.clone(),
+            _pd: PhantomData,
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        self.format.content_type()
+    }
+
+    fn accept_content_type(&mut self, content_type: &str) -> bool {
+        is_content_type(content_type, self.format.content_type())
+    }
+}
+
+/// A [`Encoder`] that knows how to serialize `T` in the format `F`.
+#[derive(Debug, Clone)]
+pub struct SerdeEncoder<T, F> {
+    format: F,
+    _pd: PhantomData<T>,
+}
+
+impl<T: Serialize, F: SerdeFormat> Encoder for SerdeEncoder<T, F> {
+    type Item = T;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        self.format
+            .serialize(&item, buf.writer())
+            .map_err(|error| Status::new(Code::Internal, error.to_string()))
+    }
+}
+
+/// A [`Decoder`] that knows how to deserialize `U` from the format `F`.
+#[derive(Debug, Clone)]
+pub struct SerdeDecoder<U, F> {
+    format: F,
+    _pd: PhantomData<U>,
+}
+
+impl<U: DeserializeOwned, F: SerdeFormat> Decoder for SerdeDecoder<U, F> {
+    type Item = U;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let bytes = buf.copy_to_bytes(buf.remaining());
+        self.format
+            .deserialize(&bytes)
+            .map(Some)
+            .map_err(|error| Status::new(Code::Internal, error.to_string()))
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+    use crate::{body::BoxBody, client::Grpc, Request, Response};
+    use http::uri::PathAndQuery;
+    use std::convert::Infallible;
+
+    /// JSON, as a stand-in for the formats of other crates.
+    #[derive(Debug, Clone, Default)]
+    struct Json;
+
+    impl SerdeFormat for Json {
+        type Error = serde_json::Error;
+
+        fn content_type(&self) -> &'static str {
+            "application/grpc+test"
+        }
+
+        fn serialize<T: Serialize>(
+            &self,
+            item: &T,
+            writer: impl io::Write,
+        ) -> serde_json::Result<()> {
+            serde_json::to_writer(writer, item)
+        }
+
+        fn deserialize<U: DeserializeOwned>(&self, buf: &[u8]) -> serde_json::Result<U> {
+            serde_json::from_slice(buf)
+        }
+    }
+
+    type TestCodec = SerdeCodec<(String, u32), (String, u32), Json>;
+
+    #[tokio::test]
+    async fn calls_hand_written_services() {
+        let server = tower::service_fn(|request: http::Request<BoxBody>| async move {
+            assert_eq!(request.headers()["content-type"], "application/grpc+test");
+            let mut grpc = crate::server::Grpc::new(TestCodec::default());
+            let repeat = tower::service_fn(|request: Request<(String, u32)>| async move {
+                let (text, times) = request.into_inner();
+                Ok(Response::new((text.repeat(times as usize), times)))
+            });
+            Ok::<_, Infallible>(grpc.unary(repeat, request).await)
+        });
+
+        let mut client = Grpc::new(server);
+        let response = client
+            .unary(
+                Request::new(("ab".to_owned(), 2)),
+                PathAndQuery::from_static("/test.Repeat/Repeat"),
+                TestCodec::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.into_inner(), ("abab".to_owned(), 2));
+    }
+
+    #[test]
+    fn accepts_the_content_type_of_the_format() {
+        let mut codec = TestCodec::default();
+        assert!(codec.accept_content_type("application/grpc+TEST; charset=utf-8"));
+        assert!(!codec.accept_content_type("application/grpc"));
+    }
+}
@@ -0,0 +1,141 @@
+use super::{BufferSettings, Codec, DecodeBuf, Decoder};
+use crate::Status;
+use std::fmt;
+
+/// A [`Codec`] validating the messages decoded by the codec `C`, failing the
+/// calls of invalid messages with the status of the validation.
+///
+/// Servers generated with the `request_validator` of `tonic-build` validate
+/// their requests with it, such as with the `buf.validate` rules checked by
+/// the code of a crate like `prost-validate`, failing invalid requests with
+/// `InvalidArgument` and the violations in the error details of `tonic-types`:
+///
+/// ```ignore
+/// pub fn validate<M: Validate>(message: &M) -> Result<(), Status> {
+///     message.validate().map_err(|violation| {
+///         let details =
+///             ErrorDetails::with_bad_request_violation(violation.field, violation.description);
+///         Status::with_error_details(Code::InvalidArgument, "invalid request", details)
+///     })
+/// }
+/// ```
+///
+/// The request of a unary or server-streaming call is validated before its
+/// handler is called, and the messages of a streaming request as the handler
+/// receives them.
+pub struct ValidateCodec<C: Codec> {
+    inner: C,
+    validate: fn(&C::Decode) -> Result<(), Status>,
+}
+
+impl<C: Codec> ValidateCodec<C> {
+    /// Validate the messages decoded by `inner` with `validate`.
+    pub fn new(inner: C, validate: fn(&C::Decode) -> Result<(), Status>) -> Self {
+        Self { inner, validate }
+    }
+}
+
+impl<C: Codec> Codec for ValidateCodec<C> {
+    type Encode = C::Encode;
+    type Decode = C::Decode;
+
+    type Encoder = C::Encoder;
+    type Decoder = ValidateDecoder<C::Decoder>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        self.inner.encoder()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        ValidateDecoder {
+            inner: self.inner.decoder(),
+            validate: self.validate,
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        self.inner.content_type()
+    }
+
+    fn accept_content_type(&mut self, content_type: &str) -> bool {
+        self.inner.accept_content_type(content_type)
+    }
+}
+
+impl<C: Codec + fmt::Debug> fmt::Debug for ValidateCodec<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidateCodec")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// A [`Decoder`] validating the messages decoded by the decoder `D`.
+pub struct ValidateDecoder<D: Decoder> {
+    inner: D,
+    validate: fn(&D::Item) -> Result<(), Status>,
+}
+
+impl<D: Decoder<Error = Status>> Decoder for ValidateDecoder<D> {
+    type Item = D::Item;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let item = self.inner.decode(buf)?;
+        if let Some(item) = &item {
+            (self.validate)(item)?;
+        }
+        Ok(item)
+    }
+
+    fn buffer_settings(&self) -> BufferSettings {
+        self.inner.buffer_settings()
+    }
+}
+
+impl<D: Decoder + fmt::Debug> fmt::Debug for ValidateDecoder<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidateDecoder")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::codec::{EncodeBuf, Encoder, ProstCodec};
+    use crate::Code;
+    use bytes::BytesMut;
+
+    fn validate(value: &u32) -> Result<(), Status> {
+        if *value > 10 {
+            Err(Status::invalid_argument("value is too large"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn decode(value: u32) -> Result<Option<u32>, Status> {
+        let mut codec = ValidateCodec::new(ProstCodec::<u32, u32>::default(), validate);
+        let mut buf = BytesMut::new();
+        codec
+            .encoder()
+            .encode(value, &mut EncodeBuf::new(&mut buf))
+            .unwrap();
+        let len = buf.len();
+        codec.decoder().decode(&mut DecodeBuf::new(&mut buf, len))
+    }
+
+    #[test]
+    fn decodes_valid_messages() {
+        assert_eq!(decode(5).unwrap(), Some(5));
+    }
+
+    #[test]
+    fn fails_invalid_messages() {
+        let status = decode(11).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "value is too large");
+    }
+}
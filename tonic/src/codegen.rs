@@ -9,15 +9,49 @@ pub use std::sync::Arc;
 pub use std::task::{Context, Poll};
 pub use tower_service::Service;
 pub type StdError = Box<dyn std::error::Error + Send + Sync + 'static>;
-pub use crate::codec::{CompressionEncoding, EnabledCompressionEncodings};
+pub use crate::codec::{CompressionEncoding, CompressionLevel, EnabledCompressionEncodings};
 pub use crate::extensions::GrpcMethod;
-pub use crate::service::interceptor::InterceptedService;
+pub use crate::service::interceptor::{AsyncInterceptedService, InterceptedService};
 pub use bytes::Bytes;
 pub use http;
 pub use http_body::Body;
 
 pub type BoxFuture<T, E> = self::Pin<Box<dyn self::Future<Output = Result<T, E>> + Send + 'static>>;
+pub type LocalBoxFuture<T, E> = self::Pin<Box<dyn self::Future<Output = Result<T, E>> + 'static>>;
 pub type BoxStream<T> =
     self::Pin<Box<dyn tokio_stream::Stream<Item = Result<T, crate::Status>> + Send + 'static>>;
 
 pub use crate::body::empty_body;
+
+/// The boxed stream of messages of a client streaming call, as the client
+/// traits of `tonic-build` take them.
+pub struct RequestStream<T> {
+    inner: self::Pin<Box<dyn tokio_stream::Stream<Item = T> + Send + 'static>>,
+}
+
+impl<T> RequestStream<T> {
+    /// Box `stream`.
+    pub fn new(stream: impl tokio_stream::Stream<Item = T> + Send + 'static) -> Self {
+        Self {
+            inner: Box::pin(stream),
+        }
+    }
+}
+
+impl<T> tokio_stream::Stream for RequestStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> std::fmt::Debug for RequestStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestStream").finish()
+    }
+}
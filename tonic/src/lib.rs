@@ -30,9 +30,31 @@
 //! - `tls-webpki-roots`: Add the standard trust roots from the `webpki-roots` crate to
 //! `rustls`-based gRPC clients. Not enabled by default.
 //! - `prost`: Enables the [`prost`] based gRPC [`Codec`] implementation.
+//! - `json`: Enables the [`serde`] based gRPC [`Codec`] implementation, encoding
+//! messages as JSON for `application/grpc+json` calls. Depends on [serde_json].
+//! Not enabled by default.
+//! - `serde`: Enables the [`serde`] based gRPC [`Codec`] of any serde data format,
+//! such as CBOR or MessagePack. Not enabled by default.
+//! - `flatbuffers`: Enables the gRPC [`Codec`] of [flatbuffers] messages, which
+//! decodes them without copies. Not enabled by default.
 //! - `gzip`: Enables compressing requests, responses, and streams.
 //! Depends on [flate2]. Not enabled by default.
 //! Replaces the `compression` flag from earlier versions of `tonic` (<= 0.7).
+//! - `zstd`: Enables compressing requests, responses, and streams with zstd, which
+//! compresses better and faster than gzip. Depends on [zstd]. Not enabled by default.
+//! - `service-config`: Enables parsing a [gRPC service config] from JSON for the
+//! `transport` channel. Depends on [serde_json]. Not enabled by default.
+//! - `service-config-dns`: Enables fetching the service config of `dns` targets
+//! from their TXT records. Depends on [hickory-resolver]. Not enabled by default.
+//! - `metrics`: Enables recording the OpenTelemetry semantic-convention metrics of
+//! calls with [`metrics::Metrics`], and the Prometheus metrics of servers with
+//! [`metrics::prometheus`]. Not enabled by default.
+//! - `http3`: Enables connecting to endpoints with HTTP/3 over QUIC, falling back to
+//! HTTP/2. Depends on [quinn] and [h3], and enables `tls`. Not enabled by default.
+//! - `blocking`: Enables the [`client::blocking`] runtime of the synchronous clients
+//! generated with `blocking_client`. Not enabled by default.
+//! - `sink`: Enables the [`client::sink`] of the requests of the clients generated with
+//! `client_sinks`. Not enabled by default.
 //!
 //! # Structure
 //!
@@ -65,8 +87,12 @@
 //! [`tonic`]: https://github.com/hyperium/tonic
 //! [`tokio`]: https://docs.rs/tokio
 //! [`prost`]: https://docs.rs/prost
+//! [`serde`]: https://docs.rs/serde
 //! [`hyper`]: https://docs.rs/hyper
 //! [`tower`]: https://docs.rs/tower
+//! [gRPC service config]: https://github.com/grpc/grpc/blob/master/doc/service_config.md
+//! [serde_json]: https://docs.rs/serde_json
+//! [hickory-resolver]: https://docs.rs/hickory-resolver
 //! [`tonic-build`]: https://docs.rs/tonic-build
 //! [`tonic-examples`]: https://github.com/hyperium/tonic/tree/master/examples
 //! [`Codec`]: codec/trait.Codec.html
@@ -76,6 +102,10 @@
 //! [`client`]: client/index.html
 //! [`transport`]: transport/index.html
 //! [flate2]: https://crates.io/crates/flate2
+//! [flatbuffers]: https://flatbuffers.dev
+//! [zstd]: https://crates.io/crates/zstd
+//! [quinn]: https://crates.io/crates/quinn
+//! [h3]: https://crates.io/crates/h3
 
 #![recursion_limit = "256"]
 #![allow(clippy::inconsistent_struct_constructor)]
@@ -98,8 +128,16 @@ pub mod body;
 pub mod client;
 pub mod codec;
 pub mod metadata;
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
+#[cfg(feature = "transport")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
+pub mod proxy;
 pub mod server;
 pub mod service;
+pub mod stats;
+pub mod testing;
 
 #[cfg(feature = "transport")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
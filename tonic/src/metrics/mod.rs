@@ -0,0 +1,372 @@
+//! The [OpenTelemetry semantic conventions] metrics of gRPC calls.
+//!
+//! [`Metrics`] is a [`StatsHandler`] measuring the calls of the server or
+//! the channel it is installed on, with
+//! [`Server::stats_handler`](crate::transport::Server::stats_handler) or
+//! [`Endpoint::stats_handler`](crate::transport::Endpoint::stats_handler).
+//! It records each [`Instrument`] to a [`Meter`], which forwards them to a
+//! metrics library such as `opentelemetry`:
+//!
+//! ```ignore
+//! use opentelemetry::{metrics::Histogram, KeyValue};
+//! use tonic::metrics::{Attributes, Instrument, Meter};
+//!
+//! struct OtelMeter(Vec<Histogram<f64>>);
+//!
+//! impl OtelMeter {
+//!     fn new(meter: opentelemetry::metrics::Meter) -> Self {
+//!         let histograms = Instrument::ALL.iter().map(|instrument| {
+//!             meter
+//!                 .f64_histogram(instrument.name())
+//!                 .with_unit(instrument.unit())
+//!                 .with_description(instrument.description())
+//!                 .init()
+//!         });
+//!         OtelMeter(histograms.collect())
+//!     }
+//! }
+//!
+//! impl Meter for OtelMeter {
+//!     fn record(&self, instrument: Instrument, value: f64, attributes: &Attributes<'_>) {
+//!         let mut pairs = vec![
+//!             KeyValue::new("rpc.system", attributes.system()),
+//!             KeyValue::new("rpc.service", attributes.service().to_string()),
+//!             KeyValue::new("rpc.method", attributes.method().to_string()),
+//!         ];
+//!         if let Some(code) = attributes.status_code() {
+//!             pairs.push(KeyValue::new("rpc.grpc.status_code", code as i64));
+//!         }
+//!         self.0[instrument as usize].record(value, &pairs);
+//!     }
+//! }
+//! ```
+//!
+//! Message sizes are those of the messages before compression, as reported
+//! by the codec.
+//!
+//! The [`prometheus`] module counts the calls of servers per method, for
+//! Prometheus to scrape.
+//!
+//! [OpenTelemetry semantic conventions]: https://opentelemetry.io/docs/specs/semconv/rpc/rpc-metrics/
+
+pub mod prometheus;
+
+use crate::{
+    stats::{split_method, Payload, RpcEnd, RpcInfo, StatsHandler},
+    Code,
+};
+use std::{collections::HashMap, fmt, sync::Mutex};
+
+/// The instruments of the semantic conventions, all histograms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instrument {
+    /// `rpc.server.duration`: the duration of the calls of a server.
+    ServerDuration,
+    /// `rpc.server.request.size`: the size of the messages a server receives.
+    ServerRequestSize,
+    /// `rpc.server.response.size`: the size of the messages a server sends.
+    ServerResponseSize,
+    /// `rpc.server.requests_per_rpc`: the count of messages a server
+    /// receives per call.
+    ServerRequestsPerRpc,
+    /// `rpc.server.responses_per_rpc`: the count of messages a server sends
+    /// per call.
+    ServerResponsesPerRpc,
+    /// `rpc.client.duration`: the duration of the calls of a client.
+    ClientDuration,
+    /// `rpc.client.request.size`: the size of the messages a client sends.
+    ClientRequestSize,
+    /// `rpc.client.response.size`: the size of the messages a client
+    /// receives.
+    ClientResponseSize,
+    /// `rpc.client.requests_per_rpc`: the count of messages a client sends
+    /// per call.
+    ClientRequestsPerRpc,
+    /// `rpc.client.responses_per_rpc`: the count of messages a client
+    /// receives per call.
+    ClientResponsesPerRpc,
+}
+
+impl Instrument {
+    /// Every instrument, in the order of their discriminants.
+    pub const ALL: [Instrument; 10] = [
+        Instrument::ServerDuration,
+        Instrument::ServerRequestSize,
+        Instrument::ServerResponseSize,
+        Instrument::ServerRequestsPerRpc,
+        Instrument::ServerResponsesPerRpc,
+        Instrument::ClientDuration,
+        Instrument::ClientRequestSize,
+        Instrument::ClientResponseSize,
+        Instrument::ClientRequestsPerRpc,
+        Instrument::ClientResponsesPerRpc,
+    ];
+
+    /// The name of the instrument, such as `rpc.server.duration`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instrument::ServerDuration => "rpc.server.duration",
+            Instrument::ServerRequestSize => "rpc.server.request.size",
+            Instrument::ServerResponseSize => "rpc.server.response.size",
+            Instrument::ServerRequestsPerRpc => "rpc.server.requests_per_rpc",
+            Instrument::ServerResponsesPerRpc => "rpc.server.responses_per_rpc",
+            Instrument::ClientDuration => "rpc.client.duration",
+            Instrument::ClientRequestSize => "rpc.client.request.size",
+            Instrument::ClientResponseSize => "rpc.client.response.size",
+            Instrument::ClientRequestsPerRpc => "rpc.client.requests_per_rpc",
+            Instrument::ClientResponsesPerRpc => "rpc.client.responses_per_rpc",
+        }
+    }
+
+    /// The unit of the values of the instrument, in UCUM.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Instrument::ServerDuration | Instrument::ClientDuration => "ms",
+            Instrument::ServerRequestSize
+            | Instrument::ServerResponseSize
+            | Instrument::ClientRequestSize
+            | Instrument::ClientResponseSize => "By",
+            _ => "{count}",
+        }
+    }
+
+    /// The description of the instrument.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Instrument::ServerDuration => "Measures the duration of inbound RPC.",
+            Instrument::ServerRequestSize => {
+                "Measures the size of RPC request messages (uncompressed)."
+            }
+            Instrument::ServerResponseSize => {
+                "Measures the size of RPC response messages (uncompressed)."
+            }
+            Instrument::ServerRequestsPerRpc => "Measures the number of messages received per RPC.",
+            Instrument::ServerResponsesPerRpc => "Measures the number of messages sent per RPC.",
+            Instrument::ClientDuration => "Measures the duration of outbound RPC.",
+            Instrument::ClientRequestSize => {
+                "Measures the size of RPC request messages (uncompressed)."
+            }
+            Instrument::ClientResponseSize => {
+                "Measures the size of RPC response messages (uncompressed)."
+            }
+            Instrument::ClientRequestsPerRpc => "Measures the number of messages sent per RPC.",
+            Instrument::ClientResponsesPerRpc => {
+                "Measures the number of messages received per RPC."
+            }
+        }
+    }
+}
+
+/// The attributes of a measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attributes<'a> {
+    service: &'a str,
+    method: &'a str,
+    status_code: Option<Code>,
+}
+
+impl<'a> Attributes<'a> {
+    /// `rpc.system`, always `grpc`.
+    pub fn system(&self) -> &'static str {
+        "grpc"
+    }
+
+    /// `rpc.service`: the full name of the service, such as
+    /// `greeter.Greeter`.
+    pub fn service(&self) -> &'a str {
+        self.service
+    }
+
+    /// `rpc.method`: the name of the method, such as `SayHello`.
+    pub fn method(&self) -> &'a str {
+        self.method
+    }
+
+    /// `rpc.grpc.status_code`: the status code of the call, for the
+    /// measurements made once it ended.
+    pub fn status_code(&self) -> Option<Code> {
+        self.status_code
+    }
+}
+
+/// Receives the measurements of [`Metrics`].
+pub trait Meter: Send + Sync + 'static {
+    /// Record `value` to the histogram of `instrument`.
+    fn record(&self, instrument: Instrument, value: f64, attributes: &Attributes<'_>);
+}
+
+/// A [`StatsHandler`] recording the metrics of calls to a [`Meter`].
+pub struct Metrics<M> {
+    meter: M,
+    /// The messages sent and received by the calls in progress.
+    messages: Mutex<HashMap<u64, (u64, u64)>>,
+}
+
+impl<M: Meter> Metrics<M> {
+    /// Create a handler recording to `meter`.
+    pub fn new(meter: M) -> Self {
+        Metrics {
+            meter,
+            messages: Mutex::default(),
+        }
+    }
+
+    /// The meter the metrics are recorded to.
+    pub fn meter(&self) -> &M {
+        &self.meter
+    }
+
+    fn record(&self, rpc: &RpcInfo, instrument: Instrument, value: f64, code: Option<Code>) {
+        let (service, method) = split_method(rpc.method());
+        let attributes = Attributes {
+            service,
+            method,
+            status_code: code,
+        };
+        self.meter.record(instrument, value, &attributes);
+    }
+
+    fn count(&self, rpc: &RpcInfo, count: impl FnOnce(&mut (u64, u64))) {
+        if let Some(messages) = self.messages.lock().unwrap().get_mut(&rpc.id()) {
+            count(messages);
+        }
+    }
+}
+
+impl<M: Meter> StatsHandler for Metrics<M> {
+    fn rpc_begin(&self, rpc: &RpcInfo) {
+        self.messages.lock().unwrap().insert(rpc.id(), (0, 0));
+    }
+
+    fn out_payload(&self, rpc: &RpcInfo, payload: &Payload) {
+        let instrument = match rpc.is_client() {
+            true => Instrument::ClientRequestSize,
+            false => Instrument::ServerResponseSize,
+        };
+        self.record(rpc, instrument, payload.uncompressed_size() as f64, None);
+        self.count(rpc, |(sent, _)| *sent += 1);
+    }
+
+    fn in_payload(&self, rpc: &RpcInfo, payload: &Payload) {
+        let instrument = match rpc.is_client() {
+            true => Instrument::ClientResponseSize,
+            false => Instrument::ServerRequestSize,
+        };
+        self.record(rpc, instrument, payload.uncompressed_size() as f64, None);
+        self.count(rpc, |(_, received)| *received += 1);
+    }
+
+    fn rpc_end(&self, rpc: &RpcInfo, end: &RpcEnd) {
+        let (sent, received) = self
+            .messages
+            .lock()
+            .unwrap()
+            .remove(&rpc.id())
+            .unwrap_or_default();
+        let code = Some(end.code());
+        let duration = end.duration().as_secs_f64() * 1000.0;
+
+        if rpc.is_client() {
+            self.record(rpc, Instrument::ClientDuration, duration, code);
+            self.record(rpc, Instrument::ClientRequestsPerRpc, sent as f64, code);
+            self.record(
+                rpc,
+                Instrument::ClientResponsesPerRpc,
+                received as f64,
+                code,
+            );
+        } else {
+            self.record(rpc, Instrument::ServerDuration, duration, code);
+            self.record(rpc, Instrument::ServerRequestsPerRpc, received as f64, code);
+            self.record(rpc, Instrument::ServerResponsesPerRpc, sent as f64, code);
+        }
+    }
+}
+
+impl<M: fmt::Debug> fmt::Debug for Metrics<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("meter", &self.meter)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Recorder;
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Recorded(Mutex<Vec<(Instrument, f64, String)>>);
+
+    impl Meter for Arc<Recorded> {
+        fn record(&self, instrument: Instrument, value: f64, attributes: &Attributes<'_>) {
+            let attributes = format!(
+                "{} {} {:?}",
+                attributes.service(),
+                attributes.method(),
+                attributes.status_code()
+            );
+            self.0.lock().unwrap().push((instrument, value, attributes));
+        }
+    }
+
+    #[test]
+    fn records_calls() {
+        let recorded = Arc::new(Recorded::default());
+        let metrics: Arc<dyn StatsHandler> = Arc::new(Metrics::new(recorded.clone()));
+
+        let info = RpcInfo::new(false, "/greeter.Greeter/SayHello", None);
+        let recorder = Recorder::begin(metrics, info);
+        let payload = |size| Payload {
+            wire_size: size + 5,
+            uncompressed_size: size,
+            compressed: false,
+        };
+        recorder.in_payload(payload(7));
+        recorder.out_payload(payload(3));
+        recorder.out_payload(payload(4));
+        recorder.end(Code::NotFound);
+
+        let recorded = recorded.0.lock().unwrap();
+        let without_duration: Vec<_> = recorded
+            .iter()
+            .filter(|(instrument, ..)| *instrument != Instrument::ServerDuration)
+            .map(|(instrument, value, attributes)| (instrument.name(), *value, attributes.as_str()))
+            .collect();
+        assert_eq!(
+            without_duration,
+            [
+                (
+                    "rpc.server.request.size",
+                    7.0,
+                    "greeter.Greeter SayHello None"
+                ),
+                (
+                    "rpc.server.response.size",
+                    3.0,
+                    "greeter.Greeter SayHello None"
+                ),
+                (
+                    "rpc.server.response.size",
+                    4.0,
+                    "greeter.Greeter SayHello None"
+                ),
+                (
+                    "rpc.server.requests_per_rpc",
+                    1.0,
+                    "greeter.Greeter SayHello Some(NotFound)"
+                ),
+                (
+                    "rpc.server.responses_per_rpc",
+                    2.0,
+                    "greeter.Greeter SayHello Some(NotFound)"
+                ),
+            ]
+        );
+        assert!(recorded
+            .iter()
+            .any(|(instrument, ..)| *instrument == Instrument::ServerDuration));
+    }
+}
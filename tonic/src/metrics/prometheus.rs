@@ -0,0 +1,558 @@
+//! Per-method Prometheus metrics of servers.
+//!
+//! [`PrometheusLayer`] counts the calls of the services it wraps into a
+//! [`Registry`], which renders them in the Prometheus text format for the
+//! `/metrics` endpoint of the application:
+//!
+//! ```
+//! use tonic::{metrics::prometheus::{PrometheusLayer, Registry}, transport::Server};
+//!
+//! let registry = Registry::new();
+//! let server = Server::builder().layer(PrometheusLayer::new(&registry));
+//!
+//! // Later, when scraped:
+//! let text = registry.encode();
+//! ```
+//!
+//! The registry holds the following metrics, labeled with the
+//! `grpc_service`, `grpc_method` and, once calls are done, `grpc_code` of
+//! the calls:
+//!
+//! - `grpc_server_started_total`: the counter of calls received.
+//! - `grpc_server_handled_total`: the counter of calls completed.
+//! - `grpc_server_in_flight`: the gauge of calls in progress.
+//! - `grpc_server_handling_seconds`: the histogram of the duration of
+//!   completed calls.
+//!
+//! Calls are completed once their trailers are sent, or once their
+//! response is dropped, in which case their code is `Cancelled`.
+
+use crate::{stats::split_method, stats::status_code, Code};
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use http_body::Body;
+use pin_project::pin_project;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// The default buckets of the duration histogram, in seconds.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The metrics of the calls counted by [`PrometheusLayer`]s.
+///
+/// Clones share the same metrics, so a single registry can be passed to
+/// the layers of several servers.
+#[derive(Clone)]
+pub struct Registry {
+    inner: Arc<RegistryInner>,
+}
+
+struct RegistryInner {
+    buckets: Vec<f64>,
+    methods: Mutex<BTreeMap<(String, String), MethodMetrics>>,
+}
+
+#[derive(Default)]
+struct MethodMetrics {
+    started: u64,
+    in_flight: u64,
+    handled: BTreeMap<i32, Handled>,
+}
+
+/// The calls of a method completed with a code.
+struct Handled {
+    /// The count of calls per bucket, the last one being `+Inf`.
+    buckets: Vec<u64>,
+    sum: f64,
+}
+
+impl Registry {
+    /// Create a registry with the [`DEFAULT_BUCKETS`].
+    pub fn new() -> Self {
+        Self::with_buckets(DEFAULT_BUCKETS.to_vec())
+    }
+
+    /// Create a registry whose duration histograms have the upper bounds
+    /// `buckets`, in seconds.
+    pub fn with_buckets(mut buckets: Vec<f64>) -> Self {
+        buckets.retain(|bucket| bucket.is_finite());
+        buckets.sort_by(f64::total_cmp);
+        buckets.dedup();
+        Registry {
+            inner: Arc::new(RegistryInner {
+                buckets,
+                methods: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let methods = self.inner.methods.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "grpc_server_started_total",
+            "counter",
+            "Total number of RPCs started on the server.",
+        );
+        for ((service, method), metrics) in methods.iter() {
+            let labels = labels(service, method, None);
+            let _ = writeln!(
+                out,
+                "grpc_server_started_total{{{}}} {}",
+                labels, metrics.started
+            );
+        }
+
+        header(
+            &mut out,
+            "grpc_server_handled_total",
+            "counter",
+            "Total number of RPCs completed on the server, regardless of success or failure.",
+        );
+        for ((service, method), metrics) in methods.iter() {
+            for (code, handled) in &metrics.handled {
+                let labels = labels(service, method, Some(Code::from_i32(*code)));
+                let count = handled.buckets.iter().sum::<u64>();
+                let _ = writeln!(out, "grpc_server_handled_total{{{}}} {}", labels, count);
+            }
+        }
+
+        header(
+            &mut out,
+            "grpc_server_in_flight",
+            "gauge",
+            "Number of RPCs in progress on the server.",
+        );
+        for ((service, method), metrics) in methods.iter() {
+            let labels = labels(service, method, None);
+            let _ = writeln!(
+                out,
+                "grpc_server_in_flight{{{}}} {}",
+                labels, metrics.in_flight
+            );
+        }
+
+        header(
+            &mut out,
+            "grpc_server_handling_seconds",
+            "histogram",
+            "Histogram of response latency (seconds) of gRPC that had been application-level handled by the server.",
+        );
+        for ((service, method), metrics) in methods.iter() {
+            for (code, handled) in &metrics.handled {
+                let labels = labels(service, method, Some(Code::from_i32(*code)));
+                let mut count = 0;
+                for (i, calls) in handled.buckets.iter().enumerate() {
+                    count += calls;
+                    let le = match self.inner.buckets.get(i) {
+                        Some(bucket) => bucket.to_string(),
+                        None => "+Inf".to_string(),
+                    };
+                    let _ = writeln!(
+                        out,
+                        "grpc_server_handling_seconds_bucket{{{},le=\"{}\"}} {}",
+                        labels, le, count
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "grpc_server_handling_seconds_sum{{{}}} {}",
+                    labels, handled.sum
+                );
+                let _ = writeln!(
+                    out,
+                    "grpc_server_handling_seconds_count{{{}}} {}",
+                    labels, count
+                );
+            }
+        }
+
+        out
+    }
+
+    fn start(&self, service: &str, method: &str) {
+        let mut methods = self.inner.methods.lock().unwrap();
+        let metrics = methods
+            .entry((service.to_string(), method.to_string()))
+            .or_default();
+        metrics.started += 1;
+        metrics.in_flight += 1;
+    }
+
+    fn finish(&self, service: &str, method: &str, code: Code, seconds: f64) {
+        let mut methods = self.inner.methods.lock().unwrap();
+        let metrics = match methods.get_mut(&(service.to_string(), method.to_string())) {
+            Some(metrics) => metrics,
+            None => return,
+        };
+        metrics.in_flight -= 1;
+
+        let bucket = self
+            .inner
+            .buckets
+            .iter()
+            .position(|bucket| seconds <= *bucket)
+            .unwrap_or(self.inner.buckets.len());
+        let handled = metrics
+            .handled
+            .entry(code as i32)
+            .or_insert_with(|| Handled {
+                buckets: vec![0; self.inner.buckets.len() + 1],
+                sum: 0.0,
+            });
+        handled.buckets[bucket] += 1;
+        handled.sum += seconds;
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("buckets", &self.inner.buckets)
+            .finish_non_exhaustive()
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn labels(service: &str, method: &str, code: Option<Code>) -> String {
+    let mut labels = format!(
+        "grpc_service=\"{}\",grpc_method=\"{}\"",
+        escape(service),
+        escape(method)
+    );
+    if let Some(code) = code {
+        let _ = write!(labels, ",grpc_code=\"{:?}\"", code);
+    }
+    labels
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Layer counting the calls of a server into a [`Registry`].
+#[derive(Debug, Clone)]
+pub struct PrometheusLayer {
+    registry: Registry,
+}
+
+impl PrometheusLayer {
+    /// Create a layer counting calls into `registry`.
+    pub fn new(registry: &Registry) -> Self {
+        PrometheusLayer {
+            registry: registry.clone(),
+        }
+    }
+}
+
+impl<S> Layer<S> for PrometheusLayer {
+    type Service = Prometheus<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Prometheus {
+            inner,
+            registry: self.registry.clone(),
+        }
+    }
+}
+
+/// Service counting calls into a [`Registry`], see [`PrometheusLayer`].
+#[derive(Debug, Clone)]
+pub struct Prometheus<S> {
+    inner: S,
+    registry: Registry,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Prometheus<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = Response<PrometheusBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let (service, method) = split_method(request.uri().path());
+        let call = Call::start(&self.registry, service, method);
+
+        ResponseFuture {
+            inner: self.inner.call(request),
+            call: Some(call),
+        }
+    }
+}
+
+/// A call in progress, completed as [`Code::Cancelled`] when dropped before
+/// it is done.
+struct Call {
+    registry: Registry,
+    service: String,
+    method: String,
+    start: Instant,
+    done: bool,
+}
+
+impl Call {
+    fn start(registry: &Registry, service: &str, method: &str) -> Self {
+        registry.start(service, method);
+        Call {
+            registry: registry.clone(),
+            service: service.to_string(),
+            method: method.to_string(),
+            start: Instant::now(),
+            done: false,
+        }
+    }
+
+    fn finish(mut self, code: Code) {
+        self.complete(code);
+    }
+
+    fn complete(&mut self, code: Code) {
+        if !self.done {
+            self.done = true;
+            let seconds = self.start.elapsed().as_secs_f64();
+            self.registry
+                .finish(&self.service, &self.method, code, seconds);
+        }
+    }
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        self.complete(Code::Cancelled);
+    }
+}
+
+/// Response future of [`Prometheus`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    call: Option<Call>,
+}
+
+impl<F, E, ResBody> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = Result<Response<PrometheusBody<ResBody>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+        let call = this.call.take().expect("polled after completion");
+
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                call.finish(Code::Unknown);
+                return Poll::Ready(Err(error));
+            }
+        };
+
+        // Trailers-only responses carry their status in the headers.
+        if response.headers().contains_key("grpc-status") {
+            call.finish(status_code(response.headers()));
+            return Poll::Ready(Ok(
+                response.map(|inner| PrometheusBody { inner, call: None })
+            ));
+        }
+
+        Poll::Ready(Ok(response.map(|inner| PrometheusBody {
+            inner,
+            call: Some(call),
+        })))
+    }
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish_non_exhaustive()
+    }
+}
+
+/// Response body of [`Prometheus`], completing the call at its trailers.
+#[pin_project]
+pub struct PrometheusBody<B> {
+    #[pin]
+    inner: B,
+    call: Option<Call>,
+}
+
+impl<B> Body for PrometheusBody<B>
+where
+    B: Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let data = ready!(this.inner.poll_data(cx));
+        if let Some(Err(_)) = data {
+            if let Some(call) = this.call.take() {
+                call.finish(Code::Unknown);
+            }
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx));
+        if let Some(call) = this.call.take() {
+            let code = match &trailers {
+                Ok(Some(trailers)) => status_code(trailers),
+                _ => Code::Unknown,
+            };
+            call.finish(code);
+        }
+        Poll::Ready(trailers)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B> fmt::Debug for PrometheusBody<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrometheusBody").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::{empty_body, BoxBody};
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    fn response(code: Code, trailers_only: bool) -> Response<BoxBody> {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", (code as i32).into());
+        if trailers_only {
+            let mut response = Response::new(empty_body());
+            *response.headers_mut() = trailers;
+            return response;
+        }
+        Response::new(crate::body::boxed(TrailersBody(Some(trailers))))
+    }
+
+    /// A body of only trailers.
+    struct TrailersBody(Option<HeaderMap>);
+
+    impl Body for TrailersBody {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_data(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            Poll::Ready(None)
+        }
+
+        fn poll_trailers(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            Poll::Ready(Ok(self.0.take()))
+        }
+    }
+
+    #[tokio::test]
+    async fn counts_calls() {
+        let registry = Registry::with_buckets(vec![60.0]);
+        let service = PrometheusLayer::new(&registry).layer(tower::service_fn(
+            |request: Request<()>| async move {
+                let code = match request.uri().path() {
+                    "/a.A/Ok" => Code::Ok,
+                    _ => Code::NotFound,
+                };
+                Ok::<_, Infallible>(response(code, code != Code::Ok))
+            },
+        ));
+
+        let call = |path: &'static str| {
+            let request = Request::builder().uri(path).body(()).unwrap();
+            service.clone().oneshot(request)
+        };
+        let mut ok = call("/a.A/Ok").await.unwrap();
+        assert!(registry
+            .encode()
+            .contains("grpc_server_in_flight{grpc_service=\"a.A\",grpc_method=\"Ok\"} 1\n"));
+        ok.body_mut().trailers().await.unwrap();
+        call("/a.A/Missing").await.unwrap();
+        drop(call("/a.A/Ok").await.unwrap());
+
+        let text = registry.encode();
+        let lines: Vec<_> = text
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.contains("_sum"))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "grpc_server_started_total{grpc_service=\"a.A\",grpc_method=\"Missing\"} 1",
+                "grpc_server_started_total{grpc_service=\"a.A\",grpc_method=\"Ok\"} 2",
+                "grpc_server_handled_total{grpc_service=\"a.A\",grpc_method=\"Missing\",grpc_code=\"NotFound\"} 1",
+                "grpc_server_handled_total{grpc_service=\"a.A\",grpc_method=\"Ok\",grpc_code=\"Ok\"} 1",
+                "grpc_server_handled_total{grpc_service=\"a.A\",grpc_method=\"Ok\",grpc_code=\"Cancelled\"} 1",
+                "grpc_server_in_flight{grpc_service=\"a.A\",grpc_method=\"Missing\"} 0",
+                "grpc_server_in_flight{grpc_service=\"a.A\",grpc_method=\"Ok\"} 0",
+                "grpc_server_handling_seconds_bucket{grpc_service=\"a.A\",grpc_method=\"Missing\",grpc_code=\"NotFound\",le=\"60\"} 1",
+                "grpc_server_handling_seconds_bucket{grpc_service=\"a.A\",grpc_method=\"Missing\",grpc_code=\"NotFound\",le=\"+Inf\"} 1",
+                "grpc_server_handling_seconds_count{grpc_service=\"a.A\",grpc_method=\"Missing\",grpc_code=\"NotFound\"} 1",
+                "grpc_server_handling_seconds_bucket{grpc_service=\"a.A\",grpc_method=\"Ok\",grpc_code=\"Ok\",le=\"60\"} 1",
+                "grpc_server_handling_seconds_bucket{grpc_service=\"a.A\",grpc_method=\"Ok\",grpc_code=\"Ok\",le=\"+Inf\"} 1",
+                "grpc_server_handling_seconds_count{grpc_service=\"a.A\",grpc_method=\"Ok\",grpc_code=\"Ok\"} 1",
+                "grpc_server_handling_seconds_bucket{grpc_service=\"a.A\",grpc_method=\"Ok\",grpc_code=\"Cancelled\",le=\"60\"} 1",
+                "grpc_server_handling_seconds_bucket{grpc_service=\"a.A\",grpc_method=\"Ok\",grpc_code=\"Cancelled\",le=\"+Inf\"} 1",
+                "grpc_server_handling_seconds_count{grpc_service=\"a.A\",grpc_method=\"Ok\",grpc_code=\"Cancelled\"} 1",
+            ]
+        );
+    }
+}
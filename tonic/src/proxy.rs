@@ -0,0 +1,126 @@
+//! Forwarding of gRPC calls to upstream servers.
+//!
+//! A [`Proxy`] forwards the calls it serves to the server of a [`Channel`], as
+//! they are: with the path of their method, their metadata, and their
+//! length-prefixed messages, which it doesn't decode. Serving the methods that
+//! no service of a server serves, it makes the server a gateway to another:
+//!
+//! ```no_run
+//! # use tonic::{proxy::Proxy, transport::{Channel, Server}};
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let upstream = Channel::from_static("http://[::1]:50052").connect_lazy();
+//! Server::builder()
+//!     .fallback_service(Proxy::new(upstream))
+//!     .serve("[::1]:50051".parse()?)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The responses are forwarded as they are received, with the `grpc-status`
+//! of their calls in their trailers, and the upstream calls are cancelled
+//! with the calls they forward.
+
+use crate::{
+    body::{boxed, BoxBody},
+    transport::{Body, Channel},
+    Status,
+};
+use http::{Request, Response};
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::ServiceExt;
+use tower_service::Service;
+
+/// A service forwarding the calls it serves to the server of a [`Channel`].
+///
+/// The calls whose upstream calls fail to be made, such as when the server is
+/// unavailable, fail with the status of the error.
+#[derive(Debug, Clone)]
+pub struct Proxy {
+    channel: Channel,
+}
+
+impl Proxy {
+    /// Forward calls to the server of `channel`.
+    pub fn new(channel: Channel) -> Self {
+        Self { channel }
+    }
+}
+
+impl Service<Request<Body>> for Proxy {
+    type Response = Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let (mut head, body) = req.into_parts();
+        // The extensions of the server are not those of the upstream call.
+        head.extensions = http::Extensions::new();
+        let req = Request::from_parts(head, boxed(body));
+
+        let channel = self.channel.clone();
+        Box::pin(async move {
+            match channel.oneshot(req).await {
+                Ok(res) => Ok(res.map(boxed)),
+                Err(err) => Ok(Status::from_error_generic(err).to_http()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{local, Server};
+    use http::HeaderMap;
+    use http_body::Body as _;
+
+    #[tokio::test]
+    async fn forwards_calls_with_their_trailers() {
+        let upstream = tower::service_fn(|req: Request<Body>| async move {
+            let path = req.uri().path().to_owned();
+            let tag = req.headers()["x-tag"].clone();
+            let message = hyper::body::to_bytes(req.into_body()).await.unwrap();
+
+            let (mut sender, body) = Body::channel();
+            tokio::spawn(async move {
+                sender.send_data(message).await.unwrap();
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", "0".parse().unwrap());
+                trailers.insert("x-path", path.parse().unwrap());
+                trailers.insert("x-tag", tag);
+                sender.send_trailers(trailers).await.unwrap();
+            });
+            Ok::<_, Infallible>(Response::new(boxed(body)))
+        });
+        let (channel, incoming) = local::pair();
+        tokio::spawn(
+            Server::builder()
+                .fallback_service(upstream)
+                .serve_with_incoming(incoming),
+        );
+
+        let req = Request::post("/test.Echo/Call")
+            .header("content-type", "application/grpc")
+            .header("x-tag", "1")
+            .body(Body::from("message"))
+            .unwrap();
+        let res = Proxy::new(channel).oneshot(req).await.unwrap();
+        let mut body = res.into_body();
+
+        assert_eq!(body.data().await.unwrap().unwrap(), "message");
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers["grpc-status"], "0");
+        assert_eq!(trailers["x-path"], "/test.Echo/Call");
+        assert_eq!(trailers["x-tag"], "1");
+    }
+}
@@ -1,3 +1,5 @@
+use crate::client::CallOptions;
+use crate::codec::CompressionEncoding;
 use crate::metadata::{MetadataMap, MetadataValue};
 #[cfg(feature = "transport")]
 use crate::transport::server::TcpConnectInfo;
@@ -247,6 +249,13 @@ impl<T> Request<T> {
                 .and_then(|i| i.get_ref().remote_addr())
         });
 
+        #[cfg(feature = "http3")]
+        let addr = addr.or_else(|| {
+            self.extensions()
+                .get::<crate::transport::server::Http3ConnectInfo>()
+                .map(|i| i.remote_addr())
+        });
+
         addr
     }
 
@@ -264,6 +273,20 @@ impl<T> Request<T> {
             .and_then(|i| i.peer_certs())
     }
 
+    /// Get the server name the connected client asked for with the TLS SNI
+    /// extension.
+    ///
+    /// Like [`peer_certs`](Self::peer_certs), this only returns `Some` on
+    /// the server side of the `transport` server with TLS enabled
+    /// connections.
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    pub fn server_name(&self) -> Option<&str> {
+        self.extensions()
+            .get::<TlsConnectInfo<TcpConnectInfo>>()
+            .and_then(|i| i.server_name())
+    }
+
     /// Set the max duration the request is allowed to take.
     ///
     /// Requires the server to support the `grpc-timeout` metadata, which Tonic does.
@@ -297,6 +320,83 @@ impl<T> Request<T> {
             .insert(crate::metadata::GRPC_TIMEOUT_HEADER, value);
     }
 
+    /// Sets whether the request waits for the channel to be ready.
+    ///
+    /// By default a request sent while the channel can't connect fails right
+    /// away with `UNAVAILABLE`. A wait-for-ready request instead waits for
+    /// the channel to connect, until its [timeout](Request::set_timeout)
+    /// expires. It still fails with `UNAVAILABLE` if the server can't be
+    /// sent its body again after a failed attempt.
+    ///
+    /// Channel retry and hedging policies take precedence over this.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use tonic::Request;
+    ///
+    /// let mut request = Request::new(());
+    /// request.set_wait_for_ready(true);
+    /// request.set_timeout(Duration::from_secs(10));
+    /// ```
+    pub fn set_wait_for_ready(&mut self, wait_for_ready: bool) {
+        if wait_for_ready {
+            self.extensions_mut().insert(WaitForReady);
+        } else {
+            self.extensions_mut().remove::<WaitForReady>();
+        }
+    }
+
+    /// Returns `true` if the request waits for the channel to be ready, see
+    /// [`Request::set_wait_for_ready`].
+    pub fn wait_for_ready(&self) -> bool {
+        self.extensions().get::<WaitForReady>().is_some()
+    }
+
+    /// Compress the messages of the request with `encoding`, overriding the
+    /// `send_compressed` setting of the client.
+    ///
+    /// The server has to accept `encoding` for the call to succeed.
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "gzip")] {
+    /// use tonic::{codec::CompressionEncoding, Request};
+    ///
+    /// let mut request = Request::new(());
+    /// request.set_compression(CompressionEncoding::Gzip);
+    /// # }
+    /// ```
+    pub fn set_compression(&mut self, encoding: CompressionEncoding) {
+        self.extensions_mut()
+            .insert(SendCompression(Some(encoding)));
+    }
+
+    /// Don't compress the messages of the request, even if the client
+    /// compresses those of its requests.
+    ///
+    /// Small messages don't gain from compression what it costs.
+    pub fn disable_compression(&mut self) {
+        self.extensions_mut().insert(SendCompression(None));
+    }
+
+    /// Sets the options of the call of the request, see [`CallOptions`].
+    pub fn set_options(&mut self, options: CallOptions) {
+        if let Some(timeout) = options.timeout {
+            self.set_timeout(timeout);
+        }
+        if let Some(wait_for_ready) = options.wait_for_ready {
+            self.set_wait_for_ready(wait_for_ready);
+        }
+        match options.compression {
+            Some(Some(encoding)) => self.set_compression(encoding),
+            Some(None) => self.disable_compression(),
+            None => {}
+        }
+        if let Some(authority) = options.authority {
+            self.extensions_mut().insert(CallAuthority(authority));
+        }
+        self.metadata_mut().merge(options.metadata);
+    }
+
     /// Returns a reference to the associated extensions.
     pub fn extensions(&self) -> &Extensions {
         &self.extensions
@@ -364,6 +464,15 @@ impl<T> IntoRequest<T> for Request<T> {
     }
 }
 
+impl<T> IntoRequest<T> for (T, CallOptions) {
+    fn into_request(self) -> Request<T> {
+        let (message, options) = self;
+        let mut request = Request::new(message);
+        request.set_options(options);
+        request
+    }
+}
+
 impl<T> IntoStreamingRequest for T
 where
     T: Stream + Send + 'static,
@@ -394,7 +503,21 @@ mod sealed {
     pub trait Sealed {}
 }
 
-fn duration_to_grpc_timeout(duration: Duration) -> String {
+/// Marks the requests set to wait for the channel to be ready.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WaitForReady;
+
+/// The compression of the messages of a request, overriding that of the
+/// client.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SendCompression(pub(crate) Option<CompressionEncoding>);
+
+/// The `:authority` of a request, overriding that of the endpoint it is sent
+/// to.
+#[derive(Debug, Clone)]
+pub(crate) struct CallAuthority(pub(crate) http::uri::Authority);
+
+pub(crate) fn duration_to_grpc_timeout(duration: Duration) -> String {
     fn try_format<T: Into<u128>>(
         duration: Duration,
         unit: char,
@@ -459,6 +582,20 @@ mod tests {
         assert!(http_request.headers().is_empty());
     }
 
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compression_overrides_the_client() {
+        let mut r = Request::new(1);
+        r.set_compression(CompressionEncoding::Gzip);
+        assert!(matches!(
+            r.extensions().get(),
+            Some(SendCompression(Some(CompressionEncoding::Gzip)))
+        ));
+
+        r.disable_compression();
+        assert!(matches!(r.extensions().get(), Some(SendCompression(None))));
+    }
+
     #[test]
     fn duration_to_grpc_timeout_less_than_second() {
         let timeout = Duration::from_millis(500);
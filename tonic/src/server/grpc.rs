@@ -1,10 +1,12 @@
 use crate::codec::compression::{
-    CompressionEncoding, EnabledCompressionEncodings, SingleMessageCompressionOverride,
+    CompressionConfig, CompressionEncoding, CompressionLevel, EnabledCompressionEncodings,
+    SingleMessageCompressionOverride,
 };
 use crate::{
     body::BoxBody,
     codec::{encode_server, Codec, Streaming},
     server::{ClientStreamingService, ServerStreamingService, StreamingService, UnaryService},
+    stats::CallStats,
     Code, Request, Status,
 };
 use http_body::Body;
@@ -35,6 +37,8 @@ pub struct Grpc<T> {
     accept_compression_encodings: EnabledCompressionEncodings,
     /// Which compression encodings might the server use for responses.
     send_compression_encodings: EnabledCompressionEncodings,
+    /// How the server compresses responses.
+    compression_config: CompressionConfig,
     /// Limits the maximum size of a decoded message.
     max_decoding_message_size: Option<usize>,
     /// Limits the maximum size of an encoded message.
@@ -51,6 +55,7 @@ where
             codec,
             accept_compression_encodings: EnabledCompressionEncodings::default(),
             send_compression_encodings: EnabledCompressionEncodings::default(),
+            compression_config: CompressionConfig::default(),
             max_decoding_message_size: None,
             max_encoding_message_size: None,
         }
@@ -119,8 +124,82 @@ where
         self
     }
 
+    /// Send the responses smaller than `bytes` uncompressed, even when
+    /// sending compressed responses.
+    ///
+    /// Compressing small messages costs more than it saves, and may even
+    /// make them larger. Defaults to `0`, compressing every response.
+    ///
+    /// # Example
+    ///
+    /// The most common way of using this is through a server generated by tonic-build:
+    ///
+    /// ```rust
+    /// # enum CompressionEncoding { Gzip }
+    /// # struct Svc;
+    /// # struct ExampleServer<T>(T);
+    /// # impl<T> ExampleServer<T> {
+    /// #     fn new(svc: T) -> Self { Self(svc) }
+    /// #     fn send_compressed(self, _: CompressionEncoding) -> Self { self }
+    /// #     fn send_compressed_min_size(self, _: usize) -> Self { self }
+    /// # }
+    /// # #[tonic::async_trait]
+    /// # trait Example {}
+    ///
+    /// #[tonic::async_trait]
+    /// impl Example for Svc {
+    ///     // ...
+    /// }
+    ///
+    /// let service = ExampleServer::new(Svc)
+    ///     .send_compressed(CompressionEncoding::Gzip)
+    ///     .send_compressed_min_size(1024);
+    /// ```
+    pub fn send_compressed_min_size(mut self, bytes: usize) -> Self {
+        self.compression_config.min_size = bytes;
+        self
+    }
+
+    /// Compress responses at the given level, instead of the default level
+    /// of their encoding.
+    ///
+    /// # Example
+    ///
+    /// The most common way of using this is through a server generated by tonic-build:
+    ///
+    /// ```rust
+    /// # enum CompressionEncoding { Gzip }
+    /// # use tonic::codec::CompressionLevel;
+    /// # struct Svc;
+    /// # struct ExampleServer<T>(T);
+    /// # impl<T> ExampleServer<T> {
+    /// #     fn new(svc: T) -> Self { Self(svc) }
+    /// #     fn send_compressed(self, _: CompressionEncoding) -> Self { self }
+    /// #     fn send_compression_level(self, _: CompressionLevel) -> Self { self }
+    /// # }
+    /// # #[tonic::async_trait]
+    /// # trait Example {}
+    ///
+    /// #[tonic::async_trait]
+    /// impl Example for Svc {
+    ///     // ...
+    /// }
+    ///
+    /// let service = ExampleServer::new(Svc)
+    ///     .send_compressed(CompressionEncoding::Gzip)
+    ///     .send_compression_level(CompressionLevel::Best);
+    /// ```
+    pub fn send_compression_level(mut self, level: CompressionLevel) -> Self {
+        self.compression_config.level = level;
+        self
+    }
+
     /// Limits the maximum size of a decoded message.
     ///
+    /// Compressed messages are limited both before and after they are
+    /// decompressed, failing with [`Code::ResourceExhausted`] if they expand
+    /// past the limit.
+    ///
     /// # Example
     ///
     /// The most common way of using this is through a server generated by tonic-build:
@@ -187,13 +266,11 @@ where
     ) -> Self {
         let mut this = self;
 
-        for &encoding in CompressionEncoding::encodings() {
-            if accept_encodings.is_enabled(encoding) {
-                this = this.accept_compressed(encoding);
-            }
-            if send_encodings.is_enabled(encoding) {
-                this = this.send_compressed(encoding);
-            }
+        for encoding in accept_encodings.iter() {
+            this = this.accept_compressed(encoding);
+        }
+        for encoding in send_encodings.iter() {
+            this = this.send_compressed(encoding);
         }
 
         this
@@ -232,6 +309,7 @@ where
             req.headers(),
             self.send_compression_encodings,
         );
+        let stats = CallStats::of(req.extensions());
 
         let request = match self.map_request_unary(req).await {
             Ok(r) => r,
@@ -241,6 +319,7 @@ where
                     accept_encoding,
                     SingleMessageCompressionOverride::default(),
                     self.max_encoding_message_size,
+                    None,
                 );
             }
         };
@@ -257,6 +336,7 @@ where
             accept_encoding,
             compression_override,
             self.max_encoding_message_size,
+            stats,
         )
     }
 
@@ -276,6 +356,7 @@ where
             req.headers(),
             self.send_compression_encodings,
         );
+        let stats = CallStats::of(req.extensions());
 
         let request = match self.map_request_unary(req).await {
             Ok(r) => r,
@@ -285,6 +366,7 @@ where
                     accept_encoding,
                     SingleMessageCompressionOverride::default(),
                     self.max_encoding_message_size,
+                    None,
                 );
             }
         };
@@ -298,6 +380,7 @@ where
             // the items themselves
             SingleMessageCompressionOverride::default(),
             self.max_encoding_message_size,
+            stats,
         )
     }
 
@@ -316,6 +399,7 @@ where
             req.headers(),
             self.send_compression_encodings,
         );
+        let stats = CallStats::of(req.extensions());
 
         let request = t!(self.map_request_streaming(req));
 
@@ -331,6 +415,7 @@ where
             accept_encoding,
             compression_override,
             self.max_encoding_message_size,
+            stats,
         )
     }
 
@@ -341,7 +426,7 @@ where
         req: http::Request<B>,
     ) -> http::Response<BoxBody>
     where
-        S: StreamingService<T::Decode, Response = T::Encode> + Send,
+        S: StreamingService<T::Decode, Response = T::Encode>,
         S::ResponseStream: Send + 'static,
         B: Body + Send + 'static,
         B::Error: Into<crate::Error> + Send,
@@ -350,6 +435,7 @@ where
             req.headers(),
             self.send_compression_encodings,
         );
+        let stats = CallStats::of(req.extensions());
 
         let request = t!(self.map_request_streaming(req));
 
@@ -360,6 +446,7 @@ where
             accept_encoding,
             SingleMessageCompressionOverride::default(),
             self.max_encoding_message_size,
+            stats,
         )
     }
 
@@ -371,8 +458,10 @@ where
         B: Body + Send + 'static,
         B::Error: Into<crate::Error> + Send,
     {
+        self.accept_content_type(&request)?;
         let request_compression_encoding = self.request_encoding_if_supported(&request)?;
 
+        let stats = CallStats::of(request.extensions());
         let (parts, body) = request.into_parts();
 
         let mut stream = pin!(Streaming::new_request(
@@ -380,7 +469,8 @@ where
             body,
             request_compression_encoding,
             self.max_decoding_message_size,
-        ));
+        )
+        .with_stats(stats));
 
         let message = stream
             .try_next()
@@ -404,8 +494,10 @@ where
         B: Body + Send + 'static,
         B::Error: Into<crate::Error> + Send,
     {
+        self.accept_content_type(&request)?;
         let encoding = self.request_encoding_if_supported(&request)?;
 
+        let stats = CallStats::of(request.extensions());
         let request = request.map(|body| {
             Streaming::new_request(
                 self.codec.decoder(),
@@ -413,6 +505,7 @@ where
                 encoding,
                 self.max_decoding_message_size,
             )
+            .with_stats(stats)
         });
 
         Ok(Request::from_http(request))
@@ -424,6 +517,7 @@ where
         accept_encoding: Option<CompressionEncoding>,
         compression_override: SingleMessageCompressionOverride,
         max_message_size: Option<usize>,
+        stats: Option<CallStats>,
     ) -> http::Response<BoxBody>
     where
         B: Stream<Item = Result<T::Encode, Status>> + Send + 'static,
@@ -438,10 +532,9 @@ where
         // Set the content type
         parts.headers.insert(
             http::header::CONTENT_TYPE,
-            http::header::HeaderValue::from_static("application/grpc"),
+            http::header::HeaderValue::from_static(self.codec.content_type()),
         );
 
-        #[cfg(any(feature = "gzip", feature = "zstd"))]
         if let Some(encoding) = accept_encoding {
             // Set the content encoding
             parts.headers.insert(
@@ -455,12 +548,30 @@ where
             body,
             accept_encoding,
             compression_override,
+            self.compression_config,
             max_message_size,
+            stats,
         );
 
         http::Response::from_parts(parts, BoxBody::new(body))
     }
 
+    fn accept_content_type<B>(&mut self, request: &http::Request<B>) -> Result<(), Status> {
+        let content_type = request
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("application/grpc");
+        if self.codec.accept_content_type(content_type) {
+            Ok(())
+        } else {
+            Err(Status::internal(format!(
+                "Content-Type '{}' is not supported",
+                content_type
+            )))
+        }
+    }
+
     fn request_encoding_if_supported<B>(
         &self,
         request: &http::Request<B>,
@@ -488,6 +599,8 @@ impl<T: fmt::Debug> fmt::Debug for Grpc<T> {
             &self.send_compression_encodings,
         );
 
+        f.field("compression_config", &self.compression_config);
+
         f.finish()
     }
 }
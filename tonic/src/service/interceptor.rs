@@ -1,11 +1,16 @@
 //! gRPC interceptors which are a kind of middleware.
 //!
-//! See [`Interceptor`] for more details.
+//! See [`Interceptor`] for more details, [`AsyncInterceptor`] for
+//! interceptors that need to wait on something, and [`ResponseInterceptor`]
+//! for interceptors of the outcome of calls. Each message of streaming calls
+//! can be intercepted with a [`StreamInterceptor`](super::StreamInterceptor).
 
 use crate::{
     body::{boxed, BoxBody},
+    metadata::MetadataMap,
     request::SanitizeHeaders,
-    Status,
+    status::{find_error_code, infer_grpc_status},
+    Code, Status,
 };
 use bytes::Bytes;
 use pin_project::pin_project;
@@ -13,7 +18,7 @@ use std::{
     fmt,
     future::Future,
     pin::Pin,
-    task::{Context, Poll},
+    task::{ready, Context, Poll},
 };
 use tower_layer::Layer;
 use tower_service::Service;
@@ -40,7 +45,43 @@ use tower_service::Service;
 /// tower-http's [`Trace`](https://docs.rs/tower-http/latest/tower_http/trace/index.html)
 /// middleware supports gRPC out of the box.
 ///
+/// On the server side, the request also carries what is known of the connection it came on:
+/// its [`remote_addr`] and [`local_addr`], and with TLS the [`peer_certs`] of the client and the
+/// [`server_name`] it asked for, read from the [`TcpConnectInfo`] and [`TlsConnectInfo`]
+/// extensions. An interceptor can turn those into typed extensions of its own, which the
+/// handlers of the calls then find in their requests:
+///
+/// ```
+/// use std::net::SocketAddr;
+/// use tonic::{Request, Status};
+///
+/// /// The client of a call.
+/// #[derive(Clone)]
+/// struct Client {
+///     addr: SocketAddr,
+/// }
+///
+/// fn identify(mut request: Request<()>) -> Result<Request<()>, Status> {
+///     let addr = request
+///         .remote_addr()
+///         .ok_or_else(|| Status::unauthenticated("unknown client"))?;
+///     request.extensions_mut().insert(Client { addr });
+///     Ok(request)
+/// }
+///
+/// // In a handler:
+/// fn client<T>(request: &Request<T>) -> Option<&Client> {
+///     request.extensions().get::<Client>()
+/// }
+/// ```
+///
 /// [tower]: https://crates.io/crates/tower
+/// [`remote_addr`]: crate::Request::remote_addr
+/// [`local_addr`]: crate::Request::local_addr
+/// [`peer_certs`]: crate::Request::peer_certs
+/// [`server_name`]: crate::Request::server_name
+/// [`TcpConnectInfo`]: crate::transport::server::TcpConnectInfo
+/// [`TlsConnectInfo`]: crate::transport::server::TlsConnectInfo
 /// [example]: https://github.com/hyperium/tonic/tree/master/examples/src/interceptor
 /// [tower-example]: https://github.com/hyperium/tonic/tree/master/examples/src/tower
 pub trait Interceptor {
@@ -172,6 +213,238 @@ where
     const NAME: &'static str = S::NAME;
 }
 
+/// An asynchronous gRPC interceptor.
+///
+/// Like an [`Interceptor`], it can inspect and change the metadata and
+/// extensions of requests, or cancel them with a `Status`, but it does so
+/// from a future. This lets it fetch tokens, check rate limits against a
+/// remote store or ask an authorization service before the request is
+/// sent.
+///
+/// Any function that satisfies the bound
+/// `FnMut(Request<()>) -> impl Future<Output = Result<Request<()>, Status>>`
+/// can be used as an `AsyncInterceptor`:
+///
+/// ```
+/// use tonic::{service::async_interceptor, Request, Status};
+///
+/// async fn fetch_token() -> Result<String, Status> {
+///     Ok("Bearer secret".to_string())
+/// }
+///
+/// let layer = async_interceptor(|mut request: Request<()>| async move {
+///     let token = fetch_token().await?;
+///     let token = token.parse().map_err(|_| Status::internal("invalid token"))?;
+///     request.metadata_mut().insert("authorization", token);
+///     Ok(request)
+/// });
+/// ```
+///
+/// The service wrapped must be `Clone`: each request is sent to a clone of
+/// the service once the interceptor is done with it.
+pub trait AsyncInterceptor {
+    /// The future intercepting a request.
+    type Future: Future<Output = Result<crate::Request<()>, Status>>;
+
+    /// Intercept a request before it is sent, optionally cancelling it.
+    fn call(&mut self, request: crate::Request<()>) -> Self::Future;
+}
+
+impl<F, U> AsyncInterceptor for F
+where
+    F: FnMut(crate::Request<()>) -> U,
+    U: Future<Output = Result<crate::Request<()>, Status>>,
+{
+    type Future = U;
+
+    fn call(&mut self, request: crate::Request<()>) -> Self::Future {
+        self(request)
+    }
+}
+
+/// Create a new asynchronous interceptor layer.
+///
+/// See [`AsyncInterceptor`] for more details.
+pub fn async_interceptor<F>(f: F) -> AsyncInterceptorLayer<F>
+where
+    F: AsyncInterceptor,
+{
+    AsyncInterceptorLayer { f }
+}
+
+/// An asynchronous gRPC interceptor that can be used as a [`Layer`],
+/// created by calling [`async_interceptor`].
+///
+/// See [`AsyncInterceptor`] for more details.
+#[derive(Debug, Clone, Copy)]
+pub struct AsyncInterceptorLayer<F> {
+    f: F,
+}
+
+impl<S, F> Layer<S> for AsyncInterceptorLayer<F>
+where
+    F: AsyncInterceptor + Clone,
+{
+    type Service = AsyncInterceptedService<S, F>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        AsyncInterceptedService::new(service, self.f.clone())
+    }
+}
+
+/// A service wrapped in an asynchronous interceptor middleware.
+///
+/// See [`AsyncInterceptor`] for more details.
+#[derive(Clone, Copy)]
+pub struct AsyncInterceptedService<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F> AsyncInterceptedService<S, F> {
+    /// Create a new `AsyncInterceptedService` that wraps `S` and intercepts
+    /// each request with the function `F`.
+    pub fn new(service: S, f: F) -> Self
+    where
+        F: AsyncInterceptor,
+    {
+        Self { inner: service, f }
+    }
+}
+
+impl<S, F> fmt::Debug for AsyncInterceptedService<S, F>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncInterceptedService")
+            .field("inner", &self.inner)
+            .field("f", &format_args!("{}", std::any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S, F, ReqBody, ResBody> Service<http::Request<ReqBody>> for AsyncInterceptedService<S, F>
+where
+    F: AsyncInterceptor,
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>> + Clone,
+    S::Error: Into<crate::Error>,
+    ResBody: Default + http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = AsyncResponseFuture<S, F::Future, ReqBody>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        // The service that was polled ready goes with the request, leaving a
+        // clone to be polled for the next one.
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+
+        // As with `InterceptedService`, the interceptor only sees the
+        // metadata and extensions of the request.
+        let uri = req.uri().clone();
+        let method = req.method().clone();
+        let version = req.version();
+        let (metadata, extensions, msg) = crate::Request::from_http(req).into_parts();
+        let intercepting = self
+            .f
+            .call(crate::Request::from_parts(metadata, extensions, ()));
+
+        AsyncResponseFuture {
+            state: AsyncState::Intercepting {
+                future: intercepting,
+                pending: Some((inner, uri, method, version, msg)),
+            },
+        }
+    }
+}
+
+// required to use `AsyncInterceptedService` with `Router`
+impl<S, F> crate::server::NamedService for AsyncInterceptedService<S, F>
+where
+    S: crate::server::NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+/// The service and parts of a request waiting on an [`AsyncInterceptor`].
+type Pending<S, B> = (S, http::Uri, http::Method, http::Version, B);
+
+/// Response future for [`AsyncInterceptedService`].
+#[pin_project]
+pub struct AsyncResponseFuture<S, I, B>
+where
+    S: Service<http::Request<B>>,
+{
+    #[pin]
+    state: AsyncState<S, I, B>,
+}
+
+#[pin_project(project = AsyncStateProj)]
+enum AsyncState<S, I, B>
+where
+    S: Service<http::Request<B>>,
+{
+    Intercepting {
+        #[pin]
+        future: I,
+        pending: Option<Pending<S, B>>,
+    },
+    Calling(#[pin] ResponseFuture<S::Future>),
+}
+
+impl<S, I, B, ResBody> Future for AsyncResponseFuture<S, I, B>
+where
+    S: Service<http::Request<B>, Response = http::Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+    I: Future<Output = Result<crate::Request<()>, Status>>,
+    ResBody: Default + http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Output = Result<http::Response<BoxBody>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.project().state;
+        loop {
+            let calling = match state.as_mut().project() {
+                AsyncStateProj::Intercepting { future, pending } => {
+                    let result = ready!(future.poll(cx));
+                    let (mut inner, uri, method, version, msg) =
+                        pending.take().expect("polled after completion");
+                    match result {
+                        Ok(req) => {
+                            let (metadata, extensions, _) = req.into_parts();
+                            let req = crate::Request::from_parts(metadata, extensions, msg);
+                            let req = req.into_http(uri, method, version, SanitizeHeaders::No);
+                            ResponseFuture::future(inner.call(req))
+                        }
+                        Err(status) => ResponseFuture::status(status),
+                    }
+                }
+                AsyncStateProj::Calling(future) => return future.poll(cx),
+            };
+            state.set(AsyncState::Calling(calling));
+        }
+    }
+}
+
+impl<S, I, B> fmt::Debug for AsyncResponseFuture<S, I, B>
+where
+    S: Service<http::Request<B>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncResponseFuture")
+            .finish_non_exhaustive()
+    }
+}
+
 /// Response future for [`InterceptedService`].
 #[pin_project]
 #[derive(Debug)]
@@ -228,6 +501,287 @@ where
     }
 }
 
+/// A gRPC interceptor of responses.
+///
+/// A response interceptor is told of the outcome of each call once it is
+/// known: with the metadata of the response headers and the status of the
+/// call, whose own metadata are the trailers of the response. Streaming
+/// calls are intercepted once their trailers arrive. This lets clients
+/// refresh their credentials once a call fails with `UNAUTHENTICATED`, or
+/// log the errors of every call in one place.
+///
+/// Any function that satisfies the bound `FnMut(&MetadataMap, &Status)` can
+/// be used as a `ResponseInterceptor`:
+///
+/// ```
+/// use tonic::{metadata::MetadataMap, service::response_interceptor, Code, Status};
+///
+/// let layer = response_interceptor(|_: &MetadataMap, status: &Status| {
+///     if status.code() != Code::Ok {
+///         eprintln!("call failed: {}", status);
+///     }
+/// });
+/// ```
+///
+/// The layer wraps clients, such as a `Channel` before it is passed to a
+/// generated client, as well as servers, through `Server::layer`. Each call
+/// is intercepted by a clone of the interceptor, exactly once: a call whose
+/// response is dropped before its end is intercepted as `Cancelled`, and
+/// one that failed to be sent with the status of the error.
+pub trait ResponseInterceptor {
+    /// Intercept the outcome of a call.
+    fn call(&mut self, headers: &MetadataMap, status: &Status);
+}
+
+impl<F> ResponseInterceptor for F
+where
+    F: FnMut(&MetadataMap, &Status),
+{
+    fn call(&mut self, headers: &MetadataMap, status: &Status) {
+        self(headers, status)
+    }
+}
+
+/// Create a new response interceptor layer.
+///
+/// See [`ResponseInterceptor`] for more details.
+pub fn response_interceptor<F>(f: F) -> ResponseInterceptorLayer<F>
+where
+    F: ResponseInterceptor,
+{
+    ResponseInterceptorLayer { f }
+}
+
+/// A gRPC response interceptor that can be used as a [`Layer`], created by
+/// calling [`response_interceptor`].
+///
+/// See [`ResponseInterceptor`] for more details.
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseInterceptorLayer<F> {
+    f: F,
+}
+
+impl<S, F> Layer<S> for ResponseInterceptorLayer<F>
+where
+    F: ResponseInterceptor + Clone,
+{
+    type Service = ResponseInterceptedService<S, F>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        ResponseInterceptedService::new(service, self.f.clone())
+    }
+}
+
+/// A service wrapped in a response interceptor middleware.
+///
+/// See [`ResponseInterceptor`] for more details.
+#[derive(Clone, Copy)]
+pub struct ResponseInterceptedService<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F> ResponseInterceptedService<S, F> {
+    /// Create a new `ResponseInterceptedService` that wraps `S` and
+    /// intercepts the outcome of each call with the function `F`.
+    pub fn new(service: S, f: F) -> Self
+    where
+        F: ResponseInterceptor,
+    {
+        Self { inner: service, f }
+    }
+}
+
+impl<S, F> fmt::Debug for ResponseInterceptedService<S, F>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseInterceptedService")
+            .field("inner", &self.inner)
+            .field("f", &format_args!("{}", std::any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S, F, ReqBody, ResBody> Service<http::Request<ReqBody>> for ResponseInterceptedService<S, F>
+where
+    F: ResponseInterceptor + Clone,
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+    ResBody: http_body::Body<Data = Bytes>,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Response = http::Response<ResponseInterceptedBody<ResBody, F>>;
+    type Error = crate::Error;
+    type Future = ResponseInterceptedFuture<S::Future, F>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        ResponseInterceptedFuture {
+            inner: self.inner.call(req),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+// required to use `ResponseInterceptedService` with `Router`
+impl<S, F> crate::server::NamedService for ResponseInterceptedService<S, F>
+where
+    S: crate::server::NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+/// Response future for [`ResponseInterceptedService`].
+#[pin_project]
+pub struct ResponseInterceptedFuture<U, F> {
+    #[pin]
+    inner: U,
+    f: Option<F>,
+}
+
+impl<U, F, E, B> Future for ResponseInterceptedFuture<U, F>
+where
+    U: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<crate::Error>,
+    F: ResponseInterceptor,
+{
+    type Output = Result<http::Response<ResponseInterceptedBody<B, F>>, crate::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx)).map_err(Into::into);
+        let mut f = this.f.take().expect("polled after completion");
+
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                let status = Status::new(find_error_code(&*error), error.to_string());
+                f.call(&MetadataMap::new(), &status);
+                return Poll::Ready(Err(error));
+            }
+        };
+
+        let headers = MetadataMap::from_headers(response.headers().clone());
+        // Trailers-only responses, and those that aren't gRPC responses at
+        // all, have their status in their headers.
+        let status =
+            Status::from_header_map(response.headers()).or_else(|| {
+                match infer_grpc_status(None, response.status()) {
+                    Err(Some(status)) => Some(status),
+                    _ => None,
+                }
+            });
+        if let Some(status) = status {
+            f.call(&headers, &status);
+            return Poll::Ready(Ok(response.map(|inner| ResponseInterceptedBody {
+                inner,
+                pending: None,
+            })));
+        }
+
+        Poll::Ready(Ok(response.map(|inner| ResponseInterceptedBody {
+            inner,
+            pending: Some((f, headers)),
+        })))
+    }
+}
+
+impl<U, F> fmt::Debug for ResponseInterceptedFuture<U, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseInterceptedFuture")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Response body for [`ResponseInterceptedService`], intercepting the call
+/// at its trailers.
+#[pin_project(PinnedDrop)]
+pub struct ResponseInterceptedBody<B, F: ResponseInterceptor> {
+    #[pin]
+    inner: B,
+    pending: Option<(F, MetadataMap)>,
+}
+
+impl<B, F: ResponseInterceptor> ResponseInterceptedBody<B, F> {
+    fn intercept(pending: &mut Option<(F, MetadataMap)>, status: Status) {
+        if let Some((mut f, headers)) = pending.take() {
+            f.call(&headers, &status);
+        }
+    }
+}
+
+impl<B, F> http_body::Body for ResponseInterceptedBody<B, F>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<crate::Error>,
+    F: ResponseInterceptor,
+{
+    type Data = Bytes;
+    type Error = crate::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        match ready!(this.inner.poll_data(cx)) {
+            Some(Err(error)) => {
+                let error = error.into();
+                let status = Status::new(find_error_code(&*error), error.to_string());
+                Self::intercept(this.pending, status);
+                Poll::Ready(Some(Err(error)))
+            }
+            data => Poll::Ready(data.map(|data| data.map_err(Into::into))),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let result = ready!(this.inner.poll_trailers(cx)).map_err(Into::into);
+        let status = match &result {
+            Ok(trailers) => trailers
+                .as_ref()
+                .and_then(Status::from_header_map)
+                .unwrap_or_else(|| Status::new(Code::Ok, "")),
+            Err(error) => Status::new(find_error_code(&**error), error.to_string()),
+        };
+        Self::intercept(this.pending, status);
+        Poll::Ready(result)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[pin_project::pinned_drop]
+impl<B, F: ResponseInterceptor> PinnedDrop for ResponseInterceptedBody<B, F> {
+    fn drop(self: Pin<&mut Self>) {
+        let status = Status::cancelled("response dropped before its end");
+        Self::intercept(self.project().pending, status);
+    }
+}
+
+impl<B, F: ResponseInterceptor> fmt::Debug for ResponseInterceptedBody<B, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseInterceptedBody")
+            .finish_non_exhaustive()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[allow(unused_imports)]
@@ -333,4 +887,174 @@ mod tests {
 
         svc.oneshot(request).await.unwrap();
     }
+
+    #[cfg(feature = "transport")]
+    #[tokio::test]
+    async fn sees_connection_info_and_inserts_extensions() {
+        use crate::transport::server::TcpConnectInfo;
+        use std::net::SocketAddr;
+
+        #[derive(Clone)]
+        struct Client(SocketAddr);
+
+        let svc = tower::service_fn(|request: http::Request<TestBody>| async move {
+            let client = request.extensions().get::<Client>().unwrap();
+            assert_eq!(client.0, "10.0.0.1:4000".parse().unwrap());
+
+            Ok::<_, Status>(http::Response::new(TestBody))
+        });
+
+        let svc = InterceptedService::new(svc, |mut request: crate::Request<()>| {
+            let addr = request.remote_addr().unwrap();
+            request.extensions_mut().insert(Client(addr));
+            Ok(request)
+        });
+
+        let mut request = http::Request::builder().body(TestBody).unwrap();
+        request.extensions_mut().insert(TcpConnectInfo {
+            local_addr: None,
+            remote_addr: Some("10.0.0.1:4000".parse().unwrap()),
+        });
+
+        svc.oneshot(request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn async_interceptor_changes_metadata() {
+        let svc = tower::service_fn(|request: http::Request<TestBody>| async move {
+            assert_eq!(request.headers()["authorization"], "Bearer token");
+
+            Ok::<_, Status>(http::Response::new(TestBody))
+        });
+
+        let svc = AsyncInterceptedService::new(svc, |mut request: crate::Request<()>| async move {
+            tokio::task::yield_now().await;
+            request
+                .metadata_mut()
+                .insert("authorization", "Bearer token".parse().unwrap());
+            Ok(request)
+        });
+
+        let request = http::Request::builder().body(TestBody).unwrap();
+        svc.oneshot(request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn async_interceptor_handles_status_as_response() {
+        let expected = Status::unauthenticated("no token").to_http();
+
+        let svc = tower::service_fn(|_: http::Request<TestBody>| async {
+            Ok::<_, Status>(http::Response::new(TestBody))
+        });
+
+        let svc = AsyncInterceptedService::new(svc, |_: crate::Request<()>| async {
+            Err(Status::unauthenticated("no token"))
+        });
+
+        let request = http::Request::builder().body(TestBody).unwrap();
+        let response = svc.oneshot(request).await.unwrap();
+
+        assert_eq!(expected.status(), response.status());
+        assert_eq!(expected.headers(), response.headers());
+    }
+
+    /// A body of only trailers, with the status `code`.
+    struct TrailersBody(Option<HeaderMap>);
+
+    impl TrailersBody {
+        fn new(code: crate::Code) -> Self {
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", (code as i32).into());
+            TrailersBody(Some(trailers))
+        }
+    }
+
+    impl http_body::Body for TrailersBody {
+        type Data = Bytes;
+        type Error = Status;
+
+        fn poll_data(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            Poll::Ready(None)
+        }
+
+        fn poll_trailers(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            Poll::Ready(Ok(self.0.take()))
+        }
+    }
+
+    type Intercepted = std::sync::Arc<std::sync::Mutex<Vec<(Option<String>, crate::Code)>>>;
+
+    fn recording(intercepted: &Intercepted) -> impl ResponseInterceptor + Clone {
+        let intercepted = intercepted.clone();
+        move |headers: &MetadataMap, status: &Status| {
+            let header = headers
+                .get("x-answer")
+                .map(|v| v.to_str().unwrap().to_string());
+            intercepted.lock().unwrap().push((header, status.code()));
+        }
+    }
+
+    #[tokio::test]
+    async fn response_interceptor_sees_trailers() {
+        use http_body::Body as _;
+
+        let svc = tower::service_fn(|_: http::Request<TestBody>| async {
+            let mut response = http::Response::new(TrailersBody::new(crate::Code::Unauthenticated));
+            response
+                .headers_mut()
+                .insert("x-answer", "42".parse().unwrap());
+            Ok::<_, Status>(response)
+        });
+        let intercepted = Intercepted::default();
+        let svc = ResponseInterceptedService::new(svc, recording(&intercepted));
+
+        let request = http::Request::builder().body(TestBody).unwrap();
+        let mut response = svc.oneshot(request).await.unwrap();
+        assert!(intercepted.lock().unwrap().is_empty());
+
+        assert!(response.body_mut().data().await.is_none());
+        response.body_mut().trailers().await.unwrap();
+        drop(response);
+        assert_eq!(
+            *intercepted.lock().unwrap(),
+            [(Some("42".to_string()), crate::Code::Unauthenticated)]
+        );
+    }
+
+    #[tokio::test]
+    async fn response_interceptor_sees_trailers_only_and_cancelled_responses() {
+        let svc = tower::service_fn(|request: http::Request<TestBody>| async move {
+            if request.uri().path() == "/a.A/Missing" {
+                return Ok::<_, Status>(Status::not_found("missing").to_http().map(|_| TestBody));
+            }
+            Ok(http::Response::new(TestBody))
+        });
+        let intercepted = Intercepted::default();
+        let svc = ResponseInterceptedService::new(svc, recording(&intercepted));
+
+        let request = http::Request::builder()
+            .uri("/a.A/Missing")
+            .body(TestBody)
+            .unwrap();
+        drop(svc.clone().oneshot(request).await.unwrap());
+        let request = http::Request::builder()
+            .uri("/a.A/Ok")
+            .body(TestBody)
+            .unwrap();
+        drop(svc.oneshot(request).await.unwrap());
+
+        assert_eq!(
+            *intercepted.lock().unwrap(),
+            [
+                (None, crate::Code::NotFound),
+                (None, crate::Code::Cancelled)
+            ]
+        );
+    }
 }
@@ -1,6 +1,12 @@
 //! Utilities for using Tower services with Tonic.
 
 pub mod interceptor;
+pub mod streaming;
 
 #[doc(inline)]
-pub use self::interceptor::{interceptor, Interceptor};
+pub use self::interceptor::{
+    async_interceptor, interceptor, response_interceptor, AsyncInterceptor, Interceptor,
+    ResponseInterceptor,
+};
+#[doc(inline)]
+pub use self::streaming::{stream_interceptor, StreamInterceptor};
@@ -0,0 +1,622 @@
+//! Interceptors of each message of streaming calls.
+//!
+//! See [`StreamInterceptor`] for more details.
+
+use crate::{
+    body::{boxed, BoxBody},
+    codec::HEADER_SIZE,
+    Status,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// An interceptor of each message sent and received on a call.
+///
+/// Where an [`Interceptor`](super::Interceptor) only sees the metadata of
+/// the initial request, a stream interceptor sees every message of the
+/// requests and responses of calls as they go through, however long the
+/// streams live: how many were sent, their size on the wire and their
+/// encoded bytes, which it may replace. Returning an error ends the stream
+/// with that status. This is what per-message quota accounting or content
+/// filtering of long-lived streams build on.
+///
+/// ```
+/// use std::sync::{
+///     atomic::{AtomicUsize, Ordering},
+///     Arc,
+/// };
+/// use tonic::{
+///     service::{stream_interceptor, streaming::Message, StreamInterceptor},
+///     Status,
+/// };
+///
+/// /// Allows each client to upload at most `limit` bytes.
+/// #[derive(Clone)]
+/// struct Quota {
+///     used: Arc<AtomicUsize>,
+///     limit: usize,
+/// }
+///
+/// impl StreamInterceptor for Quota {
+///     fn request_message(&mut self, message: &mut Message<'_>) -> Result<(), Status> {
+///         let used = self.used.fetch_add(message.size(), Ordering::Relaxed) + message.size();
+///         if used > self.limit {
+///             return Err(Status::resource_exhausted("upload quota exceeded"));
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let layer = stream_interceptor(Quota {
+///     used: Arc::default(),
+///     limit: 64 * 1024 * 1024,
+/// });
+/// ```
+///
+/// The layer wraps clients, such as a `Channel` before it is passed to a
+/// generated client, as well as servers, through `Server::layer`. The
+/// requests and the responses of each call are intercepted by two clones of
+/// the interceptor, so state to be shared by both directions, or by several
+/// calls, is to be kept behind an `Arc`.
+///
+/// Messages are seen as they are encoded for the wire, compressed if the
+/// call uses compression. An error intercepting a response ends it with the
+/// status in its trailers, while an error intercepting a request fails the
+/// stream of requests, which the receiving end sees as that status.
+pub trait StreamInterceptor {
+    /// Intercept a message of the requests of a call.
+    fn request_message(&mut self, message: &mut Message<'_>) -> Result<(), Status> {
+        let _ = message;
+        Ok(())
+    }
+
+    /// Intercept a message of the responses of a call.
+    fn response_message(&mut self, message: &mut Message<'_>) -> Result<(), Status> {
+        let _ = message;
+        Ok(())
+    }
+}
+
+/// A message of a call, seen by a [`StreamInterceptor`].
+#[derive(Debug)]
+pub struct Message<'a> {
+    path: &'a str,
+    index: u64,
+    compressed: bool,
+    data: Bytes,
+}
+
+impl<'a> Message<'a> {
+    /// The path of the method of the call, such as
+    /// `/greeter.Greeter/SayHello`.
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    /// The index of the message in its stream, starting at 0, which is also
+    /// the number of messages that came before it.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Whether the message is compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// The size of the message on the wire, without its 5 bytes prefix.
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The encoded bytes of the message.
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Replace the encoded bytes of the message, which must be compressed
+    /// if the message is.
+    pub fn set_data(&mut self, data: Bytes) {
+        self.data = data;
+    }
+}
+
+/// Create a new stream interceptor layer.
+///
+/// See [`StreamInterceptor`] for more details.
+pub fn stream_interceptor<F>(f: F) -> StreamInterceptorLayer<F>
+where
+    F: StreamInterceptor,
+{
+    StreamInterceptorLayer { f }
+}
+
+/// A stream interceptor that can be used as a [`Layer`], created by calling
+/// [`stream_interceptor`].
+///
+/// See [`StreamInterceptor`] for more details.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamInterceptorLayer<F> {
+    f: F,
+}
+
+impl<S, F> Layer<S> for StreamInterceptorLayer<F>
+where
+    F: StreamInterceptor + Clone,
+{
+    type Service = StreamInterceptedService<S, F>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        StreamInterceptedService::new(service, self.f.clone())
+    }
+}
+
+/// A service wrapped in a stream interceptor middleware.
+///
+/// See [`StreamInterceptor`] for more details.
+#[derive(Clone, Copy)]
+pub struct StreamInterceptedService<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F> StreamInterceptedService<S, F> {
+    /// Create a new `StreamInterceptedService` that wraps `S` and
+    /// intercepts each message of its calls with the function `F`.
+    pub fn new(service: S, f: F) -> Self
+    where
+        F: StreamInterceptor,
+    {
+        Self { inner: service, f }
+    }
+
+    fn intercept<B>(&self, request: http::Request<B>) -> http::Request<StreamInterceptedBody<B, F>>
+    where
+        F: Clone,
+    {
+        let path: Arc<str> = request.uri().path().into();
+        request
+            .map(|body| StreamInterceptedBody::new(body, self.f.clone(), path, Direction::Request))
+    }
+}
+
+impl<S, F> fmt::Debug for StreamInterceptedService<S, F>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamInterceptedService")
+            .field("inner", &self.inner)
+            .field("f", &format_args!("{}", std::any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S, F, ResBody> Service<http::Request<BoxBody>> for StreamInterceptedService<S, F>
+where
+    F: StreamInterceptor + Clone + Send + 'static,
+    S: Service<http::Request<BoxBody>, Response = http::Response<ResBody>>,
+{
+    type Response = http::Response<StreamInterceptedBody<ResBody, F>>;
+    type Error = S::Error;
+    type Future = StreamInterceptedFuture<S::Future, F>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let req = self.intercept(req);
+        let path = req.body().path.clone();
+        StreamInterceptedFuture {
+            inner: self.inner.call(req.map(boxed)),
+            f: Some((self.f.clone(), path)),
+        }
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<S, F, ResBody> Service<http::Request<hyper::Body>> for StreamInterceptedService<S, F>
+where
+    F: StreamInterceptor + Clone + Send + 'static,
+    S: Service<http::Request<hyper::Body>, Response = http::Response<ResBody>>,
+{
+    type Response = http::Response<StreamInterceptedBody<ResBody, F>>;
+    type Error = S::Error;
+    type Future = StreamInterceptedFuture<S::Future, F>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<hyper::Body>) -> Self::Future {
+        let req = self.intercept(req);
+        let path = req.body().path.clone();
+        // Clients don't send trailers, so the request doesn't lose any.
+        let req = req.map(|body| hyper::Body::wrap_stream(DataStream(body)));
+        StreamInterceptedFuture {
+            inner: self.inner.call(req),
+            f: Some((self.f.clone(), path)),
+        }
+    }
+}
+
+// required to use `StreamInterceptedService` with `Router`
+impl<S, F> crate::server::NamedService for StreamInterceptedService<S, F>
+where
+    S: crate::server::NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+/// Response future for [`StreamInterceptedService`].
+#[pin_project]
+pub struct StreamInterceptedFuture<U, F> {
+    #[pin]
+    inner: U,
+    f: Option<(F, Arc<str>)>,
+}
+
+impl<U, F, E, B> Future for StreamInterceptedFuture<U, F>
+where
+    U: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = Result<http::Response<StreamInterceptedBody<B, F>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx))?;
+        let (f, path) = this.f.take().expect("polled after completion");
+        Poll::Ready(Ok(response.map(|body| {
+            StreamInterceptedBody::new(body, f, path, Direction::Response)
+        })))
+    }
+}
+
+impl<U, F> fmt::Debug for StreamInterceptedFuture<U, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamInterceptedFuture")
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Request,
+    Response,
+}
+
+/// Body for [`StreamInterceptedService`], intercepting each of its messages.
+#[pin_project]
+pub struct StreamInterceptedBody<B, F> {
+    #[pin]
+    inner: B,
+    f: F,
+    path: Arc<str>,
+    direction: Direction,
+    buf: BytesMut,
+    index: u64,
+    ended: bool,
+    // Set once the interceptor failed the stream, with the trailers of
+    // the status of responses.
+    failed: Option<Option<http::HeaderMap>>,
+}
+
+impl<B, F> StreamInterceptedBody<B, F> {
+    fn new(inner: B, f: F, path: Arc<str>, direction: Direction) -> Self {
+        StreamInterceptedBody {
+            inner,
+            f,
+            path,
+            direction,
+            buf: BytesMut::new(),
+            index: 0,
+            ended: false,
+            failed: None,
+        }
+    }
+}
+
+/// Intercept the message at the front of `buf` if it is complete, returning
+/// it prefixed again.
+fn intercept<F: StreamInterceptor>(
+    buf: &mut BytesMut,
+    f: &mut F,
+    path: &str,
+    direction: Direction,
+    index: &mut u64,
+) -> Result<Option<Bytes>, Status> {
+    if buf.len() < HEADER_SIZE {
+        return Ok(None);
+    }
+    let len = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+    if buf.len() < HEADER_SIZE + len {
+        return Ok(None);
+    }
+
+    let compressed = buf[0] != 0;
+    let mut data = buf.split_to(HEADER_SIZE + len);
+    data.advance(HEADER_SIZE);
+    let mut message = Message {
+        path,
+        index: *index,
+        compressed,
+        data: data.freeze(),
+    };
+    *index += 1;
+    match direction {
+        Direction::Request => f.request_message(&mut message)?,
+        Direction::Response => f.response_message(&mut message)?,
+    }
+
+    let mut out = BytesMut::with_capacity(HEADER_SIZE + message.data.len());
+    out.put_u8(compressed as u8);
+    out.put_u32(message.data.len() as u32);
+    out.put(message.data);
+    Ok(Some(out.freeze()))
+}
+
+impl<B, F> http_body::Body for StreamInterceptedBody<B, F>
+where
+    B: http_body::Body,
+    B::Error: Into<crate::Error>,
+    F: StreamInterceptor,
+{
+    type Data = Bytes;
+    type Error = crate::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let mut this = self.project();
+        if this.failed.is_some() {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match intercept(this.buf, this.f, this.path, *this.direction, this.index) {
+                Ok(Some(data)) => return Poll::Ready(Some(Ok(data))),
+                Ok(None) => {}
+                Err(status) => {
+                    this.buf.clear();
+                    return match this.direction {
+                        Direction::Request => {
+                            *this.failed = Some(None);
+                            Poll::Ready(Some(Err(status.into())))
+                        }
+                        Direction::Response => {
+                            *this.failed = Some(status.to_header_map().ok());
+                            Poll::Ready(None)
+                        }
+                    };
+                }
+            }
+
+            if *this.ended {
+                // What is left is an incomplete message, for the other end
+                // to reject.
+                return Poll::Ready((!this.buf.is_empty()).then(|| Ok(this.buf.split().freeze())));
+            }
+
+            match ready!(this.inner.as_mut().poll_data(cx)) {
+                Some(Ok(mut data)) => {
+                    while data.has_remaining() {
+                        let chunk = data.chunk();
+                        let len = chunk.len();
+                        this.buf.extend_from_slice(chunk);
+                        data.advance(len);
+                    }
+                }
+                Some(Err(error)) => return Poll::Ready(Some(Err(error.into()))),
+                None => *this.ended = true,
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        if let Some(trailers) = this.failed {
+            return Poll::Ready(Ok(trailers.take()));
+        }
+        this.inner.poll_trailers(cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match &self.failed {
+            Some(trailers) => trailers.is_none(),
+            None => self.buf.is_empty() && self.inner.is_end_stream(),
+        }
+    }
+}
+
+impl<B, F> fmt::Debug for StreamInterceptedBody<B, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamInterceptedBody")
+            .field("path", &self.path)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The data of a request body, as a stream for `hyper::Body::wrap_stream`.
+#[cfg(feature = "transport")]
+#[pin_project]
+pub(crate) struct DataStream<B>(#[pin] pub(crate) B);
+
+#[cfg(feature = "transport")]
+impl<B> tokio_stream::Stream for DataStream<B>
+where
+    B: http_body::Body,
+{
+    type Item = Result<B::Data, B::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().0.poll_data(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+    use http_body::Body as _;
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+    use tower::ServiceExt;
+
+    /// A body of `chunks`, then of the trailers of an Ok status.
+    struct Chunks(VecDeque<Bytes>);
+
+    impl http_body::Body for Chunks {
+        type Data = Bytes;
+        type Error = Status;
+
+        fn poll_data(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            Poll::Ready(self.0.pop_front().map(Ok))
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+            Poll::Ready(Ok(Some(Status::new(Code::Ok, "").to_header_map()?)))
+        }
+    }
+
+    fn frame(message: &str) -> Vec<u8> {
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(message.as_bytes());
+        frame
+    }
+
+    async fn collect<B: http_body::Body<Data = Bytes> + Unpin>(
+        body: &mut B,
+    ) -> Result<Vec<u8>, B::Error> {
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data)
+    }
+
+    /// Uppercases requests, records the messages seen and lets at most two
+    /// responses through.
+    #[derive(Clone, Default)]
+    struct Recording(Arc<Mutex<Vec<Seen>>>);
+
+    /// The direction, index and size of a message.
+    type Seen = (&'static str, u64, usize);
+
+    impl StreamInterceptor for Recording {
+        fn request_message(&mut self, message: &mut Message<'_>) -> Result<(), Status> {
+            assert_eq!(message.path(), "/test.Echo/Stream");
+            if message.data() == "forbidden" {
+                return Err(Status::permission_denied("forbidden"));
+            }
+            self.0
+                .lock()
+                .unwrap()
+                .push(("request", message.index(), message.size()));
+            let data = message.data().to_ascii_uppercase();
+            message.set_data(data.into());
+            Ok(())
+        }
+
+        fn response_message(&mut self, message: &mut Message<'_>) -> Result<(), Status> {
+            if message.index() == 2 {
+                return Err(Status::resource_exhausted("too many responses"));
+            }
+            self.0
+                .lock()
+                .unwrap()
+                .push(("response", message.index(), message.size()));
+            Ok(())
+        }
+    }
+
+    /// A server echoing the data of requests.
+    fn echo(
+        recording: &Recording,
+    ) -> impl Service<
+        http::Request<BoxBody>,
+        Response = http::Response<StreamInterceptedBody<Chunks, Recording>>,
+        Error = Status,
+    > {
+        let echo = tower::service_fn(|mut request: http::Request<BoxBody>| async move {
+            let data = collect(request.body_mut()).await?;
+            Ok(http::Response::new(Chunks(VecDeque::from([data.into()]))))
+        });
+        StreamInterceptedService::new(echo, recording.clone())
+    }
+
+    #[tokio::test]
+    async fn intercepts_each_message() {
+        let recording = Recording::default();
+
+        // Messages are split across chunks, and chunks hold several.
+        let requests = [frame("a"), frame("bc"), frame("def")].concat();
+        let chunks = VecDeque::from([
+            Bytes::copy_from_slice(&requests[..3]),
+            Bytes::copy_from_slice(&requests[3..13]),
+            Bytes::copy_from_slice(&requests[13..]),
+        ]);
+        let request = http::Request::builder()
+            .uri("/test.Echo/Stream")
+            .body(boxed(Chunks(chunks)))
+            .unwrap();
+        let mut response = echo(&recording).oneshot(request).await.unwrap();
+
+        let data = collect(response.body_mut()).await.unwrap();
+        assert_eq!(data, [frame("A"), frame("BC")].concat());
+        let trailers = response.body_mut().trailers().await.unwrap().unwrap();
+        let status = Status::from_header_map(&trailers).unwrap();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert!(response.body().is_end_stream());
+
+        assert_eq!(
+            *recording.0.lock().unwrap(),
+            [
+                ("request", 0, 1),
+                ("request", 1, 2),
+                ("request", 2, 3),
+                ("response", 0, 1),
+                ("response", 1, 2),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_requests_with_status() {
+        let recording = Recording::default();
+        let requests = [frame("ok"), frame("forbidden")].concat();
+        let request = http::Request::builder()
+            .uri("/test.Echo/Stream")
+            .body(boxed(Chunks(VecDeque::from([requests.into()]))))
+            .unwrap();
+
+        let status = echo(&recording).oneshot(request).await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(*recording.0.lock().unwrap(), [("request", 0, 2)]);
+    }
+}
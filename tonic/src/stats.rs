@@ -0,0 +1,717 @@
+//! Hooks observing the connections and calls of clients and servers.
+//!
+//! A [`StatsHandler`] installed on an
+//! [`Endpoint`](crate::transport::Endpoint::stats_handler) or a
+//! [`Server`](crate::transport::Server::stats_handler) is told when
+//! connections are established and closed, and of each event of the calls
+//! made over them: their beginning, headers, messages, trailers and end.
+//! Metrics and tracing can be built on these events without wrapping the
+//! transport.
+//!
+//! Calls are identified by the [`RpcInfo`] passed to every event of the
+//! call, whose [`id`](RpcInfo::id) is unique within the process.
+//!
+//! [`Tracing`] is a handler opening a span per call, and a pair of
+//! handlers is a handler reporting to both.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//! use tonic::stats::{Payload, RpcInfo, StatsHandler};
+//!
+//! #[derive(Default)]
+//! struct BytesSent(AtomicU64);
+//!
+//! impl StatsHandler for BytesSent {
+//!     fn out_payload(&self, _: &RpcInfo, payload: &Payload) {
+//!         self.0.fetch_add(payload.wire_size() as u64, Ordering::Relaxed);
+//!     }
+//! }
+//! ```
+
+use crate::Code;
+use http::{uri::Authority, HeaderMap};
+use std::{
+    collections::HashMap,
+    fmt,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{field, Span};
+
+/// Callbacks for the events of connections and calls.
+///
+/// Every method does nothing by default. They are called from the tasks
+/// doing I/O, and should return quickly.
+///
+/// On clients, each attempt of a call is a call of its own, and a call that
+/// waits for a connection begins once one is ready.
+pub trait StatsHandler: Send + Sync + 'static {
+    /// Called once a connection is established.
+    fn conn_begin(&self, conn: &ConnInfo) {
+        let _ = conn;
+    }
+
+    /// Called once a connection is closed.
+    fn conn_end(&self, conn: &ConnInfo) {
+        let _ = conn;
+    }
+
+    /// Called when a call begins, before any of its other events.
+    fn rpc_begin(&self, rpc: &RpcInfo) {
+        let _ = rpc;
+    }
+
+    /// Called with the headers sent for a call: the request headers of
+    /// clients and the response headers of servers.
+    fn out_headers(&self, rpc: &RpcInfo, headers: &HeaderMap) {
+        let _ = (rpc, headers);
+    }
+
+    /// Called with the headers received for a call.
+    fn in_headers(&self, rpc: &RpcInfo, headers: &HeaderMap) {
+        let _ = (rpc, headers);
+    }
+
+    /// Called for each message sent, once it is encoded.
+    fn out_payload(&self, rpc: &RpcInfo, payload: &Payload) {
+        let _ = (rpc, payload);
+    }
+
+    /// Called for each message received, before it is decoded.
+    fn in_payload(&self, rpc: &RpcInfo, payload: &Payload) {
+        let _ = (rpc, payload);
+    }
+
+    /// Called with the trailers a server sends.
+    ///
+    /// Responses without messages may carry their trailers in their
+    /// headers, in which case there is no headers event.
+    fn out_trailers(&self, rpc: &RpcInfo, trailers: &HeaderMap) {
+        let _ = (rpc, trailers);
+    }
+
+    /// Called with the trailers a client receives, see
+    /// [`out_trailers`](StatsHandler::out_trailers).
+    fn in_trailers(&self, rpc: &RpcInfo, trailers: &HeaderMap) {
+        let _ = (rpc, trailers);
+    }
+
+    /// Called once a call ends, after all of its other events.
+    fn rpc_end(&self, rpc: &RpcInfo, end: &RpcEnd) {
+        let _ = (rpc, end);
+    }
+}
+
+impl<T: StatsHandler> StatsHandler for Arc<T> {
+    fn conn_begin(&self, conn: &ConnInfo) {
+        (**self).conn_begin(conn)
+    }
+
+    fn conn_end(&self, conn: &ConnInfo) {
+        (**self).conn_end(conn)
+    }
+
+    fn rpc_begin(&self, rpc: &RpcInfo) {
+        (**self).rpc_begin(rpc)
+    }
+
+    fn out_headers(&self, rpc: &RpcInfo, headers: &HeaderMap) {
+        (**self).out_headers(rpc, headers)
+    }
+
+    fn in_headers(&self, rpc: &RpcInfo, headers: &HeaderMap) {
+        (**self).in_headers(rpc, headers)
+    }
+
+    fn out_payload(&self, rpc: &RpcInfo, payload: &Payload) {
+        (**self).out_payload(rpc, payload)
+    }
+
+    fn in_payload(&self, rpc: &RpcInfo, payload: &Payload) {
+        (**self).in_payload(rpc, payload)
+    }
+
+    fn out_trailers(&self, rpc: &RpcInfo, trailers: &HeaderMap) {
+        (**self).out_trailers(rpc, trailers)
+    }
+
+    fn in_trailers(&self, rpc: &RpcInfo, trailers: &HeaderMap) {
+        (**self).in_trailers(rpc, trailers)
+    }
+
+    fn rpc_end(&self, rpc: &RpcInfo, end: &RpcEnd) {
+        (**self).rpc_end(rpc, end)
+    }
+}
+
+impl<A: StatsHandler, B: StatsHandler> StatsHandler for (A, B) {
+    fn conn_begin(&self, conn: &ConnInfo) {
+        self.0.conn_begin(conn);
+        self.1.conn_begin(conn);
+    }
+
+    fn conn_end(&self, conn: &ConnInfo) {
+        self.0.conn_end(conn);
+        self.1.conn_end(conn);
+    }
+
+    fn rpc_begin(&self, rpc: &RpcInfo) {
+        self.0.rpc_begin(rpc);
+        self.1.rpc_begin(rpc);
+    }
+
+    fn out_headers(&self, rpc: &RpcInfo, headers: &HeaderMap) {
+        self.0.out_headers(rpc, headers);
+        self.1.out_headers(rpc, headers);
+    }
+
+    fn in_headers(&self, rpc: &RpcInfo, headers: &HeaderMap) {
+        self.0.in_headers(rpc, headers);
+        self.1.in_headers(rpc, headers);
+    }
+
+    fn out_payload(&self, rpc: &RpcInfo, payload: &Payload) {
+        self.0.out_payload(rpc, payload);
+        self.1.out_payload(rpc, payload);
+    }
+
+    fn in_payload(&self, rpc: &RpcInfo, payload: &Payload) {
+        self.0.in_payload(rpc, payload);
+        self.1.in_payload(rpc, payload);
+    }
+
+    fn out_trailers(&self, rpc: &RpcInfo, trailers: &HeaderMap) {
+        self.0.out_trailers(rpc, trailers);
+        self.1.out_trailers(rpc, trailers);
+    }
+
+    fn in_trailers(&self, rpc: &RpcInfo, trailers: &HeaderMap) {
+        self.0.in_trailers(rpc, trailers);
+        self.1.in_trailers(rpc, trailers);
+    }
+
+    fn rpc_end(&self, rpc: &RpcInfo, end: &RpcEnd) {
+        self.0.rpc_end(rpc, end);
+        self.1.rpc_end(rpc, end);
+    }
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A connection of a client or a server.
+#[derive(Debug, Clone)]
+pub struct ConnInfo {
+    id: u64,
+    client: bool,
+    remote_addr: Option<SocketAddr>,
+}
+
+impl ConnInfo {
+    #[cfg_attr(not(feature = "transport"), allow(dead_code))]
+    pub(crate) fn new(client: bool, remote_addr: Option<SocketAddr>) -> Self {
+        ConnInfo {
+            id: next_id(),
+            client,
+            remote_addr,
+        }
+    }
+
+    /// The identifier of the connection, unique within the process.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Whether the connection is one of a client.
+    pub fn is_client(&self) -> bool {
+        self.client
+    }
+
+    /// The address of the peer, when connected over TCP.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+}
+
+/// A call of a client or a server.
+#[derive(Debug, Clone)]
+pub struct RpcInfo {
+    id: u64,
+    client: bool,
+    method: String,
+    authority: Option<String>,
+    remote_addr: Option<SocketAddr>,
+    parent: Span,
+    begin: Instant,
+}
+
+impl RpcInfo {
+    #[cfg_attr(not(feature = "transport"), allow(dead_code))]
+    pub(crate) fn new(client: bool, method: &str, remote_addr: Option<SocketAddr>) -> Self {
+        RpcInfo {
+            id: next_id(),
+            client,
+            method: method.to_string(),
+            authority: None,
+            remote_addr,
+            parent: Span::current(),
+            begin: Instant::now(),
+        }
+    }
+
+    #[cfg_attr(not(feature = "transport"), allow(dead_code))]
+    pub(crate) fn with_authority(mut self, authority: Option<&Authority>) -> Self {
+        self.authority = authority.map(|authority| authority.to_string());
+        self
+    }
+
+    #[cfg_attr(not(feature = "transport"), allow(dead_code))]
+    pub(crate) fn with_parent(mut self, parent: Span) -> Self {
+        self.parent = parent;
+        self
+    }
+
+    /// The identifier of the call, unique within the process.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Whether the call is one of a client.
+    pub fn is_client(&self) -> bool {
+        self.client
+    }
+
+    /// The path of the method called, such as `/greeter.Greeter/SayHello`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The authority of the request of the call, naming the server called.
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    /// The address of the client calling a server over TCP.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// The span current where the call was made, on clients, or where the
+    /// connection is served, on servers.
+    pub fn parent_span(&self) -> &Span {
+        &self.parent
+    }
+
+    /// When the call began.
+    pub fn begin_time(&self) -> Instant {
+        self.begin
+    }
+}
+
+/// The sizes of a message sent or received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Payload {
+    pub(crate) wire_size: usize,
+    pub(crate) uncompressed_size: usize,
+    pub(crate) compressed: bool,
+}
+
+impl Payload {
+    /// The size of the message on the wire, including the 5 bytes of its
+    /// gRPC frame header.
+    pub fn wire_size(&self) -> usize {
+        self.wire_size
+    }
+
+    /// The size of the encoded message before compression, or after
+    /// decompression.
+    pub fn uncompressed_size(&self) -> usize {
+        self.uncompressed_size
+    }
+
+    /// Whether the message was compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+}
+
+/// The end of a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcEnd {
+    code: Code,
+    duration: Duration,
+}
+
+impl RpcEnd {
+    /// The status code the call ended with.
+    ///
+    /// Calls dropped before their trailers end with [`Code::Cancelled`],
+    /// and calls of clients that failed to be sent with the code of the
+    /// error.
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    /// The time from the beginning of the call to its end.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// Reports the events of a call to a handler, and its end once the last
+/// clone is dropped if nothing else did.
+#[derive(Clone)]
+pub(crate) struct Recorder(Arc<RecorderInner>);
+
+struct RecorderInner {
+    handler: Arc<dyn StatsHandler>,
+    info: RpcInfo,
+    ended: AtomicBool,
+}
+
+#[cfg_attr(not(feature = "transport"), allow(dead_code))]
+impl Recorder {
+    pub(crate) fn begin(handler: Arc<dyn StatsHandler>, info: RpcInfo) -> Self {
+        handler.rpc_begin(&info);
+        Recorder(Arc::new(RecorderInner {
+            handler,
+            info,
+            ended: AtomicBool::new(false),
+        }))
+    }
+
+    pub(crate) fn out_headers(&self, headers: &HeaderMap) {
+        self.0.handler.out_headers(&self.0.info, headers);
+    }
+
+    pub(crate) fn in_headers(&self, headers: &HeaderMap) {
+        self.0.handler.in_headers(&self.0.info, headers);
+    }
+
+    pub(crate) fn out_payload(&self, payload: Payload) {
+        self.0.handler.out_payload(&self.0.info, &payload);
+    }
+
+    pub(crate) fn in_payload(&self, payload: Payload) {
+        self.0.handler.in_payload(&self.0.info, &payload);
+    }
+
+    pub(crate) fn out_trailers(&self, trailers: &HeaderMap) {
+        self.0.handler.out_trailers(&self.0.info, trailers);
+    }
+
+    pub(crate) fn in_trailers(&self, trailers: &HeaderMap) {
+        self.0.handler.in_trailers(&self.0.info, trailers);
+    }
+
+    /// Report the end of the call, unless it already ended.
+    pub(crate) fn end(&self, code: Code) {
+        self.0.end(code);
+    }
+}
+
+impl RecorderInner {
+    fn end(&self, code: Code) {
+        if self.ended.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let end = RpcEnd {
+            code,
+            duration: self.info.begin.elapsed(),
+        };
+        self.handler.rpc_end(&self.info, &end);
+    }
+}
+
+impl Drop for RecorderInner {
+    fn drop(&mut self) {
+        self.end(Code::Cancelled);
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Recorder").field(&self.0.info).finish()
+    }
+}
+
+/// The status code of `trailers`, [`Code::Unknown`] when they have none.
+#[cfg_attr(not(feature = "transport"), allow(dead_code))]
+pub(crate) fn status_code(trailers: &HeaderMap) -> Code {
+    trailers
+        .get("grpc-status")
+        .map(|code| Code::from_bytes(code.as_bytes()))
+        .unwrap_or(Code::Unknown)
+}
+
+/// The [`Recorder`] of a call, shared through the extensions of its request
+/// with the codec encoding and decoding its messages.
+///
+/// Clients create it empty, for their transport to fill once the call
+/// begins.
+#[derive(Debug, Clone)]
+pub(crate) struct CallStats(Arc<OnceLock<Recorder>>, Span);
+
+impl CallStats {
+    #[cfg_attr(not(feature = "transport"), allow(dead_code))]
+    pub(crate) fn new(recorder: Recorder) -> Self {
+        CallStats(Arc::new(OnceLock::from(recorder)), Span::none())
+    }
+
+    /// Create the empty stats of a call made from the current span.
+    pub(crate) fn caller() -> Self {
+        CallStats(Arc::default(), Span::current())
+    }
+
+    /// The span current where the call was made.
+    #[cfg_attr(not(feature = "transport"), allow(dead_code))]
+    pub(crate) fn caller_span(&self) -> &Span {
+        &self.1
+    }
+
+    /// The stats of the request `extensions` belong to.
+    pub(crate) fn of(extensions: &http::Extensions) -> Option<Self> {
+        extensions.get::<CallStats>().cloned()
+    }
+
+    /// Set the recorder of the call, unless an earlier attempt did.
+    #[cfg_attr(not(feature = "transport"), allow(dead_code))]
+    pub(crate) fn set(&self, recorder: Recorder) {
+        let _ = self.0.set(recorder);
+    }
+
+    pub(crate) fn get(&self) -> Option<&Recorder> {
+        self.0.get()
+    }
+}
+
+/// The service and method of the `/service/method` path of a call.
+pub(crate) fn split_method(path: &str) -> (&str, &str) {
+    let path = path.strip_prefix('/').unwrap_or(path);
+    path.rsplit_once('/').unwrap_or((path, ""))
+}
+
+/// A [`StatsHandler`] opening a [`tracing`] span for each call, with the
+/// fields of the OpenTelemetry semantic conventions of RPC spans.
+///
+/// The `grpc` spans, of the `INFO` level, begin with their calls and close
+/// once they end: at the trailers of streaming calls. Their status code is
+/// recorded as `rpc.grpc.status_code` then, and an `otel.status_code` of
+/// `ERROR` marks failed calls.
+///
+/// ```
+/// use tonic::{stats::Tracing, transport::Server};
+///
+/// let server = Server::builder().stats_handler(Tracing::default());
+/// ```
+///
+/// The spans of clients are children of the span calls are made from, and
+/// those of servers of the span the connection is served from. They aren't
+/// entered while handlers run, [`Server::trace_fn`] opening those spans.
+///
+/// [`Server::trace_fn`]: crate::transport::Server::trace_fn
+#[derive(Debug, Default)]
+pub struct Tracing {
+    spans: Mutex<HashMap<u64, Span>>,
+}
+
+impl StatsHandler for Tracing {
+    fn rpc_begin(&self, rpc: &RpcInfo) {
+        let (service, method) = split_method(rpc.method());
+        let kind = match rpc.is_client() {
+            true => "client",
+            false => "server",
+        };
+        let peer = rpc.remote_addr();
+        let span = tracing::info_span!(
+            parent: rpc.parent_span(),
+            "grpc",
+            otel.name = rpc.method().trim_start_matches('/'),
+            otel.kind = kind,
+            otel.status_code = field::Empty,
+            rpc.system = "grpc",
+            rpc.service = service,
+            rpc.method = method,
+            rpc.grpc.status_code = field::Empty,
+            server.address = rpc.authority(),
+            network.peer.address = peer.map(|addr| field::display(addr.ip())),
+            network.peer.port = peer.map(|addr| addr.port()),
+        );
+        self.spans.lock().unwrap().insert(rpc.id(), span);
+    }
+
+    fn rpc_end(&self, rpc: &RpcInfo, end: &RpcEnd) {
+        let span = self.spans.lock().unwrap().remove(&rpc.id());
+        if let Some(span) = span {
+            span.record("rpc.grpc.status_code", end.code() as i32);
+            if end.code() != Code::Ok {
+                span.record("otel.status_code", "ERROR");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<String>>);
+
+    impl StatsHandler for Events {
+        fn rpc_begin(&self, rpc: &RpcInfo) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("begin {}", rpc.method()));
+        }
+
+        fn in_trailers(&self, _: &RpcInfo, _: &HeaderMap) {
+            self.0.lock().unwrap().push("trailers".to_string());
+        }
+
+        fn rpc_end(&self, _: &RpcInfo, end: &RpcEnd) {
+            self.0.lock().unwrap().push(format!("end {:?}", end.code()));
+        }
+    }
+
+    #[test]
+    fn ends_calls_once() {
+        let events = Arc::new(Events::default());
+        let handler: Arc<dyn StatsHandler> = Arc::new(events.clone());
+
+        let recorder = Recorder::begin(handler.clone(), RpcInfo::new(true, "/a.A/B", None));
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", "5".parse().unwrap());
+        recorder.in_trailers(&trailers);
+        recorder.end(status_code(&trailers));
+        recorder.end(Code::Internal);
+        drop(recorder);
+
+        let recorder = Recorder::begin(handler, RpcInfo::new(true, "/a.A/C", None));
+        let stats = CallStats::caller();
+        stats.set(recorder);
+        drop(stats);
+
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            [
+                "begin /a.A/B",
+                "trailers",
+                "end NotFound",
+                "begin /a.A/C",
+                "end Cancelled",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_to_pairs() {
+        let first = Arc::new(Events::default());
+        let second = Arc::new(Events::default());
+        let handler: Arc<dyn StatsHandler> = Arc::new((first.clone(), second.clone()));
+
+        Recorder::begin(handler, RpcInfo::new(true, "/a.A/B", None)).end(Code::Ok);
+
+        assert_eq!(*first.0.lock().unwrap(), ["begin /a.A/B", "end Ok"]);
+        assert_eq!(*second.0.lock().unwrap(), ["begin /a.A/B", "end Ok"]);
+    }
+
+    /// The fields of the spans closed, in the order they were recorded.
+    #[derive(Clone, Default)]
+    struct ClosedSpans(Arc<Mutex<Vec<Vec<String>>>>);
+
+    #[derive(Default)]
+    struct Fields(Vec<String>);
+
+    impl tracing::field::Visit for Fields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+            self.0.push(format!("{}={:?}", field, value));
+        }
+    }
+
+    impl<S> tracing_subscriber::Layer<S> for ClosedSpans
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            ctx.span(id).unwrap().extensions_mut().insert(fields);
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let span = ctx.span(id).unwrap();
+            values.record(span.extensions_mut().get_mut::<Fields>().unwrap());
+        }
+
+        fn on_close(&self, id: tracing::span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let span = ctx.span(&id).unwrap();
+            let fields = span.extensions_mut().remove::<Fields>().unwrap();
+            self.0.lock().unwrap().push(fields.0);
+        }
+    }
+
+    #[test]
+    fn traces_calls() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let closed = ClosedSpans::default();
+        let subscriber = tracing_subscriber::registry().with(closed.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let handler: Arc<dyn StatsHandler> = Arc::new(Tracing::default());
+            let peer = "10.0.0.1:4000".parse().unwrap();
+            let authority = Authority::from_static("example.com");
+            let info = RpcInfo::new(false, "/a.A/B", Some(peer)).with_authority(Some(&authority));
+
+            let recorder = Recorder::begin(handler, info);
+            recorder.in_payload(Payload {
+                wire_size: 5,
+                uncompressed_size: 0,
+                compressed: false,
+            });
+            assert!(closed.0.lock().unwrap().is_empty());
+            recorder.end(Code::NotFound);
+        });
+
+        assert_eq!(
+            *closed.0.lock().unwrap(),
+            [[
+                "otel.name=\"a.A/B\"",
+                "otel.kind=\"server\"",
+                "rpc.system=\"grpc\"",
+                "rpc.service=\"a.A\"",
+                "rpc.method=\"B\"",
+                "server.address=\"example.com\"",
+                "network.peer.address=10.0.0.1",
+                "network.peer.port=4000",
+                "rpc.grpc.status_code=5",
+                "otel.status_code=\"ERROR\"",
+            ]]
+        );
+    }
+
+    #[test]
+    fn splits_methods() {
+        assert_eq!(split_method("/a.B/C"), ("a.B", "C"));
+        assert_eq!(split_method("/unknown"), ("unknown", ""));
+    }
+}
@@ -598,6 +598,13 @@ impl Status {
     }
 }
 
+/// Get the code of the `Status` an error maps to, without consuming it.
+pub(crate) fn find_error_code(err: &(dyn Error + 'static)) -> Code {
+    find_status_in_source_chain(err)
+        .map(|status| status.code())
+        .unwrap_or(Code::Unknown)
+}
+
 fn find_status_in_source_chain(err: &(dyn Error + 'static)) -> Option<Status> {
     let mut source = Some(err);
 
@@ -0,0 +1,163 @@
+//! Utilities for testing clients and servers without a transport.
+//!
+//! # Driving generated servers
+//!
+//! The servers generated by `tonic-build` are services of any request body,
+//! so a generated client can send its requests to one directly, each request
+//! going through the encoding, decoding and routing of both sides:
+//!
+//! ```ignore
+//! let mut client = GreeterClient::new(GreeterServer::new(MyGreeter::default()));
+//!
+//! let response = client.say_hello(HelloRequest::default()).await;
+//! tonic::testing::assert_code(&response, tonic::Code::Ok);
+//! ```
+//!
+//! The methods of the server trait can also be called with the
+//! [`Streaming`] requests built by [`streaming`], while the clients of the
+//! code under test can be mocked by returning the [`Streaming`] responses it
+//! builds.
+
+use crate::{
+    codec::{
+        compression::{CompressionConfig, SingleMessageCompressionOverride},
+        encode_server, Codec,
+    },
+    metadata::MetadataMap,
+    Code, Status, Streaming,
+};
+use http::StatusCode;
+
+/// Create a [`Streaming`] yielding `messages`, encoded and decoded with
+/// `codec`.
+///
+/// The stream ends after the first error of `messages`, which it yields as
+/// a response stream would yield the status of the server.
+pub fn streaming_with_codec<C, I>(mut codec: C, messages: I) -> Streaming<C::Decode>
+where
+    C: Codec,
+    I: IntoIterator<Item = Result<C::Encode, Status>>,
+    I::IntoIter: Send + 'static,
+{
+    let body = encode_server(
+        codec.encoder(),
+        tokio_stream::iter(messages),
+        None,
+        SingleMessageCompressionOverride::default(),
+        CompressionConfig::default(),
+        None,
+        None,
+    );
+    Streaming::new_response(codec.decoder(), body, StatusCode::OK, None, None)
+}
+
+/// Create a [`Streaming`] yielding the protobuf `messages`, see
+/// [`streaming_with_codec`].
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use tonic::{testing, Status};
+///
+/// let mut stream = testing::streaming(vec![
+///     Ok("first".to_string()),
+///     Err(Status::unavailable("gone")),
+/// ]);
+///
+/// assert_eq!(stream.message().await.unwrap().unwrap(), "first");
+/// assert_eq!(stream.message().await.unwrap_err().code(), tonic::Code::Unavailable);
+/// # }
+/// ```
+#[cfg(feature = "prost")]
+#[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
+pub fn streaming<T, I>(messages: I) -> Streaming<T>
+where
+    T: prost::Message + Default + Send + 'static,
+    I: IntoIterator<Item = Result<T, Status>>,
+    I::IntoIter: Send + 'static,
+{
+    streaming_with_codec(crate::codec::ProstCodec::default(), messages)
+}
+
+/// Assert that `result` is a success when `code` is [`Code::Ok`], or a
+/// `Status` of `code` otherwise.
+///
+/// # Panics
+///
+/// This function panics with the unexpected status or success otherwise.
+#[track_caller]
+pub fn assert_code<T>(result: &Result<T, Status>, code: Code) {
+    match (result, code) {
+        (Ok(_), Code::Ok) => {}
+        (Ok(_), code) => panic!("expected a status of code {:?}, got a success", code),
+        (Err(status), code) if status.code() == code => {}
+        (Err(status), code) => panic!("expected code {:?}, got {:?}", code, status),
+    }
+}
+
+/// Assert that `metadata` has the ASCII entry `key` with the value
+/// `expected`, or doesn't have it at all when `expected` is `None`.
+///
+/// # Panics
+///
+/// This function panics with the actual value otherwise.
+///
+/// ```
+/// use tonic::{metadata::MetadataMap, testing};
+///
+/// let mut metadata = MetadataMap::new();
+/// metadata.insert("x-request-id", "42".parse().unwrap());
+///
+/// testing::assert_metadata(&metadata, "x-request-id", Some("42"));
+/// testing::assert_metadata(&metadata, "x-retry", None);
+/// ```
+#[track_caller]
+pub fn assert_metadata(metadata: &MetadataMap, key: &str, expected: Option<&str>) {
+    let actual = metadata.get(key).map(|value| value.to_str());
+    match (actual, expected) {
+        (Some(Ok(actual)), Some(expected)) if actual == expected => {}
+        (None, None) => {}
+        (actual, expected) => panic!(
+            "expected metadata {:?} to be {:?}, got {:?}",
+            key, expected, actual
+        ),
+    }
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn streams_messages_then_status() {
+        let mut stream = streaming(vec![
+            Ok(1u32),
+            Ok(2),
+            Err(Status::not_found("missing")),
+            Ok(3),
+        ]);
+
+        assert_eq!(stream.message().await.unwrap(), Some(1));
+        assert_eq!(stream.message().await.unwrap(), Some(2));
+        let status = stream.message().await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "missing");
+    }
+
+    #[tokio::test]
+    async fn ends_with_the_messages() {
+        let mut stream = streaming(vec![Ok("only".to_string())]);
+
+        assert_eq!(stream.message().await.unwrap().as_deref(), Some("only"));
+        assert_eq!(stream.message().await.unwrap(), None);
+        assert!(stream.trailers().await.unwrap().is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected code NotFound")]
+    fn asserts_codes() {
+        assert_code::<()>(&Ok(()), Code::Ok);
+        assert_code::<()>(&Err(Status::not_found("")), Code::NotFound);
+        assert_code::<()>(&Err(Status::internal("")), Code::NotFound);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::transport::service::retry::jitter;
+use std::time::Duration;
+
+/// Configures the delay between the connection attempts of a channel.
+///
+/// After a connection attempt fails, requests fail right away until the
+/// backoff expires, the next request then connects again. The backoff grows
+/// exponentially with the number of attempts that failed in a row, and is
+/// randomized so that clients losing their server at the same time don't
+/// all reconnect at once.
+///
+/// Defaults to the values recommended by gRPC: an initial backoff of 1
+/// second, a multiplier of 1.6, a jitter of 0.2 and a maximum backoff of 120
+/// seconds.
+///
+/// ```
+/// # use tonic::transport::{channel::ConnectBackoff, Endpoint};
+/// # use std::time::Duration;
+/// let endpoint = Endpoint::from_static("https://example.com").connect_backoff(
+///     ConnectBackoff::new()
+///         .initial_backoff(Duration::from_millis(100))
+///         .max_backoff(Duration::from_secs(10)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectBackoff {
+    initial_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    max_backoff: Duration,
+}
+
+impl ConnectBackoff {
+    /// Create the default configuration.
+    pub fn new() -> Self {
+        ConnectBackoff {
+            initial_backoff: Duration::from_secs(1),
+            multiplier: 1.6,
+            jitter: 0.2,
+            max_backoff: Duration::from_secs(120),
+        }
+    }
+
+    /// Sets the backoff after the first failed connection attempt.
+    ///
+    /// Defaults to 1 second.
+    pub fn initial_backoff(self, backoff: Duration) -> Self {
+        ConnectBackoff {
+            initial_backoff: backoff,
+            ..self
+        }
+    }
+
+    /// Sets the factor the backoff grows by after each failed attempt.
+    ///
+    /// Values below 1 are raised to 1. Defaults to 1.6.
+    pub fn multiplier(self, multiplier: f64) -> Self {
+        ConnectBackoff {
+            multiplier: multiplier.max(1.0),
+            ..self
+        }
+    }
+
+    /// Sets by how much the backoff is randomized, as a fraction of the
+    /// backoff: a jitter of 0.2 picks a backoff up to 20% shorter or longer.
+    ///
+    /// The value is clamped to `0..=1`. Defaults to 0.2.
+    pub fn jitter(self, jitter: f64) -> Self {
+        ConnectBackoff {
+            jitter: jitter.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Sets the maximum backoff, before jitter.
+    ///
+    /// Defaults to 120 seconds.
+    pub fn max_backoff(self, backoff: Duration) -> Self {
+        ConnectBackoff {
+            max_backoff: backoff,
+            ..self
+        }
+    }
+
+    /// The backoff after `failures` attempts failed in a row.
+    pub(crate) fn backoff(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = Duration::try_from_secs_f64(backoff)
+            .unwrap_or(Duration::MAX)
+            .min(self.max_backoff);
+
+        let spread = backoff.mul_f64(self.jitter);
+        (backoff - spread).saturating_add(jitter(spread.saturating_mul(2)))
+    }
+}
+
+impl Default for ConnectBackoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_exponentially_within_jitter() {
+        let backoff = ConnectBackoff::new();
+        let within = |failures, expected: f64| {
+            let backoff = backoff.backoff(failures).as_secs_f64();
+            (expected * 0.8..expected * 1.2).contains(&backoff)
+        };
+
+        assert!(within(1, 1.0));
+        assert!(within(2, 1.6));
+        assert!(within(3, 2.56));
+        assert!(within(100, 120.0));
+    }
+}
@@ -0,0 +1,472 @@
+use super::{Resolution, ResolutionStream, Resolver};
+use http::Uri;
+use std::{
+    future::{poll_fn, Future},
+    net::SocketAddr,
+    sync::Arc,
+    task::Poll,
+    time::Duration,
+};
+use tokio::{sync::watch, time::Instant};
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Connection failures trigger a new resolution at most this often, so that
+/// an unreachable backend doesn't flood the system resolver.
+const MIN_RESOLVE_NOW_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The port gRPC targets default to when they don't specify one.
+const DEFAULT_PORT: u16 = 443;
+
+/// A [`Resolver`] for `dns://host:port` targets.
+///
+/// The canonical `dns:///host:port` form can't be represented by [`Uri`],
+/// which requires an authority, so the host is taken from the authority.
+/// Targets naming a DNS server, `dns://server/host:port`, are accepted as
+/// well but the server is ignored.
+///
+/// The host is resolved with the system resolver and the channel balances
+/// across all of its A and AAAA records. The records are resolved again
+/// periodically and whenever connecting to one of the addresses fails, so
+/// the channel follows backends being replaced.
+///
+/// Endpoints with a `dns` URI use this resolver automatically, see
+/// [`Endpoint::dns_refresh_interval`](super::Endpoint::dns_refresh_interval).
+/// Since the channel connects to the resolved IP addresses, the `:authority`
+/// of requests and the TLS domain name should be set with
+/// [`Endpoint::origin`](super::Endpoint::origin) and
+/// `ClientTlsConfig::domain_name`.
+///
+/// ```
+/// # use tonic::transport::channel::DnsResolver;
+/// # use tonic::transport::{Channel, Uri};
+/// # use std::time::Duration;
+/// # #[tokio::main]
+/// # async fn main() {
+/// let endpoint = Channel::from_static("dns://greeter.default.svc:50051")
+///     .origin(Uri::from_static("http://greeter.default.svc:50051"));
+///
+/// let resolver = DnsResolver::new().refresh_interval(Duration::from_secs(10));
+/// let channel = Channel::balance_resolver(endpoint, resolver);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DnsResolver {
+    refresh_interval: Duration,
+    https: bool,
+    #[cfg(feature = "service-config-dns")]
+    service_config_lookup: bool,
+    resolve_now: Arc<watch::Sender<()>>,
+}
+
+impl DnsResolver {
+    /// Create a resolver refreshing its records every 30 seconds.
+    pub fn new() -> Self {
+        DnsResolver {
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            https: false,
+            #[cfg(feature = "service-config-dns")]
+            service_config_lookup: false,
+            resolve_now: Arc::new(watch::channel(()).0),
+        }
+    }
+
+    /// Set how often the records are resolved again.
+    pub fn refresh_interval(self, interval: Duration) -> Self {
+        DnsResolver {
+            refresh_interval: interval,
+            ..self
+        }
+    }
+
+    /// Connect to the resolved addresses over TLS.
+    ///
+    /// Defaults to `false`, addresses are resolved to `http` URIs.
+    pub fn https(self, https: bool) -> Self {
+        DnsResolver { https, ..self }
+    }
+
+    /// Fetch the service config from the `_grpc_config.<host>` TXT record
+    /// on every resolution.
+    ///
+    /// The record holds a list of choices as described by the
+    /// [gRPC service config] documentation. The first choice that applies
+    /// to Rust clients and whose `percentage` is rolled is used, choices
+    /// restricted to client hostnames are skipped. When the record is
+    /// missing or invalid the channel keeps its current config.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [gRPC service config]: https://github.com/grpc/grpc/blob/master/doc/service_config.md
+    #[cfg(feature = "service-config-dns")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "service-config-dns")))]
+    pub fn service_config_lookup(self, enabled: bool) -> Self {
+        DnsResolver {
+            service_config_lookup: enabled,
+            ..self
+        }
+    }
+}
+
+impl Default for DnsResolver {
+    fn default() -> Self {
+        DnsResolver::new()
+    }
+}
+
+impl Resolver for DnsResolver {
+    fn resolve(&self, target: &Uri) -> ResolutionStream {
+        let target = parse_target(target);
+        let refresh_interval = self.refresh_interval;
+        let scheme = if self.https { "https" } else { "http" };
+        let mut resolve_now = self.resolve_now.subscribe();
+        #[cfg(feature = "service-config-dns")]
+        let mut config_lookup = self.service_config_lookup.then(txt::ConfigLookup::default);
+
+        Box::pin(async_stream::stream! {
+            let (host, port) = match target {
+                Ok(target) => target,
+                Err(error) => {
+                    yield Err(error);
+                    return;
+                }
+            };
+
+            loop {
+                resolve_now.borrow_and_update();
+                let resolved_at = Instant::now();
+                let resolution = lookup(&host, port, scheme).await;
+
+                #[cfg(feature = "service-config-dns")]
+                let resolution = match (resolution, config_lookup.as_mut()) {
+                    (Ok(resolution), Some(config_lookup)) => match config_lookup.lookup(&host).await {
+                        Some(config) => Ok(resolution.service_config(config)),
+                        None => Ok(resolution),
+                    },
+                    (resolution, _) => resolution,
+                };
+
+                yield resolution;
+
+                if wait(refresh_interval, &mut resolve_now).await {
+                    tokio::time::sleep_until(resolved_at + MIN_RESOLVE_NOW_INTERVAL).await;
+                }
+            }
+        })
+    }
+
+    fn resolve_now(&self, _target: &Uri) {
+        self.resolve_now.send_replace(());
+    }
+}
+
+/// Split a `dns://host[:port]` or `dns://server/host[:port]` target into
+/// its host and port.
+///
+/// Custom DNS servers are not supported, the system resolver is used
+/// regardless.
+fn parse_target(target: &Uri) -> Result<(String, u16), crate::Error> {
+    let invalid = || format!("invalid DNS target: {}", target);
+
+    let name = match target.path().trim_start_matches('/') {
+        "" => target.authority().ok_or_else(invalid)?.as_str(),
+        name => {
+            tracing::debug!("ignoring DNS server of target {}", target);
+            name
+        }
+    };
+
+    let (host, port) = match name.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+            match rest {
+                "" => (host, None),
+                _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        }
+        None => match name.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (name, None),
+        },
+    };
+
+    if host.is_empty() {
+        return Err(invalid().into());
+    }
+
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| invalid())?,
+        None => DEFAULT_PORT,
+    };
+
+    Ok((host.to_owned(), port))
+}
+
+async fn lookup(host: &str, port: u16, scheme: &str) -> Result<Resolution, crate::Error> {
+    let mut addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    addrs.sort();
+    addrs.dedup();
+
+    if addrs.is_empty() {
+        return Err(format!("no addresses found for {}", host).into());
+    }
+
+    let addresses = addrs
+        .into_iter()
+        .map(|addr| format!("{}://{}", scheme, addr).parse())
+        .collect::<Result<_, _>>()?;
+
+    Ok(Resolution::new(addresses))
+}
+
+/// Wait for the refresh interval to elapse or for a resolution to be
+/// requested, returning `true` in the latter case.
+async fn wait(refresh_interval: Duration, resolve_now: &mut watch::Receiver<()>) -> bool {
+    let mut refresh = Box::pin(tokio::time::sleep(refresh_interval));
+    let mut changed = Some(Box::pin(resolve_now.changed()));
+
+    poll_fn(|cx| {
+        if refresh.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(false);
+        }
+
+        if let Some(Poll::Ready(result)) = changed.as_mut().map(|changed| changed.as_mut().poll(cx))
+        {
+            match result {
+                Ok(()) => return Poll::Ready(true),
+                // The resolver is gone, only the interval is left.
+                Err(_) => changed = None,
+            }
+        }
+
+        Poll::Pending
+    })
+    .await
+}
+
+#[cfg(feature = "service-config-dns")]
+mod txt {
+    use super::super::{ServiceConfig, ServiceConfigError};
+    use hickory_resolver::TokioAsyncResolver;
+    use serde_json::Value;
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+        net::IpAddr,
+    };
+
+    const RECORD_PREFIX: &str = "grpc_config=";
+
+    /// Fetches service configs from TXT records, the DNS client is created
+    /// on first use.
+    #[derive(Default)]
+    pub(super) struct ConfigLookup {
+        resolver: Option<TokioAsyncResolver>,
+    }
+
+    impl ConfigLookup {
+        pub(super) async fn lookup(&mut self, host: &str) -> Option<ServiceConfig> {
+            if host.parse::<IpAddr>().is_ok() {
+                return None;
+            }
+
+            if self.resolver.is_none() {
+                match TokioAsyncResolver::tokio_from_system_conf() {
+                    Ok(resolver) => self.resolver = Some(resolver),
+                    Err(error) => {
+                        tracing::debug!("failed to create DNS client: {}", error);
+                        return None;
+                    }
+                }
+            }
+            let resolver = self.resolver.as_ref()?;
+
+            let name = format!("_grpc_config.{}", host);
+            let records = match resolver.txt_lookup(name.as_str()).await {
+                Ok(records) => records,
+                Err(error) => {
+                    tracing::debug!("no service config for {}: {}", host, error);
+                    return None;
+                }
+            };
+
+            let records = records.iter().map(|txt| {
+                txt.txt_data()
+                    .iter()
+                    .map(|data| String::from_utf8_lossy(data))
+                    .collect::<String>()
+            });
+
+            match parse(records, roll()) {
+                Ok(config) => config,
+                Err(error) => {
+                    tracing::debug!("ignoring service config of {}: {}", host, error);
+                    None
+                }
+            }
+        }
+    }
+
+    /// A random number in `0..100` to select choices by percentage.
+    fn roll() -> u64 {
+        RandomState::new().build_hasher().finish() % 100
+    }
+
+    /// Pick the service config applying to this client out of TXT records.
+    pub(super) fn parse(
+        records: impl IntoIterator<Item = String>,
+        roll: u64,
+    ) -> Result<Option<ServiceConfig>, ServiceConfigError> {
+        let record = match records
+            .into_iter()
+            .find_map(|record| record.strip_prefix(RECORD_PREFIX).map(str::to_owned))
+        {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        let choices: Value =
+            serde_json::from_str(&record).map_err(|e| ServiceConfigError::new(e.to_string()))?;
+        let choices = choices
+            .as_array()
+            .ok_or_else(|| ServiceConfigError::new("grpc_config must be an array of choices"))?;
+
+        for choice in choices {
+            if !applies(choice, roll)? {
+                continue;
+            }
+
+            let config = choice
+                .get("serviceConfig")
+                .ok_or_else(|| ServiceConfigError::new("choice without serviceConfig"))?;
+            return ServiceConfig::from_value(config).map(Some);
+        }
+
+        Ok(None)
+    }
+
+    fn applies(choice: &Value, roll: u64) -> Result<bool, ServiceConfigError> {
+        if let Some(languages) = choice.get("clientLanguage") {
+            let languages = languages
+                .as_array()
+                .ok_or_else(|| ServiceConfigError::new("clientLanguage must be an array"))?;
+            let rust = languages
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|language| language.eq_ignore_ascii_case("rust"));
+            if !rust {
+                return Ok(false);
+            }
+        }
+
+        if let Some(percentage) = choice.get("percentage") {
+            let percentage = percentage
+                .as_u64()
+                .filter(|&p| p <= 100)
+                .ok_or_else(|| ServiceConfigError::new("percentage must be in [0, 100]"))?;
+            if roll >= percentage {
+                return Ok(false);
+            }
+        }
+
+        Ok(choice.get("clientHostname").is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    fn parse(target: &'static str) -> Result<(String, u16), crate::Error> {
+        parse_target(&Uri::from_static(target))
+    }
+
+    fn addresses(resolution: &Resolution) -> Vec<String> {
+        resolution
+            .addresses()
+            .iter()
+            .map(|uri| uri.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parses_targets() {
+        assert_eq!(
+            parse("dns://example.com:50051").unwrap(),
+            ("example.com".into(), 50051)
+        );
+        assert_eq!(
+            parse("dns://example.com").unwrap(),
+            ("example.com".into(), 443)
+        );
+        assert_eq!(
+            parse("dns://8.8.8.8/example.com:80").unwrap(),
+            ("example.com".into(), 80)
+        );
+        assert_eq!(parse("dns://[::1]:50051").unwrap(), ("::1".into(), 50051));
+        assert_eq!(parse("dns://[::1]").unwrap(), ("::1".into(), 443));
+
+        assert!(parse("dns://8.8.8.8/:80").is_err());
+        assert!(parse("dns://example.com:port").is_err());
+        assert!(parse("dns://8.8.8.8/[::1]50051").is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resolves_periodically_and_on_demand() {
+        let resolver = DnsResolver::new().refresh_interval(Duration::from_secs(60));
+        let target = Uri::from_static("dns://127.0.0.1:50051");
+        let mut stream = resolver.resolve(&target);
+
+        let start = Instant::now();
+        let resolution = stream.next().await.unwrap().unwrap();
+        assert_eq!(addresses(&resolution), ["http://127.0.0.1:50051/"]);
+
+        stream.next().await.unwrap().unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+
+        resolver.resolve_now(&target);
+        stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            start.elapsed(),
+            Duration::from_secs(60) + MIN_RESOLVE_NOW_INTERVAL
+        );
+    }
+
+    #[cfg(feature = "service-config-dns")]
+    #[test]
+    fn selects_txt_service_config() {
+        let record = |choices: &str| vec!["other".to_string(), format!("grpc_config={}", choices)];
+        let config = r#"{ "methodConfig": [{ "name": [{}], "timeout": "1s" }] }"#;
+        let parse = |choices: String, roll| txt::parse(record(&choices), roll).unwrap();
+
+        let selected = parse(format!(r#"[{{ "serviceConfig": {} }}]"#, config), 0).unwrap();
+        assert_eq!(
+            selected.find("/pkg.Svc/Method").unwrap().timeout,
+            Some(Duration::from_secs(1))
+        );
+
+        let choices = format!(
+            r#"[
+                {{ "clientLanguage": ["go"], "serviceConfig": {{}} }},
+                {{ "clientHostname": ["host"], "serviceConfig": {{}} }},
+                {{ "clientLanguage": ["RUST"], "percentage": 50, "serviceConfig": {} }}
+            ]"#,
+            config
+        );
+        assert!(parse(choices.clone(), 10).is_some());
+        assert!(parse(choices, 50).is_none());
+
+        assert!(txt::parse(vec!["other".to_string()], 0).unwrap().is_none());
+        assert!(txt::parse(record("{}"), 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn resolves_https_addresses() {
+        let resolver = DnsResolver::new().https(true);
+        let mut stream = resolver.resolve(&Uri::from_static("dns://[::1]:443"));
+
+        let resolution = stream.next().await.unwrap().unwrap();
+        assert_eq!(addresses(&resolution), ["https://[::1]:443/"]);
+    }
+}
@@ -1,13 +1,34 @@
 use super::super::service;
-use super::Channel;
 #[cfg(feature = "tls")]
 use super::ClientTlsConfig;
+#[cfg(feature = "http3")]
+use super::Http3Config;
+use super::{
+    Channel, ConnectBackoff, DnsResolver, EndpointStateChange, HedgingPolicy, LoadBalancingPolicy,
+    LoadParser, OnStateChange, OutlierDetection, ResolveNow, RetryPolicy, ServiceConfig,
+    StateTracker,
+};
+#[cfg(feature = "http3")]
+use crate::transport::service::Http3Connector;
 #[cfg(feature = "tls")]
 use crate::transport::service::TlsConnector;
-use crate::transport::{service::SharedExec, Error, Executor};
+use crate::{
+    stats::StatsHandler,
+    transport::{
+        service::{
+            EnvProxies, Proxy, ProxyConfig, SharedExec, SharedTimer, TcpConnector, TcpOptions,
+        },
+        Error, Executor, Timer,
+    },
+};
 use bytes::Bytes;
-use http::{uri::Uri, HeaderValue};
-use std::{fmt, future::Future, pin::Pin, str::FromStr, time::Duration};
+use http::{
+    uri::{PathAndQuery, Uri},
+    HeaderValue,
+};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::{fmt, future::Future, net::IpAddr, pin::Pin, str::FromStr, sync::Arc, time::Duration};
 use tower::make::MakeConnection;
 // use crate::transport::E
 
@@ -18,6 +39,7 @@ use tower::make::MakeConnection;
 pub struct Endpoint {
     pub(crate) uri: Uri,
     pub(crate) origin: Option<Uri>,
+    pub(crate) path_prefix: Option<String>,
     pub(crate) user_agent: Option<HeaderValue>,
     pub(crate) timeout: Option<Duration>,
     pub(crate) concurrency_limit: Option<usize>,
@@ -29,17 +51,51 @@ pub struct Endpoint {
     // tls-roots-common feature for setting up TLS.
     #[cfg(feature = "tls-roots-common")]
     pub(crate) tls_assume_http2: bool,
+    #[cfg(feature = "http3")]
+    pub(crate) http3: Option<Http3Connector>,
     pub(crate) buffer_size: Option<usize>,
     pub(crate) init_stream_window_size: Option<u32>,
     pub(crate) init_connection_window_size: Option<u32>,
     pub(crate) tcp_keepalive: Option<Duration>,
     pub(crate) tcp_nodelay: bool,
+    pub(crate) tcp_keepalive_interval: Option<Duration>,
+    pub(crate) tcp_keepalive_retries: Option<u32>,
+    pub(crate) tcp_user_timeout: Option<Duration>,
+    pub(crate) tcp_send_buffer_size: Option<usize>,
+    pub(crate) tcp_recv_buffer_size: Option<usize>,
+    pub(crate) local_address: Option<IpAddr>,
+    #[cfg(unix)]
+    pub(crate) unix_path: Option<PathBuf>,
+    #[cfg(windows)]
+    pub(crate) named_pipe_path: Option<std::ffi::OsString>,
+    #[cfg(windows)]
+    pub(crate) impersonation_level: service::ImpersonationLevel,
     pub(crate) http2_keep_alive_interval: Option<Duration>,
     pub(crate) http2_keep_alive_timeout: Option<Duration>,
     pub(crate) http2_keep_alive_while_idle: Option<bool>,
     pub(crate) connect_timeout: Option<Duration>,
     pub(crate) http2_adaptive_window: Option<bool>,
     pub(crate) executor: SharedExec,
+    pub(crate) timer: SharedTimer,
+    pub(crate) service_config: Option<ServiceConfig>,
+    pub(crate) transparent_retry: bool,
+    pub(crate) deadline_propagation: Option<Duration>,
+    pub(crate) resolve_now: Option<ResolveNow>,
+    pub(crate) dns_refresh_interval: Option<Duration>,
+    pub(crate) load_balancing_policy: LoadBalancingPolicy,
+    pub(crate) on_state_change: Option<OnStateChange>,
+    pub(crate) outlier_detection: Option<OutlierDetection>,
+    pub(crate) health_check: Option<String>,
+    pub(crate) load_from_trailers: Option<LoadParser>,
+    pub(crate) state_tracker: Option<StateTracker>,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) connect_backoff: ConnectBackoff,
+    pub(crate) pool_size: usize,
+    pub(crate) min_connections: usize,
+    pub(crate) proxy: Option<ProxyConfig>,
+    pub(crate) stats_handler: Option<Arc<dyn StatsHandler>>,
+    #[cfg(feature = "service-config-dns")]
+    pub(crate) service_config_lookup: bool,
 }
 
 impl Endpoint {
@@ -57,6 +113,15 @@ impl Endpoint {
 
     /// Convert an `Endpoint` from a static string.
     ///
+    /// Besides URIs, the endpoint can be the unix socket at `path`, as
+    /// `unix:path` or `unix:///absolute/path`, or on Linux the socket `name`
+    /// of the abstract namespace, as `unix-abstract:name`. On Linux, it can
+    /// also be `port` of the virtual machine `cid`, as `vsock://cid:port`,
+    /// and on Windows the named pipe `\\.\pipe\name`, as `pipe:name`, or
+    /// any pipe path as `pipe:\\server\pipe\name`. Requests to sockets
+    /// and pipes have the `localhost` authority, unless
+    /// [`Endpoint::origin`] is set.
+    ///
     /// # Panics
     ///
     /// This function panics if the argument is an invalid URI.
@@ -66,21 +131,115 @@ impl Endpoint {
     /// Endpoint::from_static("https://example.com");
     /// ```
     pub fn from_static(s: &'static str) -> Self {
+        if let Some(endpoint) = Self::from_unix(s) {
+            return endpoint.expect("invalid unix uri");
+        }
+        if let Some(endpoint) = Self::from_named_pipe(s) {
+            return endpoint.expect("invalid named pipe");
+        }
+
         let uri = Uri::from_static(s);
         Self::from(uri)
     }
 
     /// Convert an `Endpoint` from shared bytes.
     ///
+    /// See [`Endpoint::from_static`] for unix socket and named pipe endpoints.
+    ///
     /// ```
     /// # use tonic::transport::Endpoint;
     /// Endpoint::from_shared("https://example.com".to_string());
     /// ```
     pub fn from_shared(s: impl Into<Bytes>) -> Result<Self, Error> {
-        let uri = Uri::from_maybe_shared(s.into()).map_err(|e| Error::new_invalid_uri().with(e))?;
+        let s = s.into();
+        if let Ok(s) = std::str::from_utf8(&s) {
+            if let Some(endpoint) = Self::from_unix(s).or_else(|| Self::from_named_pipe(s)) {
+                return endpoint;
+            }
+        }
+
+        let uri = Uri::from_maybe_shared(s).map_err(|e| Error::new_invalid_uri().with(e))?;
         Ok(Self::from(uri))
     }
 
+    /// Parse the `unix:path`, `unix:///absolute/path` and
+    /// `unix-abstract:name` uris of unix sockets, which [`Uri`] can't
+    /// represent.
+    ///
+    /// The endpoint connects to the socket, with the `localhost` authority.
+    fn from_unix(s: &str) -> Option<Result<Self, Error>> {
+        if let Some(name) = s.strip_prefix("unix-abstract:") {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            return Some(Ok(Self::unix(service::abstract_path(name.as_bytes()))));
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            return Some(Err(Error::new_invalid_uri()));
+        }
+
+        let path = s.strip_prefix("unix:")?;
+        let path = match path.strip_prefix("//") {
+            Some(path) if !path.starts_with('/') => return Some(Err(Error::new_invalid_uri())),
+            Some(path) => path,
+            None => path,
+        };
+        if path.is_empty() {
+            return Some(Err(Error::new_invalid_uri()));
+        }
+
+        #[cfg(unix)]
+        return Some(Ok(Self::unix(path.into())));
+        #[cfg(not(unix))]
+        Some(Err(Error::new_invalid_uri()))
+    }
+
+    #[cfg(unix)]
+    fn unix(path: PathBuf) -> Self {
+        Endpoint {
+            unix_path: Some(path),
+            ..Self::from(Uri::from_static("http://localhost"))
+        }
+    }
+
+    /// Parse the `pipe:name` and `pipe:\\server\pipe\name` endpoints of
+    /// named pipes.
+    ///
+    /// The endpoint connects to the pipe, with the `localhost` authority.
+    fn from_named_pipe(s: &str) -> Option<Result<Self, Error>> {
+        let name = s.strip_prefix("pipe:")?;
+        if name.is_empty() {
+            return Some(Err(Error::new_invalid_uri()));
+        }
+
+        #[cfg(windows)]
+        return Some(Ok(Endpoint {
+            named_pipe_path: Some(service::named_pipe_path(name)),
+            ..Self::from(Uri::from_static("http://localhost"))
+        }));
+        #[cfg(not(windows))]
+        Some(Err(Error::new_invalid_uri()))
+    }
+
+    /// Set how much of the client's security context the server of a named
+    /// pipe endpoint may act with.
+    ///
+    /// Defaults to [`ImpersonationLevel::Identification`], which lets the
+    /// server check the client's permissions without acting on its behalf.
+    ///
+    /// ```
+    /// # use tonic::transport::{channel::ImpersonationLevel, Endpoint};
+    /// # let mut builder = Endpoint::from_static("pipe:app");
+    /// builder.impersonation_level(ImpersonationLevel::Anonymous);
+    /// ```
+    ///
+    /// [`ImpersonationLevel::Identification`]: crate::transport::channel::ImpersonationLevel::Identification
+    #[cfg(windows)]
+    #[cfg_attr(docsrs, doc(cfg(windows)))]
+    pub fn impersonation_level(self, level: service::ImpersonationLevel) -> Self {
+        Endpoint {
+            impersonation_level: level,
+            ..self
+        }
+    }
+
     /// Set a custom user-agent header.
     ///
     /// `user_agent` will be prepended to Tonic's default user-agent string (`tonic/x.x.x`).
@@ -124,6 +283,32 @@ impl Endpoint {
         }
     }
 
+    /// Set a path prefix of the requests, such as `/api/v1`, to reach the
+    /// services a server nests under it, as behind a gateway or an ingress.
+    ///
+    /// The requests of a method are sent to `{prefix}/{package}.{Service}/{Method}`.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.path_prefix("/api/v1").expect("/api/v1 must be a valid path");
+    /// ```
+    pub fn path_prefix(self, prefix: &str) -> Result<Self, Error> {
+        let prefix = prefix.trim_end_matches('/');
+        let valid = prefix.is_empty()
+            || prefix.starts_with('/')
+                && !prefix.contains(['?', '#'])
+                && prefix.parse::<PathAndQuery>().is_ok();
+        if !valid {
+            return Err(Error::new_invalid_uri());
+        }
+
+        Ok(Endpoint {
+            path_prefix: Some(prefix.to_owned()).filter(|prefix| !prefix.is_empty()),
+            ..self
+        })
+    }
+
     /// Apply a timeout to each request.
     ///
     /// ```
@@ -147,6 +332,70 @@ impl Endpoint {
         }
     }
 
+    /// Close the connection once no request was in progress for `timeout`.
+    ///
+    /// A request is in progress until its response body is over. The next
+    /// request connects again. This releases the resources of clients that
+    /// are rarely used, on both ends of the connection.
+    ///
+    /// Defaults to keeping the connection open.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use std::time::Duration;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.idle_timeout(Duration::from_secs(30 * 60));
+    /// ```
+    pub fn idle_timeout(self, timeout: Duration) -> Self {
+        Endpoint {
+            idle_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Open `size` connections to the uri and spread requests over them.
+    ///
+    /// Each request goes to the ready connection with the fewest requests
+    /// in progress. A single HTTP/2 connection is limited by the number of
+    /// concurrent streams the server allows, and by the throughput of one
+    /// TCP connection, so busy clients may want more than one.
+    ///
+    /// Values below 1 are raised to 1. Defaults to 1.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.pool_size(4);
+    /// ```
+    pub fn pool_size(self, size: usize) -> Self {
+        Endpoint {
+            pool_size: size.max(1),
+            ..self
+        }
+    }
+
+    /// Keep `count` connections to the uri established, even before the
+    /// first request.
+    ///
+    /// The connections are made in the background as soon as the channel
+    /// is created, and again as soon as one is found closed, so requests
+    /// don't wait for the TCP, TLS and HTTP/2 handshakes. The pool is grown
+    /// to `count` connections if [`Endpoint::pool_size`] is smaller.
+    ///
+    /// Defaults to 0, connecting when a request needs the connection.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.min_connections(2);
+    /// ```
+    pub fn min_connections(self, count: usize) -> Self {
+        Endpoint {
+            min_connections: count,
+            ..self
+        }
+    }
+
     /// Apply a timeout to connecting to the uri.
     ///
     /// Defaults to no timeout.
@@ -164,6 +413,81 @@ impl Endpoint {
         }
     }
 
+    /// Sets the backoff between connection attempts after a connection
+    /// attempt fails.
+    ///
+    /// Defaults to [`ConnectBackoff::default`].
+    pub fn connect_backoff(self, backoff: ConnectBackoff) -> Self {
+        Endpoint {
+            connect_backoff: backoff,
+            ..self
+        }
+    }
+
+    /// Tunnel the connections through the HTTP proxy at `proxy`.
+    ///
+    /// The connector connects to the proxy, and asks it with a `CONNECT`
+    /// request for a tunnel to the endpoint, which the TLS and HTTP/2
+    /// handshakes then go through. `basic_auth` is the username and
+    /// password sent to the proxy, if it requires them.
+    ///
+    /// Defaults to connecting to the endpoint directly.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use http::Uri;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.http_proxy(Uri::from_static("http://proxy.example.com:3128"), None);
+    /// ```
+    pub fn http_proxy(self, proxy: Uri, basic_auth: Option<(&str, &str)>) -> Self {
+        Endpoint {
+            proxy: Some(ProxyConfig::Always(Proxy::http(proxy, basic_auth))),
+            ..self
+        }
+    }
+
+    /// Tunnel the connections through the SOCKS5 proxy at `proxy`, which
+    /// must include the port.
+    ///
+    /// The host name of the endpoint is resolved by the proxy. `credentials`
+    /// are the username and password sent to the proxy, if it requires
+    /// them.
+    ///
+    /// Defaults to connecting to the endpoint directly.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use http::Uri;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.socks5_proxy(Uri::from_static("socks5://127.0.0.1:1080"), None);
+    /// ```
+    pub fn socks5_proxy(self, proxy: Uri, credentials: Option<(&str, &str)>) -> Self {
+        Endpoint {
+            proxy: Some(ProxyConfig::Always(Proxy::socks5(proxy, credentials))),
+            ..self
+        }
+    }
+
+    /// Tunnel the connections through the proxies set by the environment.
+    ///
+    /// Endpoints with the `https` scheme use `HTTPS_PROXY`, others use
+    /// `HTTP_PROXY`, both defaulting to `ALL_PROXY`. The proxies are HTTP
+    /// proxies unless their url has the `socks5` scheme, and may include
+    /// a username and password. Hosts listed by `NO_PROXY` are connected to
+    /// directly: it separates with commas domains, which also match their
+    /// subdomains, IP addresses and CIDR blocks, or is `*` to match every
+    /// host. The lowercase versions of the variables take precedence.
+    ///
+    /// The variables are read when this method is called.
+    ///
+    /// Defaults to connecting to the endpoint directly.
+    pub fn proxy_from_env(self) -> Self {
+        Endpoint {
+            proxy: Some(ProxyConfig::Env(EnvProxies::from_env())),
+            ..self
+        }
+    }
+
     /// Set whether TCP keepalive messages are enabled on accepted connections.
     ///
     /// If `None` is specified, keepalive is disabled, otherwise the duration
@@ -267,6 +591,31 @@ impl Endpoint {
         }
     }
 
+    /// Connect with HTTP/3 over QUIC, falling back to HTTP/2 when a QUIC
+    /// connection can't be made within the
+    /// [fallback timeout](Http3Config::fallback_timeout).
+    ///
+    /// HTTP/3 connections are always secured, with `config` rather than the
+    /// [TLS config](Endpoint::tls_config) of the endpoint, which only applies
+    /// to the HTTP/2 connections. The endpoint still connects with a
+    /// [custom connector](Endpoint::connect_with_connector) to fall back.
+    ///
+    /// ```
+    /// # use tonic::transport::{Endpoint, Http3Config};
+    /// # fn main() -> Result<(), tonic::transport::Error> {
+    /// let endpoint = Endpoint::from_static("https://example.com").http3(Http3Config::new())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "http3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+    pub fn http3(self, config: Http3Config) -> Result<Self, Error> {
+        Ok(Endpoint {
+            http3: Some(config.connector(&self.uri).map_err(Error::from_source)?),
+            ..self
+        })
+    }
+
     /// Set the value of `TCP_NODELAY` option for accepted connections. Enabled by default.
     pub fn tcp_nodelay(self, enabled: bool) -> Self {
         Endpoint {
@@ -275,6 +624,79 @@ impl Endpoint {
         }
     }
 
+    /// Set the interval between TCP keepalive probes.
+    ///
+    /// Only applies if [`Endpoint::tcp_keepalive`] is set. Uses the default
+    /// of the operating system otherwise.
+    pub fn tcp_keepalive_interval(self, interval: Duration) -> Self {
+        Endpoint {
+            tcp_keepalive_interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Set the number of unanswered TCP keepalive probes after which the
+    /// connection is closed.
+    ///
+    /// Only applies if [`Endpoint::tcp_keepalive`] is set, and is ignored
+    /// on Windows. Uses the default of the operating system otherwise.
+    pub fn tcp_keepalive_retries(self, retries: u32) -> Self {
+        Endpoint {
+            tcp_keepalive_retries: Some(retries),
+            ..self
+        }
+    }
+
+    /// Set the value of the `TCP_USER_TIMEOUT` option: how long sent data
+    /// may remain unacknowledged before the connection is closed.
+    ///
+    /// Only applies on Linux, Android and Fuchsia. Uses the default of the
+    /// operating system otherwise.
+    pub fn tcp_user_timeout(self, timeout: Duration) -> Self {
+        Endpoint {
+            tcp_user_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Set the size of the send buffer of the socket, `SO_SNDBUF`.
+    ///
+    /// Uses the default of the operating system otherwise.
+    pub fn tcp_send_buffer_size(self, size: usize) -> Self {
+        Endpoint {
+            tcp_send_buffer_size: Some(size),
+            ..self
+        }
+    }
+
+    /// Set the size of the receive buffer of the socket, `SO_RCVBUF`.
+    ///
+    /// Uses the default of the operating system otherwise.
+    pub fn tcp_recv_buffer_size(self, size: usize) -> Self {
+        Endpoint {
+            tcp_recv_buffer_size: Some(size),
+            ..self
+        }
+    }
+
+    /// Bind the socket to `address` before connecting, to pick the network
+    /// interface connections go out of.
+    ///
+    /// Defaults to letting the operating system pick the address.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.local_address(Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))));
+    /// ```
+    pub fn local_address(self, address: Option<IpAddr>) -> Self {
+        Endpoint {
+            local_address: address,
+            ..self
+        }
+    }
+
     /// Set http2 KEEP_ALIVE_INTERVAL. Uses `hyper`'s default otherwise.
     pub fn http2_keep_alive_interval(self, interval: Duration) -> Self {
         Endpoint {
@@ -318,27 +740,405 @@ impl Endpoint {
         self
     }
 
+    /// Sets the timer of the timeouts of calls and of idle connections,
+    /// for channels running on executors other than `tokio`'s.
+    ///
+    /// Uses `tokio::time` by default.
+    pub fn timer<T>(self, timer: T) -> Self
+    where
+        T: Timer + Send + Sync + 'static,
+    {
+        Endpoint {
+            timer: SharedTimer::new(timer),
+            ..self
+        }
+    }
+
+    /// Sets the [`ServiceConfig`] used by the channel.
+    ///
+    /// Channels balancing over a list of endpoints use the config of the
+    /// first endpoint having one, see [`Channel::balance_list`], those
+    /// balancing over endpoints sent to them once they are created are given
+    /// one with [`Channel::service_config`].
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use tonic::transport::channel::{MethodConfig, RetryPolicy, ServiceConfig};
+    /// # use tonic::Code;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.service_config(ServiceConfig::new().method_config(
+    ///     MethodConfig::new()
+    ///         .method("helloworld.Greeter", "SayHello")
+    ///         .retry_policy(RetryPolicy::new(3).retryable_status_code(Code::Unavailable)),
+    /// ));
+    /// ```
+    pub fn service_config(self, config: ServiceConfig) -> Self {
+        Endpoint {
+            service_config: Some(config),
+            ..self
+        }
+    }
+
+    /// Sets the [`RetryPolicy`] of every method that doesn't have a more
+    /// specific one in the [`ServiceConfig`].
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use tonic::transport::channel::RetryPolicy;
+    /// # use tonic::Code;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.retry_policy(RetryPolicy::new(3).retryable_status_code(Code::Unavailable));
+    /// ```
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        let config = self
+            .service_config
+            .get_or_insert_with(ServiceConfig::new)
+            .default_method_config();
+        *config = std::mem::take(config).retry_policy(policy);
+        self
+    }
+
+    /// Sets the [`HedgingPolicy`] of every method that doesn't have a more
+    /// specific one in the [`ServiceConfig`].
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use tonic::transport::channel::HedgingPolicy;
+    /// # use std::time::Duration;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.hedging_policy(HedgingPolicy::new(2).hedging_delay(Duration::from_millis(20)));
+    /// ```
+    pub fn hedging_policy(mut self, policy: HedgingPolicy) -> Self {
+        let config = self
+            .service_config
+            .get_or_insert_with(ServiceConfig::new)
+            .default_method_config();
+        *config = std::mem::take(config).hedging_policy(policy);
+        self
+    }
+
+    /// Throttles retries and hedging of the channel, see
+    /// [`ServiceConfig::retry_throttling`].
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.retry_throttling(10, 0.1);
+    /// ```
+    pub fn retry_throttling(mut self, max_tokens: u32, token_ratio: f64) -> Self {
+        let config = self.service_config.take().unwrap_or_default();
+        self.service_config = Some(config.retry_throttling(max_tokens, token_ratio));
+        self
+    }
+
+    /// Sets whether requests the server didn't process are replayed.
+    ///
+    /// A request is replayed once, on a new stream or connection, when
+    /// it failed before reaching the server: the connection could not be
+    /// established, was closed before the request was sent, the server
+    /// refused the stream or shut down gracefully before processing it.
+    /// This doesn't depend on any [`RetryPolicy`].
+    ///
+    /// Replaying requires buffering the request body until the response
+    /// headers are received, up to an internal limit.
+    ///
+    /// Channels balancing over a list of endpoints only replay requests if
+    /// every endpoint allows it, see [`Channel::balance_list`].
+    ///
+    /// Enabled by default.
+    pub fn transparent_retry(self, enabled: bool) -> Self {
+        Endpoint {
+            transparent_retry: enabled,
+            ..self
+        }
+    }
+
+    /// Sets the offset subtracted from the time remaining until the
+    /// deadline of the call being handled, when applied to the calls its
+    /// handler makes. `None` disables the propagation of deadlines.
+    ///
+    /// An offset leaves the handler time to answer after the calls it makes
+    /// time out. See [`deadline`](crate::transport::deadline).
+    ///
+    /// Channels balancing over a list of endpoints only propagate deadlines
+    /// if every endpoint does, with the largest offset, see
+    /// [`Channel::balance_list`].
+    ///
+    /// Defaults to no offset.
+    pub fn deadline_propagation(self, offset: impl Into<Option<Duration>>) -> Self {
+        Endpoint {
+            deadline_propagation: offset.into(),
+            ..self
+        }
+    }
+
+    /// Sets how channels balancing across the addresses this endpoint is
+    /// resolved to choose between them.
+    ///
+    /// This applies to `dns://host:port` endpoints and to channels created
+    /// with [`Channel::balance_resolver`]. Addresses are tried in the order
+    /// the resolver returns them when using [`LoadBalancingPolicy::PickFirst`].
+    ///
+    /// Defaults to [`LoadBalancingPolicy::PowerOfTwoChoices`].
+    pub fn load_balancing_policy(self, policy: LoadBalancingPolicy) -> Self {
+        Endpoint {
+            load_balancing_policy: policy,
+            ..self
+        }
+    }
+
+    /// Eject this endpoint, or the addresses it is resolved to, from
+    /// balanced channels while their requests fail more than others.
+    ///
+    /// Disabled by default.
+    pub fn outlier_detection(self, config: OutlierDetection) -> Self {
+        Endpoint {
+            outlier_detection: Some(config),
+            ..self
+        }
+    }
+
+    /// Only send requests to this endpoint, or to the addresses it is
+    /// resolved to, while their server reports `service` as `SERVING`.
+    ///
+    /// Balanced channels watch the status of `service` with the
+    /// `grpc.health.v1.Health/Watch` method, over a connection of its own
+    /// to each endpoint, as served by `tonic-health`. The empty name is the
+    /// overall health of the server. Endpoints of servers that don't
+    /// implement the health service are used as if serving.
+    ///
+    /// This doesn't apply to [`LoadBalancingPolicy::PickFirst`].
+    ///
+    /// Disabled by default.
+    pub fn health_check(self, service: impl Into<String>) -> Self {
+        Endpoint {
+            health_check: Some(service.into()),
+            ..self
+        }
+    }
+
+    /// Sets how the load of this endpoint, or of the addresses it is
+    /// resolved to, is read from the trailers of their responses.
+    ///
+    /// Balanced channels using [`LoadBalancingPolicy::PowerOfTwoChoices`]
+    /// send each request to the least loaded of two endpoints, based on the
+    /// last load each endpoint reported. Endpoints that didn't report any
+    /// load have a load of 0.
+    ///
+    /// `parse` is called with the headers of trailers-only responses, and
+    /// with the trailers of other responses once they are read from their
+    /// body, so only the calls reading their trailers report a load.
+    pub fn load_from_trailers(
+        self,
+        parse: impl Fn(&http::HeaderMap) -> Option<f64> + Send + Sync + 'static,
+    ) -> Self {
+        Endpoint {
+            load_from_trailers: Some(LoadParser::new(parse)),
+            ..self
+        }
+    }
+
+    /// Sets a callback called every time the connection to this endpoint,
+    /// or to an address it is resolved to, changes state.
+    ///
+    /// The callback runs on the task driving the connection and should
+    /// return quickly, sending the change over a channel to observe it
+    /// asynchronously.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// let endpoint = Endpoint::from_static("dns://example.com:443").on_state_change(|change| {
+    ///     println!("{} is {:?}", change.uri(), change.state());
+    /// });
+    /// ```
+    pub fn on_state_change(
+        self,
+        callback: impl Fn(&EndpointStateChange) + Send + Sync + 'static,
+    ) -> Self {
+        Endpoint {
+            on_state_change: Some(OnStateChange::new(callback)),
+            ..self
+        }
+    }
+
+    /// Sets a [`StatsHandler`] told of the connections to this endpoint, or
+    /// to the addresses it is resolved to, and of the calls made over them.
+    ///
+    /// Clients made with tonic report the messages of their calls, and their
+    /// trailers as they read them. Other requests end once their response
+    /// headers are received.
+    pub fn stats_handler(self, handler: impl StatsHandler) -> Self {
+        Endpoint {
+            stats_handler: Some(Arc::new(handler)),
+            ..self
+        }
+    }
+
+    /// Sets how often the records of a `dns://host:port` endpoint are
+    /// resolved again.
+    ///
+    /// Channels created from such an endpoint balance across all the
+    /// addresses the host resolves to using a [`DnsResolver`], which also
+    /// resolves again when connecting to an address fails. Addresses are
+    /// connected to over TLS when a TLS config is set.
+    ///
+    /// Defaults to 30 seconds.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use std::time::Duration;
+    /// # let mut builder = Endpoint::from_static("dns://example.com:50051");
+    /// builder.dns_refresh_interval(Duration::from_secs(10));
+    /// ```
+    pub fn dns_refresh_interval(self, interval: Duration) -> Self {
+        Endpoint {
+            dns_refresh_interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Sets whether the service config of a `dns://host:port` endpoint is
+    /// fetched from its `_grpc_config.<host>` TXT record.
+    ///
+    /// The fetched config replaces the one set with
+    /// [`Endpoint::service_config`], see
+    /// [`DnsResolver::service_config_lookup`].
+    ///
+    /// Disabled by default.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # let mut builder = Endpoint::from_static("dns://example.com:50051");
+    /// builder.enable_service_config_lookup(true);
+    /// ```
+    #[cfg(feature = "service-config-dns")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "service-config-dns")))]
+    pub fn enable_service_config_lookup(self, enabled: bool) -> Self {
+        Endpoint {
+            service_config_lookup: enabled,
+            ..self
+        }
+    }
+
+    /// The endpoint connecting to a `vsock://cid:port` uri, with the
+    /// `localhost` authority unless another origin is set.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn vsock_endpoint(&self) -> Option<Self> {
+        service::vsock_address(&self.uri)?;
+        let origin = self
+            .origin
+            .clone()
+            .unwrap_or_else(|| Uri::from_static("http://localhost"));
+        Some(Endpoint {
+            origin: Some(origin),
+            ..self.clone()
+        })
+    }
+
+    fn is_unix(&self) -> bool {
+        #[cfg(unix)]
+        return self.unix_path.is_some();
+        #[cfg(not(unix))]
+        false
+    }
+
+    fn is_named_pipe(&self) -> bool {
+        #[cfg(windows)]
+        return self.named_pipe_path.is_some();
+        #[cfg(not(windows))]
+        false
+    }
+
+    /// Returns the resolver to use for a `dns` endpoint.
+    fn dns_resolver(&self) -> Option<DnsResolver> {
+        if self.uri.scheme_str() != Some("dns") {
+            return None;
+        }
+
+        let mut resolver = DnsResolver::new();
+        if let Some(interval) = self.dns_refresh_interval {
+            resolver = resolver.refresh_interval(interval);
+        }
+        #[cfg(feature = "tls")]
+        {
+            resolver = resolver.https(self.tls.is_some());
+        }
+        #[cfg(feature = "service-config-dns")]
+        {
+            resolver = resolver.service_config_lookup(self.service_config_lookup);
+        }
+        Some(resolver)
+    }
+
+    /// The TCP connector of the endpoint, connecting with `connect_timeout`
+    /// when set.
+    pub(crate) fn tcp_connector(&self, connect_timeout: Option<Duration>) -> TcpConnector {
+        let mut http = hyper::client::connect::HttpConnector::new();
+        http.enforce_http(false);
+        http.set_nodelay(self.tcp_nodelay);
+        http.set_keepalive(self.tcp_keepalive);
+        http.set_send_buffer_size(self.tcp_send_buffer_size);
+        http.set_recv_buffer_size(self.tcp_recv_buffer_size);
+        http.set_local_address(self.local_address);
+        http.set_connect_timeout(connect_timeout);
+
+        let options = TcpOptions {
+            keepalive: self.tcp_keepalive,
+            keepalive_interval: self.tcp_keepalive_interval,
+            keepalive_retries: self.tcp_keepalive_retries,
+            user_timeout: self.tcp_user_timeout,
+        };
+        TcpConnector::new(http, options)
+    }
+
     pub(crate) fn connector<C>(&self, c: C) -> service::Connector<C> {
+        let proxy = self
+            .proxy
+            .as_ref()
+            .filter(|_| {
+                !self.is_unix() && !self.is_named_pipe() && self.uri.scheme_str() != Some("vsock")
+            })
+            .and_then(|proxy| proxy.proxy_for(&self.uri));
+
         #[cfg(all(feature = "tls", not(feature = "tls-roots-common")))]
-        let connector = service::Connector::new(c, self.tls.clone());
+        let connector = service::Connector::new(c, proxy, self.tls.clone());
 
         #[cfg(all(feature = "tls", feature = "tls-roots-common"))]
-        let connector = service::Connector::new(c, self.tls.clone(), self.tls_assume_http2);
+        let connector = service::Connector::new(c, proxy, self.tls.clone(), self.tls_assume_http2);
 
         #[cfg(not(feature = "tls"))]
-        let connector = service::Connector::new(c);
+        let connector = service::Connector::new(c, proxy);
 
         connector
     }
 
     /// Create a channel from this config.
+    ///
+    /// Channels to `dns://host:port` endpoints connect to the resolved
+    /// addresses lazily, see [`Endpoint::dns_refresh_interval`].
     pub async fn connect(&self) -> Result<Channel, Error> {
-        let mut http = hyper::client::connect::HttpConnector::new();
-        http.enforce_http(false);
-        http.set_nodelay(self.tcp_nodelay);
-        http.set_keepalive(self.tcp_keepalive);
+        if let Some(resolver) = self.dns_resolver() {
+            return Ok(Channel::balance_resolver(self.clone(), resolver));
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(endpoint) = self.vsock_endpoint() {
+            return endpoint
+                .connect_with_connector(service::VsockConnector)
+                .await;
+        }
+        #[cfg(unix)]
+        if let Some(path) = &self.unix_path {
+            let connector = service::UnixConnector::new(path.clone());
+            return self.connect_with_connector(connector).await;
+        }
+        #[cfg(windows)]
+        if let Some(path) = &self.named_pipe_path {
+            let connector =
+                service::NamedPipeConnector::new(path.clone(), self.impersonation_level);
+            return self.connect_with_connector(connector).await;
+        }
 
-        let connector = self.connector(http);
+        let connector = self.connector(self.tcp_connector(None));
 
         if let Some(connect_timeout) = self.connect_timeout {
             let mut connector = hyper_timeout::TimeoutConnector::new(connector);
@@ -354,12 +1154,26 @@ impl Endpoint {
     /// The channel returned by this method does not attempt to connect to the endpoint until first
     /// use.
     pub fn connect_lazy(&self) -> Channel {
-        let mut http = hyper::client::connect::HttpConnector::new();
-        http.enforce_http(false);
-        http.set_nodelay(self.tcp_nodelay);
-        http.set_keepalive(self.tcp_keepalive);
+        if let Some(resolver) = self.dns_resolver() {
+            return Channel::balance_resolver(self.clone(), resolver);
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(endpoint) = self.vsock_endpoint() {
+            return endpoint.connect_with_connector_lazy(service::VsockConnector);
+        }
+        #[cfg(unix)]
+        if let Some(path) = &self.unix_path {
+            let connector = service::UnixConnector::new(path.clone());
+            return self.connect_with_connector_lazy(connector);
+        }
+        #[cfg(windows)]
+        if let Some(path) = &self.named_pipe_path {
+            let connector =
+                service::NamedPipeConnector::new(path.clone(), self.impersonation_level);
+            return self.connect_with_connector_lazy(connector);
+        }
 
-        let connector = self.connector(http);
+        let connector = self.connector(self.tcp_connector(None));
 
         if let Some(connect_timeout) = self.connect_timeout {
             let mut connector = hyper_timeout::TimeoutConnector::new(connector);
@@ -438,6 +1252,7 @@ impl From<Uri> for Endpoint {
         Self {
             uri,
             origin: None,
+            path_prefix: None,
             user_agent: None,
             concurrency_limit: None,
             rate_limit: None,
@@ -446,17 +1261,51 @@ impl From<Uri> for Endpoint {
             tls: None,
             #[cfg(feature = "tls-roots-common")]
             tls_assume_http2: false,
+            #[cfg(feature = "http3")]
+            http3: None,
             buffer_size: None,
             init_stream_window_size: None,
             init_connection_window_size: None,
             tcp_keepalive: None,
             tcp_nodelay: true,
+            tcp_keepalive_interval: None,
+            tcp_keepalive_retries: None,
+            tcp_user_timeout: None,
+            tcp_send_buffer_size: None,
+            tcp_recv_buffer_size: None,
+            local_address: None,
+            #[cfg(unix)]
+            unix_path: None,
+            #[cfg(windows)]
+            named_pipe_path: None,
+            #[cfg(windows)]
+            impersonation_level: service::ImpersonationLevel::default(),
             http2_keep_alive_interval: None,
             http2_keep_alive_timeout: None,
             http2_keep_alive_while_idle: None,
             connect_timeout: None,
             http2_adaptive_window: None,
             executor: SharedExec::tokio(),
+            timer: SharedTimer::tokio(),
+            service_config: None,
+            transparent_retry: true,
+            deadline_propagation: Some(Duration::ZERO),
+            resolve_now: None,
+            dns_refresh_interval: None,
+            load_balancing_policy: LoadBalancingPolicy::default(),
+            on_state_change: None,
+            outlier_detection: None,
+            health_check: None,
+            load_from_trailers: None,
+            state_tracker: None,
+            idle_timeout: None,
+            connect_backoff: ConnectBackoff::default(),
+            pool_size: 1,
+            min_connections: 0,
+            proxy: None,
+            stats_handler: None,
+            #[cfg(feature = "service-config-dns")]
+            service_config_lookup: false,
         }
     }
 }
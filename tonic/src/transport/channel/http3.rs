@@ -0,0 +1,95 @@
+use crate::transport::{
+    service::Http3Connector,
+    tls::{Certificate, Identity},
+    Error,
+};
+use http::Uri;
+use std::{fmt, time::Duration};
+
+/// Configures the HTTP/3 connections of endpoints, see
+/// [`Endpoint::http3`](super::Endpoint::http3).
+#[derive(Clone)]
+pub struct Http3Config {
+    domain: Option<String>,
+    cert: Option<Certificate>,
+    identity: Option<Identity>,
+    fallback_timeout: Duration,
+}
+
+impl fmt::Debug for Http3Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Http3Config")
+            .field("domain", &self.domain)
+            .field("cert", &self.cert)
+            .field("identity", &self.identity)
+            .field("fallback_timeout", &self.fallback_timeout)
+            .finish()
+    }
+}
+
+impl Default for Http3Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Http3Config {
+    /// Creates a new `Http3Config`, verifying servers with the roots of the
+    /// `tls-roots` and `tls-webpki-roots` features.
+    pub fn new() -> Self {
+        Http3Config {
+            domain: None,
+            cert: None,
+            identity: None,
+            fallback_timeout: Duration::from_secs(3),
+        }
+    }
+
+    /// Sets the domain name against which to verify the server's TLS certificate.
+    pub fn domain_name(self, domain_name: impl Into<String>) -> Self {
+        Http3Config {
+            domain: Some(domain_name.into()),
+            ..self
+        }
+    }
+
+    /// Sets the CA Certificate against which to verify the server's TLS certificate.
+    pub fn ca_certificate(self, ca_certificate: Certificate) -> Self {
+        Http3Config {
+            cert: Some(ca_certificate),
+            ..self
+        }
+    }
+
+    /// Sets the client identity to present to the server.
+    pub fn identity(self, identity: Identity) -> Self {
+        Http3Config {
+            identity: Some(identity),
+            ..self
+        }
+    }
+
+    /// Sets how long to try connecting with QUIC before connecting with
+    /// HTTP/2 instead.
+    ///
+    /// Default is 3 seconds.
+    pub fn fallback_timeout(self, timeout: Duration) -> Self {
+        Http3Config {
+            fallback_timeout: timeout,
+            ..self
+        }
+    }
+
+    pub(crate) fn connector(&self, uri: &Uri) -> Result<Http3Connector, crate::Error> {
+        let domain = match &self.domain {
+            Some(domain) => domain,
+            None => uri.host().ok_or_else(Error::new_invalid_uri)?,
+        };
+        Http3Connector::new(
+            self.cert.clone(),
+            self.identity.clone(),
+            domain,
+            self.fallback_timeout,
+        )
+    }
+}
@@ -0,0 +1,120 @@
+use http::{HeaderMap, Uri};
+use std::{fmt, sync::Arc};
+
+/// How a balanced [`Channel`](super::Channel) chooses the endpoint each
+/// request is sent to.
+///
+/// Except for [`PickFirst`](LoadBalancingPolicy::PickFirst), endpoints
+/// whose connection attempts keep failing are marked
+/// [`Unhealthy`](EndpointState::Unhealthy) and not sent requests until an
+/// exponential backoff expires, they are then connected to again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoadBalancingPolicy {
+    /// Send each request to the least loaded of two endpoints picked at
+    /// random, among all the connected endpoints.
+    #[default]
+    PowerOfTwoChoices,
+    /// Send every request to the first endpoint, in the order they were
+    /// added, that can be connected to.
+    ///
+    /// The channel sticks to that endpoint until its connection fails, the
+    /// following endpoints are then tried in order.
+    PickFirst,
+    /// Send requests to each ready endpoint in turn.
+    RoundRobin,
+}
+
+/// The state of one of the endpoints of a balanced channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EndpointState {
+    /// The endpoint isn't connected, and no connection attempt is in
+    /// progress.
+    Idle,
+    /// A connection to the endpoint is being established.
+    Connecting,
+    /// The endpoint is connected.
+    Ready,
+    /// The last connection attempt failed.
+    TransientFailure,
+    /// Connecting to the endpoint failed repeatedly, it isn't used until a
+    /// backoff expires.
+    Unhealthy,
+    /// Requests sent to the endpoint failed too often, it isn't used until
+    /// its ejection time expires.
+    ///
+    /// See [`OutlierDetection`](super::OutlierDetection).
+    Ejected,
+    /// The service of the endpoint isn't `SERVING`, per client-side health
+    /// checking, it isn't used until it is.
+    ///
+    /// See [`Endpoint::health_check`](super::Endpoint::health_check).
+    NotServing,
+}
+
+/// A transition of an endpoint of a balanced channel to a new state.
+///
+/// See [`Endpoint::on_state_change`](super::Endpoint::on_state_change).
+#[derive(Debug, Clone)]
+pub struct EndpointStateChange {
+    uri: Uri,
+    state: EndpointState,
+}
+
+impl EndpointStateChange {
+    pub(crate) fn new(uri: Uri, state: EndpointState) -> Self {
+        EndpointStateChange { uri, state }
+    }
+
+    /// The URI of the endpoint.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// The state the endpoint moved to.
+    pub fn state(&self) -> EndpointState {
+        self.state
+    }
+}
+
+/// The callback set with `Endpoint::on_state_change`.
+#[derive(Clone)]
+pub(crate) struct OnStateChange(Arc<dyn Fn(&EndpointStateChange) + Send + Sync>);
+
+impl OnStateChange {
+    pub(crate) fn new(callback: impl Fn(&EndpointStateChange) + Send + Sync + 'static) -> Self {
+        OnStateChange(Arc::new(callback))
+    }
+
+    pub(crate) fn call(&self, change: &EndpointStateChange) {
+        (self.0)(change)
+    }
+}
+
+impl fmt::Debug for OnStateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnStateChange").finish()
+    }
+}
+
+/// The callback set with `Endpoint::load_from_trailers`.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub(crate) struct LoadParser(Arc<dyn Fn(&HeaderMap) -> Option<f64> + Send + Sync>);
+
+impl LoadParser {
+    pub(crate) fn new(parse: impl Fn(&HeaderMap) -> Option<f64> + Send + Sync + 'static) -> Self {
+        LoadParser(Arc::new(parse))
+    }
+
+    pub(crate) fn parse(&self, trailers: &HeaderMap) -> Option<f64> {
+        (self.0)(trailers)
+    }
+}
+
+impl fmt::Debug for LoadParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadParser").finish()
+    }
+}
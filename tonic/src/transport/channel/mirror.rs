@@ -0,0 +1,288 @@
+use super::Channel;
+use crate::{body::BoxBody, Status};
+use bytes::{Buf, Bytes, BytesMut};
+use http::{HeaderMap, Request};
+use http_body::Body;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::sync::oneshot;
+use tower::{Layer, ServiceExt};
+use tower_service::Service;
+
+/// The largest request mirrored, beyond which its message is not recorded.
+const MAX_MIRRORED_SIZE: usize = 4 * 1024 * 1024;
+
+/// A [`Layer`] mirroring a percentage of the calls of a client to the server
+/// of another [`Channel`], such as a new backend tried with the traffic of
+/// production:
+///
+/// ```no_run
+/// # use tonic::transport::{channel::MirrorLayer, Channel};
+/// # use tower::ServiceBuilder;
+/// let primary = Channel::from_static("http://[::1]:50051").connect_lazy();
+/// let shadow = Channel::from_static("http://[::1]:50052").connect_lazy();
+/// // Mirror 10% of the calls.
+/// let channel = ServiceBuilder::new()
+///     .layer(MirrorLayer::new(shadow, 10))
+///     .service(primary);
+/// // let client = GreeterClient::new(channel);
+/// ```
+///
+/// The calls of a single request message, such as unary calls, are mirrored
+/// once their request was sent to the primary channel, in the background,
+/// without delaying them. The responses of the mirrored calls are discarded,
+/// and their failures only logged.
+#[derive(Debug, Clone)]
+pub struct MirrorLayer {
+    channel: Channel,
+    percentage: u64,
+}
+
+impl MirrorLayer {
+    /// Mirror `percentage` out of 100 calls to the server of `channel`.
+    pub fn new(channel: Channel, percentage: u64) -> Self {
+        Self {
+            channel,
+            percentage,
+        }
+    }
+}
+
+impl<S> Layer<S> for MirrorLayer {
+    type Service = Mirror<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Mirror {
+            inner,
+            channel: self.channel.clone(),
+            percentage: self.percentage,
+        }
+    }
+}
+
+/// A service mirroring a percentage of its calls, see [`MirrorLayer`].
+#[derive(Debug, Clone)]
+pub struct Mirror<S> {
+    inner: S,
+    channel: Channel,
+    percentage: u64,
+}
+
+impl<S> Service<Request<BoxBody>> for Mirror<S>
+where
+    S: Service<Request<BoxBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<BoxBody>) -> Self::Future {
+        // A random number in `0..100` to select calls by percentage.
+        let roll = RandomState::new().build_hasher().finish() % 100;
+        if roll >= self.percentage {
+            return self.inner.call(req);
+        }
+
+        let mut mirrored = Request::new(());
+        *mirrored.method_mut() = req.method().clone();
+        *mirrored.uri_mut() = req.uri().clone();
+        *mirrored.version_mut() = req.version();
+        *mirrored.headers_mut() = req.headers().clone();
+
+        let (sender, recorded) = oneshot::channel();
+        let req = req.map(|body| {
+            BoxBody::new(RecordingBody {
+                inner: body,
+                recorded: BytesMut::new(),
+                sender: Some(sender),
+            })
+        });
+
+        let channel = self.channel.clone();
+        tokio::spawn(async move {
+            let Ok(message) = recorded.await else {
+                return;
+            };
+            if !is_single_message(&message) {
+                return;
+            }
+            let req = mirrored.map(|()| crate::body::boxed(http_body::Full::new(message)));
+            let status = call(channel, req).await;
+            if status.code() != crate::Code::Ok {
+                tracing::debug!("mirrored call failed: {}", status);
+            }
+        });
+
+        self.inner.call(req)
+    }
+}
+
+/// Make the mirrored call `req`, discarding its response, and return its status.
+async fn call(channel: Channel, req: Request<BoxBody>) -> Status {
+    let res = match channel.oneshot(req).await {
+        Ok(res) => res,
+        Err(err) => return Status::from_error_generic(err),
+    };
+    if let Some(status) = Status::from_header_map(res.headers()) {
+        return status;
+    }
+
+    let mut body = res.into_body();
+    while let Some(data) = body.data().await {
+        if let Err(err) = data {
+            return Status::from_error_generic(err);
+        }
+    }
+    match body.trailers().await {
+        Ok(trailers) => trailers
+            .and_then(|trailers| Status::from_header_map(&trailers))
+            .unwrap_or_else(|| Status::internal("missing grpc-status")),
+        Err(err) => Status::from_error_generic(err),
+    }
+}
+
+/// Whether `body` is made of exactly one length-prefixed message.
+fn is_single_message(body: &Bytes) -> bool {
+    let mut header = &body[..];
+    if header.len() < 5 {
+        return false;
+    }
+    header.advance(1);
+    let len = header.get_u32() as usize;
+    body.len() == 5 + len
+}
+
+/// A body recording the data it yields, and sending the record once it ends.
+struct RecordingBody {
+    inner: BoxBody,
+    recorded: BytesMut,
+    /// Dropped once the body fails or outgrows [`MAX_MIRRORED_SIZE`].
+    sender: Option<oneshot::Sender<Bytes>>,
+}
+
+impl Body for RecordingBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.get_mut();
+        let data = ready!(Pin::new(&mut this.inner).poll_data(cx));
+        match &data {
+            Some(Ok(data)) if this.sender.is_some() => {
+                if this.recorded.len() + data.len() > MAX_MIRRORED_SIZE {
+                    this.sender = None;
+                    this.recorded = BytesMut::new();
+                } else {
+                    this.recorded.extend_from_slice(data);
+                }
+            }
+            Some(Ok(_)) | None => {}
+            Some(Err(_)) => this.sender = None,
+        }
+        // Bodies known to be over may not be polled again.
+        if data.is_none() || this.inner.is_end_stream() {
+            if let Some(sender) = this.sender.take() {
+                let _ = sender.send(this.recorded.split().freeze());
+            }
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{local, Server};
+    use std::{convert::Infallible, time::Duration};
+    use tokio::sync::mpsc;
+
+    /// A channel to a server sending the paths and bodies of its requests to
+    /// the returned receiver.
+    fn server() -> (Channel, mpsc::UnboundedReceiver<(String, Bytes)>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let svc = tower::service_fn(move |req: Request<hyper::Body>| {
+            let sender = sender.clone();
+            async move {
+                let path = req.uri().path().to_owned();
+                let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                sender.send((path, body)).unwrap();
+                Ok::<_, Infallible>(Status::new(crate::Code::Ok, "").to_http())
+            }
+        });
+        let (channel, incoming) = local::pair();
+        tokio::spawn(
+            Server::builder()
+                .fallback_service(svc)
+                .serve_with_incoming(incoming),
+        );
+        (channel, receiver)
+    }
+
+    async fn call(percentage: u64, body: &'static [u8]) -> Option<(String, Bytes)> {
+        let (primary, mut primary_requests) = server();
+        let (shadow, mut shadow_requests) = server();
+        let channel = MirrorLayer::new(shadow, percentage).layer(primary);
+
+        let req = Request::post("http://localhost/test.Echo/Call")
+            .header("content-type", "application/grpc")
+            .body(crate::body::boxed(http_body::Full::new(
+                Bytes::from_static(body),
+            )))
+            .unwrap();
+        channel.oneshot(req).await.unwrap();
+        assert_eq!(
+            primary_requests.recv().await.unwrap(),
+            ("/test.Echo/Call".to_owned(), Bytes::from_static(body))
+        );
+
+        tokio::time::timeout(Duration::from_millis(100), shadow_requests.recv())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    #[tokio::test]
+    async fn mirrors_calls() {
+        let body = b"\0\0\0\0\x02hi";
+        assert_eq!(
+            call(100, body).await,
+            Some(("/test.Echo/Call".to_owned(), Bytes::from_static(body)))
+        );
+    }
+
+    #[tokio::test]
+    async fn mirrors_the_percentage_of_calls() {
+        assert_eq!(call(0, b"\0\0\0\0\x02hi").await, None);
+    }
+
+    #[tokio::test]
+    async fn mirrors_single_messages_only() {
+        assert_eq!(call(100, b"\0\0\0\0\x01a\0\0\0\0\x01b").await, None);
+    }
+}
@@ -1,17 +1,51 @@
 //! Client implementation and builder.
 
+mod backoff;
+mod dns;
 mod endpoint;
+#[cfg(feature = "http3")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+mod http3;
+mod load_balancing;
+mod mirror;
+mod outlier_detection;
+mod resolver;
+mod service_config;
+mod state;
 #[cfg(feature = "tls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 mod tls;
 
+#[cfg(windows)]
+pub use super::service::ImpersonationLevel;
+pub use backoff::ConnectBackoff;
+pub use dns::DnsResolver;
 pub use endpoint::Endpoint;
+#[cfg(feature = "http3")]
+pub use http3::Http3Config;
+pub use load_balancing::{EndpointState, EndpointStateChange, LoadBalancingPolicy};
+pub(crate) use load_balancing::{LoadParser, OnStateChange};
+pub use mirror::{Mirror, MirrorLayer};
+pub use outlier_detection::OutlierDetection;
+pub(crate) use resolver::ResolveNow;
+pub use resolver::{Resolution, ResolutionStream, Resolver, ResolverRegistry};
+use service_config::SharedServiceConfig;
+pub use service_config::{
+    HedgingPolicy, MethodConfig, RetryPolicy, RetryThrottle, ServiceConfig, ServiceConfigError,
+};
+pub use state::ChannelState;
+pub(crate) use state::StateTracker;
 #[cfg(feature = "tls")]
 pub use tls::ClientTlsConfig;
 
-use super::service::{Connection, DynamicServiceStream, SharedExec};
+use super::service::{
+    grpc_timeout::try_parse_grpc_timeout, hedge, retry, Connection, DynamicServiceStream, Ejecting,
+    PickFirst, RoundRobin, SharedExec, WeightedBalance,
+};
 use crate::body::BoxBody;
-use crate::transport::Executor;
+use crate::metadata::GRPC_TIMEOUT_HEADER;
+use crate::request::WaitForReady;
+use crate::transport::{deadline, Executor};
 use bytes::Bytes;
 use http::{
     uri::{InvalidUri, Uri},
@@ -23,24 +57,30 @@ use std::{
     future::Future,
     hash::Hash,
     pin::Pin,
+    sync::Arc,
     task::{ready, Context, Poll},
+    time::Duration,
 };
 use tokio::{
     io::{AsyncRead, AsyncWrite},
-    sync::mpsc::{channel, Sender},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        watch,
+    },
+    time::Instant,
 };
 
 use tower::balance::p2c::Balance;
 use tower::{
     buffer::{self, Buffer},
-    discover::{Change, Discover},
+    discover::Change,
     util::{BoxService, Either},
     Service,
 };
 
-type Svc = Either<Connection, BoxService<Request<BoxBody>, Response<hyper::Body>, crate::Error>>;
+type Svc = Either<Connection, BoxService<Request<BoxBody>, Response<BoxBody>, crate::Error>>;
 
-const DEFAULT_BUFFER_SIZE: usize = 1024;
+pub(crate) const DEFAULT_BUFFER_SIZE: usize = 1024;
 
 /// A default batteries included `transport` channel.
 ///
@@ -68,13 +108,22 @@ const DEFAULT_BUFFER_SIZE: usize = 1024;
 #[derive(Clone)]
 pub struct Channel {
     svc: Buffer<Svc, Request<BoxBody>>,
+    service_config: SharedServiceConfig,
+    transparent_retry: bool,
+    deadline_propagation: Option<Duration>,
+    state: watch::Receiver<ChannelState>,
 }
 
 /// A future that resolves to an HTTP response.
 ///
 /// This is returned by the `Service::call` on [`Channel`].
 pub struct ResponseFuture {
-    inner: buffer::future::ResponseFuture<<Svc as Service<Request<BoxBody>>>::Future>,
+    inner: ResponseFutureInner,
+}
+
+enum ResponseFutureInner {
+    Buffered(buffer::future::ResponseFuture<<Svc as Service<Request<BoxBody>>>::Future>),
+    Retry(crate::transport::BoxFuture<'static, Result<Response<BoxBody>, crate::Error>>),
 }
 
 impl Channel {
@@ -109,19 +158,66 @@ impl Channel {
     ///
     /// This creates a [`Channel`] that will load balance across all the
     /// provided endpoints.
+    ///
+    /// Settings applying to the whole channel take the most restrictive
+    /// value of the endpoints: requests the server didn't process are only
+    /// replayed if every endpoint allows it, see
+    /// [`Endpoint::transparent_retry`], and deadlines are only propagated if
+    /// every endpoint propagates them, with the largest of their offsets, see
+    /// [`Endpoint::deadline_propagation`]. The [`ServiceConfig`] is the one
+    /// of the first endpoint having one.
     pub fn balance_list(list: impl Iterator<Item = Endpoint>) -> Self {
-        let (channel, tx) = Self::balance_channel(DEFAULT_BUFFER_SIZE);
-        list.for_each(|endpoint| {
+        Self::balance_list_with_policy(list, LoadBalancingPolicy::default())
+    }
+
+    /// Balance a list of [`Endpoint`]'s using `policy`.
+    ///
+    /// With [`LoadBalancingPolicy::PickFirst`], endpoints are tried in the
+    /// order of `list`. Settings applying to the whole channel are taken from
+    /// the endpoints as with [`Channel::balance_list`].
+    ///
+    /// ```
+    /// # use tonic::transport::{channel::LoadBalancingPolicy, Channel, Endpoint};
+    /// # async fn f() {
+    /// let endpoints = ["http://[::1]:50051", "http://127.0.0.1:50051"]
+    ///     .into_iter()
+    ///     .map(Endpoint::from_static);
+    /// let channel = Channel::balance_list_with_policy(endpoints, LoadBalancingPolicy::PickFirst);
+    /// # }
+    /// ```
+    pub fn balance_list_with_policy(
+        list: impl Iterator<Item = Endpoint>,
+        policy: LoadBalancingPolicy,
+    ) -> Self {
+        let list = list.collect::<Vec<_>>();
+        let transparent_retry = list.iter().all(|endpoint| endpoint.transparent_retry);
+        let deadline_propagation = list.iter().try_fold(Duration::ZERO, |offset, endpoint| {
+            endpoint.deadline_propagation.map(|o| o.max(offset))
+        });
+        let service_config = list
+            .iter()
+            .find_map(|endpoint| endpoint.service_config.clone());
+        let (channel, tx) = Self::balance_channel_with_policy(DEFAULT_BUFFER_SIZE, policy);
+        list.into_iter().for_each(|endpoint| {
             tx.try_send(Change::Insert(endpoint.uri.clone(), endpoint))
                 .unwrap();
         });
 
-        channel
+        let channel = channel
+            .transparent_retry(transparent_retry)
+            .deadline_propagation(deadline_propagation);
+        match service_config {
+            Some(config) => channel.service_config(config),
+            None => channel,
+        }
     }
 
     /// Balance a list of [`Endpoint`]'s.
     ///
     /// This creates a [`Channel`] that will listen to a stream of change events and will add or remove provided endpoints.
+    ///
+    /// The [`ServiceConfig`] of the endpoints isn't applied, the channel is
+    /// given one with [`Channel::service_config`].
     pub fn balance_channel<K>(capacity: usize) -> (Self, Sender<Change<K, Endpoint>>)
     where
         K: Hash + Eq + Send + Clone + 'static,
@@ -129,11 +225,39 @@ impl Channel {
         Self::balance_channel_with_executor(capacity, SharedExec::tokio())
     }
 
+    /// Balance a list of [`Endpoint`]'s using `policy`.
+    ///
+    /// This creates a [`Channel`] that will listen to a stream of change events and will add or remove provided endpoints.
+    ///
+    /// The [`ServiceConfig`] of the endpoints isn't applied, as with
+    /// [`Channel::balance_channel`].
+    pub fn balance_channel_with_policy<K>(
+        capacity: usize,
+        policy: LoadBalancingPolicy,
+    ) -> (Self, Sender<Change<K, Endpoint>>)
+    where
+        K: Hash + Eq + Send + Clone + 'static,
+    {
+        let (tx, rx) = channel(capacity);
+        let channel = Self::balance(
+            rx,
+            DEFAULT_BUFFER_SIZE,
+            SharedExec::tokio(),
+            policy,
+            true,
+            Some(Duration::ZERO),
+        );
+        (channel, tx)
+    }
+
     /// Balance a list of [`Endpoint`]'s.
     ///
     /// This creates a [`Channel`] that will listen to a stream of change events and will add or remove provided endpoints.
     ///
     /// The [`Channel`] will use the given executor to spawn async tasks.
+    ///
+    /// The [`ServiceConfig`] of the endpoints isn't applied, as with
+    /// [`Channel::balance_channel`].
     pub fn balance_channel_with_executor<K, E>(
         capacity: usize,
         executor: E,
@@ -143,36 +267,169 @@ impl Channel {
         E: Executor<Pin<Box<dyn Future<Output = ()> + Send>>> + Send + Sync + 'static,
     {
         let (tx, rx) = channel(capacity);
-        let list = DynamicServiceStream::new(rx);
-        (Self::balance(list, DEFAULT_BUFFER_SIZE, executor), tx)
+        let policy = LoadBalancingPolicy::default();
+        (
+            Self::balance(
+                rx,
+                DEFAULT_BUFFER_SIZE,
+                executor,
+                policy,
+                true,
+                Some(Duration::ZERO),
+            ),
+            tx,
+        )
     }
 
-    pub(crate) fn new<C>(connector: C, endpoint: Endpoint) -> Self
+    /// Balance a list of weighted [`Endpoint`]'s.
+    ///
+    /// This creates a [`Channel`] that will listen to a stream of change
+    /// events adding or removing endpoints along with their weight. Ready
+    /// endpoints are sent a share of the requests proportional to their
+    /// weight, endpoints with a weight of 0 aren't sent any request.
+    ///
+    /// Inserting a key again with an endpoint to the same URI only updates
+    /// its weight, keeping its connection.
+    ///
+    /// The [`ServiceConfig`] of the endpoints isn't applied, as with
+    /// [`Channel::balance_channel`].
+    ///
+    /// ```
+    /// # use tonic::transport::{Channel, Endpoint};
+    /// # use tower::discover::Change;
+    /// # async fn f() {
+    /// let (channel, tx) = Channel::balance_weighted_channel(16);
+    /// let large = Endpoint::from_static("http://10.0.0.1:50051");
+    /// let small = Endpoint::from_static("http://10.0.0.2:50051");
+    /// tx.send(Change::Insert("large", (large.clone(), 3))).await.unwrap();
+    /// tx.send(Change::Insert("small", (small, 1))).await.unwrap();
+    ///
+    /// // Later, give the large fleet more traffic.
+    /// tx.send(Change::Insert("large", (large, 5))).await.unwrap();
+    /// # }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn balance_weighted_channel<K>(
+        capacity: usize,
+    ) -> (Self, Sender<Change<K, (Endpoint, u32)>>)
+    where
+        K: Hash + Eq + Send + Clone + 'static,
+    {
+        let (tx, rx) = channel(capacity);
+        let (tracker, state) = StateTracker::new();
+        let svc = BoxService::new(WeightedBalance::new(rx, tracker));
+        (
+            Self::buffered(
+                svc,
+                DEFAULT_BUFFER_SIZE,
+                SharedExec::tokio(),
+                state,
+                true,
+                Some(Duration::ZERO),
+            ),
+            tx,
+        )
+    }
+
+    /// Balance over the addresses `resolver` resolves the endpoint's URI to.
+    ///
+    /// `endpoint` acts as a template, every resolved address is connected
+    /// to with a copy of it pointing at that address. Service configs
+    /// delivered by the resolver replace the one set on `endpoint`.
+    ///
+    /// The resolver is driven by a task spawned on the endpoint's executor.
+    pub fn balance_resolver(mut endpoint: Endpoint, resolver: impl Resolver) -> Self {
+        let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+        let executor = endpoint.executor.clone();
+        let service_config = SharedServiceConfig::new(endpoint.service_config.clone());
+        let resolver: Arc<dyn Resolver> = Arc::new(resolver);
+        let stream = resolver.resolve(&endpoint.uri);
+        endpoint.resolve_now = Some(ResolveNow::new(resolver, endpoint.uri.clone()));
+
+        let (tx, rx) = channel(buffer_size);
+        let policy = endpoint.load_balancing_policy;
+        let transparent_retry = endpoint.transparent_retry;
+        let deadline_propagation = endpoint.deadline_propagation;
+        let mut channel = Self::balance(
+            rx,
+            buffer_size,
+            executor.clone(),
+            policy,
+            transparent_retry,
+            deadline_propagation,
+        );
+        channel.service_config = service_config.clone();
+
+        executor.execute(Box::pin(resolver::drive(
+            stream,
+            endpoint,
+            tx,
+            service_config,
+        )));
+
+        channel
+    }
+
+    /// Balance over the addresses resolved by the resolver `registry` has
+    /// for the scheme of the endpoint's URI.
+    ///
+    /// See [`Channel::balance_resolver`]. Returns an error if no resolver is
+    /// registered for the scheme.
+    pub fn balance_registry(
+        endpoint: Endpoint,
+        registry: &ResolverRegistry,
+    ) -> Result<Self, super::Error> {
+        let resolver = registry
+            .get(&endpoint.uri)
+            .ok_or_else(super::Error::new_invalid_uri)?;
+        Ok(Self::balance_resolver(endpoint, resolver))
+    }
+
+    pub(crate) fn new<C>(connector: C, mut endpoint: Endpoint) -> Self
     where
         C: Service<Uri> + Send + 'static,
         C::Error: Into<crate::Error> + Send,
         C::Future: Unpin + Send,
         C::Response: AsyncRead + AsyncWrite + HyperConnection + Unpin + Send + 'static,
     {
+        let (tracker, state) = StateTracker::new();
+        endpoint.state_tracker = Some(tracker);
         let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
         let executor = endpoint.executor.clone();
+        let service_config = SharedServiceConfig::new(endpoint.service_config.clone());
+        let transparent_retry = endpoint.transparent_retry;
+        let deadline_propagation = endpoint.deadline_propagation;
 
         let svc = Connection::lazy(connector, endpoint);
         let (svc, worker) = Buffer::pair(Either::A(svc), buffer_size);
         executor.execute(Box::pin(worker));
 
-        Channel { svc }
+        Channel {
+            svc,
+            service_config,
+            transparent_retry,
+            deadline_propagation,
+            state,
+        }
     }
 
-    pub(crate) async fn connect<C>(connector: C, endpoint: Endpoint) -> Result<Self, super::Error>
+    pub(crate) async fn connect<C>(
+        connector: C,
+        mut endpoint: Endpoint,
+    ) -> Result<Self, super::Error>
     where
         C: Service<Uri> + Send + 'static,
         C::Error: Into<crate::Error> + Send,
         C::Future: Unpin + Send,
         C::Response: AsyncRead + AsyncWrite + HyperConnection + Unpin + Send + 'static,
     {
+        let (tracker, state) = StateTracker::new();
+        endpoint.state_tracker = Some(tracker);
         let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
         let executor = endpoint.executor.clone();
+        let service_config = SharedServiceConfig::new(endpoint.service_config.clone());
+        let transparent_retry = endpoint.transparent_retry;
+        let deadline_propagation = endpoint.deadline_propagation;
 
         let svc = Connection::connect(connector, endpoint)
             .await
@@ -180,28 +437,181 @@ impl Channel {
         let (svc, worker) = Buffer::pair(Either::A(svc), buffer_size);
         executor.execute(Box::pin(worker));
 
-        Ok(Channel { svc })
+        Ok(Channel {
+            svc,
+            service_config,
+            transparent_retry,
+            deadline_propagation,
+            state,
+        })
     }
 
-    pub(crate) fn balance<D, E>(discover: D, buffer_size: usize, executor: E) -> Self
+    pub(crate) fn balance<K, E>(
+        changes: Receiver<Change<K, Endpoint>>,
+        buffer_size: usize,
+        executor: E,
+        policy: LoadBalancingPolicy,
+        transparent_retry: bool,
+        deadline_propagation: Option<Duration>,
+    ) -> Self
     where
-        D: Discover<Service = Connection> + Unpin + Send + 'static,
-        D::Error: Into<crate::Error>,
-        D::Key: Hash + Send + Clone,
+        K: Hash + Eq + Send + Clone + 'static,
         E: Executor<crate::transport::BoxFuture<'static, ()>> + Send + Sync + 'static,
     {
-        let svc = Balance::new(discover);
+        let (tracker, state) = StateTracker::new();
+        let discover = DynamicServiceStream::new(changes, tracker);
+        let svc = match policy {
+            LoadBalancingPolicy::PowerOfTwoChoices => {
+                BoxService::new(Balance::new(Ejecting::new(discover)))
+            }
+            LoadBalancingPolicy::PickFirst => BoxService::new(PickFirst::new(discover)),
+            LoadBalancingPolicy::RoundRobin => {
+                BoxService::new(RoundRobin::new(Ejecting::new(discover)))
+            }
+        };
+        Self::buffered(
+            svc,
+            buffer_size,
+            executor,
+            state,
+            transparent_retry,
+            deadline_propagation,
+        )
+    }
 
-        let svc = BoxService::new(svc);
+    pub(crate) fn buffered<E>(
+        svc: BoxService<Request<BoxBody>, Response<BoxBody>, crate::Error>,
+        buffer_size: usize,
+        executor: E,
+        state: watch::Receiver<ChannelState>,
+        transparent_retry: bool,
+        deadline_propagation: Option<Duration>,
+    ) -> Self
+    where
+        E: Executor<crate::transport::BoxFuture<'static, ()>> + Send + Sync + 'static,
+    {
         let (svc, worker) = Buffer::pair(Either::B(svc), buffer_size);
         executor.execute(Box::pin(worker));
 
-        Channel { svc }
+        Channel {
+            svc,
+            service_config: SharedServiceConfig::default(),
+            transparent_retry,
+            deadline_propagation,
+            state,
+        }
+    }
+
+    /// Sets whether requests the server didn't process are replayed, see
+    /// [`Endpoint::transparent_retry`].
+    ///
+    /// Channels balancing over endpoints sent to them once they are created,
+    /// such as with [`Channel::balance_channel`], or sending their requests
+    /// to a service, such as with
+    /// [`local::direct`](crate::transport::local::direct), replay them by
+    /// default.
+    pub fn transparent_retry(self, enabled: bool) -> Self {
+        Channel {
+            transparent_retry: enabled,
+            ..self
+        }
+    }
+
+    /// Sets the offset subtracted from the time remaining until the deadline
+    /// of the call being handled, when applied to the calls made on this
+    /// channel, see [`Endpoint::deadline_propagation`].
+    ///
+    /// Like [`Channel::transparent_retry`], this configures channels created
+    /// without an endpoint, which propagate deadlines without an offset by
+    /// default.
+    pub fn deadline_propagation(self, offset: impl Into<Option<Duration>>) -> Self {
+        Channel {
+            deadline_propagation: offset.into(),
+            ..self
+        }
+    }
+
+    /// Sets the [`ServiceConfig`] used by the channel, see
+    /// [`Endpoint::service_config`].
+    ///
+    /// This configures channels balancing over endpoints sent to them once
+    /// they are created, such as with [`Channel::balance_channel`], which
+    /// don't apply the config of the endpoints. Clones of the channel share
+    /// its config, which a [`Resolver`] may replace.
+    ///
+    /// ```
+    /// # use tonic::transport::channel::{MethodConfig, RetryPolicy, ServiceConfig};
+    /// # use tonic::transport::Channel;
+    /// # use tonic::Code;
+    /// # async fn f() {
+    /// let (channel, tx) = Channel::balance_channel::<usize>(16);
+    /// let channel = channel.service_config(ServiceConfig::new().method_config(
+    ///     MethodConfig::new()
+    ///         .all_services()
+    ///         .retry_policy(RetryPolicy::new(3).retryable_status_code(Code::Unavailable)),
+    /// ));
+    /// # }
+    /// ```
+    pub fn service_config(self, config: ServiceConfig) -> Self {
+        self.service_config.set(config);
+        self
+    }
+
+    /// The current connectivity state of the channel.
+    ///
+    /// This doesn't cause the channel to connect, an idle channel connects
+    /// when it is sent a request.
+    pub fn state(&self) -> ChannelState {
+        state::current(&self.state)
+    }
+
+    /// Wait for the state of the channel to be different from `current`,
+    /// returning the new state.
+    ///
+    /// This returns right away if the state already differs from `current`,
+    /// so calling it in a loop with the last returned state observes every
+    /// change. A [`ChannelState::Shutdown`] channel never changes state,
+    /// waiting for it to do so never completes.
+    ///
+    /// ```
+    /// # use tonic::transport::{channel::ChannelState, Channel};
+    /// # async fn f(channel: Channel) {
+    /// let mut state = channel.state();
+    /// while state != ChannelState::Ready {
+    ///     state = channel.wait_for_state_change(state).await;
+    /// }
+    /// # }
+    /// ```
+    pub async fn wait_for_state_change(&self, current: ChannelState) -> ChannelState {
+        let mut rx = self.state.clone();
+        loop {
+            let state = state::current(&rx);
+            if state != current {
+                return state;
+            }
+            if rx.changed().await.is_err() {
+                if current == ChannelState::Shutdown {
+                    std::future::pending::<()>().await;
+                }
+                return ChannelState::Shutdown;
+            }
+        }
+    }
+}
+
+/// Lower the `grpc-timeout` of `request` to `timeout`.
+fn apply_timeout(request: &mut Request<BoxBody>, timeout: Duration) {
+    let current = try_parse_grpc_timeout(request.headers()).ok().flatten();
+    if current.map(|current| current > timeout).unwrap_or(true) {
+        let value = crate::request::duration_to_grpc_timeout(timeout)
+            .parse()
+            .expect("grpc-timeout is a valid header value");
+        request.headers_mut().insert(GRPC_TIMEOUT_HEADER, value);
     }
 }
 
 impl Service<http::Request<BoxBody>> for Channel {
-    type Response = http::Response<super::Body>;
+    type Response = http::Response<BoxBody>;
     type Error = super::Error;
     type Future = ResponseFuture;
 
@@ -209,18 +619,82 @@ impl Service<http::Request<BoxBody>> for Channel {
         Service::poll_ready(&mut self.svc, cx).map_err(super::Error::from_source)
     }
 
-    fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
-        let inner = Service::call(&mut self.svc, request);
+    fn call(&mut self, mut request: http::Request<BoxBody>) -> Self::Future {
+        let applied = self.service_config.get();
+        let config = applied
+            .as_ref()
+            .and_then(|applied| applied.config.find(request.uri().path()));
+        let throttle = applied
+            .as_ref()
+            .and_then(|applied| applied.throttle.clone());
+        let retry_policy = config
+            .and_then(|config| config.retry_policy.as_ref())
+            .filter(|policy| policy.max_attempts > 1);
+        let hedging_policy = config
+            .and_then(|config| config.hedging_policy.as_ref())
+            .filter(|policy| policy.max_attempts > 1);
+
+        if let Some(timeout) = config.and_then(|config| config.timeout) {
+            apply_timeout(&mut request, timeout);
+        }
+        if let (Some(offset), Some(deadline)) = (self.deadline_propagation, deadline::current()) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            apply_timeout(&mut request, remaining.saturating_sub(offset));
+        }
+
+        let wait_for_ready = request
+            .extensions()
+            .get::<WaitForReady>()
+            .map(|_| retry::ReadyBackoff::new(self.state.clone()));
+
+        // Retried and hedged calls take over the readiness of `self`, which
+        // is replaced by a fresh clone of the buffer that must be driven to
+        // readiness again.
+        let inner = if let Some(policy) = retry_policy {
+            let svc = self.svc.clone();
+            let svc = std::mem::replace(&mut self.svc, svc);
+            ResponseFutureInner::Retry(Box::pin(retry::call(
+                svc,
+                request,
+                policy.clone(),
+                throttle,
+                wait_for_ready,
+            )))
+        } else if let Some(policy) = hedging_policy {
+            let svc = self.svc.clone();
+            let svc = std::mem::replace(&mut self.svc, svc);
+            ResponseFutureInner::Retry(Box::pin(hedge::call(
+                svc,
+                request,
+                policy.clone(),
+                throttle,
+                wait_for_ready,
+            )))
+        } else if let Some(ready) = wait_for_ready {
+            let svc = self.svc.clone();
+            let svc = std::mem::replace(&mut self.svc, svc);
+            ResponseFutureInner::Retry(Box::pin(retry::wait_for_ready(svc, request, ready)))
+        } else if self.transparent_retry {
+            let svc = self.svc.clone();
+            let svc = std::mem::replace(&mut self.svc, svc);
+            ResponseFutureInner::Retry(Box::pin(retry::transparent(svc, request)))
+        } else {
+            ResponseFutureInner::Buffered(Service::call(&mut self.svc, request))
+        };
 
         ResponseFuture { inner }
     }
 }
 
 impl Future for ResponseFuture {
-    type Output = Result<Response<hyper::Body>, super::Error>;
+    type Output = Result<Response<BoxBody>, super::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let val = ready!(Pin::new(&mut self.inner).poll(cx)).map_err(super::Error::from_source)?;
+        let val = match &mut self.inner {
+            ResponseFutureInner::Buffered(inner) => ready!(Pin::new(inner).poll(cx)),
+            ResponseFutureInner::Retry(inner) => ready!(inner.as_mut().poll(cx)),
+        }
+        .map_err(super::Error::from_source)?;
         Ok(val).into()
     }
 }
@@ -236,3 +710,193 @@ impl fmt::Debug for ResponseFuture {
         f.debug_struct("ResponseFuture").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+    use http::HeaderMap;
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    /// The address of a server refusing every stream if `status` is `None`,
+    /// or else answering them with `status`, and the headers of the streams
+    /// it was sent.
+    async fn server(status: Option<Code>) -> (String, Arc<Mutex<Vec<HeaderMap>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let streams = Arc::new(Mutex::new(Vec::new()));
+        tokio::spawn({
+            let streams = streams.clone();
+            async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    let streams = streams.clone();
+                    tokio::spawn(async move {
+                        let mut connection = h2::server::handshake(socket).await.unwrap();
+                        while let Some(Ok((request, mut respond))) = connection.accept().await {
+                            streams.lock().unwrap().push(request.headers().clone());
+                            match status {
+                                None => respond.send_reset(h2::Reason::REFUSED_STREAM),
+                                Some(code) => {
+                                    let response = Response::builder()
+                                        .header("grpc-status", (code as i32).to_string())
+                                        .body(())
+                                        .unwrap();
+                                    respond.send_response(response, true).unwrap();
+                                }
+                            }
+                        }
+                    });
+                }
+            }
+        });
+        (addr, streams)
+    }
+
+    async fn call(
+        channel: Channel,
+        addr: &str,
+    ) -> Result<Response<BoxBody>, crate::transport::Error> {
+        let request = Request::post(format!("{}/test.Echo/Call", addr))
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        channel.oneshot(request).await
+    }
+
+    #[tokio::test]
+    async fn balanced_channels_replay_unprocessed_requests() {
+        let (addr, streams) = server(None).await;
+        let endpoint = Endpoint::from_shared(addr.clone()).unwrap();
+        let channel = Channel::balance_list(std::iter::once(endpoint));
+        assert!(call(channel, &addr).await.is_err());
+        assert_eq!(streams.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn balanced_channels_without_transparent_retry_send_calls_once() {
+        let (addr, streams) = server(None).await;
+        let endpoint = Endpoint::from_shared(addr.clone())
+            .unwrap()
+            .transparent_retry(false);
+        let channel = Channel::balance_list(std::iter::once(endpoint));
+        assert!(call(channel, &addr).await.is_err());
+        assert_eq!(streams.lock().unwrap().len(), 1);
+
+        let (channel, tx) = Channel::balance_channel(1);
+        let endpoint = Endpoint::from_shared(addr.clone()).unwrap();
+        tx.send(Change::Insert((), endpoint)).await.unwrap();
+        assert!(call(channel.transparent_retry(false), &addr).await.is_err());
+        assert_eq!(streams.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn balanced_channels_propagate_deadlines() {
+        let (addr, streams) = server(Some(Code::Ok)).await;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let endpoint = Endpoint::from_shared(addr.clone()).unwrap();
+        let channel = Channel::balance_list(std::iter::once(endpoint));
+        deadline::scope(Some(deadline), call(channel, &addr))
+            .await
+            .unwrap();
+        assert!(streams.lock().unwrap()[0].contains_key(GRPC_TIMEOUT_HEADER));
+    }
+
+    #[tokio::test]
+    async fn balanced_channels_without_deadline_propagation_send_no_timeout() {
+        let (addr, streams) = server(Some(Code::Ok)).await;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let endpoint = Endpoint::from_shared(addr.clone())
+            .unwrap()
+            .deadline_propagation(None);
+        let channel = Channel::balance_list(std::iter::once(endpoint));
+        deadline::scope(Some(deadline), call(channel, &addr))
+            .await
+            .unwrap();
+
+        let (channel, tx) = Channel::balance_channel(1);
+        let endpoint = Endpoint::from_shared(addr.clone()).unwrap();
+        tx.send(Change::Insert((), endpoint)).await.unwrap();
+        let channel = channel.deadline_propagation(None);
+        deadline::scope(Some(deadline), call(channel, &addr))
+            .await
+            .unwrap();
+
+        let streams = streams.lock().unwrap();
+        assert_eq!(streams.len(), 2);
+        assert!(streams
+            .iter()
+            .all(|headers| !headers.contains_key(GRPC_TIMEOUT_HEADER)));
+    }
+
+    #[tokio::test]
+    async fn balanced_channels_propagate_deadlines_if_every_endpoint_does() {
+        let (first, first_streams) = server(Some(Code::Ok)).await;
+        let (second, second_streams) = server(Some(Code::Ok)).await;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let endpoints = [
+            Endpoint::from_shared(first.clone()).unwrap(),
+            Endpoint::from_shared(second)
+                .unwrap()
+                .deadline_propagation(None),
+        ];
+        let channel = Channel::balance_list(endpoints.into_iter());
+        deadline::scope(Some(deadline), call(channel, &first))
+            .await
+            .unwrap();
+
+        let first_streams = first_streams.lock().unwrap();
+        let second_streams = second_streams.lock().unwrap();
+        assert_eq!(first_streams.len() + second_streams.len(), 1);
+        assert!(first_streams
+            .iter()
+            .chain(second_streams.iter())
+            .all(|headers| !headers.contains_key(GRPC_TIMEOUT_HEADER)));
+    }
+
+    #[tokio::test]
+    async fn balanced_channels_retry() {
+        let (addr, streams) = server(Some(Code::Unavailable)).await;
+        let policy = RetryPolicy::new(2)
+            .initial_backoff(Duration::from_millis(1))
+            .retryable_status_code(Code::Unavailable);
+        let endpoint = Endpoint::from_shared(addr.clone())
+            .unwrap()
+            .retry_policy(policy.clone());
+        let channel = Channel::balance_list(std::iter::once(endpoint));
+        call(channel, &addr).await.unwrap();
+        assert_eq!(streams.lock().unwrap().len(), 2);
+
+        let (channel, tx) = Channel::balance_channel(1);
+        let endpoint = Endpoint::from_shared(addr.clone()).unwrap();
+        tx.send(Change::Insert((), endpoint)).await.unwrap();
+        let config = MethodConfig::new().all_services().retry_policy(policy);
+        let channel = channel.service_config(ServiceConfig::new().method_config(config));
+        call(channel, &addr).await.unwrap();
+        assert_eq!(streams.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn retried_calls_wait_for_ready() {
+        let (addr, streams) = server(None).await;
+        let policy = RetryPolicy::new(2)
+            .initial_backoff(Duration::from_millis(1))
+            .retryable_status_code(Code::Unavailable);
+        let channel = Endpoint::from_shared(addr.clone())
+            .unwrap()
+            .retry_policy(policy)
+            .connect_lazy();
+
+        let mut request = Request::post(format!("{}/test.Echo/Call", addr))
+            .header("content-type", "application/grpc")
+            .header(GRPC_TIMEOUT_HEADER, "1S")
+            .body(BoxBody::default())
+            .unwrap();
+        request.extensions_mut().insert(WaitForReady);
+        let error = channel.oneshot(request).await.unwrap_err();
+
+        let source = std::error::Error::source(&error).unwrap();
+        assert!(source.is::<crate::transport::TimeoutExpired>());
+        assert!(streams.lock().unwrap().len() > 3);
+    }
+}
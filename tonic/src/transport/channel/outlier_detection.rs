@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+/// Configures the ejection of the endpoints of a balanced channel whose
+/// requests fail more than those of the other endpoints.
+///
+/// A request fails when it can't be sent, or its response has a 5xx HTTP
+/// status or an `UNAVAILABLE` gRPC status in its headers. An endpoint is
+/// ejected when enough of its requests fail in a row, or when the
+/// proportion of its requests that failed during an interval exceeds a
+/// threshold. Ejected endpoints aren't sent requests for the ejection
+/// time, which grows each time the same endpoint is ejected again. At most
+/// [`max_ejection_percent`](Self::max_ejection_percent) of the endpoints are
+/// ejected at once.
+///
+/// This doesn't apply to [`LoadBalancingPolicy::PickFirst`](super::LoadBalancingPolicy::PickFirst).
+///
+/// ```
+/// # use tonic::transport::{channel::OutlierDetection, Endpoint};
+/// # use std::time::Duration;
+/// let endpoint = Endpoint::from_static("dns://example.com:443").outlier_detection(
+///     OutlierDetection::new()
+///         .consecutive_errors(10)
+///         .failure_percentage(50, 20)
+///         .base_ejection_time(Duration::from_secs(10)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutlierDetection {
+    pub(crate) consecutive_errors: Option<u32>,
+    pub(crate) failure_percentage: Option<(u32, u32)>,
+    pub(crate) interval: Duration,
+    pub(crate) base_ejection_time: Duration,
+    pub(crate) max_ejection_time: Duration,
+    pub(crate) max_ejection_percent: u32,
+}
+
+impl OutlierDetection {
+    /// Create the default configuration, ejecting endpoints after 5 failed
+    /// requests in a row for 30 seconds, up to 5 minutes after repeated
+    /// ejections.
+    pub fn new() -> Self {
+        OutlierDetection {
+            consecutive_errors: Some(5),
+            failure_percentage: None,
+            interval: Duration::from_secs(10),
+            base_ejection_time: Duration::from_secs(30),
+            max_ejection_time: Duration::from_secs(300),
+            max_ejection_percent: 10,
+        }
+    }
+
+    /// Eject endpoints once `errors` requests failed in a row.
+    ///
+    /// Defaults to 5.
+    pub fn consecutive_errors(self, errors: u32) -> Self {
+        OutlierDetection {
+            consecutive_errors: Some(errors),
+            ..self
+        }
+    }
+
+    /// Don't eject endpoints based on the failures in a row.
+    pub fn disable_consecutive_errors(self) -> Self {
+        OutlierDetection {
+            consecutive_errors: None,
+            ..self
+        }
+    }
+
+    /// Eject endpoints whose percentage of failed requests during an
+    /// [`interval`](Self::interval) is at least `threshold`, when they were
+    /// sent at least `min_requests` requests during that interval.
+    ///
+    /// Disabled by default.
+    pub fn failure_percentage(self, threshold: u32, min_requests: u32) -> Self {
+        OutlierDetection {
+            failure_percentage: Some((threshold.min(100), min_requests)),
+            ..self
+        }
+    }
+
+    /// Sets the interval over which the percentage of failed requests is
+    /// computed.
+    ///
+    /// The ejection time multiplier of endpoints that weren't ejected
+    /// during an interval also decreases.
+    ///
+    /// Defaults to 10 seconds.
+    pub fn interval(self, interval: Duration) -> Self {
+        OutlierDetection { interval, ..self }
+    }
+
+    /// Sets for how long an endpoint is ejected, multiplied by the number
+    /// of times it was ejected recently.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn base_ejection_time(self, time: Duration) -> Self {
+        OutlierDetection {
+            base_ejection_time: time,
+            ..self
+        }
+    }
+
+    /// Sets the maximum time an endpoint is ejected for.
+    ///
+    /// Defaults to 5 minutes, or the base ejection time if it is longer.
+    pub fn max_ejection_time(self, time: Duration) -> Self {
+        OutlierDetection {
+            max_ejection_time: time,
+            ..self
+        }
+    }
+
+    /// Sets the maximum percentage of the endpoints that are ejected at
+    /// once.
+    ///
+    /// An endpoint is only ejected if the percentage of ejected endpoints
+    /// is below this, so at least one endpoint may always be ejected.
+    ///
+    /// Defaults to 10.
+    pub fn max_ejection_percent(self, percent: u32) -> Self {
+        OutlierDetection {
+            max_ejection_percent: percent.min(100),
+            ..self
+        }
+    }
+
+    pub(crate) fn ejection_time(&self, ejections: u32) -> Duration {
+        let max = self.max_ejection_time.max(self.base_ejection_time);
+        self.base_ejection_time.saturating_mul(ejections).min(max)
+    }
+}
+
+impl Default for OutlierDetection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,285 @@
+use super::{service_config::SharedServiceConfig, Endpoint, ServiceConfig};
+use http::Uri;
+use std::{collections::HashMap, collections::HashSet, fmt, pin::Pin, sync::Arc};
+use tokio::sync::mpsc::Sender;
+use tokio_stream::{Stream, StreamExt};
+use tower::discover::Change;
+
+/// A stream of [`Resolution`]s produced by a [`Resolver`].
+pub type ResolutionStream = Pin<Box<dyn Stream<Item = Result<Resolution, crate::Error>> + Send>>;
+
+/// Resolves a target URI to the addresses a [`Channel`](super::Channel)
+/// balances over.
+///
+/// A resolver watches its target and yields a new [`Resolution`] whenever
+/// the addresses or the service config change. Every resolution replaces
+/// the previous one: addresses that are no longer part of it are removed
+/// from the channel and new ones are connected to. Errors are logged and
+/// the channel keeps using the last resolved addresses.
+///
+/// The channel stops polling the stream once all of its clones are dropped.
+///
+/// ```
+/// # use tonic::transport::channel::{Resolution, ResolutionStream, Resolver};
+/// # use tonic::transport::Uri;
+/// /// Always resolves to the same two addresses.
+/// struct Static;
+///
+/// impl Resolver for Static {
+///     fn resolve(&self, _target: &Uri) -> ResolutionStream {
+///         let resolution = Resolution::new(vec![
+///             Uri::from_static("http://10.0.0.1:50051"),
+///             Uri::from_static("http://10.0.0.2:50051"),
+///         ]);
+///         Box::pin(tokio_stream::once(Ok(resolution)))
+///     }
+/// }
+/// ```
+pub trait Resolver: Send + Sync + 'static {
+    /// Start watching `target`.
+    fn resolve(&self, target: &Uri) -> ResolutionStream;
+
+    /// Hint that the addresses of `target` may be stale.
+    ///
+    /// The channel calls this whenever connecting to one of the resolved
+    /// addresses fails or an established connection is lost. It is called
+    /// from within the connection's task and must not block, resolvers
+    /// should only schedule a new resolution.
+    fn resolve_now(&self, target: &Uri) {
+        let _ = target;
+    }
+}
+
+impl<R: Resolver + ?Sized> Resolver for Arc<R> {
+    fn resolve(&self, target: &Uri) -> ResolutionStream {
+        (**self).resolve(target)
+    }
+
+    fn resolve_now(&self, target: &Uri) {
+        (**self).resolve_now(target)
+    }
+}
+
+/// Forwards connection failures of resolved addresses to
+/// [`Resolver::resolve_now`].
+#[derive(Clone)]
+pub(crate) struct ResolveNow(Arc<dyn Fn() + Send + Sync>);
+
+impl ResolveNow {
+    pub(crate) fn new(resolver: Arc<dyn Resolver>, target: Uri) -> Self {
+        ResolveNow(Arc::new(move || resolver.resolve_now(&target)))
+    }
+
+    pub(crate) fn call(&self) {
+        (self.0)()
+    }
+}
+
+/// The result of resolving a target.
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    pub(crate) addresses: Vec<Uri>,
+    pub(crate) service_config: Option<ServiceConfig>,
+}
+
+impl Resolution {
+    /// Create a resolution to the given addresses.
+    pub fn new(addresses: Vec<Uri>) -> Self {
+        Resolution {
+            addresses,
+            service_config: None,
+        }
+    }
+
+    /// Apply `config` to the channel.
+    ///
+    /// When a resolution has no service config the previous one is kept,
+    /// which initially is the config set on the [`Endpoint`].
+    pub fn service_config(self, config: ServiceConfig) -> Self {
+        Resolution {
+            service_config: Some(config),
+            ..self
+        }
+    }
+
+    /// The resolved addresses.
+    pub fn addresses(&self) -> &[Uri] {
+        &self.addresses
+    }
+}
+
+/// A set of [`Resolver`]s keyed by the URI scheme they handle.
+///
+/// ```
+/// # use tonic::transport::channel::{Resolution, ResolutionStream, Resolver, ResolverRegistry};
+/// # use tonic::transport::{Channel, Uri};
+/// # struct Static;
+/// # impl Resolver for Static {
+/// #     fn resolve(&self, _target: &Uri) -> ResolutionStream {
+/// #         Box::pin(tokio_stream::empty())
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut registry = ResolverRegistry::new();
+/// registry.register("static", Static);
+///
+/// let channel = Channel::balance_registry(Channel::from_static("static://greeter"), &registry)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct ResolverRegistry {
+    resolvers: HashMap<String, Arc<dyn Resolver>>,
+}
+
+impl ResolverRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        ResolverRegistry::default()
+    }
+
+    /// Use `resolver` for targets with the given scheme, replacing any
+    /// resolver previously registered for it.
+    pub fn register(&mut self, scheme: impl Into<String>, resolver: impl Resolver) {
+        self.resolvers
+            .insert(scheme.into().to_ascii_lowercase(), Arc::new(resolver));
+    }
+
+    /// Returns the resolver handling `target`, if any.
+    pub fn get(&self, target: &Uri) -> Option<Arc<dyn Resolver>> {
+        let scheme = target.scheme_str()?.to_ascii_lowercase();
+        self.resolvers.get(&scheme).cloned()
+    }
+}
+
+impl fmt::Debug for ResolverRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResolverRegistry")
+            .field("schemes", &self.resolvers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Feed the resolutions of `stream` to a balanced channel.
+///
+/// Each address is connected to with a copy of `template` pointing at it.
+pub(crate) async fn drive(
+    mut stream: ResolutionStream,
+    template: Endpoint,
+    tx: Sender<Change<Uri, Endpoint>>,
+    service_config: SharedServiceConfig,
+) {
+    let mut current = HashSet::new();
+
+    while let Some(resolution) = stream.next().await {
+        let resolution = match resolution {
+            Ok(resolution) => resolution,
+            Err(error) => {
+                tracing::debug!("resolver error: {}", error);
+                continue;
+            }
+        };
+
+        if let Some(config) = resolution.service_config {
+            service_config.set(config);
+        }
+
+        let addresses: HashSet<Uri> = resolution.addresses.iter().cloned().collect();
+
+        for removed in current.difference(&addresses) {
+            if tx.send(Change::Remove(removed.clone())).await.is_err() {
+                return;
+            }
+        }
+
+        // Insert in the order of the resolution, which pick_first follows.
+        let added = resolution
+            .addresses
+            .iter()
+            .filter(|address| !current.contains(*address));
+        for added in added {
+            let endpoint = Endpoint {
+                uri: added.clone(),
+                ..template.clone()
+            };
+            if tx
+                .send(Change::Insert(added.clone(), endpoint))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+
+        current = addresses;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::channel::MethodConfig;
+    use tokio::sync::mpsc;
+
+    struct Fixed(Vec<Resolution>);
+
+    impl Resolver for Fixed {
+        fn resolve(&self, _target: &Uri) -> ResolutionStream {
+            Box::pin(tokio_stream::iter(self.0.clone().into_iter().map(Ok)))
+        }
+    }
+
+    fn uri(s: &'static str) -> Uri {
+        Uri::from_static(s)
+    }
+
+    #[test]
+    fn registry_matches_scheme() {
+        let mut registry = ResolverRegistry::new();
+        registry.register("Fixed", Fixed(Vec::new()));
+
+        assert!(registry.get(&uri("fixed://service")).is_some());
+        assert!(registry.get(&uri("FIXED://service")).is_some());
+        assert!(registry.get(&uri("http://service")).is_none());
+    }
+
+    #[tokio::test]
+    async fn applies_resolution_changes() {
+        let resolver = Fixed(vec![
+            Resolution::new(vec![uri("http://a:1"), uri("http://b:1")]),
+            Resolution::new(vec![uri("http://b:1"), uri("http://c:1")])
+                .service_config(ServiceConfig::new().method_config(MethodConfig::new())),
+        ]);
+        let (tx, mut rx) = mpsc::channel(16);
+        let service_config = SharedServiceConfig::default();
+
+        drive(
+            resolver.resolve(&uri("fixed://service")),
+            Endpoint::from_static("http://template")
+                .user_agent("resolved")
+                .unwrap(),
+            tx,
+            service_config.clone(),
+        )
+        .await;
+
+        let mut inserted = Vec::new();
+        let mut removed = Vec::new();
+        while let Some(change) = rx.recv().await {
+            match change {
+                Change::Insert(key, endpoint) => {
+                    assert_eq!(key, endpoint.uri);
+                    assert!(endpoint.user_agent.is_some());
+                    inserted.push(key.to_string());
+                }
+                Change::Remove(key) => removed.push(key.to_string()),
+            }
+        }
+        inserted.sort();
+
+        assert_eq!(inserted, ["http://a:1/", "http://b:1/", "http://c:1/"]);
+        assert_eq!(removed, ["http://a:1/"]);
+        assert!(service_config.get().is_some());
+    }
+}
@@ -0,0 +1,1027 @@
+use crate::Code;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+/// The spec caps `maxAttempts` at 5, any larger value is treated as 5.
+const MAX_ATTEMPTS_LIMIT: usize = 5;
+
+/// Per-channel configuration following the [gRPC service config] format.
+///
+/// A service config is a list of [`MethodConfig`]s, each applying to a set of
+/// methods. When a request is sent the most specific matching config is used:
+/// an exact method match first, then a match on the service name and finally
+/// a default config that applies to every method.
+///
+/// ```
+/// # use tonic::transport::channel::{MethodConfig, RetryPolicy, ServiceConfig};
+/// # use tonic::Code;
+/// let config = ServiceConfig::new().method_config(
+///     MethodConfig::new()
+///         .service("helloworld.Greeter")
+///         .retry_policy(RetryPolicy::new(3).retryable_status_code(Code::Unavailable)),
+/// );
+/// ```
+///
+/// [gRPC service config]: https://github.com/grpc/grpc/blob/master/doc/service_config.md
+#[derive(Debug, Clone, Default)]
+pub struct ServiceConfig {
+    pub(crate) method_configs: Vec<MethodConfig>,
+    pub(crate) retry_throttling: Option<(u32, f64)>,
+}
+
+/// Configuration applied to a set of methods of a [`ServiceConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct MethodConfig {
+    pub(crate) names: Vec<MethodName>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) retry_policy: Option<RetryPolicy>,
+    pub(crate) hedging_policy: Option<HedgingPolicy>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MethodName {
+    service: Option<String>,
+    method: Option<String>,
+}
+
+/// Policy describing how failed requests are retried.
+///
+/// Follows the [gRPC retry design]: a request is retried when the server
+/// answers with a "Trailers-Only" response carrying one of the retryable
+/// status codes, or when the transport fails with an error mapping to one of
+/// them. Attempts are spaced by an exponential backoff with full jitter,
+/// unless the server pushes back with a delay of its own.
+///
+/// Retries require the request body to be buffered, so they are only
+/// performed while the body stays below an internal buffer limit.
+///
+/// ```
+/// # use tonic::transport::channel::RetryPolicy;
+/// # use tonic::Code;
+/// # use std::time::Duration;
+/// let policy = RetryPolicy::new(4)
+///     .initial_backoff(Duration::from_millis(100))
+///     .max_backoff(Duration::from_secs(1))
+///     .backoff_multiplier(2.0)
+///     .retryable_status_code(Code::Unavailable);
+/// ```
+///
+/// [gRPC retry design]: https://github.com/grpc/proposal/blob/master/A6-client-retries.md
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub(crate) max_attempts: usize,
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    pub(crate) backoff_multiplier: f64,
+    pub(crate) retryable_status_codes: Vec<Code>,
+    pub(crate) use_retry_info: bool,
+}
+
+/// Policy describing how requests are hedged.
+///
+/// Follows the [gRPC retry design]: the request is sent again every
+/// `hedging_delay` until `max_attempts` attempts are in flight. The first
+/// response that is either successful or carries a status that isn't listed
+/// as non-fatal is returned and the other attempts are cancelled.
+///
+/// Only hedge idempotent methods, since the server may process the same
+/// request several times.
+///
+/// ```
+/// # use tonic::transport::channel::HedgingPolicy;
+/// # use tonic::Code;
+/// # use std::time::Duration;
+/// let policy = HedgingPolicy::new(3)
+///     .hedging_delay(Duration::from_millis(50))
+///     .non_fatal_status_code(Code::Unavailable);
+/// ```
+///
+/// [gRPC retry design]: https://github.com/grpc/proposal/blob/master/A6-client-retries.md
+#[derive(Debug, Clone, PartialEq)]
+pub struct HedgingPolicy {
+    pub(crate) max_attempts: usize,
+    pub(crate) hedging_delay: Duration,
+    pub(crate) non_fatal_status_codes: Vec<Code>,
+}
+
+impl ServiceConfig {
+    /// Create an empty service config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a [`MethodConfig`] to this service config.
+    pub fn method_config(mut self, config: MethodConfig) -> Self {
+        self.method_configs.push(config);
+        self
+    }
+
+    /// Throttle retries and hedging once too many attempts fail.
+    ///
+    /// The channel keeps a single [`RetryThrottle`] bucket shared by every
+    /// method, see its documentation for the meaning of `max_tokens` and
+    /// `token_ratio`.
+    pub fn retry_throttling(self, max_tokens: u32, token_ratio: f64) -> Self {
+        ServiceConfig {
+            retry_throttling: Some((max_tokens, token_ratio)),
+            ..self
+        }
+    }
+
+    /// Find the config applying to the request `path`, of the form
+    /// `/package.Service/Method`.
+    pub(crate) fn find(&self, path: &str) -> Option<&MethodConfig> {
+        let (service, method) = path
+            .strip_prefix('/')
+            .and_then(|path| path.split_once('/'))
+            .unwrap_or(("", ""));
+
+        let exact = MethodName {
+            service: Some(service.to_string()),
+            method: Some(method.to_string()),
+        };
+        let by_service = MethodName {
+            service: Some(service.to_string()),
+            method: None,
+        };
+        let default = MethodName {
+            service: None,
+            method: None,
+        };
+
+        [exact, by_service, default].iter().find_map(|name| {
+            self.method_configs
+                .iter()
+                .find(|config| config.names.contains(name))
+        })
+    }
+
+    /// Get the default method config, creating it if needed.
+    pub(crate) fn default_method_config(&mut self) -> &mut MethodConfig {
+        let default = MethodName {
+            service: None,
+            method: None,
+        };
+
+        let idx = match self
+            .method_configs
+            .iter()
+            .position(|config| config.names.contains(&default))
+        {
+            Some(idx) => idx,
+            None => {
+                self.method_configs.push(MethodConfig::new().all_services());
+                self.method_configs.len() - 1
+            }
+        };
+
+        &mut self.method_configs[idx]
+    }
+
+    /// Parse a service config from its JSON representation.
+    ///
+    /// Only the fields tonic understands are read, any other field is
+    /// ignored.
+    ///
+    /// ```
+    /// # use tonic::transport::channel::ServiceConfig;
+    /// let config = ServiceConfig::from_json(r#"{
+    ///     "methodConfig": [{
+    ///         "name": [{ "service": "helloworld.Greeter" }],
+    ///         "retryPolicy": {
+    ///             "maxAttempts": 4,
+    ///             "initialBackoff": "0.1s",
+    ///             "maxBackoff": "1s",
+    ///             "backoffMultiplier": 2,
+    ///             "retryableStatusCodes": ["UNAVAILABLE"]
+    ///         }
+    ///     }]
+    /// }"#).unwrap();
+    /// ```
+    #[cfg(feature = "service-config")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "service-config")))]
+    pub fn from_json(json: &str) -> Result<Self, ServiceConfigError> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| ServiceConfigError::new(e.to_string()))?;
+
+        json::service_config(&value)
+    }
+
+    #[cfg(feature = "service-config-dns")]
+    pub(crate) fn from_value(value: &serde_json::Value) -> Result<Self, ServiceConfigError> {
+        json::service_config(value)
+    }
+}
+
+impl MethodConfig {
+    /// Create a method config that doesn't apply to any method yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply this config to every method of `service`.
+    ///
+    /// `service` is the fully qualified service name, e.g. `helloworld.Greeter`.
+    pub fn service(mut self, service: impl Into<String>) -> Self {
+        self.names.push(MethodName {
+            service: Some(service.into()),
+            method: None,
+        });
+        self
+    }
+
+    /// Apply this config to `method` of `service`.
+    pub fn method(mut self, service: impl Into<String>, method: impl Into<String>) -> Self {
+        self.names.push(MethodName {
+            service: Some(service.into()),
+            method: Some(method.into()),
+        });
+        self
+    }
+
+    /// Apply this config to every method not matched by a more specific
+    /// config.
+    pub fn all_services(mut self) -> Self {
+        self.names.push(MethodName {
+            service: None,
+            method: None,
+        });
+        self
+    }
+
+    /// Set the deadline of requests to the matched methods.
+    ///
+    /// Requests with a shorter deadline keep it.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        MethodConfig {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Set the [`RetryPolicy`] of the matched methods.
+    ///
+    /// This replaces any [`HedgingPolicy`], a method can't both retry and
+    /// hedge requests.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self.hedging_policy = None;
+        self
+    }
+
+    /// Set the [`HedgingPolicy`] of the matched methods.
+    ///
+    /// This replaces any [`RetryPolicy`], a method can't both retry and
+    /// hedge requests.
+    pub fn hedging_policy(mut self, policy: HedgingPolicy) -> Self {
+        self.hedging_policy = Some(policy);
+        self.retry_policy = None;
+        self
+    }
+}
+
+impl RetryPolicy {
+    /// Create a retry policy allowing at most `max_attempts` attempts,
+    /// including the original one.
+    ///
+    /// Values above 5 are treated as 5 and values below 2 disable retries.
+    ///
+    /// Defaults to an initial backoff of 100ms, a max backoff of 1s, a
+    /// multiplier of 2, no retryable status codes and delays of
+    /// `google.rpc.RetryInfo` details honored.
+    pub fn new(max_attempts: usize) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.min(MAX_ATTEMPTS_LIMIT),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            backoff_multiplier: 2.0,
+            retryable_status_codes: Vec::new(),
+            use_retry_info: true,
+        }
+    }
+
+    /// Set the backoff before the first retry.
+    pub fn initial_backoff(self, backoff: Duration) -> Self {
+        RetryPolicy {
+            initial_backoff: backoff,
+            ..self
+        }
+    }
+
+    /// Set the upper bound of the backoff between two attempts.
+    pub fn max_backoff(self, backoff: Duration) -> Self {
+        RetryPolicy {
+            max_backoff: backoff,
+            ..self
+        }
+    }
+
+    /// Set the factor the backoff is multiplied by after each attempt.
+    pub fn backoff_multiplier(self, multiplier: f64) -> Self {
+        RetryPolicy {
+            backoff_multiplier: multiplier,
+            ..self
+        }
+    }
+
+    /// Add a status code that triggers a retry.
+    pub fn retryable_status_code(mut self, code: Code) -> Self {
+        self.retryable_status_codes.push(code);
+        self
+    }
+
+    /// Set the status codes that trigger a retry.
+    pub fn retryable_status_codes(self, codes: impl IntoIterator<Item = Code>) -> Self {
+        RetryPolicy {
+            retryable_status_codes: codes.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Set whether the delay of the `google.rpc.RetryInfo` details of a
+    /// status is waited for before retrying, rather than the backoff of the
+    /// policy.
+    ///
+    /// The `grpc-retry-pushback-ms` header of a response takes precedence
+    /// over its details either way.
+    pub fn use_retry_info(self, enabled: bool) -> Self {
+        RetryPolicy {
+            use_retry_info: enabled,
+            ..self
+        }
+    }
+
+    pub(crate) fn is_retryable(&self, code: Code) -> bool {
+        self.retryable_status_codes.contains(&code)
+    }
+
+    /// The backoff before attempt number `attempt`, without jitter.
+    ///
+    /// `attempt` starts at 1 for the first retry.
+    pub(crate) fn backoff(&self, attempt: usize) -> Duration {
+        let exp = attempt.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.backoff_multiplier.powi(exp);
+
+        if backoff.is_finite() && backoff < self.max_backoff.as_secs_f64() {
+            Duration::from_secs_f64(backoff.max(0.0))
+        } else {
+            self.max_backoff
+        }
+    }
+}
+
+impl HedgingPolicy {
+    /// Create a hedging policy sending at most `max_attempts` attempts,
+    /// including the original one.
+    ///
+    /// Values above 5 are treated as 5 and values below 2 disable hedging.
+    ///
+    /// Defaults to no hedging delay, meaning all attempts are sent at once,
+    /// and no non-fatal status codes.
+    pub fn new(max_attempts: usize) -> Self {
+        HedgingPolicy {
+            max_attempts: max_attempts.min(MAX_ATTEMPTS_LIMIT),
+            hedging_delay: Duration::ZERO,
+            non_fatal_status_codes: Vec::new(),
+        }
+    }
+
+    /// Set the delay between two attempts.
+    pub fn hedging_delay(self, delay: Duration) -> Self {
+        HedgingPolicy {
+            hedging_delay: delay,
+            ..self
+        }
+    }
+
+    /// Add a status code that doesn't stop the other attempts.
+    pub fn non_fatal_status_code(mut self, code: Code) -> Self {
+        self.non_fatal_status_codes.push(code);
+        self
+    }
+
+    /// Set the status codes that don't stop the other attempts.
+    pub fn non_fatal_status_codes(self, codes: impl IntoIterator<Item = Code>) -> Self {
+        HedgingPolicy {
+            non_fatal_status_codes: codes.into_iter().collect(),
+            ..self
+        }
+    }
+
+    pub(crate) fn is_non_fatal(&self, code: Code) -> bool {
+        self.non_fatal_status_codes.contains(&code)
+    }
+}
+
+/// Token bucket throttling retries as specified by the [gRPC retry design].
+///
+/// The bucket starts full with `max_tokens` tokens. Every failed attempt
+/// takes one token and every successful one gives back `token_ratio` tokens.
+/// Retries and hedged attempts are only sent while the bucket is more than
+/// half full, so a burst of failures stops generating extra load on the
+/// server.
+///
+/// Channels with [`ServiceConfig::retry_throttling`] use a bucket internally,
+/// this type is also exposed to allow application level retry layers to
+/// follow the same rules. Clones share the same bucket.
+///
+/// ```
+/// # use tonic::transport::channel::RetryThrottle;
+/// let throttle = RetryThrottle::new(10, 0.1);
+///
+/// for _ in 0..5 {
+///     throttle.record_failure();
+/// }
+/// assert!(!throttle.is_retry_allowed());
+///
+/// throttle.record_success();
+/// assert!(throttle.is_retry_allowed());
+/// ```
+///
+/// [gRPC retry design]: https://github.com/grpc/proposal/blob/master/A6-client-retries.md
+#[derive(Debug, Clone)]
+pub struct RetryThrottle {
+    inner: Arc<Throttle>,
+}
+
+/// Token counts are stored in thousandths of a token, the precision the spec
+/// defines for `token_ratio`.
+#[derive(Debug)]
+struct Throttle {
+    tokens: AtomicU64,
+    max_tokens: u64,
+    token_ratio: u64,
+}
+
+const TOKEN_SCALE: u64 = 1000;
+
+impl RetryThrottle {
+    /// Create a full bucket.
+    ///
+    /// `max_tokens` is clamped to `1..=1000` and `token_ratio` is rounded to
+    /// three decimal places, with a minimum of `0.001`.
+    pub fn new(max_tokens: u32, token_ratio: f64) -> Self {
+        let max_tokens = u64::from(max_tokens.clamp(1, 1000)) * TOKEN_SCALE;
+        let token_ratio = (token_ratio * TOKEN_SCALE as f64).round();
+        let token_ratio = if token_ratio.is_finite() {
+            (token_ratio as u64).clamp(1, max_tokens)
+        } else {
+            1
+        };
+
+        RetryThrottle {
+            inner: Arc::new(Throttle {
+                tokens: AtomicU64::new(max_tokens),
+                max_tokens,
+                token_ratio,
+            }),
+        }
+    }
+
+    /// Record an attempt that failed with a retryable status.
+    pub fn record_failure(&self) {
+        let _ = self
+            .inner
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tokens| {
+                Some(tokens.saturating_sub(TOKEN_SCALE))
+            });
+    }
+
+    /// Record a successful attempt.
+    pub fn record_success(&self) {
+        let max_tokens = self.inner.max_tokens;
+        let token_ratio = self.inner.token_ratio;
+        let _ = self
+            .inner
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tokens| {
+                Some((tokens + token_ratio).min(max_tokens))
+            });
+    }
+
+    /// Returns `true` if the bucket allows another attempt to be sent.
+    pub fn is_retry_allowed(&self) -> bool {
+        self.inner.tokens.load(Ordering::Acquire) > self.inner.max_tokens / 2
+    }
+}
+
+/// The service config a channel currently applies to its calls.
+///
+/// Channels balancing over a [`Resolver`](super::Resolver) replace it
+/// whenever a resolution carries a new config, clones of the channel observe
+/// the update.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SharedServiceConfig {
+    inner: Arc<RwLock<Option<AppliedServiceConfig>>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct AppliedServiceConfig {
+    pub(crate) config: Arc<ServiceConfig>,
+    pub(crate) throttle: Option<RetryThrottle>,
+}
+
+impl SharedServiceConfig {
+    pub(crate) fn new(config: Option<ServiceConfig>) -> Self {
+        let shared = SharedServiceConfig::default();
+        if let Some(config) = config {
+            shared.set(config);
+        }
+        shared
+    }
+
+    pub(crate) fn get(&self) -> Option<AppliedServiceConfig> {
+        self.inner.read().unwrap().clone()
+    }
+
+    /// Apply `config` to subsequent calls.
+    ///
+    /// The retry throttle is kept when its parameters didn't change, so that
+    /// a resolver repeating the same config doesn't refill the bucket.
+    pub(crate) fn set(&self, config: ServiceConfig) {
+        let mut current = self.inner.write().unwrap();
+
+        let throttle = match (current.as_ref(), config.retry_throttling) {
+            (Some(current), Some(params)) if current.config.retry_throttling == Some(params) => {
+                current.throttle.clone()
+            }
+            (_, params) => {
+                params.map(|(max_tokens, token_ratio)| RetryThrottle::new(max_tokens, token_ratio))
+            }
+        };
+
+        *current = Some(AppliedServiceConfig {
+            config: Arc::new(config),
+            throttle,
+        });
+    }
+}
+
+/// Error returned when a [`ServiceConfig`] cannot be parsed.
+#[derive(Debug)]
+pub struct ServiceConfigError {
+    message: String,
+}
+
+impl ServiceConfigError {
+    #[cfg_attr(not(feature = "service-config"), allow(dead_code))]
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        ServiceConfigError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ServiceConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid service config: {}", self.message)
+    }
+}
+
+impl std::error::Error for ServiceConfigError {}
+
+#[cfg(feature = "service-config")]
+mod json {
+    use super::{
+        HedgingPolicy, MethodConfig, MethodName, RetryPolicy, ServiceConfig, ServiceConfigError,
+    };
+    use crate::Code;
+    use serde_json::Value;
+    use std::time::Duration;
+
+    pub(super) fn service_config(value: &Value) -> Result<ServiceConfig, ServiceConfigError> {
+        let mut config = ServiceConfig::new();
+
+        if let Some(method_configs) = field(value, "methodConfig") {
+            for method_config in array(method_configs, "methodConfig")? {
+                config = config.method_config(self::method_config(method_config)?);
+            }
+        }
+
+        if let Some(throttling) = field(value, "retryThrottling") {
+            let max_tokens = field(throttling, "maxTokens")
+                .and_then(Value::as_u64)
+                .filter(|n| (1..=1000).contains(n))
+                .ok_or_else(|| {
+                    ServiceConfigError::new(
+                        "retryThrottling.maxTokens must be an integer in (0, 1000]",
+                    )
+                })?;
+            let token_ratio = field(throttling, "tokenRatio")
+                .and_then(Value::as_f64)
+                .filter(|&r| r > 0.0)
+                .ok_or_else(|| {
+                    ServiceConfigError::new("retryThrottling.tokenRatio must be a number > 0")
+                })?;
+
+            config = config.retry_throttling(max_tokens as u32, token_ratio);
+        }
+
+        Ok(config)
+    }
+
+    fn method_config(value: &Value) -> Result<MethodConfig, ServiceConfigError> {
+        let mut config = MethodConfig::new();
+
+        if let Some(names) = field(value, "name") {
+            for name in array(names, "name")? {
+                config.names.push(method_name(name)?);
+            }
+        }
+
+        if let Some(timeout) = field(value, "timeout") {
+            config.timeout = Some(
+                timeout
+                    .as_str()
+                    .and_then(parse_duration)
+                    .ok_or_else(|| ServiceConfigError::new("timeout must be a duration"))?,
+            );
+        }
+
+        if let Some(policy) = field(value, "retryPolicy") {
+            config.retry_policy = Some(retry_policy(policy)?);
+        }
+
+        if let Some(policy) = field(value, "hedgingPolicy") {
+            if config.retry_policy.is_some() {
+                return Err(ServiceConfigError::new(
+                    "retryPolicy and hedgingPolicy cannot both be set",
+                ));
+            }
+            config.hedging_policy = Some(hedging_policy(policy)?);
+        }
+
+        Ok(config)
+    }
+
+    fn method_name(value: &Value) -> Result<MethodName, ServiceConfigError> {
+        let service = field(value, "service")
+            .map(|v| string(v, "name.service"))
+            .transpose()?
+            .filter(|s| !s.is_empty());
+        let method = field(value, "method")
+            .map(|v| string(v, "name.method"))
+            .transpose()?
+            .filter(|s| !s.is_empty());
+
+        if service.is_none() && method.is_some() {
+            return Err(ServiceConfigError::new(
+                "name.method cannot be set without name.service",
+            ));
+        }
+
+        Ok(MethodName {
+            service: service.map(str::to_string),
+            method: method.map(str::to_string),
+        })
+    }
+
+    fn retry_policy(value: &Value) -> Result<RetryPolicy, ServiceConfigError> {
+        let max_attempts = max_attempts(value, "retryPolicy")?;
+
+        let initial_backoff = positive_duration(value, "initialBackoff")?;
+        let max_backoff = positive_duration(value, "maxBackoff")?;
+
+        let backoff_multiplier = field(value, "backoffMultiplier")
+            .and_then(Value::as_f64)
+            .filter(|&m| m > 0.0)
+            .ok_or_else(|| {
+                ServiceConfigError::new("retryPolicy.backoffMultiplier must be a number > 0")
+            })?;
+
+        let codes = field(value, "retryableStatusCodes")
+            .ok_or_else(|| ServiceConfigError::new("retryPolicy.retryableStatusCodes is required"))
+            .and_then(|codes| array(codes, "retryPolicy.retryableStatusCodes"))?
+            .iter()
+            .map(status_code)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if codes.is_empty() {
+            return Err(ServiceConfigError::new(
+                "retryPolicy.retryableStatusCodes must not be empty",
+            ));
+        }
+
+        Ok(RetryPolicy::new(max_attempts)
+            .initial_backoff(initial_backoff)
+            .max_backoff(max_backoff)
+            .backoff_multiplier(backoff_multiplier)
+            .retryable_status_codes(codes))
+    }
+
+    fn hedging_policy(value: &Value) -> Result<HedgingPolicy, ServiceConfigError> {
+        let max_attempts = max_attempts(value, "hedgingPolicy")?;
+
+        let hedging_delay = match field(value, "hedgingDelay") {
+            Some(delay) => delay.as_str().and_then(parse_duration).ok_or_else(|| {
+                ServiceConfigError::new("hedgingPolicy.hedgingDelay must be a duration")
+            })?,
+            None => Duration::ZERO,
+        };
+
+        let codes = match field(value, "nonFatalStatusCodes") {
+            Some(codes) => array(codes, "hedgingPolicy.nonFatalStatusCodes")?
+                .iter()
+                .map(status_code)
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(HedgingPolicy::new(max_attempts)
+            .hedging_delay(hedging_delay)
+            .non_fatal_status_codes(codes))
+    }
+
+    fn max_attempts(value: &Value, policy: &str) -> Result<usize, ServiceConfigError> {
+        field(value, "maxAttempts")
+            .and_then(Value::as_u64)
+            .filter(|&n| n >= 2)
+            .map(|n| n as usize)
+            .ok_or_else(|| {
+                ServiceConfigError::new(format!("{}.maxAttempts must be an integer >= 2", policy))
+            })
+    }
+
+    fn field<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+        value.get(name).filter(|v| !v.is_null())
+    }
+
+    fn array<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, ServiceConfigError> {
+        value
+            .as_array()
+            .ok_or_else(|| ServiceConfigError::new(format!("{} must be an array", name)))
+    }
+
+    fn string<'a>(value: &'a Value, name: &str) -> Result<&'a str, ServiceConfigError> {
+        value
+            .as_str()
+            .ok_or_else(|| ServiceConfigError::new(format!("{} must be a string", name)))
+    }
+
+    fn positive_duration(value: &Value, name: &str) -> Result<Duration, ServiceConfigError> {
+        field(value, name)
+            .and_then(Value::as_str)
+            .and_then(parse_duration)
+            .filter(|d| !d.is_zero())
+            .ok_or_else(|| ServiceConfigError::new(format!("{} must be a positive duration", name)))
+    }
+
+    /// Parse the JSON representation of a `google.protobuf.Duration`, e.g. `1.5s`.
+    pub(crate) fn parse_duration(s: &str) -> Option<Duration> {
+        let secs: f64 = s.strip_suffix('s')?.parse().ok()?;
+
+        if secs.is_finite() && secs >= 0.0 {
+            Some(Duration::from_secs_f64(secs))
+        } else {
+            None
+        }
+    }
+
+    fn status_code(value: &Value) -> Result<Code, ServiceConfigError> {
+        let code = match value {
+            Value::Number(n) => n
+                .as_i64()
+                .and_then(|n| i32::try_from(n).ok())
+                .map(Code::from),
+            Value::String(s) => code_from_name(s),
+            _ => None,
+        };
+
+        code.filter(|&code| code != Code::Ok)
+            .ok_or_else(|| ServiceConfigError::new(format!("invalid status code {}", value)))
+    }
+
+    fn code_from_name(name: &str) -> Option<Code> {
+        let code = match name {
+            "CANCELLED" => Code::Cancelled,
+            "UNKNOWN" => Code::Unknown,
+            "INVALID_ARGUMENT" => Code::InvalidArgument,
+            "DEADLINE_EXCEEDED" => Code::DeadlineExceeded,
+            "NOT_FOUND" => Code::NotFound,
+            "ALREADY_EXISTS" => Code::AlreadyExists,
+            "PERMISSION_DENIED" => Code::PermissionDenied,
+            "RESOURCE_EXHAUSTED" => Code::ResourceExhausted,
+            "FAILED_PRECONDITION" => Code::FailedPrecondition,
+            "ABORTED" => Code::Aborted,
+            "OUT_OF_RANGE" => Code::OutOfRange,
+            "UNIMPLEMENTED" => Code::Unimplemented,
+            "INTERNAL" => Code::Internal,
+            "UNAVAILABLE" => Code::Unavailable,
+            "DATA_LOSS" => Code::DataLoss,
+            "UNAUTHENTICATED" => Code::Unauthenticated,
+            _ => return None,
+        };
+
+        Some(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl ServiceConfig {
+        fn retry_policy(&self, path: &str) -> Option<&RetryPolicy> {
+            self.find(path)?.retry_policy.as_ref()
+        }
+
+        fn hedging_policy(&self, path: &str) -> Option<&HedgingPolicy> {
+            self.find(path)?.hedging_policy.as_ref()
+        }
+    }
+
+    #[test]
+    fn find_most_specific_config() {
+        let config = ServiceConfig::new()
+            .method_config(
+                MethodConfig::new()
+                    .all_services()
+                    .retry_policy(RetryPolicy::new(2)),
+            )
+            .method_config(
+                MethodConfig::new()
+                    .service("pkg.Svc")
+                    .retry_policy(RetryPolicy::new(3)),
+            )
+            .method_config(
+                MethodConfig::new()
+                    .method("pkg.Svc", "Method")
+                    .retry_policy(RetryPolicy::new(4)),
+            );
+
+        let attempts = |path| config.retry_policy(path).unwrap().max_attempts;
+
+        assert_eq!(attempts("/pkg.Svc/Method"), 4);
+        assert_eq!(attempts("/pkg.Svc/Other"), 3);
+        assert_eq!(attempts("/pkg.Other/Method"), 2);
+        assert_eq!(attempts("garbage"), 2);
+    }
+
+    #[test]
+    fn no_default_config() {
+        let config = ServiceConfig::new().method_config(
+            MethodConfig::new()
+                .service("pkg.Svc")
+                .retry_policy(RetryPolicy::new(3)),
+        );
+
+        assert!(config.retry_policy("/pkg.Other/Method").is_none());
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(350))
+            .backoff_multiplier(2.0);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(350));
+        assert_eq!(policy.backoff(100), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn throttle_bucket() {
+        let throttle = RetryThrottle::new(4, 0.5);
+        assert!(throttle.is_retry_allowed());
+
+        throttle.record_failure();
+        assert!(throttle.is_retry_allowed());
+        throttle.record_failure();
+        assert!(!throttle.is_retry_allowed());
+        throttle.record_failure();
+        throttle.record_failure();
+        throttle.record_failure();
+        assert_eq!(throttle.inner.tokens.load(Ordering::Relaxed), 0);
+
+        for _ in 0..5 {
+            throttle.record_success();
+        }
+        assert!(throttle.is_retry_allowed());
+
+        for _ in 0..100 {
+            throttle.record_success();
+        }
+        assert_eq!(throttle.inner.tokens.load(Ordering::Relaxed), 4000);
+    }
+
+    #[test]
+    fn max_attempts_is_capped() {
+        assert_eq!(RetryPolicy::new(10).max_attempts, MAX_ATTEMPTS_LIMIT);
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn parse_json() {
+        let config = ServiceConfig::from_json(
+            r#"{
+                "loadBalancingConfig": [{ "round_robin": {} }],
+                "methodConfig": [{
+                    "name": [{ "service": "pkg.Svc", "method": "Method" }, {}],
+                    "timeout": "1.5s",
+                    "retryPolicy": {
+                        "maxAttempts": 3,
+                        "initialBackoff": "0.5s",
+                        "maxBackoff": "2s",
+                        "backoffMultiplier": 1.5,
+                        "retryableStatusCodes": ["UNAVAILABLE", 10]
+                    }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let policy = config.retry_policy("/pkg.Svc/Method").unwrap();
+        assert_eq!(
+            policy,
+            &RetryPolicy::new(3)
+                .initial_backoff(Duration::from_millis(500))
+                .max_backoff(Duration::from_secs(2))
+                .backoff_multiplier(1.5)
+                .retryable_status_codes([Code::Unavailable, Code::Aborted])
+        );
+        assert!(config.retry_policy("/any.Svc/Any").is_some());
+        assert_eq!(
+            config.find("/pkg.Svc/Method").unwrap().timeout,
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(config.retry_throttling, None);
+
+        let config = ServiceConfig::from_json(
+            r#"{ "retryThrottling": { "maxTokens": 10, "tokenRatio": 0.1 } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.retry_throttling, Some((10, 0.1)));
+    }
+
+    #[test]
+    fn retry_and_hedging_are_exclusive() {
+        let config = MethodConfig::new()
+            .retry_policy(RetryPolicy::new(2))
+            .hedging_policy(HedgingPolicy::new(2));
+
+        assert!(config.retry_policy.is_none());
+        assert!(config.hedging_policy.is_some());
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn parse_hedging_json() {
+        let config = ServiceConfig::from_json(
+            r#"{
+                "methodConfig": [{
+                    "name": [{ "service": "pkg.Svc" }],
+                    "hedgingPolicy": {
+                        "maxAttempts": 3,
+                        "hedgingDelay": "0.05s",
+                        "nonFatalStatusCodes": ["UNAVAILABLE"]
+                    }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.hedging_policy("/pkg.Svc/Method").unwrap(),
+            &HedgingPolicy::new(3)
+                .hedging_delay(Duration::from_millis(50))
+                .non_fatal_status_code(Code::Unavailable)
+        );
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn reject_invalid_json() {
+        let invalid = [
+            r#"{ "methodConfig": {} }"#,
+            r#"{ "methodConfig": [{ "name": [{ "method": "Method" }] }] }"#,
+            r#"{ "methodConfig": [{ "retryPolicy": { "maxAttempts": 1 } }] }"#,
+            r#"{ "methodConfig": [{ "retryPolicy": {
+                "maxAttempts": 2, "initialBackoff": "1s", "maxBackoff": "1s",
+                "backoffMultiplier": 2, "retryableStatusCodes": ["NOPE"]
+            } }] }"#,
+            r#"{ "methodConfig": [{
+                "retryPolicy": {
+                    "maxAttempts": 2, "initialBackoff": "1s", "maxBackoff": "1s",
+                    "backoffMultiplier": 2, "retryableStatusCodes": ["UNAVAILABLE"]
+                },
+                "hedgingPolicy": { "maxAttempts": 2 }
+            }] }"#,
+            r#"{ "retryThrottling": { "maxTokens": 0, "tokenRatio": 1 } }"#,
+            r#"{ "retryThrottling": { "maxTokens": 10 } }"#,
+            r#"{ "methodConfig": [{ "timeout": 5 }] }"#,
+        ];
+
+        for json in invalid {
+            assert!(ServiceConfig::from_json(json).is_err(), "{}", json);
+        }
+    }
+}
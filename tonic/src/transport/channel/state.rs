@@ -0,0 +1,176 @@
+use crate::transport::service::ConnectivityState;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// The connectivity state of a [`Channel`](super::Channel).
+///
+/// A balanced channel is [`Ready`](ChannelState::Ready) when any of its
+/// endpoints is, otherwise it takes the state of its most hopeful endpoint:
+/// connecting, then idle, then failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChannelState {
+    /// The channel isn't connected, and no connection attempt is in
+    /// progress. It connects when it is sent a request.
+    Idle,
+    /// A connection is being established.
+    Connecting,
+    /// The channel is connected and can send requests right away.
+    Ready,
+    /// The last connection attempts failed, the channel tries again when it
+    /// is sent a request.
+    TransientFailure,
+    /// The background task of the channel stopped, it can't send requests
+    /// anymore.
+    Shutdown,
+}
+
+/// Aggregates the [`ConnectivityState`] of the connections of a channel
+/// into its [`ChannelState`].
+///
+/// The state becomes [`ChannelState::Shutdown`] once every clone of the
+/// tracker is dropped, meaning the connections of the channel are gone.
+#[derive(Debug, Clone)]
+pub(crate) struct StateTracker(Arc<Tracked>);
+
+#[derive(Debug)]
+struct Tracked {
+    /// The number of connections in each state.
+    counts: Mutex<[usize; 4]>,
+    tx: watch::Sender<ChannelState>,
+}
+
+impl StateTracker {
+    pub(crate) fn new() -> (Self, watch::Receiver<ChannelState>) {
+        let (tx, rx) = watch::channel(ChannelState::Idle);
+        let tracked = Tracked {
+            counts: Mutex::new([0; 4]),
+            tx,
+        };
+        (StateTracker(Arc::new(tracked)), rx)
+    }
+
+    /// Count a new connection, in the `Idle` state.
+    pub(crate) fn add(&self) {
+        self.update(|counts| counts[ConnectivityState::Idle as usize] += 1);
+    }
+
+    /// Stop counting a connection that was in `state`.
+    pub(crate) fn remove(&self, state: ConnectivityState) {
+        self.update(|counts| counts[state as usize] -= 1);
+    }
+
+    pub(crate) fn transition(&self, from: ConnectivityState, to: ConnectivityState) {
+        self.update(|counts| {
+            counts[from as usize] -= 1;
+            counts[to as usize] += 1;
+        });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut [usize; 4])) {
+        let mut counts = self.0.counts.lock().unwrap();
+        f(&mut counts);
+
+        let count = |state: ConnectivityState| counts[state as usize];
+        let state = if count(ConnectivityState::Ready) > 0 {
+            ChannelState::Ready
+        } else if count(ConnectivityState::Connecting) > 0 {
+            ChannelState::Connecting
+        } else if count(ConnectivityState::TransientFailure) > 0
+            && count(ConnectivityState::Idle) == 0
+        {
+            ChannelState::TransientFailure
+        } else {
+            ChannelState::Idle
+        };
+
+        self.0.tx.send_if_modified(|current| {
+            let modified = *current != state;
+            *current = state;
+            modified
+        });
+    }
+}
+
+/// The state published by a [`StateTracker`].
+pub(crate) fn current(rx: &watch::Receiver<ChannelState>) -> ChannelState {
+    match rx.has_changed() {
+        Ok(_) => *rx.borrow(),
+        Err(_) => ChannelState::Shutdown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Endpoint;
+    use http::Request;
+    use std::{convert::Infallible, time::Duration};
+    use tower::ServiceExt;
+
+    #[test]
+    fn aggregates_connection_states() {
+        let (tracker, rx) = StateTracker::new();
+        tracker.add();
+        tracker.add();
+        assert_eq!(current(&rx), ChannelState::Idle);
+
+        tracker.transition(ConnectivityState::Idle, ConnectivityState::TransientFailure);
+        assert_eq!(current(&rx), ChannelState::Idle);
+        tracker.transition(ConnectivityState::Idle, ConnectivityState::Connecting);
+        assert_eq!(current(&rx), ChannelState::Connecting);
+        tracker.transition(
+            ConnectivityState::Connecting,
+            ConnectivityState::TransientFailure,
+        );
+        assert_eq!(current(&rx), ChannelState::TransientFailure);
+        tracker.transition(
+            ConnectivityState::TransientFailure,
+            ConnectivityState::Ready,
+        );
+        assert_eq!(current(&rx), ChannelState::Ready);
+
+        drop(tracker);
+        assert_eq!(current(&rx), ChannelState::Shutdown);
+    }
+
+    #[tokio::test]
+    async fn reports_channel_state_changes() {
+        let make = hyper::service::make_service_fn(|_| async {
+            Ok::<_, Infallible>(hyper::service::service_fn(|_| async {
+                Ok::<_, Infallible>(http::Response::new(hyper::Body::empty()))
+            }))
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect_lazy();
+        assert_eq!(channel.state(), ChannelState::Idle);
+
+        let waiter = {
+            let channel = channel.clone();
+            tokio::spawn(async move {
+                let mut state = channel.state();
+                while state != ChannelState::Ready {
+                    state = channel.wait_for_state_change(state).await;
+                }
+            })
+        };
+
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        channel.clone().oneshot(request).await.unwrap();
+        assert_eq!(channel.state(), ChannelState::Ready);
+        tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
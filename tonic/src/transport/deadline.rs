@@ -0,0 +1,149 @@
+//! Propagation of the deadlines of the calls a server handles to the calls
+//! their handlers make.
+//!
+//! While the [`Server`] polls the future of a handler, the deadline of the
+//! call it handles, from its `grpc-timeout` header or [`Server::timeout`], is
+//! the [`current`] deadline of the task. [`Channel`]s then apply the time
+//! remaining until that deadline as the timeout of the calls the handler
+//! makes, unless they already have a shorter one, less the offset set with
+//! [`Endpoint::deadline_propagation`].
+//!
+//! The deadline isn't inherited by the tasks a handler spawns, nor by the
+//! streams of the responses of server streaming calls, which are polled once
+//! the handler returned. Run those with the deadline of the handler with
+//! [`scope`]:
+//!
+//! ```
+//! # async fn handle() {
+//! use tonic::transport::deadline;
+//!
+//! let deadline = deadline::current();
+//! tokio::spawn(deadline::scope(deadline, async {
+//!     // Calls made here have the deadline of the call being handled.
+//! }));
+//! # }
+//! ```
+//!
+//! [`Server`]: super::Server
+//! [`Server::timeout`]: super::Server::timeout
+//! [`Channel`]: super::Channel
+//! [`Endpoint::deadline_propagation`]: super::Endpoint::deadline_propagation
+
+use std::future::Future;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// The deadline of the call the current task handles, if it has one.
+pub fn current() -> Option<Instant> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// Run `future` with `deadline` as the deadline of the call it handles, or
+/// without a deadline if `deadline` is `None`.
+pub async fn scope<F: Future>(deadline: Option<Instant>, future: F) -> F::Output {
+    match deadline {
+        Some(deadline) => DEADLINE.scope(deadline, future).await,
+        None => future.await,
+    }
+}
+
+/// Poll `poll` with `deadline` as the deadline of the current task.
+pub(crate) fn sync_scope<R>(deadline: Option<Instant>, poll: impl FnOnce() -> R) -> R {
+    match deadline {
+        Some(deadline) => DEADLINE.sync_scope(deadline, poll),
+        None => poll(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn scopes_deadlines() {
+        assert_eq!(current(), None);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let inner = scope(Some(deadline), async {
+            let outer = current();
+            let inner = scope(None, async { current() }).await;
+            (outer, inner)
+        })
+        .await;
+        assert_eq!(inner, (Some(deadline), Some(deadline)));
+        assert_eq!(current(), None);
+    }
+
+    #[tokio::test]
+    async fn propagates_deadlines_to_outbound_calls() {
+        use crate::{
+            body::empty_body,
+            metadata::GRPC_TIMEOUT_HEADER,
+            transport::{local, server::Routes, service::grpc_timeout::try_parse_grpc_timeout},
+        };
+        use http::{Request, Response};
+        use hyper::Body;
+        use std::{
+            convert::Infallible,
+            sync::{Arc, Mutex},
+        };
+        use tower::ServiceExt;
+
+        let serve = |routes: Routes, incoming| {
+            tokio::spawn(
+                crate::transport::Server::builder()
+                    .add_routes(routes)
+                    .serve_with_incoming(incoming),
+            );
+        };
+
+        // The downstream server records the timeouts of its calls.
+        let timeouts = Arc::new(Mutex::new(Vec::new()));
+        let (downstream, incoming) = local::pair();
+        let recorded = timeouts.clone();
+        serve(
+            Routes::default().fallback_service(tower::service_fn(move |req: Request<Body>| {
+                let timeout = try_parse_grpc_timeout(req.headers()).unwrap();
+                recorded.lock().unwrap().push(timeout);
+                async { Ok::<_, Infallible>(Response::new(empty_body())) }
+            })),
+            incoming,
+        );
+
+        // The upstream server calls the downstream one while handling calls.
+        let (upstream, incoming) = local::pair();
+        serve(
+            Routes::default().fallback_service(tower::service_fn(move |_: Request<Body>| {
+                let downstream = downstream.clone();
+                async move {
+                    let request = Request::post("/test.Test/Call").body(empty_body()).unwrap();
+                    downstream.oneshot(request).await.unwrap();
+                    Ok::<_, Infallible>(Response::new(empty_body()))
+                }
+            })),
+            incoming,
+        );
+
+        let call = |timeout: Option<&'static str>| {
+            let mut request = Request::post("/test.Test/Call").body(empty_body()).unwrap();
+            if let Some(timeout) = timeout {
+                request
+                    .headers_mut()
+                    .insert(GRPC_TIMEOUT_HEADER, timeout.parse().unwrap());
+            }
+            upstream.clone().oneshot(request)
+        };
+        let _: Response<_> = call(Some("1S")).await.unwrap();
+        let _: Response<_> = call(None).await.unwrap();
+
+        let timeouts = timeouts.lock().unwrap();
+        let propagated = timeouts[0].unwrap();
+        assert!(propagated <= Duration::from_secs(1));
+        assert!(propagated > Duration::from_millis(500));
+        assert_eq!(timeouts[1], None);
+    }
+}
@@ -0,0 +1,269 @@
+//! In-process transport, connecting a client to a server of the same
+//! process without sockets.
+//!
+//! [`pair`] connects a [`Channel`] to the [`LocalIncoming`] of a server
+//! through in-memory streams, which still carry HTTP/2 like sockets would.
+//! [`direct`] goes further and hands the requests of the channel to the
+//! service of the server as they are.
+//!
+//! ```no_run
+//! # use tonic::transport::{local, Server};
+//! # use tower::Service;
+//! # #[derive(Clone)]
+//! # pub struct Svc;
+//! # impl Service<hyper::Request<hyper::Body>> for Svc {
+//! #   type Response = hyper::Response<tonic::body::BoxBody>;
+//! #   type Error = std::convert::Infallible;
+//! #   type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+//! #   fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+//! #       Ok(()).into()
+//! #  }
+//! #   fn call(&mut self, _req: hyper::Request<hyper::Body>) -> Self::Future {
+//! #       unimplemented!()
+//! #   }
+//! # }
+//! # impl tonic::server::NamedService for Svc {
+//! # const NAME: &'static str = "some_svc";
+//! # }
+//! # #[tokio::main]
+//! # async fn main() {
+//! # let my_svc = Svc;
+//! let (channel, incoming) = local::pair();
+//!
+//! tokio::spawn(
+//!     Server::builder()
+//!         .add_service(my_svc)
+//!         .serve_with_incoming(incoming),
+//! );
+//! // Build clients from `channel`.
+//! # }
+//! ```
+
+use super::{
+    channel::{Channel, StateTracker},
+    service::{ConnectivityState, SharedExec},
+    BoxFuture, Endpoint,
+};
+use crate::{body::BoxBody, service::streaming::DataStream};
+use http::{Request, Response, Uri};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{io::DuplexStream, sync::mpsc};
+use tokio_stream::Stream;
+use tower::util::BoxService;
+use tower_service::Service;
+
+/// The capacity of each direction of the streams, in bytes.
+const STREAM_CAPACITY: usize = 64 * 1024;
+
+/// Create a [`Channel`] connected to the server serving the returned
+/// [`LocalIncoming`], through in-memory streams.
+///
+/// The server is passed the incoming with
+/// [`Router::serve_with_incoming`](super::server::Router::serve_with_incoming),
+/// and stops once every clone of the channel is dropped.
+pub fn pair() -> (Channel, LocalIncoming) {
+    pair_with(Endpoint::from_static("http://localhost"))
+}
+
+/// Create a [`Channel`] with the settings of `endpoint`, connected to the
+/// server serving the returned [`LocalIncoming`] as [`pair`] does.
+///
+/// The uri of `endpoint` doesn't matter, besides the authority of the
+/// requests.
+pub fn pair_with(endpoint: Endpoint) -> (Channel, LocalIncoming) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let channel = endpoint.connect_with_connector_lazy(LocalConnector { server: tx });
+    (channel, LocalIncoming { connections: rx })
+}
+
+/// Create a [`Channel`] sending its requests to `service`, without a
+/// connection.
+///
+/// The requests aren't encoded as HTTP/2, which makes them cheaper than
+/// they are with [`pair`], but they also skip the layers of the server, as
+/// well as the timeout and user agent of the channel. Request bodies are
+/// passed on without trailers, which clients don't send.
+///
+/// `service` can be the [`Routes`](super::server::Routes) of the services
+/// of a server.
+pub fn direct<S>(service: S) -> Channel
+where
+    S: Service<Request<hyper::Body>, Response = Response<BoxBody>> + Send + 'static,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+{
+    let (tracker, state) = StateTracker::new();
+    tracker.add();
+    tracker.transition(ConnectivityState::Idle, ConnectivityState::Ready);
+
+    let executor = SharedExec::tokio();
+    let service = Direct {
+        inner: service,
+        _tracker: tracker,
+    };
+    let buffer_size = super::channel::DEFAULT_BUFFER_SIZE;
+    Channel::buffered(
+        BoxService::new(service),
+        buffer_size,
+        executor,
+        state,
+        true,
+        Some(std::time::Duration::ZERO),
+    )
+}
+
+/// The server side of [`pair`], yielding the streams of the connections of
+/// the channel.
+#[derive(Debug)]
+pub struct LocalIncoming {
+    connections: mpsc::UnboundedReceiver<DuplexStream>,
+}
+
+impl Stream for LocalIncoming {
+    type Item = Result<DuplexStream, io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.connections.poll_recv(cx).map(|stream| stream.map(Ok))
+    }
+}
+
+/// Connects by sending the server side of a new stream to the
+/// [`LocalIncoming`].
+#[derive(Debug, Clone)]
+struct LocalConnector {
+    server: mpsc::UnboundedSender<DuplexStream>,
+}
+
+impl Service<Uri> for LocalConnector {
+    type Response = DuplexStream;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Uri) -> Self::Future {
+        let (client, server) = tokio::io::duplex(STREAM_CAPACITY);
+        let sent = self.server.send(server);
+
+        Box::pin(async move {
+            sent.map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+            Ok(client)
+        })
+    }
+}
+
+/// The service of a [`direct`] channel, converting the request bodies of
+/// the channel for `service`.
+struct Direct<S> {
+    inner: S,
+    /// Keeps the channel ready until the service is dropped.
+    _tracker: StateTracker,
+}
+
+impl<S> Service<Request<BoxBody>> for Direct<S>
+where
+    S: Service<Request<hyper::Body>, Response = Response<BoxBody>>,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        let request = request.map(|body| hyper::Body::wrap_stream(DataStream(body)));
+        let response = self.inner.call(request);
+
+        Box::pin(async move { response.await.map_err(Into::into) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{server::NamedService, transport::Server};
+    use http::HeaderMap;
+    use http_body::Body as _;
+    use std::{convert::Infallible, future::Ready};
+    use tower::ServiceExt;
+
+    /// Echoes the body of requests, with an `echo` trailer.
+    #[derive(Clone)]
+    struct Echo;
+
+    impl NamedService for Echo {
+        const NAME: &'static str = "test.Echo";
+    }
+
+    impl Service<Request<hyper::Body>> for Echo {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<hyper::Body>) -> Self::Future {
+            let (mut tx, body) = hyper::Body::channel();
+            let mut request = request.into_body();
+            tokio::spawn(async move {
+                while let Some(data) = request.data().await {
+                    tx.send_data(data.unwrap()).await.unwrap();
+                }
+                let mut trailers = HeaderMap::new();
+                trailers.insert("echo", "done".parse().unwrap());
+                tx.send_trailers(trailers).await.unwrap();
+            });
+            std::future::ready(Ok(Response::new(crate::body::boxed(body))))
+        }
+    }
+
+    async fn echo(channel: Channel) {
+        let request = Request::builder()
+            .uri("/test.Echo/Call")
+            .body(crate::body::boxed(http_body::Full::new(
+                bytes::Bytes::from("hello"),
+            )))
+            .unwrap();
+        let mut response = channel.oneshot(request).await.unwrap();
+
+        let body = hyper::body::to_bytes(response.body_mut()).await.unwrap();
+        assert_eq!(body, "hello");
+        let trailers = response.body_mut().trailers().await.unwrap().unwrap();
+        assert_eq!(trailers["echo"], "done");
+    }
+
+    #[tokio::test]
+    async fn serves_through_pair() {
+        let (channel, incoming) = pair();
+        tokio::spawn(
+            Server::builder()
+                .add_service(Echo)
+                .serve_with_incoming(incoming),
+        );
+
+        echo(channel).await;
+    }
+
+    #[tokio::test]
+    async fn serves_directly() {
+        let channel = direct(crate::transport::server::Routes::new(Echo));
+        assert_eq!(
+            channel.state(),
+            crate::transport::channel::ChannelState::Ready
+        );
+
+        echo(channel).await;
+    }
+}
@@ -12,6 +12,7 @@
 //! - Timeouts
 //! - Concurrency Limits
 //! - Rate limiting
+//! - Retries
 //!
 //! # Examples
 //!
@@ -85,9 +86,23 @@
 //! # }
 //! ```
 //!
+//! ## Runtimes
+//!
+//! The transport runs on `tokio` by default. Clients on other executors,
+//! such as those of `smol` or `async-std`, set how they spawn tasks with
+//! [`Endpoint::executor`] and how they time calls out with
+//! [`Endpoint::timer`], and bring their own IO, adapted to `tokio`'s IO
+//! traits, which don't need its runtime, with
+//! [`Endpoint::connect_with_connector`]. Servers take their timer with
+//! [`Server::timer`] and their IO with
+//! [`Router::serve_with_incoming`](server::Router::serve_with_incoming),
+//! though they still spawn their connections with `tokio`.
+//!
 //! [rustls]: https://docs.rs/rustls/0.16.0/rustls/
 
 pub mod channel;
+pub mod deadline;
+pub mod local;
 pub mod server;
 
 mod error;
@@ -104,6 +119,7 @@ pub use self::error::Error;
 pub use self::server::Server;
 #[doc(inline)]
 pub use self::service::grpc_timeout::TimeoutExpired;
+pub use self::service::timer::Timer;
 #[cfg(feature = "tls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 pub use self::tls::Certificate;
@@ -115,6 +131,9 @@ pub(crate) use self::service::executor::Executor;
 #[cfg(feature = "tls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 pub use self::channel::ClientTlsConfig;
+#[cfg(feature = "http3")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+pub use self::channel::Http3Config;
 #[cfg(feature = "tls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 pub use self::server::ServerTlsConfig;
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// The connections and calls in flight of a [`Server`](super::Server).
+///
+/// Receivers of [`Server::activity`](super::Server::activity) see it change
+/// as the server accepts and closes connections and calls, such as to log
+/// the progress of a graceful shutdown, or to alert on drains that don't
+/// end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Activity {
+    connections: usize,
+    streams: usize,
+    draining: bool,
+}
+
+impl Activity {
+    /// The number of open connections.
+    pub fn connections(&self) -> usize {
+        self.connections
+    }
+
+    /// The number of calls in flight, from their request headers to the
+    /// end of their response.
+    pub fn streams(&self) -> usize {
+        self.streams
+    }
+
+    /// Whether the shutdown signal of the server resolved, the server
+    /// waiting for its connections to close.
+    pub fn is_draining(&self) -> bool {
+        self.draining
+    }
+}
+
+/// The sender of the [`Activity`] of a server.
+#[derive(Debug, Clone)]
+pub(crate) struct ActivityTracker {
+    activity: Arc<watch::Sender<Activity>>,
+}
+
+impl ActivityTracker {
+    pub(crate) fn new() -> Self {
+        ActivityTracker {
+            activity: Arc::new(watch::channel(Activity::default()).0),
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<Activity> {
+        self.activity.subscribe()
+    }
+
+    /// Count a connection, for as long as the guard lives.
+    pub(crate) fn connection(&self) -> ActivityGuard {
+        self.activity.send_modify(|a| a.connections += 1);
+        ActivityGuard {
+            tracker: self.clone(),
+            count: |a| &mut a.connections,
+        }
+    }
+
+    /// Count a call, for as long as the guard lives.
+    pub(crate) fn stream(&self) -> ActivityGuard {
+        self.activity.send_modify(|a| a.streams += 1);
+        ActivityGuard {
+            tracker: self.clone(),
+            count: |a| &mut a.streams,
+        }
+    }
+
+    pub(crate) fn drain(&self) {
+        self.activity.send_modify(|a| a.draining = true);
+    }
+}
+
+pub(crate) struct ActivityGuard {
+    tracker: ActivityTracker,
+    count: fn(&mut Activity) -> &mut usize,
+}
+
+impl std::fmt::Debug for ActivityGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActivityGuard").finish()
+    }
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        self.tracker.activity.send_modify(|a| *(self.count)(a) -= 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::BoxBody;
+    use crate::transport::{local, server::Routes, Server};
+    use std::sync::Mutex;
+    use tokio::sync::oneshot;
+    use tower::{service_fn, Service, ServiceExt};
+
+    #[tokio::test]
+    async fn reports_drain_progress() {
+        let senders = Arc::new(Mutex::new(Vec::new()));
+        let svc = service_fn({
+            let senders = senders.clone();
+            move |_: http::Request<hyper::Body>| {
+                let (sender, body) = hyper::Body::channel();
+                senders.lock().unwrap().push(sender);
+                let response = http::Response::new(crate::body::boxed(body));
+                async move { Ok::<_, std::convert::Infallible>(response) }
+            }
+        });
+
+        let mut server = Server::builder();
+        let mut activity = server.activity();
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let (mut channel, incoming) = local::pair();
+        let server = tokio::spawn(
+            server
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = signal.await;
+                }),
+        );
+
+        let request = http::Request::post("http://localhost/test.Service/Stream")
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        let response = channel.ready().await.unwrap().call(request).await.unwrap();
+        assert_eq!(activity.borrow().connections(), 1);
+        assert_eq!(activity.borrow().streams(), 1);
+
+        shutdown.send(()).unwrap();
+        activity.wait_for(|a| a.is_draining()).await.unwrap();
+        assert_eq!(activity.borrow().streams(), 1);
+
+        drop(response);
+        senders.lock().unwrap().clear();
+        activity
+            .wait_for(|a| a.connections() == 0 && a.streams() == 0)
+            .await
+            .unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
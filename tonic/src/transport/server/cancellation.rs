@@ -0,0 +1,465 @@
+use super::activity::ActivityGuard;
+use crate::{transport::BoxFuture, Status};
+use bytes::Bytes;
+use http_body::Body;
+use pin_project::pin_project;
+use std::{
+    future::{pending, Future},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+};
+use tokio::{sync::watch, task::JoinHandle};
+use tower_service::Service;
+use tracing::Instrument;
+
+/// A handle telling whether the client of a call cancelled it.
+///
+/// The [`Server`](super::Server) inserts a `Cancellation` into the
+/// extensions of each request it receives. It fires when the call ends
+/// before the server sent all its response, because the client reset its
+/// stream, the connection closed or the call timed out. Handlers can hand
+/// it to the tasks they spawn, which outlive their future being dropped on
+/// cancellation, so that they stop their work too:
+///
+/// ```
+/// use tonic::transport::server::Cancellation;
+/// use tonic::{Request, Response, Status};
+///
+/// async fn handle(request: Request<()>) -> Result<Response<()>, Status> {
+///     let cancellation = request.extensions().get::<Cancellation>().cloned();
+///     tokio::spawn(async move {
+///         if let Some(cancellation) = cancellation {
+///             cancellation.cancelled().await;
+///             // Stop the work of the call.
+///         }
+///     });
+///     Ok(Response::new(()))
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cancellation {
+    cancelled: watch::Receiver<bool>,
+}
+
+impl Cancellation {
+    /// Whether the call is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    /// Wait for the call to be cancelled, never returning if it completes.
+    pub async fn cancelled(&self) {
+        let mut cancelled = self.cancelled.clone();
+        if cancelled.wait_for(|cancelled| *cancelled).await.is_err() {
+            pending::<()>().await;
+        }
+    }
+}
+
+/// Cancels the [`Cancellation`] of a call when dropped before the call
+/// completed, and holds the guard counting the call in the activity of the
+/// server.
+#[derive(Debug)]
+pub(crate) struct CancelOnDrop {
+    cancel: watch::Sender<bool>,
+    completed: AtomicBool,
+    _stream: Option<ActivityGuard>,
+}
+
+impl CancelOnDrop {
+    pub(crate) fn new(stream: Option<ActivityGuard>) -> (Self, Cancellation) {
+        let (cancel, cancelled) = watch::channel(false);
+        let guard = CancelOnDrop {
+            cancel,
+            completed: AtomicBool::new(false),
+            _stream: stream,
+        };
+        (guard, Cancellation { cancelled })
+    }
+
+    pub(crate) fn complete(&self) {
+        self.completed.store(true, Ordering::Release);
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if !self.completed.load(Ordering::Acquire) {
+            self.cancel.send_replace(true);
+        }
+    }
+}
+
+/// The end of the grace period of the shutdown of a server, after which
+/// the calls still in flight are cut short.
+#[derive(Debug, Clone)]
+pub(crate) struct GracePeriod {
+    expired: watch::Receiver<bool>,
+}
+
+impl GracePeriod {
+    pub(crate) fn new() -> (watch::Sender<bool>, Self) {
+        let (expire, expired) = watch::channel(false);
+        (expire, GracePeriod { expired })
+    }
+
+    /// A future resolving once the grace period expired.
+    pub(crate) fn expired(&self) -> BoxFuture<'static, ()> {
+        let mut expired = self.expired.clone();
+        Box::pin(async move {
+            if expired.wait_for(|expired| *expired).await.is_err() {
+                pending::<()>().await;
+            }
+        })
+    }
+}
+
+/// The status of the calls cut short by the end of the grace period.
+pub(crate) fn shutting_down() -> Status {
+    Status::unavailable("the server is shutting down")
+}
+
+/// A response body completing the call once it was entirely sent, and
+/// ending early with an `UNAVAILABLE` status when the grace period of the
+/// server expires.
+#[pin_project]
+pub(crate) struct CancellableBody<B> {
+    #[pin]
+    inner: B,
+    guard: CancelOnDrop,
+    expiry: Option<BoxFuture<'static, ()>>,
+    expired: bool,
+}
+
+impl<B> CancellableBody<B> {
+    pub(crate) fn new(
+        inner: B,
+        guard: CancelOnDrop,
+        expiry: Option<BoxFuture<'static, ()>>,
+    ) -> Self {
+        CancellableBody {
+            inner,
+            guard,
+            expiry,
+            expired: false,
+        }
+    }
+}
+
+/// Whether the grace period expired, polling `expiry` until it does.
+fn poll_expired(
+    expiry: &mut Option<BoxFuture<'static, ()>>,
+    expired: &mut bool,
+    cx: &mut Context<'_>,
+) -> bool {
+    if let Some(future) = expiry {
+        if future.as_mut().poll(cx).is_ready() {
+            *expiry = None;
+            *expired = true;
+        }
+    }
+    *expired
+}
+
+impl<B> Body for CancellableBody<B>
+where
+    B: Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        if *this.expired {
+            return Poll::Ready(None);
+        }
+        match this.inner.poll_data(cx) {
+            Poll::Pending if poll_expired(this.expiry, this.expired, cx) => Poll::Ready(None),
+            data => data,
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        if !*this.expired {
+            let trailers = this.inner.poll_trailers(cx);
+            if trailers.is_ready() {
+                this.guard.complete();
+                return trailers;
+            }
+            if !poll_expired(this.expiry, this.expired, cx) {
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(Ok(shutting_down().to_header_map().ok()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        if self.expired {
+            return false;
+        }
+        // Bodies ending with their data aren't asked for their trailers.
+        let end = self.inner.is_end_stream();
+        if end {
+            self.guard.complete();
+        }
+        end
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Spawns the futures of the calls of some methods, so that they run to
+/// completion even when the call is cancelled.
+#[derive(Debug, Clone)]
+pub(crate) struct RunToCompletion<S> {
+    inner: S,
+    names: Arc<[String]>,
+}
+
+impl<S> RunToCompletion<S> {
+    pub(crate) fn new(inner: S, names: Arc<[String]>) -> Self {
+        RunToCompletion { inner, names }
+    }
+
+    /// Whether the method of `path`, `/package.Service/Method`, or its
+    /// service is one of `names`.
+    fn runs_to_completion(&self, path: &str) -> bool {
+        let method = path.strip_prefix('/').unwrap_or(path);
+        let service = method
+            .split_once('/')
+            .map_or(method, |(service, _)| service);
+        self.names
+            .iter()
+            .any(|name| name == method || name == service)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for RunToCompletion<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send,
+    ResBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = crate::Error;
+    type Future = RunToCompletionFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        let spawn = self.runs_to_completion(req.uri().path());
+        let future = self.inner.call(req);
+        if !spawn {
+            return RunToCompletionFuture::Inline { future };
+        }
+
+        let future = async move { future.await.map_err(Into::into) };
+        RunToCompletionFuture::Spawned {
+            handle: tokio::spawn(future.in_current_span()),
+        }
+    }
+}
+
+#[pin_project(project = RunToCompletionProj)]
+pub(crate) enum RunToCompletionFuture<F, B> {
+    Inline {
+        #[pin]
+        future: F,
+    },
+    Spawned {
+        handle: JoinHandle<Result<http::Response<B>, crate::Error>>,
+    },
+}
+
+impl<F, B, E> Future for RunToCompletionFuture<F, B>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<crate::Error>,
+{
+    type Output = Result<http::Response<B>, crate::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            RunToCompletionProj::Inline { future } => future.poll(cx).map_err(Into::into),
+            RunToCompletionProj::Spawned { handle } => match ready!(Pin::new(handle).poll(cx)) {
+                Ok(result) => Poll::Ready(result),
+                Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+                Err(error) => Poll::Ready(Err(error.into())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::{empty_body, BoxBody};
+    use crate::transport::{local, server::Routes, Server};
+    use std::{sync::Mutex, time::Duration};
+    use tokio::sync::oneshot;
+    use tower::{service_fn, Service, ServiceExt};
+
+    #[test]
+    fn completes_sent_bodies() {
+        let (guard, cancellation) = CancelOnDrop::new(None);
+        let body = CancellableBody::new(empty_body(), guard, None);
+        assert!(body.is_end_stream());
+        drop(body);
+        assert!(!cancellation.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn cancels_calls_reset_by_clients() {
+        let (tx, rx) = oneshot::channel();
+        let tx = Arc::new(Mutex::new(Some(tx)));
+        let svc = service_fn(move |request: http::Request<hyper::Body>| {
+            let cancellation = request.extensions().get::<Cancellation>().cloned();
+            // A response of a body that doesn't end while its sender is alive.
+            let (sender, body) = hyper::Body::channel();
+            let _ = tx
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap()
+                .send((cancellation.unwrap(), sender));
+            let response = http::Response::new(crate::body::boxed(body));
+            async move { Ok::<_, std::convert::Infallible>(response) }
+        });
+
+        let (mut channel, incoming) = local::pair();
+        tokio::spawn(
+            Server::builder()
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming(incoming),
+        );
+
+        let request = http::Request::post("http://localhost/test.Service/Call")
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        let response = channel.ready().await.unwrap().call(request).await.unwrap();
+        let (cancellation, _sender) = rx.await.unwrap();
+        assert!(!cancellation.is_cancelled());
+
+        drop(response);
+        cancellation.cancelled().await;
+        assert!(cancellation.is_cancelled());
+    }
+
+    #[test]
+    fn matches_services_and_methods() {
+        let names: Arc<[String]> = vec!["a.A".to_owned(), "b.B/Call".to_owned()].into();
+        let svc = RunToCompletion::new((), names);
+        assert!(svc.runs_to_completion("/a.A/Call"));
+        assert!(svc.runs_to_completion("/a.A/Other"));
+        assert!(svc.runs_to_completion("/b.B/Call"));
+        assert!(!svc.runs_to_completion("/b.B/Other"));
+        assert!(!svc.runs_to_completion("/a.AB/Call"));
+    }
+
+    #[tokio::test]
+    async fn runs_cancelled_calls_to_completion() {
+        let (started_tx, mut started) = tokio::sync::mpsc::unbounded_channel();
+        let (done_tx, mut done) = tokio::sync::mpsc::unbounded_channel();
+        let svc = service_fn(move |request: http::Request<hyper::Body>| {
+            let cancellation = request.extensions().get::<Cancellation>().cloned();
+            let (proceed_tx, proceed) = oneshot::channel::<()>();
+            let _ = started_tx.send((cancellation.unwrap(), proceed_tx));
+            let done_tx = done_tx.clone();
+            async move {
+                let _ = proceed.await;
+                let _ = done_tx.send(());
+                Ok::<_, std::convert::Infallible>(http::Response::new(empty_body()))
+            }
+        });
+
+        let (mut channel, incoming) = local::pair();
+        tokio::spawn(
+            Server::builder()
+                .run_to_completion("test.Service")
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming(incoming),
+        );
+
+        let request = http::Request::post("http://localhost/test.Service/Call")
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        let call = tokio::spawn(channel.ready().await.unwrap().call(request));
+        let (cancellation, proceed) = started.recv().await.unwrap();
+
+        call.abort();
+        cancellation.cancelled().await;
+        proceed.send(()).unwrap();
+        done.recv().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn cuts_calls_short_once_the_grace_period_expires() {
+        let senders = Arc::new(Mutex::new(Vec::new()));
+        let svc = service_fn({
+            let senders = senders.clone();
+            move |request: http::Request<hyper::Body>| {
+                let (sender, body) = hyper::Body::channel();
+                senders.lock().unwrap().push(sender);
+                let stuck = request.uri().path() == "/test.Service/Stuck";
+                async move {
+                    if stuck {
+                        pending::<()>().await;
+                    }
+                    let response = http::Response::new(crate::body::boxed(body));
+                    Ok::<_, std::convert::Infallible>(response)
+                }
+            }
+        });
+
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let (mut channel, incoming) = local::pair();
+        let server = tokio::spawn(
+            Server::builder()
+                .grace_period(Duration::from_millis(10))
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = signal.await;
+                }),
+        );
+
+        let request = |method| {
+            http::Request::post(format!("http://localhost/test.Service/{}", method))
+                .header("content-type", "application/grpc")
+                .body(BoxBody::default())
+                .unwrap()
+        };
+        let streaming = channel.ready().await.unwrap().call(request("Stream"));
+        let mut streaming = streaming.await.unwrap().into_body();
+        let stuck = channel.ready().await.unwrap().call(request("Stuck"));
+        let stuck = tokio::spawn(stuck);
+        while senders.lock().unwrap().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        shutdown.send(()).unwrap();
+        let stuck = stuck.await.unwrap().unwrap();
+        assert_eq!(stuck.headers()["grpc-status"], "14");
+        assert!(streaming.data().await.is_none());
+        let trailers = streaming.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers["grpc-status"], "14");
+        server.await.unwrap().unwrap();
+    }
+}
@@ -132,7 +132,13 @@ where
             None
         };
 
-        TlsConnectInfo { inner, certs }
+        let server_name = session.server_name().map(str::to_owned);
+
+        TlsConnectInfo {
+            inner,
+            certs,
+            server_name,
+        }
     }
 }
 
@@ -149,6 +155,7 @@ where
 pub struct TlsConnectInfo<T> {
     inner: T,
     certs: Option<Arc<Vec<Certificate>>>,
+    server_name: Option<String>,
 }
 
 #[cfg(feature = "tls")]
@@ -168,4 +175,9 @@ impl<T> TlsConnectInfo<T> {
     pub fn peer_certs(&self) -> Option<Arc<Vec<Certificate>>> {
         self.certs.clone()
     }
+
+    /// Return the server name the client asked for with the SNI extension.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
 }
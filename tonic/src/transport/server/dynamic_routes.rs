@@ -0,0 +1,166 @@
+use crate::{body::BoxBody, server::NamedService, transport::Body, Status};
+use http::{Request, Response};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    future::{self, Future},
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll},
+};
+use tower::{util::BoxCloneService, ServiceExt};
+use tower_service::Service;
+
+type BoxService = BoxCloneService<Request<Body>, Response<BoxBody>, Infallible>;
+
+/// Routes of services added, replaced and removed while they're served, such
+/// as those of plugins or of feature flags, shared by their clones.
+///
+/// The routes serve the methods that the services added to a server don't,
+/// as its fallback service:
+///
+/// ```no_run
+/// # use tonic::transport::{server::{DynamicRoutes, Routes}, Server};
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let routes = DynamicRoutes::new();
+/// let server = Server::builder()
+///     .add_routes(Routes::default().fallback_service(routes.clone()))
+///     .serve("[::1]:50051".parse()?);
+/// // Add, replace and remove services with `routes` while the server runs.
+/// server.await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Calls in progress go on with the service they started with when it's
+/// replaced or removed.
+#[derive(Clone, Default)]
+pub struct DynamicRoutes {
+    services: Arc<Mutex<HashMap<&'static str, BoxService>>>,
+}
+
+impl DynamicRoutes {
+    /// Create routes without services.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the service `svc` to the routes, replacing the service of the
+    /// same name at once, and return whether one was replaced.
+    pub fn add_service<S>(&self, svc: S) -> bool
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        self.services()
+            .insert(S::NAME, BoxCloneService::new(svc))
+            .is_some()
+    }
+
+    /// Remove the service named `name` from the routes, and return whether
+    /// there was one.
+    pub fn remove_service(&self, name: &str) -> bool {
+        self.services().remove(name).is_some()
+    }
+
+    /// Whether the routes have a service named `name`.
+    pub fn contains_service(&self, name: &str) -> bool {
+        self.services().contains_key(name)
+    }
+
+    fn services(&self) -> MutexGuard<'_, HashMap<&'static str, BoxService>> {
+        self.services.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for DynamicRoutes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicRoutes")
+            .field("services", &self.services().keys())
+            .finish()
+    }
+}
+
+impl Service<Request<Body>> for DynamicRoutes {
+    type Response = Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let name = req.uri().path().split('/').nth(1).unwrap_or_default();
+        let svc = self.services().get(name).cloned();
+        match svc {
+            Some(svc) => Box::pin(svc.oneshot(req)),
+            None => Box::pin(future::ready(Ok(Status::unimplemented("").to_http()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::server::Routes;
+
+    /// Answers with the status of its version.
+    #[derive(Clone)]
+    struct Versioned(&'static str);
+
+    impl Service<Request<Body>> for Versioned {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Request<Body>) -> Self::Future {
+            future::ready(Ok(Status::ok(self.0).to_http()))
+        }
+    }
+
+    impl NamedService for Versioned {
+        const NAME: &'static str = "test.Versioned";
+    }
+
+    async fn call(routes: &Routes) -> (String, Option<String>) {
+        let req = Request::post("/test.Versioned/Call")
+            .body(Body::empty())
+            .unwrap();
+        let res = routes.clone().oneshot(req).await.unwrap();
+        let status = res.headers()["grpc-status"].to_str().unwrap().to_owned();
+        let message = res
+            .headers()
+            .get("grpc-message")
+            .map(|message| message.to_str().unwrap().to_owned());
+        (status, message)
+    }
+
+    #[tokio::test]
+    async fn changes_services_while_served() {
+        let dynamic = DynamicRoutes::new();
+        let routes = Routes::default()
+            .fallback_service(dynamic.clone())
+            .prepare();
+        assert_eq!(call(&routes).await.0, "12");
+
+        assert!(!dynamic.add_service(Versioned("1")));
+        assert_eq!(call(&routes).await, ("0".to_owned(), Some("1".to_owned())));
+
+        assert!(dynamic.add_service(Versioned("2")));
+        assert_eq!(call(&routes).await, ("0".to_owned(), Some("2".to_owned())));
+
+        assert!(dynamic.remove_service(Versioned::NAME));
+        assert!(!dynamic.contains_service(Versioned::NAME));
+        assert_eq!(call(&routes).await.0, "12");
+    }
+}
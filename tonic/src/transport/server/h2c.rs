@@ -0,0 +1,450 @@
+use super::keepalive::{EnforcedIo, KeepaliveEnforcementPolicy};
+use super::serve::{self, ConnectionAge};
+use super::{BoxHttpBody, BoxService};
+use crate::transport::BoxFuture;
+use bytes::{BufMut, Bytes, BytesMut};
+use http::{header, HeaderName, Request, Response, StatusCode, Version};
+use http_body::Body as _;
+use hyper::{upgrade::OnUpgrade, Body};
+use std::{
+    fmt,
+    future::{self, Future},
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::{oneshot, watch, OwnedSemaphorePermit},
+};
+use tower::Service;
+
+const PREFACE_LEN: usize = 24;
+const FRAME_HEADER_LEN: usize = 9;
+/// The largest frame a server accepts before its settings apply.
+const MAX_FRAME_SIZE: usize = 16_384;
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+
+/// The HTTP/1.1 request of a connection upgrading to h2c.
+pub(crate) struct Upgrade {
+    on_upgrade: OnUpgrade,
+    svc: BoxService,
+    /// The frames of the request, as the first stream of the client.
+    request: Bytes,
+}
+
+/// Upgrades the connection of the HTTP/1.1 request asking for h2c to
+/// HTTP/2, handing the service of the connection over to the upgraded one.
+pub(crate) struct H2cUpgrade {
+    inner: Option<BoxService>,
+    upgrade: Option<oneshot::Sender<Upgrade>>,
+}
+
+impl H2cUpgrade {
+    pub(crate) fn new(inner: BoxService, upgrade: oneshot::Sender<Upgrade>) -> Self {
+        Self {
+            inner: Some(inner),
+            upgrade: Some(upgrade),
+        }
+    }
+}
+
+impl Service<Request<Body>> for H2cUpgrade {
+    type Response = Response<BoxHttpBody>;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.inner {
+            Some(inner) => inner.poll_ready(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let headers = self
+            .upgrade
+            .as_ref()
+            .and_then(|_| upgrade_headers(&request));
+        let (Some(headers), Some(upgrade), Some(svc)) =
+            (headers, self.upgrade.take(), self.inner.take())
+        else {
+            return match &mut self.inner {
+                Some(inner) => inner.call(request),
+                None => Box::pin(future::ready(Err("the connection upgraded to h2c".into()))),
+            };
+        };
+
+        Box::pin(async move {
+            let on_upgrade = hyper::upgrade::on(&mut request);
+            // The body is read before switching protocols, to be sent on the
+            // first stream.
+            let body = hyper::body::to_bytes(request.into_body()).await?;
+            let _ = upgrade.send(Upgrade {
+                on_upgrade,
+                svc,
+                request: request_frames(&headers, &body),
+            });
+
+            let mut response =
+                Response::new(crate::body::empty_body().map_err(Into::into).boxed_unsync());
+            *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+            let headers = response.headers_mut();
+            headers.insert(header::CONNECTION, "upgrade".parse().unwrap());
+            headers.insert(header::UPGRADE, "h2c".parse().unwrap());
+            Ok(response)
+        })
+    }
+}
+
+/// Serve `connection`, then the HTTP/2 connection it upgrades to with `http`
+/// if it does, holding the `permit` of its connection limit meanwhile.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn serve_connection<C, E>(
+    connection: C,
+    graceful_shutdown: impl Fn(Pin<&mut C>),
+    upgrade: Option<(oneshot::Receiver<Upgrade>, hyper::server::conn::Http)>,
+    drain: watch::Receiver<()>,
+    age: ConnectionAge,
+    keepalive_enforcement: Option<KeepaliveEnforcementPolicy>,
+    _permit: Option<OwnedSemaphorePermit>,
+) where
+    C: Future<Output = Result<(), E>>,
+    E: fmt::Display,
+{
+    serve::serve_connection(connection, drain.clone(), age, graceful_shutdown).await;
+    // The connection ends once it switched protocols.
+    let Some((mut upgraded, http)) = upgrade else {
+        return;
+    };
+    if let Ok(upgrade) = upgraded.try_recv() {
+        serve_upgraded(upgrade, http, drain, age, keepalive_enforcement).await;
+    }
+}
+
+/// Serve the connection of `upgrade` with HTTP/2 once it switched protocols.
+async fn serve_upgraded(
+    upgrade: Upgrade,
+    http: hyper::server::conn::Http,
+    drain: watch::Receiver<()>,
+    age: ConnectionAge,
+    keepalive_enforcement: Option<KeepaliveEnforcementPolicy>,
+) {
+    let io = match upgrade.on_upgrade.await {
+        Ok(io) => io,
+        Err(e) => {
+            tracing::debug!("failed to upgrade a connection to h2c: {}", e);
+            return;
+        }
+    };
+    let io = EnforcedIo::new(H2cIo::new(io, upgrade.request), keepalive_enforcement);
+    serve_http2(http, io, upgrade.svc, drain, age).await
+}
+
+/// Made outside of an async block, for the future of the connection to be
+/// `Send`.
+fn serve_http2(
+    http: hyper::server::conn::Http,
+    io: EnforcedIo<H2cIo<hyper::upgrade::Upgraded>>,
+    svc: BoxService,
+    drain: watch::Receiver<()>,
+    age: ConnectionAge,
+) -> impl Future<Output = ()> {
+    let connection = http.serve_connection(io, svc);
+    serve::serve_connection(connection, drain, age, |connection| {
+        connection.graceful_shutdown()
+    })
+}
+
+/// The HTTP/2 header block of `request`, if it asks for an upgrade to h2c
+/// that the server makes.
+///
+/// Upgrades with bodies too large for the frame of a single stream before
+/// the settings of the server apply, or with chunked ones, are ignored, the
+/// requests being served over HTTP/1.1.
+fn upgrade_headers(request: &Request<Body>) -> Option<BytesMut> {
+    let headers = request.headers();
+    let h2c = headers
+        .get_all(header::UPGRADE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|protocol| protocol.trim().eq_ignore_ascii_case("h2c"));
+    if request.version() != Version::HTTP_11 || !h2c || !headers.contains_key("http2-settings") {
+        return None;
+    }
+    if headers.contains_key(header::TRANSFER_ENCODING) {
+        return None;
+    }
+    if let Some(length) = headers.get(header::CONTENT_LENGTH) {
+        let length = length.to_str().ok()?.parse::<usize>().ok()?;
+        if length > MAX_FRAME_SIZE {
+            return None;
+        }
+    }
+
+    let mut block = BytesMut::new();
+    put_header(&mut block, b":method", request.method().as_str().as_bytes());
+    put_header(&mut block, b":scheme", b"http");
+    if let Some(host) = headers.get(header::HOST) {
+        put_header(&mut block, b":authority", host.as_bytes());
+    }
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |path| path.as_str());
+    put_header(&mut block, b":path", path.as_bytes());
+    for (name, value) in headers {
+        if !is_connection_specific(name, value.as_bytes()) {
+            put_header(&mut block, name.as_str().as_bytes(), value.as_bytes());
+        }
+    }
+    (block.len() <= MAX_FRAME_SIZE).then_some(block)
+}
+
+/// Whether the header is specific to the HTTP/1.1 connection, which HTTP/2
+/// doesn't allow.
+fn is_connection_specific(name: &HeaderName, value: &[u8]) -> bool {
+    match name.as_str() {
+        "connection" | "upgrade" | "http2-settings" | "host" | "keep-alive"
+        | "proxy-connection" | "transfer-encoding" => true,
+        "te" => value != b"trailers",
+        _ => false,
+    }
+}
+
+/// Put the header as a literal field that isn't indexed, so that the
+/// decoder of the server keeps the state the client expects.
+fn put_header(block: &mut BytesMut, name: &[u8], value: &[u8]) {
+    block.put_u8(0);
+    put_int(block, 7, name.len());
+    block.put_slice(name);
+    put_int(block, 7, value.len());
+    block.put_slice(value);
+}
+
+/// Put an HPACK integer with a prefix of `bits`.
+fn put_int(block: &mut BytesMut, bits: u8, mut value: usize) {
+    let max = (1 << bits) - 1;
+    if value < max {
+        block.put_u8(value as u8);
+        return;
+    }
+    block.put_u8(max as u8);
+    value -= max;
+    while value >= 0x80 {
+        block.put_u8((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    block.put_u8(value as u8);
+}
+
+/// The HEADERS and DATA frames of the request on stream 1.
+fn request_frames(headers: &[u8], body: &[u8]) -> Bytes {
+    let mut frames = BytesMut::new();
+    let flags = if body.is_empty() { END_STREAM } else { 0 };
+    put_frame(&mut frames, HEADERS, END_HEADERS | flags, headers);
+    if !body.is_empty() {
+        put_frame(&mut frames, DATA, END_STREAM, body);
+    }
+    frames.freeze()
+}
+
+fn put_frame(frames: &mut BytesMut, kind: u8, flags: u8, payload: &[u8]) {
+    frames.put_uint(payload.len() as u64, 3);
+    frames.put_u8(kind);
+    frames.put_u8(flags);
+    frames.put_u32(1);
+    frames.put_slice(payload);
+}
+
+/// The IO of a connection upgraded to h2c, from which the server reads the
+/// frames of the upgrade request right after the preface of the client and
+/// its first `SETTINGS` frame.
+struct H2cIo<IO> {
+    inner: IO,
+    /// The number of bytes read from the client.
+    read: usize,
+    /// The length of the preface and the first frame of the client, once
+    /// its header was read.
+    prefix: Option<usize>,
+    header: [u8; FRAME_HEADER_LEN],
+    request: Bytes,
+}
+
+impl<IO> H2cIo<IO> {
+    fn new(inner: IO, request: Bytes) -> Self {
+        Self {
+            inner,
+            read: 0,
+            prefix: None,
+            header: [0; FRAME_HEADER_LEN],
+            request,
+        }
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for H2cIo<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let prefix = this.prefix.unwrap_or(PREFACE_LEN + FRAME_HEADER_LEN);
+        if this.read < prefix {
+            // Read up to the end of the prefix, for the request to follow it.
+            let dst = buf.initialize_unfilled_to(buf.remaining().min(prefix - this.read));
+            let mut limited = ReadBuf::new(dst);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut limited))?;
+            let read = limited.filled();
+            for (offset, byte) in read.iter().enumerate() {
+                let position = this.read + offset;
+                if (PREFACE_LEN..PREFACE_LEN + FRAME_HEADER_LEN).contains(&position) {
+                    this.header[position - PREFACE_LEN] = *byte;
+                }
+            }
+            let n = read.len();
+            buf.advance(n);
+            this.read += n;
+            if this.prefix.is_none() && this.read == prefix {
+                let length =
+                    u32::from_be_bytes([0, this.header[0], this.header[1], this.header[2]]);
+                this.prefix = Some(prefix + length as usize);
+            }
+            return Poll::Ready(Ok(()));
+        }
+
+        if !this.request.is_empty() {
+            let n = buf.remaining().min(this.request.len());
+            buf.put_slice(&this.request.split_to(n));
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for H2cIo<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::server::{Routes, TcpIncoming};
+    use crate::transport::Server;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tower::service_fn;
+
+    async fn read_frame(stream: &mut TcpStream) -> (u8, u32, Vec<u8>) {
+        let mut header = [0; 9];
+        stream.read_exact(&mut header).await.unwrap();
+        let length = u32::from_be_bytes([0, header[0], header[1], header[2]]);
+        let stream_id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+        let mut payload = vec![0; length as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        (header[3], stream_id, payload)
+    }
+
+    #[tokio::test]
+    async fn upgrades_to_h2c() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let svc = service_fn({
+            let requests = requests.clone();
+            move |request: http::Request<hyper::Body>| {
+                let requests = requests.clone();
+                async move {
+                    let path = request.uri().path().to_owned();
+                    let version = request.version();
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    requests.lock().unwrap().push((version, path, body));
+                    Ok::<_, std::convert::Infallible>(
+                        crate::Status::new(crate::Code::Ok, "").to_http(),
+                    )
+                }
+            }
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            Server::builder()
+                .accept_http1(true)
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming(incoming),
+        );
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"POST /test.Service/Call HTTP/1.1\r\n\
+                  Host: localhost\r\n\
+                  Connection: Upgrade, HTTP2-Settings\r\n\
+                  Upgrade: h2c\r\n\
+                  HTTP2-Settings: AAMAAABkAAQAAP__\r\n\
+                  Content-Length: 5\r\n\
+                  \r\n\
+                  hello",
+            )
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            response.push(stream.read_u8().await.unwrap());
+        }
+        assert!(response.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+
+        stream
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0")
+            .await
+            .unwrap();
+        let status = loop {
+            let (kind, stream_id, payload) = read_frame(&mut stream).await;
+            if kind == super::HEADERS && stream_id == 1 {
+                break payload[0];
+            }
+        };
+        // `:status: 200`, indexed.
+        assert_eq!(status, 0x88);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [(
+                http::Version::HTTP_2,
+                "/test.Service/Call".to_owned(),
+                bytes::Bytes::from_static(b"hello")
+            )]
+        );
+    }
+}
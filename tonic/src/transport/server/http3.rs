@@ -0,0 +1,314 @@
+use super::listeners::Connections;
+use super::{Accepted, BoxService, Connected, MakeSvc, SharedService};
+use crate::transport::service::ServerIo;
+use crate::transport::BoxFuture;
+use bytes::{Buf, Bytes};
+use http::{header::ALT_SVC, HeaderValue, Request, Response, Version};
+use http_body::Body as _;
+use hyper::Body;
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::watch;
+use tokio_stream::Stream;
+use tower::{Service, ServiceExt};
+
+type RequestStream<S> = h3::server::RequestStream<S, Bytes>;
+
+/// Connection info for HTTP/3 connections.
+///
+/// This type will be accessible through [request extensions][ext] if you're
+/// serving [HTTP/3 listeners](super::Listeners::http3).
+///
+/// See [Connected] for more details.
+///
+/// [ext]: crate::Request::extensions
+/// [Connected]: crate::transport::server::Connected
+#[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+#[derive(Clone, Debug)]
+pub struct Http3ConnectInfo {
+    /// The local address of the connection.
+    pub local_addr: Option<SocketAddr>,
+    /// The remote (peer) address of the connection, which changes if the
+    /// connection migrates.
+    pub remote_addr: SocketAddr,
+}
+
+impl Http3ConnectInfo {
+    /// Return the local address of the connection.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Return the remote address of the connection.
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+}
+
+/// The peer of a QUIC connection, making its service.
+struct Peer(Http3ConnectInfo);
+
+impl Connected for Peer {
+    type ConnectInfo = Http3ConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.0.clone()
+    }
+}
+
+/// The `Alt-Svc` header advertising the HTTP/3 `endpoints` to clients of
+/// other connections.
+pub(super) fn alt_svc(endpoints: &[quinn::Endpoint]) -> Result<HeaderValue, crate::Error> {
+    let mut services = Vec::new();
+    for endpoint in endpoints {
+        services.push(format!("h3=\":{}\"", endpoint.local_addr()?.port()));
+    }
+    Ok(HeaderValue::try_from(services.join(", "))?)
+}
+
+/// Add the `alt_svc` header to the responses of the requests of other
+/// versions than HTTP/3.
+pub(super) fn advertised(svc: SharedService, alt_svc: HeaderValue) -> SharedService {
+    let svc = tower::service_fn(move |request: Request<Body>| {
+        let http3 = request.version() == Version::HTTP_3;
+        let response = svc.clone().oneshot(request);
+        let alt_svc = alt_svc.clone();
+        async move {
+            let mut response = response.await?;
+            if !http3 {
+                response.headers_mut().insert(ALT_SVC, alt_svc);
+            }
+            Ok(response)
+        }
+    });
+    SharedService::new(svc)
+}
+
+/// Serve the connections of `endpoint` as they're accepted, until it's
+/// closed or the returned stream is dropped.
+///
+/// The stream doesn't yield its connections to the acceptor, since they
+/// aren't byte streams, but serves them with the services of `svc` until
+/// `drained` changes.
+pub(super) fn connections(
+    endpoint: quinn::Endpoint,
+    svc: MakeSvc<SharedService>,
+    drained: watch::Receiver<()>,
+) -> Connections {
+    let accept = async move {
+        while let Some(connecting) = endpoint.accept().await {
+            tokio::spawn(serve_connection(connecting, svc.clone(), drained.clone()));
+        }
+    };
+    Box::pin(Serving(Some(Box::pin(accept))))
+}
+
+struct Serving(Option<BoxFuture<'static, ()>>);
+
+impl Stream for Serving {
+    type Item = Result<Accepted, crate::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(accept) = &mut self.0 else {
+            return Poll::Ready(None);
+        };
+        match accept.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.0 = None;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+async fn serve_connection(
+    connecting: quinn::Connecting,
+    svc: MakeSvc<SharedService>,
+    mut drained: watch::Receiver<()>,
+) {
+    let connection = match connecting.await {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::debug!("http3 handshake error: {}", e);
+            return;
+        }
+    };
+    let info = Http3ConnectInfo {
+        local_addr: None,
+        remote_addr: connection.remote_address(),
+    };
+    let mut svc = svc.make_service(&ServerIo::new_io(Peer(info)));
+
+    let connection = h3::server::Connection::<_, Bytes>::new(h3_quinn::Connection::new(connection));
+    let mut connection = match connection.await {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::debug!("http3 connection error: {}", e);
+            return;
+        }
+    };
+
+    let mut draining = false;
+    loop {
+        let accepted = tokio::select! {
+            accepted = connection.accept() => accepted,
+            _ = drained.changed(), if !draining => {
+                draining = true;
+                // Let the client know to stop sending requests, serving those
+                // it already sent.
+                if let Err(e) = connection.shutdown(0).await {
+                    tracing::debug!("http3 connection error: {}", e);
+                    return;
+                }
+                continue;
+            }
+        };
+        let (request, stream) = match accepted {
+            Ok(Some(accepted)) => accepted,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::debug!("http3 connection error: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = serve_request(&mut svc, request, stream, &drained).await {
+            tracing::debug!("http3 connection error: {}", e);
+            return;
+        }
+    }
+}
+
+/// Call `svc` with the request of `stream`, sending its response in a task
+/// of its own.
+async fn serve_request(
+    svc: &mut BoxService,
+    request: Request<()>,
+    stream: RequestStream<h3_quinn::BidiStream<Bytes>>,
+    drained: &watch::Receiver<()>,
+) -> Result<(), crate::Error> {
+    let (mut send, mut recv) = stream.split();
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let received = async {
+            while let Some(mut data) = recv.recv_data().await? {
+                sender
+                    .send_data(data.copy_to_bytes(data.remaining()))
+                    .await?;
+            }
+            if let Some(trailers) = recv.recv_trailers().await? {
+                sender.send_trailers(trailers).await?;
+            }
+            Ok::<_, crate::Error>(())
+        };
+        if let Err(e) = received.await {
+            tracing::debug!("http3 request body error: {}", e);
+            sender.abort();
+        }
+    });
+
+    let response = svc.ready().await?.call(request.map(|()| body));
+    // The drain waits for the response to be sent.
+    let drained = drained.clone();
+    tokio::spawn(async move {
+        let sent = async {
+            let (parts, mut body) = response.await?.into_parts();
+            send.send_response(Response::from_parts(parts, ())).await?;
+            while let Some(data) = body.data().await {
+                send.send_data(data?).await?;
+            }
+            match body.trailers().await? {
+                Some(trailers) => send.send_trailers(trailers).await?,
+                None => send.finish().await?,
+            }
+            Ok::<_, crate::Error>(())
+        };
+        if let Err(e) = sent.await {
+            tracing::debug!("http3 response error: {}", e);
+        }
+        drop(drained);
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::body::BoxBody;
+    use crate::transport::server::{Listeners, Routes};
+    use crate::transport::{local, Certificate, Channel, Endpoint, Http3Config, Identity, Server};
+    use tokio::sync::oneshot;
+    use tower::{service_fn, Service, ServiceExt};
+
+    const CA: &[u8] = include_bytes!("../../../../examples/data/tls/ca.pem");
+    const CERT: &[u8] = include_bytes!("../../../../examples/data/tls/server.pem");
+    const KEY: &[u8] = include_bytes!("../../../../examples/data/tls/server.key");
+
+    async fn call(channel: &mut Channel) -> http::Response<BoxBody> {
+        let request = http::Request::post("http://localhost/test.Service/Call")
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        channel.ready().await.unwrap().call(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_http3_listeners() {
+        let svc = service_fn(|request: http::Request<hyper::Body>| async move {
+            let mut response = crate::Status::new(crate::Code::Ok, "").to_http();
+            let request = crate::Request::from_http(request);
+            let remote_addr = format!("{:?}", request.remote_addr());
+            response
+                .headers_mut()
+                .insert("remote-addr", remote_addr.parse().unwrap());
+            Ok::<_, std::convert::Infallible>(response)
+        });
+
+        let (mut local, incoming) = local::pair();
+        let listeners = Listeners::new().incoming(incoming).http3(
+            "127.0.0.1:0".parse().unwrap(),
+            Identity::from_pem(CERT, KEY),
+        );
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let server = tokio::spawn(
+            Server::builder()
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_listeners_with_shutdown(listeners, async {
+                    let _ = signal.await;
+                }),
+        );
+
+        // Other connections advertise the port of the HTTP/3 listener.
+        let response = call(&mut local).await;
+        let alt_svc = response.headers()["alt-svc"].to_str().unwrap();
+        let port = alt_svc
+            .strip_prefix("h3=\":")
+            .and_then(|port| port.strip_suffix('"'))
+            .unwrap();
+
+        let config = Http3Config::new().ca_certificate(Certificate::from_pem(CA));
+        let mut http3 = Endpoint::from_shared(format!("https://localhost:{}", port))
+            .unwrap()
+            .http3(config)
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let response = call(&mut http3).await;
+        assert!(!response.headers().contains_key("alt-svc"));
+        let remote_addr = response.headers()["remote-addr"].to_str().unwrap();
+        assert!(
+            remote_addr.starts_with("Some(127.0.0.1:"),
+            "{}",
+            remote_addr
+        );
+        drop((local, http3));
+
+        shutdown.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
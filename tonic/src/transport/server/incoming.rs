@@ -1,25 +1,41 @@
-use super::{Connected, Server};
+use super::Connected;
 use crate::transport::service::ServerIo;
+#[cfg(feature = "tls")]
+use crate::transport::service::TlsAcceptor;
 use hyper::server::{
     accept::Accept,
     conn::{AddrIncoming, AddrStream},
 };
 use std::{
+    io,
     net::SocketAddr,
     pin::{pin, Pin},
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
 use tokio::{
     io::{AsyncRead, AsyncWrite},
-    net::TcpListener,
+    net::{TcpListener, TcpStream},
 };
 use tokio_stream::{Stream, StreamExt};
 
+/// The options of a server for the connections it accepts.
+#[derive(Clone)]
+pub(crate) struct AcceptOptions {
+    pub(crate) tcp_nodelay: bool,
+    pub(crate) tcp_keepalive: Option<Duration>,
+    pub(crate) configure_listener: Option<ListenerHook>,
+    pub(crate) configure_connection: Option<AcceptHook>,
+    pub(crate) reuse_port_acceptors: Option<usize>,
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Option<TlsAcceptor>,
+}
+
 #[cfg(not(feature = "tls"))]
-pub(crate) fn tcp_incoming<IO, IE, L>(
+pub(crate) fn tcp_incoming<IO, IE>(
     incoming: impl Stream<Item = Result<IO, IE>>,
-    _server: Server<L>,
+    _options: AcceptOptions,
 ) -> impl Stream<Item = Result<ServerIo<IO>, crate::Error>>
 where
     IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
@@ -35,9 +51,9 @@ where
 }
 
 #[cfg(feature = "tls")]
-pub(crate) fn tcp_incoming<IO, IE, L>(
+pub(crate) fn tcp_incoming<IO, IE>(
     incoming: impl Stream<Item = Result<IO, IE>>,
-    server: Server<L>,
+    options: AcceptOptions,
 ) -> impl Stream<Item = Result<ServerIo<IO>, crate::Error>>
 where
     IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
@@ -51,7 +67,7 @@ where
         loop {
             match select(&mut incoming, &mut tasks).await {
                 SelectOutput::Incoming(stream) => {
-                    if let Some(tls) = &server.tls {
+                    if let Some(tls) = &options.tls {
                         let tls = tls.clone();
                         tasks.spawn(async move {
                             let io = tls.accept(stream).await?;
@@ -121,6 +137,84 @@ enum SelectOutput<A> {
     Done,
 }
 
+pub(crate) type ListenerHook = Arc<dyn Fn(&socket2::Socket) -> io::Result<()> + Send + Sync>;
+pub(crate) type AcceptHook = Arc<dyn Fn(&TcpStream) -> io::Result<()> + Send + Sync>;
+
+/// Listen on `addr` with the listener hook of a server, binding it with
+/// `SO_REUSEPORT` when `reuse_port`.
+pub(crate) fn listen(
+    addr: SocketAddr,
+    options: &AcceptOptions,
+    reuse_port: bool,
+) -> Result<std::net::TcpListener, crate::Error> {
+    let socket = socket2::Socket::new(
+        socket2::Domain::for_address(addr),
+        socket2::Type::STREAM,
+        Some(socket2::Protocol::TCP),
+    )?;
+    // As `TcpListener::bind` does.
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    if reuse_port {
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        socket.set_reuse_port(true)?;
+    }
+    if let Some(configure) = &options.configure_listener {
+        configure(&socket)?;
+    }
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    Ok(socket.into())
+}
+
+/// Accept the connections of the nonblocking `listener`, configuring them
+/// with the TCP options and the connection hook of a server.
+pub(crate) fn accept(
+    listener: std::net::TcpListener,
+    options: &AcceptOptions,
+) -> Result<impl Stream<Item = io::Result<TcpStream>>, crate::Error> {
+    let listener = TcpListener::from_std(listener)?;
+
+    let nodelay = options.tcp_nodelay;
+    let keepalive = options.tcp_keepalive;
+    let configure = options.configure_connection.clone();
+    let configured = move |stream: &TcpStream| -> io::Result<()> {
+        stream.set_nodelay(nodelay)?;
+        if let Some(time) = keepalive {
+            let keepalive = socket2::TcpKeepalive::new().with_time(time);
+            socket2::SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+        }
+        match &configure {
+            Some(configure) => configure(stream),
+            None => Ok(()),
+        }
+    };
+
+    Ok(async_stream::stream! {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => match configured(&stream) {
+                    Ok(()) => yield Ok::<_, io::Error>(stream),
+                    Err(e) => tracing::debug!("failed to configure a connection: {}", e),
+                },
+                // Errors of the connection rather than of the listener.
+                Err(e) if matches!(
+                    e.kind(),
+                    io::ErrorKind::ConnectionRefused
+                        | io::ErrorKind::ConnectionAborted
+                        | io::ErrorKind::ConnectionReset
+                ) => {}
+                // Such as running out of file descriptors, which may last.
+                Err(e) => {
+                    tracing::error!("accept error: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    })
+}
+
 /// Binds a socket address for a [Router](super::Router)
 ///
 /// An incoming stream, usable with [Router::serve_with_incoming](super::Router::serve_with_incoming),
@@ -196,7 +290,55 @@ impl Stream for TcpIncoming {
 
 #[cfg(test)]
 mod tests {
-    use crate::transport::server::TcpIncoming;
+    use crate::body::BoxBody;
+    use crate::transport::server::{Routes, TcpIncoming};
+    use crate::transport::{Channel, Server};
+    use std::sync::{Arc, Mutex};
+    use tower::{service_fn, Service, ServiceExt};
+
+    #[tokio::test]
+    async fn configures_sockets() {
+        let svc = service_fn(|_: http::Request<hyper::Body>| async {
+            Ok::<_, std::convert::Infallible>(crate::Status::new(crate::Code::Ok, "").to_http())
+        });
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let addr = "127.0.0.1:1323".parse().unwrap();
+        let server = Server::builder()
+            .configure_listener({
+                let events = events.clone();
+                move |_: &socket2::Socket| {
+                    events.lock().unwrap().push("listener");
+                    Ok(())
+                }
+            })
+            .configure_connection({
+                let events = events.clone();
+                move |stream| {
+                    assert!(stream.nodelay()?);
+                    events.lock().unwrap().push("connection");
+                    Ok(())
+                }
+            })
+            .add_routes(Routes::default().fallback_service(svc))
+            .serve(addr);
+        tokio::spawn(server);
+
+        let mut channel = loop {
+            match Channel::from_static("http://127.0.0.1:1323")
+                .connect()
+                .await
+            {
+                Ok(channel) => break channel,
+                Err(_) => tokio::task::yield_now().await,
+            }
+        };
+        let request = http::Request::post("http://localhost/test.Service/Call")
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        channel.ready().await.unwrap().call(request).await.unwrap();
+        assert_eq!(*events.lock().unwrap(), ["listener", "connection"]);
+    }
     #[tokio::test]
     async fn one_tcpincoming_at_a_time() {
         let addr = "127.0.0.1:1322".parse().unwrap();
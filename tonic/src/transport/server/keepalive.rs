@@ -0,0 +1,424 @@
+use std::{
+    collections::HashSet,
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::Instant,
+};
+
+/// The policy of the pings a [`Server`](super::Server) lets its clients
+/// send, the equivalent of the `keepalive.EnforcementPolicy` of grpc-go.
+///
+/// Pings sent sooner than [`min_time`](Self::min_time) after the previous
+/// one, or sent while the connection has no call in flight unless
+/// [`permit_without_stream`](Self::permit_without_stream), are strikes. The
+/// server closes the connections of clients getting more than two strikes
+/// with a `GOAWAY` frame of `ENHANCE_YOUR_CALM` and `too_many_pings`,
+/// telling gRPC clients to ping less often. Sending headers or messages
+/// clears the strikes of a connection.
+#[derive(Debug, Clone, Copy)]
+pub struct KeepaliveEnforcementPolicy {
+    min_time: Duration,
+    permit_without_stream: bool,
+}
+
+impl Default for KeepaliveEnforcementPolicy {
+    fn default() -> Self {
+        KeepaliveEnforcementPolicy {
+            min_time: Duration::from_secs(5 * 60),
+            permit_without_stream: false,
+        }
+    }
+}
+
+impl KeepaliveEnforcementPolicy {
+    /// Create the default policy, of a minimum time of 5 minutes between
+    /// pings, which aren't permitted without calls in flight.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the minimum time clients wait between two pings.
+    #[must_use]
+    pub fn min_time(self, min_time: Duration) -> Self {
+        KeepaliveEnforcementPolicy { min_time, ..self }
+    }
+
+    /// Set whether clients may ping while they have no call in flight.
+    ///
+    /// Pings without calls are strikes if they are sooner than two hours
+    /// after the previous one otherwise.
+    #[must_use]
+    pub fn permit_without_stream(self, permit: bool) -> Self {
+        KeepaliveEnforcementPolicy {
+            permit_without_stream: permit,
+            ..self
+        }
+    }
+}
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const PING: u8 = 0x6;
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+
+const ENHANCE_YOUR_CALM: u32 = 0xb;
+const MAX_PING_STRIKES: u8 = 2;
+/// The minimum time between the pings of connections without calls, when
+/// they aren't permitted.
+const IDLE_MIN_TIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// The header of an HTTP/2 frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameHeader {
+    length: usize,
+    kind: u8,
+    flags: u8,
+    stream: u32,
+}
+
+/// Finds the headers of the frames of one direction of a connection.
+#[derive(Debug, Default)]
+struct Frames {
+    header: [u8; 9],
+    filled: usize,
+    /// The bytes left of the payload of the current frame.
+    remaining: usize,
+}
+
+impl Frames {
+    /// Parse `bytes`, the next bytes of the connection, calling `on_header`
+    /// with the header of each frame they start.
+    fn parse(&mut self, mut bytes: &[u8], mut on_header: impl FnMut(FrameHeader)) {
+        while !bytes.is_empty() {
+            if self.remaining > 0 {
+                let n = self.remaining.min(bytes.len());
+                self.remaining -= n;
+                bytes = &bytes[n..];
+                continue;
+            }
+
+            let n = (self.header.len() - self.filled).min(bytes.len());
+            self.header[self.filled..self.filled + n].copy_from_slice(&bytes[..n]);
+            self.filled += n;
+            bytes = &bytes[n..];
+            if self.filled == self.header.len() {
+                self.filled = 0;
+                let h = &self.header;
+                let header = FrameHeader {
+                    length: usize::from(h[0]) << 16 | usize::from(h[1]) << 8 | usize::from(h[2]),
+                    kind: h[3],
+                    flags: h[4],
+                    stream: u32::from_be_bytes([h[5], h[6], h[7], h[8]]) & 0x7fff_ffff,
+                };
+                self.remaining = header.length;
+                on_header(header);
+            }
+        }
+    }
+
+    /// Whether the next byte starts a frame.
+    fn at_frame_start(&self) -> bool {
+        self.filled == 0 && self.remaining == 0
+    }
+}
+
+/// The pings and calls of a connection.
+#[derive(Debug)]
+struct Enforcement {
+    policy: KeepaliveEnforcementPolicy,
+    streams: HashSet<u32>,
+    last_stream: u32,
+    last_ping: Option<Instant>,
+    strikes: u8,
+}
+
+impl Enforcement {
+    fn read(&mut self, header: FrameHeader) {
+        match header.kind {
+            HEADERS if header.stream > self.last_stream => {
+                self.last_stream = header.stream;
+                self.streams.insert(header.stream);
+            }
+            RST_STREAM => {
+                self.streams.remove(&header.stream);
+            }
+            PING if header.flags & ACK == 0 => {
+                let now = Instant::now();
+                let min_time = if self.streams.is_empty() && !self.policy.permit_without_stream {
+                    IDLE_MIN_TIME
+                } else {
+                    self.policy.min_time
+                };
+                if self.last_ping.is_some_and(|last| now < last + min_time) {
+                    self.strikes += 1;
+                }
+                self.last_ping = Some(now);
+            }
+            _ => {}
+        }
+    }
+
+    fn written(&mut self, header: FrameHeader) {
+        match header.kind {
+            DATA | HEADERS => {
+                self.strikes = 0;
+                if header.flags & END_STREAM != 0 {
+                    self.streams.remove(&header.stream);
+                }
+            }
+            RST_STREAM => {
+                self.streams.remove(&header.stream);
+            }
+            _ => {}
+        }
+    }
+
+    fn abusive(&self) -> bool {
+        self.strikes > MAX_PING_STRIKES
+    }
+
+    /// The `GOAWAY` frame closing the connection of an abusive client.
+    fn goaway(&self) -> Vec<u8> {
+        let debug = b"too_many_pings";
+        let length = 8 + debug.len();
+        let mut frame = vec![0, 0, length as u8, 0x7, 0, 0, 0, 0, 0];
+        frame.extend_from_slice(&self.last_stream.to_be_bytes());
+        frame.extend_from_slice(&ENHANCE_YOUR_CALM.to_be_bytes());
+        frame.extend_from_slice(debug);
+        frame
+    }
+}
+
+/// The IO of a server connection, closing it with a `GOAWAY` frame once
+/// its client breaks the [`KeepaliveEnforcementPolicy`] of the server.
+///
+/// The IO follows the frames the connection reads and writes, so that it
+/// only writes the `GOAWAY` between two frames. Connections that don't
+/// start with the HTTP/2 preface, such as HTTP/1 ones, aren't enforced.
+#[derive(Debug)]
+pub(crate) struct EnforcedIo<IO> {
+    inner: IO,
+    enforcement: Option<Enforcement>,
+    preface: usize,
+    read: Frames,
+    written: Frames,
+    goaway: Option<(Vec<u8>, usize)>,
+}
+
+impl<IO> EnforcedIo<IO> {
+    pub(crate) fn new(inner: IO, policy: Option<KeepaliveEnforcementPolicy>) -> Self {
+        EnforcedIo {
+            inner,
+            enforcement: policy.map(|policy| Enforcement {
+                policy,
+                streams: HashSet::new(),
+                last_stream: 0,
+                last_ping: None,
+                strikes: 0,
+            }),
+            preface: 0,
+            read: Frames::default(),
+            written: Frames::default(),
+            goaway: None,
+        }
+    }
+
+    fn on_read(&mut self, mut bytes: &[u8]) {
+        let Some(enforcement) = &mut self.enforcement else {
+            return;
+        };
+
+        if self.preface < PREFACE.len() {
+            let n = (PREFACE.len() - self.preface).min(bytes.len());
+            if bytes[..n] != PREFACE[self.preface..self.preface + n] {
+                self.enforcement = None;
+                return;
+            }
+            self.preface += n;
+            bytes = &bytes[n..];
+        }
+
+        self.read.parse(bytes, |header| enforcement.read(header));
+        if enforcement.abusive() && self.goaway.is_none() {
+            tracing::debug!("closing the connection of a client sending too many pings");
+            self.goaway = Some((enforcement.goaway(), 0));
+        }
+    }
+
+    fn on_written<'a>(&mut self, bufs: impl IntoIterator<Item = &'a [u8]>, mut n: usize) {
+        let Some(enforcement) = &mut self.enforcement else {
+            return;
+        };
+        for buf in bufs {
+            if n == 0 {
+                break;
+            }
+            let len = buf.len().min(n);
+            self.written
+                .parse(&buf[..len], |header| enforcement.written(header));
+            n -= len;
+        }
+    }
+}
+
+impl<IO> EnforcedIo<IO>
+where
+    IO: AsyncWrite + Unpin,
+{
+    /// Write the pending `GOAWAY`, if any and between two frames, then fail.
+    fn poll_goaway(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let Some((frame, written)) = &mut self.goaway else {
+            return Poll::Ready(Ok(()));
+        };
+        if !self.written.at_frame_start() {
+            return Poll::Ready(Ok(()));
+        }
+
+        while *written < frame.len() {
+            *written += ready!(Pin::new(&mut self.inner).poll_write(cx, &frame[*written..]))?;
+        }
+        ready!(Pin::new(&mut self.inner).poll_flush(cx))?;
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "the client sent too many pings",
+        )))
+    }
+}
+
+impl<IO> AsyncRead for EnforcedIo<IO>
+where
+    IO: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.on_read(&buf.filled()[before..]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO> AsyncWrite for EnforcedIo<IO>
+where
+    IO: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        ready!(this.poll_goaway(cx))?;
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.on_written([buf], n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        ready!(this.poll_goaway(cx))?;
+        let n = ready!(Pin::new(&mut this.inner).poll_write_vectored(cx, bufs))?;
+        this.on_written(bufs.iter().map(|buf| &**buf), n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        ready!(this.poll_goaway(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{server::Routes, server::TcpIncoming, Server};
+    use tower::service_fn;
+
+    #[test]
+    fn parses_split_frame_headers() {
+        let mut frames = Frames::default();
+        let mut headers = Vec::new();
+        let bytes = [
+            0, 0, 2, PING, ACK, 0, 0, 0, 3, 9, 9, 0, 0, 0, DATA, 0, 0, 0, 0, 5,
+        ];
+        frames.parse(&bytes[..4], |h| headers.push(h));
+        frames.parse(&bytes[4..12], |h| headers.push(h));
+        assert!(!frames.at_frame_start());
+        frames.parse(&bytes[12..], |h| headers.push(h));
+        assert!(frames.at_frame_start());
+        assert_eq!(
+            headers,
+            [
+                FrameHeader {
+                    length: 2,
+                    kind: PING,
+                    flags: ACK,
+                    stream: 3,
+                },
+                FrameHeader {
+                    length: 0,
+                    kind: DATA,
+                    flags: 0,
+                    stream: 5,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn closes_connections_of_clients_pinging_too_often() {
+        let svc = service_fn(|_: http::Request<hyper::Body>| async {
+            Ok::<_, std::convert::Infallible>(crate::Status::new(crate::Code::Ok, "").to_http())
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        let policy = KeepaliveEnforcementPolicy::new().permit_without_stream(true);
+        tokio::spawn(
+            Server::builder()
+                .keepalive_enforcement_policy(policy)
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming(incoming),
+        );
+
+        let io = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (_client, mut connection) = h2::client::handshake(io).await.unwrap();
+        let mut ping_pong = connection.ping_pong().unwrap();
+        let connection = tokio::spawn(connection);
+
+        // The first ping isn't a strike, and the connection closes at the
+        // third strike.
+        for _ in 0..3 {
+            ping_pong.ping(h2::Ping::opaque()).await.unwrap();
+        }
+        assert!(ping_pong.ping(h2::Ping::opaque()).await.is_err());
+        let error = connection.await.unwrap().unwrap_err();
+        assert_eq!(error.reason(), Some(h2::Reason::ENHANCE_YOUR_CALM));
+    }
+}
@@ -0,0 +1,280 @@
+use super::incoming::{self, AcceptOptions};
+#[cfg(unix)]
+use super::systemd::{self, Activated};
+#[cfg(unix)]
+use super::UnixIncoming;
+use super::{accept, Accepted, Connected, MakeSvc, SharedService};
+#[cfg(feature = "http3")]
+use crate::transport::Identity;
+use std::{fmt, net::SocketAddr, pin::Pin};
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio_stream::Stream;
+
+pub(super) type Connections = Pin<Box<dyn Stream<Item = Result<Accepted, crate::Error>> + Send>>;
+type Listener = Box<
+    dyn FnOnce(&AcceptOptions, MakeSvc<SharedService>) -> Result<Vec<Connections>, crate::Error>
+        + Send,
+>;
+
+/// The listeners a [Router](super::Router) serves together, see
+/// [`Router::serve_listeners`](super::Router::serve_listeners).
+///
+/// All the listeners serve the services, layers and TLS of the same
+/// [`Server`](super::Server), and shut down together.
+///
+/// ```no_run
+/// # use tonic::transport::server::Listeners;
+/// # fn main() -> Result<(), tonic::transport::Error> {
+/// let listeners = Listeners::new().tcp("[::1]:50051".parse().unwrap());
+/// # #[cfg(unix)]
+/// let listeners = listeners.incoming(
+///     tonic::transport::server::UnixIncoming::bind("/var/run/app.sock").unwrap(),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Listeners {
+    listeners: Vec<Listener>,
+    #[cfg(feature = "http3")]
+    http3: Vec<(SocketAddr, Identity)>,
+}
+
+impl Listeners {
+    /// Create an empty set of listeners.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Listen on the TCP address `addr`, with the TCP options and socket hooks
+    /// of the server.
+    ///
+    /// The address is bound once the router is served, as many times as the
+    /// server has [acceptors](super::Server::reuse_port_acceptors).
+    pub fn tcp(mut self, addr: SocketAddr) -> Self {
+        self.listeners.push(Box::new(move |options, svc| {
+            let Some(acceptors) = options.reuse_port_acceptors else {
+                let listener = incoming::listen(addr, options, false)?;
+                return Ok(vec![tcp_connections(listener, options, svc)?]);
+            };
+            let listener = incoming::listen(addr, options, true)?;
+            // The other listeners bind the port of the first, when it's chosen
+            // by the system.
+            let addr = listener.local_addr()?;
+            let mut connections = vec![tcp_connections(listener, options, svc.clone())?];
+            for _ in 1..acceptors {
+                let listener = incoming::listen(addr, options, true)?;
+                connections.push(tcp_connections(listener, options, svc.clone())?);
+            }
+            Ok(connections)
+        }));
+        self
+    }
+
+    /// Listen on the sockets systemd passed to the process with socket
+    /// activation, TCP or unix ones.
+    ///
+    /// The sockets are found with the `LISTEN_PID` and `LISTEN_FDS`
+    /// environment variables as with `sd_listen_fds`, which are unset so that
+    /// they're taken once. There are none unless systemd started the process
+    /// for the sockets of its socket unit, and those that aren't listening
+    /// TCP or unix stream sockets are an error.
+    ///
+    /// Connections of the TCP sockets are configured with the TCP options of
+    /// the server, but not the listeners, which systemd configures.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    pub fn systemd(mut self) -> Result<Self, crate::Error> {
+        for socket in systemd::listen_fds()? {
+            let listener: Listener = match socket {
+                Activated::Tcp(listener) => {
+                    Box::new(move |options, svc| Ok(vec![tcp_connections(listener, options, svc)?]))
+                }
+                Activated::Unix(listener) => Box::new(move |options, svc| {
+                    let incoming = UnixIncoming::from_listener(UnixListener::from_std(listener)?);
+                    Ok(vec![
+                        Box::pin(accept(incoming, options.clone(), svc)) as Connections
+                    ])
+                }),
+            };
+            self.listeners.push(listener);
+        }
+        Ok(self)
+    }
+
+    /// Serve the connections of `incoming`, such as a
+    /// [`UnixIncoming`](super::UnixIncoming).
+    ///
+    /// As with [`Router::serve_with_incoming`](super::Router::serve_with_incoming),
+    /// the TCP options of the server don't apply to them.
+    pub fn incoming<I, IO, IE>(mut self, incoming: I) -> Self
+    where
+        I: Stream<Item = Result<IO, IE>> + Send + 'static,
+        IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+        IO::ConnectInfo: Clone + Send + Sync + 'static,
+        IE: Into<crate::Error> + Send + 'static,
+    {
+        self.listeners.push(Box::new(move |options, svc| {
+            Ok(vec![
+                Box::pin(accept(incoming, options.clone(), svc)) as Connections
+            ])
+        }));
+        self
+    }
+
+    /// Listen for HTTP/3 connections on the UDP address `addr`, secured with
+    /// `identity`.
+    ///
+    /// The other listeners advertise HTTP/3 to their clients with the
+    /// `Alt-Svc` header of their responses, for the ports of the HTTP/3
+    /// listeners. HTTP/3 connections aren't configured with the TCP, HTTP/2
+    /// or connection options of the server, only with its services and
+    /// layers, and their requests have the [`Http3ConnectInfo`] of their
+    /// connection.
+    ///
+    /// [`Http3ConnectInfo`]: super::Http3ConnectInfo
+    #[cfg(feature = "http3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+    pub fn http3(mut self, addr: SocketAddr, identity: Identity) -> Self {
+        self.http3.push((addr, identity));
+        self
+    }
+
+    pub(super) fn into_listeners(self) -> impl Iterator<Item = Listener> {
+        self.listeners.into_iter()
+    }
+
+    /// Bind the QUIC endpoints of the HTTP/3 listeners.
+    #[cfg(feature = "http3")]
+    pub(super) fn bind_http3(&mut self) -> Result<Vec<quinn::Endpoint>, crate::Error> {
+        let mut endpoints = Vec::new();
+        for (addr, identity) in self.http3.drain(..) {
+            let config = crate::transport::service::http3_server_config(identity)?;
+            endpoints.push(quinn::Endpoint::server(config, addr)?);
+        }
+        Ok(endpoints)
+    }
+}
+
+fn tcp_connections(
+    listener: std::net::TcpListener,
+    options: &AcceptOptions,
+    svc: MakeSvc<SharedService>,
+) -> Result<Connections, crate::Error> {
+    let incoming = incoming::accept(listener, options)?;
+    Ok(Box::pin(accept(incoming, options.clone(), svc)))
+}
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Listeners")
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::body::BoxBody;
+    use crate::transport::server::{Routes, UdsConnectInfo, UnixIncoming};
+    use crate::transport::{local, Channel, Endpoint, Server};
+    use tokio::sync::oneshot;
+    use tower::{service_fn, Service, ServiceExt};
+
+    async fn call(channel: &mut Channel) -> http::Response<BoxBody> {
+        let request = http::Request::post("http://localhost/test.Service/Call")
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        channel.ready().await.unwrap().call(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_listeners_together() {
+        let svc = service_fn(|request: http::Request<hyper::Body>| async move {
+            let mut response = crate::Status::new(crate::Code::Ok, "").to_http();
+            let unix = request.extensions().get::<UdsConnectInfo>().is_some();
+            response
+                .headers_mut()
+                .insert("unix", unix.to_string().parse().unwrap());
+            Ok::<_, std::convert::Infallible>(response)
+        });
+
+        let path =
+            std::env::temp_dir().join(format!("tonic-listeners-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (mut local, incoming) = local::pair();
+        let listeners = Listeners::new()
+            .incoming(incoming)
+            .incoming(UnixIncoming::bind(&path).unwrap());
+
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let server = tokio::spawn(
+            Server::builder()
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_listeners_with_shutdown(listeners, async {
+                    let _ = signal.await;
+                }),
+        );
+
+        let mut unix = Endpoint::from_shared(format!("unix:{}", path.display()))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(call(&mut local).await.headers()["unix"], "false");
+        assert_eq!(call(&mut unix).await.headers()["unix"], "true");
+        drop((local, unix));
+
+        shutdown.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+    #[tokio::test]
+    async fn serves_connections_on_acceptor_runtimes() {
+        let svc = service_fn(|_: http::Request<hyper::Body>| async {
+            let mut response = crate::Status::new(crate::Code::Ok, "").to_http();
+            let thread = std::thread::current().name().unwrap_or_default().to_owned();
+            response
+                .headers_mut()
+                .insert("thread", thread.parse().unwrap());
+            Ok::<_, std::convert::Infallible>(response)
+        });
+
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let server = tokio::spawn(
+            Server::builder()
+                .reuse_port_acceptors(2)
+                .acceptor_runtimes(true)
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_shutdown("127.0.0.1:1324".parse().unwrap(), async {
+                    let _ = signal.await;
+                }),
+        );
+
+        let mut channels = Vec::new();
+        while channels.len() < 4 {
+            match Channel::from_static("http://127.0.0.1:1324")
+                .connect()
+                .await
+            {
+                Ok(channel) => channels.push(channel),
+                Err(_) => tokio::task::yield_now().await,
+            }
+        }
+        for channel in &mut channels {
+            let response = call(channel).await;
+            let thread = response.headers()["thread"].to_str().unwrap();
+            assert!(thread.starts_with("tonic-acceptor-"), "{}", thread);
+        }
+        drop(channels);
+
+        shutdown.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,238 @@
+use super::Connected;
+use crate::{
+    body::BoxBody,
+    server::NamedService,
+    transport::{Body, Error},
+    Status,
+};
+use http::{Request, Response};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    future::{self, Future},
+    net::SocketAddr,
+    pin::{pin, Pin},
+    rc::Rc,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_stream::{Stream, StreamExt};
+use tower::ServiceExt;
+use tower_service::Service;
+
+type LocalFuture = Pin<Box<dyn Future<Output = Response<BoxBody>>>>;
+
+type Handler = Rc<dyn Fn(Request<Body>) -> LocalFuture>;
+
+/// Routes of services whose futures aren't `Send`, such as those generated
+/// with `local_server`, which may hold `Rc` or `RefCell` state.
+///
+/// The routes serve their connections on the current
+/// [`LocalSet`](tokio::task::LocalSet):
+///
+/// ```no_run
+/// # use tonic::transport::server::LocalRoutes;
+/// # async fn run<S>(svc: S) -> Result<(), Box<dyn std::error::Error>>
+/// # where
+/// #     S: tower_service::Service<
+/// #             http::Request<tonic::transport::Body>,
+/// #             Response = http::Response<tonic::body::BoxBody>,
+/// #             Error = std::convert::Infallible,
+/// #         > + tonic::server::NamedService + Clone + 'static,
+/// # {
+/// let local = tokio::task::LocalSet::new();
+/// local
+///     .run_until(LocalRoutes::new(svc).serve("[::1]:50051".parse()?))
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The options of [`Server`](super::Server) don't apply to these routes.
+#[derive(Clone, Default)]
+pub struct LocalRoutes {
+    handlers: HashMap<&'static str, Handler>,
+}
+
+impl LocalRoutes {
+    /// Create routes with the service `svc`.
+    pub fn new<S>(svc: S) -> Self
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>> + NamedService + Clone + 'static,
+        S::Error: Into<crate::Error>,
+    {
+        LocalRoutes::default().add_service(svc)
+    }
+
+    /// Add the service `svc` to the routes.
+    pub fn add_service<S>(mut self, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>> + NamedService + Clone + 'static,
+        S::Error: Into<crate::Error>,
+    {
+        let handler: Handler = Rc::new(move |req| {
+            let svc = svc.clone();
+            Box::pin(async move {
+                match svc.oneshot(req).await {
+                    Ok(res) => res,
+                    Err(e) => Status::from_error(e.into()).to_http(),
+                }
+            })
+        });
+        self.handlers.insert(S::NAME, handler);
+        self
+    }
+
+    /// Serve the routes on `addr` until the current task is dropped.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), Error> {
+        let incoming = super::TcpIncoming::new(addr, false, None).map_err(Error::from_source)?;
+        self.serve_with_incoming(incoming).await
+    }
+
+    /// Serve the routes on the connections of `incoming`, until it ends.
+    pub async fn serve_with_incoming<I, IO, IE>(self, incoming: I) -> Result<(), Error>
+    where
+        I: Stream<Item = Result<IO, IE>>,
+        IO: AsyncRead + AsyncWrite + Connected + Unpin + 'static,
+        IO::ConnectInfo: Clone + Send + Sync + 'static,
+        IE: Into<crate::Error>,
+    {
+        let mut http = hyper::server::conn::Http::new().with_executor(LocalExec);
+        http.http2_only(true);
+
+        let mut incoming = pin!(incoming);
+        while let Some(io) = incoming.next().await {
+            let io = io.map_err(|e| Error::from_source(e.into()))?;
+            let svc = LocalSvc {
+                routes: self.clone(),
+                connect_info: io.connect_info(),
+            };
+            let connection = http.serve_connection(io, svc);
+            tokio::task::spawn_local(async move {
+                if let Err(e) = connection.await {
+                    tracing::debug!("failed serving connection: {:#}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn call(&self, req: Request<Body>) -> LocalFuture {
+        let name = req.uri().path().split('/').nth(1).unwrap_or_default();
+        match self.handlers.get(name) {
+            Some(handler) => handler(req),
+            None => Box::pin(future::ready(Status::unimplemented("").to_http())),
+        }
+    }
+}
+
+impl fmt::Debug for LocalRoutes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalRoutes")
+            .field("services", &self.handlers.keys())
+            .finish()
+    }
+}
+
+/// The service of a connection, passing its info to the calls.
+#[derive(Clone)]
+struct LocalSvc<C> {
+    routes: LocalRoutes,
+    connect_info: C,
+}
+
+impl<C: Clone + Send + Sync + 'static> Service<Request<Body>> for LocalSvc<C> {
+    type Response = Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        req.extensions_mut().insert(self.connect_info.clone());
+        let res = self.routes.call(req);
+        Box::pin(async move { Ok(res.await) })
+    }
+}
+
+/// Spawns the streams of connections on the current `LocalSet`.
+#[derive(Clone, Copy)]
+struct LocalExec;
+
+impl<F> hyper::rt::Executor<F> for LocalExec
+where
+    F: Future + 'static,
+{
+    fn execute(&self, fut: F) {
+        tokio::task::spawn_local(fut);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Endpoint;
+    use std::cell::Cell;
+    use tokio::net::TcpListener;
+
+    /// Counts its calls with state that isn't `Send`.
+    #[derive(Clone, Default)]
+    struct Counter(Rc<Cell<u32>>);
+
+    impl Service<Request<Body>> for Counter {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Request<Body>) -> Self::Future {
+            let count = self.0.clone();
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                count.set(count.get() + 1);
+                Ok(Status::ok("").to_http())
+            })
+        }
+    }
+
+    impl NamedService for Counter {
+        const NAME: &'static str = "test.Counter";
+    }
+
+    #[tokio::test]
+    async fn serves_local_services() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let counter = Counter::default();
+        let routes = LocalRoutes::new(counter.clone());
+
+        let local = tokio::task::LocalSet::new();
+        let incoming =
+            crate::transport::server::TcpIncoming::from_listener(listener, true, None).unwrap();
+        local.spawn_local(routes.serve_with_incoming(incoming));
+        local
+            .run_until(async move {
+                let channel = Endpoint::from_shared(format!("http://{}", addr))
+                    .unwrap()
+                    .connect()
+                    .await
+                    .unwrap();
+
+                for (path, status) in [("/test.Counter/Count", "0"), ("/test.Other/Count", "12")] {
+                    let mut req = Request::new(crate::body::empty_body());
+                    *req.uri_mut() = path.parse().unwrap();
+                    let res = channel.clone().oneshot(req).await.unwrap();
+                    assert_eq!(res.headers()["grpc-status"], status);
+                }
+            })
+            .await;
+
+        assert_eq!(counter.0.get(), 1);
+    }
+}
@@ -1,18 +1,42 @@
 //! Server implementation and builder.
 
+mod activity;
+mod cancellation;
 mod conn;
+mod dynamic_routes;
+mod h2c;
+#[cfg(feature = "http3")]
+mod http3;
 mod incoming;
+mod keepalive;
+mod listeners;
+mod local_routes;
+#[cfg(windows)]
+mod named_pipe;
 mod recover_error;
+mod serve;
+#[cfg(unix)]
+mod systemd;
 #[cfg(feature = "tls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 mod tls;
 #[cfg(unix)]
 mod unix;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod vsock;
 
 pub use super::service::Routes;
 pub use super::service::RoutesBuilder;
 
+pub use activity::Activity;
+pub use cancellation::Cancellation;
 pub use conn::{Connected, TcpConnectInfo};
+pub use dynamic_routes::DynamicRoutes;
+#[cfg(feature = "http3")]
+pub use http3::Http3ConnectInfo;
+pub use keepalive::KeepaliveEnforcementPolicy;
+pub use listeners::Listeners;
+pub use local_routes::LocalRoutes;
 #[cfg(feature = "tls")]
 pub use tls::ServerTlsConfig;
 
@@ -22,8 +46,15 @@ pub use conn::TlsConnectInfo;
 #[cfg(feature = "tls")]
 use super::service::TlsAcceptor;
 
+#[cfg(windows)]
+pub use named_pipe::NamedPipeIncoming;
 #[cfg(unix)]
-pub use unix::UdsConnectInfo;
+pub use unix::{UdsConnectInfo, UnixIncoming};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use super::service::VsockStream;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use vsock::{VsockConnectInfo, VsockIncoming};
 
 pub use incoming::TcpIncoming;
 
@@ -33,39 +64,58 @@ pub(crate) use tokio_rustls::server::TlsStream;
 #[cfg(feature = "tls")]
 use crate::transport::Error;
 
+use self::activity::{ActivityGuard, ActivityTracker};
+use self::cancellation::{
+    shutting_down, CancelOnDrop, CancellableBody, GracePeriod, RunToCompletion,
+};
+use self::h2c::H2cUpgrade;
+use self::incoming::{AcceptHook, AcceptOptions, ListenerHook};
+use self::keepalive::EnforcedIo;
+use self::listeners::Connections;
 use self::recover_error::RecoverError;
-use super::service::{GrpcTimeout, ServerIo};
+use self::serve::{unless_signalled, ConnectionAge, ConnectionLimit, LimitHook};
+use super::service::{BoxedIo, GrpcTimeout, ServerIo, ServerStats, SharedTimer};
+use super::{BoxFuture, Timer};
 use crate::body::BoxBody;
 use crate::server::NamedService;
+use crate::stats::StatsHandler;
 use bytes::Bytes;
 use http::{Request, Response};
 use http_body::Body as _;
-use hyper::{server::accept, Body};
+use hyper::Body;
 use pin_project::pin_project;
 use std::{
+    any::Any,
     convert::Infallible,
     fmt,
-    future::{self, Future},
-    marker::PhantomData,
+    future::{self, poll_fn, Future},
     net::SocketAddr,
-    pin::Pin,
+    pin::{pin, Pin},
     sync::Arc,
-    task::{ready, Context, Poll},
+    task::{Context, Poll},
     time::Duration,
 };
-use tokio::io::{AsyncRead, AsyncWrite};
-use tokio_stream::Stream;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{mpsc, oneshot, watch},
+};
+use tokio_stream::{Stream, StreamExt, StreamMap};
 use tower::{
     layer::util::{Identity, Stack},
     layer::Layer,
     limit::concurrency::ConcurrencyLimitLayer,
-    util::Either,
-    Service, ServiceBuilder,
+    util::{BoxCloneService, Either},
+    Service, ServiceBuilder, ServiceExt,
 };
 
 type BoxHttpBody = http_body::combinators::UnsyncBoxBody<Bytes, crate::Error>;
 type BoxService = tower::util::BoxService<Request<Body>, Response<BoxHttpBody>, crate::Error>;
+/// The service of the listeners of a server, whichever its layers.
+type SharedService = BoxCloneService<Request<Body>, Response<BoxHttpBody>, crate::Error>;
+/// An accepted connection along with its service.
+type Accepted = (BoxedIo, BoxService);
 type TraceInterceptor = Arc<dyn Fn(&http::Request<()>) -> tracing::Span + Send + Sync + 'static>;
+type ShutdownHook = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync + 'static>;
 
 const DEFAULT_HTTP2_KEEPALIVE_TIMEOUT_SECS: u64 = 20;
 
@@ -82,6 +132,7 @@ pub struct Server<L = Identity> {
     trace_interceptor: Option<TraceInterceptor>,
     concurrency_limit: Option<usize>,
     timeout: Option<Duration>,
+    timer: SharedTimer,
     #[cfg(feature = "tls")]
     tls: Option<TlsAcceptor>,
     init_stream_window_size: Option<u32>,
@@ -89,12 +140,26 @@ pub struct Server<L = Identity> {
     max_concurrent_streams: Option<u32>,
     tcp_keepalive: Option<Duration>,
     tcp_nodelay: bool,
+    configure_listener: Option<ListenerHook>,
+    configure_connection: Option<AcceptHook>,
     http2_keepalive_interval: Option<Duration>,
     http2_keepalive_timeout: Option<Duration>,
+    keepalive_enforcement: Option<KeepaliveEnforcementPolicy>,
     http2_adaptive_window: Option<bool>,
     http2_max_pending_accept_reset_streams: Option<usize>,
     max_frame_size: Option<u32>,
     accept_http1: bool,
+    stats_handler: Option<Arc<dyn StatsHandler>>,
+    shutdown_hooks: Vec<ShutdownHook>,
+    run_to_completion: Vec<String>,
+    grace_period: Option<Duration>,
+    max_connection_age: Option<Duration>,
+    max_connection_age_grace: Option<Duration>,
+    max_connections: Option<usize>,
+    on_max_connections: Option<LimitHook>,
+    reuse_port_acceptors: Option<usize>,
+    acceptor_runtimes: bool,
+    activity: Option<ActivityTracker>,
     service_builder: ServiceBuilder<L>,
 }
 
@@ -104,6 +169,7 @@ impl Default for Server<Identity> {
             trace_interceptor: None,
             concurrency_limit: None,
             timeout: None,
+            timer: SharedTimer::tokio(),
             #[cfg(feature = "tls")]
             tls: None,
             init_stream_window_size: None,
@@ -111,12 +177,26 @@ impl Default for Server<Identity> {
             max_concurrent_streams: None,
             tcp_keepalive: None,
             tcp_nodelay: false,
+            configure_listener: None,
+            configure_connection: None,
             http2_keepalive_interval: None,
             http2_keepalive_timeout: None,
+            keepalive_enforcement: None,
             http2_adaptive_window: None,
             http2_max_pending_accept_reset_streams: None,
             max_frame_size: None,
             accept_http1: false,
+            stats_handler: None,
+            shutdown_hooks: Vec::new(),
+            run_to_completion: Vec::new(),
+            grace_period: None,
+            max_connection_age: None,
+            max_connection_age_grace: None,
+            max_connections: None,
+            on_max_connections: None,
+            reuse_port_acceptors: None,
+            acceptor_runtimes: false,
+            activity: None,
             service_builder: Default::default(),
         }
     }
@@ -192,6 +272,21 @@ impl<L> Server<L> {
         }
     }
 
+    /// Sets the timer of the timeouts of calls, for servers running on
+    /// executors other than `tokio`'s.
+    ///
+    /// Uses `tokio::time` by default.
+    #[must_use]
+    pub fn timer<T>(self, timer: T) -> Self
+    where
+        T: Timer + Send + Sync + 'static,
+    {
+        Server {
+            timer: SharedTimer::new(timer),
+            ..self
+        }
+    }
+
     /// Sets the [`SETTINGS_INITIAL_WINDOW_SIZE`][spec] option for HTTP2
     /// stream-level flow control.
     ///
@@ -263,6 +358,19 @@ impl<L> Server<L> {
         }
     }
 
+    /// Enforce `policy` on the pings of clients, closing the connections of
+    /// clients pinging too often with a `GOAWAY` frame of
+    /// `ENHANCE_YOUR_CALM`, see [`KeepaliveEnforcementPolicy`].
+    ///
+    /// Clients may ping as often as they like by default.
+    #[must_use]
+    pub fn keepalive_enforcement_policy(self, policy: KeepaliveEnforcementPolicy) -> Self {
+        Server {
+            keepalive_enforcement: Some(policy),
+            ..self
+        }
+    }
+
     /// Sets whether to use an adaptive flow control. Defaults to false.
     /// Enabling this will override the limits set in http2_initial_stream_window_size and
     /// http2_initial_connection_window_size.
@@ -312,6 +420,49 @@ impl<L> Server<L> {
         }
     }
 
+    /// Configure the socket of the listener of [`Router::serve`] with
+    /// `configure` before it is bound, such as to set `SO_REUSEPORT`, its
+    /// buffer sizes or TCP fast open.
+    ///
+    /// The socket is a [`socket2::Socket`] of `socket2` 0.5, that the server
+    /// fails to start with the error of `configure`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.configure_listener(|socket| socket.set_recv_buffer_size(1 << 20));
+    /// ```
+    ///
+    /// [`socket2::Socket`]: https://docs.rs/socket2/0.5/socket2/struct.Socket.html
+    #[must_use]
+    pub fn configure_listener<F>(self, configure: F) -> Self
+    where
+        F: Fn(&socket2::Socket) -> std::io::Result<()> + Send + Sync + 'static,
+    {
+        Server {
+            configure_listener: Some(Arc::new(configure)),
+            ..self
+        }
+    }
+
+    /// Configure each connection [`Router::serve`] accepts with `configure`,
+    /// after the `TCP_NODELAY` and keepalive options of the server, such as
+    /// to set their type of service.
+    ///
+    /// Connections are closed when `configure` fails.
+    #[must_use]
+    pub fn configure_connection<F>(self, configure: F) -> Self
+    where
+        F: Fn(&tokio::net::TcpStream) -> std::io::Result<()> + Send + Sync + 'static,
+    {
+        Server {
+            configure_connection: Some(Arc::new(configure)),
+            ..self
+        }
+    }
+
     /// Sets the maximum frame size to use for HTTP2.
     ///
     /// Passing `None` will do nothing.
@@ -332,6 +483,12 @@ impl<L> Server<L> {
     /// not correctly configured to handle grpc-web requests, your server may
     /// return confusing (but correct) protocol errors.
     ///
+    /// The protocol of each connection is detected, so that a single port
+    /// serves both http1 clients and http2 ones with prior knowledge. Http1
+    /// clients may also upgrade their connection to http2 over cleartext
+    /// (`h2c`), as curl does with `--http2`, when their request has no body
+    /// or one small enough to fit a single frame.
+    ///
     /// Default is `false`.
     #[must_use]
     pub fn accept_http1(self, accept_http1: bool) -> Self {
@@ -353,6 +510,203 @@ impl<L> Server<L> {
         }
     }
 
+    /// Set a [`StatsHandler`] told of the connections the server accepts,
+    /// and of the calls it receives over them.
+    ///
+    /// The handler sees the calls before the layers of the server, and the
+    /// messages of the services generated by `tonic-build`.
+    #[must_use]
+    pub fn stats_handler(self, handler: impl StatsHandler) -> Self {
+        Server {
+            stats_handler: Some(Arc::new(handler)),
+            ..self
+        }
+    }
+
+    /// Run `hook` when the shutdown signal of the server resolves, before
+    /// the server stops accepting connections and drains the calls in
+    /// flight.
+    ///
+    /// Hooks run one after the other, in the order they are added, such as
+    /// to report the services of the server as not serving, so that load
+    /// balancers stop sending it calls while it drains.
+    #[must_use]
+    pub fn on_shutdown<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown_hooks
+            .push(Arc::new(move || Box::pin(hook()) as BoxFuture<'static, ()>));
+        self
+    }
+
+    /// Set how long the server waits for the calls in flight once its
+    /// shutdown signal resolves.
+    ///
+    /// The server tells its clients to stop sending calls with a `GOAWAY`
+    /// frame when the signal resolves, and waits for the calls in flight
+    /// before it returns, which may never happen with stuck calls. Once the
+    /// grace period expires, the calls still in flight end with an
+    /// `UNAVAILABLE` status instead: their handler futures are dropped, and
+    /// their response streams end with the status rather than their next
+    /// message. There is no grace period by default.
+    #[must_use]
+    pub fn grace_period(self, period: Duration) -> Self {
+        Server {
+            grace_period: Some(period),
+            ..self
+        }
+    }
+
+    /// Set the maximum age of the connections of the server, after which
+    /// they are shut down gracefully with a `GOAWAY` frame.
+    ///
+    /// The age of each connection is up to 10% shorter or longer than
+    /// `age`, so that connections opened together don't close together.
+    /// Clients connect again on their next call, which spreads long-lived
+    /// clients across servers as they are added behind a load balancer.
+    /// There is no maximum age by default.
+    #[must_use]
+    pub fn max_connection_age(self, age: Duration) -> Self {
+        Server {
+            max_connection_age: Some(age),
+            ..self
+        }
+    }
+
+    /// Set how long the calls in flight of a connection may last once it
+    /// reached its [maximum age](Self::max_connection_age), after which the
+    /// connection is closed.
+    ///
+    /// Calls last as long as they need by default.
+    #[must_use]
+    pub fn max_connection_age_grace(self, grace: Duration) -> Self {
+        Server {
+            max_connection_age_grace: Some(grace),
+            ..self
+        }
+    }
+
+    /// Set the maximum number of connections the server keeps open.
+    ///
+    /// The server stops accepting connections while it has `max` open,
+    /// leaving the new ones waiting in the backlog of its listener until
+    /// others close. It logs a warning, and runs the hook of
+    /// [`on_max_connections`](Self::on_max_connections), each time it
+    /// reaches the limit. There is no limit by default.
+    #[must_use]
+    pub fn max_connections(self, max: usize) -> Self {
+        Server {
+            max_connections: Some(max),
+            ..self
+        }
+    }
+
+    /// Run `hook` each time the server reaches its
+    /// [maximum number of connections](Self::max_connections), such as to
+    /// count it in a metric.
+    #[must_use]
+    pub fn on_max_connections<F>(self, hook: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Server {
+            on_max_connections: Some(Arc::new(hook)),
+            ..self
+        }
+    }
+
+    /// Accept the connections of each TCP address with `acceptors` listeners,
+    /// all bound to the address with `SO_REUSEPORT`.
+    ///
+    /// Each listener accepts its connections in a task of its own rather
+    /// than in the task serving the server, and on Linux the kernel spreads
+    /// the connections of the address across them, so that servers with
+    /// high connection rates aren't bound by a single accept loop. There is
+    /// a single listener per address by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `acceptors` is 0.
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos")))))
+    )]
+    #[must_use]
+    pub fn reuse_port_acceptors(self, acceptors: usize) -> Self {
+        assert!(acceptors > 0, "a server needs an acceptor");
+        Server {
+            reuse_port_acceptors: Some(acceptors),
+            ..self
+        }
+    }
+
+    /// Run each of the [acceptors](Self::reuse_port_acceptors) of the server
+    /// on a single-threaded runtime of its own, on a thread of its own, along
+    /// with the connections it accepts.
+    ///
+    /// The connections are spawned on the runtime serving the server
+    /// otherwise.
+    #[must_use]
+    pub fn acceptor_runtimes(self, enabled: bool) -> Self {
+        Server {
+            acceptor_runtimes: enabled,
+            ..self
+        }
+    }
+
+    /// Let the calls of `name` run to completion when they are cancelled,
+    /// rather than dropping their handler futures.
+    ///
+    /// `name` is either the fully-qualified name of a service, such as
+    /// `package.Service`, for all its methods, or of a single method, such
+    /// as `package.Service/Method`. The futures of these methods are spawned
+    /// in tasks of their own, that keep running after their client
+    /// disconnects or their timeout expires, so that handlers doing several
+    /// writes don't stop between two of them. The [`Cancellation`] of their
+    /// requests still fires, telling the handlers that nobody waits for
+    /// their response anymore.
+    #[must_use]
+    pub fn run_to_completion(mut self, name: impl Into<String>) -> Self {
+        self.run_to_completion.push(name.into());
+        self
+    }
+
+    /// Receive the [`Activity`] of the server, its connections and calls in
+    /// flight.
+    ///
+    /// The server counts its connections and calls only once this is
+    /// called, and the servers of clones of the builder share their counts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # async fn log_drain() {
+    /// let mut builder = Server::builder();
+    /// let mut activity = builder.activity();
+    /// tokio::spawn(async move {
+    ///     while activity.changed().await.is_ok() {
+    ///         let activity = *activity.borrow();
+    ///         if activity.is_draining() {
+    ///             println!(
+    ///                 "draining {} connections and {} calls",
+    ///                 activity.connections(),
+    ///                 activity.streams()
+    ///             );
+    ///         }
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn activity(&mut self) -> tokio::sync::watch::Receiver<Activity> {
+        self.activity
+            .get_or_insert_with(ActivityTracker::new)
+            .subscribe()
+    }
+
     /// Create a router with the `S` typed service as the first service.
     ///
     /// This will clone the `Server` builder and create a router that will
@@ -392,6 +746,21 @@ impl<L> Server<L> {
         Router::new(self.clone(), routes)
     }
 
+    /// Create a router serving all requests with the service `svc`, such as
+    /// that of a proxy or a gateway, until services are added to it, see
+    /// [`Routes::fallback_service`].
+    pub fn fallback_service<S>(&mut self, svc: S) -> Router<L>
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+        L: Clone,
+    {
+        Router::new(self.clone(), Routes::default().fallback_service(svc))
+    }
+
     /// Create a router with given [`Routes`].
     ///
     /// This will clone the `Server` builder and create a router that will
@@ -470,6 +839,7 @@ impl<L> Server<L> {
             trace_interceptor: self.trace_interceptor,
             concurrency_limit: self.concurrency_limit,
             timeout: self.timeout,
+            timer: self.timer,
             #[cfg(feature = "tls")]
             tls: self.tls,
             init_stream_window_size: self.init_stream_window_size,
@@ -477,15 +847,66 @@ impl<L> Server<L> {
             max_concurrent_streams: self.max_concurrent_streams,
             tcp_keepalive: self.tcp_keepalive,
             tcp_nodelay: self.tcp_nodelay,
+            configure_listener: self.configure_listener,
+            configure_connection: self.configure_connection,
             http2_keepalive_interval: self.http2_keepalive_interval,
             http2_keepalive_timeout: self.http2_keepalive_timeout,
+            keepalive_enforcement: self.keepalive_enforcement,
             http2_adaptive_window: self.http2_adaptive_window,
             http2_max_pending_accept_reset_streams: self.http2_max_pending_accept_reset_streams,
             max_frame_size: self.max_frame_size,
             accept_http1: self.accept_http1,
+            stats_handler: self.stats_handler,
+            shutdown_hooks: self.shutdown_hooks,
+            run_to_completion: self.run_to_completion,
+            grace_period: self.grace_period,
+            max_connection_age: self.max_connection_age,
+            max_connection_age_grace: self.max_connection_age_grace,
+            max_connections: self.max_connections,
+            on_max_connections: self.on_max_connections,
+            reuse_port_acceptors: self.reuse_port_acceptors,
+            acceptor_runtimes: self.acceptor_runtimes,
+            activity: self.activity,
+        }
+    }
+
+    /// The options of the connections the server accepts.
+    fn accept_options(&self) -> AcceptOptions {
+        AcceptOptions {
+            tcp_nodelay: self.tcp_nodelay,
+            tcp_keepalive: self.tcp_keepalive,
+            configure_listener: self.configure_listener.clone(),
+            configure_connection: self.configure_connection.clone(),
+            reuse_port_acceptors: self.reuse_port_acceptors,
+            #[cfg(feature = "tls")]
+            tls: self.tls.clone(),
         }
     }
 
+    /// Make the services of the connections to `inner`, along with the
+    /// sender expiring the grace period of a `graceful` shutdown.
+    fn make_svc<S>(&self, inner: S, graceful: bool) -> (MakeSvc<S>, Option<watch::Sender<bool>>) {
+        let (expire, grace) = match self.grace_period.filter(|_| graceful) {
+            Some(_) => {
+                let (expire, grace) = GracePeriod::new();
+                (Some(expire), Some(grace))
+            }
+            None => (None, None),
+        };
+        let svc = MakeSvc {
+            inner,
+            concurrency_limit: self.concurrency_limit,
+            timeout: self.timeout,
+            timer: self.timer.clone(),
+            trace_interceptor: self.trace_interceptor.clone(),
+            stats_handler: self.stats_handler.clone(),
+            run_to_completion: self.run_to_completion.clone().into(),
+            grace,
+            activity: self.activity.clone(),
+        };
+        (svc, expire)
+    }
+
     pub(crate) async fn serve_with_shutdown<S, I, F, IO, IE, ResBody>(
         self,
         svc: S,
@@ -505,60 +926,355 @@ impl<L> Server<L> {
         ResBody: http_body::Body<Data = Bytes> + Send + 'static,
         ResBody::Error: Into<crate::Error>,
     {
-        let trace_interceptor = self.trace_interceptor.clone();
-        let concurrency_limit = self.concurrency_limit;
-        let init_connection_window_size = self.init_connection_window_size;
-        let init_stream_window_size = self.init_stream_window_size;
-        let max_concurrent_streams = self.max_concurrent_streams;
-        let timeout = self.timeout;
-        let max_frame_size = self.max_frame_size;
-        let http2_only = !self.accept_http1;
+        let svc = self.service_builder.service(svc);
+        let (svc, expire) = self.make_svc(svc, signal.is_some());
+        let connections = accept(incoming, self.accept_options(), svc);
+        let (drain, _) = watch::channel(());
+        self.serve_connections(connections, signal, expire, drain)
+            .await
+    }
+
+    pub(crate) async fn serve_listeners<S, F, ResBody>(
+        self,
+        svc: S,
+        listeners: Listeners,
+        signal: Option<F>,
+    ) -> Result<(), super::Error>
+    where
+        L: Layer<S>,
+        L::Service: Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + 'static,
+        <<L as Layer<S>>::Service as Service<Request<Body>>>::Future: Send + 'static,
+        <<L as Layer<S>>::Service as Service<Request<Body>>>::Error: Into<crate::Error> + Send,
+        F: Future<Output = ()>,
+        ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<crate::Error>,
+    {
+        // The listeners yield connections of their own kind, sharing a
+        // service of a single type.
+        let svc = self
+            .service_builder
+            .service(svc)
+            .map_response(|response: Response<ResBody>| {
+                response.map(|body| body.map_err(Into::into).boxed_unsync())
+            })
+            .map_err(Into::into);
+        let svc = BoxCloneService::new(svc);
+
+        #[cfg(feature = "http3")]
+        let mut listeners = listeners;
+        #[cfg(feature = "http3")]
+        let http3 = listeners.bind_http3().map_err(super::Error::from_source)?;
+        #[cfg(feature = "http3")]
+        let svc = match http3.is_empty() {
+            true => svc,
+            false => {
+                let alt_svc = http3::alt_svc(&http3).map_err(super::Error::from_source)?;
+                http3::advertised(svc, alt_svc)
+            }
+        };
+        let (svc, expire) = self.make_svc(svc, signal.is_some());
+
+        let options = self.accept_options();
+        let mut connections = Vec::new();
+        for listener in listeners.into_listeners() {
+            connections.extend(listener(&options, svc.clone()).map_err(super::Error::from_source)?);
+        }
+        let (drain, _) = watch::channel(());
+        #[cfg(feature = "http3")]
+        for endpoint in http3 {
+            connections.push(http3::connections(endpoint, svc.clone(), drain.subscribe()));
+        }
+        if options.reuse_port_acceptors.is_some() {
+            return self
+                .serve_acceptors(connections, signal, expire, drain)
+                .await;
+        }
+        let connections = StreamMap::from_iter(connections.into_iter().enumerate());
+        let connections = connections.map(|(_, accepted)| accepted);
+        self.serve_connections(connections, signal, expire, drain)
+            .await
+    }
+
+    /// Serve the `connections` until `signal` resolves, then drain them
+    /// along with the other receivers of `drain`.
+    async fn serve_connections<C, F>(
+        self,
+        connections: C,
+        signal: Option<F>,
+        expire: Option<watch::Sender<bool>>,
+        drain: watch::Sender<()>,
+    ) -> Result<(), super::Error>
+    where
+        C: Stream<Item = Result<Accepted, crate::Error>>,
+        F: Future<Output = ()>,
+    {
+        let acceptor = self.acceptor(drain.subscribe());
+        let grace_period = self.grace_period;
+        let (signal, shutting_down) = self.shutdown_signal(signal);
+
+        let mut signal = pin!(signal);
+        if !acceptor.run(connections, signal.as_mut()).await? {
+            // Connections outlive the end of their incoming stream.
+            return Ok(());
+        }
+        drain_connections(&drain, grace_period, expire, shutting_down).await;
+        Ok(())
+    }
+
+    /// Serve the `connections` with acceptors of their own until `signal`
+    /// resolves, then drain them along with the other receivers of `drain`.
+    async fn serve_acceptors<F>(
+        self,
+        connections: Vec<Connections>,
+        signal: Option<F>,
+        expire: Option<watch::Sender<bool>>,
+        drain: watch::Sender<()>,
+    ) -> Result<(), super::Error>
+    where
+        F: Future<Output = ()>,
+    {
+        let acceptor = self.acceptor(drain.subscribe());
+        let drain = Arc::new(drain);
+        let acceptor_runtimes = self.acceptor_runtimes;
+        let grace_period = self.grace_period;
+        let (signal, shutting_down) = self.shutdown_signal(signal);
+
+        let (stop, stopped) = watch::channel(());
+        let (done, mut acceptors) = mpsc::unbounded_channel();
+        for (index, connections) in connections.into_iter().enumerate() {
+            let acceptor = acceptor.clone();
+            let mut stopped = stopped.clone();
+            let done = done.clone();
+            let run = async move {
+                let stop = pin!(async move {
+                    let _ = stopped.changed().await;
+                });
+                let _ = done.send(acceptor.run(connections, stop).await);
+            };
+
+            if !acceptor_runtimes {
+                tokio::spawn(run);
+                continue;
+            }
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(super::Error::from_source)?;
+            let drain = drain.clone();
+            std::thread::Builder::new()
+                .name(format!("tonic-acceptor-{}", index))
+                .spawn(move || {
+                    runtime.block_on(async move {
+                        run.await;
+                        // Keep serving the connections until they all drain.
+                        drain.closed().await;
+                    })
+                })
+                .map_err(super::Error::from_source)?;
+        }
+        drop((acceptor, done));
+
+        let mut signal = pin!(signal);
+        loop {
+            match unless_signalled(signal.as_mut(), acceptors.recv()).await {
+                Some(Some(Ok(_))) => {}
+                // Stop the other acceptors, dropping `stop`.
+                Some(Some(Err(error))) => return Err(error),
+                // Connections outlive the end of their incoming stream.
+                Some(None) => return Ok(()),
+                None => break,
+            }
+        }
+        stop.send_replace(());
+        while acceptors.recv().await.is_some() {}
+
+        drain_connections(&drain, grace_period, expire, shutting_down).await;
+        Ok(())
+    }
 
-        let http2_keepalive_interval = self.http2_keepalive_interval;
+    /// The acceptor of the connections of the server, which drain once
+    /// `drained` changes.
+    fn acceptor(&self, drained: watch::Receiver<()>) -> Acceptor {
         let http2_keepalive_timeout = self
             .http2_keepalive_timeout
             .unwrap_or_else(|| Duration::new(DEFAULT_HTTP2_KEEPALIVE_TIMEOUT_SECS, 0));
-        let http2_adaptive_window = self.http2_adaptive_window;
-        let http2_max_pending_accept_reset_streams = self.http2_max_pending_accept_reset_streams;
-
-        let svc = self.service_builder.service(svc);
 
-        let tcp = incoming::tcp_incoming(incoming, self);
-        let incoming = accept::from_stream::<_, _, crate::Error>(tcp);
+        let mut http = hyper::server::conn::Http::new();
+        http.http2_only(!self.accept_http1)
+            .http2_initial_connection_window_size(self.init_connection_window_size)
+            .http2_initial_stream_window_size(self.init_stream_window_size)
+            .http2_max_concurrent_streams(self.max_concurrent_streams)
+            .http2_keep_alive_interval(self.http2_keepalive_interval)
+            .http2_keep_alive_timeout(http2_keepalive_timeout)
+            .http2_adaptive_window(self.http2_adaptive_window.unwrap_or_default())
+            .http2_max_pending_accept_reset_streams(self.http2_max_pending_accept_reset_streams)
+            .http2_max_frame_size(self.max_frame_size);
+
+        let h2c = self.accept_http1.then(|| {
+            let mut h2c = http.clone();
+            h2c.http2_only(true);
+            h2c
+        });
+
+        Acceptor {
+            http,
+            h2c,
+            connection_limit: self
+                .max_connections
+                .map(|max| ConnectionLimit::new(max, self.on_max_connections.clone())),
+            connection_age: ConnectionAge {
+                max: self.max_connection_age,
+                grace: self.max_connection_age_grace,
+            },
+            keepalive_enforcement: self.keepalive_enforcement,
+            drained,
+        }
+    }
 
-        let svc = MakeSvc {
-            inner: svc,
-            concurrency_limit,
-            timeout,
-            trace_interceptor,
-            _io: PhantomData,
+    /// The future resolving once `signal` did and the server ran its
+    /// shutdown hooks, along with the receiver told then.
+    fn shutdown_signal<F>(
+        &self,
+        signal: Option<F>,
+    ) -> (impl Future<Output = ()>, oneshot::Receiver<()>)
+    where
+        F: Future<Output = ()>,
+    {
+        let shutdown_hooks = self.shutdown_hooks.clone();
+        let activity = self.activity.clone();
+        let (started, shutting_down) = oneshot::channel();
+        let signal = async move {
+            match signal {
+                Some(signal) => signal.await,
+                None => future::pending().await,
+            }
+            if let Some(activity) = &activity {
+                activity.drain();
+            }
+            for hook in &shutdown_hooks {
+                hook().await;
+            }
+            let _ = started.send(());
         };
+        (signal, shutting_down)
+    }
+}
 
-        let server = hyper::Server::builder(incoming)
-            .http2_only(http2_only)
-            .http2_initial_connection_window_size(init_connection_window_size)
-            .http2_initial_stream_window_size(init_stream_window_size)
-            .http2_max_concurrent_streams(max_concurrent_streams)
-            .http2_keep_alive_interval(http2_keepalive_interval)
-            .http2_keep_alive_timeout(http2_keepalive_timeout)
-            .http2_adaptive_window(http2_adaptive_window.unwrap_or_default())
-            .http2_max_pending_accept_reset_streams(http2_max_pending_accept_reset_streams)
-            .http2_max_frame_size(max_frame_size);
-
-        if let Some(signal) = signal {
-            server
-                .serve(svc)
-                .with_graceful_shutdown(signal)
-                .await
-                .map_err(super::Error::from_source)?
-        } else {
-            server.serve(svc).await.map_err(super::Error::from_source)?;
-        }
+/// Accepts the connections of a server, serving each in a task of its own.
+#[derive(Clone)]
+struct Acceptor {
+    http: hyper::server::conn::Http,
+    connection_limit: Option<ConnectionLimit>,
+    connection_age: ConnectionAge,
+    keepalive_enforcement: Option<KeepaliveEnforcementPolicy>,
+    /// The HTTP/2 connections of HTTP/1.1 ones upgrading to h2c, when the
+    /// server accepts HTTP/1.1.
+    h2c: Option<hyper::server::conn::Http>,
+    drained: watch::Receiver<()>,
+}
 
-        Ok(())
+impl Acceptor {
+    /// Accept the `connections` until `stop` resolves, returning whether it
+    /// did rather than the connections ending.
+    async fn run<C, F>(self, connections: C, mut stop: Pin<&mut F>) -> Result<bool, super::Error>
+    where
+        C: Stream<Item = Result<Accepted, crate::Error>>,
+        F: Future<Output = ()>,
+    {
+        let mut connections = pin!(connections);
+        loop {
+            let permit = match &self.connection_limit {
+                Some(limit) => match unless_signalled(stop.as_mut(), limit.acquire()).await {
+                    Some(permit) => Some(permit),
+                    None => return Ok(true),
+                },
+                None => None,
+            };
+            let accepted = unless_signalled(stop.as_mut(), connections.next()).await;
+            let (io, svc) = match accepted {
+                Some(Some(accepted)) => accepted.map_err(super::Error::from_source)?,
+                Some(None) => return Ok(false),
+                None => return Ok(true),
+            };
+
+            let io = EnforcedIo::new(io, self.keepalive_enforcement);
+            let (svc, upgrade) = match &self.h2c {
+                Some(_) => {
+                    let (upgrade, upgraded) = oneshot::channel();
+                    (
+                        BoxService::new(H2cUpgrade::new(svc, upgrade)),
+                        Some(upgraded),
+                    )
+                }
+                None => (svc, None),
+            };
+            let connection = self.http.serve_connection(io, svc).with_upgrades();
+
+            tokio::spawn(h2c::serve_connection(
+                connection,
+                |connection| connection.graceful_shutdown(),
+                upgrade.zip(self.h2c.clone()),
+                self.drained.clone(),
+                self.connection_age,
+                self.keepalive_enforcement,
+                permit,
+            ));
+        }
     }
 }
 
+/// Drain the connections of `drain`, expiring the grace period once it
+/// elapsed after the shutdown.
+async fn drain_connections(
+    drain: &watch::Sender<()>,
+    grace_period: Option<Duration>,
+    expire: Option<watch::Sender<bool>>,
+    shutting_down: oneshot::Receiver<()>,
+) {
+    let expiry = async move {
+        if let (Some(period), Some(expire)) = (grace_period, expire) {
+            if shutting_down.await.is_ok() {
+                tokio::time::sleep(period).await;
+                expire.send_replace(true);
+            }
+        }
+        future::pending::<()>().await
+    };
+    drain.send_replace(());
+    let mut drained = pin!(drain.closed());
+    let mut expiry = pin!(expiry);
+    poll_fn(|cx| {
+        let _ = expiry.as_mut().poll(cx);
+        drained.as_mut().poll(cx)
+    })
+    .await;
+}
+/// Accept the connections of `incoming`, along with their service made by
+/// `svc`.
+fn accept<S, I, IO, IE, ResBody>(
+    incoming: I,
+    options: AcceptOptions,
+    svc: MakeSvc<S>,
+) -> impl Stream<Item = Result<Accepted, crate::Error>>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send,
+    I: Stream<Item = Result<IO, IE>>,
+    IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+    IO::ConnectInfo: Clone + Send + Sync + 'static,
+    IE: Into<crate::Error>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::Error>,
+{
+    incoming::tcp_incoming(incoming, options).map(move |io| {
+        io.map(|io| {
+            let svc = svc.make_service(&io);
+            (BoxedIo::new(io), svc)
+        })
+    })
+}
+
 impl<L> Router<L> {
     pub(crate) fn new(server: Server<L>, routes: Routes) -> Self {
         Self { server, routes }
@@ -601,6 +1317,35 @@ impl<L> Router<L> {
         self
     }
 
+    /// Add a new service to this router under the path `prefix`, see
+    /// [`Routes::nest`].
+    pub fn nest<S>(mut self, prefix: &str, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        self.routes = self.routes.nest(prefix, svc);
+        self
+    }
+
+    /// Set the service of the requests of methods that no added service
+    /// serves, see [`Routes::fallback_service`].
+    pub fn fallback_service<S>(mut self, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        self.routes = self.routes.fallback_service(svc);
+        self
+    }
+
     /// Convert this tonic `Router` into an axum `Router` consuming the tonic one.
     pub fn into_router(self) -> axum::Router {
         self.routes.into_router()
@@ -620,12 +1365,10 @@ impl<L> Router<L> {
         ResBody: http_body::Body<Data = Bytes> + Send + 'static,
         ResBody::Error: Into<crate::Error>,
     {
-        let incoming = TcpIncoming::new(addr, self.server.tcp_nodelay, self.server.tcp_keepalive)
-            .map_err(super::Error::from_source)?;
         self.server
-            .serve_with_shutdown::<_, _, future::Ready<()>, _, _, ResBody>(
+            .serve_listeners::<_, future::Ready<()>, ResBody>(
                 self.routes.prepare(),
-                incoming,
+                Listeners::new().tcp(addr),
                 None,
             )
             .await
@@ -650,10 +1393,12 @@ impl<L> Router<L> {
         ResBody: http_body::Body<Data = Bytes> + Send + 'static,
         ResBody::Error: Into<crate::Error>,
     {
-        let incoming = TcpIncoming::new(addr, self.server.tcp_nodelay, self.server.tcp_keepalive)
-            .map_err(super::Error::from_source)?;
         self.server
-            .serve_with_shutdown(self.routes.prepare(), incoming, Some(signal))
+            .serve_listeners(
+                self.routes.prepare(),
+                Listeners::new().tcp(addr),
+                Some(signal),
+            )
             .await
     }
 
@@ -719,6 +1464,51 @@ impl<L> Router<L> {
             .await
     }
 
+    /// Consume this [`Server`] creating a future that will execute the server
+    /// on all the provided [`Listeners`] at once.
+    ///
+    /// [`Server`]: struct.Server.html
+    pub async fn serve_listeners<ResBody>(self, listeners: Listeners) -> Result<(), super::Error>
+    where
+        L: Layer<Routes>,
+        L::Service: Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + 'static,
+        <<L as Layer<Routes>>::Service as Service<Request<Body>>>::Future: Send + 'static,
+        <<L as Layer<Routes>>::Service as Service<Request<Body>>>::Error: Into<crate::Error> + Send,
+        ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<crate::Error>,
+    {
+        self.server
+            .serve_listeners::<_, future::Ready<()>, ResBody>(
+                self.routes.prepare(),
+                listeners,
+                None,
+            )
+            .await
+    }
+
+    /// Consume this [`Server`] creating a future that will execute the server
+    /// on all the provided [`Listeners`] at once, shutting them all down when
+    /// the provided signal is received.
+    ///
+    /// [`Server`]: struct.Server.html
+    pub async fn serve_listeners_with_shutdown<F: Future<Output = ()>, ResBody>(
+        self,
+        listeners: Listeners,
+        signal: F,
+    ) -> Result<(), super::Error>
+    where
+        L: Layer<Routes>,
+        L::Service: Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + 'static,
+        <<L as Layer<Routes>>::Service as Service<Request<Body>>>::Future: Send + 'static,
+        <<L as Layer<Routes>>::Service as Service<Request<Body>>>::Error: Into<crate::Error> + Send,
+        ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<crate::Error>,
+    {
+        self.server
+            .serve_listeners(self.routes.prepare(), listeners, Some(signal))
+            .await
+    }
+
     /// Create a tower service out of a router.
     pub fn into_service<ResBody>(self) -> L::Service
     where
@@ -742,6 +1532,10 @@ impl<L> fmt::Debug for Server<L> {
 struct Svc<S> {
     inner: S,
     trace_interceptor: Option<TraceInterceptor>,
+    grace: Option<GracePeriod>,
+    activity: Option<ActivityTracker>,
+    /// Counts the connection of the service while it lives.
+    _connection: Option<ActivityGuard>,
 }
 
 impl<S, ResBody> Service<Request<Body>> for Svc<S>
@@ -774,9 +1568,15 @@ where
             tracing::Span::none()
         };
 
+        let stream = self.activity.as_ref().map(ActivityTracker::stream);
+        let (guard, cancellation) = CancelOnDrop::new(stream);
+        req.extensions_mut().insert(cancellation);
+
         SvcFuture {
             inner: self.inner.call(req),
             span,
+            guard: Some(guard),
+            expiry: self.grace.as_ref().map(GracePeriod::expired),
         }
     }
 }
@@ -786,6 +1586,8 @@ struct SvcFuture<F> {
     #[pin]
     inner: F,
     span: tracing::Span,
+    guard: Option<CancelOnDrop>,
+    expiry: Option<BoxFuture<'static, ()>>,
 }
 
 impl<F, E, ResBody> Future for SvcFuture<F>
@@ -801,8 +1603,29 @@ where
         let this = self.project();
         let _guard = this.span.enter();
 
-        let response: Response<ResBody> = ready!(this.inner.poll(cx)).map_err(Into::into)?;
-        let response = response.map(|body| body.map_err(Into::into).boxed_unsync());
+        let result = match this.inner.poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => {
+                let expiry = this.expiry.as_mut().map(|expiry| expiry.as_mut().poll(cx));
+                if !matches!(expiry, Some(Poll::Ready(()))) {
+                    return Poll::Pending;
+                }
+                // Cancel the call, dropping the guard.
+                this.guard.take();
+                let response = shutting_down().to_http();
+                return Poll::Ready(Ok(
+                    response.map(|body| body.map_err(Into::into).boxed_unsync())
+                ));
+            }
+        };
+        let guard = this.guard.take().expect("polled after completion");
+        let response: Response<ResBody> = result.map_err(|error| {
+            guard.complete();
+            error.into()
+        })?;
+        let response = response.map(|body| {
+            CancellableBody::new(body.map_err(Into::into), guard, this.expiry.take()).boxed_unsync()
+        });
         Poll::Ready(Ok(response))
     }
 }
@@ -813,43 +1636,71 @@ impl<S> fmt::Debug for Svc<S> {
     }
 }
 
-struct MakeSvc<S, IO> {
+struct MakeSvc<S> {
     concurrency_limit: Option<usize>,
     timeout: Option<Duration>,
+    timer: SharedTimer,
     inner: S,
     trace_interceptor: Option<TraceInterceptor>,
-    _io: PhantomData<fn() -> IO>,
+    stats_handler: Option<Arc<dyn StatsHandler>>,
+    run_to_completion: Arc<[String]>,
+    grace: Option<GracePeriod>,
+    activity: Option<ActivityTracker>,
+}
+
+impl<S: Clone> Clone for MakeSvc<S> {
+    fn clone(&self) -> Self {
+        Self {
+            concurrency_limit: self.concurrency_limit,
+            timeout: self.timeout,
+            timer: self.timer.clone(),
+            inner: self.inner.clone(),
+            trace_interceptor: self.trace_interceptor.clone(),
+            stats_handler: self.stats_handler.clone(),
+            run_to_completion: self.run_to_completion.clone(),
+            grace: self.grace.clone(),
+            activity: self.activity.clone(),
+        }
+    }
 }
 
-impl<S, ResBody, IO> Service<&ServerIo<IO>> for MakeSvc<S, IO>
+impl<S, ResBody> MakeSvc<S>
 where
-    IO: Connected,
     S: Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + 'static,
     S::Future: Send + 'static,
     S::Error: Into<crate::Error> + Send,
     ResBody: http_body::Body<Data = Bytes> + Send + 'static,
     ResBody::Error: Into<crate::Error>,
 {
-    type Response = BoxService;
-    type Error = crate::Error;
-    type Future = future::Ready<Result<Self::Response, Self::Error>>;
-
-    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        Ok(()).into()
-    }
-
-    fn call(&mut self, io: &ServerIo<IO>) -> Self::Future {
+    /// Make the service of the connection `io`.
+    fn make_service<IO>(&self, io: &ServerIo<IO>) -> BoxService
+    where
+        IO: Connected,
+        IO::ConnectInfo: Clone + Send + Sync + 'static,
+    {
         let conn_info = io.connect_info();
 
         let svc = self.inner.clone();
         let concurrency_limit = self.concurrency_limit;
         let timeout = self.timeout;
+        let timer = self.timer.clone();
         let trace_interceptor = self.trace_interceptor.clone();
+        let remote_addr = match &conn_info {
+            tower::util::Either::A(inner) => tcp_remote_addr(inner),
+            #[cfg(feature = "tls")]
+            tower::util::Either::B(inner) => tcp_remote_addr(inner.get_ref()),
+            #[cfg(not(feature = "tls"))]
+            tower::util::Either::B(_) => None,
+        };
+        let stats_handler = self.stats_handler.clone();
+        let run_to_completion = self.run_to_completion.clone();
 
         let svc = ServiceBuilder::new()
+            .layer_fn(|s| ServerStats::new(s, stats_handler.clone(), remote_addr))
             .layer_fn(RecoverError::new)
             .option_layer(concurrency_limit.map(ConcurrencyLimitLayer::new))
-            .layer_fn(|s| GrpcTimeout::new(s, timeout))
+            .layer_fn(|s| GrpcTimeout::new(s, timeout, timer.clone()).scoped())
+            .layer_fn(|s| RunToCompletion::new(s, run_to_completion.clone()))
             .service(svc);
 
         let svc = ServiceBuilder::new()
@@ -880,8 +1731,20 @@ where
             .service(Svc {
                 inner: svc,
                 trace_interceptor,
+                grace: self.grace.clone(),
+                activity: self.activity.clone(),
+                _connection: self.activity.as_ref().map(ActivityTracker::connection),
             });
 
-        future::ready(Ok(svc))
+        svc
+    }
+}
+
+/// The address of the client of a TCP connection, described by `info`.
+fn tcp_remote_addr(info: &dyn Any) -> Option<SocketAddr> {
+    #[cfg(feature = "http3")]
+    if let Some(info) = info.downcast_ref::<Http3ConnectInfo>() {
+        return Some(info.remote_addr());
     }
+    info.downcast_ref::<TcpConnectInfo>()?.remote_addr()
 }
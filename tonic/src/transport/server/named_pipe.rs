@@ -0,0 +1,149 @@
+use super::Connected;
+use crate::transport::BoxFuture;
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+    future::Future,
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+use tokio_stream::Stream;
+
+impl Connected for NamedPipeServer {
+    type ConnectInfo = ();
+
+    fn connect_info(&self) -> Self::ConnectInfo {}
+}
+
+/// Creates a named pipe and accepts its clients, to pass to
+/// [`Router::serve_with_incoming`](super::Router::serve_with_incoming).
+///
+/// Clients connect to it with endpoints such as `pipe:app`, for the pipe
+/// `\\.\pipe\app`.
+///
+/// ```no_run
+/// # use tonic::transport::server::NamedPipeIncoming;
+/// # #[tokio::main]
+/// # async fn main() {
+/// let incoming = NamedPipeIncoming::bind(r"\\.\pipe\app").unwrap();
+/// # }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+pub struct NamedPipeIncoming {
+    path: OsString,
+    options: ServerOptions,
+    connecting: Option<BoxFuture<'static, io::Result<NamedPipeServer>>>,
+}
+
+impl NamedPipeIncoming {
+    /// Create the pipe at `path`, which fails if another server already
+    /// created it.
+    pub fn bind(path: impl AsRef<OsStr>) -> Result<Self, crate::Error> {
+        Self::bind_with_options(path, ServerOptions::new())
+    }
+
+    /// Create the pipe at `path` with `options`, to set its security
+    /// attributes or accept remote clients for instance.
+    ///
+    /// The first instance of the pipe is always created with
+    /// [`ServerOptions::first_pipe_instance`].
+    pub fn bind_with_options(
+        path: impl AsRef<OsStr>,
+        mut options: ServerOptions,
+    ) -> Result<Self, crate::Error> {
+        let path = path.as_ref().to_owned();
+        let first = options.first_pipe_instance(true).create(&path)?;
+        options.first_pipe_instance(false);
+
+        Ok(NamedPipeIncoming {
+            path,
+            options,
+            connecting: Some(accept(first)),
+        })
+    }
+}
+
+/// Wait for a client to connect to the instance `server`.
+fn accept(server: NamedPipeServer) -> BoxFuture<'static, io::Result<NamedPipeServer>> {
+    Box::pin(async move {
+        server.connect().await?;
+        Ok(server)
+    })
+}
+
+impl Stream for NamedPipeIncoming {
+    type Item = Result<NamedPipeServer, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let connecting = match &mut this.connecting {
+            Some(connecting) => connecting,
+            None => match this.options.create(&this.path) {
+                Ok(server) => this.connecting.insert(accept(server)),
+                Err(error) => return Poll::Ready(Some(Err(error))),
+            },
+        };
+
+        let connected = ready!(connecting.as_mut().poll(cx));
+        // Each client takes an instance of the pipe, and clients can't open
+        // the pipe while it has none left, so the next one is created right
+        // away. Creating it is tried again on the next poll if this fails.
+        this.connecting = this.options.create(&this.path).ok().map(accept);
+        Poll::Ready(Some(connected))
+    }
+}
+
+impl fmt::Debug for NamedPipeIncoming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamedPipeIncoming")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Endpoint;
+    use http::Request;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn serves_named_pipe() {
+        let name = format!("tonic-serves-named-pipe-{}", std::process::id());
+        let mut incoming = NamedPipeIncoming::bind(format!(r"\\.\pipe\{}", name)).unwrap();
+        let incoming =
+            hyper::server::accept::poll_fn(move |cx| Pin::new(&mut incoming).poll_next(cx));
+        let make = hyper::service::make_service_fn(|_| async {
+            Ok::<_, Infallible>(hyper::service::service_fn(|request: Request<_>| {
+                let authority = request.uri().authority().map(|a| a.to_string());
+                async move {
+                    assert_eq!(authority.as_deref(), Some("localhost"));
+                    Ok::<_, Infallible>(http::Response::new(hyper::Body::empty()))
+                }
+            }))
+        });
+        tokio::spawn(
+            hyper::Server::builder(incoming)
+                .http2_only(true)
+                .serve(make),
+        );
+
+        let channel = Endpoint::from_shared(format!("pipe:{}", name))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let request = Request::builder()
+                .uri("/")
+                .body(crate::body::empty_body())
+                .unwrap();
+            let response = channel.clone().oneshot(request).await.unwrap();
+            assert!(response.status().is_success());
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::transport::service::retry::jitter;
+use std::{
+    fmt,
+    future::{pending, poll_fn, Future},
+    pin::{pin, Pin},
+    sync::Arc,
+    task::Poll,
+    time::Duration,
+};
+use tokio::{
+    sync::{watch, OwnedSemaphorePermit, Semaphore},
+    time::Sleep,
+};
+
+pub(crate) type LimitHook = Arc<dyn Fn() + Send + Sync + 'static>;
+
+/// How long the server keeps a connection open.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ConnectionAge {
+    /// The age after which the connection is gracefully shut down.
+    pub(crate) max: Option<Duration>,
+    /// How long the calls of the connection may last once it is shut down
+    /// for its age, before it is closed.
+    pub(crate) grace: Option<Duration>,
+}
+
+impl ConnectionAge {
+    /// The age of a new connection, up to 10% shorter or longer than the
+    /// maximum, so that the connections opened together don't all close
+    /// together.
+    fn jittered(&self) -> Option<Duration> {
+        self.max.map(|max| {
+            let spread = max / 10;
+            (max - spread).saturating_add(jitter(spread * 2))
+        })
+    }
+}
+
+/// The maximum number of connections a server keeps open.
+#[derive(Clone)]
+pub(crate) struct ConnectionLimit {
+    permits: Arc<Semaphore>,
+    max: usize,
+    on_limit: Option<LimitHook>,
+}
+
+impl ConnectionLimit {
+    pub(crate) fn new(max: usize, on_limit: Option<LimitHook>) -> Self {
+        ConnectionLimit {
+            permits: Arc::new(Semaphore::new(max)),
+            max,
+            on_limit,
+        }
+    }
+
+    /// Wait for a permit to accept one more connection, released once the
+    /// connection closes.
+    pub(crate) async fn acquire(&self) -> OwnedSemaphorePermit {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return permit;
+        }
+
+        tracing::warn!(
+            "reached the limit of {} connections, not accepting more",
+            self.max
+        );
+        if let Some(on_limit) = &self.on_limit {
+            on_limit();
+        }
+        self.permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the permits are never closed")
+    }
+}
+
+/// Run `future` to completion, unless `signal` resolves first.
+pub(crate) async fn unless_signalled<T>(
+    mut signal: Pin<&mut impl Future<Output = ()>>,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    let mut future = pin!(future);
+    poll_fn(|cx| match signal.as_mut().poll(cx) {
+        Poll::Ready(()) => Poll::Ready(None),
+        Poll::Pending => future.as_mut().poll(cx).map(Some),
+    })
+    .await
+}
+
+/// Serve `connection` until it closes, shutting it down gracefully once
+/// `drain` changes or it reached its maximum age.
+pub(crate) async fn serve_connection<C, E>(
+    connection: C,
+    mut drain: watch::Receiver<()>,
+    age: ConnectionAge,
+    graceful_shutdown: impl Fn(Pin<&mut C>),
+) where
+    C: Future<Output = Result<(), E>>,
+    E: fmt::Display,
+{
+    let mut connection = pin!(connection);
+    // Borrowing `drain` rather than moving it keeps the receiver until the
+    // connection closes, which the server waits for when it shuts down.
+    let mut drained = pin!(async {
+        // The server stopped serving without shutting down.
+        if drain.changed().await.is_err() {
+            pending::<()>().await;
+        }
+    });
+    let mut max_age: Option<Pin<Box<Sleep>>> =
+        age.jittered().map(|age| Box::pin(tokio::time::sleep(age)));
+    let mut grace: Option<Pin<Box<Sleep>>> = None;
+    let mut draining = false;
+
+    poll_fn(|cx| {
+        if let Poll::Ready(result) = connection.as_mut().poll(cx) {
+            if let Err(error) = result {
+                tracing::debug!("connection error: {}", error);
+            }
+            return Poll::Ready(());
+        }
+
+        if !draining {
+            let aged = max_age
+                .as_mut()
+                .is_some_and(|sleep| sleep.as_mut().poll(cx).is_ready());
+            if aged || drained.as_mut().poll(cx).is_ready() {
+                draining = true;
+                graceful_shutdown(connection.as_mut());
+                if aged {
+                    tracing::debug!("shutting down a connection at its maximum age");
+                    grace = age.grace.map(|grace| Box::pin(tokio::time::sleep(grace)));
+                }
+                // The connection sends its `GOAWAY` once polled again.
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        }
+
+        if let Some(grace) = &mut grace {
+            if grace.as_mut().poll(cx).is_ready() {
+                tracing::debug!("closing a connection at the end of its maximum age grace");
+                return Poll::Ready(());
+            }
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::BoxBody;
+    use crate::transport::{local, server::Routes, server::TcpIncoming, Channel, Server};
+    use http_body::Body as _;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+    use tower::{service_fn, Service, ServiceExt};
+
+    #[test]
+    fn jitters_ages() {
+        let age = ConnectionAge {
+            max: Some(Duration::from_secs(100)),
+            grace: None,
+        };
+        for _ in 0..100 {
+            let age = age.jittered().unwrap();
+            assert!(age >= Duration::from_secs(90) && age < Duration::from_secs(110));
+        }
+        assert_eq!(ConnectionAge::default().jittered(), None);
+    }
+
+    #[tokio::test]
+    async fn serves_calls_with_the_fallback_service() {
+        let svc = service_fn(|request: http::Request<hyper::Body>| async move {
+            let mut response = crate::Status::new(crate::Code::Ok, "").to_http();
+            let method = http::HeaderValue::from_str(request.uri().path()).unwrap();
+            response.headers_mut().insert("x-method", method);
+            Ok::<_, std::convert::Infallible>(response)
+        });
+
+        let (mut channel, incoming) = local::pair();
+        tokio::spawn(
+            Server::builder()
+                .fallback_service(svc)
+                .serve_with_incoming(incoming),
+        );
+
+        let request = http::Request::post("http://localhost/unknown.Service/Call")
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        let response = channel.ready().await.unwrap().call(request).await.unwrap();
+        assert_eq!(response.headers()["x-method"], "/unknown.Service/Call");
+    }
+
+    #[tokio::test]
+    async fn closes_connections_after_their_max_age_grace() {
+        let senders = Arc::new(Mutex::new(Vec::new()));
+        let svc = service_fn({
+            let senders = senders.clone();
+            move |_: http::Request<hyper::Body>| {
+                let (sender, body) = hyper::Body::channel();
+                senders.lock().unwrap().push(sender);
+                let response = http::Response::new(crate::body::boxed(body));
+                async move { Ok::<_, std::convert::Infallible>(response) }
+            }
+        });
+
+        let (mut channel, incoming) = local::pair();
+        tokio::spawn(
+            Server::builder()
+                .max_connection_age(Duration::from_millis(20))
+                .max_connection_age_grace(Duration::from_millis(20))
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming(incoming),
+        );
+
+        let request = http::Request::post("http://localhost/test.Service/Stream")
+            .header("content-type", "application/grpc")
+            .body(BoxBody::default())
+            .unwrap();
+        let response = channel.ready().await.unwrap().call(request).await.unwrap();
+        let mut body = response.into_body();
+        let data = tokio::time::timeout(Duration::from_secs(5), body.data());
+        assert!(matches!(data.await, Ok(Some(Err(_)))));
+    }
+
+    #[tokio::test]
+    async fn stops_accepting_connections_at_the_limit() {
+        let svc = service_fn(|_: http::Request<hyper::Body>| async {
+            Ok::<_, std::convert::Infallible>(crate::Status::new(crate::Code::Ok, "").to_http())
+        });
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            Server::builder()
+                .max_connections(1)
+                .on_max_connections({
+                    let hits = hits.clone();
+                    move || {
+                        hits.fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming(incoming),
+        );
+
+        let call = |mut channel: Channel| async move {
+            let request = http::Request::post("http://localhost/test.Service/Call")
+                .header("content-type", "application/grpc")
+                .body(BoxBody::default())
+                .unwrap();
+            channel.ready().await.unwrap().call(request).await.unwrap();
+        };
+        let endpoint = Channel::from_shared(format!("http://{}", addr)).unwrap();
+        let first = endpoint.connect().await.unwrap();
+        call(first.clone()).await;
+
+        let second = endpoint.connect().await.unwrap();
+        let mut second = tokio::spawn(call(second));
+        let blocked = tokio::time::timeout(Duration::from_millis(50), &mut second);
+        assert!(blocked.await.is_err());
+        assert_eq!(hits.load(Ordering::Relaxed), 1);
+
+        drop(first);
+        second.await.unwrap();
+    }
+}
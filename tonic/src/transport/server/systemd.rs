@@ -0,0 +1,129 @@
+use std::{
+    env, io,
+    ops::Range,
+    os::fd::{FromRawFd, RawFd},
+    os::unix::net::UnixListener,
+};
+
+/// The first file descriptor systemd passes, after stdin, stdout and stderr.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// A listening socket passed by systemd.
+#[derive(Debug)]
+pub(crate) enum Activated {
+    Tcp(std::net::TcpListener),
+    Unix(UnixListener),
+}
+
+/// Take the sockets systemd passed to the process with socket activation,
+/// as `sd_listen_fds` does, unsetting the variables describing them.
+pub(crate) fn listen_fds() -> io::Result<Vec<Activated>> {
+    let fds = passed_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    // The sockets are owned once taken, so they mustn't be taken again.
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    fds?.map(|fd| {
+        // SAFETY: systemd passes the process the ownership of the open file
+        // descriptors from `LISTEN_FDS_START` on, and they're only taken once.
+        let socket = unsafe { socket2::Socket::from_raw_fd(fd) };
+        activated(socket).map_err(|e| io::Error::new(e.kind(), format!("LISTEN_FDS {}: {}", fd, e)))
+    })
+    .collect()
+}
+
+/// The file descriptors passed according to the `LISTEN_PID` and `LISTEN_FDS`
+/// variables, none if they are for another process than `pid`.
+fn passed_fds(
+    listen_pid: Option<&str>,
+    listen_fds: Option<&str>,
+    pid: u32,
+) -> io::Result<Range<RawFd>> {
+    let invalid = |name| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid {}", name));
+
+    let listen_pid = match listen_pid {
+        Some(listen_pid) => listen_pid
+            .parse::<u32>()
+            .map_err(|_| invalid("LISTEN_PID"))?,
+        None => return Ok(LISTEN_FDS_START..LISTEN_FDS_START),
+    };
+    if listen_pid != pid {
+        return Ok(LISTEN_FDS_START..LISTEN_FDS_START);
+    }
+    let count = listen_fds
+        .ok_or_else(|| invalid("LISTEN_FDS"))?
+        .parse::<RawFd>()
+        .ok()
+        .filter(|count| *count >= 0)
+        .ok_or_else(|| invalid("LISTEN_FDS"))?;
+    Ok(LISTEN_FDS_START..LISTEN_FDS_START + count)
+}
+
+/// Check `socket` is a listening TCP or unix stream socket.
+fn activated(socket: socket2::Socket) -> io::Result<Activated> {
+    let unsupported = |what| io::Error::new(io::ErrorKind::InvalidInput, what);
+
+    if socket.r#type()? != socket2::Type::STREAM {
+        return Err(unsupported("not a stream socket"));
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if !socket.is_listener()? {
+        return Err(unsupported("not a listening socket"));
+    }
+    let addr = socket.local_addr()?;
+    socket.set_cloexec(true)?;
+    socket.set_nonblocking(true)?;
+
+    if addr.as_socket().is_some() {
+        Ok(Activated::Tcp(socket.into()))
+    } else if addr.is_unix() {
+        Ok(Activated::Unix(socket.into()))
+    } else {
+        Err(unsupported("neither a TCP nor a unix socket"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use socket2::Socket;
+
+    #[test]
+    fn parses_passed_fds() {
+        assert_eq!(passed_fds(None, None, 7).unwrap(), 3..3);
+        assert_eq!(passed_fds(Some("7"), Some("2"), 7).unwrap(), 3..5);
+        // Inherited from a parent process.
+        assert_eq!(passed_fds(Some("8"), Some("2"), 7).unwrap(), 3..3);
+        assert!(passed_fds(Some("7"), None, 7).is_err());
+        assert!(passed_fds(Some("7"), Some("-1"), 7).is_err());
+        assert!(passed_fds(Some("pid"), Some("2"), 7).is_err());
+    }
+
+    #[test]
+    fn checks_socket_types() {
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tcp = activated(Socket::from(tcp)).unwrap();
+        assert!(matches!(tcp, Activated::Tcp(_)));
+
+        let path = env::temp_dir().join(format!("tonic-systemd-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let unix = UnixListener::bind(&path).unwrap();
+        let unix = activated(Socket::from(unix)).unwrap();
+        assert!(matches!(unix, Activated::Unix(_)));
+        let _ = std::fs::remove_file(&path);
+
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(activated(Socket::from(udp)).is_err());
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            let unbound = Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+            assert!(activated(unbound).is_err());
+        }
+    }
+}
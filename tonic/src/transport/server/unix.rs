@@ -1,5 +1,12 @@
 use super::Connected;
-use std::sync::Arc;
+use std::{
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::net::{UnixListener, UnixStream};
+use tokio_stream::Stream;
 
 /// Connection info for Unix domain socket streams.
 ///
@@ -19,7 +26,7 @@ pub struct UdsConnectInfo {
     pub peer_cred: Option<tokio::net::unix::UCred>,
 }
 
-impl Connected for tokio::net::UnixStream {
+impl Connected for UnixStream {
     type ConnectInfo = UdsConnectInfo;
 
     fn connect_info(&self) -> Self::ConnectInfo {
@@ -29,3 +36,93 @@ impl Connected for tokio::net::UnixStream {
         }
     }
 }
+
+/// Binds to a unix socket and accepts its connections, to pass to
+/// [`Router::serve_with_incoming`](super::Router::serve_with_incoming).
+///
+/// ```no_run
+/// # use tonic::transport::server::UnixIncoming;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), tonic::transport::Error> {
+/// let incoming = UnixIncoming::bind("/var/run/app.sock").unwrap();
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug)]
+pub struct UnixIncoming {
+    listener: UnixListener,
+}
+
+impl UnixIncoming {
+    /// Bind to the socket at `path`.
+    pub fn bind(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
+        let listener = UnixListener::bind(path)?;
+        Ok(UnixIncoming { listener })
+    }
+
+    /// Bind to the socket `name` of the abstract namespace, which clients
+    /// connect to as `unix-abstract:name`.
+    ///
+    /// Abstract sockets have no file, so they don't depend on filesystem
+    /// permissions, and disappear once closed.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+    pub fn bind_abstract(name: impl AsRef<[u8]>) -> Result<Self, crate::Error> {
+        let path = crate::transport::service::abstract_path(name.as_ref());
+        Self::bind(path)
+    }
+
+    /// Accept the connections of `listener`.
+    pub fn from_listener(listener: UnixListener) -> Self {
+        UnixIncoming { listener }
+    }
+}
+
+impl Stream for UnixIncoming {
+    type Item = Result<UnixStream, std::io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.listener
+            .poll_accept(cx)
+            .map(|accepted| Some(accepted.map(|(stream, _)| stream)))
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+    use crate::transport::Endpoint;
+    use http::Request;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn serves_abstract_socket() {
+        let name = format!("tonic-serves-abstract-socket-{}", std::process::id());
+        let incoming = UnixIncoming::bind_abstract(&name).unwrap();
+        let make = hyper::service::make_service_fn(|_| async {
+            Ok::<_, Infallible>(hyper::service::service_fn(|_| async {
+                Ok::<_, Infallible>(http::Response::new(hyper::Body::empty()))
+            }))
+        });
+        let incoming = hyper::server::accept::from_stream(incoming);
+        tokio::spawn(
+            hyper::Server::builder(incoming)
+                .http2_only(true)
+                .serve(make),
+        );
+
+        let channel = Endpoint::from_shared(format!("unix-abstract:{}", name))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        let response = channel.oneshot(request).await.unwrap();
+        assert!(response.status().is_success());
+    }
+}
@@ -0,0 +1,99 @@
+use super::Connected;
+use crate::transport::service::VsockStream;
+use socket2::{Domain, SockAddr, Socket, Type};
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::unix::AsyncFd;
+use tokio_stream::Stream;
+
+/// The context identifier accepting connections to any address of the
+/// machine, `VMADDR_CID_ANY`.
+const CID_ANY: u32 = u32::MAX;
+
+/// Connection info for `AF_VSOCK` streams.
+///
+/// This type will be accessible through [request extensions][ext] if you're using
+/// a [`VsockIncoming`].
+///
+/// See [Connected] for more details.
+///
+/// [ext]: crate::Request::extensions
+/// [Connected]: crate::transport::server::Connected
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+#[derive(Clone, Debug)]
+pub struct VsockConnectInfo {
+    /// The context identifier of the peer, identifying its virtual machine.
+    pub peer_cid: Option<u32>,
+    /// The port of the peer.
+    pub peer_port: Option<u32>,
+}
+
+impl Connected for VsockStream {
+    type ConnectInfo = VsockConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        let peer = self.peer_addr().ok();
+        VsockConnectInfo {
+            peer_cid: peer.map(|(cid, _)| cid),
+            peer_port: peer.map(|(_, port)| port),
+        }
+    }
+}
+
+/// Binds to an `AF_VSOCK` port and accepts its connections, to pass to
+/// [`Router::serve_with_incoming`](super::Router::serve_with_incoming).
+///
+/// Clients connect to it with endpoints such as `vsock://3:50051`, with the
+/// context identifier of the machine and the port.
+///
+/// ```no_run
+/// # use tonic::transport::server::VsockIncoming;
+/// # #[tokio::main]
+/// # async fn main() {
+/// let incoming = VsockIncoming::bind(50051).unwrap();
+/// # }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+#[derive(Debug)]
+pub struct VsockIncoming {
+    listener: AsyncFd<Socket>,
+}
+
+impl VsockIncoming {
+    /// Bind to `port`, for connections to any context identifier of this
+    /// machine.
+    pub fn bind(port: u32) -> Result<Self, crate::Error> {
+        Self::bind_cid(CID_ANY, port)
+    }
+
+    /// Bind to `port` of the context identifier `cid`.
+    pub fn bind_cid(cid: u32, port: u32) -> Result<Self, crate::Error> {
+        let socket = Socket::new(Domain::VSOCK, Type::STREAM.cloexec(), None)?;
+        socket.bind(&SockAddr::vsock(cid, port))?;
+        socket.listen(1024)?;
+        socket.set_nonblocking(true)?;
+        Ok(VsockIncoming {
+            listener: AsyncFd::new(socket)?,
+        })
+    }
+}
+
+impl Stream for VsockIncoming {
+    type Item = Result<VsockStream, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let mut guard = ready!(self.listener.poll_read_ready(cx))?;
+            match guard.try_io(|listener| listener.get_ref().accept()) {
+                Ok(accepted) => {
+                    let stream = accepted.and_then(|(socket, _)| VsockStream::new(socket));
+                    return Poll::Ready(Some(stream));
+                }
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
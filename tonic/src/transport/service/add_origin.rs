@@ -1,3 +1,4 @@
+use crate::request::CallAuthority;
 use crate::transport::BoxFuture;
 use http::uri::Authority;
 use http::uri::Scheme;
@@ -10,10 +11,11 @@ pub(crate) struct AddOrigin<T> {
     inner: T,
     scheme: Option<Scheme>,
     authority: Option<Authority>,
+    path_prefix: Option<String>,
 }
 
 impl<T> AddOrigin<T> {
-    pub(crate) fn new(inner: T, origin: Uri) -> Self {
+    pub(crate) fn new(inner: T, origin: Uri, path_prefix: Option<String>) -> Self {
         let http::uri::Parts {
             scheme, authority, ..
         } = origin.into_parts();
@@ -22,6 +24,7 @@ impl<T> AddOrigin<T> {
             inner,
             scheme,
             authority,
+            path_prefix,
         }
     }
 }
@@ -55,7 +58,20 @@ where
             let mut uri: http::uri::Parts = head.uri.into();
             // Update the URI parts, setting hte scheme and authority
             uri.scheme = self.scheme.clone();
-            uri.authority = self.authority.clone();
+            uri.authority = match head.extensions.get::<CallAuthority>() {
+                Some(CallAuthority(authority)) => Some(authority.clone()),
+                None => self.authority.clone(),
+            };
+            // Prefix the path of the method with the path of the services
+            if let (Some(prefix), Some(path_and_query)) = (&self.path_prefix, &uri.path_and_query) {
+                match format!("{}{}", prefix, path_and_query).parse() {
+                    Ok(path_and_query) => uri.path_and_query = Some(path_and_query),
+                    Err(_) => {
+                        let err = crate::transport::Error::new_invalid_uri();
+                        return Box::pin(async move { Err::<Self::Response, _>(err.into()) });
+                    }
+                }
+            }
 
             http::Uri::from_parts(uri).expect("valid uri")
         };
@@ -67,3 +83,34 @@ where
         Box::pin(async move { fut.await.map_err(Into::into) })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn uri(path_prefix: Option<&str>) -> Uri {
+        let svc = tower::service_fn(|req: Request<()>| async move {
+            Ok::<_, crate::Error>(req.uri().clone())
+        });
+        let mut svc = AddOrigin::new(
+            svc,
+            Uri::from_static("http://example.com"),
+            path_prefix.map(str::to_owned),
+        );
+        let req = Request::get("/test.Echo/Call").body(()).unwrap();
+        svc.call(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn sets_the_origin() {
+        assert_eq!(uri(None).await, "http://example.com/test.Echo/Call");
+    }
+
+    #[tokio::test]
+    async fn prefixes_the_path() {
+        assert_eq!(
+            uri(Some("/api/v1")).await,
+            "http://example.com/api/v1/test.Echo/Call"
+        );
+    }
+}
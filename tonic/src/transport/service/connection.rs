@@ -1,7 +1,19 @@
-use super::{grpc_timeout::GrpcTimeout, reconnect::Reconnect, AddOrigin, UserAgent};
+use super::{
+    grpc_timeout::GrpcTimeout,
+    health::HealthCheck,
+    idle::IdleTimeout,
+    load::ReportedLoad,
+    pool::Pool,
+    reconnect::{Connectivity, Reconnect},
+    warm::Warm,
+    AddOrigin, ClientStats, StatsConnector, UserAgent,
+};
 use crate::{
     body::BoxBody,
-    transport::{BoxFuture, Endpoint},
+    transport::{
+        channel::{ConnectBackoff, OutlierDetection},
+        BoxFuture, Endpoint, Executor,
+    },
 };
 use http::Uri;
 use hyper::client::conn::Builder;
@@ -14,6 +26,7 @@ use std::{
 use tokio::io::{AsyncRead, AsyncWrite};
 use tower::load::Load;
 use tower::{
+    buffer::Buffer,
     layer::Layer,
     limit::{concurrency::ConcurrencyLimitLayer, rate::RateLimitLayer},
     util::BoxService,
@@ -22,10 +35,16 @@ use tower::{
 use tower_service::Service;
 
 pub(crate) type Request = http::Request<BoxBody>;
-pub(crate) type Response = http::Response<hyper::Body>;
+pub(crate) type Response = http::Response<BoxBody>;
 
 pub(crate) struct Connection {
     inner: BoxService<Request, Response, crate::Error>,
+    connectivity: Connectivity,
+    outlier_detection: Option<OutlierDetection>,
+    load: Option<ReportedLoad>,
+    idle_timeout: Option<IdleTimeout>,
+    connect_backoff: ConnectBackoff,
+    health_check: Option<HealthCheck>,
 }
 
 impl Connection {
@@ -36,45 +55,93 @@ impl Connection {
         C::Future: Unpin + Send,
         C::Response: AsyncRead + AsyncWrite + HyperConnection + Unpin + Send + 'static,
     {
-        let mut settings = Builder::new()
-            .http2_initial_stream_window_size(endpoint.init_stream_window_size)
-            .http2_initial_connection_window_size(endpoint.init_connection_window_size)
-            .http2_only(true)
-            .http2_keep_alive_interval(endpoint.http2_keep_alive_interval)
-            .executor(endpoint.executor.clone())
-            .clone();
-
-        if let Some(val) = endpoint.http2_keep_alive_timeout {
-            settings.http2_keep_alive_timeout(val);
-        }
+        let connector = StatsConnector::new(connector, endpoint.stats_handler.clone());
+        let connectivity = Connectivity::new(
+            endpoint.uri.clone(),
+            endpoint.on_state_change.clone(),
+            endpoint.state_tracker.clone(),
+        );
+        let idle_timeout = endpoint.idle_timeout.map(|timeout| {
+            IdleTimeout::new(timeout, endpoint.executor.clone(), endpoint.timer.clone())
+        });
 
-        if let Some(val) = endpoint.http2_keep_alive_while_idle {
-            settings.http2_keep_alive_while_idle(val);
-        }
+        let pool_size = endpoint.pool_size.max(endpoint.min_connections);
+        let warm = |i| i < endpoint.min_connections;
+        let inner = if pool_size == 1 {
+            let conn = member(
+                connector,
+                &endpoint,
+                &idle_timeout,
+                &connectivity,
+                is_lazy,
+                warm(0),
+            );
+            layered(conn, &endpoint)
+        } else {
+            // The connections of the pool share the connector.
+            let (connector, worker) = Buffer::pair(connector.map_err(Into::into), pool_size);
+            endpoint.executor.execute(Box::pin(worker));
+
+            // Only the first connection reports its state changes, as those
+            // of the endpoint.
+            let members = (0..pool_size).map(|i| {
+                let connectivity = if i == 0 {
+                    connectivity.clone()
+                } else {
+                    Connectivity::new(endpoint.uri.clone(), None, endpoint.state_tracker.clone())
+                };
+                let conn = member(
+                    connector.clone(),
+                    &endpoint,
+                    &idle_timeout,
+                    &connectivity,
+                    is_lazy,
+                    warm(i),
+                );
+                (conn, connectivity)
+            });
+            let pool = Pool::new(members.collect::<Vec<_>>());
+            layered(pool, &endpoint)
+        };
 
-        if let Some(val) = endpoint.http2_adaptive_window {
-            settings.http2_adaptive_window(val);
+        Self {
+            inner,
+            connectivity,
+            outlier_detection: endpoint.outlier_detection.clone(),
+            load: endpoint.load_from_trailers.clone().map(ReportedLoad::new),
+            idle_timeout,
+            connect_backoff: endpoint.connect_backoff.clone(),
+            health_check: None,
         }
+    }
 
-        let stack = ServiceBuilder::new()
-            .layer_fn(|s| {
-                let origin = endpoint.origin.as_ref().unwrap_or(&endpoint.uri).clone();
+    pub(crate) fn connectivity(&self) -> &Connectivity {
+        &self.connectivity
+    }
 
-                AddOrigin::new(s, origin)
-            })
-            .layer_fn(|s| UserAgent::new(s, endpoint.user_agent.clone()))
-            .layer_fn(|s| GrpcTimeout::new(s, endpoint.timeout))
-            .option_layer(endpoint.concurrency_limit.map(ConcurrencyLimitLayer::new))
-            .option_layer(endpoint.rate_limit.map(|(l, d)| RateLimitLayer::new(l, d)))
-            .into_inner();
+    pub(crate) fn connect_backoff(&self) -> &ConnectBackoff {
+        &self.connect_backoff
+    }
 
-        let connector = HyperConnect::new(connector, settings);
-        let conn = Reconnect::new(connector, endpoint.uri.clone(), is_lazy);
+    pub(crate) fn outlier_detection(&self) -> Option<&OutlierDetection> {
+        self.outlier_detection.as_ref()
+    }
 
-        let inner = stack.layer(conn);
+    /// Only use the connection while `health_check` finds its service
+    /// serving.
+    pub(crate) fn with_health_check(self, health_check: HealthCheck) -> Self {
+        Connection {
+            health_check: Some(health_check),
+            ..self
+        }
+    }
 
-        Self {
-            inner: BoxService::new(inner),
+    /// Returns `Poll::Pending` while the health check of the connection, if
+    /// any, doesn't find its service serving.
+    pub(crate) fn poll_serving(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &mut self.health_check {
+            Some(health_check) => health_check.poll_serving(cx, &self.connectivity),
+            None => Poll::Ready(()),
         }
     }
 
@@ -99,6 +166,139 @@ impl Connection {
     }
 }
 
+/// Apply the layers configured on `endpoint` to `inner`.
+fn layered<S>(inner: S, endpoint: &Endpoint) -> BoxService<Request, Response, crate::Error>
+where
+    S: Service<Request, Response = Response, Error = crate::Error> + Send + 'static,
+    S::Future: Send,
+{
+    let stack =
+        ServiceBuilder::new()
+            .layer_fn(|s| {
+                let origin = endpoint.origin.as_ref().unwrap_or(&endpoint.uri).clone();
+
+                AddOrigin::new(s, origin, endpoint.path_prefix.clone())
+            })
+            .layer_fn(|s| UserAgent::new(s, endpoint.user_agent.clone()))
+            .layer_fn(|s| GrpcTimeout::new(s, endpoint.timeout, endpoint.timer.clone()))
+            .option_layer(endpoint.concurrency_limit.map(ConcurrencyLimitLayer::new))
+            .option_layer(endpoint.rate_limit.map(|(l, d)| RateLimitLayer::new(l, d)))
+            .option_layer(endpoint.stats_handler.clone().map(|handler| {
+                tower::layer::layer_fn(move |s| ClientStats::new(s, handler.clone()))
+            }))
+            .into_inner();
+
+    BoxService::new(stack.layer(inner))
+}
+
+/// Connect to `endpoint` with `connector`, reconnecting once the
+/// connection is closed.
+///
+/// `warm` connections are made in the background rather than when a call
+/// needs them.
+fn member<C>(
+    connector: C,
+    endpoint: &Endpoint,
+    idle_timeout: &Option<IdleTimeout>,
+    connectivity: &Connectivity,
+    is_lazy: bool,
+    warm: bool,
+) -> BoxService<Request, Response, crate::Error>
+where
+    C: Service<Uri> + Send + 'static,
+    C::Error: Into<crate::Error> + Send,
+    C::Future: Unpin + Send,
+    C::Response: AsyncRead + AsyncWrite + HyperConnection + Unpin + Send + 'static,
+{
+    let executor = match idle_timeout {
+        Some(idle_timeout) => idle_timeout.executor(connectivity.clone()),
+        None => endpoint.executor.clone(),
+    };
+
+    let mut settings = Builder::new()
+        .http2_initial_stream_window_size(endpoint.init_stream_window_size)
+        .http2_initial_connection_window_size(endpoint.init_connection_window_size)
+        .http2_only(true)
+        .http2_keep_alive_interval(endpoint.http2_keep_alive_interval)
+        .executor(executor)
+        .clone();
+
+    if let Some(val) = endpoint.http2_keep_alive_timeout {
+        settings.http2_keep_alive_timeout(val);
+    }
+
+    if let Some(val) = endpoint.http2_keep_alive_while_idle {
+        settings.http2_keep_alive_while_idle(val);
+    }
+
+    if let Some(val) = endpoint.http2_adaptive_window {
+        settings.http2_adaptive_window(val);
+    }
+
+    let connector = HyperConnect::new(connector, settings)
+        .map_response(|send_request| send_request.map_response(boxed_response));
+    #[cfg(feature = "http3")]
+    if let Some(http3) = &endpoint.http3 {
+        let connector = http3
+            .clone()
+            .with_fallback(connector, endpoint.executor.clone());
+        return reconnecting(connector, endpoint, connectivity, is_lazy, warm);
+    }
+    reconnecting(connector, endpoint, connectivity, is_lazy, warm)
+}
+
+fn reconnecting<M, S>(
+    connector: M,
+    endpoint: &Endpoint,
+    connectivity: &Connectivity,
+    is_lazy: bool,
+    warm: bool,
+) -> BoxService<Request, Response, crate::Error>
+where
+    M: Service<Uri, Response = S> + Send + 'static,
+    M::Error: Into<crate::Error> + Send,
+    M::Future: Unpin + Send,
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+    crate::Error: From<M::Error> + From<S::Error>,
+{
+    if warm {
+        let connector = Warm::new(
+            connector,
+            endpoint.uri.clone(),
+            endpoint.connect_backoff.clone(),
+            &endpoint.executor,
+        );
+        BoxService::new(reconnect(connector, endpoint, connectivity, is_lazy))
+    } else {
+        BoxService::new(reconnect(connector, endpoint, connectivity, is_lazy))
+    }
+}
+
+fn reconnect<M>(
+    connector: M,
+    endpoint: &Endpoint,
+    connectivity: &Connectivity,
+    is_lazy: bool,
+) -> Reconnect<M, Uri>
+where
+    M: Service<Uri>,
+    M::Error: Into<crate::Error>,
+{
+    Reconnect::new(
+        connector,
+        endpoint.uri.clone(),
+        is_lazy,
+        endpoint.resolve_now.clone(),
+        connectivity.clone(),
+        endpoint.connect_backoff.clone(),
+    )
+}
+
+fn boxed_response(response: http::Response<hyper::Body>) -> Response {
+    response.map(crate::body::boxed)
+}
+
 impl Service<Request> for Connection {
     type Response = Response;
     type Error = crate::Error;
@@ -109,15 +309,22 @@ impl Service<Request> for Connection {
     }
 
     fn call(&mut self, req: Request) -> Self::Future {
-        self.inner.call(req)
+        let mut future = self.inner.call(req);
+        if let Some(load) = &self.load {
+            future = load.track(future);
+        }
+        if let Some(idle_timeout) = &self.idle_timeout {
+            future = idle_timeout.track(future);
+        }
+        future
     }
 }
 
 impl Load for Connection {
-    type Metric = usize;
+    type Metric = f64;
 
     fn load(&self) -> Self::Metric {
-        0
+        self.load.as_ref().map(ReportedLoad::get).unwrap_or(0.0)
     }
 }
 
@@ -1,5 +1,6 @@
 use super::super::BoxFuture;
 use super::io::BoxedIo;
+use super::proxy::Proxy;
 #[cfg(feature = "tls")]
 use super::tls::TlsConnector;
 use http::Uri;
@@ -10,6 +11,7 @@ use tower_service::Service;
 
 pub(crate) struct Connector<C> {
     inner: C,
+    proxy: Option<Proxy>,
     #[cfg(feature = "tls")]
     tls: Option<TlsConnector>,
     // When connecting to a URI with the https scheme, assume that the server
@@ -21,11 +23,13 @@ pub(crate) struct Connector<C> {
 impl<C> Connector<C> {
     pub(crate) fn new(
         inner: C,
+        proxy: Option<Proxy>,
         #[cfg(feature = "tls")] tls: Option<TlsConnector>,
         #[cfg(feature = "tls-roots-common")] assume_http2: bool,
     ) -> Self {
         Self {
             inner,
+            proxy,
             #[cfg(feature = "tls")]
             tls,
             #[cfg(feature = "tls-roots-common")]
@@ -72,10 +76,17 @@ where
 
         #[cfg(feature = "tls")]
         let is_https = uri.scheme_str() == Some("https");
-        let connect = self.inner.make_connection(uri);
+        let proxy = self.proxy.clone();
+        let connect = match &proxy {
+            Some(proxy) => self.inner.make_connection(proxy.uri().clone()),
+            None => self.inner.make_connection(uri.clone()),
+        };
 
         Box::pin(async move {
-            let io = connect.await?;
+            let mut io = connect.await?;
+            if let Some(proxy) = proxy {
+                proxy.tunnel(&mut io, &uri).await?;
+            }
 
             #[cfg(feature = "tls")]
             {
@@ -1,5 +1,5 @@
-use super::connection::Connection;
-use crate::transport::Endpoint;
+use super::{connection::Connection, health::HealthCheck};
+use crate::transport::{channel::StateTracker, Endpoint};
 
 use std::{
     hash::Hash,
@@ -15,11 +15,12 @@ type DiscoverResult<K, S, E> = Result<Change<K, S>, E>;
 
 pub(crate) struct DynamicServiceStream<K: Hash + Eq + Clone> {
     changes: Receiver<Change<K, Endpoint>>,
+    tracker: StateTracker,
 }
 
 impl<K: Hash + Eq + Clone> DynamicServiceStream<K> {
-    pub(crate) fn new(changes: Receiver<Change<K, Endpoint>>) -> Self {
-        Self { changes }
+    pub(crate) fn new(changes: Receiver<Change<K, Endpoint>>, tracker: StateTracker) -> Self {
+        Self { changes, tracker }
     }
 }
 
@@ -27,19 +28,11 @@ impl<K: Hash + Eq + Clone> Stream for DynamicServiceStream<K> {
     type Item = DiscoverResult<K, Connection, crate::Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let c = &mut self.changes;
-        match Pin::new(&mut *c).poll_recv(cx) {
+        match Pin::new(&mut self.changes).poll_recv(cx) {
             Poll::Pending | Poll::Ready(None) => Poll::Pending,
             Poll::Ready(Some(change)) => match change {
                 Change::Insert(k, endpoint) => {
-                    let mut http = hyper::client::connect::HttpConnector::new();
-                    http.set_nodelay(endpoint.tcp_nodelay);
-                    http.set_keepalive(endpoint.tcp_keepalive);
-                    http.set_connect_timeout(endpoint.connect_timeout);
-                    http.enforce_http(false);
-
-                    let connection = Connection::lazy(endpoint.connector(http), endpoint);
-                    let change = Ok(Change::Insert(k, connection));
+                    let change = Ok(Change::Insert(k, lazy_connection(endpoint, &self.tracker)));
                     Poll::Ready(Some(change))
                 }
                 Change::Remove(k) => Poll::Ready(Some(Ok(Change::Remove(k)))),
@@ -49,3 +42,55 @@ impl<K: Hash + Eq + Clone> Stream for DynamicServiceStream<K> {
 }
 
 impl<K: Hash + Eq + Clone> Unpin for DynamicServiceStream<K> {}
+
+/// Create a connection to a balanced endpoint, connecting on first use.
+///
+/// The state of the connection counts towards the state of the channel
+/// `tracker` belongs to.
+pub(crate) fn lazy_connection(mut endpoint: Endpoint, tracker: &StateTracker) -> Connection {
+    endpoint.state_tracker = Some(tracker.clone());
+
+    let health_check = endpoint.health_check.take().map(|service| {
+        // The watch has a connection of its own, that isn't balanced nor
+        // observed as the endpoint.
+        let mut watched = endpoint.clone();
+        watched.on_state_change = None;
+        watched.state_tracker = None;
+        watched.stats_handler = None;
+        watched.load_from_trailers = None;
+        watched.outlier_detection = None;
+        watched.timeout = None;
+        watched.idle_timeout = None;
+        watched.pool_size = 1;
+        watched.min_connections = 0;
+
+        HealthCheck::new(
+            service,
+            connect(watched),
+            endpoint.connect_backoff.clone(),
+            endpoint.executor.clone(),
+        )
+    });
+
+    let connection = connect(endpoint);
+    match health_check {
+        Some(health_check) => connection.with_health_check(health_check),
+        None => connection,
+    }
+}
+
+fn connect(endpoint: Endpoint) -> Connection {
+    #[cfg(unix)]
+    if let Some(path) = &endpoint.unix_path {
+        let unix = super::UnixConnector::new(path.clone());
+        return Connection::lazy(endpoint.connector(unix), endpoint);
+    }
+    #[cfg(windows)]
+    if let Some(path) = &endpoint.named_pipe_path {
+        let pipe = super::NamedPipeConnector::new(path.clone(), endpoint.impersonation_level);
+        return Connection::lazy(endpoint.connector(pipe), endpoint);
+    }
+
+    let tcp = endpoint.tcp_connector(endpoint.connect_timeout);
+    Connection::lazy(endpoint.connector(tcp), endpoint)
+}
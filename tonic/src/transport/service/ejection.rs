@@ -0,0 +1,228 @@
+use super::{
+    outlier::{EjectionBudget, Outliers},
+    reconnect::ConnectivityState,
+    Connection,
+};
+use crate::{body::BoxBody, transport::channel::EndpointState};
+use http::{Request, Response};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+use tokio::time::Sleep;
+use tokio_stream::Stream;
+use tower::{
+    discover::{Change, Discover},
+    load::Load,
+};
+use tower_service::Service;
+
+/// Failed connection attempts in a row after which an endpoint is unhealthy.
+const FAILURES_BEFORE_UNHEALTHY: u32 = 3;
+
+/// Wraps the connections of `D` so that those whose attempts keep failing,
+/// or are outliers, stop being ready until a backoff expires.
+///
+/// Endpoints are connected to again right away until they are unhealthy,
+/// rather than failing the requests sent to them during the backoff of
+/// their connection. Unhealthy endpoints then follow their
+/// [`ConnectBackoff`](crate::transport::channel::ConnectBackoff).
+///
+/// Balancers only send requests to ready endpoints, so unhealthy endpoints
+/// are skipped meanwhile.
+pub(crate) struct Ejecting<D> {
+    discover: D,
+    budget: Arc<EjectionBudget>,
+}
+
+impl<D> Ejecting<D> {
+    pub(crate) fn new(discover: D) -> Self {
+        Ejecting {
+            discover,
+            budget: Arc::default(),
+        }
+    }
+}
+
+impl<D> Stream for Ejecting<D>
+where
+    D: Discover<Service = Connection> + Unpin,
+{
+    type Item = Result<Change<D::Key, Ejectable>, D::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let change = ready!(Pin::new(&mut self.discover).poll_discover(cx));
+        Poll::Ready(change.map(|change| {
+            change.map(|change| match change {
+                Change::Insert(key, connection) => {
+                    Change::Insert(key, Ejectable::new(connection, &self.budget))
+                }
+                Change::Remove(key) => Change::Remove(key),
+            })
+        }))
+    }
+}
+
+pub(crate) struct Ejectable {
+    connection: Connection,
+    unhealthy: Option<Pin<Box<Sleep>>>,
+    outliers: Option<Outliers>,
+}
+
+impl Ejectable {
+    pub(crate) fn new(connection: Connection, budget: &Arc<EjectionBudget>) -> Self {
+        let outliers = connection
+            .outlier_detection()
+            .map(|config| Outliers::new(config.clone(), budget.join()));
+
+        Ejectable {
+            connection,
+            unhealthy: None,
+            outliers,
+        }
+    }
+}
+
+impl Service<Request<BoxBody>> for Ejectable {
+    type Response = Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = <Connection as Service<Request<BoxBody>>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.connection.poll_serving(cx));
+
+        if let Some(outliers) = &mut self.outliers {
+            ready!(outliers.poll_admitted(cx, self.connection.connectivity()));
+        }
+
+        if let Some(backoff) = &mut self.unhealthy {
+            ready!(backoff.as_mut().poll(cx));
+            self.unhealthy = None;
+            self.connection.connectivity().reset();
+        }
+
+        let connectivity = self.connection.connectivity();
+        if connectivity.failures() < FAILURES_BEFORE_UNHEALTHY {
+            connectivity.reset();
+        }
+
+        ready!(self.connection.poll_ready(cx))?;
+
+        let connectivity = self.connection.connectivity();
+        let failures = connectivity.failures();
+        if connectivity.get() == ConnectivityState::TransientFailure
+            && failures >= FAILURES_BEFORE_UNHEALTHY
+        {
+            let backoff = self
+                .connection
+                .connect_backoff()
+                .backoff(failures - FAILURES_BEFORE_UNHEALTHY + 1);
+            tracing::debug!(
+                "endpoint unhealthy after {} failed connection attempts, retrying in {:?}",
+                failures,
+                backoff
+            );
+            connectivity.report(EndpointState::Unhealthy);
+
+            self.unhealthy = Some(Box::pin(tokio::time::sleep(backoff)));
+            // Register for the wake up at the end of the backoff.
+            return self.poll_ready(cx);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        let future = self.connection.call(request);
+        match &self.outliers {
+            Some(outliers) => outliers.record(future),
+            None => future,
+        }
+    }
+}
+
+impl Load for Ejectable {
+    type Metric = <Connection as Load>::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.connection.load()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::{
+        channel::{EndpointState, LoadBalancingPolicy},
+        Channel, Endpoint,
+    };
+    use http::Request;
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+    use tower::ServiceExt;
+
+    async fn send(channel: &Channel) -> Result<String, crate::transport::Error> {
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        let response = channel.clone().oneshot(request).await?;
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        Ok(String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn skips_unhealthy_endpoints() {
+        let make = hyper::service::make_service_fn(|_| async {
+            Ok::<_, Infallible>(hyper::service::service_fn(|_| async {
+                Ok::<_, Infallible>(http::Response::new(hyper::Body::from("up")))
+            }))
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let up = server.local_addr();
+        tokio::spawn(server);
+
+        let unused = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let down = unused.local_addr().unwrap();
+        drop(unused);
+
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let endpoints = [down, up].into_iter().map(|addr| {
+            let states = states.clone();
+            Endpoint::from_shared(format!("http://{}", addr))
+                .unwrap()
+                .on_state_change(move |change| {
+                    if change.uri().port_u16() == Some(down.port()) {
+                        states.lock().unwrap().push(change.state());
+                    }
+                })
+        });
+        let channel = Channel::balance_list_with_policy(endpoints, LoadBalancingPolicy::RoundRobin);
+
+        let mut failures = 0;
+        for _ in 0..10 {
+            if send(&channel).await.is_err() {
+                failures += 1;
+            }
+        }
+        assert!(failures < super::FAILURES_BEFORE_UNHEALTHY);
+        for _ in 0..10 {
+            assert_eq!(send(&channel).await.unwrap(), "up");
+        }
+
+        let states = states.lock().unwrap();
+        assert_eq!(states.last(), Some(&EndpointState::Unhealthy));
+        assert_eq!(
+            states
+                .iter()
+                .filter(|&&state| state == EndpointState::TransientFailure)
+                .count() as u32,
+            super::FAILURES_BEFORE_UNHEALTHY
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use crate::metadata::GRPC_TIMEOUT_HEADER;
+use super::{timer::Timer, SharedTimer};
+use crate::{
+    metadata::GRPC_TIMEOUT_HEADER,
+    transport::{deadline, BoxFuture},
+};
 use http::{HeaderMap, HeaderValue, Request};
 use pin_project::pin_project;
 use std::{
@@ -8,20 +12,33 @@ use std::{
     task::{ready, Context, Poll},
     time::Duration,
 };
-use tokio::time::Sleep;
+use tokio::time::Instant;
 use tower_service::Service;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub(crate) struct GrpcTimeout<S> {
     inner: S,
     server_timeout: Option<Duration>,
+    timer: SharedTimer,
+    scoped: bool,
 }
 
 impl<S> GrpcTimeout<S> {
-    pub(crate) fn new(inner: S, server_timeout: Option<Duration>) -> Self {
+    pub(crate) fn new(inner: S, server_timeout: Option<Duration>, timer: SharedTimer) -> Self {
         Self {
             inner,
             server_timeout,
+            timer,
+            scoped: false,
+        }
+    }
+
+    /// Poll the calls with their deadline as the [`deadline::current`] one,
+    /// for servers to propagate it.
+    pub(crate) fn scoped(self) -> Self {
+        Self {
+            scoped: true,
+            ..self
         }
     }
 }
@@ -56,12 +73,11 @@ where
             }
         };
 
+        let deadline = timeout_duration.map(|timeout| Instant::now() + timeout);
         ResponseFuture {
             inner: self.inner.call(req),
-            sleep: timeout_duration
-                .map(tokio::time::sleep)
-                .map(Some)
-                .unwrap_or(None),
+            sleep: deadline.map(|deadline| self.timer.sleep_until(deadline.into_std())),
+            scope: deadline.filter(|_| self.scoped),
         }
     }
 }
@@ -70,8 +86,8 @@ where
 pub(crate) struct ResponseFuture<F> {
     #[pin]
     inner: F,
-    #[pin]
-    sleep: Option<Sleep>,
+    sleep: Option<BoxFuture<'static, ()>>,
+    scope: Option<Instant>,
 }
 
 impl<F, Res, E> Future for ResponseFuture<F>
@@ -84,12 +100,13 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
 
-        if let Poll::Ready(result) = this.inner.poll(cx) {
+        let inner = this.inner;
+        if let Poll::Ready(result) = deadline::sync_scope(*this.scope, || inner.poll(cx)) {
             return Poll::Ready(result.map_err(Into::into));
         }
 
-        if let Some(sleep) = this.sleep.as_pin_mut() {
-            ready!(sleep.poll(cx));
+        if let Some(sleep) = this.sleep {
+            ready!(sleep.as_mut().poll(cx));
             return Poll::Ready(Err(TimeoutExpired(()).into()));
         }
 
@@ -104,7 +121,7 @@ const SECONDS_IN_MINUTE: u64 = 60;
 /// the value we attempted to parse.
 ///
 /// Follows the [gRPC over HTTP2 spec](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md).
-fn try_parse_grpc_timeout(
+pub(crate) fn try_parse_grpc_timeout(
     headers: &HeaderMap<HeaderValue>,
 ) -> Result<Option<Duration>, &HeaderValue> {
     match headers.get(GRPC_TIMEOUT_HEADER) {
@@ -161,6 +178,12 @@ fn try_parse_grpc_timeout(
 #[derive(Debug)]
 pub struct TimeoutExpired(());
 
+impl TimeoutExpired {
+    pub(crate) fn new() -> Self {
+        TimeoutExpired(())
+    }
+}
+
 impl fmt::Display for TimeoutExpired {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Timeout expired")
@@ -187,6 +210,38 @@ mod tests {
         try_parse_grpc_timeout(&hm).map_err(|e| e.clone())
     }
 
+    #[tokio::test]
+    async fn uses_timers() {
+        use std::sync::{Arc, Mutex};
+
+        /// Fires at once, recording the deadlines it was given.
+        #[derive(Clone, Default)]
+        struct Immediate(Arc<Mutex<Vec<std::time::Instant>>>);
+
+        impl Timer for Immediate {
+            fn sleep_until(&self, deadline: std::time::Instant) -> BoxFuture<'static, ()> {
+                self.0.lock().unwrap().push(deadline);
+                Box::pin(std::future::ready(()))
+            }
+        }
+
+        let timer = Immediate::default();
+        let never =
+            tower::service_fn(|_: Request<()>| std::future::pending::<Result<(), crate::Error>>());
+        let mut svc = GrpcTimeout::new(
+            never,
+            Some(Duration::from_secs(60)),
+            SharedTimer::new(timer.clone()),
+        );
+
+        let before = std::time::Instant::now();
+        let err = svc.call(Request::new(())).await.unwrap_err();
+        assert!(err.is::<TimeoutExpired>());
+        let deadlines = timer.0.lock().unwrap();
+        assert_eq!(deadlines.len(), 1);
+        assert!(deadlines[0] >= before + Duration::from_secs(60));
+    }
+
     #[test]
     fn test_hours() {
         let parsed_duration = setup_map_try_parse(Some("3H")).unwrap().unwrap();
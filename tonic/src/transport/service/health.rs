@@ -0,0 +1,235 @@
+use super::{reconnect::Connectivity, wire, Connection, SharedExec};
+use crate::{
+    client::Grpc,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    transport::{
+        channel::{ConnectBackoff, EndpointState},
+        BoxFuture, Executor,
+    },
+    Code, Request, Status,
+};
+use bytes::Buf;
+use http::uri::PathAndQuery;
+use std::{
+    future::{poll_fn, Future},
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+use tokio::sync::oneshot;
+
+const WATCH: &str = "/grpc.health.v1.Health/Watch";
+
+/// The `SERVING` value of `grpc.health.v1.HealthCheckResponse.ServingStatus`.
+const SERVING: u64 = 1;
+
+/// Watches whether a service of an endpoint is `SERVING`, with the
+/// `grpc.health.v1.Health/Watch` method of its server.
+///
+/// The watch starts when the health is first polled, in a spawned task
+/// that stops once the check is dropped. The stream of the watch is opened
+/// again after a backoff when it fails.
+pub(crate) struct HealthCheck {
+    shared: Arc<Shared>,
+    watch: Option<BoxFuture<'static, ()>>,
+    executor: SharedExec,
+    /// Whether `NotServing` is the last state reported.
+    not_serving: bool,
+    _stop: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct Shared {
+    serving: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Shared {
+    fn set(&self, serving: bool) {
+        self.serving.store(serving, Ordering::Release);
+        if serving {
+            if let Some(waker) = self.waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl HealthCheck {
+    /// Watch the health of `service` over `connection`, a connection of its
+    /// own to the endpoint.
+    pub(crate) fn new(
+        service: String,
+        connection: Connection,
+        backoff: ConnectBackoff,
+        executor: SharedExec,
+    ) -> Self {
+        let shared = Arc::<Shared>::default();
+        let (stop, stopped) = oneshot::channel();
+
+        let watch = watch(Grpc::new(connection), service, shared.clone(), backoff);
+        let watch = Box::pin(async move {
+            let mut watch = pin!(watch);
+            let mut stopped = pin!(stopped);
+            poll_fn(|cx| match stopped.as_mut().poll(cx) {
+                Poll::Ready(_) => Poll::Ready(()),
+                Poll::Pending => watch.as_mut().poll(cx),
+            })
+            .await
+        });
+
+        HealthCheck {
+            shared,
+            watch: Some(watch),
+            executor,
+            not_serving: false,
+            _stop: stop,
+        }
+    }
+
+    /// Returns `Poll::Pending` while the service isn't `SERVING`, reporting
+    /// the endpoint as `NotServing` meanwhile.
+    pub(crate) fn poll_serving(
+        &mut self,
+        cx: &mut Context<'_>,
+        connectivity: &Connectivity,
+    ) -> Poll<()> {
+        if let Some(watch) = self.watch.take() {
+            self.executor.execute(watch);
+        }
+
+        if !self.shared.serving.load(Ordering::Acquire) {
+            *self.shared.waker.lock().unwrap() = Some(cx.waker().clone());
+            // The service may have become serving before the waker was set.
+            if !self.shared.serving.load(Ordering::Acquire) {
+                if !self.not_serving {
+                    self.not_serving = true;
+                    connectivity.report(EndpointState::NotServing);
+                }
+                return Poll::Pending;
+            }
+        }
+
+        if self.not_serving {
+            self.not_serving = false;
+            connectivity.report(connectivity.get().into());
+        }
+        Poll::Ready(())
+    }
+}
+
+async fn watch(
+    mut client: Grpc<Connection>,
+    service: String,
+    shared: Arc<Shared>,
+    backoff: ConnectBackoff,
+) {
+    let mut failures = 0;
+    loop {
+        let mut received = false;
+        let result = watch_once(&mut client, &service, &shared, &mut received).await;
+        shared.set(false);
+
+        match result {
+            Err(status) if status.code() == Code::Unimplemented => {
+                tracing::warn!(
+                    "the server doesn't implement {}, not checking the health of its endpoint",
+                    WATCH
+                );
+                shared.set(true);
+                return;
+            }
+            Err(status) => tracing::debug!("health check failed: {}", status),
+            Ok(()) => tracing::debug!("health check ended"),
+        }
+
+        if received {
+            failures = 0;
+        }
+        failures += 1;
+        tokio::time::sleep(backoff.backoff(failures)).await;
+    }
+}
+
+async fn watch_once(
+    client: &mut Grpc<Connection>,
+    service: &str,
+    shared: &Shared,
+    received: &mut bool,
+) -> Result<(), Status> {
+    client
+        .ready()
+        .await
+        .map_err(|e| Status::unavailable(format!("health check not ready: {}", e)))?;
+    let mut statuses = client
+        .server_streaming(
+            Request::new(service.to_owned()),
+            PathAndQuery::from_static(WATCH),
+            HealthCodec,
+        )
+        .await?
+        .into_inner();
+
+    while let Some(status) = statuses.message().await? {
+        *received = true;
+        shared.set(status == SERVING);
+    }
+    Ok(())
+}
+
+/// The codec of the `grpc.health.v1` messages of health checks, encoding
+/// the service name of `HealthCheckRequest`s and decoding the status of
+/// `HealthCheckResponse`s.
+#[derive(Debug, Clone, Copy)]
+struct HealthCodec;
+
+impl Codec for HealthCodec {
+    type Encode = String;
+    type Decode = u64;
+
+    type Encoder = Self;
+    type Decoder = Self;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        *self
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        *self
+    }
+}
+
+impl Encoder for HealthCodec {
+    type Item = String;
+    type Error = Status;
+
+    fn encode(&mut self, service: String, buf: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        // The `service` field, skipped when it has its default value.
+        if !service.is_empty() {
+            wire::put_bytes(buf, 1, service.as_bytes());
+        }
+        Ok(())
+    }
+}
+
+impl Decoder for HealthCodec {
+    type Item = u64;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<u64>, Status> {
+        let message = buf.copy_to_bytes(buf.remaining());
+
+        // Fields missing from the message have their default value, `UNKNOWN`.
+        let mut status = 0;
+        wire::for_each_field(&message, |number, field| {
+            if let (1, wire::Field::Varint(value)) = (number, field) {
+                status = value;
+            }
+        })
+        .ok_or_else(|| Status::internal("invalid HealthCheckResponse"))?;
+        Ok(Some(status))
+    }
+}
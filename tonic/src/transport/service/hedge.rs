@@ -0,0 +1,365 @@
+use super::retry::{
+    is_retry_allowed, is_unprocessed, pushback, record, result_code, Attempts, Pushback,
+    ReadyBackoff,
+};
+use crate::{
+    body::BoxBody,
+    transport::channel::{HedgingPolicy, RetryThrottle},
+    transport::{BoxFuture, TimeoutExpired},
+};
+use http::{Request, Response};
+use std::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::Poll,
+    time::Duration,
+};
+use tokio::time::{Instant, Sleep};
+use tower::ServiceExt;
+use tower_service::Service;
+
+type AttemptFuture = BoxFuture<'static, Result<Response<BoxBody>, crate::Error>>;
+
+/// Send `request` through `svc`, hedging it according to `policy`.
+///
+/// Every attempt goes through its own clone of `svc`, so hedged attempts
+/// are multiplexed as new streams alongside the original one. Once an
+/// attempt commits the call the others are dropped, which cancels their
+/// streams.
+///
+/// Hedged attempts are not sent while `throttle` doesn't allow them. With
+/// `wait_for_ready`, attempts the server didn't process are sent again once
+/// the channel is ready or after a backoff, without counting as hedged
+/// attempts.
+pub(crate) async fn call<S>(
+    svc: S,
+    request: Request<BoxBody>,
+    policy: HedgingPolicy,
+    throttle: Option<RetryThrottle>,
+    mut wait_for_ready: Option<ReadyBackoff>,
+) -> Result<Response<BoxBody>, crate::Error>
+where
+    S: Service<Request<BoxBody>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Error: Into<crate::Error>,
+    S::Future: Send,
+{
+    let template = svc.clone();
+    let mut first = Some(svc);
+    let mut attempts = Attempts::new(request);
+
+    let mut in_flight: Vec<AttemptFuture> = Vec::new();
+    let mut waiting: Vec<BoxFuture<'static, ()>> = Vec::new();
+    let mut sent = 0;
+    let mut transparent = true;
+    let mut timer = Some(sleep(Duration::ZERO));
+    let mut last = None;
+
+    poll_fn(move |cx| loop {
+        let mut rearmed = false;
+
+        if let Some(fired) = timer
+            .as_mut()
+            .map(|timer| timer.as_mut().poll(cx).is_ready())
+        {
+            if fired {
+                timer = None;
+
+                let request = if sent == 0
+                    || (attempts.is_replayable() && is_retry_allowed(throttle.as_ref()))
+                {
+                    attempts.next(Instant::now())
+                } else {
+                    None
+                };
+
+                match request {
+                    Some(request) => {
+                        let svc = first.take().unwrap_or_else(|| template.clone());
+                        in_flight.push(send(svc, request));
+                        sent += 1;
+
+                        if sent < policy.max_attempts {
+                            timer = Some(sleep(policy.hedging_delay));
+                            rearmed = true;
+                        }
+                    }
+                    // Out of time, throttled or unable to replay the body,
+                    // stop hedging.
+                    None => sent = policy.max_attempts,
+                }
+            }
+        }
+
+        let mut idx = 0;
+        while idx < waiting.len() {
+            if waiting[idx].as_mut().poll(cx).is_pending() {
+                idx += 1;
+                continue;
+            }
+            drop(waiting.swap_remove(idx));
+
+            let attempt = match attempts.next(Instant::now()) {
+                Some(request) => {
+                    tracing::debug!("channel not ready, sending wait-for-ready attempt again");
+                    send(template.clone(), request)
+                }
+                None => Box::pin(async { Err(TimeoutExpired::new().into()) }) as AttemptFuture,
+            };
+            in_flight.push(attempt);
+            rearmed = true;
+        }
+
+        let mut idx = 0;
+        while idx < in_flight.len() {
+            let result = match in_flight[idx].as_mut().poll(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => {
+                    idx += 1;
+                    continue;
+                }
+            };
+            drop(in_flight.swap_remove(idx));
+
+            // Wait-for-ready attempts the server didn't process are sent
+            // again once the channel is ready.
+            if let (Some(ready), true) = (&mut wait_for_ready, is_unprocessed(&result)) {
+                if attempts.is_replayable() {
+                    waiting.push(Box::pin(ready.wait(attempts.deadline())));
+                    rearmed = true;
+                    continue;
+                }
+            }
+
+            // An attempt the server didn't process is replayed right away,
+            // without counting as a hedged attempt.
+            if transparent && is_unprocessed(&result) && attempts.is_replayable() {
+                if let Some(request) = attempts.next(Instant::now()) {
+                    tracing::debug!("transparently retrying unprocessed request");
+                    transparent = false;
+                    in_flight.push(send(template.clone(), request));
+                    rearmed = true;
+                    continue;
+                }
+            }
+
+            let code = result_code(&result);
+            let non_fatal = matches!(code, Some(code) if policy.is_non_fatal(code));
+            record(throttle.as_ref(), code, non_fatal);
+
+            match code {
+                Some(code) if non_fatal => {
+                    let pushback = match &result {
+                        Ok(response) => pushback(response.headers()),
+                        Err(_) => Pushback::None,
+                    };
+
+                    // A non-fatal failure sends the next attempt right
+                    // away, unless the server asks otherwise.
+                    if sent < policy.max_attempts {
+                        match pushback {
+                            Pushback::None => timer = Some(sleep(Duration::ZERO)),
+                            Pushback::Delay(delay) => timer = Some(sleep(delay)),
+                            Pushback::Stop => {
+                                timer = None;
+                                sent = policy.max_attempts;
+                            }
+                        }
+                        rearmed = true;
+                    }
+
+                    tracing::debug!(?code, sent, "hedged attempt failed");
+                    last = Some(result);
+                }
+                _ => {
+                    attempts.commit();
+                    return Poll::Ready(result);
+                }
+            }
+        }
+
+        if in_flight.is_empty() && waiting.is_empty() && timer.is_none() {
+            attempts.commit();
+            return Poll::Ready(last.take().expect("at least one attempt was sent"));
+        }
+
+        if !rearmed {
+            return Poll::Pending;
+        }
+    })
+    .await
+}
+
+fn sleep(duration: Duration) -> Pin<Box<Sleep>> {
+    Box::pin(tokio::time::sleep(duration))
+}
+
+fn send<S>(mut svc: S, request: Request<BoxBody>) -> AttemptFuture
+where
+    S: Service<Request<BoxBody>, Response = Response<BoxBody>> + Send + 'static,
+    S::Error: Into<crate::Error>,
+    S::Future: Send,
+{
+    Box::pin(async move {
+        svc.ready()
+            .await
+            .map_err(Into::into)?
+            .call(request)
+            .await
+            .map_err(Into::into)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{transport::channel::ChannelState, Code, Status};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    fn response(code: Code) -> Response<BoxBody> {
+        let mut response = Response::new(BoxBody::default());
+        Status::new(code, "")
+            .add_header(response.headers_mut())
+            .unwrap();
+        response
+    }
+
+    /// A service answering the `n`th attempt with the code of `answers[n]`,
+    /// after its delay.
+    fn service(
+        answers: Vec<(Duration, Code)>,
+    ) -> (
+        impl Service<
+                Request<BoxBody>,
+                Response = Response<BoxBody>,
+                Error = crate::Error,
+                Future = impl Send,
+            > + Clone
+            + Send,
+        Arc<AtomicUsize>,
+    ) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let answers = Arc::new(answers);
+        let svc = {
+            let count = count.clone();
+            tower::service_fn(move |_: Request<BoxBody>| {
+                let (delay, code) = answers[count.fetch_add(1, Ordering::SeqCst)];
+                async move {
+                    tokio::time::sleep(delay).await;
+                    Ok::<_, crate::Error>(response(code))
+                }
+            })
+        };
+        (svc, count)
+    }
+
+    fn code(response: &Response<BoxBody>) -> Code {
+        Status::from_header_map(response.headers()).unwrap().code()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fastest_attempt_wins() {
+        let (svc, count) = service(vec![
+            (Duration::from_secs(10), Code::Internal),
+            (Duration::from_millis(10), Code::Ok),
+        ]);
+        let policy = HedgingPolicy::new(2).hedging_delay(Duration::from_millis(100));
+
+        let response = call(
+            svc,
+            Request::new(crate::body::empty_body()),
+            policy,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(code(&response), Code::Ok);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fatal_status_stops_hedging() {
+        let (svc, count) = service(vec![
+            (Duration::from_millis(10), Code::InvalidArgument),
+            (Duration::from_millis(10), Code::Ok),
+        ]);
+        let policy = HedgingPolicy::new(2).hedging_delay(Duration::from_millis(100));
+
+        let response = call(
+            svc,
+            Request::new(crate::body::empty_body()),
+            policy,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(code(&response), Code::InvalidArgument);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn non_fatal_status_sends_next_attempt() {
+        let (svc, count) = service(vec![
+            (Duration::from_millis(10), Code::Unavailable),
+            (Duration::from_millis(10), Code::Unavailable),
+            (Duration::from_millis(10), Code::Unavailable),
+        ]);
+        let policy = HedgingPolicy::new(3)
+            .hedging_delay(Duration::from_secs(100))
+            .non_fatal_status_code(Code::Unavailable);
+
+        let start = Instant::now();
+        let response = call(
+            svc,
+            Request::new(crate::body::empty_body()),
+            policy,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(code(&response), Code::Unavailable);
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_ready_between_unprocessed_attempts() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let svc = {
+            let count = count.clone();
+            tower::service_fn(move |_: Request<BoxBody>| {
+                let attempt = count.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt < 3 {
+                        Err(h2::Error::from(h2::Reason::REFUSED_STREAM).into())
+                    } else {
+                        Ok::<_, crate::Error>(response(Code::Ok))
+                    }
+                }
+            })
+        };
+        let policy = HedgingPolicy::new(2).hedging_delay(Duration::from_secs(100));
+        let (_tx, state) = tokio::sync::watch::channel(ChannelState::TransientFailure);
+
+        let response = call(
+            svc,
+            Request::new(crate::body::empty_body()),
+            policy,
+            None,
+            Some(ReadyBackoff::new(state)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(code(&response), Code::Ok);
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
+}
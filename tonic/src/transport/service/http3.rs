@@ -0,0 +1,456 @@
+use super::connection::{Request, Response};
+use super::SharedExec;
+use crate::transport::{BoxFuture, Certificate, Executor, Identity};
+use bytes::{Buf, Bytes};
+use http::{uri::Scheme, Uri};
+use quinn_rustls::{
+    Certificate as RustlsCertificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig,
+};
+use std::{
+    fmt,
+    future::poll_fn,
+    io::Cursor,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+use tower_service::Service;
+
+/// h3 alpn in plain format for rustls.
+const ALPN_H3: &[u8] = b"h3";
+
+type SendRequest = h3::client::SendRequest<h3_quinn::OpenStreams, Bytes>;
+
+/// Connects with HTTP/3 over QUIC.
+#[derive(Clone)]
+pub(crate) struct Http3Connector {
+    config: quinn::ClientConfig,
+    domain: Arc<str>,
+    fallback_timeout: Duration,
+}
+
+impl Http3Connector {
+    pub(crate) fn new(
+        ca_cert: Option<Certificate>,
+        identity: Option<Identity>,
+        domain: &str,
+        fallback_timeout: Duration,
+    ) -> Result<Self, crate::Error> {
+        let mut roots = RootCertStore::empty();
+
+        #[cfg(feature = "tls-roots")]
+        roots.add_parsable_certificates(&rustls_native_certs::load_native_certs()?);
+
+        #[cfg(feature = "tls-webpki-roots")]
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            quinn_rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject.as_ref(),
+                anchor.subject_public_key_info.as_ref(),
+                anchor.name_constraints.as_deref(),
+            )
+        }));
+
+        if let Some(cert) = ca_cert {
+            for cert in rustls_pemfile::certs(&mut Cursor::new(cert)) {
+                roots.add(&RustlsCertificate(cert?.to_vec()))?;
+            }
+        }
+
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let mut config = match identity {
+            Some(identity) => {
+                let (certs, key) = load_identity(identity)?;
+                builder.with_client_auth_cert(certs, key)?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        config.alpn_protocols.push(ALPN_H3.into());
+        Ok(Self {
+            config: quinn::ClientConfig::new(Arc::new(config)),
+            domain: domain.trim_start_matches('[').trim_end_matches(']').into(),
+            fallback_timeout,
+        })
+    }
+
+    /// Connect with HTTP/3, or with `http2` when a QUIC connection can't be
+    /// made within the fallback timeout.
+    pub(crate) fn with_fallback<C>(self, http2: C, executor: SharedExec) -> Http3Connect<C> {
+        Http3Connect {
+            http3: self,
+            http2: Arc::new(Mutex::new(http2)),
+            executor,
+        }
+    }
+
+    async fn connect(&self, uri: &Uri, executor: &SharedExec) -> Result<Http3Send, crate::Error> {
+        let host = uri.host().ok_or("missing host")?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = uri.port_u16().unwrap_or(443);
+        let addr = tokio::net::lookup_host((host, port))
+            .await?
+            .next()
+            .ok_or("no address found")?;
+
+        let local: SocketAddr = if addr.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let endpoint = quinn::Endpoint::client(local)?;
+        let connection = endpoint
+            .connect_with(self.config.clone(), addr, &self.domain)?
+            .await?;
+
+        let (mut driver, send_request) =
+            h3::client::new(h3_quinn::Connection::new(connection)).await?;
+        let closed = Arc::new(AtomicBool::new(false));
+        executor.execute(Box::pin({
+            let closed = closed.clone();
+            async move {
+                if let Err(e) = poll_fn(|cx| driver.poll_close(cx)).await {
+                    tracing::debug!("http3 connection error: {}", e);
+                }
+                closed.store(true, Ordering::Release);
+            }
+        }));
+
+        Ok(Http3Send {
+            send_request,
+            closed,
+            executor: executor.clone(),
+            _endpoint: endpoint,
+        })
+    }
+}
+
+impl fmt::Debug for Http3Connector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Http3Connector")
+            .field("domain", &self.domain)
+            .field("fallback_timeout", &self.fallback_timeout)
+            .finish()
+    }
+}
+
+/// The QUIC config of servers accepting HTTP/3 connections.
+pub(crate) fn server_config(identity: Identity) -> Result<quinn::ServerConfig, crate::Error> {
+    let (certs, key) = load_identity(identity)?;
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    config.alpn_protocols.push(ALPN_H3.into());
+    Ok(quinn::ServerConfig::with_crypto(Arc::new(config)))
+}
+
+fn load_identity(identity: Identity) -> Result<(Vec<RustlsCertificate>, PrivateKey), crate::Error> {
+    let certs = rustls_pemfile::certs(&mut Cursor::new(identity.cert))
+        .map(|cert| cert.map(|cert| RustlsCertificate(cert.to_vec())))
+        .collect::<Result<_, _>>()?;
+    let key = rustls_pemfile::private_key(&mut Cursor::new(identity.key))?
+        .ok_or("no private key found")?;
+    Ok((certs, PrivateKey(key.secret_der().to_vec())))
+}
+
+/// The connector of an endpoint preferring HTTP/3, falling back to the
+/// HTTP/2 connections of `C`.
+pub(crate) struct Http3Connect<C> {
+    http3: Http3Connector,
+    // Only connected when HTTP/3 isn't available.
+    http2: Arc<Mutex<C>>,
+    executor: SharedExec,
+}
+
+impl<C, S> Service<Uri> for Http3Connect<C>
+where
+    C: Service<Uri, Response = S> + Send + 'static,
+    C::Error: Into<crate::Error>,
+    C::Future: Send,
+    S: Send + 'static,
+{
+    type Response = Http3OrHttp2<S>;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let http3 = self.http3.clone();
+        let http2 = self.http2.clone();
+        let executor = self.executor.clone();
+
+        Box::pin(async move {
+            let connect = http3.connect(&uri, &executor);
+            match tokio::time::timeout(http3.fallback_timeout, connect).await {
+                Ok(Ok(send)) => return Ok(Http3OrHttp2::Http3(send)),
+                Ok(Err(e)) => tracing::debug!("http3 connection failed: {}", e),
+                Err(_) => tracing::debug!("http3 connection timed out"),
+            }
+
+            let mut http2 = http2.lock().await;
+            let connection = http2
+                .ready()
+                .await
+                .map_err(Into::into)?
+                .call(uri)
+                .await
+                .map_err(Into::into)?;
+            Ok(Http3OrHttp2::Http2(connection))
+        })
+    }
+}
+
+/// A connection made by [`Http3Connect`].
+pub(crate) enum Http3OrHttp2<S> {
+    Http3(Http3Send),
+    Http2(S),
+}
+
+impl<S> Service<Request> for Http3OrHttp2<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Http3OrHttp2::Http3(send) => send.poll_ready(cx),
+            Http3OrHttp2::Http2(send) => send.poll_ready(cx).map_err(Into::into),
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        match self {
+            Http3OrHttp2::Http3(send) => send.call(request),
+            Http3OrHttp2::Http2(send) => {
+                let response = send.call(request);
+                Box::pin(async move { response.await.map_err(Into::into) })
+            }
+        }
+    }
+}
+
+impl<S> fmt::Debug for Http3OrHttp2<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Http3OrHttp2::Http3(_) => f.write_str("Http3"),
+            Http3OrHttp2::Http2(_) => f.write_str("Http2"),
+        }
+    }
+}
+
+/// Sends the requests of an HTTP/3 connection.
+pub(crate) struct Http3Send {
+    send_request: SendRequest,
+    closed: Arc<AtomicBool>,
+    executor: SharedExec,
+    // The endpoint drives the UDP socket of the connection.
+    _endpoint: quinn::Endpoint,
+}
+
+impl Http3Send {
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), crate::Error>> {
+        if self.closed.load(Ordering::Acquire) {
+            return Poll::Ready(Err("http3 connection closed".into()));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> BoxFuture<'static, Result<Response, crate::Error>> {
+        let mut send_request = self.send_request.clone();
+        let executor = self.executor.clone();
+
+        Box::pin(async move {
+            let (mut parts, mut body) = request.into_parts();
+            // HTTP/3 requests are always secured.
+            let mut uri = parts.uri.into_parts();
+            uri.scheme = Some(Scheme::HTTPS);
+            parts.uri = Uri::from_parts(uri)?;
+
+            let stream = send_request
+                .send_request(http::Request::from_parts(parts, ()))
+                .await?;
+            let (mut send, mut recv) = stream.split();
+
+            executor.execute(Box::pin(async move {
+                let sent = async {
+                    while let Some(data) = http_body::Body::data(&mut body).await {
+                        send.send_data(data?).await?;
+                    }
+                    match http_body::Body::trailers(&mut body).await? {
+                        Some(trailers) => send.send_trailers(trailers).await?,
+                        None => send.finish().await?,
+                    }
+                    Ok::<_, crate::Error>(())
+                };
+                if let Err(e) = sent.await {
+                    tracing::debug!("http3 request body error: {}", e);
+                }
+            }));
+
+            let response = recv.recv_response().await?;
+            let (mut sender, body) = hyper::Body::channel();
+            executor.execute(Box::pin(async move {
+                let received = async {
+                    while let Some(mut data) = recv.recv_data().await? {
+                        sender
+                            .send_data(data.copy_to_bytes(data.remaining()))
+                            .await?;
+                    }
+                    if let Some(trailers) = recv.recv_trailers().await? {
+                        sender.send_trailers(trailers).await?;
+                    }
+                    Ok::<_, crate::Error>(())
+                };
+                if let Err(e) = received.await {
+                    tracing::debug!("http3 response body error: {}", e);
+                    sender.abort();
+                }
+            }));
+
+            Ok(response.map(|()| crate::body::boxed(body)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::body::BoxBody;
+    use crate::transport::server::{Routes, TcpIncoming};
+    use crate::transport::{Certificate, Endpoint, Http3Config, Identity, Server};
+    use bytes::{Buf, Bytes};
+    use http_body::Body as _;
+    use std::{net::SocketAddr, time::Duration};
+    use tower::{service_fn, Service, ServiceExt};
+
+    const CA: &[u8] = include_bytes!("../../../../examples/data/tls/ca.pem");
+    const CERT: &[u8] = include_bytes!("../../../../examples/data/tls/server.pem");
+    const KEY: &[u8] = include_bytes!("../../../../examples/data/tls/server.key");
+
+    /// Serve HTTP/3 requests, echoing their body with the trailers of an OK
+    /// status.
+    fn serve_http3() -> SocketAddr {
+        let config = super::server_config(Identity::from_pem(CERT, KEY)).unwrap();
+        let endpoint = quinn::Endpoint::server(config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Some(connecting) = endpoint.accept().await {
+                let connection = h3_quinn::Connection::new(connecting.await.unwrap());
+                let mut connection = h3::server::Connection::<_, Bytes>::new(connection)
+                    .await
+                    .unwrap();
+                while let Ok(Some((request, mut stream))) = connection.accept().await {
+                    let mut body = Vec::new();
+                    while let Some(data) = stream.recv_data().await.unwrap() {
+                        body.extend_from_slice(data.chunk());
+                    }
+                    let response = http::Response::builder()
+                        .header("content-type", "application/grpc")
+                        .header("path", request.uri().path())
+                        .body(())
+                        .unwrap();
+                    stream.send_response(response).await.unwrap();
+                    stream.send_data(body.into()).await.unwrap();
+                    let mut trailers = http::HeaderMap::new();
+                    trailers.insert("grpc-status", "0".parse().unwrap());
+                    stream.send_trailers(trailers).await.unwrap();
+                }
+            }
+        });
+        addr
+    }
+
+    fn request(body: &'static [u8]) -> http::Request<BoxBody> {
+        let body = http_body::Full::new(Bytes::from_static(body))
+            .map_err(|e| match e {})
+            .boxed_unsync();
+        http::Request::post("http://localhost/test.Service/Call")
+            .header("content-type", "application/grpc")
+            .body(body)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn calls_over_http3() {
+        let addr = serve_http3();
+        let config = Http3Config::new().ca_certificate(Certificate::from_pem(CA));
+        let mut channel = Endpoint::from_shared(format!("https://localhost:{}", addr.port()))
+            .unwrap()
+            .http3(config)
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            let response = channel
+                .ready()
+                .await
+                .unwrap()
+                .call(request(b"ping"))
+                .await
+                .unwrap();
+            assert_eq!(response.headers()["path"], "/test.Service/Call");
+            let mut body = response.into_body();
+            assert_eq!(body.data().await.unwrap().unwrap(), "ping");
+            while body.data().await.is_some() {}
+            let trailers = body.trailers().await.unwrap().unwrap();
+            assert_eq!(trailers["grpc-status"], "0");
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_http2() {
+        let svc = service_fn(|request: http::Request<hyper::Body>| async move {
+            let mut response = crate::Status::new(crate::Code::Ok, "").to_http();
+            let version = format!("{:?}", request.version());
+            response
+                .headers_mut()
+                .insert("version", version.parse().unwrap());
+            Ok::<_, std::convert::Infallible>(response)
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_routes(Routes::default().fallback_service(svc))
+                .serve_with_incoming(incoming),
+        );
+
+        let config = Http3Config::new()
+            .ca_certificate(Certificate::from_pem(CA))
+            .fallback_timeout(Duration::from_millis(100));
+        let mut channel = Endpoint::from_shared(format!("http://127.0.0.1:{}", addr.port()))
+            .unwrap()
+            .http3(config)
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let response = channel
+            .ready()
+            .await
+            .unwrap()
+            .call(request(b""))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["version"], "HTTP/2.0");
+    }
+}
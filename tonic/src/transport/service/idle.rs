@@ -0,0 +1,198 @@
+use super::{
+    load::{on_trailers, ResponseFuture},
+    reconnect::{Connectivity, ConnectivityState},
+    timer::Timer,
+    SharedExec, SharedTimer,
+};
+use crate::transport::{BoxFuture, Executor};
+use http_body::Body as _;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::Poll,
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Closes the connection of an endpoint once no call used it for a while.
+///
+/// Calls are active until their response body is over. The tasks the
+/// connection spawns on its executor, the connection itself first, are
+/// dropped once the connection has been idle for the timeout. `Reconnect`
+/// then sees the connection is closed and connects again on the next call.
+#[derive(Clone)]
+pub(crate) struct IdleTimeout {
+    shared: Arc<Shared>,
+    /// Runs the tasks of the connection.
+    executor: SharedExec,
+}
+
+struct Shared {
+    timeout: Duration,
+    timer: SharedTimer,
+    active: AtomicUsize,
+    last_active: Mutex<Instant>,
+}
+
+/// Counts a call as active until dropped.
+struct Active(Arc<Shared>);
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        *self.0.last_active.lock().unwrap() = Instant::now();
+        self.0.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl IdleTimeout {
+    pub(crate) fn new(timeout: Duration, executor: SharedExec, timer: SharedTimer) -> Self {
+        let shared = Shared {
+            timeout,
+            timer,
+            active: AtomicUsize::new(0),
+            last_active: Mutex::new(Instant::now()),
+        };
+        IdleTimeout {
+            shared: Arc::new(shared),
+            executor,
+        }
+    }
+
+    /// Wrap the executor of the connection, so its tasks are dropped once
+    /// idle.
+    pub(crate) fn executor(&self, connectivity: Connectivity) -> SharedExec {
+        SharedExec::new(IdleExec {
+            idle: self.clone(),
+            connectivity,
+        })
+    }
+
+    /// Count the call of `future` as active until its response body is
+    /// over.
+    pub(crate) fn track(&self, future: ResponseFuture) -> ResponseFuture {
+        self.shared.active.fetch_add(1, Ordering::AcqRel);
+        let active = Active(self.shared.clone());
+
+        Box::pin(async move {
+            let response = future.await?;
+            if response.body().is_end_stream() {
+                return Ok(response);
+            }
+            Ok(on_trailers(response, move |_| drop(active)))
+        })
+    }
+
+    /// Resolves once no call was active for the timeout, counting from
+    /// `since` at the earliest.
+    ///
+    /// Connections are made by `poll_ready`, before the call using them is
+    /// counted as active.
+    async fn expired(shared: Arc<Shared>, since: Instant) {
+        loop {
+            let last_active = (*shared.last_active.lock().unwrap()).max(since);
+            let active = shared.active.load(Ordering::Acquire) > 0;
+            if !active && last_active.elapsed() >= shared.timeout {
+                return;
+            }
+
+            let deadline = if active {
+                Instant::now() + shared.timeout
+            } else {
+                last_active + shared.timeout
+            };
+            shared.timer.sleep_until(deadline.into_std()).await;
+        }
+    }
+}
+
+#[derive(Clone)]
+struct IdleExec {
+    idle: IdleTimeout,
+    connectivity: Connectivity,
+}
+
+impl Executor<BoxFuture<'static, ()>> for IdleExec {
+    fn execute(&self, fut: BoxFuture<'static, ()>) {
+        let idle = IdleTimeout::expired(self.idle.shared.clone(), Instant::now());
+        let connectivity = self.connectivity.clone();
+
+        self.idle.executor.execute(Box::pin(async move {
+            let mut fut = fut;
+            let mut idle = std::pin::pin!(idle);
+            let expired = std::future::poll_fn(|cx| {
+                if fut.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(false);
+                }
+                idle.as_mut().poll(cx).map(|()| true)
+            });
+
+            if expired.await {
+                tracing::debug!("closing idle connection");
+                connectivity.set(ConnectivityState::Idle);
+            }
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::{channel::ChannelState, Endpoint};
+    use http::Request;
+    use std::{
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn reconnects_after_idle_timeout() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let make = {
+            let connections = connections.clone();
+            hyper::service::make_service_fn(move |_| {
+                connections.fetch_add(1, Ordering::SeqCst);
+                async {
+                    Ok::<_, Infallible>(hyper::service::service_fn(|_| async {
+                        Ok::<_, Infallible>(http::Response::new(hyper::Body::from("body")))
+                    }))
+                }
+            })
+        };
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .idle_timeout(Duration::from_millis(100))
+            .connect_lazy();
+        let send = || async {
+            let request = Request::builder()
+                .uri("/")
+                .body(crate::body::empty_body())
+                .unwrap();
+            let response = channel.clone().oneshot(request).await.unwrap();
+            hyper::body::to_bytes(response.into_body()).await.unwrap();
+        };
+
+        send().await;
+        send().await;
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        let state = channel.wait_for_state_change(ChannelState::Ready);
+        let state = tokio::time::timeout(Duration::from_secs(5), state).await;
+        assert_eq!(state.unwrap(), ChannelState::Idle);
+
+        send().await;
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{
+    body::BoxBody,
+    transport::{channel::LoadParser, BoxFuture},
+};
+use http::{HeaderMap, Response};
+use http_body::Body;
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+};
+
+pub(crate) type ResponseFuture = BoxFuture<'static, Result<Response<BoxBody>, crate::Error>>;
+
+/// The last load an endpoint reported in the trailers of its responses.
+#[derive(Clone)]
+pub(crate) struct ReportedLoad {
+    parser: LoadParser,
+    /// The bits of the load as a `f64`.
+    load: Arc<AtomicU64>,
+}
+
+impl ReportedLoad {
+    pub(crate) fn new(parser: LoadParser) -> Self {
+        ReportedLoad {
+            parser,
+            load: Arc::new(AtomicU64::new(0f64.to_bits())),
+        }
+    }
+
+    pub(crate) fn get(&self) -> f64 {
+        f64::from_bits(self.load.load(Ordering::Acquire))
+    }
+
+    fn set(&self, load: f64) {
+        self.load.store(load.to_bits(), Ordering::Release);
+    }
+
+    /// Read the load reported by the response of `future`.
+    ///
+    /// The trailers are only received after the whole body, so they are read
+    /// as the body of the returned response is polled for them.
+    pub(crate) fn track(&self, future: ResponseFuture) -> ResponseFuture {
+        let this = self.clone();
+
+        Box::pin(async move {
+            let response = future.await?;
+
+            // Trailers-only responses carry their trailers in the headers.
+            if let Some(load) = this.parser.parse(response.headers()) {
+                this.set(load);
+            }
+            if response.body().is_end_stream() {
+                return Ok(response);
+            }
+
+            Ok(on_trailers(response, move |trailers| {
+                if let Some(load) = trailers.and_then(|trailers| this.parser.parse(trailers)) {
+                    this.set(load);
+                }
+            }))
+        })
+    }
+}
+
+/// Call `on_trailers` once the body of `response` is over, see
+/// [`OnTrailers`].
+pub(crate) fn on_trailers(
+    response: Response<BoxBody>,
+    on_trailers: impl FnOnce(Option<&HeaderMap>) + Send + 'static,
+) -> Response<BoxBody> {
+    response.map(|body| OnTrailers::new(body, on_trailers).boxed_unsync())
+}
+
+/// A body calling `on_trailers` with its trailers once they are received.
+///
+/// `on_trailers` is dropped without being called if the body fails or is
+/// dropped first.
+#[pin_project]
+pub(crate) struct OnTrailers<B, F> {
+    #[pin]
+    inner: B,
+    on_trailers: Option<F>,
+}
+
+impl<B, F> OnTrailers<B, F> {
+    pub(crate) fn new(inner: B, on_trailers: F) -> Self {
+        OnTrailers {
+            inner,
+            on_trailers: Some(on_trailers),
+        }
+    }
+}
+
+impl<B, F> Body for OnTrailers<B, F>
+where
+    B: Body,
+    F: FnOnce(Option<&HeaderMap>),
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let data = ready!(this.inner.poll_data(cx));
+        if let Some(Err(_)) = data {
+            this.on_trailers.take();
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx));
+        let on_trailers = this.on_trailers.take();
+        if let (Ok(trailers), Some(on_trailers)) = (&trailers, on_trailers) {
+            on_trailers(trailers.as_ref());
+        }
+        Poll::Ready(trailers)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OnTrailers;
+    use crate::{
+        transport::{channel::StateTracker, service::discover::lazy_connection, Endpoint},
+        Status,
+    };
+    use bytes::Bytes;
+    use http::{HeaderMap, Request};
+    use http_body::Body;
+    use std::{convert::Infallible, sync::Arc};
+    use tower::{load::Load, Service, ServiceExt};
+
+    #[tokio::test]
+    async fn reads_load_from_trailers() {
+        let make = hyper::service::make_service_fn(|_| async {
+            Ok::<_, Infallible>(hyper::service::service_fn(|_| async {
+                let (mut tx, body) = hyper::Body::channel();
+                tokio::spawn(async move {
+                    tx.send_data("hello".into()).await.unwrap();
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert("load", "0.75".parse().unwrap());
+                    tx.send_trailers(trailers).await.unwrap();
+                });
+                Ok::<_, Infallible>(http::Response::new(body))
+            }))
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let endpoint = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .load_from_trailers(|trailers| trailers.get("load")?.to_str().ok()?.parse().ok());
+        let mut connection = lazy_connection(endpoint, &StateTracker::new().0);
+        assert_eq!(connection.load(), 0.0);
+
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        let response = connection
+            .ready()
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+        let mut body = response.into_body();
+        assert_eq!(hyper::body::to_bytes(&mut body).await.unwrap(), "hello");
+        let trailers = http_body::Body::trailers(&mut body).await.unwrap().unwrap();
+
+        assert_eq!(trailers["load"], "0.75");
+        assert_eq!(connection.load(), 0.75);
+    }
+
+    #[tokio::test]
+    async fn drops_callback_on_errors_and_drop() {
+        let guard = Arc::new(());
+
+        let failing = tokio_stream::iter([Err::<Bytes, _>(Status::internal("failed"))]);
+        let callback = {
+            let guard = guard.clone();
+            move |_: Option<&HeaderMap>| drop(guard)
+        };
+        let mut body = OnTrailers::new(hyper::Body::wrap_stream(failing), callback);
+        assert!(body.data().await.unwrap().is_err());
+        assert_eq!(Arc::strong_count(&guard), 1);
+
+        let (_tx, pending) = hyper::Body::channel();
+        let callback = {
+            let guard = guard.clone();
+            move |_: Option<&HeaderMap>| drop(guard)
+        };
+        drop(OnTrailers::new(pending, callback));
+        assert_eq!(Arc::strong_count(&guard), 1);
+    }
+}
@@ -2,25 +2,73 @@ mod add_origin;
 mod connection;
 mod connector;
 mod discover;
+mod ejection;
 pub(crate) mod executor;
 pub(crate) mod grpc_timeout;
+mod health;
+pub(crate) mod hedge;
+#[cfg(feature = "http3")]
+mod http3;
+mod idle;
 mod io;
+mod load;
+#[cfg(windows)]
+mod named_pipe;
+mod outlier;
+mod pick_first;
+mod pool;
+mod proxy;
 mod reconnect;
+pub(crate) mod retry;
+mod round_robin;
 mod router;
+mod stats;
+mod tcp;
+pub(crate) mod timer;
 #[cfg(feature = "tls")]
 mod tls;
+#[cfg(unix)]
+mod unix;
 mod user_agent;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod vsock;
+mod warm;
+mod weighted;
+mod wire;
 
 pub(crate) use self::add_origin::AddOrigin;
 pub(crate) use self::connection::Connection;
 pub(crate) use self::connector::Connector;
 pub(crate) use self::discover::DynamicServiceStream;
+pub(crate) use self::ejection::Ejecting;
 pub(crate) use self::executor::SharedExec;
 pub(crate) use self::grpc_timeout::GrpcTimeout;
-pub(crate) use self::io::ServerIo;
+#[cfg(feature = "http3")]
+pub(crate) use self::http3::{server_config as http3_server_config, Http3Connector};
+pub(crate) use self::io::{BoxedIo, ServerIo};
+#[cfg(windows)]
+pub use self::named_pipe::ImpersonationLevel;
+#[cfg(windows)]
+pub(crate) use self::named_pipe::{named_pipe_path, NamedPipeConnector};
+pub(crate) use self::pick_first::PickFirst;
+pub(crate) use self::proxy::{EnvProxies, Proxy, ProxyConfig};
+pub(crate) use self::reconnect::ConnectivityState;
+pub(crate) use self::round_robin::RoundRobin;
+pub(crate) use self::stats::{ClientStats, ServerStats, StatsConnector};
+pub(crate) use self::tcp::{TcpConnector, TcpOptions};
+pub(crate) use self::timer::SharedTimer;
 #[cfg(feature = "tls")]
 pub(crate) use self::tls::{TlsAcceptor, TlsConnector};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) use self::unix::abstract_path;
+#[cfg(unix)]
+pub(crate) use self::unix::UnixConnector;
 pub(crate) use self::user_agent::UserAgent;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::vsock::VsockStream;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) use self::vsock::{vsock_address, VsockConnector};
+pub(crate) use self::weighted::WeightedBalance;
 
 pub use self::router::Routes;
 pub use self::router::RoutesBuilder;
@@ -0,0 +1,104 @@
+use crate::transport::BoxFuture;
+use http::Uri;
+use std::{
+    ffi::OsString,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeClient};
+use tower_service::Service;
+
+/// `ERROR_PIPE_BUSY`, returned while every instance of the pipe is connected.
+const ERROR_PIPE_BUSY: i32 = 231;
+
+/// The delay before opening a busy pipe again.
+const BUSY_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// How much of its client's security context a named pipe server may act
+/// with.
+///
+/// See [Impersonation Levels] for the details of each level.
+///
+/// [Impersonation Levels]: https://docs.microsoft.com/en-us/windows/win32/api/winnt/ne-winnt-security_impersonation_level
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImpersonationLevel {
+    /// The server can neither identify nor impersonate the client.
+    Anonymous,
+    /// The server can identify the client, and check its permissions, but
+    /// can't impersonate it.
+    #[default]
+    Identification,
+    /// The server can impersonate the client on the local machine.
+    Impersonation,
+    /// The server can impersonate the client on remote machines.
+    Delegation,
+}
+
+impl ImpersonationLevel {
+    /// The `SECURITY_*` flag of the level, for `CreateFile`.
+    fn qos_flags(self) -> u32 {
+        match self {
+            ImpersonationLevel::Anonymous => 0,
+            ImpersonationLevel::Identification => 1 << 16,
+            ImpersonationLevel::Impersonation => 2 << 16,
+            ImpersonationLevel::Delegation => 3 << 16,
+        }
+    }
+}
+
+/// Connects to the named pipe at `path`, whatever the uri.
+#[derive(Debug, Clone)]
+pub(crate) struct NamedPipeConnector {
+    path: OsString,
+    impersonation_level: ImpersonationLevel,
+}
+
+impl NamedPipeConnector {
+    pub(crate) fn new(path: OsString, impersonation_level: ImpersonationLevel) -> Self {
+        NamedPipeConnector {
+            path,
+            impersonation_level,
+        }
+    }
+}
+
+impl Service<Uri> for NamedPipeConnector {
+    type Response = NamedPipeClient;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Uri) -> Self::Future {
+        let path = self.path.clone();
+        let mut options = ClientOptions::new();
+        options.security_qos_flags(self.impersonation_level.qos_flags());
+
+        Box::pin(async move {
+            // The server creates an instance of the pipe for each client, a
+            // busy pipe has an instance again once the server accepted the
+            // last client.
+            loop {
+                match options.open(&path) {
+                    Ok(client) => return Ok(client),
+                    Err(error) if error.raw_os_error() == Some(ERROR_PIPE_BUSY) => {}
+                    Err(error) => return Err(error.into()),
+                }
+                tokio::time::sleep(BUSY_RETRY_DELAY).await;
+            }
+        })
+    }
+}
+
+/// The path of the pipe `name` of the local machine, `\\.\pipe\name`, or
+/// `name` itself when it's already a pipe path.
+pub(crate) fn named_pipe_path(name: &str) -> OsString {
+    if name.starts_with(r"\\") {
+        name.into()
+    } else {
+        format!(r"\\.\pipe\{}", name).into()
+    }
+}
@@ -0,0 +1,278 @@
+use super::reconnect::Connectivity;
+use crate::{
+    body::BoxBody,
+    transport::{
+        channel::{EndpointState, OutlierDetection},
+        BoxFuture,
+    },
+};
+use http::Response;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{ready, Context, Poll},
+};
+use tokio::time::{Instant, Sleep};
+
+/// Counts the endpoints of a balancer, and how many of them are ejected.
+#[derive(Debug, Default)]
+pub(crate) struct EjectionBudget {
+    endpoints: AtomicUsize,
+    ejected: AtomicUsize,
+}
+
+impl EjectionBudget {
+    /// Register a new endpoint of the balancer.
+    pub(crate) fn join(self: &Arc<Self>) -> Member {
+        self.endpoints.fetch_add(1, Ordering::AcqRel);
+        Member {
+            budget: self.clone(),
+            ejected: false,
+        }
+    }
+}
+
+/// An endpoint counted by an [`EjectionBudget`].
+#[derive(Debug)]
+pub(crate) struct Member {
+    budget: Arc<EjectionBudget>,
+    ejected: bool,
+}
+
+impl Member {
+    /// Count the endpoint as ejected, if less than `max_percent` of the
+    /// endpoints are.
+    fn eject(&mut self, max_percent: u32) -> bool {
+        let endpoints = self.budget.endpoints.load(Ordering::Acquire);
+        let ejected = self.budget.ejected.load(Ordering::Acquire);
+        if ejected * 100 >= max_percent as usize * endpoints {
+            return false;
+        }
+
+        self.budget.ejected.fetch_add(1, Ordering::AcqRel);
+        self.ejected = true;
+        true
+    }
+
+    fn readmit(&mut self) {
+        if std::mem::take(&mut self.ejected) {
+            self.budget.ejected.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+impl Drop for Member {
+    fn drop(&mut self) {
+        self.readmit();
+        self.budget.endpoints.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Results {
+    consecutive_failures: u32,
+    requests: u32,
+    failures: u32,
+}
+
+/// Tracks the results of the requests sent to an endpoint, deciding when
+/// it is ejected as configured by [`OutlierDetection`].
+pub(crate) struct Outliers {
+    config: OutlierDetection,
+    member: Member,
+    results: Arc<Mutex<Results>>,
+    interval_start: Instant,
+    /// How many times the endpoint was ejected recently.
+    ejections: u32,
+    ejected: Option<Pin<Box<Sleep>>>,
+}
+
+impl Outliers {
+    pub(crate) fn new(config: OutlierDetection, member: Member) -> Self {
+        Outliers {
+            config,
+            member,
+            results: Default::default(),
+            interval_start: Instant::now(),
+            ejections: 0,
+            ejected: None,
+        }
+    }
+
+    /// Returns `Poll::Pending` while the endpoint is ejected.
+    pub(crate) fn poll_admitted(
+        &mut self,
+        cx: &mut Context<'_>,
+        connectivity: &Connectivity,
+    ) -> Poll<()> {
+        if let Some(ejected) = &mut self.ejected {
+            ready!(ejected.as_mut().poll(cx));
+
+            self.ejected = None;
+            self.member.readmit();
+            *self.results.lock().unwrap() = Results::default();
+            self.interval_start = Instant::now();
+            connectivity.report(connectivity.get().into());
+            return Poll::Ready(());
+        }
+
+        if !self.should_eject() || !self.member.eject(self.config.max_ejection_percent) {
+            return Poll::Ready(());
+        }
+
+        self.ejections += 1;
+        let time = self.config.ejection_time(self.ejections);
+        tracing::debug!("ejecting outlier endpoint for {:?}", time);
+        connectivity.report(EndpointState::Ejected);
+        self.ejected = Some(Box::pin(tokio::time::sleep(time)));
+
+        // Register for the wake up at the end of the ejection.
+        self.poll_admitted(cx, connectivity)
+    }
+
+    fn should_eject(&mut self) -> bool {
+        let mut results = self.results.lock().unwrap();
+
+        if let Some(errors) = self.config.consecutive_errors {
+            if results.consecutive_failures >= errors {
+                return true;
+            }
+        }
+
+        if self.interval_start.elapsed() < self.config.interval {
+            return false;
+        }
+
+        let eject = match self.config.failure_percentage {
+            Some((threshold, min_requests)) => {
+                results.requests >= min_requests.max(1)
+                    && u64::from(results.failures) * 100
+                        >= u64::from(threshold) * u64::from(results.requests)
+            }
+            None => false,
+        };
+
+        if !eject {
+            // Forget about ejections long past.
+            self.ejections = self.ejections.saturating_sub(1);
+            results.requests = 0;
+            results.failures = 0;
+            self.interval_start = Instant::now();
+        }
+
+        eject
+    }
+
+    /// Record the result of `future`.
+    pub(crate) fn record(
+        &self,
+        future: BoxFuture<'static, Result<Response<BoxBody>, crate::Error>>,
+    ) -> BoxFuture<'static, Result<Response<BoxBody>, crate::Error>> {
+        let results = self.results.clone();
+
+        Box::pin(async move {
+            let result = future.await;
+            let failed = match &result {
+                Ok(response) => {
+                    response.status().is_server_error()
+                        || response.headers().get("grpc-status").map(|s| s.as_bytes())
+                            == Some(b"14")
+                }
+                Err(_) => true,
+            };
+
+            let mut results = results.lock().unwrap();
+            results.requests += 1;
+            if failed {
+                results.failures += 1;
+                results.consecutive_failures += 1;
+            } else {
+                results.consecutive_failures = 0;
+            }
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::{
+        channel::{EndpointState, LoadBalancingPolicy, OutlierDetection},
+        Channel, Endpoint,
+    };
+    use http::{Request, StatusCode};
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tower::ServiceExt;
+
+    fn serve(status: StatusCode) -> Endpoint {
+        let make = hyper::service::make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(hyper::service::service_fn(move |_| async move {
+                let mut response = http::Response::new(hyper::Body::empty());
+                *response.status_mut() = status;
+                Ok::<_, Infallible>(response)
+            }))
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        Endpoint::from_shared(format!("http://{}", addr)).unwrap()
+    }
+
+    async fn send(channel: &Channel) -> StatusCode {
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        channel.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn ejects_failing_endpoints() {
+        let config = OutlierDetection::new()
+            .consecutive_errors(2)
+            .base_ejection_time(Duration::from_millis(200))
+            .max_ejection_percent(50);
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let bad = {
+            let states = states.clone();
+            serve(StatusCode::SERVICE_UNAVAILABLE).on_state_change(move |change| {
+                states.lock().unwrap().push(change.state());
+            })
+        };
+        let endpoints = [bad, serve(StatusCode::OK)]
+            .into_iter()
+            .map(|endpoint| endpoint.outlier_detection(config.clone()));
+        let channel = Channel::balance_list_with_policy(endpoints, LoadBalancingPolicy::RoundRobin);
+
+        let mut failures = 0;
+        for _ in 0..20 {
+            if send(&channel).await != StatusCode::OK {
+                failures += 1;
+            }
+        }
+        assert_eq!(failures, 2);
+        assert_eq!(states.lock().unwrap().last(), Some(&EndpointState::Ejected));
+
+        // The endpoint is sent requests again once readmitted.
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let mut failures = 0;
+        for _ in 0..4 {
+            if send(&channel).await != StatusCode::OK {
+                failures += 1;
+            }
+        }
+        assert!(failures > 0);
+        assert!(states.lock().unwrap().contains(&EndpointState::Ready));
+    }
+}
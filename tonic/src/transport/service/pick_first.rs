@@ -0,0 +1,214 @@
+use super::{reconnect::ConnectivityState, Connection};
+use crate::body::BoxBody;
+use http::{Request, Response};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::discover::{Change, Discover};
+use tower_service::Service;
+
+/// Send every request to the first endpoint that can be connected to.
+///
+/// Endpoints are tried in the order they were discovered. Once one of
+/// them is connected it is used until its connection fails, the next
+/// endpoints are then tried in order, wrapping around to the first.
+pub(crate) struct PickFirst<D: Discover> {
+    discover: D,
+    endpoints: Vec<(D::Key, Connection)>,
+    /// The endpoint in use, or being connected to.
+    current: Option<usize>,
+    /// The endpoint the next call is sent to.
+    ready: Option<usize>,
+}
+
+impl<D> PickFirst<D>
+where
+    D: Discover<Service = Connection> + Unpin,
+    D::Error: Into<crate::Error>,
+{
+    pub(crate) fn new(discover: D) -> Self {
+        PickFirst {
+            discover,
+            endpoints: Vec::new(),
+            current: None,
+            ready: None,
+        }
+    }
+
+    fn update(&mut self, cx: &mut Context<'_>) -> Result<(), crate::Error> {
+        while let Poll::Ready(Some(change)) = Pin::new(&mut self.discover).poll_discover(cx) {
+            match change.map_err(Into::into)? {
+                Change::Insert(key, connection) => {
+                    match self.endpoints.iter().position(|(k, _)| *k == key) {
+                        Some(i) => self.endpoints[i].1 = connection,
+                        None => self.endpoints.push((key, connection)),
+                    }
+                }
+                Change::Remove(key) => {
+                    if let Some(i) = self.endpoints.iter().position(|(k, _)| *k == key) {
+                        self.remove(i);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.endpoints.remove(i);
+        self.current = match self.current {
+            Some(current) if current > i => Some(current - 1),
+            Some(current) if current == i => None,
+            current => current,
+        };
+    }
+}
+
+impl<D> Service<Request<BoxBody>> for PickFirst<D>
+where
+    D: Discover<Service = Connection> + Unpin,
+    D::Error: Into<crate::Error>,
+{
+    type Response = Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = <Connection as Service<Request<BoxBody>>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.ready = None;
+        self.update(cx)?;
+
+        if self.endpoints.is_empty() {
+            return Poll::Pending;
+        }
+
+        let mut i = self.current.unwrap_or(0);
+        let mut failed = None;
+
+        for _ in 0..self.endpoints.len() {
+            let connection = &mut self.endpoints[i].1;
+
+            // Give endpoints that failed before another chance, except for
+            // the one that was just being connected to.
+            if self.current != Some(i) {
+                connection.connectivity().reset();
+            }
+
+            match connection.poll_ready(cx) {
+                Poll::Pending => {
+                    self.current = Some(i);
+                    return Poll::Pending;
+                }
+                Poll::Ready(Ok(())) => {
+                    if connection.connectivity().get() != ConnectivityState::TransientFailure {
+                        self.current = Some(i);
+                        self.ready = Some(i);
+                        return Poll::Ready(Ok(()));
+                    }
+                    failed = Some(i);
+                    i += 1;
+                }
+                Poll::Ready(Err(error)) => {
+                    tracing::debug!("pick_first: dropping endpoint: {}", error);
+                    self.remove(i);
+                    if self.endpoints.is_empty() {
+                        return Poll::Pending;
+                    }
+                    if failed.is_some_and(|failed| failed > i) {
+                        failed = failed.map(|failed| failed - 1);
+                    }
+                }
+            }
+
+            if i >= self.endpoints.len() {
+                i = 0;
+            }
+        }
+
+        // Every endpoint failed, let the last one tried return its error and
+        // start over from the first endpoint.
+        self.current = None;
+        match failed {
+            Some(failed) => {
+                self.ready = Some(failed);
+                Poll::Ready(Ok(()))
+            }
+            None => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        let i = self.ready.take().expect("called before ready");
+        self.endpoints[i].1.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::{channel::LoadBalancingPolicy, Channel, Endpoint};
+    use http::Request;
+    use std::{convert::Infallible, net::SocketAddr, time::Duration};
+    use tokio::sync::oneshot;
+    use tower::ServiceExt;
+
+    /// Serve responses naming `name` until the returned sender is dropped.
+    fn serve(name: &'static str) -> (SocketAddr, oneshot::Sender<()>) {
+        let make = hyper::service::make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(hyper::service::service_fn(move |_| async move {
+                Ok::<_, Infallible>(http::Response::new(hyper::Body::from(name)))
+            }))
+        });
+        let (tx, rx) = oneshot::channel::<()>();
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            let _ = rx.await;
+        }));
+        (addr, tx)
+    }
+
+    async fn send(channel: &Channel) -> Option<String> {
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        let response = channel.clone().oneshot(request).await.ok()?;
+        let body = hyper::body::to_bytes(response.into_body()).await.ok()?;
+        Some(String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn sticks_to_first_connected_endpoint() {
+        let unused = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let down = unused.local_addr().unwrap();
+        drop(unused);
+        let (first, shutdown) = serve("first");
+        let (second, _second) = serve("second");
+
+        let endpoints = [down, first, second]
+            .into_iter()
+            .map(|addr| Endpoint::from_shared(format!("http://{}", addr)).unwrap());
+        let channel = Channel::balance_list_with_policy(endpoints, LoadBalancingPolicy::PickFirst);
+
+        for _ in 0..5 {
+            assert_eq!(send(&channel).await.as_deref(), Some("first"));
+        }
+
+        drop(shutdown);
+        let failover = async {
+            while send(&channel).await.as_deref() != Some("second") {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), failover)
+            .await
+            .unwrap();
+        assert_eq!(send(&channel).await.as_deref(), Some("second"));
+    }
+}
@@ -0,0 +1,163 @@
+use super::{
+    load::on_trailers,
+    reconnect::{Connectivity, ConnectivityState},
+};
+use crate::body::BoxBody;
+use http::Response;
+use http_body::Body as _;
+use std::task::{Context, Poll};
+use tower::load::{
+    completion::TrackCompletion,
+    pending_requests::{Handle, PendingRequests},
+    Load,
+};
+use tower_service::Service;
+
+/// Spreads requests over several connections to the same endpoint, each
+/// request going to the ready connection with the fewest requests in
+/// progress.
+///
+/// Connections whose last attempt failed are only used if every ready
+/// connection failed, to return their error.
+pub(crate) struct Pool<S> {
+    members: Vec<(PendingRequests<S, CompleteOnBodyEnd>, Connectivity)>,
+    /// The member the next call is sent to.
+    ready: Option<usize>,
+}
+
+impl<S> Pool<S> {
+    pub(crate) fn new(members: impl IntoIterator<Item = (S, Connectivity)>) -> Self {
+        let members = members
+            .into_iter()
+            .map(|(service, connectivity)| {
+                let service = PendingRequests::new(service, CompleteOnBodyEnd);
+                (service, connectivity)
+            })
+            .collect();
+
+        Pool {
+            members,
+            ready: None,
+        }
+    }
+}
+
+impl<S, Request> Service<Request> for Pool<S>
+where
+    S: Service<Request, Response = Response<BoxBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = <PendingRequests<S, CompleteOnBodyEnd> as Service<Request>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut least_loaded = None;
+        let mut failed = None;
+
+        for (i, (service, connectivity)) in self.members.iter_mut().enumerate() {
+            if service.poll_ready(cx)?.is_pending() {
+                continue;
+            }
+
+            if connectivity.get() == ConnectivityState::TransientFailure {
+                failed = Some(i);
+                continue;
+            }
+
+            let load = service.load();
+            if least_loaded.map(|(_, least)| load < least).unwrap_or(true) {
+                least_loaded = Some((i, load));
+            }
+        }
+
+        self.ready = least_loaded.map(|(i, _)| i).or(failed);
+        match self.ready {
+            Some(_) => Poll::Ready(Ok(())),
+            None => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let i = self.ready.take().expect("called before ready");
+        self.members[i].0.call(request)
+    }
+}
+
+/// Counts a request as in progress until its response body is over, rather
+/// than until its response headers are received, so streaming calls count.
+#[derive(Clone)]
+pub(crate) struct CompleteOnBodyEnd;
+
+impl TrackCompletion<Handle, Response<BoxBody>> for CompleteOnBodyEnd {
+    type Output = Response<BoxBody>;
+
+    fn track_completion(&self, handle: Handle, response: Response<BoxBody>) -> Self::Output {
+        if response.body().is_end_stream() {
+            return response;
+        }
+        on_trailers(response, move |_| drop(handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::Endpoint;
+    use http::Request;
+    use std::{
+        collections::HashSet,
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::sync::mpsc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn spreads_requests_over_connections() {
+        let (received, mut rx) = mpsc::unbounded_channel();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let make = hyper::service::make_service_fn(move |_| {
+            let connection = connections.fetch_add(1, Ordering::SeqCst);
+            let received = received.clone();
+            async move {
+                Ok::<_, Infallible>(hyper::service::service_fn(move |_| {
+                    received.send(connection).unwrap();
+                    // Keep the request in progress.
+                    std::future::pending::<Result<http::Response<hyper::Body>, Infallible>>()
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .pool_size(2)
+            .connect()
+            .await
+            .unwrap();
+
+        // The requests in progress on the first connection send the next
+        // ones to the second connection once it is ready.
+        let mut used = HashSet::new();
+        for _ in 0..100 {
+            let request = Request::builder()
+                .uri("/")
+                .body(crate::body::empty_body())
+                .unwrap();
+            tokio::spawn(channel.clone().oneshot(request));
+            used.insert(rx.recv().await.unwrap());
+            if used.len() == 2 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("requests were only sent to {:?}", used);
+    }
+}
@@ -1,11 +1,20 @@
+use crate::transport::channel::{
+    ConnectBackoff, EndpointState, EndpointStateChange, OnStateChange, ResolveNow, StateTracker,
+};
 use crate::Error;
+use http::Uri;
 use pin_project::pin_project;
 use std::fmt;
 use std::{
     future::Future,
     pin::Pin,
+    sync::{
+        atomic::{AtomicU32, AtomicU8, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
+use tokio::time::Sleep;
 use tower::make::MakeService;
 use tower_service::Service;
 use tracing::trace;
@@ -18,9 +27,15 @@ where
     mk_service: M,
     state: State<M::Future, M::Response>,
     target: Target,
-    error: Option<crate::Error>,
+    /// The error of the last connection attempt, returned to the calls made
+    /// until the backoff expires.
+    error: Option<Arc<crate::Error>>,
+    backoff: Option<Pin<Box<Sleep>>>,
+    connect_backoff: ConnectBackoff,
     has_been_connected: bool,
     is_lazy: bool,
+    resolve_now: Option<ResolveNow>,
+    connectivity: Connectivity,
 }
 
 #[derive(Debug)]
@@ -35,14 +50,32 @@ where
     M: Service<Target>,
     M::Error: Into<Error>,
 {
-    pub(crate) fn new(mk_service: M, target: Target, is_lazy: bool) -> Self {
+    pub(crate) fn new(
+        mk_service: M,
+        target: Target,
+        is_lazy: bool,
+        resolve_now: Option<ResolveNow>,
+        connectivity: Connectivity,
+        connect_backoff: ConnectBackoff,
+    ) -> Self {
         Reconnect {
             mk_service,
             state: State::Idle,
             target,
             error: None,
+            backoff: None,
+            connect_backoff,
             has_been_connected: false,
             is_lazy,
+            resolve_now,
+            connectivity,
+        }
+    }
+
+    /// Let the resolver of the target know its address may be stale.
+    fn resolve_now(&self) {
+        if let Some(resolve_now) = &self.resolve_now {
+            resolve_now.call();
         }
     }
 }
@@ -64,7 +97,18 @@ where
         let mut state;
 
         if self.error.is_some() {
-            return Poll::Ready(Ok(()));
+            let backing_off = match &mut self.backoff {
+                Some(backoff) => backoff.as_mut().poll(cx).is_pending(),
+                None => false,
+            };
+            if backing_off && self.connectivity.get() == ConnectivityState::TransientFailure {
+                return Poll::Ready(Ok(()));
+            }
+
+            // The backoff expired, or the balancer would rather try again
+            // than surface the error, see `Connectivity::reset`.
+            self.error = None;
+            self.backoff = None;
         }
 
         loop {
@@ -81,6 +125,7 @@ where
 
                     let fut = self.mk_service.make_service(self.target.clone());
                     self.state = State::Connecting(fut);
+                    self.connectivity.set(ConnectivityState::Connecting);
                     continue;
                 }
                 State::Connecting(ref mut f) => {
@@ -88,6 +133,7 @@ where
                     match Pin::new(f).poll(cx) {
                         Poll::Ready(Ok(service)) => {
                             state = State::Connected(service);
+                            self.connectivity.set(ConnectivityState::Ready);
                         }
                         Poll::Pending => {
                             trace!("poll_ready; not ready");
@@ -97,13 +143,22 @@ where
                             trace!("poll_ready; error");
 
                             state = State::Idle;
+                            self.resolve_now();
+                            self.connectivity.set(ConnectivityState::TransientFailure);
 
                             if !(self.has_been_connected || self.is_lazy) {
                                 return Poll::Ready(Err(e.into()));
                             } else {
                                 let error = e.into();
-                                tracing::debug!("reconnect::poll_ready: {:?}", error);
-                                self.error = Some(error);
+                                let backoff =
+                                    self.connect_backoff.backoff(self.connectivity.failures());
+                                tracing::debug!(
+                                    "reconnect::poll_ready: {:?}, reconnecting in {:?}",
+                                    error,
+                                    backoff
+                                );
+                                self.error = Some(Arc::new(error));
+                                self.backoff = Some(Box::pin(tokio::time::sleep(backoff)));
                                 break;
                             }
                         }
@@ -126,6 +181,8 @@ where
                         Poll::Ready(Err(_)) => {
                             trace!("poll_ready; error");
                             state = State::Idle;
+                            self.resolve_now();
+                            self.connectivity.set(ConnectivityState::Idle);
                         }
                     }
                 }
@@ -140,9 +197,9 @@ where
 
     fn call(&mut self, request: Request) -> Self::Future {
         tracing::trace!("Reconnect::call");
-        if let Some(error) = self.error.take() {
+        if let Some(error) = &self.error {
             tracing::debug!("error: {}", error);
-            return ResponseFuture::error(error);
+            return ResponseFuture::error(ConnectError(error.clone()).into());
         }
 
         let service = match self.state {
@@ -172,6 +229,201 @@ where
     }
 }
 
+/// The state of a connection, as tracked by [`Reconnect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConnectivityState {
+    /// No connection was attempted yet, or the last one was closed.
+    Idle,
+    Connecting,
+    Ready,
+    /// The last connection attempt failed.
+    TransientFailure,
+}
+
+impl ConnectivityState {
+    fn from_u8(state: u8) -> Self {
+        match state {
+            0 => ConnectivityState::Idle,
+            1 => ConnectivityState::Connecting,
+            2 => ConnectivityState::Ready,
+            _ => ConnectivityState::TransientFailure,
+        }
+    }
+}
+
+impl From<ConnectivityState> for EndpointState {
+    fn from(state: ConnectivityState) -> Self {
+        match state {
+            ConnectivityState::Idle => EndpointState::Idle,
+            ConnectivityState::Connecting => EndpointState::Connecting,
+            ConnectivityState::Ready => EndpointState::Ready,
+            ConnectivityState::TransientFailure => EndpointState::TransientFailure,
+        }
+    }
+}
+
+/// A handle on the [`ConnectivityState`] of a connection, shared with the
+/// balancers choosing between connections.
+#[derive(Clone)]
+pub(crate) struct Connectivity(Arc<Shared>);
+
+struct Shared {
+    state: AtomicU8,
+    /// Connection attempts that failed since the last one that succeeded.
+    failures: AtomicU32,
+    uri: Uri,
+    on_state_change: Option<OnStateChange>,
+    /// The state of the channel the connection belongs to.
+    tracker: Option<StateTracker>,
+}
+
+impl Connectivity {
+    pub(crate) fn new(
+        uri: Uri,
+        on_state_change: Option<OnStateChange>,
+        tracker: Option<StateTracker>,
+    ) -> Self {
+        if let Some(tracker) = &tracker {
+            tracker.add();
+        }
+
+        Connectivity(Arc::new(Shared {
+            state: AtomicU8::new(ConnectivityState::Idle as u8),
+            failures: AtomicU32::new(0),
+            uri,
+            on_state_change,
+            tracker,
+        }))
+    }
+
+    pub(crate) fn get(&self) -> ConnectivityState {
+        self.0.get()
+    }
+
+    pub(crate) fn failures(&self) -> u32 {
+        self.0.failures.load(Ordering::Acquire)
+    }
+
+    /// Drop the error of a failed connection attempt rather than returning
+    /// it from the next call, so the next `poll_ready` reconnects.
+    pub(crate) fn reset(&self) {
+        let reset = self.0.state.compare_exchange(
+            ConnectivityState::TransientFailure as u8,
+            ConnectivityState::Idle as u8,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        if reset.is_ok() {
+            self.0
+                .track(ConnectivityState::TransientFailure, ConnectivityState::Idle);
+            self.report(EndpointState::Idle);
+        }
+    }
+
+    /// Let the observer of the endpoint know about `state`.
+    pub(crate) fn report(&self, state: EndpointState) {
+        if let Some(on_state_change) = &self.0.on_state_change {
+            on_state_change.call(&EndpointStateChange::new(self.0.uri.clone(), state));
+        }
+    }
+
+    pub(crate) fn set(&self, state: ConnectivityState) {
+        let previous = self.0.state.swap(state as u8, Ordering::AcqRel);
+        if previous == state as u8 {
+            return;
+        }
+        self.0.track(ConnectivityState::from_u8(previous), state);
+
+        match state {
+            ConnectivityState::Ready => self.0.failures.store(0, Ordering::Release),
+            ConnectivityState::TransientFailure => {
+                self.0.failures.fetch_add(1, Ordering::AcqRel);
+            }
+            _ => {}
+        }
+        self.report(state.into());
+    }
+}
+
+impl Shared {
+    fn get(&self) -> ConnectivityState {
+        ConnectivityState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    fn track(&self, from: ConnectivityState, to: ConnectivityState) {
+        if let Some(tracker) = &self.tracker {
+            tracker.transition(from, to);
+        }
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        if let Some(tracker) = &self.tracker {
+            tracker.remove(self.get());
+        }
+    }
+}
+
+impl fmt::Debug for Connectivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connectivity")
+            .field("state", &self.get())
+            .field("uri", &self.0.uri)
+            .finish()
+    }
+}
+
+/// Error returned to a request sent while the connection could not be
+/// established, the request was never written to the wire.
+#[derive(Debug)]
+pub(crate) struct ConnectError(Arc<crate::Error>);
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("error trying to connect")
+    }
+}
+
+impl std::error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&**self.0)
+    }
+}
+
+/// Returns `true` if `error` guarantees its request was not processed by the
+/// server, meaning it's safe to send it again.
+///
+/// That is the case if the request never left the client, if the server
+/// refused the stream, or if the server announced a graceful shutdown before
+/// processing the stream.
+pub(crate) fn is_unprocessed(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+
+    while let Some(error) = source {
+        if error.is::<ConnectError>() {
+            return true;
+        }
+
+        if let Some(hyper) = error.downcast_ref::<hyper::Error>() {
+            if hyper.is_canceled() {
+                return true;
+            }
+        }
+
+        if let Some(h2) = error.downcast_ref::<h2::Error>() {
+            return h2.reason() == Some(h2::Reason::REFUSED_STREAM)
+                || (h2.is_go_away()
+                    && h2.is_remote()
+                    && h2.reason() == Some(h2::Reason::NO_ERROR));
+        }
+
+        source = error.source();
+    }
+
+    false
+}
+
 /// Future that resolves to the response or failure to connect.
 #[pin_project]
 #[derive(Debug)]
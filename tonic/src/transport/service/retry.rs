@@ -0,0 +1,961 @@
+use super::{reconnect, wire};
+use crate::{
+    body::BoxBody,
+    metadata::GRPC_TIMEOUT_HEADER,
+    request::CallAuthority,
+    status::find_error_code,
+    transport::{
+        channel::{ChannelState, RetryPolicy, RetryThrottle},
+        service::grpc_timeout::try_parse_grpc_timeout,
+        TimeoutExpired,
+    },
+    Code, Status,
+};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, Method, Request, Response, Uri, Version};
+use http_body::Body;
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+    time::Duration,
+};
+use tokio::{sync::watch, time::Instant};
+use tower::ServiceExt;
+use tower_service::Service;
+
+/// Upper bound of the request body buffered to be replayed on a retry.
+///
+/// Bodies growing past this limit are still sent but can't be retried.
+const RETRY_BUFFER_LIMIT: usize = 1024 * 1024;
+
+const GRPC_RETRY_PUSHBACK_HEADER: &str = "grpc-retry-pushback-ms";
+
+const RETRY_INFO_TYPE: &[u8] = b"google.rpc.RetryInfo";
+
+/// Bounds of the delay between the attempts of wait-for-ready requests.
+const WAIT_FOR_READY_BACKOFF: Duration = Duration::from_millis(100);
+const WAIT_FOR_READY_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Send `request` through `svc`, retrying it according to `policy`.
+///
+/// `svc` is driven to readiness before every attempt. Retries are skipped
+/// while `throttle` doesn't allow them. With `wait_for_ready`, attempts the
+/// server didn't process are sent again as [`wait_for_ready`] does, without
+/// counting as retries.
+pub(crate) async fn call<S>(
+    mut svc: S,
+    request: Request<BoxBody>,
+    policy: RetryPolicy,
+    throttle: Option<RetryThrottle>,
+    mut wait_for_ready: Option<ReadyBackoff>,
+) -> Result<Response<BoxBody>, crate::Error>
+where
+    S: Service<Request<BoxBody>, Response = Response<BoxBody>>,
+    S::Error: Into<crate::Error>,
+{
+    let mut attempts = Attempts::new(request);
+    let mut request = attempts.next(Instant::now()).expect("first attempt");
+
+    let mut attempt = 1;
+    let mut transparent = true;
+    let result = loop {
+        let result = svc
+            .ready()
+            .await
+            .map_err(Into::into)?
+            .call(request)
+            .await
+            .map_err(Into::into);
+
+        if let (Some(ready), true) = (&mut wait_for_ready, is_unprocessed(&result)) {
+            if attempts.is_replayable() {
+                ready.wait(attempts.deadline).await;
+                request = match attempts.next(Instant::now()) {
+                    Some(request) => request,
+                    None => break Err(TimeoutExpired::new().into()),
+                };
+                tracing::debug!("channel not ready, sending wait-for-ready request again");
+                continue;
+            }
+        }
+
+        if transparent && is_unprocessed(&result) && attempts.is_replayable() {
+            if let Some(next) = attempts.next(Instant::now()) {
+                tracing::debug!("transparently retrying unprocessed request");
+                transparent = false;
+                request = next;
+                continue;
+            }
+        }
+
+        let code = result_code(&result);
+        let retryable = matches!(code, Some(code) if policy.is_retryable(code));
+        record(throttle.as_ref(), code, retryable);
+
+        if !retryable
+            || attempt >= policy.max_attempts
+            || !attempts.is_replayable()
+            || !is_retry_allowed(throttle.as_ref())
+        {
+            break result;
+        }
+
+        let backoff = match &result {
+            Ok(response) => match pushback(response.headers()) {
+                Pushback::Delay(delay) => delay,
+                Pushback::Stop => break result,
+                Pushback::None => policy
+                    .use_retry_info
+                    .then(|| retry_info_delay(response.headers()))
+                    .flatten()
+                    .unwrap_or_else(|| jitter(policy.backoff(attempt))),
+            },
+            Err(_) => jitter(policy.backoff(attempt)),
+        };
+
+        let start = Instant::now() + backoff;
+        request = match attempts.next(start) {
+            Some(request) => request,
+            None => break result,
+        };
+
+        tracing::debug!(?code, attempt, ?backoff, "retrying request");
+        tokio::time::sleep_until(start).await;
+
+        attempt += 1;
+    };
+
+    attempts.commit();
+    result
+}
+
+/// Send `request` through `svc`, replaying it once if it fails before the
+/// server processed it.
+///
+/// This applies to every request sent on a channel, regardless of any retry
+/// policy, since replaying such a request can't result in it being processed
+/// twice.
+pub(crate) async fn transparent<S>(
+    mut svc: S,
+    request: Request<BoxBody>,
+) -> Result<Response<BoxBody>, crate::Error>
+where
+    S: Service<Request<BoxBody>, Response = Response<BoxBody>>,
+    S::Error: Into<crate::Error>,
+{
+    let mut attempts = Attempts::new(request);
+    let request = attempts.next(Instant::now()).expect("first attempt");
+
+    let mut result = svc
+        .ready()
+        .await
+        .map_err(Into::into)?
+        .call(request)
+        .await
+        .map_err(Into::into);
+
+    if is_unprocessed(&result) && attempts.is_replayable() {
+        if let Some(request) = attempts.next(Instant::now()) {
+            tracing::debug!("transparently retrying unprocessed request");
+            result = svc
+                .ready()
+                .await
+                .map_err(Into::into)?
+                .call(request)
+                .await
+                .map_err(Into::into);
+        }
+    }
+
+    attempts.commit();
+    result
+}
+
+/// Send `request` through `svc`, sending it again while it fails before the
+/// server processed it, until its deadline expires.
+///
+/// Attempts are spaced by an exponential backoff, cut short when the
+/// channel becomes ready.
+pub(crate) async fn wait_for_ready<S>(
+    mut svc: S,
+    request: Request<BoxBody>,
+    mut ready: ReadyBackoff,
+) -> Result<Response<BoxBody>, crate::Error>
+where
+    S: Service<Request<BoxBody>, Response = Response<BoxBody>>,
+    S::Error: Into<crate::Error>,
+{
+    let mut attempts = Attempts::new(request);
+    let mut request = attempts.next(Instant::now()).expect("first attempt");
+
+    let result = loop {
+        let result = svc
+            .ready()
+            .await
+            .map_err(Into::into)?
+            .call(request)
+            .await
+            .map_err(Into::into);
+
+        if !is_unprocessed(&result) || !attempts.is_replayable() {
+            break result;
+        }
+
+        ready.wait(attempts.deadline).await;
+        request = match attempts.next(Instant::now()) {
+            Some(request) => request,
+            None => break Err(TimeoutExpired::new().into()),
+        };
+        tracing::debug!("channel not ready, sending wait-for-ready request again");
+    };
+
+    attempts.commit();
+    result
+}
+
+/// The delay before sending a wait-for-ready request the server didn't
+/// process again, an exponential backoff cut short when the channel becomes
+/// ready.
+#[derive(Clone)]
+pub(crate) struct ReadyBackoff {
+    state: watch::Receiver<ChannelState>,
+    backoff: Duration,
+}
+
+impl ReadyBackoff {
+    pub(crate) fn new(state: watch::Receiver<ChannelState>) -> Self {
+        ReadyBackoff {
+            state,
+            backoff: WAIT_FOR_READY_BACKOFF,
+        }
+    }
+
+    /// Resolves once the channel becomes ready, or after the backoff or at
+    /// `deadline` if they come first. The backoff doubles for the next wait.
+    pub(crate) fn wait(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let start = Instant::now() + self.backoff;
+        let deadline = deadline.map_or(start, |deadline| deadline.min(start));
+        self.backoff = (self.backoff * 2).min(WAIT_FOR_READY_MAX_BACKOFF);
+
+        let mut state = self.state.clone();
+        state.borrow_and_update();
+        let ready = async move {
+            loop {
+                if state.changed().await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+                if *state.borrow() == ChannelState::Ready {
+                    return;
+                }
+            }
+        };
+        async move {
+            let _ = tokio::time::timeout_at(deadline, ready).await;
+        }
+    }
+}
+
+/// Returns `true` if the attempt failed without the server processing it.
+pub(crate) fn is_unprocessed(result: &Result<Response<BoxBody>, crate::Error>) -> bool {
+    matches!(result, Err(error) if reconnect::is_unprocessed(&**error))
+}
+
+/// Account for an attempt in `throttle`.
+///
+/// Only failures the policy would retry take tokens, other failures neither
+/// take nor give back tokens.
+pub(crate) fn record(throttle: Option<&RetryThrottle>, code: Option<Code>, retryable: bool) {
+    match (throttle, code) {
+        (Some(throttle), None) => throttle.record_success(),
+        (Some(throttle), Some(_)) if retryable => throttle.record_failure(),
+        _ => {}
+    }
+}
+
+pub(crate) fn is_retry_allowed(throttle: Option<&RetryThrottle>) -> bool {
+    throttle
+        .map(RetryThrottle::is_retry_allowed)
+        .unwrap_or(true)
+}
+
+/// Builds the successive attempts of a request.
+///
+/// The first attempt is the original request, later ones share its body
+/// through a [`ReplayBody`] and carry a `grpc-timeout` accounting for the time
+/// already spent.
+pub(crate) struct Attempts {
+    first: Option<Request<BoxBody>>,
+    method: Method,
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap,
+    authority: Option<CallAuthority>,
+    body: ReplayBody,
+    deadline: Option<Instant>,
+}
+
+impl Attempts {
+    pub(crate) fn new(request: Request<BoxBody>) -> Self {
+        let deadline = try_parse_grpc_timeout(request.headers())
+            .ok()
+            .flatten()
+            .map(|timeout| Instant::now() + timeout);
+
+        let (parts, body) = request.into_parts();
+        let body = ReplayBody::new(body, RETRY_BUFFER_LIMIT);
+
+        Attempts {
+            method: parts.method.clone(),
+            uri: parts.uri.clone(),
+            version: parts.version,
+            headers: parts.headers.clone(),
+            authority: parts.extensions.get::<CallAuthority>().cloned(),
+            first: Some(Request::from_parts(parts, BoxBody::new(body.replay()))),
+            body,
+            deadline,
+        }
+    }
+
+    /// Build the next attempt, to be sent at `start`.
+    ///
+    /// Returns `None` if `start` is past the deadline of the request.
+    pub(crate) fn next(&mut self, start: Instant) -> Option<Request<BoxBody>> {
+        if let Some(first) = self.first.take() {
+            return Some(first);
+        }
+
+        let mut headers = self.headers.clone();
+        if let Some(deadline) = self.deadline {
+            if start >= deadline {
+                return None;
+            }
+
+            let remaining = deadline - start;
+            headers.insert(
+                GRPC_TIMEOUT_HEADER,
+                HeaderValue::from_str(&crate::request::duration_to_grpc_timeout(remaining))
+                    .expect("grpc-timeout is a valid header value"),
+            );
+        }
+
+        let mut request = Request::new(BoxBody::new(self.body.replay()));
+        *request.method_mut() = self.method.clone();
+        *request.uri_mut() = self.uri.clone();
+        *request.version_mut() = self.version;
+        *request.headers_mut() = headers;
+        if let Some(authority) = self.authority.clone() {
+            request.extensions_mut().insert(authority);
+        }
+        Some(request)
+    }
+
+    /// The deadline of the request, past which no attempt is built.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Returns `true` if another attempt can be built.
+    pub(crate) fn is_replayable(&self) -> bool {
+        self.body.is_replayable()
+    }
+
+    /// Stop recording the request body, no attempt will be built anymore.
+    pub(crate) fn commit(&self) {
+        self.body.commit();
+    }
+}
+
+/// The status code of a failed attempt, `None` if it succeeded or if the
+/// server committed the call by sending response headers.
+pub(crate) fn result_code(result: &Result<Response<BoxBody>, crate::Error>) -> Option<Code> {
+    match result {
+        Ok(response) => trailers_only_code(response.headers()),
+        Err(error) => Some(find_error_code(&**error)),
+    }
+}
+
+/// The status code of a "Trailers-Only" response, which is the only kind of
+/// response that can be retried since any other response commits the call.
+fn trailers_only_code(headers: &HeaderMap) -> Option<Code> {
+    Status::from_header_map(headers)
+        .map(|status| status.code())
+        .filter(|&code| code != Code::Ok)
+}
+
+pub(crate) enum Pushback {
+    None,
+    Delay(Duration),
+    Stop,
+}
+
+/// Parse the `grpc-retry-pushback-ms` header, a missing header lets the
+/// policy pick the delay while an invalid one stops retries.
+pub(crate) fn pushback(headers: &HeaderMap) -> Pushback {
+    match headers.get(GRPC_RETRY_PUSHBACK_HEADER) {
+        None => Pushback::None,
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(|ms| Pushback::Delay(Duration::from_millis(ms)))
+            .unwrap_or(Pushback::Stop),
+    }
+}
+
+/// The delay of the first `google.rpc.RetryInfo` of the details of the
+/// status in `headers`, if any.
+pub(crate) fn retry_info_delay(headers: &HeaderMap) -> Option<Duration> {
+    let status = Status::from_header_map(headers)?;
+
+    // The `details` of a `google.rpc.Status` are `google.protobuf.Any`s.
+    let mut retry_info = None;
+    wire::for_each_field(status.details(), |number, field| match (number, field) {
+        (3, wire::Field::Bytes(any)) if retry_info.is_none() => {
+            let (mut type_url, mut value) = (&[][..], &[][..]);
+            let _ = wire::for_each_field(any, |number, field| match (number, field) {
+                (1, wire::Field::Bytes(bytes)) => type_url = bytes,
+                (2, wire::Field::Bytes(bytes)) => value = bytes,
+                _ => {}
+            });
+            if type_url.rsplit(|&b| b == b'/').next() == Some(RETRY_INFO_TYPE) {
+                retry_info = Some(value);
+            }
+        }
+        _ => {}
+    })?;
+
+    // The `retry_delay` of a `RetryInfo` is a `google.protobuf.Duration`.
+    let mut delay = None;
+    wire::for_each_field(retry_info?, |number, field| {
+        if let (1, wire::Field::Bytes(duration)) = (number, field) {
+            delay = Some(duration);
+        }
+    })?;
+    let (mut seconds, mut nanos) = (0, 0);
+    wire::for_each_field(delay?, |number, field| match (number, field) {
+        (1, wire::Field::Varint(value)) => seconds = value as i64,
+        (2, wire::Field::Varint(value)) => nanos = value as i64,
+        _ => {}
+    })?;
+
+    let seconds = u64::try_from(seconds).ok()?;
+    let nanos = u32::try_from(nanos)
+        .ok()
+        .filter(|&nanos| nanos < 1_000_000_000)?;
+    Some(Duration::new(seconds, nanos))
+}
+
+/// Pick a uniformly random duration in `[0, duration)`.
+pub(crate) fn jitter(duration: Duration) -> Duration {
+    // Every `RandomState` is seeded differently, which is random enough here.
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u8(0);
+    let random = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+
+    duration.mul_f64(random)
+}
+
+/// A body that records the data it yields so it can be sent again.
+///
+/// All replays share the same source body: a replay first yields the data
+/// already recorded then continues reading from the source. Recording stops
+/// once more than `limit` bytes have been read, after which the body is no
+/// longer replayable.
+pub(crate) struct ReplayBody {
+    shared: Arc<Mutex<Shared>>,
+    wakers: Arc<Wakers>,
+    pos: usize,
+}
+
+struct Shared {
+    source: BoxBody,
+    chunks: Vec<Bytes>,
+    /// Number of chunks read from the source, including discarded ones.
+    read: usize,
+    buffered: usize,
+    limit: usize,
+    overflowed: bool,
+    data_done: bool,
+    trailers: Option<Option<HeaderMap>>,
+}
+
+impl ReplayBody {
+    pub(crate) fn new(source: BoxBody, limit: usize) -> Self {
+        ReplayBody {
+            shared: Arc::new(Mutex::new(Shared {
+                source,
+                chunks: Vec::new(),
+                read: 0,
+                buffered: 0,
+                limit,
+                overflowed: false,
+                data_done: false,
+                trailers: None,
+            })),
+            wakers: Arc::new(Wakers::default()),
+            pos: 0,
+        }
+    }
+
+    /// Returns `true` if a fresh replay of this body yields the whole body.
+    pub(crate) fn is_replayable(&self) -> bool {
+        !self.shared.lock().unwrap().overflowed
+    }
+
+    /// Drop the recorded data and stop recording, replays that didn't catch
+    /// up with the source will fail.
+    pub(crate) fn commit(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.overflowed = true;
+        shared.chunks = Vec::new();
+    }
+
+    /// Returns a new body sharing this body's source, starting from the
+    /// beginning.
+    pub(crate) fn replay(&self) -> Self {
+        ReplayBody {
+            shared: self.shared.clone(),
+            wakers: self.wakers.clone(),
+            pos: 0,
+        }
+    }
+}
+
+impl Body for ReplayBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.get_mut();
+        let mut shared = this.shared.lock().unwrap();
+
+        if this.pos < shared.read {
+            let discarded = shared.read - shared.chunks.len();
+            return match this.pos.checked_sub(discarded) {
+                Some(idx) => {
+                    this.pos += 1;
+                    Poll::Ready(Some(Ok(shared.chunks[idx].clone())))
+                }
+                None => Poll::Ready(Some(Err(Status::internal(
+                    "request body is too large to be replayed",
+                )))),
+            };
+        }
+
+        if shared.data_done {
+            return Poll::Ready(None);
+        }
+
+        // Several clones may wait on the source at once, wake them all.
+        this.wakers.register(cx.waker());
+        let waker = Waker::from(this.wakers.clone());
+        let mut source_cx = Context::from_waker(&waker);
+
+        match Pin::new(&mut shared.source).poll_data(&mut source_cx) {
+            Poll::Ready(Some(Ok(data))) => {
+                shared.read += 1;
+                shared.buffered += data.len();
+                if shared.overflowed || shared.buffered > shared.limit {
+                    shared.overflowed = true;
+                    shared.chunks = Vec::new();
+                } else {
+                    shared.chunks.push(data.clone());
+                }
+                this.pos = shared.read;
+                Poll::Ready(Some(Ok(data)))
+            }
+            Poll::Ready(Some(Err(error))) => {
+                shared.overflowed = true;
+                Poll::Ready(Some(Err(error)))
+            }
+            Poll::Ready(None) => {
+                shared.data_done = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.get_mut();
+        let mut shared = this.shared.lock().unwrap();
+
+        if let Some(trailers) = &shared.trailers {
+            return Poll::Ready(Ok(trailers.clone()));
+        }
+
+        this.wakers.register(cx.waker());
+        let waker = Waker::from(this.wakers.clone());
+        let mut source_cx = Context::from_waker(&waker);
+
+        match Pin::new(&mut shared.source).poll_trailers(&mut source_cx) {
+            Poll::Ready(Ok(trailers)) => {
+                shared.trailers = Some(trailers.clone());
+                Poll::Ready(Ok(trailers))
+            }
+            Poll::Ready(Err(error)) => {
+                shared.overflowed = true;
+                Poll::Ready(Err(error))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        let shared = self.shared.lock().unwrap();
+        shared.data_done && self.pos >= shared.read && shared.trailers.is_some()
+    }
+}
+
+#[derive(Default)]
+struct Wakers(Mutex<Vec<Waker>>);
+
+impl Wakers {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.0.lock().unwrap();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+impl Wake for Wakers {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        for waker in self.0.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(chunks: &[&'static str]) -> BoxBody {
+        let chunks = chunks
+            .iter()
+            .map(|c| Ok::<_, Status>(Bytes::from_static(c.as_bytes())))
+            .collect::<Vec<_>>();
+        let stream = tokio_stream::iter(chunks);
+        BoxBody::new(hyper::Body::wrap_stream(stream).map_err(Status::from_error_generic))
+    }
+
+    async fn collect(mut body: ReplayBody) -> Result<Vec<u8>, Status> {
+        let mut out = Vec::new();
+        while let Some(chunk) = body.data().await {
+            out.extend_from_slice(&chunk?);
+        }
+        Ok(out)
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_data() {
+        let body = ReplayBody::new(body(&["hello ", "world"]), 1024);
+
+        assert_eq!(collect(body.replay()).await.unwrap(), b"hello world");
+        assert!(body.is_replayable());
+        assert_eq!(collect(body.replay()).await.unwrap(), b"hello world");
+    }
+
+    #[tokio::test]
+    async fn replays_partially_read_body() {
+        let mut first = ReplayBody::new(body(&["a", "b", "c"]), 1024);
+        assert_eq!(&first.data().await.unwrap().unwrap()[..], b"a");
+
+        assert_eq!(collect(first.replay()).await.unwrap(), b"abc");
+        assert_eq!(collect(first).await.unwrap(), b"bc");
+    }
+
+    #[tokio::test]
+    async fn too_large_body_is_not_replayable() {
+        let body = ReplayBody::new(body(&["hello ", "world"]), 8);
+
+        assert_eq!(collect(body.replay()).await.unwrap(), b"hello world");
+        assert!(!body.is_replayable());
+        assert!(collect(body.replay()).await.is_err());
+    }
+
+    fn response(code: Code) -> Response<BoxBody> {
+        let mut response = Response::new(BoxBody::default());
+        Status::new(code, "")
+            .add_header(response.headers_mut())
+            .unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let svc = {
+            let attempts = attempts.clone();
+            tower::service_fn(move |req: Request<BoxBody>| {
+                let attempts = attempts.clone();
+                async move {
+                    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                    let mut attempts = attempts.lock().unwrap();
+                    attempts.push(body);
+                    let code = if attempts.len() < 3 {
+                        Code::Unavailable
+                    } else {
+                        Code::Ok
+                    };
+                    Ok::<_, crate::Error>(response(code))
+                }
+            })
+        };
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(1))
+            .retryable_status_code(Code::Unavailable);
+
+        let response = call(svc, Request::new(body(&["a", "b"])), policy, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(trailers_only_code(response.headers()), None);
+        assert_eq!(*attempts.lock().unwrap(), vec![Bytes::from("ab"); 3]);
+    }
+
+    #[tokio::test]
+    async fn stops_on_non_retryable_code() {
+        let attempts = Arc::new(Mutex::new(0));
+        let svc = {
+            let attempts = attempts.clone();
+            tower::service_fn(move |_: Request<BoxBody>| {
+                *attempts.lock().unwrap() += 1;
+                async { Ok::<_, crate::Error>(response(Code::InvalidArgument)) }
+            })
+        };
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(1))
+            .retryable_status_code(Code::Unavailable);
+
+        let response = call(svc, Request::new(body(&["a"])), policy, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            trailers_only_code(response.headers()),
+            Some(Code::InvalidArgument)
+        );
+        assert_eq!(*attempts.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn throttled_retries() {
+        let attempts = Arc::new(Mutex::new(0));
+        let svc = {
+            let attempts = attempts.clone();
+            tower::service_fn(move |_: Request<BoxBody>| {
+                *attempts.lock().unwrap() += 1;
+                async { Ok::<_, crate::Error>(response(Code::Unavailable)) }
+            })
+        };
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(1))
+            .retryable_status_code(Code::Unavailable);
+        let throttle = RetryThrottle::new(4, 1.0);
+
+        let response = call(
+            svc,
+            Request::new(body(&["a"])),
+            policy,
+            Some(throttle.clone()),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            trailers_only_code(response.headers()),
+            Some(Code::Unavailable)
+        );
+        assert_eq!(*attempts.lock().unwrap(), 2);
+        assert!(!throttle.is_retry_allowed());
+    }
+
+    #[tokio::test]
+    async fn transparently_retries_unprocessed_request() {
+        let attempts = Arc::new(Mutex::new(0));
+        let svc = {
+            let attempts = attempts.clone();
+            tower::service_fn(move |_: Request<BoxBody>| {
+                let attempt = {
+                    let mut attempts = attempts.lock().unwrap();
+                    *attempts += 1;
+                    *attempts
+                };
+                async move {
+                    if attempt == 1 {
+                        Err(h2::Error::from(h2::Reason::REFUSED_STREAM).into())
+                    } else {
+                        Ok::<_, crate::Error>(response(Code::Ok))
+                    }
+                }
+            })
+        };
+
+        transparent(svc, Request::new(body(&["a"]))).await.unwrap();
+        assert_eq!(*attempts.lock().unwrap(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_ready_until_deadline() {
+        let attempts = Arc::new(Mutex::new(0));
+        let svc = |succeed_at| {
+            let attempts = attempts.clone();
+            tower::service_fn(move |_: Request<BoxBody>| {
+                let attempt = {
+                    let mut attempts = attempts.lock().unwrap();
+                    *attempts += 1;
+                    *attempts
+                };
+                async move {
+                    if attempt < succeed_at {
+                        Err(h2::Error::from(h2::Reason::REFUSED_STREAM).into())
+                    } else {
+                        Ok::<_, crate::Error>(response(Code::Ok))
+                    }
+                }
+            })
+        };
+        let (_tx, state) = watch::channel(ChannelState::TransientFailure);
+
+        wait_for_ready(
+            svc(4),
+            Request::new(body(&["a"])),
+            ReadyBackoff::new(state.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(*attempts.lock().unwrap(), 4);
+
+        *attempts.lock().unwrap() = 0;
+        let mut request = Request::new(body(&["a"]));
+        request
+            .headers_mut()
+            .insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static("1S"));
+        let error = wait_for_ready(svc(u32::MAX), request, ReadyBackoff::new(state))
+            .await
+            .unwrap_err();
+        assert!(error.is::<TimeoutExpired>());
+        assert!(*attempts.lock().unwrap() > 2);
+    }
+
+    /// A response with a status detailing a `RetryInfo` of a delay of
+    /// `seconds` and 128 nanoseconds.
+    fn retry_info_response(code: Code, seconds: u8) -> Response<BoxBody> {
+        let duration = [0x08, seconds, 0x10, 0x80, 0x01];
+        let mut retry_info = Vec::new();
+        wire::put_bytes(&mut retry_info, 1, &duration);
+        let mut any = Vec::new();
+        wire::put_bytes(&mut any, 1, b"type.googleapis.com/google.rpc.RetryInfo");
+        wire::put_bytes(&mut any, 2, &retry_info);
+        let mut details = Vec::new();
+        wire::put_bytes(&mut details, 3, &any);
+
+        let mut response = Response::new(BoxBody::default());
+        Status::with_details(code, "", details.into())
+            .add_header(response.headers_mut())
+            .unwrap();
+        response
+    }
+
+    #[test]
+    fn parse_retry_info() {
+        let response = retry_info_response(Code::Unavailable, 2);
+        assert_eq!(
+            retry_info_delay(response.headers()),
+            Some(Duration::new(2, 128))
+        );
+        assert_eq!(
+            retry_info_delay(self::response(Code::Unavailable).headers()),
+            None
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_the_delay_of_retry_info() {
+        let svc = |attempts: Arc<Mutex<usize>>| {
+            tower::service_fn(move |_: Request<BoxBody>| {
+                let attempts = attempts.clone();
+                async move {
+                    let mut attempts = attempts.lock().unwrap();
+                    *attempts += 1;
+                    let response = if *attempts == 1 {
+                        retry_info_response(Code::Unavailable, 3)
+                    } else {
+                        response(Code::Ok)
+                    };
+                    Ok::<_, crate::Error>(response)
+                }
+            })
+        };
+        let policy = RetryPolicy::new(2)
+            .initial_backoff(Duration::from_millis(1))
+            .max_backoff(Duration::from_millis(1))
+            .retryable_status_code(Code::Unavailable);
+
+        let start = Instant::now();
+        let attempts = Arc::new(Mutex::new(0));
+        call(
+            svc(attempts.clone()),
+            Request::new(body(&[])),
+            policy.clone(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(*attempts.lock().unwrap(), 2);
+        assert!(start.elapsed() >= Duration::from_secs(3));
+
+        let start = Instant::now();
+        let policy = policy.use_retry_info(false);
+        call(
+            svc(Arc::default()),
+            Request::new(body(&[])),
+            policy,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_is_bounded() {
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_millis(10)) < Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn parse_pushback() {
+        let mut headers = HeaderMap::new();
+        assert!(matches!(pushback(&headers), Pushback::None));
+
+        headers.insert(GRPC_RETRY_PUSHBACK_HEADER, HeaderValue::from_static("20"));
+        assert!(matches!(pushback(&headers), Pushback::Delay(d) if d == Duration::from_millis(20)));
+
+        headers.insert(GRPC_RETRY_PUSHBACK_HEADER, HeaderValue::from_static("-1"));
+        assert!(matches!(pushback(&headers), Pushback::Stop));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::body::BoxBody;
+use http::{Request, Response};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::discover::{Change, Discover};
+use tower_service::Service;
+
+/// Send requests to each ready endpoint in turn.
+pub(crate) struct RoundRobin<D: Discover> {
+    discover: D,
+    endpoints: Vec<(D::Key, D::Service)>,
+    /// The endpoint to try first for the next request.
+    next: usize,
+    /// The endpoint the next call is sent to.
+    ready: Option<usize>,
+}
+
+impl<D> RoundRobin<D>
+where
+    D: Discover + Unpin,
+    D::Error: Into<crate::Error>,
+{
+    pub(crate) fn new(discover: D) -> Self {
+        RoundRobin {
+            discover,
+            endpoints: Vec::new(),
+            next: 0,
+            ready: None,
+        }
+    }
+
+    fn update(&mut self, cx: &mut Context<'_>) -> Result<(), crate::Error> {
+        while let Poll::Ready(Some(change)) = Pin::new(&mut self.discover).poll_discover(cx) {
+            match change.map_err(Into::into)? {
+                Change::Insert(key, service) => {
+                    match self.endpoints.iter().position(|(k, _)| *k == key) {
+                        Some(i) => self.endpoints[i].1 = service,
+                        None => self.endpoints.push((key, service)),
+                    }
+                }
+                Change::Remove(key) => self.endpoints.retain(|(k, _)| *k != key),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<D> Service<Request<BoxBody>> for RoundRobin<D>
+where
+    D: Discover + Unpin,
+    D::Error: Into<crate::Error>,
+    D::Service: Service<Request<BoxBody>, Response = Response<BoxBody>, Error = crate::Error>,
+{
+    type Response = Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = <D::Service as Service<Request<BoxBody>>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.ready = None;
+        self.update(cx)?;
+
+        // Poll every endpoint that isn't ready, so each of them is woken up
+        // when it becomes ready.
+        let mut i = self.next;
+        for _ in 0..self.endpoints.len() {
+            if i >= self.endpoints.len() {
+                i = 0;
+            }
+
+            match self.endpoints[i].1.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {
+                    self.ready = Some(i);
+                    self.next = i + 1;
+                    return Poll::Ready(Ok(()));
+                }
+                Poll::Pending => i += 1,
+                Poll::Ready(Err(error)) => {
+                    tracing::debug!("round_robin: dropping endpoint: {}", error);
+                    self.endpoints.remove(i);
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        let i = self.ready.take().expect("called before ready");
+        self.endpoints[i].1.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::{channel::LoadBalancingPolicy, Channel, Endpoint};
+    use http::Request;
+    use std::{convert::Infallible, net::SocketAddr};
+    use tower::ServiceExt;
+
+    fn serve(name: &'static str) -> SocketAddr {
+        let make = hyper::service::make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(hyper::service::service_fn(move |_| async move {
+                Ok::<_, Infallible>(http::Response::new(hyper::Body::from(name)))
+            }))
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    async fn send(channel: &Channel) -> String {
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        let response = channel.clone().oneshot(request).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn alternates_between_ready_endpoints() {
+        let endpoints = [serve("a"), serve("b")]
+            .into_iter()
+            .map(|addr| Endpoint::from_shared(format!("http://{}", addr)).unwrap());
+        let channel = Channel::balance_list_with_policy(endpoints, LoadBalancingPolicy::RoundRobin);
+
+        // Wait for both endpoints to be connected.
+        while send(&channel).await != "b" {}
+
+        for _ in 0..3 {
+            assert_eq!(send(&channel).await, "a");
+            assert_eq!(send(&channel).await, "b");
+        }
+    }
+}
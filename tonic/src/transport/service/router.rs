@@ -44,6 +44,36 @@ impl RoutesBuilder {
         self
     }
 
+    /// Add a new service under the path `prefix`, see [`Routes::nest`].
+    pub fn nest<S>(&mut self, prefix: &str, svc: S) -> &mut Self
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        let routes = self.routes.take().unwrap_or_default();
+        self.routes.replace(routes.nest(prefix, svc));
+        self
+    }
+
+    /// Set the service of the requests that no added service serves, see
+    /// [`Routes::fallback_service`].
+    pub fn fallback_service<S>(&mut self, svc: S) -> &mut Self
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        let routes = self.routes.take().unwrap_or_default();
+        self.routes.replace(routes.fallback_service(svc));
+        self
+    }
+
     /// Returns the routes with added services or empty [`Routes`] if no service was added
     pub fn routes(self) -> Routes {
         self.routes.unwrap_or_default()
@@ -83,6 +113,56 @@ impl Routes {
         self
     }
 
+    /// Add a new service under the path `prefix`, serving its methods at
+    /// `{prefix}/{package}.{Service}/{Method}`, as behind gateways and
+    /// ingresses routing on a path such as `/api/v1`.
+    ///
+    /// The service receives the requests with the prefix removed from their
+    /// path, as if it was added with [`Routes::add_service`]. Clients reach it
+    /// with the prefix in the path of their origin, or with
+    /// [`Endpoint::path_prefix`](crate::transport::Endpoint::path_prefix).
+    pub fn nest<S>(mut self, prefix: &str, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        let prefix = prefix.trim_end_matches('/');
+        let prefix_len = prefix.len();
+        let svc = svc
+            .map_request(move |req: Request<Body>| strip_prefix(req, prefix_len))
+            .map_response(|res| res.map(axum::body::boxed));
+        self.router = self
+            .router
+            .route_service(&format!("{}/{}/*rest", prefix, S::NAME), svc);
+        self
+    }
+
+    /// Set the service of the requests of methods that no added service
+    /// serves, instead of answering them with an `UNIMPLEMENTED` status.
+    ///
+    /// The service receives the requests as they are, with the path of their
+    /// method in their URI, their metadata in their headers, and their body
+    /// of length-prefixed messages, and responds alike, with the `grpc-status`
+    /// of calls in the trailers of its responses. It can so serve methods
+    /// unknown at compile time, such as those of proxies, recorders, and
+    /// bridges to other protocols.
+    pub fn fallback_service<S>(mut self, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        let svc = svc.map_response(|res| res.map(axum::body::boxed));
+        self.router = self.router.fallback_service(svc);
+        self
+    }
+
     pub(crate) fn prepare(self) -> Self {
         Self {
             // this makes axum perform update some internals of the router that improves perf
@@ -97,6 +177,26 @@ impl Routes {
     }
 }
 
+impl From<axum::Router> for Routes {
+    fn from(router: axum::Router) -> Self {
+        Self { router }
+    }
+}
+
+/// Remove the first `len` bytes, a matched prefix, from the path of `req`.
+fn strip_prefix(mut req: Request<Body>, len: usize) -> Request<Body> {
+    let mut parts = req.uri().clone().into_parts();
+    if let Some(path_and_query) = &parts.path_and_query {
+        if let Some(rest) = path_and_query.as_str().get(len..) {
+            parts.path_and_query = rest.parse().ok();
+        }
+    }
+    if let Ok(uri) = http::Uri::from_parts(parts) {
+        *req.uri_mut() = uri;
+    }
+    req
+}
+
 async fn unimplemented() -> impl axum::response::IntoResponse {
     let status = http::StatusCode::OK;
     let headers = [("grpc-status", "12"), ("content-type", "application/grpc")];
@@ -137,3 +237,80 @@ impl Future for RoutesFuture {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_unknown_methods_with_the_fallback_service() {
+        let fallback = tower::service_fn(|request: Request<Body>| async move {
+            let method = request.uri().path().to_owned();
+            let response = Response::builder()
+                .header("grpc-status", "0")
+                .header("x-method", method)
+                .body(crate::body::empty_body())
+                .unwrap();
+            Ok::<_, Infallible>(response)
+        });
+        let routes = Routes::default().fallback_service(fallback).prepare();
+
+        let request = Request::post("/unknown.Service/Method")
+            .body(Body::empty())
+            .unwrap();
+        let response = routes.oneshot(request).await.unwrap();
+        assert_eq!(response.headers()["x-method"], "/unknown.Service/Method");
+    }
+
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<Request<Body>> for Echo {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<Body>) -> Self::Future {
+            let response = Response::builder()
+                .header("grpc-status", "0")
+                .header("x-method", request.uri().path())
+                .body(crate::body::empty_body())
+                .unwrap();
+            std::future::ready(Ok(response))
+        }
+    }
+
+    impl NamedService for Echo {
+        const NAME: &'static str = "test.Echo";
+    }
+
+    async fn status(routes: &Routes, path: &str) -> (String, Option<String>) {
+        let request = Request::post(path).body(Body::empty()).unwrap();
+        let response = routes.clone().oneshot(request).await.unwrap();
+        let headers = response.headers();
+        (
+            headers["grpc-status"].to_str().unwrap().to_owned(),
+            headers
+                .get("x-method")
+                .map(|method| method.to_str().unwrap().to_owned()),
+        )
+    }
+
+    #[tokio::test]
+    async fn serves_nested_services_under_their_prefix() {
+        let routes = Routes::from(axum::Router::new().fallback(unimplemented))
+            .nest("/api/v1/", Echo)
+            .prepare();
+
+        assert_eq!(
+            status(&routes, "/api/v1/test.Echo/Call").await,
+            ("0".to_owned(), Some("/test.Echo/Call".to_owned()))
+        );
+        assert_eq!(status(&routes, "/test.Echo/Call").await.0, "12");
+        assert_eq!(status(&routes, "/api/v2/test.Echo/Call").await.0, "12");
+    }
+}
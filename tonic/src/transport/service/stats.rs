@@ -0,0 +1,600 @@
+use crate::{
+    body::BoxBody,
+    stats::{status_code, CallStats, ConnInfo, Recorder, RpcInfo, StatsHandler},
+    status::find_error_code,
+    transport::server::TcpConnectInfo,
+    Code,
+};
+use http::{HeaderMap, Request, Response};
+use http_body::Body;
+use hyper::client::connect::{Connected as HyperConnected, Connection as HyperConnection};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tower_service::Service;
+
+/// Reports the beginning of a connection, and its end once dropped.
+pub(crate) struct ConnGuard {
+    handler: Arc<dyn StatsHandler>,
+    info: ConnInfo,
+}
+
+impl ConnGuard {
+    pub(crate) fn new(handler: Arc<dyn StatsHandler>, info: ConnInfo) -> Self {
+        handler.conn_begin(&info);
+        ConnGuard { handler, info }
+    }
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        self.handler.conn_end(&self.info);
+    }
+}
+
+/// Reports the calls a server receives over one connection.
+pub(crate) struct ServerStats<S> {
+    inner: S,
+    handler: Option<Arc<dyn StatsHandler>>,
+    _conn: Option<ConnGuard>,
+}
+
+impl<S> ServerStats<S> {
+    /// Wrap the service of the connection from `remote_addr`.
+    pub(crate) fn new(
+        inner: S,
+        handler: Option<Arc<dyn StatsHandler>>,
+        remote_addr: Option<SocketAddr>,
+    ) -> Self {
+        let conn = handler
+            .clone()
+            .map(|handler| ConnGuard::new(handler, ConnInfo::new(false, remote_addr)));
+        ServerStats {
+            inner,
+            handler,
+            _conn: conn,
+        }
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for ServerStats<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+{
+    type Response = Response<StatsBody<ResBody>>;
+    type Error = crate::Error;
+    type Future = StatsFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let recorder = self.handler.clone().map(|handler| {
+            let peer = request
+                .extensions()
+                .get::<TcpConnectInfo>()
+                .and_then(TcpConnectInfo::remote_addr);
+            let info = RpcInfo::new(false, request.uri().path(), peer)
+                .with_authority(request.uri().authority());
+
+            let recorder = Recorder::begin(handler, info);
+            recorder.in_headers(request.headers());
+            let stats = CallStats::new(recorder.clone());
+            request.extensions_mut().insert(stats);
+            recorder
+        });
+
+        StatsFuture {
+            inner: self.inner.call(request),
+            recorder,
+            client: false,
+        }
+    }
+}
+
+/// Reports the calls a client sends over one endpoint.
+#[derive(Clone)]
+pub(crate) struct ClientStats<S> {
+    inner: S,
+    handler: Arc<dyn StatsHandler>,
+}
+
+impl<S> ClientStats<S> {
+    pub(crate) fn new(inner: S, handler: Arc<dyn StatsHandler>) -> Self {
+        ClientStats { inner, handler }
+    }
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for ClientStats<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<BoxBody>>,
+    S::Error: Into<crate::Error>,
+{
+    type Response = Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = ClientStatsFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let stats = request.extensions().get::<CallStats>();
+        let mut info = RpcInfo::new(true, request.uri().path(), None)
+            .with_authority(request.uri().authority());
+        if let Some(stats) = stats {
+            info = info.with_parent(stats.caller_span().clone());
+        }
+        let recorder = Recorder::begin(self.handler.clone(), info);
+        recorder.out_headers(request.headers());
+
+        // The codec of the call reports its messages and trailers.
+        let decoded = match stats {
+            Some(stats) => {
+                stats.set(recorder.clone());
+                true
+            }
+            None => false,
+        };
+
+        ClientStatsFuture {
+            inner: StatsFuture {
+                inner: self.inner.call(request),
+                recorder: Some(recorder),
+                client: true,
+            },
+            decoded,
+        }
+    }
+}
+
+/// Response future of [`ServerStats`] and [`ClientStats`], reporting the
+/// headers of the response.
+#[pin_project]
+pub(crate) struct StatsFuture<F> {
+    #[pin]
+    inner: F,
+    recorder: Option<Recorder>,
+    client: bool,
+}
+
+impl<F, E, ResBody> Future for StatsFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    E: Into<crate::Error>,
+{
+    type Output = Result<Response<StatsBody<ResBody>>, crate::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx)).map_err(Into::into);
+
+        let recorder = match this.recorder.take() {
+            Some(recorder) => recorder,
+            None => return Poll::Ready(result.map(|response| response.map(StatsBody::unrecorded))),
+        };
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                recorder.end(find_error_code(&*error));
+                return Poll::Ready(Err(error));
+            }
+        };
+
+        let headers = response.headers();
+        // Trailers-only responses carry their trailers in the headers.
+        if headers.contains_key("grpc-status") {
+            if *this.client {
+                recorder.in_trailers(headers);
+            } else {
+                recorder.out_trailers(headers);
+            }
+            recorder.end(status_code(headers));
+            return Poll::Ready(Ok(response.map(StatsBody::unrecorded)));
+        }
+
+        if *this.client {
+            recorder.in_headers(headers);
+        } else {
+            recorder.out_headers(headers);
+        }
+        Poll::Ready(Ok(response.map(|body| StatsBody::new(body, recorder))))
+    }
+}
+
+/// Response future of [`ClientStats`].
+#[pin_project]
+pub(crate) struct ClientStatsFuture<F> {
+    #[pin]
+    inner: StatsFuture<F>,
+    /// Whether the codec of the call reports its end.
+    decoded: bool,
+}
+
+impl<F, E> Future for ClientStatsFuture<F>
+where
+    F: Future<Output = Result<Response<BoxBody>, E>>,
+    E: Into<crate::Error>,
+{
+    type Output = Result<Response<BoxBody>, crate::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx))?;
+
+        let (parts, body) = response.into_parts();
+        if let Some(recorder) = body.recorder {
+            if !*this.decoded {
+                // Nothing reads the trailers of the response on its behalf.
+                recorder.end(status_code(&parts.headers));
+            }
+        }
+        Poll::Ready(Ok(Response::from_parts(parts, body.inner)))
+    }
+}
+
+/// Body of the responses of a server, reporting their trailers.
+#[pin_project]
+pub(crate) struct StatsBody<B> {
+    #[pin]
+    inner: B,
+    recorder: Option<Recorder>,
+}
+
+impl<B> StatsBody<B> {
+    fn new(inner: B, recorder: Recorder) -> Self {
+        StatsBody {
+            inner,
+            recorder: Some(recorder),
+        }
+    }
+
+    fn unrecorded(inner: B) -> Self {
+        StatsBody {
+            inner,
+            recorder: None,
+        }
+    }
+}
+
+impl<B: Body> Body for StatsBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx));
+
+        if let (Some(recorder), Ok(trailers)) = (this.recorder.take(), &trailers) {
+            match trailers {
+                Some(trailers) => {
+                    recorder.out_trailers(trailers);
+                    recorder.end(status_code(trailers));
+                }
+                None => recorder.end(Code::Unknown),
+            }
+        }
+        Poll::Ready(trailers)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Connector reporting the connections it makes.
+#[derive(Clone)]
+pub(crate) struct StatsConnector<C> {
+    inner: C,
+    handler: Option<Arc<dyn StatsHandler>>,
+}
+
+impl<C> StatsConnector<C> {
+    pub(crate) fn new(inner: C, handler: Option<Arc<dyn StatsHandler>>) -> Self {
+        StatsConnector { inner, handler }
+    }
+}
+
+impl<C, T> Service<T> for StatsConnector<C>
+where
+    C: Service<T>,
+{
+    type Response = StatsIo<C::Response>;
+    type Error = C::Error;
+    type Future = StatsConnecting<C::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        StatsConnecting {
+            inner: self.inner.call(target),
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+/// Connecting future of [`StatsConnector`].
+#[pin_project]
+pub(crate) struct StatsConnecting<F> {
+    #[pin]
+    inner: F,
+    handler: Option<Arc<dyn StatsHandler>>,
+}
+
+impl<F, IO, E> Future for StatsConnecting<F>
+where
+    F: Future<Output = Result<IO, E>>,
+{
+    type Output = Result<StatsIo<IO>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let io = ready!(this.inner.poll(cx))?;
+        let conn = this
+            .handler
+            .take()
+            .map(|handler| ConnGuard::new(handler, ConnInfo::new(true, None)));
+        Poll::Ready(Ok(StatsIo { io, _conn: conn }))
+    }
+}
+
+/// A connection of a [`StatsConnector`], reporting its end once dropped.
+#[pin_project]
+pub(crate) struct StatsIo<IO> {
+    #[pin]
+    io: IO,
+    _conn: Option<ConnGuard>,
+}
+
+impl<IO: HyperConnection> HyperConnection for StatsIo<IO> {
+    fn connected(&self) -> HyperConnected {
+        self.io.connected()
+    }
+}
+
+impl<IO: AsyncRead> AsyncRead for StatsIo<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.project().io.poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite> AsyncWrite for StatsIo<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{
+        codec::ProstCodec,
+        server::NamedService,
+        stats::{Payload, RpcEnd},
+        transport::{local, Endpoint, Server},
+    };
+    use http::uri::PathAndQuery;
+    use std::{convert::Infallible, sync::Mutex};
+
+    /// Records the events of one side, with the sizes of messages.
+    #[derive(Default)]
+    struct Events(Mutex<Vec<String>>);
+
+    impl Events {
+        fn push(&self, event: impl Into<String>) {
+            self.0.lock().unwrap().push(event.into());
+        }
+
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    impl StatsHandler for Events {
+        fn conn_begin(&self, _: &ConnInfo) {
+            self.push("conn_begin");
+        }
+
+        fn rpc_begin(&self, rpc: &RpcInfo) {
+            self.push(format!("rpc_begin {}", rpc.method()));
+        }
+
+        fn out_headers(&self, _: &RpcInfo, _: &HeaderMap) {
+            self.push("out_headers");
+        }
+
+        fn in_headers(&self, _: &RpcInfo, _: &HeaderMap) {
+            self.push("in_headers");
+        }
+
+        fn out_payload(&self, _: &RpcInfo, payload: &Payload) {
+            assert_eq!(payload.wire_size(), payload.uncompressed_size() + 5);
+            self.push(format!("out_payload {}", payload.uncompressed_size()));
+        }
+
+        fn in_payload(&self, _: &RpcInfo, payload: &Payload) {
+            assert_eq!(payload.wire_size(), payload.uncompressed_size() + 5);
+            self.push(format!("in_payload {}", payload.uncompressed_size()));
+        }
+
+        fn out_trailers(&self, _: &RpcInfo, _: &HeaderMap) {
+            self.push("out_trailers");
+        }
+
+        fn in_trailers(&self, _: &RpcInfo, _: &HeaderMap) {
+            self.push("in_trailers");
+        }
+
+        fn rpc_end(&self, _: &RpcInfo, end: &RpcEnd) {
+            self.push(format!("rpc_end {:?}", end.code()));
+        }
+    }
+
+    /// Answers `hello` with `hello, world`, and anything else with an error.
+    #[derive(Clone)]
+    struct Greeter;
+
+    impl NamedService for Greeter {
+        const NAME: &'static str = "test.Greeter";
+    }
+
+    impl Service<Request<hyper::Body>> for Greeter {
+        type Response = Response<crate::body::BoxBody>;
+        type Error = Infallible;
+        type Future = crate::codegen::BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<hyper::Body>) -> Self::Future {
+            let greet = tower::service_fn(|request: crate::Request<String>| async move {
+                match request.into_inner().as_str() {
+                    "hello" => Ok(crate::Response::new("hello, world".to_string())),
+                    _ => Err(crate::Status::not_found("no greeting")),
+                }
+            });
+            let mut grpc = crate::server::Grpc::new(ProstCodec::<String, String>::default());
+            Box::pin(async move { Ok(grpc.unary(greet, request).await) })
+        }
+    }
+
+    async fn greet(
+        channel: crate::transport::Channel,
+        message: &str,
+    ) -> Result<String, crate::Status> {
+        let mut client = crate::client::Grpc::new(channel);
+        client.ready().await.unwrap();
+        let response = client
+            .unary(
+                crate::Request::new(message.to_string()),
+                PathAndQuery::from_static("/test.Greeter/Greet"),
+                ProstCodec::<String, String>::default(),
+            )
+            .await?;
+        Ok(response.into_inner())
+    }
+
+    #[tokio::test]
+    async fn reports_calls() {
+        let client_events = Arc::new(Events::default());
+        let server_events = Arc::new(Events::default());
+
+        let endpoint =
+            Endpoint::from_static("http://localhost").stats_handler(client_events.clone());
+        let (channel, incoming) = local::pair_with(endpoint);
+        tokio::spawn(
+            Server::builder()
+                .stats_handler(server_events.clone())
+                .add_service(Greeter)
+                .serve_with_incoming(incoming),
+        );
+
+        assert_eq!(
+            greet(channel.clone(), "hello").await.unwrap(),
+            "hello, world"
+        );
+        assert_eq!(
+            client_events.take(),
+            [
+                "conn_begin",
+                "rpc_begin /test.Greeter/Greet",
+                "out_headers",
+                "out_payload 7",
+                "in_headers",
+                "in_payload 14",
+                "in_trailers",
+                "rpc_end Ok",
+            ]
+        );
+        assert_eq!(
+            server_events.take(),
+            [
+                "conn_begin",
+                "rpc_begin /test.Greeter/Greet",
+                "in_headers",
+                "in_payload 7",
+                "out_headers",
+                "out_payload 14",
+                "out_trailers",
+                "rpc_end Ok",
+            ]
+        );
+
+        let status = greet(channel, "bye").await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(
+            client_events.take(),
+            [
+                "rpc_begin /test.Greeter/Greet",
+                "out_headers",
+                "out_payload 5",
+                "in_trailers",
+                "rpc_end NotFound",
+            ]
+        );
+        assert_eq!(
+            server_events.take(),
+            [
+                "rpc_begin /test.Greeter/Greet",
+                "in_headers",
+                "in_payload 5",
+                "out_trailers",
+                "rpc_end NotFound",
+            ]
+        );
+    }
+}
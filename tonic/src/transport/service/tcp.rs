@@ -0,0 +1,141 @@
+use crate::transport::BoxFuture;
+use http::Uri;
+use hyper::client::connect::HttpConnector;
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+/// Socket options `HttpConnector` can't set itself, applied once
+/// connected.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TcpOptions {
+    /// The idle time before keepalive probes are sent, keepalive is
+    /// disabled without it.
+    pub(crate) keepalive: Option<Duration>,
+    pub(crate) keepalive_interval: Option<Duration>,
+    pub(crate) keepalive_retries: Option<u32>,
+    pub(crate) user_timeout: Option<Duration>,
+}
+
+impl TcpOptions {
+    fn apply(&self, stream: &TcpStream) -> std::io::Result<()> {
+        let socket = socket2::SockRef::from(stream);
+
+        if let Some(time) = self.keepalive {
+            #[allow(unused_mut)]
+            let mut keepalive = socket2::TcpKeepalive::new().with_time(time);
+            #[cfg(any(
+                target_os = "android",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "fuchsia",
+                target_os = "illumos",
+                target_os = "ios",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "netbsd",
+                target_os = "tvos",
+                target_os = "watchos",
+                target_os = "windows",
+            ))]
+            if let Some(interval) = self.keepalive_interval {
+                keepalive = keepalive.with_interval(interval);
+            }
+            #[cfg(any(
+                target_os = "android",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "fuchsia",
+                target_os = "illumos",
+                target_os = "ios",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "netbsd",
+                target_os = "tvos",
+                target_os = "watchos",
+            ))]
+            if let Some(retries) = self.keepalive_retries {
+                keepalive = keepalive.with_retries(retries);
+            }
+            socket.set_tcp_keepalive(&keepalive)?;
+        }
+
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if self.user_timeout.is_some() {
+            socket.set_tcp_user_timeout(self.user_timeout)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Connects with `HttpConnector`, then sets the [`TcpOptions`].
+#[derive(Debug, Clone)]
+pub(crate) struct TcpConnector {
+    http: HttpConnector,
+    options: TcpOptions,
+}
+
+impl TcpConnector {
+    pub(crate) fn new(http: HttpConnector, options: TcpOptions) -> Self {
+        TcpConnector { http, options }
+    }
+}
+
+impl Service<Uri> for TcpConnector {
+    type Response = TcpStream;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.http.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connect = self.http.call(uri);
+        let options = self.options.clone();
+
+        Box::pin(async move {
+            let stream = connect.await?;
+            options.apply(&stream)?;
+            Ok(stream)
+        })
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn sets_socket_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let options = TcpOptions {
+            keepalive: Some(Duration::from_secs(60)),
+            keepalive_interval: Some(Duration::from_secs(5)),
+            keepalive_retries: Some(3),
+            user_timeout: Some(Duration::from_secs(30)),
+        };
+        let mut connector = TcpConnector::new(HttpConnector::new(), options);
+        let stream = connector
+            .call(format!("http://{}", addr).parse().unwrap())
+            .await
+            .unwrap();
+
+        let socket = socket2::SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(60));
+        assert_eq!(socket.keepalive_interval().unwrap(), Duration::from_secs(5));
+        assert_eq!(socket.keepalive_retries().unwrap(), 3);
+        assert_eq!(
+            socket.tcp_user_timeout().unwrap(),
+            Some(Duration::from_secs(30))
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Instant};
+
+/// A timer for the timeouts of a transport, such as that of a runtime
+/// other than `tokio`.
+pub trait Timer {
+    /// A future resolving once `deadline` is reached.
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+#[derive(Copy, Clone)]
+struct TokioTimer;
+
+impl Timer for TokioTimer {
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedTimer {
+    inner: Arc<dyn Timer + Send + Sync + 'static>,
+}
+
+impl SharedTimer {
+    pub(crate) fn new<T>(timer: T) -> Self
+    where
+        T: Timer + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(timer),
+        }
+    }
+
+    pub(crate) fn tokio() -> Self {
+        Self::new(TokioTimer)
+    }
+}
+
+impl Timer for SharedTimer {
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        self.inner.sleep_until(deadline)
+    }
+}
@@ -0,0 +1,97 @@
+use crate::transport::BoxFuture;
+use http::Uri;
+use std::{
+    path::PathBuf,
+    task::{Context, Poll},
+};
+use tokio::net::UnixStream;
+use tower_service::Service;
+
+/// Connects to the unix socket at `path`, whatever the uri.
+#[derive(Debug, Clone)]
+pub(crate) struct UnixConnector {
+    path: PathBuf,
+}
+
+impl UnixConnector {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        UnixConnector { path }
+    }
+}
+
+impl Service<Uri> for UnixConnector {
+    type Response = UnixStream;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Uri) -> Self::Future {
+        let path = self.path.clone();
+        Box::pin(async move { Ok(UnixStream::connect(path).await?) })
+    }
+}
+
+/// The path tokio binds and connects to for the socket `name` of the
+/// abstract namespace: `name` after a nul byte.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn abstract_path(name: &[u8]) -> PathBuf {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let mut path = vec![0];
+    path.extend_from_slice(name);
+    PathBuf::from(OsStr::from_bytes(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::Endpoint;
+    use http::Request;
+    use std::{convert::Infallible, task::Poll};
+    use tokio::net::UnixListener;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn connects_to_unix_uri() {
+        let dir = std::env::temp_dir().join(format!("tonic-unix-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("connects_to_unix_uri.sock");
+        let _ = std::fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let incoming = hyper::server::accept::poll_fn(move |cx| match listener.poll_accept(cx) {
+            Poll::Ready(accepted) => Poll::Ready(Some(accepted.map(|(stream, _)| stream))),
+            Poll::Pending => Poll::Pending,
+        });
+        let make = hyper::service::make_service_fn(|_| async {
+            Ok::<_, Infallible>(hyper::service::service_fn(|request: Request<_>| {
+                let authority = request.uri().authority().map(|a| a.to_string());
+                async move {
+                    assert_eq!(authority.as_deref(), Some("localhost"));
+                    Ok::<_, Infallible>(http::Response::new(hyper::Body::empty()))
+                }
+            }))
+        });
+        tokio::spawn(
+            hyper::Server::builder(incoming)
+                .http2_only(true)
+                .serve(make),
+        );
+
+        let channel = Endpoint::from_shared(format!("unix://{}", path.display()))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        let response = channel.oneshot(request).await.unwrap();
+        assert!(response.status().is_success());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
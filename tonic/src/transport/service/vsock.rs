@@ -0,0 +1,144 @@
+use crate::transport::BoxFuture;
+use http::Uri;
+use socket2::{Domain, SockAddr, Socket, Type};
+use std::{
+    io::{self, Read, Write},
+    net::Shutdown,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{unix::AsyncFd, AsyncRead, AsyncWrite, ReadBuf};
+use tower_service::Service;
+
+/// A connected `AF_VSOCK` stream socket.
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+#[derive(Debug)]
+pub struct VsockStream {
+    inner: AsyncFd<Socket>,
+}
+
+impl VsockStream {
+    /// Wrap a connected socket, which is made non-blocking.
+    pub(crate) fn new(socket: Socket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(VsockStream {
+            inner: AsyncFd::new(socket)?,
+        })
+    }
+
+    /// Connect to `port` of the machine `cid`.
+    pub(crate) async fn connect(cid: u32, port: u32) -> io::Result<Self> {
+        // Connecting doesn't go over a network, it only waits for the peer to
+        // accept the connection.
+        let socket = tokio::task::spawn_blocking(move || {
+            let socket = Socket::new(Domain::VSOCK, Type::STREAM.cloexec(), None)?;
+            socket.connect(&SockAddr::vsock(cid, port))?;
+            Ok::<_, io::Error>(socket)
+        })
+        .await??;
+        Self::new(socket)
+    }
+
+    /// The context identifier and port of the peer.
+    pub fn peer_addr(&self) -> io::Result<(u32, u32)> {
+        self.inner
+            .get_ref()
+            .peer_addr()?
+            .as_vsock_address()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a vsock address"))
+    }
+}
+
+impl AsyncRead for VsockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|inner| inner.get_ref().read(unfilled)) {
+                Ok(Ok(read)) => {
+                    buf.advance(read);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(error)) => return Poll::Ready(Err(error)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for VsockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_write_ready(cx))?;
+            match guard.try_io(|inner| inner.get_ref().write(buf)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.inner.get_ref().shutdown(Shutdown::Write))
+    }
+}
+
+/// Connects to the `vsock://cid:port` uri of an endpoint.
+#[derive(Debug, Clone)]
+pub(crate) struct VsockConnector;
+
+impl Service<Uri> for VsockConnector {
+    type Response = VsockStream;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        Box::pin(async move {
+            let (cid, port) = vsock_address(&uri)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid vsock uri"))?;
+            Ok(VsockStream::connect(cid, port).await?)
+        })
+    }
+}
+
+/// The context identifier and port of a `vsock://cid:port` uri.
+pub(crate) fn vsock_address(uri: &Uri) -> Option<(u32, u32)> {
+    if uri.scheme_str() != Some("vsock") {
+        return None;
+    }
+    let authority = uri.authority()?;
+    let cid = authority.host().parse().ok()?;
+    let port = authority.port()?.as_str().parse().ok()?;
+    Some((cid, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_vsock_uris() {
+        let address = |uri| vsock_address(&Uri::from_static(uri));
+
+        assert_eq!(address("vsock://3:50051"), Some((3, 50051)));
+        assert_eq!(address("vsock://2:8000/"), Some((2, 8000)));
+        assert_eq!(address("vsock://host:8000"), None);
+        assert_eq!(address("vsock://3"), None);
+        assert_eq!(address("http://3:8000"), None);
+    }
+}
@@ -0,0 +1,172 @@
+use super::SharedExec;
+use crate::transport::{channel::ConnectBackoff, Executor};
+use std::{
+    future::{self, Ready},
+    task::{Context, Poll},
+};
+use tokio::sync::{mpsc, oneshot};
+use tower::ServiceExt;
+use tower_service::Service;
+
+/// Connects in the background, so a connection is ready before it is
+/// needed.
+///
+/// A task connects as soon as the `Warm` is created, and again as soon as
+/// `Reconnect` drops the connection it got, keeping one connection
+/// established at all times. `Warm` makes the connections of `Reconnect` by
+/// handing it the last one the task established.
+pub(crate) struct Warm<S> {
+    connections: mpsc::Receiver<Result<Guarded<S>, crate::Error>>,
+    next: Option<Result<Guarded<S>, crate::Error>>,
+}
+
+impl<S> Warm<S>
+where
+    S: Send + 'static,
+{
+    pub(crate) fn new<M, Target>(
+        mut connector: M,
+        target: Target,
+        connect_backoff: ConnectBackoff,
+        executor: &SharedExec,
+    ) -> Self
+    where
+        M: Service<Target, Response = S> + Send + 'static,
+        M::Error: Into<crate::Error> + Send,
+        M::Future: Send,
+        Target: Clone + Send + 'static,
+    {
+        let (tx, connections) = mpsc::channel(1);
+
+        executor.execute(Box::pin(async move {
+            let mut failures = 0;
+            while !tx.is_closed() {
+                let connected = match connector.ready().await {
+                    Ok(connector) => connector.call(target.clone()).await,
+                    Err(error) => Err(error),
+                };
+
+                match connected {
+                    Ok(service) => {
+                        failures = 0;
+                        let (guard, dropped) = oneshot::channel();
+                        let service = Guarded {
+                            inner: service,
+                            _guard: guard,
+                        };
+                        if tx.send(Ok(service)).await.is_err() {
+                            return;
+                        }
+                        let _ = dropped.await;
+                    }
+                    Err(error) => {
+                        failures += 1;
+                        if tx.send(Err(error.into())).await.is_err() {
+                            return;
+                        }
+                        tokio::time::sleep(connect_backoff.backoff(failures)).await;
+                    }
+                }
+            }
+        }));
+
+        Warm {
+            connections,
+            next: None,
+        }
+    }
+}
+
+impl<S, Target> Service<Target> for Warm<S> {
+    type Response = Guarded<S>;
+    type Error = crate::Error;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.next.is_none() {
+            self.next = match self.connections.poll_recv(cx) {
+                Poll::Ready(Some(connection)) => Some(connection),
+                // The executor dropped the task.
+                Poll::Ready(None) => Some(Err("the connection task stopped".into())),
+                Poll::Pending => return Poll::Pending,
+            };
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Target) -> Self::Future {
+        future::ready(self.next.take().expect("called before ready"))
+    }
+}
+
+/// A connection made by a [`Warm`] task, which lets the task know once it is
+/// dropped.
+pub(crate) struct Guarded<S> {
+    inner: S,
+    _guard: oneshot::Sender<()>,
+}
+
+impl<S, Request> Service<Request> for Guarded<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::Endpoint;
+    use std::{
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn connects_before_the_first_request() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let make = {
+            let connections = connections.clone();
+            hyper::service::make_service_fn(move |_| {
+                connections.fetch_add(1, Ordering::SeqCst);
+                async {
+                    Ok::<_, Infallible>(hyper::service::service_fn(|_| async {
+                        Ok::<_, Infallible>(http::Response::new(hyper::Body::empty()))
+                    }))
+                }
+            })
+        };
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let _channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .min_connections(2)
+            .connect_lazy();
+
+        let connected = async {
+            while connections.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), connected)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use super::{discover::lazy_connection, ejection::Ejectable, outlier::EjectionBudget};
+use crate::{
+    body::BoxBody,
+    transport::{channel::StateTracker, Endpoint},
+};
+use http::{Request, Response, Uri};
+use std::{
+    hash::Hash,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc::Receiver;
+use tower::discover::Change;
+use tower_service::Service;
+
+/// Send requests to the ready endpoints in proportion of their weights.
+///
+/// Uses smooth weighted round robin: every pick adds its weight to the
+/// credit of each ready endpoint, the endpoint with the most credit is
+/// picked and loses the sum of the weights, so that picks of heavy
+/// endpoints are spread out rather than sent in bursts.
+pub(crate) struct WeightedBalance<K> {
+    changes: Receiver<Change<K, (Endpoint, u32)>>,
+    endpoints: Vec<Weighted<K>>,
+    budget: Arc<EjectionBudget>,
+    tracker: StateTracker,
+    /// The endpoint the next call is sent to.
+    ready: Option<usize>,
+}
+
+struct Weighted<K> {
+    key: K,
+    uri: Uri,
+    weight: u32,
+    credit: i64,
+    service: Ejectable,
+}
+
+impl<K: Hash + Eq> WeightedBalance<K> {
+    pub(crate) fn new(
+        changes: Receiver<Change<K, (Endpoint, u32)>>,
+        tracker: StateTracker,
+    ) -> Self {
+        WeightedBalance {
+            changes,
+            endpoints: Vec::new(),
+            budget: Arc::default(),
+            tracker,
+            ready: None,
+        }
+    }
+
+    fn update(&mut self, cx: &mut Context<'_>) {
+        while let Poll::Ready(Some(change)) = self.changes.poll_recv(cx) {
+            match change {
+                Change::Insert(key, (endpoint, weight)) => {
+                    let existing = self.endpoints.iter_mut().find(|e| e.key == key);
+                    match existing {
+                        // Only the weight changed, keep the connection.
+                        Some(existing) if existing.uri == endpoint.uri => {
+                            existing.weight = weight;
+                            existing.credit = 0;
+                        }
+                        Some(existing) => {
+                            existing.uri = endpoint.uri.clone();
+                            existing.weight = weight;
+                            existing.credit = 0;
+                            existing.service = Ejectable::new(
+                                lazy_connection(endpoint, &self.tracker),
+                                &self.budget,
+                            );
+                        }
+                        None => self.endpoints.push(Weighted {
+                            key,
+                            uri: endpoint.uri.clone(),
+                            weight,
+                            credit: 0,
+                            service: Ejectable::new(
+                                lazy_connection(endpoint, &self.tracker),
+                                &self.budget,
+                            ),
+                        }),
+                    }
+                }
+                Change::Remove(key) => self.endpoints.retain(|e| e.key != key),
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq> Service<Request<BoxBody>> for WeightedBalance<K> {
+    type Response = Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = <Ejectable as Service<Request<BoxBody>>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.ready = None;
+        self.update(cx);
+
+        let mut total = 0;
+        let mut best: Option<usize> = None;
+        let mut i = 0;
+        while i < self.endpoints.len() {
+            let endpoint = &mut self.endpoints[i];
+            if endpoint.weight == 0 {
+                i += 1;
+                continue;
+            }
+
+            match endpoint.service.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {
+                    endpoint.credit += i64::from(endpoint.weight);
+                    total += i64::from(endpoint.weight);
+                    if best
+                        .map(|best| self.endpoints[best].credit < self.endpoints[i].credit)
+                        .unwrap_or(true)
+                    {
+                        best = Some(i);
+                    }
+                }
+                Poll::Pending => {}
+                Poll::Ready(Err(error)) => {
+                    tracing::debug!("weighted: dropping endpoint: {}", error);
+                    self.endpoints.remove(i);
+                    continue;
+                }
+            }
+            i += 1;
+        }
+
+        match best {
+            Some(best) => {
+                self.endpoints[best].credit -= total;
+                self.ready = Some(best);
+                Poll::Ready(Ok(()))
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        let i = self.ready.take().expect("called before ready");
+        self.endpoints[i].service.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::{Channel, Endpoint};
+    use http::Request;
+    use std::{collections::HashMap, convert::Infallible, net::SocketAddr};
+    use tower::{discover::Change, ServiceExt};
+
+    fn serve(name: &'static str) -> Endpoint {
+        let make = hyper::service::make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(hyper::service::service_fn(move |_| async move {
+                Ok::<_, Infallible>(http::Response::new(hyper::Body::from(name)))
+            }))
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let addr: SocketAddr = server.local_addr();
+        tokio::spawn(server);
+        Endpoint::from_shared(format!("http://{}", addr)).unwrap()
+    }
+
+    async fn send(channel: &Channel) -> String {
+        let request = Request::builder()
+            .uri("/")
+            .body(crate::body::empty_body())
+            .unwrap();
+        let response = channel.clone().oneshot(request).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    async fn count(channel: &Channel, requests: usize) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for _ in 0..requests {
+            *counts.entry(send(channel).await).or_default() += 1;
+        }
+        counts
+    }
+
+    #[tokio::test]
+    async fn routes_in_proportion_of_weights() {
+        let (large, small) = (serve("large"), serve("small"));
+        let (channel, tx) = Channel::balance_weighted_channel(4);
+        tx.send(Change::Insert(1, (large.clone(), 3)))
+            .await
+            .unwrap();
+        tx.send(Change::Insert(2, (small, 1))).await.unwrap();
+
+        // Wait for both endpoints to be connected.
+        while send(&channel).await != "small" {}
+
+        let counts = count(&channel, 40).await;
+        assert_eq!(counts["large"], 30);
+        assert_eq!(counts["small"], 10);
+
+        tx.send(Change::Insert(1, (large, 0))).await.unwrap();
+        let counts = count(&channel, 10).await;
+        assert_eq!(counts.get("large"), None);
+        assert_eq!(counts["small"], 10);
+    }
+}
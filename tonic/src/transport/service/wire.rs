@@ -0,0 +1,96 @@
+//! Minimal reading and writing of the protobuf wire format, for the few
+//! messages the transport handles itself without depending on `prost`.
+
+use bytes::BufMut;
+
+/// The value of a field of a message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// A 32 or 64-bit value, that none of the messages read here use.
+    Fixed,
+}
+
+/// Call `f` with the number and the value of each field of the message
+/// `buf`, returning `None` if the message is malformed.
+pub(crate) fn for_each_field<'a>(
+    mut buf: &'a [u8],
+    mut f: impl FnMut(u64, Field<'a>),
+) -> Option<()> {
+    while !buf.is_empty() {
+        let key = get_varint(&mut buf)?;
+        let field = match key & 7 {
+            0 => Field::Varint(get_varint(&mut buf)?),
+            1 | 5 => {
+                let len = if key & 7 == 1 { 8 } else { 4 };
+                buf = buf.get(len..)?;
+                Field::Fixed
+            }
+            2 => {
+                let len = usize::try_from(get_varint(&mut buf)?).ok()?;
+                let (bytes, rest) = (buf.get(..len)?, buf.get(len..)?);
+                buf = rest;
+                Field::Bytes(bytes)
+            }
+            _ => return None,
+        };
+        f(key >> 3, field);
+    }
+    Some(())
+}
+
+/// Write the length delimited field `number` of value `bytes`.
+pub(crate) fn put_bytes(buf: &mut impl BufMut, number: u64, bytes: &[u8]) {
+    put_varint(buf, number << 3 | 2);
+    put_varint(buf, bytes.len() as u64);
+    buf.put_slice(bytes);
+}
+
+fn put_varint(buf: &mut impl BufMut, mut value: u64) {
+    while value >= 0x80 {
+        buf.put_u8(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.put_u8(value as u8);
+}
+
+fn get_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_written_fields() {
+        let mut buf = Vec::new();
+        put_bytes(&mut buf, 1, b"name");
+        put_bytes(&mut buf, 300, &[]);
+        buf.extend_from_slice(&[0x10, 0xac, 0x02]);
+
+        let mut fields = Vec::new();
+        for_each_field(&buf, |number, field| fields.push((number, field))).unwrap();
+        assert_eq!(
+            fields,
+            [
+                (1, Field::Bytes(b"name")),
+                (300, Field::Bytes(&[])),
+                (2, Field::Varint(300)),
+            ]
+        );
+
+        assert_eq!(for_each_field(&[0x0a, 0x05, 0x00], |_, _| {}), None);
+        assert_eq!(for_each_field(&[0x08, 0x80], |_, _| {}), None);
+    }
+}